@@ -13,6 +13,8 @@ use keympostor::trigger::KeyTrigger;
 
 type Group = FxHashMap<KeyModifiers, KeyTransformRule>;
 
+type Matrix = Box<[Vec<Vec<Vec<Option<Group>>>>]>;
+
 trait KeyTransformMap {
     fn get(&self, event: &KeyEvent) -> Option<&KeyTransformRule>;
     fn put(&mut self, rule: KeyTransformRule);
@@ -40,7 +42,7 @@ impl KeyTransformMap for KeyTransformHashMap {
 
 #[derive(Debug)]
 pub struct KeyTransformMatrix {
-    matrix: Box<[Vec<Vec<Vec<Option<Group>>>>]>,
+    matrix: Matrix,
 }
 
 impl Default for KeyTransformMatrix {
@@ -100,11 +102,21 @@ fn create_action(vk: u8, sc: u8, ext: bool, trans: KeyTransition) -> KeyAction {
 
 fn create_rule(vk: u8, sc: u8, ext: bool, trans: KeyTransition) -> KeyTransformRule {
     KeyTransformRule {
+        id: None,
         trigger: KeyTrigger {
             action: create_action(vk, sc, ext, trans),
             modifiers: Any,
+            locks: Default::default(),
+            taps: 0,
+            is_alone: false,
         },
         actions: KeyActionSequence::new(vec![]),
+        repeat: Default::default(),
+        is_pass_through: false,
+        device: None,
+        window: None,
+        priority: 0,
+        target: None,
     }
 }
 
@@ -113,16 +125,28 @@ fn create_event(vk: u8, sc: u8, ext: bool, trans: KeyTransition) -> KeyEvent {
         trigger: KeyTrigger {
             action: create_action(vk, sc, ext, trans),
             modifiers: Any,
+            locks: Default::default(),
+            taps: 0,
+            is_alone: false,
         },
+        locks: Default::default(),
+        taps: 0,
+        is_alone: false,
+        is_repeat: false,
         time: 0,
+        timestamp: 0,
         is_injected: false,
         is_private: false,
+        depth: 0,
+        device: None,
+        window: None,
+        character: None,
     }
 }
 
 pub fn for_all<F>(mut f: F)
 where
-    F: FnMut(u8, u8, bool, KeyTransition) -> (),
+    F: FnMut(u8, u8, bool, KeyTransition),
 {
     for vk in 0..=255 {
         for sc in 0..135 {
@@ -147,11 +171,28 @@ fn bench_map<M: KeyTransformMap>(group: &mut BenchmarkGroup<WallTime>, id: &str,
     });
 }
 
+/// Benchmarks the lookup of the hook.
+fn bench_hook_map(group: &mut BenchmarkGroup<WallTime>, id: &str) {
+    let mut rules = vec![];
+    for_all(|vk, sc, ext, trans| {
+        rules.push(create_rule(vk, sc, ext, trans));
+    });
+    let map = keympostor::transform::KeyTransformMap::new(rules.iter());
+    group.bench_function(id, move |b| {
+        b.iter(|| {
+            for_all(|vk, sc, ext, trans| {
+                let _ = map.get(&create_event(vk, sc, ext, trans));
+            })
+        })
+    });
+}
+
 pub(crate) fn bench_transform_container(c: &mut Criterion) {
     let mut group = c.benchmark_group("transform_container_benchmark");
 
     bench_map(&mut group, "Map", KeyTransformHashMap::default());
     bench_map(&mut group, "Matrix", KeyTransformMatrix::default());
+    bench_hook_map(&mut group, "Table");
 
     group.finish();
 }
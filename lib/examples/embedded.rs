@@ -0,0 +1,36 @@
+//! Minimal embedded usage: runs the engine with the rules loaded from a string
+//! and prints every processed event. Press ESC to exit.
+//!
+//! ```text
+//! cargo run --example embedded
+//! ```
+use keympostor::engine::Engine;
+use keympostor::key::Key;
+use keympostor::rule::KeyTransformRules;
+use std::error::Error;
+use std::str::FromStr;
+
+const RULES: &str = r#"
+    CAPS_LOCK↓ : LEFT_CTRL↓
+    CAPS_LOCK↑ : LEFT_CTRL↑
+    [RIGHT_ALT] H↓ : LEFT↓
+    [RIGHT_ALT] H↑ : LEFT↑
+"#;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let rules = KeyTransformRules::from_str(RULES)?;
+
+    let mut engine = Engine::new(rules);
+    engine.start()?;
+    println!("Engine started. Press ESC to exit.");
+
+    for event in engine.events() {
+        println!("{}", event);
+        if event.trigger.action.key == Key::Esc {
+            break;
+        }
+    }
+
+    engine.stop();
+    Ok(())
+}
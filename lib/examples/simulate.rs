@@ -0,0 +1,72 @@
+//! Builds the rules programmatically and shows which of them the hook would apply
+//! to the simulated events. The hook is not installed, so keyboard input is not affected.
+//!
+//! ```text
+//! cargo run --example simulate
+//! ```
+use keympostor::builder::{RuleBuilder, SequenceBuilder, TriggerBuilder};
+use keympostor::event::KeyEvent;
+use keympostor::key::Key;
+use keympostor::repeat::KeyRepeat;
+use keympostor::rule::{KeyTransformRule, KeyTransformRules};
+use keympostor::trigger::KeyTrigger;
+use std::str::FromStr;
+
+fn main() {
+    let mut rules = KeyTransformRules::default();
+    rules.insert(
+        RuleBuilder::new(
+            TriggerBuilder::down(Key::CapsLock).build(),
+            SequenceBuilder::new().down(Key::LeftCtrl).build(),
+        )
+        .build(),
+    );
+    rules.insert(
+        RuleBuilder::new(
+            TriggerBuilder::up(Key::CapsLock).build(),
+            SequenceBuilder::new().up(Key::LeftCtrl).build(),
+        )
+        .build(),
+    );
+    rules.insert(
+        RuleBuilder::new(
+            TriggerBuilder::down(Key::F1)
+                .modifier(Key::LeftCtrl)
+                .modifier(Key::LeftShift)
+                .build(),
+            SequenceBuilder::new()
+                .down(Key::LeftWin)
+                .press(Key::R)
+                .up(Key::LeftWin)
+                .delay(100)
+                .build(),
+        )
+        .repeat(KeyRepeat::Suppress)
+        .build(),
+    );
+    rules.assign_ids(0);
+
+    println!("Rules:\n{rules}\n");
+
+    /* same notation as the rules triggers */
+    let events = [
+        "[] CAPS_LOCK↓",
+        "[] CAPS_LOCK↑",
+        "[LEFT_CTRL + LEFT_SHIFT] F1↓",
+        "[LEFT_CTRL] F1↓",
+        "[] A↓",
+    ];
+    for text in events {
+        let event = KeyEvent {
+            trigger: KeyTrigger::from_str(text).expect("Invalid event"),
+            ..Default::default()
+        };
+        match rules.find_rule(&event) {
+            Some(rule) => println!("{event:<32} -> {}", rule.actions),
+            None => println!("{event:<32} -> passed unchanged"),
+        }
+    }
+
+    let rule = KeyTransformRule::from_str("[RIGHT_ALT] H↓ : LEFT↓").expect("Invalid rule");
+    println!("\nParsed rule: {rule}");
+}
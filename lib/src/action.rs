@@ -1,9 +1,10 @@
 use crate::error::KeyError;
 use crate::key::Key;
 use crate::key_error;
+use crate::os_layout::LayoutLocale;
 use crate::transition::KeyTransition;
 use crate::transition::KeyTransition::{Down, Up};
-use crate::{deserialize_from_string, key_err, serialize_to_string, write_joined};
+use crate::{deserialize_from_string, key_err, serialize_to_string};
 use serde::Deserializer;
 use serde::Serializer;
 use serde::{de, Deserialize, Serialize};
@@ -13,19 +14,24 @@ use std::hash::Hash;
 use std::slice;
 use std::str::FromStr;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+const DELAY: &str = "DELAY";
+const PRESERVE_CLIPBOARD: &str = "PRESERVE_CLIPBOARD";
+const OS_LAYOUT: &str = "OS_LAYOUT";
+const TYPE_CLIPBOARD: &str = "TYPE_CLIPBOARD";
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
 pub struct KeyAction {
     pub key: Key,
     pub transition: KeyTransition,
 }
 
 impl KeyAction {
-    pub(crate) const fn new(key: Key, transition: KeyTransition) -> Self {
+    pub const fn new(key: Key, transition: KeyTransition) -> Self {
         Self { key, transition }
     }
 
     pub(crate) fn from_str_expand(s: &str) -> Result<Vec<Self>, KeyError> {
-        let (key_part, trans_part) = match s.find(|c| KeyTransition::is_transition_char(c)) {
+        let (key_part, trans_part) = match s.find(KeyTransition::is_transition_char) {
             Some(p) => (
                 s.get(..p).ok_or(key_error!("Missing key part"))?,
                 s.get(p..),
@@ -33,14 +39,18 @@ impl KeyAction {
             None => (s, None),
         };
 
-        let key =
-            Key::from_str(key_part.trim()).ok_or(key_error!("Invalid key part: `{key_part}`"))?;
+        let key = Key::from_str(key_part.trim()).ok_or_else(|| {
+            key_error!("Invalid key part: `{key_part}`").with_token_in(s, key_part.trim())
+        })?;
 
         match trans_part {
             Some(part) => {
+                let part = part.trim();
                 let mut vec = vec![];
-                for char in part.trim().chars() {
-                    vec.push(KeyAction::new(key, KeyTransition::from_char(char)?))
+                for (i, char) in part.char_indices() {
+                    let transition =
+                        KeyTransition::from_char(char).map_err(|e| e.within(s, &part[i..]))?;
+                    vec.push(KeyAction::new(key, transition))
                 }
                 Ok(vec)
             }
@@ -65,7 +75,9 @@ impl FromStr for KeyAction {
         if vec.len() > 1 {
             return key_err!("String must be exactly single action");
         }
-        Ok(vec[0])
+        vec.first()
+            .copied()
+            .ok_or_else(|| key_error!("Invalid action string"))
     }
 }
 
@@ -77,44 +89,229 @@ impl<'de> Deserialize<'de> for KeyAction {
     deserialize_from_string!();
 }
 
+/// Item of the actions sequence.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum KeySequenceItem {
+    Action(KeyAction),
+    /// Pause before the next item in milliseconds.
+    Delay(u32),
+    /// Start of `PRESERVE_CLIPBOARD { ... }` scope. Clipboard contents are saved here.
+    SaveClipboard,
+    /// End of `PRESERVE_CLIPBOARD { ... }` scope. Saved clipboard contents are restored here.
+    RestoreClipboard,
+    /// Switch of the input language of the foreground window.
+    OsLayout(LayoutLocale),
+    /// Typing of the clipboard text with the pause after each character in milliseconds.
+    TypeClipboard(u32),
+}
+
+impl KeySequenceItem {
+    fn parse_delay(s: &str) -> Result<Option<u32>, KeyError> {
+        let Some(args) = s.strip_prefix(DELAY) else {
+            return Ok(None);
+        };
+        let value = args
+            .trim()
+            .strip_prefix('(')
+            .and_then(|a| a.strip_suffix(')'))
+            .ok_or_else(|| {
+                key_error!("Invalid delay: `{s}`")
+                    .with_token(s)
+                    .at(0)
+                    .with_expected(&["DELAY(<milliseconds>)"])
+            })?;
+        let delay = value.trim().parse().map_err(|_| {
+            key_error!("Invalid delay value: `{value}`").with_token_in(s, value.trim())
+        })?;
+        Ok(Some(delay))
+    }
+
+    fn parse_os_layout(s: &str) -> Result<Option<LayoutLocale>, KeyError> {
+        let Some(args) = s.strip_prefix(OS_LAYOUT) else {
+            return Ok(None);
+        };
+        let value = args
+            .trim()
+            .strip_prefix('(')
+            .and_then(|a| a.strip_suffix(')'))
+            .ok_or_else(|| {
+                key_error!("Invalid OS layout: `{s}`")
+                    .with_token(s)
+                    .at(0)
+                    .with_expected(&["OS_LAYOUT(<locale name>)"])
+            })?;
+        let locale = LayoutLocale::new(value.trim()).map_err(|e| e.within(s, value.trim()))?;
+        Ok(Some(locale))
+    }
+
+    fn parse_type_clipboard(s: &str) -> Result<Option<u32>, KeyError> {
+        let Some(args) = s.strip_prefix(TYPE_CLIPBOARD) else {
+            return Ok(None);
+        };
+        let args = args.trim();
+        if args.is_empty() {
+            return Ok(Some(0));
+        }
+        let value = args
+            .strip_prefix('(')
+            .and_then(|a| a.strip_suffix(')'))
+            .ok_or_else(|| {
+                key_error!("Invalid clipboard typing: `{s}`")
+                    .with_token(s)
+                    .at(0)
+                    .with_expected(&["TYPE_CLIPBOARD", "TYPE_CLIPBOARD(<milliseconds>)"])
+            })?;
+        let delay = value.trim().parse().map_err(|_| {
+            key_error!("Invalid clipboard typing delay: `{value}`").with_token_in(s, value.trim())
+        })?;
+        Ok(Some(delay))
+    }
+
+    /// Returns the delay, the OS layout switch, the clipboard typing or the expanded actions
+    /// of the sequence part.
+    fn from_str_expand(s: &str) -> Result<Vec<Self>, KeyError> {
+        let part = s.trim();
+        let located = |e: KeyError| e.within(s, part);
+        if let Some(delay) = Self::parse_delay(part).map_err(located)? {
+            return Ok(vec![Self::Delay(delay)]);
+        }
+        if let Some(locale) = Self::parse_os_layout(part).map_err(located)? {
+            return Ok(vec![Self::OsLayout(locale)]);
+        }
+        if let Some(delay) = Self::parse_type_clipboard(part).map_err(located)? {
+            return Ok(vec![Self::TypeClipboard(delay)]);
+        }
+
+        Ok(KeyAction::from_str_expand(s)?
+            .into_iter()
+            .map(Self::Action)
+            .collect())
+    }
+}
+
+impl Display for KeySequenceItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeySequenceItem::Action(action) => Display::fmt(action, f),
+            KeySequenceItem::Delay(delay) => f.pad(&format!("{DELAY}({delay})")),
+            KeySequenceItem::SaveClipboard => f.pad(&format!("{PRESERVE_CLIPBOARD} {{")),
+            KeySequenceItem::RestoreClipboard => f.pad("}"),
+            KeySequenceItem::OsLayout(locale) => f.pad(&format!("{OS_LAYOUT}({locale})")),
+            KeySequenceItem::TypeClipboard(0) => f.pad(TYPE_CLIPBOARD),
+            KeySequenceItem::TypeClipboard(delay) => f.pad(&format!("{TYPE_CLIPBOARD}({delay})")),
+        }
+    }
+}
+
 #[derive(Clone, Eq)]
-pub struct KeyActionSequence(Vec<KeyAction>);
+pub struct KeyActionSequence(Vec<KeySequenceItem>);
 
 impl KeyActionSequence {
     pub fn new(actions: Vec<KeyAction>) -> Self {
-        Self(actions)
+        Self(actions.into_iter().map(KeySequenceItem::Action).collect())
     }
 
-    pub fn iter(&self) -> Iter<'_, KeyAction> {
+    pub fn from_items(items: Vec<KeySequenceItem>) -> Self {
+        Self(items)
+    }
+
+    pub fn iter(&self) -> Iter<'_, KeySequenceItem> {
         self.0.iter()
     }
 
+    /// Returns key actions skipping delays, clipboard scopes and typing and OS layout switches.
+    pub fn actions(&self) -> impl Iterator<Item = &KeyAction> {
+        self.0.iter().filter_map(|item| match item {
+            KeySequenceItem::Action(action) => Some(action),
+            _ => None,
+        })
+    }
+
     pub(crate) fn from_str_expand(s: &str) -> Result<Vec<Self>, KeyError> {
-        let mut down_actions = Vec::new();
-        let mut up_actions = Vec::new();
+        let mut down_items = Vec::new();
+        let mut up_items = Vec::new();
 
         let mut is_expanded = false;
+        let mut is_preserving = false;
         for part in s.split(|c| ['→', '>'].contains(&c)) {
-            let actions = KeyAction::from_str_expand(part)?;
-            down_actions.push(actions[0]);
-            if actions.len() == 1 {
-                up_actions.push(actions[0]);
-            } else {
-                up_actions.push(actions[1]);
-                is_expanded = true;
+            let mut part = part.trim();
+
+            let is_opening = part.starts_with(PRESERVE_CLIPBOARD);
+            if is_opening {
+                if is_preserving {
+                    return Err(nested_scope_error(s, part));
+                }
+                part = part[PRESERVE_CLIPBOARD.len()..]
+                    .trim_start()
+                    .strip_prefix('{')
+                    .ok_or_else(|| {
+                        key_error!("Invalid clipboard scope: `{part}`")
+                            .with_token_in(s, part)
+                            .with_expected(&["PRESERVE_CLIPBOARD { <actions> }"])
+                    })?
+                    .trim();
+                /* the nested scope may open in the same part */
+                if part.starts_with(PRESERVE_CLIPBOARD) {
+                    return Err(nested_scope_error(s, part));
+                }
+                is_preserving = true;
+                down_items.push(KeySequenceItem::SaveClipboard);
+                up_items.push(KeySequenceItem::SaveClipboard);
+            }
+
+            let is_closing = match part.strip_suffix('}') {
+                Some(rest) if is_preserving => {
+                    part = rest.trim();
+                    true
+                }
+                Some(_) => {
+                    return Err(key_error!("Unexpected end of clipboard scope: `{part}`")
+                        .with_token_in(s, part));
+                }
+                None => false,
+            };
+
+            if !part.is_empty() || !(is_opening || is_closing) {
+                let items =
+                    KeySequenceItem::from_str_expand(part).map_err(|e| e.within(s, part))?;
+                let (&down, &up) = match items.as_slice() {
+                    [item] => (item, item),
+                    [down, up] => {
+                        is_expanded = true;
+                        (down, up)
+                    }
+                    _ => return key_err!("Invalid sequence part: `{part}`"),
+                };
+                down_items.push(down);
+                up_items.push(up);
             }
+
+            if is_closing {
+                is_preserving = false;
+                down_items.push(KeySequenceItem::RestoreClipboard);
+                up_items.push(KeySequenceItem::RestoreClipboard);
+            }
+        }
+
+        if is_preserving {
+            return key_err!("Missing end of clipboard scope: `}}`");
         }
 
         let mut list = Vec::new();
-        list.push(KeyActionSequence::new(down_actions));
+        list.push(KeyActionSequence(down_items));
         if is_expanded {
-            list.push(KeyActionSequence::new(up_actions))
+            list.push(KeyActionSequence(up_items))
         }
 
         Ok(list)
     }
 }
 
+fn nested_scope_error(s: &str, part: &str) -> KeyError {
+    key_error!("Nested clipboard scope: `{part}`")
+        .with_token_in(s, &part[..PRESERVE_CLIPBOARD.len()])
+}
+
 impl PartialEq<Self> for KeyActionSequence {
     fn eq(&self, other: &Self) -> bool {
         self.0 == other.0
@@ -129,7 +326,18 @@ impl Debug for KeyActionSequence {
 
 impl Display for KeyActionSequence {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write_joined!(f, &self.0, " → ")
+        let mut previous: Option<&KeySequenceItem> = None;
+        for item in &self.0 {
+            match (previous, item) {
+                (None, _) => {}
+                (Some(KeySequenceItem::SaveClipboard), _)
+                | (_, KeySequenceItem::RestoreClipboard) => f.write_char(' ')?,
+                _ => f.write_str(" → ")?,
+            }
+            write!(f, "{}", item)?;
+            previous = Some(item);
+        }
+        Ok(())
     }
 }
 
@@ -141,7 +349,9 @@ impl FromStr for KeyActionSequence {
         if vec.len() > 1 {
             return key_err!("String must be exactly single sequence");
         }
-        Ok(vec[0].clone())
+        vec.first()
+            .cloned()
+            .ok_or_else(|| key_error!("Invalid sequence string"))
     }
 }
 
@@ -171,8 +381,10 @@ macro_rules! key_action_seq {
 mod tests {
     use crate::action::KeyAction;
     use crate::action::KeyActionSequence;
+    use crate::action::KeySequenceItem;
     use crate::key;
     use crate::key::Key;
+    use crate::os_layout::LayoutLocale;
     use crate::transition::KeyTransition::{Down, Up};
     use crate::utils::test::SerdeWrapper;
     use std::str::FromStr;
@@ -219,6 +431,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_key_action_from_str_alias() {
+        let expected = KeyAction {
+            key: key!("ENTER"),
+            transition: Down,
+        };
+        assert_eq!(expected, KeyAction::from_str("RETURN↓").unwrap());
+        assert_eq!(expected, KeyAction::from_str("⏎↓").unwrap());
+        assert_eq!("ENTER↓", KeyAction::from_str("⏎↓").unwrap().to_string());
+    }
+
     #[test]
     fn test_key_action_from_str_expand() {
         assert_eq!(
@@ -377,6 +600,127 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_key_action_sequence_delay() {
+        let actual = key_action_seq!("LEFT_WIN↓ → DELAY(50) → SPACE↓ → SPACE↑ → LEFT_WIN↑");
+
+        assert_eq!(Some(&KeySequenceItem::Delay(50)), actual.iter().nth(1));
+        assert_eq!(4, actual.actions().count());
+        assert_eq!(
+            "LEFT_WIN↓ → DELAY(50) → SPACE↓ → SPACE↑ → LEFT_WIN↑",
+            actual.to_string()
+        );
+
+        assert_eq!(
+            vec![
+                key_action_seq!("A↓ → DELAY(20) → B↓"),
+                key_action_seq!("A↑ → DELAY(20) → B↑")
+            ],
+            KeyActionSequence::from_str_expand("A → DELAY (20) → B").unwrap()
+        );
+
+        assert!(KeyActionSequence::from_str("A↓ → DELAY → B↓").is_err());
+        assert!(KeyActionSequence::from_str("A↓ → DELAY(-5) → B↓").is_err());
+    }
+
+    #[test]
+    fn test_key_action_sequence_preserve_clipboard() {
+        let actual = key_action_seq!(
+            "PRESERVE_CLIPBOARD { LEFT_CTRL↓ → V↓ → V↑ → LEFT_CTRL↑ → DELAY(50) } → ENTER↓"
+        );
+
+        assert_eq!(Some(&KeySequenceItem::SaveClipboard), actual.iter().next());
+        assert_eq!(
+            Some(&KeySequenceItem::RestoreClipboard),
+            actual.iter().nth(6)
+        );
+        assert_eq!(5, actual.actions().count());
+        assert_eq!(
+            "PRESERVE_CLIPBOARD { LEFT_CTRL↓ → V↓ → V↑ → LEFT_CTRL↑ → DELAY(50) } → ENTER↓",
+            actual.to_string()
+        );
+
+        assert_eq!(
+            vec![
+                key_action_seq!("PRESERVE_CLIPBOARD { A↓ → B↓ }"),
+                key_action_seq!("PRESERVE_CLIPBOARD { A↑ → B↑ }")
+            ],
+            KeyActionSequence::from_str_expand("PRESERVE_CLIPBOARD{A → B}").unwrap()
+        );
+
+        assert_eq!(
+            key_action_seq!("PRESERVE_CLIPBOARD { A↓ }"),
+            key_action_seq!("PRESERVE_CLIPBOARD { → A↓ → }")
+        );
+
+        assert!(KeyActionSequence::from_str("PRESERVE_CLIPBOARD A↓").is_err());
+        assert!(KeyActionSequence::from_str("PRESERVE_CLIPBOARD { A↓").is_err());
+        assert!(KeyActionSequence::from_str("A↓ }").is_err());
+        let error = KeyActionSequence::from_str("PRESERVE_CLIPBOARD { PRESERVE_CLIPBOARD { A↓ } }")
+            .unwrap_err();
+
+        assert_eq!(
+            "Nested clipboard scope: `PRESERVE_CLIPBOARD { A↓ } }`",
+            error.message
+        );
+        assert_eq!(Some(21), error.offset);
+
+        let error =
+            KeyActionSequence::from_str("PRESERVE_CLIPBOARD { A↓ → PRESERVE_CLIPBOARD { B↓ }")
+                .unwrap_err();
+
+        assert!(error.message.starts_with("Nested clipboard scope"));
+    }
+
+    #[test]
+    fn test_key_action_sequence_os_layout() {
+        let actual = key_action_seq!("OS_LAYOUT(en-US) → A↓");
+
+        assert_eq!(
+            Some(&KeySequenceItem::OsLayout(
+                LayoutLocale::new("en-US").unwrap()
+            )),
+            actual.iter().next()
+        );
+        assert_eq!(1, actual.actions().count());
+        assert_eq!("OS_LAYOUT(en-US) → A↓", actual.to_string());
+
+        assert_eq!(
+            vec![
+                key_action_seq!("OS_LAYOUT(ru-RU) → A↓"),
+                key_action_seq!("OS_LAYOUT(ru-RU) → A↑")
+            ],
+            KeyActionSequence::from_str_expand("OS_LAYOUT ( ru-RU ) → A").unwrap()
+        );
+
+        assert!(KeyActionSequence::from_str("OS_LAYOUT").is_err());
+        assert!(KeyActionSequence::from_str("OS_LAYOUT()").is_err());
+        assert!(KeyActionSequence::from_str("OS_LAYOUT(en US)").is_err());
+    }
+
+    #[test]
+    fn test_key_action_sequence_type_clipboard() {
+        let actual = key_action_seq!("TYPE_CLIPBOARD → ENTER↓");
+
+        assert_eq!(
+            Some(&KeySequenceItem::TypeClipboard(0)),
+            actual.iter().next()
+        );
+        assert_eq!(1, actual.actions().count());
+        assert_eq!("TYPE_CLIPBOARD → ENTER↓", actual.to_string());
+
+        let actual = key_action_seq!("TYPE_CLIPBOARD ( 20 )");
+        assert_eq!(
+            Some(&KeySequenceItem::TypeClipboard(20)),
+            actual.iter().next()
+        );
+        assert_eq!("TYPE_CLIPBOARD(20)", actual.to_string());
+
+        assert!(KeyActionSequence::from_str("TYPE_CLIPBOARD()").is_err());
+        assert!(KeyActionSequence::from_str("TYPE_CLIPBOARD(-5)").is_err());
+        assert!(KeyActionSequence::from_str("TYPE_CLIPBOARD 20").is_err());
+    }
+
     #[test]
     fn test_key_action_sequence_serialize() {
         let source = SerdeWrapper::new(key_action_seq!("ENTER↓ → SHIFT↓"));
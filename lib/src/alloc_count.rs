@@ -0,0 +1,74 @@
+//! Counts memory allocations to check that the hook processes events without allocating.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// System allocator counting the allocations made by each thread.
+///
+/// Counting works only when it is installed as the global allocator:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// ```
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[inline(always)]
+fn count_allocation() {
+    /* the counter is gone while the thread is being destroyed */
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+/// Returns the number of allocations made by the current thread so far.
+pub fn allocations() -> u64 {
+    ALLOCATIONS.get()
+}
+
+/// Returns the number of allocations made by the current thread while running `f`.
+pub fn allocations_in(f: impl FnOnce()) -> u64 {
+    let start = allocations();
+    f();
+    allocations() - start
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alloc_count::allocations_in;
+    use std::hint::black_box;
+
+    #[test]
+    fn test_allocations_in() {
+        assert_eq!(
+            0,
+            allocations_in(|| {
+                black_box(1);
+            })
+        );
+        assert_eq!(1, allocations_in(|| drop(black_box(Box::new(1)))));
+    }
+}
@@ -0,0 +1,363 @@
+//! Audit of the rules output.
+//!
+//! The trace records the physical events together with the rule applied to each of them and
+//! the output synthesized by the rule. The trace recorded by the engine, or the one replayed
+//! through the edited rules, is compared to the golden trace to find the events the rules
+//! process differently now.
+use crate::action::KeySequenceItem;
+use crate::event::KeyEvent;
+use crate::rule::{KeyTransformRule, KeyTransformRules};
+use crate::trigger::KeyTrigger;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Write};
+
+const OUTPUT_SEPARATOR: &str = " → ";
+
+/// Physical event and the output of the rules for it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub input: KeyTrigger,
+    /// Applied rule as written in the rules source.
+    #[serde(default)]
+    pub rule: Option<String>,
+    /// Actions sent to the system including the passed original event.
+    pub output: String,
+}
+
+impl AuditEntry {
+    pub fn new(event: &KeyEvent, rule: Option<&KeyTransformRule>) -> Self {
+        let input = event.trigger.action;
+        let mut items: Vec<_> = rule
+            .map(|r| r.actions.iter().map(KeySequenceItem::to_string).collect())
+            .unwrap_or_default();
+        if rule.is_none_or(|r| r.is_pass_through) {
+            items.push(input.to_string());
+        }
+
+        Self {
+            input: event.trigger.clone(),
+            rule: rule.map(|r| r.to_string()),
+            output: items.join(OUTPUT_SEPARATOR),
+        }
+    }
+}
+
+impl Display for AuditEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} => {}", self.input, self.output)?;
+        if let Some(rule) = &self.rule {
+            write!(f, " (rule `{rule}`)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Sequence of the audit entries stored as JSON lines.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AuditTrace(Vec<AuditEntry>);
+
+impl AuditTrace {
+    /// Records the physical event. Private events are the output of the recorded ones.
+    pub fn record(&mut self, event: &KeyEvent, rule: Option<&KeyTransformRule>) {
+        if !event.is_private {
+            self.0.push(AuditEntry::new(event, rule));
+        }
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.0
+    }
+
+    /// Builds the trace the rules produce for the inputs of this trace.
+    pub fn replay(&self, rules: &KeyTransformRules) -> Self {
+        let entries = self
+            .0
+            .iter()
+            .map(|entry| {
+                let event = KeyEvent {
+                    trigger: entry.input.clone(),
+                    locks: entry.input.locks,
+                    taps: entry.input.taps,
+                    is_alone: entry.input.is_alone,
+                    ..Default::default()
+                };
+                AuditEntry::new(&event, rules.find_rule(&event).as_ref())
+            })
+            .collect();
+        Self(entries)
+    }
+
+    /// Compares this trace to the golden one.
+    pub fn diff(&self, golden: &AuditTrace) -> AuditReport {
+        let mut differences = Vec::new();
+        for (index, (expected, actual)) in golden.0.iter().zip(&self.0).enumerate() {
+            if expected.input != actual.input {
+                differences.push(AuditDifference::Diverged {
+                    index,
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+                /* the rest of the traces is not aligned */
+                return AuditReport { differences };
+            }
+            if expected.output != actual.output {
+                differences.push(AuditDifference::Changed {
+                    index,
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+
+        let common = golden.0.len().min(self.0.len());
+        differences.extend(
+            golden.0[common..]
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| AuditDifference::Missing(common + i, entry.clone())),
+        );
+        differences.extend(
+            self.0[common..]
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| AuditDifference::Unexpected(common + i, entry.clone())),
+        );
+        AuditReport { differences }
+    }
+
+    pub fn to_json_lines(&self) -> Result<String, serde_json::Error> {
+        let mut s = String::new();
+        for entry in &self.0 {
+            s.push_str(&serde_json::to_string(entry)?);
+            s.push('\n');
+        }
+        Ok(s)
+    }
+
+    /// Parses the trace skipping the blank lines.
+    pub fn from_json_lines(s: &str) -> Result<Self, serde_json::Error> {
+        let entries = s
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(Self(entries))
+    }
+}
+
+impl From<Vec<AuditEntry>> for AuditTrace {
+    fn from(vec: Vec<AuditEntry>) -> Self {
+        Self(vec)
+    }
+}
+
+/// Difference of the audit trace from the golden one. Indices are the entry numbers from 0.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditDifference {
+    /// Same input produced different output.
+    Changed {
+        index: usize,
+        expected: AuditEntry,
+        actual: AuditEntry,
+    },
+    /// Inputs differ, so the traces are not compared further.
+    Diverged {
+        index: usize,
+        expected: AuditEntry,
+        actual: AuditEntry,
+    },
+    /// Golden entry absent from the trace.
+    Missing(usize, AuditEntry),
+    /// Trace entry absent from the golden one.
+    Unexpected(usize, AuditEntry),
+}
+
+impl Display for AuditDifference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Changed {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "#{index} {}: expected {}, got {}",
+                expected.input, expected.output, actual.output
+            ),
+            Self::Diverged {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "#{index} input {} instead of {}, traces diverged",
+                actual.input, expected.input
+            ),
+            Self::Missing(index, entry) => write!(f, "#{index} missing: {entry}"),
+            Self::Unexpected(index, entry) => write!(f, "#{index} unexpected: {entry}"),
+        }
+    }
+}
+
+/// Result of the comparison of the audit trace to the golden one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AuditReport {
+    pub differences: Vec<AuditDifference>,
+}
+
+impl AuditReport {
+    pub fn is_passed(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl Display for AuditReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_passed() {
+            return f.write_str("Output matches the golden trace");
+        }
+
+        let mut s = format!(
+            "Output differs from the golden trace in {} entries:",
+            self.differences.len()
+        );
+        for difference in &self.differences {
+            write!(s, "\n  {difference}")?;
+        }
+        f.write_str(&s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audit::{AuditDifference, AuditEntry, AuditTrace};
+    use crate::event::KeyEvent;
+    use crate::rule::{KeyTransformRule, KeyTransformRules};
+    use crate::trigger::KeyTrigger;
+    use crate::{key_event, key_rule, key_rules, key_trigger};
+    use std::str::FromStr;
+
+    fn trace(rules: &KeyTransformRules, events: &[&str]) -> AuditTrace {
+        let mut trace = AuditTrace::default();
+        for event in events {
+            let event = KeyEvent {
+                trigger: KeyTrigger::from_str(event).unwrap(),
+                ..Default::default()
+            };
+            trace.record(&event, rules.find_rule(&event).as_ref());
+        }
+        trace
+    }
+
+    #[test]
+    fn test_audit_entry() {
+        let entry = AuditEntry::new(
+            &key_event!("[] A↓"),
+            Some(&key_rule!("A↓ : B↓ → DELAY(10) → C↓")),
+        );
+
+        assert_eq!(key_trigger!("[] A↓"), entry.input);
+        assert_eq!(Some("A↓ : B↓ → DELAY(10) → C↓"), entry.rule.as_deref());
+        assert_eq!("B↓ → DELAY(10) → C↓", entry.output);
+        assert_eq!(
+            "[] A↓ => B↓ → DELAY(10) → C↓ (rule `A↓ : B↓ → DELAY(10) → C↓`)",
+            entry.to_string()
+        );
+
+        let entry = AuditEntry::new(&key_event!("A↓"), Some(&key_rule!("A↓ : B↓ | PASS")));
+        assert_eq!("B↓ → A↓", entry.output);
+
+        let entry = AuditEntry::new(&key_event!("A↓"), None);
+        assert_eq!(None, entry.rule);
+        assert_eq!("A↓", entry.output);
+        assert_eq!("A↓ => A↓", entry.to_string());
+    }
+
+    #[test]
+    fn test_audit_trace_record_skips_private() {
+        let mut trace = AuditTrace::default();
+        trace.record(&key_event!("A↓"), None);
+        trace.record(
+            &KeyEvent {
+                is_private: true,
+                ..key_event!("B↓")
+            },
+            None,
+        );
+
+        assert_eq!(1, trace.entries().len());
+    }
+
+    #[test]
+    fn test_audit_trace_json_lines() {
+        let trace = trace(&key_rules!("A↓ : B↓"), &["[LEFT_SHIFT] A↓", "C↓"]);
+        let text = trace.to_json_lines().unwrap();
+
+        assert_eq!(2, text.lines().count());
+        assert_eq!(
+            trace,
+            AuditTrace::from_json_lines(&format!("{text}\n\n")).unwrap()
+        );
+        assert!(AuditTrace::from_json_lines("{}").is_err());
+    }
+
+    #[test]
+    fn test_audit_trace_replay() {
+        let golden = trace(
+            &key_rules!("A↓ : B↓\nC↓ : D↓"),
+            &["[] A↓", "[] C↓", "[] E↓"],
+        );
+        let rules = key_rules!("A↓ : B↓\nC↓ : F↓\nE↓ : G↓");
+        let report = golden.replay(&rules).diff(&golden);
+
+        assert_eq!(2, report.differences.len());
+        assert!(matches!(
+            &report.differences[0],
+            AuditDifference::Changed { index: 1, expected, actual }
+                if expected.output == "D↓" && actual.output == "F↓"
+        ));
+        assert_eq!(
+            "Output differs from the golden trace in 2 entries:\n  \
+            #1 [] C↓: expected D↓, got F↓\n  \
+            #2 [] E↓: expected E↓, got G↓",
+            report.to_string()
+        );
+        assert!(
+            golden
+                .replay(&key_rules!("A↓ : B↓\nC↓ : D↓"))
+                .diff(&golden)
+                .is_passed()
+        );
+    }
+
+    #[test]
+    fn test_audit_trace_diff_length() {
+        let rules = key_rules!("A↓ : B↓");
+        let golden = trace(&rules, &["A↓", "C↓"]);
+
+        assert_eq!(
+            vec![AuditDifference::Missing(1, golden.entries()[1].clone())],
+            trace(&rules, &["A↓"]).diff(&golden).differences
+        );
+
+        let actual = trace(&rules, &["A↓", "C↓", "D↓"]);
+        assert_eq!(
+            vec![AuditDifference::Unexpected(2, actual.entries()[2].clone())],
+            actual.diff(&golden).differences
+        );
+    }
+
+    #[test]
+    fn test_audit_trace_diff_diverged() {
+        let rules = key_rules!("A↓ : B↓");
+        let golden = trace(&rules, &["A↓", "C↓", "D↓"]);
+        let report = trace(&rules, &["A↓", "D↓", "C↓"]).diff(&golden);
+
+        assert_eq!(1, report.differences.len());
+        assert_eq!(
+            "#1 input D↓ instead of C↓, traces diverged",
+            report.differences[0].to_string()
+        );
+        assert!(!report.is_passed());
+    }
+}
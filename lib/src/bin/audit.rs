@@ -0,0 +1,85 @@
+//! Audits the rules output against a golden trace.
+//!
+//! `record` runs the engine with the rules in the audit mode and writes the physical events
+//! with the output of the rules into the trace file. Press ESC to finish. `check` replays the
+//! inputs of the golden trace through the rules and `diff` compares two recorded traces. Both
+//! print the report and fail when the output differs.
+//!
+//! ```text
+//! cargo run --bin audit -- record rules.txt golden.jsonl
+//! cargo run --bin audit -- check rules.txt golden.jsonl
+//! cargo run --bin audit -- diff trace.jsonl golden.jsonl
+//! ```
+use keympostor::audit::{AuditReport, AuditTrace};
+use keympostor::rule::KeyTransformRules;
+use std::error::Error;
+use std::str::FromStr;
+use std::{env, fs, process};
+
+const USAGE: &str = "Usage: audit <record|check> <rules> <golden> | audit diff <trace> <golden>";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<_> = env::args().skip(1).collect();
+    let [command, source, golden] = args.as_slice() else {
+        return Err(USAGE.into());
+    };
+
+    match command.as_str() {
+        "record" => record(&load_rules(source)?, golden),
+        "check" => {
+            let golden = load_trace(golden)?;
+            report(golden.replay(&load_rules(source)?).diff(&golden))
+        }
+        "diff" => report(load_trace(source)?.diff(&load_trace(golden)?)),
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn load_rules(path: &str) -> Result<KeyTransformRules, Box<dyn Error>> {
+    Ok(KeyTransformRules::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn load_trace(path: &str) -> Result<AuditTrace, Box<dyn Error>> {
+    Ok(AuditTrace::from_json_lines(&fs::read_to_string(path)?)?)
+}
+
+fn report(report: AuditReport) -> Result<(), Box<dyn Error>> {
+    println!("{report}");
+    if !report.is_passed() {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "win32")]
+fn record(rules: &KeyTransformRules, path: &str) -> Result<(), Box<dyn Error>> {
+    use keympostor::engine::Engine;
+    use keympostor::key::Key;
+    use std::io::Write;
+
+    let mut file = fs::File::create(path)?;
+    let mut engine = Engine::new(rules.clone());
+    engine.start()?;
+    let entries = engine
+        .controller()
+        .ok_or("Engine is not running")?
+        .audit()?;
+    println!("Recording the audit trace. Press ESC to finish.");
+
+    for entry in entries {
+        if entry.input.action.key == Key::Esc {
+            break;
+        }
+        println!("{entry}");
+        /* written line by line so that the trace survives the interruption */
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    }
+
+    engine.stop();
+    Ok(())
+}
+
+#[cfg(not(feature = "win32"))]
+fn record(_rules: &KeyTransformRules, _path: &str) -> Result<(), Box<dyn Error>> {
+    Err("Recording requires the `win32` feature".into())
+}
@@ -0,0 +1,27 @@
+//! Describes the rules in plain language.
+//!
+//! Each argument is a rule as written in the rules source. Rules that expand into several
+//! ones are described one by one.
+//!
+//! ```text
+//! cargo run --bin explain -- "[LEFT_CTRL] K : ESC"
+//! ```
+use keympostor::explain::explain_rule;
+use keympostor::rule::KeyTransformRules;
+use std::env;
+use std::error::Error;
+use std::str::FromStr;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<_> = env::args().skip(1).collect();
+    if args.is_empty() {
+        return Err("Usage: explain \"<rule>\"...".into());
+    }
+
+    for arg in args {
+        for rule in KeyTransformRules::from_str(&arg)?.iter() {
+            println!("{rule}\n{}\n", explain_rule(rule));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,20 @@
+//! Prints the table of the key names, virtual keys, scan codes and USB HID usages for the
+//! tools interpreting the rules on the other platforms.
+//!
+//! ```text
+//! cargo run --bin key_table -- json
+//! cargo run --bin key_table -- rust > keys.rs
+//! ```
+use keympostor::key_table::{key_table_json, key_table_rust};
+use std::env;
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let format = env::args().nth(1).ok_or("Usage: key_table <json|rust>")?;
+    match format.as_str() {
+        "json" => println!("{}", key_table_json()?),
+        "rust" => print!("{}", key_table_rust()),
+        _ => return Err(format!("Unknown format: `{format}`").into()),
+    }
+    Ok(())
+}
@@ -0,0 +1,21 @@
+//! Prints the rules of a built-in layout preset.
+//!
+//! The output can be pasted into a layout file to tweak the preset.
+//!
+//! ```text
+//! cargo run --bin preset -- colemak
+//! ```
+use keympostor::presets::LayoutPreset;
+use std::env;
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let name = env::args()
+        .nth(1)
+        .ok_or("Usage: preset <colemak|dvorak|workman>")?;
+    let preset =
+        LayoutPreset::from_name(&name).ok_or_else(|| format!("Unknown preset: `{name}`"))?;
+
+    println!("{}", preset.rules());
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Prints the triggers of the pressed keys to be pasted into the rules.
+//!
+//! The pressed keys are swallowed so they do not reach the other applications. Modifier keys
+//! pass through and go into the triggers. Press ESC to finish.
+//!
+//! ```text
+//! cargo run --bin record -- 3
+//! ```
+use keympostor::hook::KeyboardHook;
+use keympostor::key::Key;
+use std::env;
+use std::error::Error;
+use windows::Win32::UI::WindowsAndMessaging::{
+    DispatchMessageW, GetMessageW, MSG, PostQuitMessage, TranslateMessage,
+};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let count = match env::args().nth(1) {
+        Some(arg) => Some(
+            arg.parse::<usize>()
+                .map_err(|_| format!("Usage: record [number of keys], got `{arg}`"))?,
+        ),
+        None => None,
+    };
+
+    /* the hook lives until the process exits, the captures refer to it */
+    let hook: &'static KeyboardHook = Box::leak(Box::default());
+    hook.install()?;
+    println!("Press the keys to record, ESC to finish");
+    record(hook, count);
+
+    let mut msg = MSG::default();
+    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.as_bool() {
+        unsafe {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+
+    hook.uninstall();
+    Ok(())
+}
+
+/// Captures the keys one by one until the count is reached.
+fn record(hook: &'static KeyboardHook, remaining: Option<usize>) {
+    if remaining == Some(0) {
+        unsafe { PostQuitMessage(0) };
+        return;
+    }
+
+    hook.capture_next(move |event| {
+        if event.trigger.action.key == Key::Esc {
+            unsafe { PostQuitMessage(0) };
+        } else {
+            println!("{}", event.trigger);
+            record(hook, remaining.map(|n| n - 1));
+        }
+    });
+}
@@ -0,0 +1,444 @@
+//! Long-run stability test of the keyboard hook.
+//!
+//! Runs the hook with the rules while a generator thread types random words into the test
+//! window. Typing pauses while the window is not in the foreground, so the other applications
+//! never receive generated input. Press ESC to finish early.
+//!
+//! ```text
+//! cargo run --release --bin soak -- --minutes 240 --rate 8 --rules rules.txt
+//! ```
+use keympostor::hook::KeyboardHook;
+use keympostor::key::Key;
+use keympostor::notify::{KeyEventNotification, WM_KEY_HOOK_NOTIFY};
+use keympostor::rule::KeyTransformRules;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs, thread};
+use windows::Win32::System::ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+use windows::Win32::System::Threading::GetCurrentProcess;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBD_EVENT_FLAGS, KEYBDINPUT,
+    KEYEVENTF_KEYUP, SendInput, VIRTUAL_KEY,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CW_USEDEFAULT, CreateWindowExW, DestroyWindow, DispatchMessageW, GetForegroundWindow,
+    GetMessageW, KillTimer, MSG, PostQuitMessage, SetForegroundWindow, SetTimer, TranslateMessage,
+    WINDOW_EX_STYLE, WM_TIMER, WS_OVERLAPPEDWINDOW, WS_VISIBLE,
+};
+use windows::core::w;
+
+const DEFAULT_RULES: &str = r#"
+    Q↓ : Z↓
+    Q↑ : Z↑
+    [LEFT_SHIFT] E↓ : LEFT_SHIFT↑ → E↓ → E↑ → LEFT_SHIFT↓
+    CAPS_LOCK↓ : LEFT_CTRL↓
+    CAPS_LOCK↑ : LEFT_CTRL↑
+"#;
+
+const TIMER_ID: usize = 1;
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Events not received for this long while typing means the system removed the hook.
+const HOOK_LOSS_TIMEOUT: Duration = Duration::from_secs(5);
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+/// Time to receive the last generated events before counting dropped ones.
+const DRAIN_TIME: Duration = Duration::from_secs(2);
+
+const LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+const MODIFIERS: [Key; 8] = [
+    Key::LeftShift,
+    Key::RightShift,
+    Key::LeftCtrl,
+    Key::RightCtrl,
+    Key::LeftAlt,
+    Key::RightAlt,
+    Key::LeftWin,
+    Key::RightWin,
+];
+
+struct Options {
+    duration: Duration,
+    /// Average keys per second while typing.
+    rate: u32,
+    rules: KeyTransformRules,
+}
+
+impl Options {
+    fn parse() -> Result<Self, Box<dyn Error>> {
+        let mut options = Self {
+            duration: Duration::from_secs(60 * 60),
+            rate: 8,
+            rules: KeyTransformRules::from_str(DEFAULT_RULES)?,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value of `{arg}`"));
+            match arg.as_str() {
+                "--minutes" => {
+                    options.duration = Duration::from_secs(60 * value()?.parse::<u64>()?)
+                }
+                "--rate" => options.rate = value()?.parse::<u32>()?.max(1),
+                "--rules" => {
+                    options.rules = KeyTransformRules::from_str(&fs::read_to_string(value()?)?)?
+                }
+                _ => return Err(format!("Unknown argument: `{arg}`").into()),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Counters shared with the generator thread.
+#[derive(Default)]
+struct Generator {
+    sent: AtomicU64,
+    paused_ms: AtomicU64,
+    stop: AtomicBool,
+}
+
+/// Xorshift generator. Test input need not be cryptographically random.
+struct Random(u64);
+
+impl Random {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(1);
+        Self(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn range(&mut self, from: u64, to: u64) -> u64 {
+        from + self.next() % (to - from)
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.range(0, 100) < percent
+    }
+}
+
+impl Generator {
+    fn run(&self, hwnd: isize, rate: u32) {
+        let mut random = Random::new();
+        let key_interval = 1000 / rate as u64;
+
+        while !self.stop.load(Ordering::Relaxed) {
+            for _ in 0..random.range(2, 10) {
+                let shift = random.chance(10);
+                let key = LETTERS[random.range(0, LETTERS.len() as u64) as usize];
+                if shift {
+                    self.send(hwnd, Key::LeftShift, false);
+                }
+                self.send(hwnd, key, false);
+                self.sleep(random.range(20, 120));
+                self.send(hwnd, key, true);
+                if shift {
+                    self.send(hwnd, Key::LeftShift, true);
+                }
+                self.sleep(random.range(key_interval / 2, key_interval * 3 / 2 + 1));
+            }
+
+            self.send(hwnd, Key::Space, false);
+            self.send(hwnd, Key::Space, true);
+
+            if random.chance(5) {
+                self.sleep(random.range(1000, 3000));
+            }
+        }
+    }
+
+    /// Sends the key when the test window is active. Otherwise waits for it.
+    fn send(&self, hwnd: isize, key: Key, is_up: bool) {
+        while unsafe { GetForegroundWindow() }.0 as isize != hwnd {
+            if self.stop.load(Ordering::Relaxed) {
+                if is_up {
+                    break; /* releases the keys pressed before the stop anyway */
+                }
+                return;
+            }
+            self.sleep(100);
+            self.paused_ms.fetch_add(100, Ordering::Relaxed);
+        }
+
+        let input = INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: VIRTUAL_KEY(key.vk() as u16),
+                    wScan: key.sc() as u16,
+                    dwFlags: if is_up {
+                        KEYEVENTF_KEYUP
+                    } else {
+                        KEYBD_EVENT_FLAGS::default()
+                    },
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        };
+        if unsafe { SendInput(&[input], size_of::<INPUT>() as i32) } == 1 {
+            self.sent.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn sleep(&self, millis: u64) {
+        thread::sleep(Duration::from_millis(millis));
+    }
+}
+
+#[derive(Default)]
+struct Report {
+    duration: Duration,
+    paused: Duration,
+    sent: u64,
+    received: u64,
+    transformed: u64,
+    hook_losses: u32,
+    stuck_modifiers: Vec<Key>,
+    memory_start: usize,
+    memory_peak: usize,
+    memory_end: usize,
+}
+
+impl Report {
+    fn dropped(&self) -> u64 {
+        self.sent.saturating_sub(self.received)
+    }
+
+    fn is_passed(&self) -> bool {
+        self.dropped() == 0 && self.hook_losses == 0 && self.stuck_modifiers.is_empty()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kib = |bytes: usize| bytes / 1024;
+        writeln!(f, "Duration:        {:?}", self.duration)?;
+        writeln!(f, "Paused:          {:?}", self.paused)?;
+        writeln!(f, "Events sent:     {}", self.sent)?;
+        writeln!(f, "Events received: {}", self.received)?;
+        writeln!(f, "Transformed:     {}", self.transformed)?;
+        writeln!(f, "Dropped:         {}", self.dropped())?;
+        writeln!(f, "Hook losses:     {}", self.hook_losses)?;
+        writeln!(f, "Stuck modifiers: {:?}", self.stuck_modifiers)?;
+        writeln!(
+            f,
+            "Working set:     start {} KiB, peak {} KiB, end {} KiB, growth {} KiB",
+            kib(self.memory_start),
+            kib(self.memory_peak),
+            kib(self.memory_end),
+            kib(self.memory_end) as isize - kib(self.memory_start) as isize
+        )?;
+        write!(
+            f,
+            "Result:          {}",
+            if self.is_passed() { "PASSED" } else { "FAILED" }
+        )
+    }
+}
+
+fn working_set() -> usize {
+    let mut counters = PROCESS_MEMORY_COUNTERS::default();
+    unsafe {
+        GetProcessMemoryInfo(
+            GetCurrentProcess(),
+            &mut counters,
+            size_of::<PROCESS_MEMORY_COUNTERS>() as u32,
+        )
+        .map(|_| counters.WorkingSetSize)
+        .unwrap_or_default()
+    }
+}
+
+fn pressed_modifiers() -> Vec<Key> {
+    MODIFIERS
+        .into_iter()
+        .filter(|key| unsafe { GetAsyncKeyState(key.vk() as i32) } < 0)
+        .collect()
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = Options::parse()?;
+
+    let hwnd = unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            w!("STATIC"),
+            w!("Keympostor soak test. Keep this window active."),
+            WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            480,
+            120,
+            None,
+            None,
+            None,
+            None,
+        )?
+    };
+    unsafe {
+        let _ = SetForegroundWindow(hwnd);
+        SetTimer(
+            Some(hwnd),
+            TIMER_ID,
+            CHECK_INTERVAL.as_millis() as u32,
+            None,
+        );
+    }
+
+    let hook = KeyboardHook::default();
+    hook.setup(hwnd);
+    hook.set_rules(Some(&options.rules));
+    hook.install()?;
+
+    let generator = Arc::new(Generator::default());
+    let generator_thread = thread::spawn({
+        let generator = generator.clone();
+        let hwnd = hwnd.0 as isize;
+        let rate = options.rate;
+        move || generator.run(hwnd, rate)
+    });
+
+    println!(
+        "Soak test started for {:?} at {} keys per second",
+        options.duration, options.rate
+    );
+    let mut report = Report {
+        memory_start: working_set(),
+        ..Default::default()
+    };
+    report.memory_peak = report.memory_start;
+
+    let start = Instant::now();
+    let mut last_received = Instant::now();
+    let mut last_memory_sample = Instant::now();
+    let mut last_sent = 0;
+    let mut stop_time: Option<Instant> = None;
+
+    let mut msg = MSG::default();
+    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.as_bool() {
+        if msg.message == WM_KEY_HOOK_NOTIFY {
+            let notification = unsafe { Box::from_raw(msg.lParam.0 as *mut KeyEventNotification) };
+            on_notification(&notification, &mut report, &generator);
+            last_received = Instant::now();
+        } else if msg.message == WM_TIMER && msg.wParam.0 == TIMER_ID {
+            let now = Instant::now();
+
+            if let Some(time) = stop_time {
+                if now.duration_since(time) >= DRAIN_TIME {
+                    unsafe { PostQuitMessage(0) };
+                }
+                continue;
+            }
+
+            let sent = generator.sent.load(Ordering::Relaxed);
+            if sent > last_sent && now.duration_since(last_received) > HOOK_LOSS_TIMEOUT {
+                eprintln!("Hook lost after {:?}. Reinstalling", start.elapsed());
+                report.hook_losses += 1;
+                hook.uninstall();
+                if let Err(e) = hook.install() {
+                    eprintln!("{e}");
+                }
+                last_received = now;
+            }
+            last_sent = sent;
+
+            if now.duration_since(last_memory_sample) >= MEMORY_SAMPLE_INTERVAL {
+                let memory = working_set();
+                report.memory_peak = report.memory_peak.max(memory);
+                println!(
+                    "{:?}: {} events, {} KiB",
+                    start.elapsed(),
+                    report.received,
+                    memory / 1024
+                );
+                last_memory_sample = now;
+            }
+
+            if start.elapsed() >= options.duration || generator.stop.load(Ordering::Relaxed) {
+                generator.stop.store(true, Ordering::Relaxed);
+                stop_time = Some(now);
+            }
+        } else {
+            unsafe {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+    }
+
+    generator_thread.join().ok();
+    report.duration = start.elapsed();
+    report.paused = Duration::from_millis(generator.paused_ms.load(Ordering::Relaxed));
+    report.sent = generator.sent.load(Ordering::Relaxed);
+    report.stuck_modifiers = pressed_modifiers();
+    report.memory_end = working_set();
+    report.memory_peak = report.memory_peak.max(report.memory_end);
+
+    hook.uninstall();
+    unsafe {
+        let _ = KillTimer(Some(hwnd), TIMER_ID);
+        DestroyWindow(hwnd)?;
+    }
+
+    println!("{report}");
+    if !report.is_passed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn on_notification(
+    notification: &KeyEventNotification,
+    report: &mut Report,
+    generator: &Generator,
+) {
+    let event = &notification.event;
+    if event.is_injected && !event.is_private {
+        report.received += 1;
+        if notification.rule.is_some() {
+            report.transformed += 1;
+        }
+    } else if !event.is_injected && event.trigger.action.key == Key::Esc {
+        generator.stop.store(true, Ordering::Relaxed);
+    }
+}
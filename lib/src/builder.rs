@@ -0,0 +1,235 @@
+use crate::action::{KeyAction, KeyActionSequence, KeySequenceItem};
+use crate::key::Key;
+use crate::modifiers::KeyModifiers::{All, Any};
+use crate::repeat::KeyRepeat;
+use crate::rule::KeyTransformRule;
+use crate::state::KeyboardState;
+use crate::transition::KeyTransition;
+use crate::transition::KeyTransition::{Down, Up};
+use crate::trigger::KeyTrigger;
+use crate::window::WindowCondition;
+
+/// Builds trigger without parsing the rules source.
+#[derive(Clone, Debug)]
+pub struct TriggerBuilder {
+    trigger: KeyTrigger,
+}
+
+impl TriggerBuilder {
+    pub fn new(key: Key, transition: KeyTransition) -> Self {
+        Self {
+            trigger: KeyTrigger {
+                action: KeyAction::new(key, transition),
+                modifiers: Any,
+                locks: Default::default(),
+                taps: 0,
+                is_alone: false,
+            },
+        }
+    }
+
+    pub fn down(key: Key) -> Self {
+        Self::new(key, Down)
+    }
+
+    pub fn up(key: Key) -> Self {
+        Self::new(key, Up)
+    }
+
+    /// Adds the key to the modifiers that must be pressed.
+    pub fn modifier(mut self, key: Key) -> Self {
+        let mut state = match self.trigger.modifiers {
+            All(state) => state,
+            Any => KeyboardState::default(),
+        };
+        state.update(&KeyAction::new(key, Down));
+        self.trigger.modifiers = All(state);
+        self
+    }
+
+    /// Requires no modifiers to be pressed.
+    pub fn no_modifiers(mut self) -> Self {
+        self.trigger.modifiers = All(KeyboardState::default());
+        self
+    }
+
+    pub fn lock(mut self, key: Key, is_on: bool) -> Self {
+        self.trigger.locks = self.trigger.locks.with(key, is_on);
+        self
+    }
+
+    pub fn taps(mut self, taps: u8) -> Self {
+        self.trigger.taps = taps;
+        self
+    }
+
+    /// Requires no other key to be pressed since the key press.
+    pub fn alone(mut self) -> Self {
+        self.trigger.is_alone = true;
+        self
+    }
+
+    pub fn build(self) -> KeyTrigger {
+        self.trigger
+    }
+}
+
+/// Builds output sequence without parsing the rules source.
+#[derive(Clone, Debug, Default)]
+pub struct SequenceBuilder {
+    items: Vec<KeySequenceItem>,
+}
+
+impl SequenceBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn action(mut self, action: KeyAction) -> Self {
+        self.items.push(KeySequenceItem::Action(action));
+        self
+    }
+
+    pub fn down(self, key: Key) -> Self {
+        self.action(KeyAction::new(key, Down))
+    }
+
+    pub fn up(self, key: Key) -> Self {
+        self.action(KeyAction::new(key, Up))
+    }
+
+    /// Adds key press followed by release.
+    pub fn press(self, key: Key) -> Self {
+        self.down(key).up(key)
+    }
+
+    /// Adds pause in milliseconds.
+    pub fn delay(mut self, delay: u32) -> Self {
+        self.items.push(KeySequenceItem::Delay(delay));
+        self
+    }
+
+    pub fn build(self) -> KeyActionSequence {
+        KeyActionSequence::from_items(self.items)
+    }
+}
+
+/// Builds rule without parsing the rules source.
+#[derive(Clone, Debug)]
+pub struct RuleBuilder {
+    rule: KeyTransformRule,
+}
+
+impl RuleBuilder {
+    pub fn new(trigger: KeyTrigger, actions: KeyActionSequence) -> Self {
+        Self {
+            rule: KeyTransformRule {
+                id: None,
+                trigger,
+                actions,
+                repeat: KeyRepeat::Pass,
+                is_pass_through: false,
+                device: None,
+                window: None,
+                priority: 0,
+                target: None,
+            },
+        }
+    }
+
+    pub fn id(mut self, id: u32) -> Self {
+        self.rule.id = Some(id);
+        self
+    }
+
+    pub fn repeat(mut self, repeat: KeyRepeat) -> Self {
+        self.rule.repeat = repeat;
+        self
+    }
+
+    pub fn pass_through(mut self) -> Self {
+        self.rule.is_pass_through = true;
+        self
+    }
+
+    pub fn device(mut self, device: &str) -> Self {
+        self.rule.device = Some(device.into());
+        self
+    }
+
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.rule.priority = priority;
+        self
+    }
+
+    /// Posts the actions to the window instead of sending them as the global input.
+    pub fn target(mut self, target: WindowCondition) -> Self {
+        self.rule.target = Some(target);
+        self
+    }
+
+    pub fn build(self) -> KeyTransformRule {
+        self.rule
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::{RuleBuilder, SequenceBuilder, TriggerBuilder};
+    use crate::key::Key;
+    use crate::repeat::KeyRepeat;
+    use crate::rule::KeyTransformRule;
+    use crate::trigger::KeyTrigger;
+    use crate::window::WindowCondition;
+    use crate::{key_rule, key_trigger};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_trigger_builder() {
+        assert_eq!(key_trigger!("A↓"), TriggerBuilder::down(Key::A).build());
+        assert_eq!(
+            key_trigger!("[] A↑"),
+            TriggerBuilder::up(Key::A).no_modifiers().build()
+        );
+        assert_eq!(
+            key_trigger!("[LEFT_CTRL + LEFT_SHIFT + NUM_LOCK=off] A××↓"),
+            TriggerBuilder::down(Key::A)
+                .modifier(Key::LeftShift)
+                .modifier(Key::LeftCtrl)
+                .lock(Key::NumLock, false)
+                .taps(2)
+                .build()
+        );
+        assert_eq!(
+            key_trigger!("LEFT_CTRL!↑"),
+            TriggerBuilder::up(Key::LeftCtrl).alone().build()
+        );
+    }
+
+    #[test]
+    fn test_rule_builder() {
+        let rule = RuleBuilder::new(
+            TriggerBuilder::down(Key::A).modifier(Key::LeftAlt).build(),
+            SequenceBuilder::new()
+                .down(Key::LeftShift)
+                .press(Key::B)
+                .delay(100)
+                .up(Key::LeftShift)
+                .build(),
+        )
+        .id(7)
+        .repeat(KeyRepeat::Suppress)
+        .pass_through()
+        .device("PAD")
+        .priority(2)
+        .target(WindowCondition::new("Notepad").unwrap())
+        .build();
+
+        assert_eq!(
+            key_rule!(
+                "#7 [LEFT_ALT] A↓ : LEFT_SHIFT↓ → B↓ → B↑ → DELAY(100) → LEFT_SHIFT↑ | NO_REPEAT, PASS, DEVICE=PAD, PRIORITY=2, TARGET=Notepad"
+            ),
+            rule
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::action::KeyAction;
+use crate::event::KeyEvent;
+use crate::key::Key;
+use crate::key_char::{KeyChar, KeyText, MAX_UNITS};
+use crate::modifiers::KeyModifiers::All;
+use crate::modifiers::{KeyLocks, LOCK_KEYS};
+use crate::state::KeyboardState;
+use crate::transition::KeyTransition::Down;
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetKeyboardLayout, HKL, ToUnicodeEx};
+use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+/* keeps the keyboard state including pending dead key unchanged (Windows 10 1607+) */
+const NO_STATE_CHANGE: u32 = 0x4;
+const KEY_PRESSED: u8 = 0x80;
+const KEY_TOGGLED: u8 = 0x01;
+
+/// Output of the keyboard layout for a key.
+#[derive(Debug, PartialEq)]
+enum LayoutOutput {
+    None,
+    Text(KeyText),
+    Dead(char),
+}
+
+/// Resolves characters of the key presses tracking dead key composition.
+///
+/// Keyboard state is never changed so the resolution does not interfere
+/// with dead keys typed into the foreground window.
+#[derive(Debug, Default)]
+pub struct CharResolver {
+    dead: Option<char>,
+}
+
+impl CharResolver {
+    /// Returns the character of the key press in the keyboard layout of the foreground window.
+    pub fn resolve(
+        &mut self,
+        action: &KeyAction,
+        modifiers: &KeyboardState,
+        locks: &KeyLocks,
+    ) -> Option<KeyChar> {
+        if action.transition != Down || action.key.is_modifier() {
+            return None;
+        }
+
+        let output = to_unicode(
+            action.key,
+            &key_state(modifiers, locks),
+            foreground_layout(),
+        );
+        self.compose(output)
+    }
+
+    /// Sets the character of the key event unless it is known already, like the one carried
+    /// by a packet. Called by the event consumers, so that the hook does not query the layout.
+    pub fn resolve_event(&mut self, event: &mut KeyEvent) {
+        if event.character.is_some() {
+            return;
+        }
+        if let All(modifiers) = &event.trigger.modifiers {
+            event.character = self.resolve(&event.trigger.action, modifiers, &event.locks);
+        }
+    }
+
+    /// Returns the character carried by `VK_PACKET` key press.
+    pub fn resolve_packet(action: &KeyAction, unit: u16) -> Option<KeyChar> {
+        if action.transition != Down {
+            return None;
+        }
+        char::from_u32(unit as u32).map(|ch| KeyChar::Text(KeyText::from_iter([ch])))
+    }
+
+    fn compose(&mut self, output: LayoutOutput) -> Option<KeyChar> {
+        match output {
+            LayoutOutput::None => None,
+            LayoutOutput::Dead(dead) => {
+                self.dead = Some(dead);
+                Some(KeyChar::Dead(dead))
+            }
+            LayoutOutput::Text(text) => match self.dead.take() {
+                Some(dead) => Some(KeyChar::Composed { dead, text }),
+                None => Some(KeyChar::Text(text)),
+            },
+        }
+    }
+}
+
+fn foreground_layout() -> HKL {
+    unsafe { GetKeyboardLayout(GetWindowThreadProcessId(GetForegroundWindow(), None)) }
+}
+
+fn key_state(modifiers: &KeyboardState, locks: &KeyLocks) -> [u8; 256] {
+    let mut state = [0u8; 256];
+    for key in modifiers.keys() {
+        state[key.vk() as usize] |= KEY_PRESSED;
+
+        /* layouts check generic modifier keys */
+        let generic = match key {
+            Key::LeftShift | Key::RightShift => Some(Key::Shift),
+            Key::LeftCtrl | Key::RightCtrl => Some(Key::Ctrl),
+            Key::LeftAlt | Key::RightAlt => Some(Key::Menu),
+            _ => None,
+        };
+        if let Some(generic) = generic {
+            state[generic.vk() as usize] |= KEY_PRESSED;
+        }
+    }
+    for key in LOCK_KEYS {
+        if locks.get(key) == Some(true) {
+            state[key.vk() as usize] |= KEY_TOGGLED;
+        }
+    }
+    state
+}
+
+fn to_unicode(key: Key, state: &[u8; 256], layout: HKL) -> LayoutOutput {
+    let mut buffer = [0u16; MAX_UNITS];
+    let count = unsafe {
+        ToUnicodeEx(
+            key.vk() as u32,
+            key.sc() as u32,
+            state,
+            &mut buffer,
+            NO_STATE_CHANGE,
+            Some(layout),
+        )
+    };
+
+    match count {
+        0 => LayoutOutput::None,
+        c if c < 0 => char::from_u32(buffer[0] as u32)
+            .map(LayoutOutput::Dead)
+            .unwrap_or(LayoutOutput::None),
+        c => LayoutOutput::Text(KeyText::from_utf16_lossy(&buffer[..c as usize])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alloc_count::allocations_in;
+    use crate::char_resolver::{CharResolver, LayoutOutput, key_state};
+    use crate::key::Key;
+    use crate::key_char::{KeyChar, KeyText};
+    use crate::modifiers::KeyLocks;
+    use crate::state::KeyboardState;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_compose() {
+        let mut resolver = CharResolver::default();
+
+        assert_eq!(
+            Some(KeyChar::Text("a".into())),
+            resolver.compose(LayoutOutput::Text("a".into()))
+        );
+        assert_eq!(
+            Some(KeyChar::Dead('´')),
+            resolver.compose(LayoutOutput::Dead('´'))
+        );
+        assert_eq!(None, resolver.compose(LayoutOutput::None));
+        assert_eq!(
+            Some(KeyChar::Composed {
+                dead: '´',
+                text: "á".into()
+            }),
+            resolver.compose(LayoutOutput::Text("á".into()))
+        );
+        assert_eq!(
+            Some(KeyChar::Text("b".into())),
+            resolver.compose(LayoutOutput::Text("b".into()))
+        );
+    }
+
+    #[test]
+    fn test_compose_no_allocations() {
+        let mut resolver = CharResolver::default();
+
+        assert_eq!(
+            0,
+            allocations_in(|| {
+                resolver.compose(LayoutOutput::Dead('´'));
+                resolver.compose(LayoutOutput::Text(KeyText::from_utf16_lossy(&[0xE1])));
+            })
+        );
+    }
+
+    #[test]
+    fn test_key_state() {
+        let modifiers = KeyboardState::from_str("RIGHT_ALT + LEFT_CTRL").unwrap();
+        let locks = KeyLocks::default().with(Key::CapsLock, true);
+
+        let state = key_state(&modifiers, &locks);
+
+        assert_eq!(0x80, state[Key::RightAlt.vk() as usize]);
+        assert_eq!(0x80, state[Key::Menu.vk() as usize]);
+        assert_eq!(0x80, state[Key::Ctrl.vk() as usize]);
+        assert_eq!(0x01, state[Key::CapsLock.vk() as usize]);
+        assert_eq!(0, state[Key::Shift.vk() as usize]);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::event::KeyEventRecord;
+use crate::rule::KeyTransformRules;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Named pipe the application control server listens on by default.
+pub const DEFAULT_PIPE: &str = r"\\.\pipe\keympostor";
+
+/// Application state returned by `get_state` command.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RemoteState {
+    /// Key processing is enabled.
+    pub enabled: bool,
+    pub autoswitch_enabled: bool,
+    /// Reloaded layouts wait for confirmation.
+    pub layouts_trial: bool,
+    pub profile: Option<String>,
+    pub layout: String,
+    pub profiles: Vec<String>,
+    pub layouts: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum RemoteError {
+    Io(std::io::Error),
+    /// Malformed or unexpected server message.
+    Protocol(String),
+    /// Error response of the server.
+    Remote {
+        code: i32,
+        message: String,
+    },
+}
+
+impl Display for RemoteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Protocol(message) => write!(f, "Protocol error: {message}"),
+            Self::Remote { code, message } => write!(f, "{message} ({code})"),
+        }
+    }
+}
+
+impl Error for RemoteError {}
+
+/* errors are compared by message since `io::Error` is not comparable */
+impl PartialEq for RemoteError {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+impl From<std::io::Error> for RemoteError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for RemoteError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Protocol(e.to_string())
+    }
+}
+
+/// Message received from the server.
+#[derive(Debug, PartialEq)]
+enum Incoming {
+    Response {
+        id: Value,
+        result: Result<Value, RemoteError>,
+    },
+    KeyEvent(Box<KeyEventRecord>),
+}
+
+impl FromStr for Incoming {
+    type Err = RemoteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut message: Value = serde_json::from_str(s)?;
+
+        if let Some(method) = message.get("method") {
+            return if method == "key_event" {
+                Ok(Self::KeyEvent(serde_json::from_value(
+                    message["params"].take(),
+                )?))
+            } else {
+                Err(RemoteError::Protocol(format!(
+                    "Unexpected notification: {method}"
+                )))
+            };
+        }
+
+        let id = message["id"].take();
+        let result = match message.get_mut("error") {
+            Some(error) => Err(RemoteError::Remote {
+                code: error["code"].as_i64().unwrap_or_default() as i32,
+                message: error["message"].as_str().unwrap_or_default().into(),
+            }),
+            None => Ok(message["result"].take()),
+        };
+        Ok(Self::Response { id, result })
+    }
+}
+
+/// Typed client of the application control server.
+///
+/// Key events received while waiting for a command response are kept
+/// and returned by [`RemoteController::next_event`] later.
+pub struct RemoteController {
+    reader: BufReader<File>,
+    writer: File,
+    next_id: u64,
+    events: VecDeque<KeyEventRecord>,
+}
+
+impl RemoteController {
+    /// Connects to the server pipe, [`DEFAULT_PIPE`] unless configured otherwise.
+    pub fn connect<P: AsRef<Path>>(pipe: P) -> Result<Self, RemoteError> {
+        let writer = OpenOptions::new().read(true).write(true).open(pipe)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self {
+            reader,
+            writer,
+            next_id: 1,
+            events: VecDeque::new(),
+        })
+    }
+
+    pub fn state(&mut self) -> Result<RemoteState, RemoteError> {
+        self.call("get_state", Value::Null)
+    }
+
+    pub fn active_profile(&mut self) -> Result<Option<String>, RemoteError> {
+        Ok(self.state()?.profile)
+    }
+
+    /// Selects the profile by name or no profile.
+    pub fn set_profile(&mut self, name: Option<&str>) -> Result<(), RemoteError> {
+        self.call("set_profile", json!({ "name": name }))
+    }
+
+    pub fn enable(&mut self) -> Result<(), RemoteError> {
+        self.call("enable", Value::Null)
+    }
+
+    pub fn disable(&mut self) -> Result<(), RemoteError> {
+        self.call("disable", Value::Null)
+    }
+
+    /// Reloads layouts from disk. They are rolled back unless the user confirms them.
+    pub fn reload(&mut self) -> Result<(), RemoteError> {
+        self.call("reload", Value::Null)
+    }
+
+    /// Replaces rules of the layout, or of the active one when no name given, and saves it.
+    pub fn set_rules(
+        &mut self,
+        layout: Option<&str>,
+        rules: &KeyTransformRules,
+    ) -> Result<(), RemoteError> {
+        /* array keeps the order of the rules and the ones of the same trigger */
+        let rules: Vec<String> = rules.iter().map(ToString::to_string).collect();
+        self.call("set_rules", json!({ "layout": layout, "rules": rules }))
+    }
+
+    /// Pushes rules source file having one rule per line.
+    pub fn push_rules_file<P: AsRef<Path>>(
+        &mut self,
+        layout: Option<&str>,
+        path: P,
+    ) -> Result<(), RemoteError> {
+        let text = fs::read_to_string(path)?;
+        let rules =
+            KeyTransformRules::from_str(&text).map_err(|e| RemoteError::Protocol(e.to_string()))?;
+        self.set_rules(layout, &rules)
+    }
+
+    /// Starts receiving key events with [`RemoteController::next_event`].
+    pub fn subscribe(&mut self) -> Result<(), RemoteError> {
+        self.call("subscribe", Value::Null)
+    }
+
+    pub fn unsubscribe(&mut self) -> Result<(), RemoteError> {
+        self.call("unsubscribe", Value::Null)
+    }
+
+    /// Waits for the next key event of the subscription.
+    pub fn next_event(&mut self) -> Result<KeyEventRecord, RemoteError> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(event);
+        }
+        match self.receive()? {
+            Incoming::KeyEvent(event) => Ok(*event),
+            Incoming::Response { id, .. } => {
+                Err(RemoteError::Protocol(format!("Unexpected response: {id}")))
+            }
+        }
+    }
+
+    fn call<T: DeserializeOwned>(&mut self, method: &str, params: Value) -> Result<T, RemoteError> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        writeln!(self.writer, "{request}")?;
+
+        loop {
+            match self.receive()? {
+                Incoming::KeyEvent(event) => self.events.push_back(*event),
+                Incoming::Response {
+                    id: response_id,
+                    result,
+                } if response_id == id => return Ok(serde_json::from_value(result?)?),
+                Incoming::Response { id, .. } => {
+                    return Err(RemoteError::Protocol(format!("Unexpected response: {id}")));
+                }
+            }
+        }
+    }
+
+    fn receive(&mut self) -> Result<Incoming, RemoteError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(RemoteError::Protocol("Connection closed".into()));
+        }
+        Incoming::from_str(&line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{Incoming, RemoteError, RemoteState};
+    use crate::event::{KeyEvent, KeyEventRecord};
+    use crate::key_event;
+    use crate::trigger::KeyTrigger;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_incoming_response() {
+        let state = RemoteState {
+            enabled: true,
+            layout: "default".into(),
+            layouts: vec!["default".into()],
+            ..Default::default()
+        };
+        let line = json!({"jsonrpc": "2.0", "id": 3, "result": state}).to_string();
+
+        assert_eq!(
+            Incoming::Response {
+                id: json!(3),
+                result: Ok(serde_json::to_value(&state).unwrap()),
+            },
+            Incoming::from_str(&line).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_incoming_error() {
+        let line =
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"Profile not found"}}"#;
+
+        let Incoming::Response { result, .. } = Incoming::from_str(line).unwrap() else {
+            panic!("Response expected");
+        };
+        assert_eq!(
+            "Profile not found (-32000)",
+            result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_incoming_key_event() {
+        let record = KeyEventRecord::new(&key_event!("[LEFT_SHIFT] A↓"), None);
+        let line = json!({"jsonrpc": "2.0", "method": "key_event", "params": record}).to_string();
+
+        assert_eq!(
+            Incoming::KeyEvent(Box::new(record)),
+            Incoming::from_str(&line).unwrap()
+        );
+        assert!(matches!(
+            Incoming::from_str(r#"{"method": "unknown"}"#),
+            Err(RemoteError::Protocol(_))
+        ));
+    }
+}
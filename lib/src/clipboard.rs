@@ -0,0 +1,190 @@
+use log::{debug, warn};
+use std::thread;
+use std::time::Duration;
+use windows::Win32::Foundation::{GlobalFree, HANDLE, HGLOBAL};
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardData, OpenClipboard,
+    SetClipboardData,
+};
+use windows::Win32::System::Memory::{
+    GMEM_MOVEABLE, GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock,
+};
+
+/* standard formats kept in global memory. GDI handle formats are not copied */
+const CF_TEXT: u32 = 1;
+const CF_OEMTEXT: u32 = 7;
+const CF_DIB: u32 = 8;
+const CF_UNICODETEXT: u32 = 13;
+const CF_HDROP: u32 = 15;
+const CF_LOCALE: u32 = 16;
+const CF_DIBV5: u32 = 17;
+const MEMORY_FORMATS: [u32; 7] = [
+    CF_TEXT,
+    CF_OEMTEXT,
+    CF_DIB,
+    CF_UNICODETEXT,
+    CF_HDROP,
+    CF_LOCALE,
+    CF_DIBV5,
+];
+/* registered formats like "HTML Format" are always kept in global memory */
+const FIRST_REGISTERED_FORMAT: u32 = 0xC000;
+
+/* clipboard may be briefly opened by another application */
+const OPEN_ATTEMPTS: u32 = 5;
+const OPEN_RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Copy of the clipboard contents in text and other common formats.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct ClipboardSnapshot {
+    formats: Vec<(u32, Vec<u8>)>,
+}
+
+impl ClipboardSnapshot {
+    pub(crate) fn take() -> windows::core::Result<Self> {
+        let _clipboard = OpenedClipboard::open()?;
+
+        let mut formats = vec![];
+        let mut format = 0;
+        loop {
+            format = unsafe { EnumClipboardFormats(format) };
+            if format == 0 {
+                break;
+            }
+            if !is_memory_format(format) {
+                continue;
+            }
+            match unsafe { GetClipboardData(format) }.map(|handle| read_global(HGLOBAL(handle.0))) {
+                Ok(Some(data)) => formats.push((format, data)),
+                Ok(None) => debug!("Failed to read clipboard format {}", format),
+                Err(e) => debug!("Failed to get clipboard format {}: {}", format, e),
+            }
+        }
+
+        debug!("Clipboard saved: {} formats", formats.len());
+        Ok(Self { formats })
+    }
+
+    /// Replaces clipboard contents with the saved ones.
+    /// Formats failed to restore are skipped.
+    pub(crate) fn restore(&self) -> windows::core::Result<()> {
+        let _clipboard = OpenedClipboard::open()?;
+        unsafe { EmptyClipboard()? };
+
+        for (format, data) in &self.formats {
+            let result = write_global(data).and_then(|memory| unsafe {
+                SetClipboardData(*format, Some(HANDLE(memory.0))).inspect_err(|_| {
+                    /* the system takes ownership of the memory only on success */
+                    let _ = GlobalFree(Some(memory));
+                })
+            });
+            if let Err(e) = result {
+                warn!("Failed to restore clipboard format {}: {}", format, e);
+            }
+        }
+
+        debug!("Clipboard restored: {} formats", self.formats.len());
+        Ok(())
+    }
+}
+
+/// Returns the text of the clipboard. Empty when the clipboard has no text.
+pub(crate) fn read_clipboard_text() -> windows::core::Result<String> {
+    let _clipboard = OpenedClipboard::open()?;
+    let Ok(handle) = (unsafe { GetClipboardData(CF_UNICODETEXT) }) else {
+        return Ok(String::new());
+    };
+    let data = read_global(HGLOBAL(handle.0)).unwrap_or_default();
+    Ok(decode_text(&data))
+}
+
+/// Decodes null-terminated UTF-16 text.
+fn decode_text(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Clipboard opened by the current thread. It is closed when dropped.
+struct OpenedClipboard;
+
+impl OpenedClipboard {
+    fn open() -> windows::core::Result<Self> {
+        let mut attempt = 1;
+        loop {
+            match unsafe { OpenClipboard(None) } {
+                Ok(_) => return Ok(Self),
+                Err(e) if attempt >= OPEN_ATTEMPTS => return Err(e),
+                Err(_) => {
+                    attempt += 1;
+                    thread::sleep(OPEN_RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for OpenedClipboard {
+    fn drop(&mut self) {
+        if let Err(e) = unsafe { CloseClipboard() } {
+            warn!("Failed to close clipboard: {}", e);
+        }
+    }
+}
+
+fn is_memory_format(format: u32) -> bool {
+    format >= FIRST_REGISTERED_FORMAT || MEMORY_FORMATS.contains(&format)
+}
+
+fn read_global(memory: HGLOBAL) -> Option<Vec<u8>> {
+    unsafe {
+        let size = GlobalSize(memory);
+        let ptr = GlobalLock(memory) as *const u8;
+        if ptr.is_null() {
+            return None;
+        }
+        let data = std::slice::from_raw_parts(ptr, size).to_vec();
+        let _ = GlobalUnlock(memory); /* fails when lock count drops to zero */
+        Some(data)
+    }
+}
+
+fn write_global(data: &[u8]) -> windows::core::Result<HGLOBAL> {
+    unsafe {
+        let memory = GlobalAlloc(GMEM_MOVEABLE, data.len())?;
+        let ptr = GlobalLock(memory) as *mut u8;
+        if ptr.is_null() {
+            let _ = GlobalFree(Some(memory));
+            return Err(windows::core::Error::from_thread());
+        }
+        std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+        let _ = GlobalUnlock(memory);
+        Ok(memory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clipboard::{decode_text, is_memory_format};
+
+    #[test]
+    fn test_is_memory_format() {
+        assert!(is_memory_format(13)); /* CF_UNICODETEXT */
+        assert!(is_memory_format(0xC0F1)); /* registered format */
+        assert!(!is_memory_format(2)); /* CF_BITMAP */
+        assert!(!is_memory_format(14)); /* CF_ENHMETAFILE */
+    }
+
+    #[test]
+    fn test_decode_text() {
+        let data: Vec<u8> = "Hi\r\nЯ\0junk"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!("Hi\r\nЯ", decode_text(&data));
+        assert_eq!("", decode_text(&[]));
+    }
+}
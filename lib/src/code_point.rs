@@ -0,0 +1,210 @@
+use crate::action::KeyAction;
+use crate::key::Key;
+use crate::pending::PendingReleases;
+use crate::transition::KeyTransition::Up;
+use log::warn;
+use CodePointInput::{Commit, Consumed, Ignored};
+
+const MAX_DIGITS: usize = 6;
+
+/// Result of feeding a key action to the code point entry.
+#[derive(Debug, PartialEq)]
+pub(crate) enum CodePointInput {
+    /// Action is not related to the entry and must be processed as usual.
+    Ignored,
+    /// Action is swallowed by the entry.
+    Consumed,
+    /// Entry completed. The character must be injected.
+    Commit(char),
+}
+
+/// Modal state of hexadecimal code point entry. Started by a trigger, then buffers
+/// typed hex digits until ENTER or SPACE commits them or ESC cancels.
+#[derive(Debug, Default)]
+pub(crate) struct CodePointEntry {
+    digits: Option<String>,
+    pending_ups: PendingReleases,
+}
+
+impl CodePointEntry {
+    pub(crate) fn start(&mut self, trigger_key: Key) {
+        self.digits = Some(String::with_capacity(MAX_DIGITS));
+        self.pending_ups.push(trigger_key);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_active(&self) -> bool {
+        self.digits.is_some()
+    }
+
+    pub(crate) fn handle(&mut self, action: &KeyAction) -> CodePointInput {
+        let key = action.key;
+
+        if action.transition == Up {
+            /* swallow releases of the keys whose presses were swallowed */
+            return if self.pending_ups.take(key) {
+                Consumed
+            } else {
+                Ignored
+            };
+        }
+
+        let Some(digits) = self.digits.as_mut() else {
+            return Ignored;
+        };
+
+        match key {
+            Key::Enter | Key::NumEnter | Key::Space => {
+                self.pending_ups.push(key);
+                let digits = self.digits.take().unwrap_or_default();
+                match u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32) {
+                    Some(ch) => Commit(ch),
+                    None => {
+                        warn!("Invalid code point: `{digits}`");
+                        Consumed
+                    }
+                }
+            }
+            Key::Esc => {
+                self.pending_ups.push(key);
+                self.digits = None;
+                Consumed
+            }
+            Key::Backspace => {
+                self.pending_ups.push(key);
+                digits.pop();
+                Consumed
+            }
+            _ => match hex_digit(key) {
+                Some(digit) => {
+                    self.pending_ups.push(key);
+                    if digits.len() < MAX_DIGITS {
+                        digits.push(digit);
+                    }
+                    Consumed
+                }
+                None => {
+                    if !key.is_modifier() {
+                        /* any other key cancels the entry */
+                        self.digits = None;
+                    }
+                    Ignored
+                }
+            },
+        }
+    }
+}
+
+fn hex_digit(key: Key) -> Option<char> {
+    let digit = match key {
+        Key::Digit0 | Key::Num0 => '0',
+        Key::Digit1 | Key::Num1 => '1',
+        Key::Digit2 | Key::Num2 => '2',
+        Key::Digit3 | Key::Num3 => '3',
+        Key::Digit4 | Key::Num4 => '4',
+        Key::Digit5 | Key::Num5 => '5',
+        Key::Digit6 | Key::Num6 => '6',
+        Key::Digit7 | Key::Num7 => '7',
+        Key::Digit8 | Key::Num8 => '8',
+        Key::Digit9 | Key::Num9 => '9',
+        Key::A => 'A',
+        Key::B => 'B',
+        Key::C => 'C',
+        Key::D => 'D',
+        Key::E => 'E',
+        Key::F => 'F',
+        _ => return None,
+    };
+    Some(digit)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::KeyAction;
+    use crate::code_point::CodePointEntry;
+    use crate::code_point::CodePointInput::{Commit, Consumed, Ignored};
+    use crate::key::Key;
+    use crate::key_action;
+    use std::str::FromStr;
+
+    fn type_keys(entry: &mut CodePointEntry, keys: &str) -> Vec<super::CodePointInput> {
+        keys.split_whitespace()
+            .flat_map(|k| KeyAction::from_str_expand(k).unwrap())
+            .map(|a| entry.handle(&a))
+            .collect()
+    }
+
+    #[test]
+    fn test_code_point_entry_commit() {
+        let mut entry = CodePointEntry::default();
+        entry.start(Key::U);
+
+        type_keys(&mut entry, "0 0 E 9");
+        assert!(entry.is_active());
+        assert_eq!(Commit('é'), entry.handle(&key_action!("ENTER↓")));
+        assert!(!entry.is_active());
+        assert_eq!(Consumed, entry.handle(&key_action!("ENTER↑")));
+        assert_eq!(Consumed, entry.handle(&key_action!("U↑")));
+    }
+
+    #[test]
+    fn test_code_point_entry_backspace() {
+        let mut entry = CodePointEntry::default();
+        entry.start(Key::U);
+
+        type_keys(&mut entry, "1 F 6 0 0 BACKSPACE 2");
+        assert_eq!(Commit('😂'), entry.handle(&key_action!("SPACE↓")));
+    }
+
+    #[test]
+    fn test_code_point_entry_cancel() {
+        let mut entry = CodePointEntry::default();
+        entry.start(Key::U);
+
+        type_keys(&mut entry, "4 1");
+        assert_eq!(Ignored, entry.handle(&key_action!("LEFT_SHIFT↓")));
+        assert!(entry.is_active());
+        assert_eq!(Ignored, entry.handle(&key_action!("G↓")));
+        assert!(!entry.is_active());
+        assert_eq!(Ignored, entry.handle(&key_action!("ENTER↓")));
+
+        entry.start(Key::U);
+        assert_eq!(Consumed, entry.handle(&key_action!("ESC↓")));
+        assert!(!entry.is_active());
+    }
+
+    #[test]
+    fn test_code_point_entry_invalid() {
+        let mut entry = CodePointEntry::default();
+        entry.start(Key::U);
+
+        type_keys(&mut entry, "D 8 0 0");
+        assert_eq!(Consumed, entry.handle(&key_action!("ENTER↓")));
+        assert!(!entry.is_active());
+    }
+
+    #[test]
+    fn test_code_point_entry_repeat() {
+        let mut entry = CodePointEntry::default();
+        entry.start(Key::U);
+
+        assert_eq!(Consumed, entry.handle(&key_action!("A↓")));
+        assert_eq!(Consumed, entry.handle(&key_action!("A↓")));
+        assert_eq!(Consumed, entry.handle(&key_action!("A↑")));
+        assert_eq!(Consumed, entry.handle(&key_action!("ESC↓")));
+        assert_eq!(Consumed, entry.handle(&key_action!("ESC↑")));
+        assert_eq!(Consumed, entry.handle(&key_action!("U↑")));
+
+        /* the held key is released once */
+        assert_eq!(Ignored, entry.handle(&key_action!("A↓")));
+        assert_eq!(Ignored, entry.handle(&key_action!("A↑")));
+    }
+
+    #[test]
+    fn test_code_point_entry_inactive() {
+        let mut entry = CodePointEntry::default();
+
+        assert_eq!(Ignored, entry.handle(&key_action!("A↓")));
+        assert_eq!(Ignored, entry.handle(&key_action!("A↑")));
+    }
+}
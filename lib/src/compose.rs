@@ -0,0 +1,370 @@
+#[cfg(any(test, feature = "win32"))]
+use crate::action::KeyAction;
+use crate::error::KeyError;
+use crate::key::Key;
+use crate::key_error;
+#[cfg(any(test, feature = "win32"))]
+use crate::modifiers::KeyModifiers;
+#[cfg(any(test, feature = "win32"))]
+use crate::modifiers::KeyModifiers::{All, Any};
+#[cfg(any(test, feature = "win32"))]
+use crate::pending::PendingReleases;
+#[cfg(any(test, feature = "win32"))]
+use crate::transition::KeyTransition::Up;
+#[cfg(any(test, feature = "win32"))]
+use ComposeInput::{Commit, Consumed, Ignored};
+#[cfg(any(test, feature = "win32"))]
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(any(test, feature = "win32"))]
+use std::ops::Bound::{Excluded, Unbounded};
+use std::str::FromStr;
+
+/// Mnemonics are never longer than this.
+const MAX_MNEMONIC_LEN: usize = 8;
+
+/// Characters composed from the mnemonics typed after the compose key, e.g. `a'` → `á`.
+/// Mnemonics are the characters the keys produce in the US QWERTY layout regardless of
+/// the active one. Loaded from TOML table like `"a'" = "á"`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ComposeTable {
+    entries: BTreeMap<String, char>,
+}
+
+impl ComposeTable {
+    pub fn get(&self, mnemonic: &str) -> Option<char> {
+        self.entries.get(mnemonic).copied()
+    }
+
+    pub fn insert(&mut self, mnemonic: &str, ch: char) -> Result<(), KeyError> {
+        Self::validate(mnemonic)?;
+        self.entries.insert(mnemonic.to_string(), ch);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Checks whether any longer mnemonic starts with this one.
+    #[cfg(any(test, feature = "win32"))]
+    fn has_continuation(&self, mnemonic: &str) -> bool {
+        self.entries
+            .range::<str, _>((Excluded(mnemonic), Unbounded))
+            .next()
+            .is_some_and(|(m, _)| m.starts_with(mnemonic))
+    }
+
+    fn validate(mnemonic: &str) -> Result<(), KeyError> {
+        if mnemonic.is_empty() || mnemonic.chars().count() > MAX_MNEMONIC_LEN {
+            return Err(key_error!("Invalid compose mnemonic length: `{mnemonic}`"));
+        }
+        match mnemonic.chars().find(|ch| qwerty_key(*ch).is_none()) {
+            Some(ch) => Err(key_error!(
+                "Compose mnemonic `{mnemonic}` has character `{ch}` that cannot be typed"
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for ComposeTable {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let table: Self =
+            toml::from_str(s).map_err(|e| key_error!("Invalid compose table: {e}"))?;
+        for mnemonic in table.entries.keys() {
+            Self::validate(mnemonic)?;
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(any(test, feature = "win32"))]
+/// Result of feeding a key action to the composition.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ComposeInput {
+    /// Action is not related to the composition and must be processed as usual.
+    Ignored,
+    /// Action is swallowed by the composition.
+    Consumed,
+    /// Composition completed. The character must be injected.
+    Commit(char),
+}
+
+#[cfg(any(test, feature = "win32"))]
+/// Modal state of the composition. Started by the compose trigger, then buffers the typed
+/// mnemonic until it matches the table entry. Mnemonic matching no entry or ESC cancels it.
+#[derive(Debug, Default)]
+pub(crate) struct ComposeEntry {
+    mnemonic: Option<String>,
+    pending_ups: PendingReleases,
+}
+
+#[cfg(any(test, feature = "win32"))]
+impl ComposeEntry {
+    pub(crate) fn start(&mut self, trigger_key: Key) {
+        self.mnemonic = Some(String::with_capacity(MAX_MNEMONIC_LEN));
+        self.pending_ups.push(trigger_key);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_active(&self) -> bool {
+        self.mnemonic.is_some()
+    }
+
+    pub(crate) fn handle(
+        &mut self,
+        action: &KeyAction,
+        modifiers: &KeyModifiers,
+        table: &ComposeTable,
+    ) -> ComposeInput {
+        let key = action.key;
+
+        if action.transition == Up {
+            /* swallow releases of the keys whose presses were swallowed */
+            return if self.pending_ups.take(key) {
+                Consumed
+            } else {
+                Ignored
+            };
+        }
+
+        let Some(mnemonic) = self.mnemonic.as_mut() else {
+            return Ignored;
+        };
+
+        match key {
+            Key::Esc => {
+                self.pending_ups.push(key);
+                self.mnemonic = None;
+                Consumed
+            }
+            Key::Backspace => {
+                self.pending_ups.push(key);
+                mnemonic.pop();
+                Consumed
+            }
+            _ => match qwerty_char(key, is_shifted(modifiers)) {
+                Some(ch) => {
+                    self.pending_ups.push(key);
+                    mnemonic.push(ch);
+                    if let Some(composed) = table.get(mnemonic) {
+                        self.mnemonic = None;
+                        Commit(composed)
+                    } else if table.has_continuation(mnemonic) {
+                        Consumed
+                    } else {
+                        warn!("Unknown compose sequence: `{mnemonic}`");
+                        self.mnemonic = None;
+                        Consumed
+                    }
+                }
+                None => {
+                    if !key.is_modifier() {
+                        /* any other key cancels the composition */
+                        self.mnemonic = None;
+                    }
+                    Ignored
+                }
+            },
+        }
+    }
+}
+
+#[cfg(any(test, feature = "win32"))]
+fn is_shifted(modifiers: &KeyModifiers) -> bool {
+    match modifiers {
+        All(state) => state.contains(Key::LeftShift) || state.contains(Key::RightShift),
+        Any => false,
+    }
+}
+
+/// Keys of the US QWERTY layout with the characters they produce without and with SHIFT.
+#[rustfmt::skip]
+const QWERTY_CHARS: [(Key, char, char); 47] = [
+    (Key::Backtick, '`', '~'),
+    (Key::Digit1, '1', '!'), (Key::Digit2, '2', '@'), (Key::Digit3, '3', '#'),
+    (Key::Digit4, '4', '$'), (Key::Digit5, '5', '%'), (Key::Digit6, '6', '^'),
+    (Key::Digit7, '7', '&'), (Key::Digit8, '8', '*'), (Key::Digit9, '9', '('),
+    (Key::Digit0, '0', ')'), (Key::Minus, '-', '_'), (Key::Eq, '=', '+'),
+    (Key::Q, 'q', 'Q'), (Key::W, 'w', 'W'), (Key::E, 'e', 'E'), (Key::R, 'r', 'R'),
+    (Key::T, 't', 'T'), (Key::Y, 'y', 'Y'), (Key::U, 'u', 'U'), (Key::I, 'i', 'I'),
+    (Key::O, 'o', 'O'), (Key::P, 'p', 'P'), (Key::LeftBracket, '[', '{'),
+    (Key::RightBracket, ']', '}'), (Key::Backslash, '\\', '|'),
+    (Key::A, 'a', 'A'), (Key::S, 's', 'S'), (Key::D, 'd', 'D'), (Key::F, 'f', 'F'),
+    (Key::G, 'g', 'G'), (Key::H, 'h', 'H'), (Key::J, 'j', 'J'), (Key::K, 'k', 'K'),
+    (Key::L, 'l', 'L'), (Key::Semicolon, ';', ':'), (Key::Apostrophe, '\'', '"'),
+    (Key::Z, 'z', 'Z'), (Key::X, 'x', 'X'), (Key::C, 'c', 'C'), (Key::V, 'v', 'V'),
+    (Key::B, 'b', 'B'), (Key::N, 'n', 'N'), (Key::M, 'm', 'M'), (Key::Comma, ',', '<'),
+    (Key::Dot, '.', '>'), (Key::Slash, '/', '?'),
+];
+
+#[cfg(any(test, feature = "win32"))]
+fn qwerty_char(key: Key, is_shifted: bool) -> Option<char> {
+    if key == Key::Space {
+        return Some(' ');
+    }
+    QWERTY_CHARS
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, ch, shifted)| if is_shifted { *shifted } else { *ch })
+}
+
+fn qwerty_key(ch: char) -> Option<Key> {
+    if ch == ' ' {
+        return Some(Key::Space);
+    }
+    QWERTY_CHARS
+        .iter()
+        .find(|(_, c, shifted)| *c == ch || *shifted == ch)
+        .map(|(key, _, _)| *key)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::KeyAction;
+    use crate::compose::ComposeInput::{Commit, Consumed, Ignored};
+    use crate::compose::{ComposeEntry, ComposeInput, ComposeTable, qwerty_char, qwerty_key};
+    use crate::key::Key;
+    use crate::modifiers::KeyModifiers;
+    use std::str::FromStr;
+
+    fn create_table() -> ComposeTable {
+        ComposeTable::from_str(
+            r#"
+            "a'" = "á"
+            "o\"" = "ö"
+            "oe" = "œ"
+            "--." = "–"
+            "---" = "—"
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn press(entry: &mut ComposeEntry, table: &ComposeTable, trigger: &str) -> ComposeInput {
+        let (modifiers, action) = match trigger.split_once("] ") {
+            Some((modifiers, action)) => (
+                KeyModifiers::from_str(&format!("{modifiers}]")).unwrap(),
+                action,
+            ),
+            None => (KeyModifiers::from_str("[]").unwrap(), trigger),
+        };
+        entry.handle(&KeyAction::from_str(action).unwrap(), &modifiers, table)
+    }
+
+    #[test]
+    fn test_compose_table_parse() {
+        let table = create_table();
+
+        assert_eq!(5, table.len());
+        assert_eq!(Some('á'), table.get("a'"));
+        assert_eq!(Some('ö'), table.get("o\""));
+        assert_eq!(None, table.get("a"));
+        assert!(table.has_continuation("--"));
+        assert!(!table.has_continuation("---"));
+
+        assert!(ComposeTable::from_str(r#""aé" = "x""#).is_err());
+        assert!(ComposeTable::from_str(r#""a'" = "ab""#).is_err());
+        assert!(ComposeTable::from_str(r#""" = "x""#).is_err());
+    }
+
+    #[test]
+    fn test_compose_commit() {
+        let table = create_table();
+        let mut entry = ComposeEntry::default();
+        entry.start(Key::RightAlt);
+
+        assert_eq!(Consumed, press(&mut entry, &table, "A↓"));
+        assert_eq!(Consumed, press(&mut entry, &table, "A↑"));
+        assert!(entry.is_active());
+        assert_eq!(Commit('á'), press(&mut entry, &table, "APOSTROPHE↓"));
+        assert!(!entry.is_active());
+        assert_eq!(Consumed, press(&mut entry, &table, "APOSTROPHE↑"));
+        assert_eq!(Consumed, press(&mut entry, &table, "RIGHT_ALT↑"));
+        assert_eq!(Ignored, press(&mut entry, &table, "A↓"));
+    }
+
+    #[test]
+    fn test_compose_shifted() {
+        let table = create_table();
+        let mut entry = ComposeEntry::default();
+        entry.start(Key::RightAlt);
+
+        assert_eq!(Consumed, press(&mut entry, &table, "O↓"));
+        assert_eq!(
+            Ignored,
+            press(&mut entry, &table, "[LEFT_SHIFT] LEFT_SHIFT↓")
+        );
+        assert_eq!(
+            Commit('ö'),
+            press(&mut entry, &table, "[LEFT_SHIFT] APOSTROPHE↓")
+        );
+    }
+
+    #[test]
+    fn test_compose_longer_mnemonic() {
+        let table = create_table();
+        let mut entry = ComposeEntry::default();
+        entry.start(Key::RightAlt);
+
+        assert_eq!(Consumed, press(&mut entry, &table, "MINUS↓"));
+        assert_eq!(Consumed, press(&mut entry, &table, "MINUS↓"));
+        assert_eq!(Consumed, press(&mut entry, &table, "BACKSPACE↓"));
+        assert_eq!(Consumed, press(&mut entry, &table, "MINUS↓"));
+        assert_eq!(Commit('—'), press(&mut entry, &table, "MINUS↓"));
+    }
+
+    #[test]
+    fn test_compose_repeat() {
+        let table = create_table();
+        let mut entry = ComposeEntry::default();
+        entry.start(Key::RightAlt);
+
+        assert_eq!(Consumed, press(&mut entry, &table, "MINUS↓"));
+        assert_eq!(Consumed, press(&mut entry, &table, "MINUS↓"));
+        assert_eq!(Consumed, press(&mut entry, &table, "MINUS↑"));
+        assert_eq!(Commit('–'), press(&mut entry, &table, "DOT↓"));
+        assert_eq!(Consumed, press(&mut entry, &table, "DOT↑"));
+        assert_eq!(Consumed, press(&mut entry, &table, "RIGHT_ALT↑"));
+
+        /* the held key is released once */
+        assert_eq!(Ignored, press(&mut entry, &table, "MINUS↓"));
+        assert_eq!(Ignored, press(&mut entry, &table, "MINUS↑"));
+    }
+
+    #[test]
+    fn test_compose_cancel() {
+        let table = create_table();
+        let mut entry = ComposeEntry::default();
+
+        entry.start(Key::RightAlt);
+        assert_eq!(Consumed, press(&mut entry, &table, "ESC↓"));
+        assert!(!entry.is_active());
+
+        entry.start(Key::RightAlt);
+        assert_eq!(Consumed, press(&mut entry, &table, "Z↓"));
+        assert!(!entry.is_active());
+
+        entry.start(Key::RightAlt);
+        assert_eq!(Ignored, press(&mut entry, &table, "F1↓"));
+        assert!(!entry.is_active());
+    }
+
+    #[test]
+    fn test_qwerty_chars() {
+        assert_eq!(Some('a'), qwerty_char(Key::A, false));
+        assert_eq!(Some('"'), qwerty_char(Key::Apostrophe, true));
+        assert_eq!(None, qwerty_char(Key::F1, false));
+        assert_eq!(Some(Key::Digit1), qwerty_key('!'));
+        assert_eq!(None, qwerty_key('é'));
+    }
+}
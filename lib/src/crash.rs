@@ -0,0 +1,167 @@
+use crate::hook;
+use crate::input::PRIVATE_EVENT_MARKER;
+use log::error;
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, Once, OnceLock, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use windows::Win32::System::Diagnostics::Debug::{EXCEPTION_POINTERS, SetUnhandledExceptionFilter};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP,
+    KEYEVENTF_UNICODE, SendInput,
+};
+
+/// Tells the system to proceed with the default handling, i.e. terminate the process.
+const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+/// Keys sent down by the hook and not released yet.
+static PRESSED_INPUT: Mutex<Vec<KEYBDINPUT>> = Mutex::new(Vec::new());
+static CRASH_LOG: OnceLock<PathBuf> = OnceLock::new();
+static HANDLER: Once = Once::new();
+
+/// Installs the panic hook and the unhandled exception filter. On a crash they release the
+/// keys the hook left pressed so that no modifier stays stuck system-wide, remove the hooks
+/// of the crashing thread and write the crash log.
+pub fn install_crash_handler(crash_log: impl Into<PathBuf>) {
+    CRASH_LOG.get_or_init(|| crash_log.into());
+    HANDLER.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            previous(info);
+            on_crash(&format!("{info}\n{}", Backtrace::force_capture()));
+        }));
+
+        unsafe { SetUnhandledExceptionFilter(Some(exception_filter)) };
+    });
+}
+
+/// Remembers the keys the input presses and forgets the ones it releases.
+pub(crate) fn track_input(input: &[INPUT]) {
+    update_pressed(&mut lock(), input);
+}
+
+fn lock() -> MutexGuard<'static, Vec<KEYBDINPUT>> {
+    PRESSED_INPUT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn update_pressed(pressed: &mut Vec<KEYBDINPUT>, input: &[INPUT]) {
+    for item in input {
+        if item.r#type != INPUT_KEYBOARD {
+            continue;
+        }
+
+        let ki = unsafe { item.Anonymous.ki };
+        /* unicode characters are always sent with both the press and the release */
+        if ki.dwFlags.contains(KEYEVENTF_UNICODE) {
+            continue;
+        }
+
+        pressed.retain(|p| !is_same_key(p, &ki));
+        if !ki.dwFlags.contains(KEYEVENTF_KEYUP) {
+            pressed.push(ki);
+        }
+    }
+}
+
+fn is_same_key(a: &KEYBDINPUT, b: &KEYBDINPUT) -> bool {
+    a.wVk == b.wVk
+        && a.wScan == b.wScan
+        && a.dwFlags.contains(KEYEVENTF_EXTENDEDKEY) == b.dwFlags.contains(KEYEVENTF_EXTENDEDKEY)
+}
+
+fn build_release_input(pressed: &[KEYBDINPUT]) -> Vec<INPUT> {
+    pressed
+        .iter()
+        .rev()
+        .map(|ki| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    dwFlags: ki.dwFlags | KEYEVENTF_KEYUP,
+                    time: 0,
+                    dwExtraInfo: PRIVATE_EVENT_MARKER,
+                    ..*ki
+                },
+            },
+        })
+        .collect()
+}
+
+fn on_crash(reason: &str) {
+    release_pressed_keys();
+    hook::remove_hooks();
+    write_crash_log(reason);
+}
+
+fn release_pressed_keys() {
+    /* the lock may be held by the crashed code of this very thread */
+    let pressed = match PRESSED_INPUT.try_lock() {
+        Ok(mut pressed) => std::mem::take(&mut *pressed),
+        Err(TryLockError::Poisoned(e)) => std::mem::take(&mut *e.into_inner()),
+        Err(TryLockError::WouldBlock) => return,
+    };
+
+    let input = build_release_input(&pressed);
+    if !input.is_empty() {
+        unsafe { SendInput(&input, size_of::<INPUT>() as i32) };
+    }
+}
+
+fn write_crash_log(reason: &str) {
+    let Some(path) = CRASH_LOG.get() else {
+        return;
+    };
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let text = format!("Crash at {time} (seconds since Unix epoch)\n{reason}\n");
+    if let Err(e) = fs::write(path, text) {
+        error!("Failed to write crash log `{}`: {}", path.display(), e);
+    }
+}
+
+unsafe extern "system" fn exception_filter(info: *const EXCEPTION_POINTERS) -> i32 {
+    let code = unsafe { info.as_ref() }
+        .and_then(|info| unsafe { info.ExceptionRecord.as_ref() })
+        .map_or(0, |record| record.ExceptionCode.0);
+    on_crash(&format!("Unhandled exception: {code:#010X}"));
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::KeyActionSequence;
+    use crate::crash::{build_release_input, update_pressed};
+    use crate::input::{PRIVATE_EVENT_MARKER, build_input, build_unicode_input};
+    use crate::key_action_seq;
+    use std::str::FromStr;
+    use windows::Win32::UI::Input::KeyboardAndMouse::KEYEVENTF_KEYUP;
+
+    #[test]
+    fn test_update_pressed() {
+        let mut pressed = vec![];
+
+        update_pressed(
+            &mut pressed,
+            &build_input(&key_action_seq!("LEFT_CTRL↓ → A↓ → A↑ → RIGHT_ALT↓")),
+        );
+        update_pressed(&mut pressed, &build_unicode_input('ё'));
+
+        assert_eq!(2, pressed.len());
+
+        update_pressed(&mut pressed, &build_input(&key_action_seq!("LEFT_CTRL↑")));
+        assert_eq!(1, pressed.len());
+
+        let release = build_release_input(&pressed);
+        let ki = unsafe { release[0].Anonymous.ki };
+        assert_eq!(pressed[0].wScan, ki.wScan);
+        assert!(ki.dwFlags.contains(KEYEVENTF_KEYUP));
+        assert_eq!(PRIVATE_EVENT_MARKER, ki.dwExtraInfo);
+
+        update_pressed(&mut pressed, &build_input(&key_action_seq!("RIGHT_ALT↑")));
+        assert!(pressed.is_empty());
+    }
+}
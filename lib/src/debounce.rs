@@ -0,0 +1,123 @@
+#[cfg(any(test, feature = "win32"))]
+use crate::action::KeyAction;
+use crate::key::Key;
+#[cfg(any(test, feature = "win32"))]
+use crate::transition::KeyTransition::{Down, Up};
+use fxhash::FxHashMap;
+#[cfg(any(test, feature = "win32"))]
+use fxhash::FxHashSet;
+
+/// Dead zone for the chattering key switches. Press of a key arriving within the interval since
+/// the release of the same key is ignored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Debounce {
+    /// Dead zone of all keys in milliseconds. Zero disables the filter.
+    pub interval: u32,
+    /// Dead zones of the particular keys replacing the common one.
+    pub keys: FxHashMap<Key, u32>,
+}
+
+impl Debounce {
+    pub fn interval(&self, key: Key) -> u32 {
+        self.keys.get(&key).copied().unwrap_or(self.interval)
+    }
+}
+
+/// Drops the bounces of the key switches. Release of the ignored press and its auto-repeat are
+/// ignored as well so that the key is not released twice.
+#[cfg(any(test, feature = "win32"))]
+#[derive(Debug, Default)]
+pub(crate) struct DebounceFilter {
+    debounce: Debounce,
+    released: FxHashMap<Key, u32>,
+    bouncing: FxHashSet<Key>,
+}
+
+#[cfg(any(test, feature = "win32"))]
+impl DebounceFilter {
+    pub(crate) fn set_debounce(&mut self, debounce: Debounce) {
+        self.debounce = debounce;
+        self.released.clear();
+        self.bouncing.clear();
+    }
+
+    /// Returns `true` if the action is a bounce to be ignored.
+    pub(crate) fn is_bounce(&mut self, action: &KeyAction, time: u32) -> bool {
+        let key = action.key;
+        match action.transition {
+            Down => {
+                if self.bouncing.contains(&key) {
+                    return true;
+                }
+                let interval = self.debounce.interval(key);
+                let is_bounce = interval > 0
+                    && self
+                        .released
+                        .get(&key)
+                        .is_some_and(|&released| time.wrapping_sub(released) < interval);
+                if is_bounce {
+                    self.bouncing.insert(key);
+                }
+                is_bounce
+            }
+            Up => {
+                self.released.insert(key, time);
+                self.bouncing.remove(&key)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::KeyAction;
+    use crate::debounce::{Debounce, DebounceFilter};
+    use crate::key::Key;
+    use crate::key_action;
+    use fxhash::FxHashMap;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_debounce_interval() {
+        let debounce = Debounce {
+            interval: 30,
+            keys: FxHashMap::from_iter([(Key::Space, 50)]),
+        };
+
+        assert_eq!(30, debounce.interval(Key::A));
+        assert_eq!(50, debounce.interval(Key::Space));
+        assert_eq!(0, Debounce::default().interval(Key::A));
+    }
+
+    #[test]
+    fn test_debounce_filter() {
+        let mut filter = DebounceFilter::default();
+        filter.set_debounce(Debounce {
+            interval: 30,
+            ..Default::default()
+        });
+
+        assert!(!filter.is_bounce(&key_action!("A↓"), 1000));
+        assert!(!filter.is_bounce(&key_action!("A↑"), 1080));
+        assert!(filter.is_bounce(&key_action!("A↓"), 1090));
+        assert!(filter.is_bounce(&key_action!("A↓"), 1120));
+        assert!(filter.is_bounce(&key_action!("A↑"), 1150));
+        assert!(!filter.is_bounce(&key_action!("B↓"), 1160));
+        assert!(!filter.is_bounce(&key_action!("A↓"), 1200));
+        assert!(!filter.is_bounce(&key_action!("A↑"), 1250));
+    }
+
+    #[test]
+    fn test_debounce_filter_per_key() {
+        let mut filter = DebounceFilter::default();
+        filter.set_debounce(Debounce {
+            interval: 0,
+            keys: FxHashMap::from_iter([(Key::Space, 50)]),
+        });
+
+        assert!(!filter.is_bounce(&key_action!("A↑"), 1000));
+        assert!(!filter.is_bounce(&key_action!("A↓"), 1010));
+        assert!(!filter.is_bounce(&key_action!("SPACE↑"), 1000));
+        assert!(filter.is_bounce(&key_action!("SPACE↓"), 1040));
+    }
+}
@@ -0,0 +1,115 @@
+use fxhash::FxHashMap;
+use log::{info, warn};
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::sync::Arc;
+use windows::Win32::Foundation::{HANDLE, HWND};
+use windows::Win32::UI::Input::{
+    GetRawInputData, GetRawInputDeviceInfoW, HRAWINPUT, RAWINPUT, RAWINPUTDEVICE, RAWINPUTHEADER,
+    RID_INPUT, RIDEV_INPUTSINK, RIDI_DEVICENAME, RIM_TYPEKEYBOARD, RegisterRawInputDevices,
+};
+use windows::Win32::UI::WindowsAndMessaging::RI_KEY_E0;
+
+const HID_USAGE_PAGE_GENERIC: u16 = 0x01;
+const HID_USAGE_GENERIC_KEYBOARD: u16 = 0x06;
+
+/// Scan code with extended flag identifying physical key in both raw input and hook events.
+type ScanCode = (u8, bool);
+
+thread_local! {
+    static DEVICE_NAMES: RefCell<FxHashMap<isize, Arc<str>>> = RefCell::new(FxHashMap::default());
+    static LAST_DEVICES: RefCell<FxHashMap<ScanCode, Arc<str>>> = RefCell::new(FxHashMap::default());
+}
+
+/// Subscribes the window to raw keyboard input even when it is not in foreground.
+pub(crate) fn register_raw_input(owner: HWND) {
+    let device = RAWINPUTDEVICE {
+        usUsagePage: HID_USAGE_PAGE_GENERIC,
+        usUsage: HID_USAGE_GENERIC_KEYBOARD,
+        dwFlags: RIDEV_INPUTSINK,
+        hwndTarget: owner,
+    };
+
+    unsafe {
+        RegisterRawInputDevices(&[device], size_of::<RAWINPUTDEVICE>() as u32)
+            .unwrap_or_else(|e| warn!("Failed to register raw input: {}", e));
+    }
+}
+
+/// Remembers the device that produced `WM_INPUT` keyboard message.
+pub(crate) fn handle_raw_input(l_param: isize) {
+    let mut input = RAWINPUT::default();
+    let mut size = size_of::<RAWINPUT>() as u32;
+    let read = unsafe {
+        GetRawInputData(
+            HRAWINPUT(l_param as _),
+            RID_INPUT,
+            Some(&mut input as *mut _ as *mut c_void),
+            &mut size,
+            size_of::<RAWINPUTHEADER>() as u32,
+        )
+    };
+    if read == u32::MAX || input.header.dwType != RIM_TYPEKEYBOARD.0 {
+        return;
+    }
+
+    /* injected input has no device */
+    let handle = input.header.hDevice;
+    if handle.is_invalid() {
+        return;
+    }
+
+    let keyboard = unsafe { input.data.keyboard };
+    let scan_code = (
+        keyboard.MakeCode as u8,
+        keyboard.Flags & RI_KEY_E0 as u16 != 0,
+    );
+    let name = device_name(handle);
+    LAST_DEVICES.with_borrow_mut(|devices| devices.insert(scan_code, name));
+}
+
+/// Returns the device that last reported the key.
+///
+/// Raw input arrives after the low-level hook has processed the event, so the first press
+/// of the key on another device is attributed to the previous one.
+pub(crate) fn last_device(scan_code: ScanCode) -> Option<Arc<str>> {
+    LAST_DEVICES.with_borrow(|devices| devices.get(&scan_code).cloned())
+}
+
+fn device_name(handle: HANDLE) -> Arc<str> {
+    DEVICE_NAMES.with_borrow_mut(|names| {
+        names
+            .entry(handle.0 as isize)
+            .or_insert_with(|| {
+                let name = query_device_name(handle);
+                info!("Keyboard device found: `{name}`");
+                name.into()
+            })
+            .clone()
+    })
+}
+
+fn query_device_name(handle: HANDLE) -> String {
+    let mut size = 0;
+    unsafe {
+        GetRawInputDeviceInfoW(Some(handle), RIDI_DEVICENAME, None, &mut size);
+    }
+
+    let mut buffer = vec![0u16; size as usize];
+    let read = unsafe {
+        GetRawInputDeviceInfoW(
+            Some(handle),
+            RIDI_DEVICENAME,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            &mut size,
+        )
+    };
+    if read == u32::MAX {
+        warn!("Failed to get raw input device name");
+        return format!("{:?}", handle.0);
+    }
+
+    String::from_utf16_lossy(&buffer)
+        .trim_end_matches('\0')
+        .to_string()
+}
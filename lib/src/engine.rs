@@ -0,0 +1,499 @@
+//! Keyboard remapping engine to embed into other applications.
+//!
+//! ```no_run
+//! use keympostor::engine::Engine;
+//! use keympostor::rule::KeyTransformRules;
+//! use std::str::FromStr;
+//!
+//! let rules = KeyTransformRules::from_str("CAPS_LOCK↓ : LEFT_CTRL↓\nCAPS_LOCK↑ : LEFT_CTRL↑")?;
+//! let mut engine = Engine::new(rules);
+//! engine.start()?;
+//! for event in engine.events() {
+//!     println!("{event}");
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::audit::AuditEntry;
+use crate::char_resolver::CharResolver;
+use crate::event::KeyEvent;
+use crate::hook::KeyboardHook;
+use crate::notify::{KeyEventNotification, WM_KEY_HOOK_NOTIFY};
+use crate::rule::KeyTransformRules;
+use crate::session::{SessionState, SessionWatcher};
+use crate::subscription::EventFilter;
+use log::{debug, info, warn};
+use std::cell::OnceCell;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering::{AcqRel, Acquire};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+use std::thread::JoinHandle;
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DestroyWindow, DispatchMessageW, GetMessageW, HWND_MESSAGE, MSG, PM_REMOVE,
+    PeekMessageW, PostThreadMessageW, TranslateMessage, WINDOW_EX_STYLE, WINDOW_STYLE, WM_QUIT,
+};
+use windows::core::w;
+
+const WM_ENGINE_COMMAND: u32 = 88477;
+
+#[derive(Debug, PartialEq)]
+pub enum EngineError {
+    AlreadyStarted,
+    /// The hook thread failed to start.
+    Start(String),
+    /// The engine is not running.
+    Stopped,
+    /// The command was not delivered to the hook thread.
+    Post(String),
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AlreadyStarted => write!(f, "Engine is already started"),
+            Self::Start(message) => write!(f, "Failed to start engine: {message}"),
+            Self::Stopped => write!(f, "Engine is not running"),
+            Self::Post(message) => write!(f, "Failed to send engine command: {message}"),
+        }
+    }
+}
+
+impl Error for EngineError {}
+
+/// Called on the hook thread when the session state changes.
+pub type SessionListener = Arc<dyn Fn(SessionState) + Send + Sync>;
+
+/// Applies the rules to the keyboard and mouse input of the whole system.
+///
+/// The hook runs on its own thread with a message loop, so the engine can be used from
+/// any application including the console ones. Stopped when dropped.
+///
+/// The hook is suspended while the session is locked, the secure desktop is shown or
+/// the session is controlled remotely.
+pub struct Engine {
+    rules: KeyTransformRules,
+    /// IDs of the rules temporarily excluded from processing.
+    disabled_rules: BTreeSet<u32>,
+    session_listener: Option<SessionListener>,
+    worker: Option<Worker>,
+}
+
+/// Thread running the hook.
+struct Worker {
+    controller: EngineController,
+    handle: JoinHandle<()>,
+    /// Subscribed on the first request of the events, so they are not queued for nobody.
+    events: OnceCell<Option<Receiver<KeyEvent>>>,
+}
+
+/// Command executed on the hook thread.
+enum Command {
+    SetRules(KeyTransformRules),
+    SetDisabledRules(BTreeSet<u32>),
+    SetEnabled(bool),
+    Subscribe(EventFilter, Sender<KeyEvent>),
+    Audit(Sender<AuditEntry>),
+}
+
+/// Controls the running engine from any thread.
+///
+/// Commands are posted to the hook thread, so Win32 is never touched from the calling one.
+/// Fails with [`EngineError::Stopped`] after the engine stops.
+#[derive(Clone, Debug)]
+pub struct EngineController {
+    /// Hook thread ID or 0 when stopped.
+    thread_id: Arc<AtomicU32>,
+}
+
+impl EngineController {
+    /// Replaces the rules. The disabled rules of the engine stay disabled.
+    pub fn set_rules(&self, rules: KeyTransformRules) -> Result<(), EngineError> {
+        self.post(Command::SetRules(rules))
+    }
+
+    fn set_disabled_rules(&self, rule_ids: BTreeSet<u32>) -> Result<(), EngineError> {
+        self.post(Command::SetDisabledRules(rule_ids))
+    }
+
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), EngineError> {
+        self.post(Command::SetEnabled(enabled))
+    }
+
+    /// Returns a new receiver of the processed events. The receiver is disconnected
+    /// when the engine stops.
+    pub fn subscribe(&self) -> Result<Receiver<KeyEvent>, EngineError> {
+        self.subscribe_filtered(EventFilter::default())
+    }
+
+    /// Returns a new receiver of the processed events matching the filter.
+    pub fn subscribe_filtered(
+        &self,
+        filter: EventFilter,
+    ) -> Result<Receiver<KeyEvent>, EngineError> {
+        let (sender, receiver) = channel();
+        self.post(Command::Subscribe(filter, sender))?;
+        Ok(receiver)
+    }
+
+    /// Switches the engine to the audit mode. Returns a new receiver of the physical events
+    /// with the rules applied to them and the output synthesized by the rules. The entries
+    /// are logged as well while the receiver is connected.
+    pub fn audit(&self) -> Result<Receiver<AuditEntry>, EngineError> {
+        let (sender, receiver) = channel();
+        self.post(Command::Audit(sender))?;
+        Ok(receiver)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread_id.load(Acquire) != 0
+    }
+
+    fn post(&self, command: Command) -> Result<(), EngineError> {
+        let thread_id = self.thread_id.load(Acquire);
+        if thread_id == 0 {
+            return Err(EngineError::Stopped);
+        }
+
+        let raw_ptr = Box::into_raw(Box::new(command)) as isize;
+        unsafe { PostThreadMessageW(thread_id, WM_ENGINE_COMMAND, WPARAM(0), LPARAM(raw_ptr)) }
+            .map_err(|e| {
+                /* not delivered so still owned here */
+                drop(unsafe { Box::from_raw(raw_ptr as *mut Command) });
+                EngineError::Post(e.to_string())
+            })
+    }
+
+    fn stop(&self) -> Result<(), EngineError> {
+        let thread_id = self.thread_id.swap(0, AcqRel);
+        if thread_id == 0 {
+            return Err(EngineError::Stopped);
+        }
+
+        unsafe { PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) }
+            .map_err(|e| EngineError::Post(e.to_string()))
+    }
+}
+
+impl Engine {
+    pub fn new(rules: KeyTransformRules) -> Self {
+        Self {
+            rules,
+            disabled_rules: BTreeSet::new(),
+            session_listener: None,
+            worker: None,
+        }
+    }
+
+    /// Sets the listener of the session state changes. Takes effect on the next start.
+    pub fn set_session_listener(
+        &mut self,
+        listener: impl Fn(SessionState) + Send + Sync + 'static,
+    ) {
+        self.session_listener = Some(Arc::new(listener));
+    }
+
+    pub fn rules(&self) -> &KeyTransformRules {
+        &self.rules
+    }
+
+    /// Returns the rules applied to the input, i.e. all the rules except the disabled ones.
+    pub fn active_rules(&self) -> KeyTransformRules {
+        self.rules.without_disabled(&self.disabled_rules)
+    }
+
+    pub fn is_rule_enabled(&self, rule_id: u32) -> bool {
+        !self.disabled_rules.contains(&rule_id)
+    }
+
+    /// Disables the rules having the ID without removing them, or enables them back. Takes
+    /// effect immediately when the engine is running. Survives the rules update.
+    pub fn set_rule_enabled(&mut self, rule_id: u32, enabled: bool) {
+        let changed = if enabled {
+            self.disabled_rules.remove(&rule_id)
+        } else {
+            self.disabled_rules.insert(rule_id)
+        };
+        if changed
+            && let Some(controller) = self.controller()
+            && let Err(e) = controller.set_disabled_rules(self.disabled_rules.clone())
+        {
+            warn!("Failed to update engine disabled rules: {}", e);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.worker.is_some()
+    }
+
+    /// Installs the hook. Returns when the hook is ready to process the input.
+    pub fn start(&mut self) -> Result<(), EngineError> {
+        if self.worker.is_some() {
+            return Err(EngineError::AlreadyStarted);
+        }
+
+        let (ready_sender, ready) = channel();
+        let rules = self.rules.clone();
+        let disabled_rules = self.disabled_rules.clone();
+        let session_listener = self.session_listener.clone();
+        let handle = thread::Builder::new()
+            .name("keympostor-hook".into())
+            .spawn(move || run_hook(rules, disabled_rules, session_listener, ready_sender))
+            .map_err(|e| EngineError::Start(e.to_string()))?;
+
+        let thread_id = ready
+            .recv()
+            .map_err(|e| EngineError::Start(e.to_string()))?
+            .map_err(EngineError::Start)?;
+
+        self.worker = Some(Worker {
+            controller: EngineController {
+                thread_id: Arc::new(AtomicU32::new(thread_id)),
+            },
+            handle,
+            events: OnceCell::new(),
+        });
+        debug!("Engine started");
+        Ok(())
+    }
+
+    /// Uninstalls the hook and waits for its thread to finish.
+    pub fn stop(&mut self) {
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+
+        if let Err(e) = worker.controller.stop() {
+            warn!("Failed to stop engine: {}", e);
+            return;
+        }
+        if worker.handle.join().is_err() {
+            warn!("Engine thread panicked");
+        }
+        debug!("Engine stopped");
+    }
+
+    /// Replaces the rules. Takes effect immediately when the engine is running.
+    pub fn update_rules(&mut self, rules: KeyTransformRules) {
+        self.rules = rules;
+        if let Some(controller) = self.controller()
+            && let Err(e) = controller.set_rules(self.rules.clone())
+        {
+            warn!("Failed to update engine rules: {}", e);
+        }
+    }
+
+    /// Returns the handle to control the running engine from other threads.
+    pub fn controller(&self) -> Option<EngineController> {
+        self.worker.as_ref().map(|worker| worker.controller.clone())
+    }
+
+    /// Returns the events processed by the running engine. Blocks waiting for the next event
+    /// and ends when the engine stops. Empty when the engine is not running.
+    ///
+    /// The engine starts collecting the events on the first call, the earlier ones are not
+    /// returned.
+    pub fn events(&self) -> impl Iterator<Item = KeyEvent> + '_ {
+        self.worker.iter().flat_map(|worker| {
+            worker
+                .events
+                .get_or_init(|| {
+                    worker
+                        .controller
+                        .subscribe()
+                        .inspect_err(|e| warn!("Failed to subscribe to engine events: {}", e))
+                        .ok()
+                })
+                .iter()
+                .flat_map(Receiver::iter)
+        })
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run_hook(
+    rules: KeyTransformRules,
+    disabled_rules: BTreeSet<u32>,
+    session_listener: Option<SessionListener>,
+    ready: Sender<Result<u32, String>>,
+) {
+    /* message-only window receiving the hook notifications */
+    let hwnd = match unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            w!("STATIC"),
+            w!("keympostor"),
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            Some(HWND_MESSAGE),
+            None,
+            None,
+            None,
+        )
+    } {
+        Ok(hwnd) => hwnd,
+        Err(e) => {
+            let _ = ready.send(Err(e.to_string()));
+            return;
+        }
+    };
+
+    let hook = KeyboardHook::default();
+    hook.setup(hwnd);
+    hook.set_disabled_rules(disabled_rules);
+    hook.set_rules(Some(&rules));
+    if let Err(e) = hook.install() {
+        let _ = ready.send(Err(e.to_string()));
+        return;
+    }
+    let session = SessionWatcher::default();
+    session.setup(hwnd);
+    hook.set_suspended(session.state().is_suspended());
+    let _ = ready.send(Ok(unsafe { GetCurrentThreadId() }));
+
+    let mut subscribers: Vec<(EventFilter, Sender<KeyEvent>)> = Vec::new();
+    let mut char_resolver = CharResolver::default();
+    let mut auditors: Vec<Sender<AuditEntry>> = Vec::new();
+    let mut msg = MSG::default();
+    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.as_bool() {
+        match msg.message {
+            WM_KEY_HOOK_NOTIFY => {
+                let mut notification =
+                    unsafe { Box::from_raw(msg.lParam.0 as *mut KeyEventNotification) };
+                if !subscribers.is_empty() {
+                    char_resolver.resolve_event(&mut notification.event);
+                }
+                /* forget the subscribers that stopped listening */
+                subscribers.retain(|(filter, sender)| {
+                    !filter.matches(&notification.event)
+                        || sender.send(notification.event.clone()).is_ok()
+                });
+                if !auditors.is_empty() && !notification.event.is_private {
+                    let entry = AuditEntry::new(&notification.event, notification.rule.as_deref());
+                    info!("Audit: {entry}");
+                    auditors.retain(|sender| sender.send(entry.clone()).is_ok());
+                }
+            }
+            WM_ENGINE_COMMAND => {
+                let command = unsafe { Box::from_raw(msg.lParam.0 as *mut Command) };
+                match *command {
+                    Command::SetRules(rules) => hook.set_rules(Some(&rules)),
+                    Command::SetDisabledRules(rule_ids) => hook.set_disabled_rules(rule_ids),
+                    Command::SetEnabled(enabled) => hook.set_enabled(enabled),
+                    Command::Subscribe(filter, sender) => subscribers.push((filter, sender)),
+                    Command::Audit(sender) => auditors.push(sender),
+                }
+            }
+            _ => {
+                if let Some(state) = session.handle_message(msg.message, msg.wParam.0) {
+                    hook.set_suspended(state.is_suspended());
+                    if let Some(listener) = &session_listener {
+                        listener(state);
+                    }
+                    continue;
+                }
+                unsafe {
+                    let _ = TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+            }
+        }
+    }
+
+    session.stop();
+    hook.uninstall();
+
+    /* release the commands posted after the stop */
+    while unsafe {
+        PeekMessageW(
+            &mut msg,
+            None,
+            WM_ENGINE_COMMAND,
+            WM_ENGINE_COMMAND,
+            PM_REMOVE,
+        )
+    }
+    .as_bool()
+    {
+        drop(unsafe { Box::from_raw(msg.lParam.0 as *mut Command) });
+    }
+
+    if let Err(e) = unsafe { DestroyWindow(hwnd) } {
+        warn!("Failed to destroy engine window: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{Engine, EngineController, EngineError};
+    use crate::key_rules;
+    use crate::rule::KeyTransformRules;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicU32;
+
+    #[test]
+    fn test_engine_not_started() {
+        let mut engine = Engine::new(key_rules!("A↓ : B↓"));
+        assert!(!engine.is_running());
+        assert_eq!(0, engine.events().count());
+
+        engine.update_rules(key_rules!("A↓ : C↓"));
+        assert_eq!(&key_rules!("A↓ : C↓"), engine.rules());
+
+        engine.stop();
+        assert!(!engine.is_running());
+        assert!(engine.controller().is_none());
+    }
+
+    #[test]
+    fn test_engine_rule_enabled() {
+        let mut engine = Engine::new(key_rules!("#1 A↓ : B↓\n#2 C↓ : D↓\nE↓ : F↓"));
+        assert!(engine.is_rule_enabled(1));
+
+        engine.set_rule_enabled(1, false);
+        assert!(!engine.is_rule_enabled(1));
+        assert_eq!(key_rules!("#2 C↓ : D↓\nE↓ : F↓"), engine.active_rules());
+
+        /* disabled rules stay disabled after the update */
+        engine.update_rules(key_rules!("#1 A↓ : C↓\n#2 C↓ : D↓"));
+        assert_eq!(key_rules!("#2 C↓ : D↓"), engine.active_rules());
+        assert_eq!(2, engine.rules().iter().count());
+
+        engine.set_rule_enabled(1, true);
+        assert_eq!(engine.rules(), &engine.active_rules());
+    }
+
+    #[test]
+    fn test_controller_stopped() {
+        let controller = EngineController {
+            thread_id: Arc::new(AtomicU32::new(0)),
+        };
+        assert!(!controller.is_running());
+        assert_eq!(Err(EngineError::Stopped), controller.set_enabled(false));
+        assert_eq!(
+            Err(EngineError::Stopped),
+            controller.set_rules(key_rules!("A↓ : B↓"))
+        );
+        assert!(controller.subscribe().is_err());
+    }
+
+    #[test]
+    fn test_controller_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<EngineController>();
+    }
+}
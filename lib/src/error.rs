@@ -5,34 +5,252 @@ use std::fmt::{Display, Formatter};
 #[derive(PartialEq)]
 pub struct KeyError {
     pub message: String,
+    /// Source text fragment that failed to parse.
+    pub token: Option<String>,
+    /// 1-based line number in the parsed source.
+    pub line: Option<usize>,
+    /// 1-based column of the token in the line.
+    pub column: Option<usize>,
+    /// Byte offset of the token in the text given to the parser.
+    pub offset: Option<usize>,
+    /// Alternatives accepted in place of the token.
+    pub expected: Vec<String>,
+}
+
+impl KeyError {
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Sets the token sliced from the text given to the parser along with its offset.
+    pub(crate) fn with_token_in(self, source: &str, token: &str) -> Self {
+        self.with_token(token).at(offset_in(source, token))
+    }
+
+    /// Sets byte offset of the token in the text given to the parser.
+    pub fn at(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Moves the offset of the error in the part of the source given to the nested parser to
+    /// the offset in the source.
+    pub(crate) fn within(mut self, source: &str, part: &str) -> Self {
+        self.offset = self.offset.map(|offset| offset + offset_in(source, part));
+        self
+    }
+
+    pub fn with_expected(mut self, expected: &[&str]) -> Self {
+        self.expected = expected.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Sets position of the error in the source line given to the parser.
+    pub fn locate(mut self, line: usize, source: &str) -> Self {
+        self.line = Some(line);
+        self.column = self
+            .offset
+            .and_then(|offset| source.get(..offset))
+            .map(|s| s.chars().count() + 1);
+        self
+    }
+}
+
+/// Returns the byte offset of the part sliced from the source.
+fn offset_in(source: &str, part: &str) -> usize {
+    let offset = (part.as_ptr() as usize).wrapping_sub(source.as_ptr() as usize);
+    debug_assert!(offset + part.len() <= source.len(), "`{part}` is not a part of `{source}`");
+    offset
 }
 
 impl Default for KeyError {
     fn default() -> Self {
         Self {
             message: "Keyboard crate error".into(),
+            token: None,
+            line: None,
+            column: None,
+            offset: None,
+            expected: vec![],
         }
     }
 }
 
 impl Display for KeyError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
+        /* location goes before the final period of the message */
+        let message = self.message.strip_suffix('.').unwrap_or(&self.message);
+        write!(f, "{message}")?;
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, " at line {line}, column {column}")?,
+            (Some(line), None) => write!(f, " at line {line}")?,
+            _ => {}
+        }
+        if !self.expected.is_empty() {
+            write!(f, ". Expected one of: {}", self.expected.join(", "))?;
+        } else if message.len() < self.message.len() {
+            write!(f, ".")?;
+        }
+        Ok(())
     }
 }
 
 impl Error for KeyError {}
 
+/// Kind of the system hook.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HookKind {
+    Keyboard,
+    Mouse,
+}
+
+impl Display for HookKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Keyboard => write!(f, "keyboard"),
+            Self::Mouse => write!(f, "mouse"),
+        }
+    }
+}
+
+/// Failure to install the system hook, e.g. when the desktop denies the hooks or another
+/// application holds the system busy with its own hooks.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HookError {
+    pub kind: HookKind,
+    /// Win32 error code of the last attempt.
+    pub code: u32,
+    pub message: String,
+    /// Number of the attempts made before giving up.
+    pub attempts: u32,
+}
+
+impl HookError {
+    /// Creates the error from the `HRESULT` of the failed call, unwrapping the Win32 error
+    /// code from it.
+    #[cfg(any(test, feature = "win32"))]
+    pub(crate) fn new(kind: HookKind, hresult: i32, message: &str, attempts: u32) -> Self {
+        let code = hresult as u32;
+        Self {
+            kind,
+            code: if code & 0xFFFF_0000 == 0x8007_0000 {
+                code & 0xFFFF
+            } else {
+                code
+            },
+            message: message.trim().into(),
+            attempts,
+        }
+    }
+}
+
+impl Display for HookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to install {} hook", self.kind)?;
+        if self.attempts > 1 {
+            write!(f, " after {} attempts", self.attempts)?;
+        }
+        write!(f, ": {} (error {})", self.message, self.code)
+    }
+}
+
+impl Error for HookError {}
+
 #[macro_export]
 macro_rules! key_error {
     ($($arg:tt)*) => {
-        KeyError{ message: format!($($arg)*) }
+        KeyError{ message: format!($($arg)*), ..Default::default() }
     }
 }
 
 #[macro_export]
 macro_rules! key_err {
     ($($arg:tt)*) => {
-        Err(KeyError{ message: format!($($arg)*) })
+        Err(KeyError{ message: format!($($arg)*), ..Default::default() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{HookError, HookKind, KeyError};
+
+    #[test]
+    fn test_key_error_display() {
+        assert_eq!("Invalid key", key_error!("Invalid key").to_string());
+
+        assert_eq!(
+            "Invalid key: `BAD` at line 2, column 3",
+            key_error!("Invalid key: `BAD`")
+                .with_token("BAD")
+                .at(2)
+                .locate(2, "A BAD")
+                .to_string()
+        );
+
+        assert_eq!(
+            "Missing rule part in `A` at line 1.",
+            key_error!("Missing rule part in `A`.").locate(1, "A").to_string()
+        );
+
+        assert_eq!(
+            "Invalid key at line 2",
+            key_error!("Invalid key").locate(2, "A").to_string()
+        );
+
+        assert_eq!(
+            "Invalid option: `X`. Expected one of: A, B",
+            key_error!("Invalid option: `X`")
+                .with_token("X")
+                .with_expected(&["A", "B"])
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_key_error_locate() {
+        let source = "BAD↓ → BAD↓";
+        let part = &source[11..];
+        let error = key_error!("Invalid key")
+            .with_token_in(part, &part[..3])
+            .within(source, part)
+            .locate(1, source);
+
+        assert_eq!(Some(11), error.offset);
+        assert_eq!(Some(1), error.line);
+        assert_eq!(Some(8), error.column);
+
+        let error = key_error!("Invalid key").within(source, part).locate(1, source);
+
+        assert_eq!(None, error.column);
+    }
+
+    #[test]
+    fn test_hook_error() {
+        let error = HookError::new(
+            HookKind::Keyboard,
+            0x8007_0005u32 as i32,
+            "Access is denied.\r\n",
+            3,
+        );
+
+        assert_eq!(5, error.code);
+        assert_eq!(
+            "Failed to install keyboard hook after 3 attempts: Access is denied. (error 5)",
+            error.to_string()
+        );
+
+        let error = HookError::new(
+            HookKind::Mouse,
+            0x8000_4005u32 as i32,
+            "Unspecified error",
+            1,
+        );
+
+        assert_eq!(0x8000_4005, error.code);
+        assert_eq!(
+            "Failed to install mouse hook: Unspecified error (error 2147500037)",
+            error.to_string()
+        );
     }
 }
@@ -1,12 +1,40 @@
+use crate::key_char::KeyChar;
+use crate::modifiers::KeyModifiers::{All, Any};
+use crate::modifiers::{KeyLocks, LOCK_KEYS};
+use crate::rule::KeyTransformRule;
+use crate::transition::KeyTransition::{Down, Up};
 use crate::trigger::KeyTrigger;
+use crate::window::WindowInfo;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Write};
+use std::sync::Arc;
 
-#[derive(Clone, Debug, PartialEq)]
+/// Version of the [`KeyEventRecord`] schema. Incremented on incompatible changes.
+pub const KEY_EVENT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct KeyEvent {
     pub trigger: KeyTrigger,
+    pub locks: KeyLocks,
+    /// Number of the tap in a series of successive taps of the key.
+    pub taps: u8,
+    /// Key is released with no other key pressed since its press.
+    pub is_alone: bool,
+    /// Key is held down and the event is generated by auto-repeat.
+    pub is_repeat: bool,
     pub time: u32,
+    /// Performance counter value at the hook entry. Zero for the events not built by the hook.
+    pub timestamp: u64,
     pub is_injected: bool,
     pub is_private: bool,
+    /// Number of the rule expansions that produced the private event. Zero for the user input.
+    pub depth: u8,
+    /// Name of the keyboard device that produced the event if known.
+    pub device: Option<Arc<str>>,
+    /// Foreground window at the moment of the event if known.
+    pub window: Option<Arc<WindowInfo>>,
+    /// Character produced by the key press in the active keyboard layout if any.
+    pub character: Option<KeyChar>,
 }
 
 impl Display for KeyEvent {
@@ -23,28 +51,135 @@ impl Display for KeyEvent {
     }
 }
 
+/// Stable serializable representation of the key event for external consumers.
+///
+/// Key names are the same as in the rules source. Transition is `DOWN` or `UP`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyEventRecord {
+    pub version: u32,
+    pub key: String,
+    /// Virtual key code.
+    #[serde(default)]
+    pub vk: u8,
+    /// Scan code with `0xE0` prefix for extended keys.
+    #[serde(default)]
+    pub scan_code: u16,
+    pub transition: String,
+    /// Pressed modifier keys.
+    pub modifiers: Vec<String>,
+    /// Lock keys that are on.
+    pub locks: Vec<String>,
+    pub taps: u8,
+    #[serde(default)]
+    pub is_alone: bool,
+    pub is_repeat: bool,
+    /// Event time in milliseconds since system start.
+    pub time: u32,
+    pub is_injected: bool,
+    pub is_private: bool,
+    #[serde(default)]
+    pub device: Option<String>,
+    /// Identifier of the applied rule.
+    #[serde(default)]
+    pub rule_id: Option<u32>,
+    /// Applied rule as written in the rules source.
+    #[serde(default)]
+    pub rule: Option<String>,
+    /// Character produced by the key press including dead key composition.
+    #[serde(default)]
+    pub character: Option<String>,
+}
+
+impl KeyEventRecord {
+    pub fn new(event: &KeyEvent, rule: Option<&KeyTransformRule>) -> Self {
+        let action = event.trigger.action;
+        Self {
+            version: KEY_EVENT_SCHEMA_VERSION,
+            key: action.key.to_string(),
+            vk: action.key.vk(),
+            scan_code: action.key.sc_ext(),
+            transition: match action.transition {
+                Down => "DOWN",
+                Up => "UP",
+            }
+            .to_string(),
+            modifiers: match event.trigger.modifiers {
+                All(state) => state.keys().map(|k| k.to_string()).collect(),
+                Any => vec![],
+            },
+            locks: LOCK_KEYS
+                .into_iter()
+                .filter(|&k| event.locks.get(k) == Some(true))
+                .map(|k| k.to_string())
+                .collect(),
+            taps: event.taps,
+            is_alone: event.is_alone,
+            is_repeat: event.is_repeat,
+            time: event.time,
+            is_injected: event.is_injected,
+            is_private: event.is_private,
+            device: event.device.as_deref().map(String::from),
+            rule_id: rule.and_then(|r| r.id),
+            rule: rule.map(|r| r.to_string()),
+            character: event.character.as_ref().map(|c| c.to_string()),
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! key_event {
+    ($text:literal) => {
+        KeyEvent {
+            trigger: KeyTrigger::from_str($text).unwrap(),
+            ..Default::default()
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::event::KeyEvent;
-    use crate::key_trigger;
+    use crate::event::{KeyEvent, KeyEventRecord};
+    use crate::key::Key;
+    use crate::key_char::KeyChar;
+    use crate::modifiers::KeyLocks;
+    use crate::rule::KeyTransformRule;
     use crate::trigger::KeyTrigger;
+    use crate::{key_rule, key_trigger};
     use std::str::FromStr;
 
     #[test]
     fn test_key_event_display() {
         let event = KeyEvent {
             trigger: key_trigger!("[LEFT_SHIFT] A↓"),
+            locks: Default::default(),
+            taps: 0,
+            is_alone: false,
+            is_repeat: false,
             time: 0,
+            timestamp: 0,
             is_injected: false,
             is_private: false,
+            depth: 0,
+            device: None,
+            window: None,
+            character: None,
         };
         assert_eq!("|     [LEFT_SHIFT] A↓|", format!("|{:>20}|", event));
 
         let event = KeyEvent {
             trigger: key_trigger!("[LEFT_SHIFT] A↓"),
+            locks: Default::default(),
+            taps: 0,
+            is_alone: false,
+            is_repeat: false,
             time: 0,
+            timestamp: 0,
             is_injected: true,
             is_private: false,
+            depth: 0,
+            device: None,
+            window: None,
+            character: None,
         };
         assert_eq!(
             "|                [LEFT_SHIFT] A↓ INJECTED|",
@@ -53,13 +188,73 @@ mod tests {
 
         let event = KeyEvent {
             trigger: key_trigger!("[LEFT_SHIFT] A↓"),
+            locks: Default::default(),
+            taps: 0,
+            is_alone: false,
+            is_repeat: false,
             time: 0,
+            timestamp: 0,
             is_injected: true,
             is_private: true,
+            depth: 0,
+            device: None,
+            window: None,
+            character: None,
         };
         assert_eq!(
             "|        [LEFT_SHIFT] A↓ INJECTED PRIVATE|",
             format!("|{:>40}|", event)
         );
     }
+
+    #[test]
+    fn test_key_event_record() {
+        let event = KeyEvent {
+            trigger: key_trigger!("[LEFT_CTRL + LEFT_SHIFT] A↓"),
+            locks: KeyLocks::capture(|k| k == Key::CapsLock),
+            taps: 2,
+            time: 1000,
+            is_injected: true,
+            device: Some("PAD".into()),
+            character: Some(KeyChar::Text("A".into())),
+            ..Default::default()
+        };
+        let record = KeyEventRecord::new(&event, Some(&key_rule!("#5 A↓ : B↓")));
+
+        assert_eq!(
+            KeyEventRecord {
+                version: 1,
+                key: "A".to_string(),
+                vk: 0x41,
+                scan_code: 0x1E,
+                transition: "DOWN".to_string(),
+                modifiers: vec!["LEFT_SHIFT".to_string(), "LEFT_CTRL".to_string()],
+                locks: vec!["CAPS_LOCK".to_string()],
+                taps: 2,
+                is_alone: false,
+                is_repeat: false,
+                time: 1000,
+                is_injected: true,
+                is_private: false,
+                device: Some("PAD".to_string()),
+                rule_id: Some(5),
+                rule: Some("#5 A↓ : B↓".to_string()),
+                character: Some("A".to_string()),
+            },
+            record
+        );
+        assert_eq!(None, KeyEventRecord::new(&event, None).rule_id);
+        assert_eq!(None, KeyEventRecord::new(&event, None).rule);
+    }
+
+    #[test]
+    fn test_key_event_record_serialize() {
+        let record = KeyEventRecord::new(&key_event!("[] ENTER↑"), None);
+        let text = toml::to_string(&record).unwrap();
+
+        assert!(text.contains("version = 1"));
+        assert!(text.contains("key = \"ENTER\""));
+        assert!(text.contains("transition = \"UP\""));
+        assert_eq!(record, toml::from_str(&text).unwrap());
+    }
 }
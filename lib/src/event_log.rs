@@ -0,0 +1,211 @@
+use crate::event::KeyEventRecord;
+use crate::key::Key;
+use std::collections::VecDeque;
+use std::collections::vec_deque::Iter;
+
+pub const DEFAULT_LOG_CAPACITY: usize = 256;
+
+/// Key event records of limited capacity. The oldest records are dropped when it is full.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyEventLog {
+    records: VecDeque<KeyEventRecord>,
+    capacity: usize,
+}
+
+impl KeyEventLog {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes capacity dropping the oldest records that do not fit.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.records.len() > self.capacity {
+            self.records.pop_front();
+        }
+    }
+
+    /// Appends the record. Returns the dropped oldest record if the log was full.
+    pub fn push(&mut self, record: KeyEventRecord) -> Option<KeyEventRecord> {
+        let dropped = if self.records.len() >= self.capacity {
+            self.records.pop_front()
+        } else {
+            None
+        };
+        self.records.push_back(record);
+        dropped
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    /// Returns records from the oldest to the newest.
+    pub fn iter(&self) -> Iter<'_, KeyEventRecord> {
+        self.records.iter()
+    }
+
+    pub fn filter<'a>(
+        &'a self,
+        filter: &'a KeyEventFilter,
+    ) -> impl Iterator<Item = &'a KeyEventRecord> {
+        self.records.iter().filter(|r| filter.matches(r))
+    }
+}
+
+impl Default for KeyEventLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_CAPACITY)
+    }
+}
+
+/// Conditions the log records must satisfy. Default filter passes all records.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyEventFilter {
+    pub key: Option<Key>,
+    /// Modifier key that must be pressed.
+    pub modifier: Option<Key>,
+    /// Only events to which a rule was applied.
+    pub only_transformed: bool,
+    pub only_injected: bool,
+}
+
+impl KeyEventFilter {
+    pub fn matches(&self, record: &KeyEventRecord) -> bool {
+        self.key
+            .is_none_or(|k| Key::from_str(&record.key) == Some(k))
+            && self
+                .modifier
+                .is_none_or(|k| record.modifiers.iter().any(|m| Key::from_str(m) == Some(k)))
+            && (!self.only_transformed || record.rule.is_some())
+            && (!self.only_injected || record.is_injected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::{KeyEvent, KeyEventRecord};
+    use crate::event_log::{KeyEventFilter, KeyEventLog};
+    use crate::key::Key;
+    use crate::rule::KeyTransformRule;
+    use crate::trigger::KeyTrigger;
+    use crate::{key_event, key_rule};
+    use std::str::FromStr;
+
+    fn record(text: &str) -> KeyEventRecord {
+        KeyEventRecord::new(
+            &KeyEvent {
+                trigger: KeyTrigger::from_str(text).unwrap(),
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    fn create_test_log() -> KeyEventLog {
+        let mut log = KeyEventLog::new(10);
+        log.push(record("[LEFT_SHIFT] A↓"));
+        log.push(KeyEventRecord::new(
+            &key_event!("[] B↓"),
+            Some(&key_rule!("B↓ : C↓")),
+        ));
+        log.push(KeyEventRecord::new(
+            &KeyEvent {
+                is_injected: true,
+                ..key_event!("[] C↓")
+            },
+            None,
+        ));
+        log.push(record("[LEFT_SHIFT + LEFT_CTRL] B↑"));
+        log
+    }
+
+    fn keys<'a>(records: impl Iterator<Item = &'a KeyEventRecord>) -> Vec<String> {
+        records
+            .map(|r| format!("{}{}", r.key, r.transition))
+            .collect()
+    }
+
+    #[test]
+    fn test_key_event_log_push() {
+        let mut log = KeyEventLog::new(2);
+
+        assert_eq!(None, log.push(record("A↓")));
+        assert_eq!(None, log.push(record("B↓")));
+        assert_eq!(Some(record("A↓")), log.push(record("C↓")));
+        assert_eq!(vec!["BDOWN", "CDOWN"], keys(log.iter()));
+    }
+
+    #[test]
+    fn test_key_event_log_set_capacity() {
+        let mut log = create_test_log();
+        log.set_capacity(2);
+
+        assert_eq!(2, log.len());
+        assert_eq!(vec!["CDOWN", "BUP"], keys(log.iter()));
+
+        log.set_capacity(0);
+        assert_eq!(1, log.capacity());
+        assert_eq!(vec!["BUP"], keys(log.iter()));
+    }
+
+    #[test]
+    fn test_key_event_log_filter() {
+        let log = create_test_log();
+
+        assert_eq!(4, log.filter(&KeyEventFilter::default()).count());
+        assert_eq!(
+            vec!["BDOWN", "BUP"],
+            keys(log.filter(&KeyEventFilter {
+                key: Some(Key::B),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            vec!["ADOWN", "BUP"],
+            keys(log.filter(&KeyEventFilter {
+                modifier: Some(Key::LeftShift),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            vec!["BDOWN"],
+            keys(log.filter(&KeyEventFilter {
+                only_transformed: true,
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            vec!["CDOWN"],
+            keys(log.filter(&KeyEventFilter {
+                only_injected: true,
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            0,
+            log.filter(&KeyEventFilter {
+                key: Some(Key::A),
+                only_transformed: true,
+                ..Default::default()
+            })
+            .count()
+        );
+    }
+}
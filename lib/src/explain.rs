@@ -0,0 +1,187 @@
+use crate::action::{KeyAction, KeySequenceItem};
+use crate::key::Key;
+use crate::modifiers::KeyModifiers::{All, Any};
+use crate::modifiers::LOCK_KEYS;
+use crate::repeat::KeyRepeat;
+use crate::rule::KeyTransformRule;
+use crate::transition::KeyTransition::{Down, Up};
+use std::fmt::Write;
+
+/// Describes the rule in plain language, e.g. to review the rules written by others.
+pub fn explain_rule(rule: &KeyTransformRule) -> String {
+    let mut lines = vec![];
+    if let Some(id) = rule.id {
+        lines.push(format!("Rule #{id}"));
+    }
+    lines.push(format!("When {}", explain_trigger(rule)));
+    if let Some(device) = &rule.device {
+        lines.push(format!("only on the keyboard device named like `{device}`"));
+    }
+    if let Some(window) = &rule.window {
+        lines.push(format!(
+            "only while the foreground window matches `{window}`"
+        ));
+    }
+
+    if rule.actions.iter().next().is_none() {
+        lines.push("then nothing is sent".to_string());
+    } else {
+        lines.push("then:".to_string());
+        for (index, item) in rule.actions.iter().enumerate() {
+            lines.push(format!("  {}. {}", index + 1, explain_item(item)));
+        }
+    }
+
+    match rule.repeat {
+        KeyRepeat::Pass => {}
+        KeyRepeat::Suppress => {
+            lines.push("Auto-repeat of the held key is ignored so the rule fires once.".into())
+        }
+        KeyRepeat::Custom { delay, interval } => lines.push(format!(
+            "While the key is held the actions are repeated every {interval} ms \
+            after {delay} ms."
+        )),
+        KeyRepeat::Turbo { interval } => lines.push(format!(
+            "While the key is held the actions are repeated every {interval} ms \
+            until it is released, even when other keys are pressed."
+        )),
+    }
+    if let Some(target) = &rule.target {
+        lines.push(format!(
+            "The actions are posted to the window matching `{target}` without activating it."
+        ));
+    }
+    lines.push(if rule.is_pass_through {
+        "The original key event is passed through as well.".into()
+    } else {
+        "The original key event is suppressed.".into()
+    });
+
+    lines.join("\n")
+}
+
+fn explain_trigger(rule: &KeyTransformRule) -> String {
+    let trigger = &rule.trigger;
+    let mut s = explain_action(&trigger.action, true);
+    match trigger.modifiers {
+        Any => {}
+        All(state) if state.is_empty() => s.push_str(" with no modifier keys held"),
+        All(state) => {
+            let keys: Vec<_> = state.keys().map(|key| key.to_string()).collect();
+            write!(s, " while only {} held", join_keys(&keys)).unwrap();
+        }
+    }
+    for key in LOCK_KEYS {
+        if let Some(is_on) = trigger.locks.get(key) {
+            write!(s, ", {key} is {}", if is_on { "on" } else { "off" }).unwrap();
+        }
+    }
+    if trigger.taps > 0 {
+        write!(s, ", at tap {} of a series", trigger.taps).unwrap();
+    }
+    if trigger.is_alone {
+        s.push_str(", with no other key pressed since its press");
+    }
+    s
+}
+
+fn explain_item(item: &KeySequenceItem) -> String {
+    match item {
+        KeySequenceItem::Action(action) => explain_action(action, false),
+        KeySequenceItem::Delay(delay) => format!("wait {delay} ms"),
+        KeySequenceItem::SaveClipboard => "save the clipboard contents".into(),
+        KeySequenceItem::RestoreClipboard => "restore the saved clipboard contents".into(),
+        KeySequenceItem::OsLayout(locale) => format!("switch the input language to {locale}"),
+        KeySequenceItem::TypeClipboard(0) => "type the clipboard text".into(),
+        KeySequenceItem::TypeClipboard(delay) => {
+            format!("type the clipboard text pausing {delay} ms after each character")
+        }
+    }
+}
+
+/// Describes the action as a trigger (`A is pressed`) or as an output (`press A`).
+fn explain_action(action: &KeyAction, is_trigger: bool) -> String {
+    let key = action.key;
+    if matches!(key, Key::WheelX | Key::WheelY) {
+        let direction = match (key, action.transition) {
+            (Key::WheelY, Down) => "up",
+            (Key::WheelY, Up) => "down",
+            (_, Down) => "right",
+            (_, Up) => "left",
+        };
+        return if is_trigger {
+            format!("the wheel is scrolled {direction}")
+        } else {
+            format!("scroll the wheel {direction}")
+        };
+    }
+
+    match (action.transition, is_trigger) {
+        (Down, true) => format!("{key} is pressed"),
+        (Up, true) => format!("{key} is released"),
+        (Down, false) => format!("press {key}"),
+        (Up, false) => format!("release {key}"),
+    }
+}
+
+fn join_keys(keys: &[String]) -> String {
+    match keys {
+        [key] => format!("{key} is"),
+        [rest @ .., last] => format!("{} and {last} are", rest.join(", ")),
+        [] => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::explain::explain_rule;
+    use crate::key_rule;
+    use crate::rule::KeyTransformRule;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_explain_rule() {
+        let rule = key_rule!(
+            "#3 [LEFT_CTRL + LEFT_SHIFT + NUM_LOCK=off] A××↓ : B↓ → DELAY(50) → \
+            OS_LAYOUT(en-US) | NO_REPEAT, PASS, DEVICE=PAD"
+        );
+
+        assert_eq!(
+            "Rule #3\n\
+            When A is pressed while only LEFT_SHIFT and LEFT_CTRL are held, NUM_LOCK is off, \
+            at tap 2 of a series\n\
+            only on the keyboard device named like `PAD`\n\
+            then:\n  \
+            1. press B\n  \
+            2. wait 50 ms\n  \
+            3. switch the input language to en-US\n\
+            Auto-repeat of the held key is ignored so the rule fires once.\n\
+            The original key event is passed through as well.",
+            explain_rule(&rule)
+        );
+    }
+
+    #[test]
+    fn test_explain_rule_wheel() {
+        assert_eq!(
+            "When the wheel is scrolled up with no modifier keys held\n\
+            then:\n  \
+            1. press A\n  \
+            2. release A\n\
+            The original key event is suppressed.",
+            explain_rule(&key_rule!("[] WHEEL_UP : A"))
+        );
+    }
+
+    #[test]
+    fn test_explain_rule_alone() {
+        assert_eq!(
+            "When LEFT_CTRL is released, with no other key pressed since its press\n\
+            then:\n  \
+            1. press ESC\n  \
+            2. release ESC\n\
+            The original key event is suppressed.",
+            explain_rule(&key_rule!("LEFT_CTRL!↑ : ESC↓ → ESC↑"))
+        );
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(feature = "win32")]
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "win32")]
+use std::panic;
+#[cfg(feature = "win32")]
+use std::process::Command;
+#[cfg(any(test, feature = "win32"))]
+use std::sync::Mutex;
+#[cfg(feature = "win32")]
+use std::sync::Once;
+#[cfg(any(test, feature = "win32"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Environment variable passing the failure description to the failsafe command.
+pub const FAILSAFE_REASON_VAR: &str = "KEYMPOSTOR_FAILSAFE_REASON";
+
+#[cfg(any(test, feature = "win32"))]
+static COMMAND: Mutex<Option<FailsafeCommand>> = Mutex::new(None);
+#[cfg(any(test, feature = "win32"))]
+static IS_FIRED: AtomicBool = AtomicBool::new(false);
+#[cfg(feature = "win32")]
+static PANIC_HOOK: Once = Once::new();
+
+/// External command executed when the engine fails unrecoverably or panics,
+/// e.g. a script that restores default layouts or alerts the user.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FailsafeCommand {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[cfg(feature = "win32")]
+impl FailsafeCommand {
+    fn spawn(&self, reason: &str) {
+        match Command::new(&self.program)
+            .args(&self.args)
+            .env(FAILSAFE_REASON_VAR, reason)
+            .spawn()
+        {
+            Ok(_) => warn!("Failsafe command started: `{}`", self.program),
+            Err(e) => error!("Failed to start failsafe command `{}`: {}", self.program, e),
+        }
+    }
+}
+
+#[cfg(feature = "win32")]
+/// Sets the failsafe command. Panic hook running it is installed on the first call.
+pub(crate) fn set_command(command: Option<FailsafeCommand>) {
+    PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            previous(info);
+            fire(&info.to_string());
+        }));
+    });
+    *COMMAND.lock().unwrap_or_else(|e| e.into_inner()) = command;
+}
+
+#[cfg(feature = "win32")]
+/// Runs the failsafe command. It is run only once per process.
+pub(crate) fn fire(reason: &str) {
+    error!("Engine failure: {}", reason);
+    if let Some(command) = take_command() {
+        command.spawn(reason);
+    }
+}
+
+#[cfg(any(test, feature = "win32"))]
+fn take_command() -> Option<FailsafeCommand> {
+    /* poisoned lock must not prevent the command in the panic hook */
+    let command = COMMAND.lock().unwrap_or_else(|e| e.into_inner()).clone()?;
+    (!IS_FIRED.swap(true, Ordering::Relaxed)).then_some(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::failsafe::{COMMAND, FailsafeCommand, take_command};
+
+    #[test]
+    fn test_take_command_once() {
+        let command = FailsafeCommand {
+            program: "restore.cmd".into(),
+            args: vec!["--alert".into()],
+        };
+        /* panic hook is not installed so that failing tests do not run the command */
+        COMMAND.lock().unwrap().replace(command.clone());
+
+        assert_eq!(Some(command), take_command());
+        assert_eq!(None, take_command());
+    }
+
+    #[test]
+    fn test_deserialize_command() {
+        let actual: FailsafeCommand = toml::from_str("program = \"restore.cmd\"").unwrap();
+
+        assert_eq!("restore.cmd", actual.program);
+        assert!(actual.args.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use log::debug;
+use std::ffi::c_void;
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Security::{
+    GetSidSubAuthority, GetSidSubAuthorityCount, GetTokenInformation, TOKEN_MANDATORY_LABEL,
+    TOKEN_QUERY, TokenIntegrityLevel, TokenUIAccess,
+};
+use windows::Win32::System::Threading::{
+    GetCurrentProcess, OpenProcess, OpenProcessToken, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+/* relative identifier of the mandatory label of elevated processes */
+const HIGH_INTEGRITY_LEVEL: u32 = 0x3000;
+
+/// Ability of the hook to send input to the foreground window.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HookHealth {
+    #[default]
+    Ok,
+    /// Foreground window belongs to a process of higher integrity level, usually
+    /// an elevated one. Windows blocks input sent to it so the rules have no effect.
+    ElevatedForeground,
+}
+
+impl HookHealth {
+    /// Checks the current foreground window. Windows of processes that cannot be queried
+    /// are considered accessible.
+    pub fn check() -> Self {
+        let own = match process_token_info(unsafe { GetCurrentProcess() }) {
+            Ok(info) => info,
+            Err(e) => {
+                debug!("Failed to query own process token: {}", e);
+                return Self::Ok;
+            }
+        };
+
+        let foreground = foreground_process_id().and_then(|process_id| {
+            let process =
+                unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id) }
+                    .ok()?;
+            let info = process_token_info(process);
+            unsafe {
+                let _ = CloseHandle(process);
+            }
+            info.ok()
+        });
+
+        Self::evaluate(&own, foreground.as_ref())
+    }
+
+    fn evaluate(own: &TokenInfo, foreground: Option<&TokenInfo>) -> Self {
+        match foreground {
+            Some(foreground) if !own.ui_access && foreground.integrity > own.integrity => {
+                Self::ElevatedForeground
+            }
+            _ => Self::Ok,
+        }
+    }
+}
+
+/// Returns `true` if the current process runs with administrator rights.
+pub fn is_elevated() -> bool {
+    process_token_info(unsafe { GetCurrentProcess() })
+        .is_ok_and(|info| info.integrity >= HIGH_INTEGRITY_LEVEL)
+}
+
+#[derive(Debug, Default)]
+struct TokenInfo {
+    integrity: u32,
+    /// Process is allowed to send input to higher integrity level windows.
+    ui_access: bool,
+}
+
+fn foreground_process_id() -> Option<u32> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_invalid() {
+        return None;
+    }
+
+    let mut process_id = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut process_id)) };
+    (process_id != 0).then_some(process_id)
+}
+
+fn process_token_info(process: HANDLE) -> windows::core::Result<TokenInfo> {
+    let mut token = HANDLE::default();
+    unsafe {
+        OpenProcessToken(process, TOKEN_QUERY, &mut token)?;
+        let info = query_integrity_level(token).and_then(|integrity| {
+            Ok(TokenInfo {
+                integrity,
+                ui_access: query_ui_access(token)?,
+            })
+        });
+        let _ = CloseHandle(token);
+        info
+    }
+}
+
+unsafe fn query_integrity_level(token: HANDLE) -> windows::core::Result<u32> {
+    unsafe {
+        let mut size = 0;
+        let _ = GetTokenInformation(token, TokenIntegrityLevel, None, 0, &mut size);
+
+        /* u64 buffer keeps the label aligned */
+        let mut buffer = vec![0u64; (size as usize).div_ceil(size_of::<u64>())];
+        GetTokenInformation(
+            token,
+            TokenIntegrityLevel,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            size,
+            &mut size,
+        )?;
+
+        let label = &*(buffer.as_ptr() as *const TOKEN_MANDATORY_LABEL);
+        let count = *GetSidSubAuthorityCount(label.Label.Sid);
+        Ok(*GetSidSubAuthority(label.Label.Sid, count as u32 - 1))
+    }
+}
+
+unsafe fn query_ui_access(token: HANDLE) -> windows::core::Result<bool> {
+    let mut value = 0u32;
+    let mut size = 0;
+    unsafe {
+        GetTokenInformation(
+            token,
+            TokenUIAccess,
+            Some(&mut value as *mut u32 as *mut c_void),
+            size_of::<u32>() as u32,
+            &mut size,
+        )?;
+    }
+    Ok(value != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::health::HookHealth::ElevatedForeground;
+    use crate::health::{HookHealth, TokenInfo};
+
+    fn token(integrity: u32, ui_access: bool) -> TokenInfo {
+        TokenInfo {
+            integrity,
+            ui_access,
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let medium = token(0x2000, false);
+        let high = token(0x3000, false);
+
+        assert_eq!(HookHealth::Ok, HookHealth::evaluate(&medium, None));
+        assert_eq!(HookHealth::Ok, HookHealth::evaluate(&medium, Some(&medium)));
+        assert_eq!(HookHealth::Ok, HookHealth::evaluate(&high, Some(&medium)));
+        assert_eq!(
+            ElevatedForeground,
+            HookHealth::evaluate(&medium, Some(&high))
+        );
+        assert_eq!(
+            HookHealth::Ok,
+            HookHealth::evaluate(&token(0x2000, true), Some(&high))
+        );
+    }
+}
@@ -1,61 +1,322 @@
-use crate::action::KeyAction;
+use crate::action::{KeyAction, KeyActionSequence};
+use crate::char_resolver::CharResolver;
+use crate::clipboard::{ClipboardSnapshot, read_clipboard_text};
+use crate::code_point::CodePointEntry;
+use crate::code_point::CodePointInput::{Commit, Consumed, Ignored};
+use crate::compose::{ComposeEntry, ComposeInput, ComposeTable};
+use crate::crash::track_input;
+use crate::debounce::{Debounce, DebounceFilter};
+use crate::device::{handle_raw_input, last_device, register_raw_input};
+use crate::error::{HookError, HookKind};
 use crate::event::KeyEvent;
-use crate::input::PRIVATE_EVENT_MARKER;
+use crate::failsafe::{self, FailsafeCommand};
+use crate::health::HookHealth;
+use crate::input::{MAX_INJECTION_DEPTH, injection_depth, set_injection_depth, set_injection_mode};
 use crate::key::Key;
 use crate::key::Key::{LeftButton, MiddleButton, RightButton, WheelX, WheelY};
+use crate::latency::{LatencyStats, elapsed_micros, timestamp};
+use crate::modifiers::KeyLocks;
 use crate::modifiers::KeyModifiers::All;
-use crate::notify::install_notify_listener;
+use crate::notify::{KeyEventError, install_notify_listener, notify_key_error};
+use crate::pairing::{KeyPresses, PairedRule};
+use crate::repeat::KeyRepeat;
 use crate::rule::{KeyTransformRule, KeyTransformRules};
+use crate::scheduler::{RepeatScheduler, RepeatStats};
 use crate::state::KeyboardState;
+use crate::tap::{AloneTracker, TapTracker};
+use crate::target::{find_target_window, post_input};
 use crate::transform::KeyTransformMap;
 use crate::transition::KeyTransition;
 use crate::transition::KeyTransition::{Down, Up};
 use crate::trigger::KeyTrigger;
 use crate::utils::if_else;
+use crate::window::WindowInfo;
 use crate::{input, notify};
 use fxhash::FxHashSet;
-use input::build_input;
+use input::{
+    ClipboardOp, InputPart, build_delayed_input, build_input, build_locks_restore_input,
+    build_modifiers_release_input, build_modifiers_restore_input, build_typing_input,
+    build_unicode_input,
+};
 use log::{debug, trace, warn};
 use notify::notify_key_event;
+use serde::{Deserialize, Serialize};
 use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 use windows::Win32::Foundation::*;
-use windows::Win32::UI::Input::KeyboardAndMouse::{SendInput, INPUT};
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetKeyState, SendInput, INPUT, VK_PACKET};
 use windows::Win32::UI::WindowsAndMessaging::*;
 
 #[derive(Debug, Default)]
 pub struct KeyboardHook {}
 
+/// Splitting of the sequence input sent at once into chunks separated by a delay, for the
+/// applications that lose input arriving too fast. Zero size sends every part of the sequence
+/// between its delays in a single call so that it does not interleave with the user input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputChunking {
+    /// Maximum number of the input events sent at once.
+    pub size: usize,
+    /// Delay in milliseconds between the chunks.
+    pub delay: u32,
+}
+
+/// Key codes the output of the rules is sent with.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InjectionMode {
+    /// Scan codes along with virtual keys.
+    #[default]
+    Both,
+    /// Scan codes only, for the games reading the keyboard through DirectInput or raw input.
+    Scancode,
+    /// Virtual keys only.
+    Vk,
+}
+
+/// Retrying of the failed hook installation, e.g. while the system is busy with the hooks of
+/// another application starting at the same time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HookRetry {
+    /// Maximum number of the installation attempts.
+    pub attempts: u32,
+    /// Delay in milliseconds before the second attempt, doubled before each next one.
+    pub delay: u32,
+}
+
+impl Default for HookRetry {
+    fn default() -> Self {
+        Self {
+            attempts: 4,
+            delay: 100,
+        }
+    }
+}
+
+impl HookRetry {
+    /// Returns the delays before the repeated attempts.
+    pub fn delays(self) -> impl Iterator<Item = u32> {
+        (0..self.attempts.saturating_sub(1)).map(move |i| self.delay.saturating_mul(1 << i.min(16)))
+    }
+}
+
+/* one event per frame of a 60 FPS game */
+const SCANCODE_INPUT_DELAY: u32 = 16;
+
+impl InjectionMode {
+    /// Returns the chunking the sequences are sent with. Games polling the keyboard state
+    /// miss the keys pressed and released within a frame, so scan code only input is sent
+    /// one event per frame unless the chunking is set explicitly.
+    pub fn chunking(self, chunking: InputChunking) -> InputChunking {
+        if self == Self::Scancode && chunking.size == 0 {
+            InputChunking {
+                size: 1,
+                delay: SCANCODE_INPUT_DELAY,
+            }
+        } else {
+            chunking
+        }
+    }
+}
+
 impl KeyboardHook {
     pub fn setup(&self, owner: HWND) {
         install_notify_listener(owner);
+        register_raw_input(owner);
     }
 
-    pub fn install(&self) {
+    /// Handles `WM_INPUT` message to attribute key events to the keyboard devices.
+    pub fn handle_raw_input(&self, l_param: isize) {
+        handle_raw_input(l_param);
+    }
+
+    /// Installs the hooks retrying the failed attempts. Fails if the keyboard hook cannot be
+    /// installed. Failure of the mouse hook is logged only since the key rules work without it.
+    pub fn install(&self) -> Result<(), HookError> {
         KEYBOARD_STATE.replace(KeyboardState::default());
+        PRESSED_KEYS.replace(KeyboardState::default());
+        KEY_PRESSES.with_borrow_mut(KeyPresses::clear);
         trace!("Keyboard state cleared");
 
-        install_keyboard_hook();
+        install_keyboard_hook()?;
 
         #[cfg(feature = "no_mouse")]
         warn!("Mouse hook is disabled by feature flag");
         #[cfg(not(feature = "no_mouse"))]
-        install_mouse_hook();
+        if let Err(e) = install_mouse_hook() {
+            warn!("{}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Sets how the failed hook installation is retried.
+    pub fn set_retry(&self, retry: HookRetry) {
+        HOOK_RETRY.set(retry);
     }
 
     pub fn uninstall(&self) {
+        stop_repeat();
+        cancel_delayed_input();
         uninstall_key_hook();
         #[cfg(not(feature = "no_mouse"))]
         uninstall_mouse_hook();
     }
 
+    /// Sets the rules applied to the input except the disabled ones.
     pub fn set_rules(&self, rules: Option<&KeyTransformRules>) {
-        let map = rules.and_then(|r| Some(KeyTransformMap::new(r.iter())));
-        TRANSFOFM_MAP.replace(map);
+        RULES.replace(rules.cloned());
+        update_transform_map();
+    }
+
+    /// Excludes the rules having the IDs from processing without removing them. Survives
+    /// the rules update.
+    pub fn set_disabled_rules(&self, rule_ids: BTreeSet<u32>) {
+        DISABLED_RULES.replace(rule_ids);
+        update_transform_map();
     }
 
     pub fn suppress_keys(&self, keys: &[Key]) {
         SUPPRESSED_KEYS.replace(FxHashSet::from_iter(keys.iter().cloned()));
     }
+
+    /// Sets trigger that starts hexadecimal code point entry.
+    pub fn set_code_point_trigger(&self, trigger: Option<KeyTrigger>) {
+        CODE_POINT_TRIGGER.replace(trigger);
+    }
+
+    /// Sets trigger that starts composition of the character from the mnemonic.
+    pub fn set_compose_trigger(&self, trigger: Option<KeyTrigger>) {
+        COMPOSE_TRIGGER.replace(trigger);
+    }
+
+    pub fn set_compose_table(&self, table: ComposeTable) {
+        COMPOSE_TABLE.replace(table);
+    }
+
+    /// Enables or disables applying of the rules. Disabled hook passes all events through.
+    pub fn set_enabled(&self, enabled: bool) {
+        set_enabled(enabled);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        IS_ENABLED.get()
+    }
+
+    /// Suspends or resumes the hook regardless of whether processing is enabled, e.g. while
+    /// the session is locked. Key states are reset on resume since the input sent to the other
+    /// desktops is not seen by the hook.
+    pub fn set_suspended(&self, suspended: bool) {
+        set_suspended(suspended);
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        IS_SUSPENDED.get()
+    }
+
+    /// Sets trigger that toggles processing bypassing the rules even when processing is disabled.
+    pub fn set_toggle_trigger(&self, trigger: Option<KeyTrigger>) {
+        TOGGLE_TRIGGER.replace(trigger);
+    }
+
+    /// Sets trigger that is suppressed before the rules are applied, so that the owner can
+    /// restore the working rules on its notification whatever the current rules are.
+    pub fn set_panic_trigger(&self, trigger: Option<KeyTrigger>) {
+        PANIC_TRIGGER.replace(trigger);
+    }
+
+    /// Returns timing statistics of the current or the last custom auto-repeat.
+    pub fn repeat_stats(&self) -> Option<RepeatStats> {
+        ACTIVE_REPEAT
+            .with_borrow(|repeat| repeat.as_ref().map(|r| r.scheduler.stats()))
+            .or(LAST_REPEAT_STATS.get())
+    }
+
+    /// Returns processing latencies of the latest events.
+    pub fn latency(&self) -> LatencyStats {
+        LATENCY.with_borrow(Clone::clone)
+    }
+
+    /// Returns whether the hook is able to send input to the foreground window.
+    pub fn health(&self) -> HookHealth {
+        HookHealth::check()
+    }
+
+    /// Sets the command executed when the hook fails unrecoverably or the application panics.
+    pub fn set_failsafe_command(&self, command: Option<FailsafeCommand>) {
+        failsafe::set_command(command);
+    }
+
+    /// Sets the foreground window the rules having window condition are checked against.
+    pub fn set_foreground_window(&self, window: Option<WindowInfo>) {
+        FOREGROUND_WINDOW.replace(window.map(Arc::new));
+    }
+
+    /// Diverts the next physical key press to the callback instead of the rules and the
+    /// applications. The release of the captured key is swallowed as well. Modifier keys pass
+    /// through so that the captured trigger carries them. The callback runs inside the hook
+    /// procedure so it must return quickly; it may start the next capture.
+    pub fn capture_next(&self, callback: impl FnOnce(&KeyEvent) + 'static) {
+        KEY_CAPTURE.with_borrow_mut(|capture| capture.callback = Some(Box::new(callback)));
+    }
+
+    /// Cancels the capture started by [`capture_next`](Self::capture_next).
+    pub fn cancel_capture(&self) {
+        KEY_CAPTURE.with_borrow_mut(|capture| capture.callback = None);
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        KEY_CAPTURE.with_borrow(|capture| capture.callback.is_some())
+    }
+
+    /// Sends the actions the same way as the actions of a matched rule.
+    pub fn play(&self, actions: &KeyActionSequence) {
+        play_actions(actions, 1);
+    }
+
+    /// Sets how many times the rules may be applied to the input sent by the rules. Zero keeps
+    /// the rules from seeing their own input. Rules triggering each other deeper are stopped.
+    pub fn set_max_injection_depth(&self, depth: u8) {
+        MAX_DEPTH.set(depth.min(MAX_INJECTION_DEPTH - 1));
+    }
+
+    /// Sets how the input of the sequences is split into chunks.
+    pub fn set_input_chunking(&self, chunking: InputChunking) {
+        INPUT_CHUNKING.set(chunking);
+    }
+
+    /// Sets the key codes the output of the rules is sent with.
+    pub fn set_injection_mode(&self, mode: InjectionMode) {
+        INJECTION_MODE.set(mode);
+    }
+
+    /// Enables releasing the modifier keys held by the user while the rule output is sent, so
+    /// that `[LEFT_CTRL] J : DOWN` sends plain `DOWN`. The modifiers are pressed again after
+    /// the output if they are still held.
+    pub fn set_release_modifiers(&self, enabled: bool) {
+        RELEASE_MODIFIERS.set(enabled);
+    }
+
+    /// Enables applying the rules of the numpad digits to the numpad keys regardless of the
+    /// NUM_LOCK state, so that `NUM_4` rules also fire on `NUM_LEFT`. The rules written for
+    /// the NUM_LOCK off keys take precedence.
+    pub fn set_normalize_numpad(&self, enabled: bool) {
+        NORMALIZE_NUMPAD.set(enabled);
+    }
+
+    /// Sets maximum interval in milliseconds between successive taps of a key.
+    pub fn set_tap_interval(&self, interval: u32) {
+        TAP_TRACKER.with_borrow_mut(|tracker| tracker.set_interval(interval));
+    }
+
+    /// Sets the dead zone after a key release in which a press of the same key is ignored,
+    /// to work around the chattering key switches.
+    pub fn set_debounce(&self, debounce: Debounce) {
+        DEBOUNCE_FILTER.with_borrow_mut(|filter| filter.set_debounce(debounce));
+    }
 }
 
 impl Drop for KeyboardHook {
@@ -65,27 +326,115 @@ impl Drop for KeyboardHook {
 }
 
 thread_local! {
-    static KEY_HOOK: Cell<Option<HHOOK>> = Cell::new(None);
-    static MOUSE_HOOK: Cell<Option<HHOOK>> = Cell::new(None);
+    static KEY_HOOK: Cell<Option<HHOOK>> = const { Cell::new(None) };
+    static IS_ENABLED: Cell<bool> = const { Cell::new(true) };
+    static IS_SUSPENDED: Cell<bool> = const { Cell::new(false) };
+    static TOGGLE_TRIGGER: RefCell<Option<KeyTrigger>> = const { RefCell::new(None) };
+    static PANIC_TRIGGER: RefCell<Option<KeyTrigger>> = const { RefCell::new(None) };
+    static MOUSE_HOOK: Cell<Option<HHOOK>> = const { Cell::new(None) };
+    static HOOK_RETRY: Cell<HookRetry> = Cell::new(HookRetry::default());
     static KEYBOARD_STATE: Cell<KeyboardState> = Cell::new(KeyboardState::default());
-    static TRANSFOFM_MAP: RefCell<Option<KeyTransformMap>> = RefCell::new(None);
+    static RULES: RefCell<Option<KeyTransformRules>> = const { RefCell::new(None) };
+    static DISABLED_RULES: RefCell<BTreeSet<u32>> = const { RefCell::new(BTreeSet::new()) };
+    static TRANSFOFM_MAP: RefCell<Option<KeyTransformMap>> = const { RefCell::new(None) };
     static SUPPRESSED_KEYS: RefCell<FxHashSet<Key>> = RefCell::new(FxHashSet::default());
+    static CODE_POINT_TRIGGER: RefCell<Option<KeyTrigger>> = const { RefCell::new(None) };
+    static CODE_POINT_ENTRY: RefCell<CodePointEntry> = RefCell::new(CodePointEntry::default());
+    static COMPOSE_TRIGGER: RefCell<Option<KeyTrigger>> = const { RefCell::new(None) };
+    static COMPOSE_TABLE: RefCell<ComposeTable> = RefCell::new(ComposeTable::default());
+    static COMPOSE_ENTRY: RefCell<ComposeEntry> = RefCell::new(ComposeEntry::default());
+    static DEBOUNCE_FILTER: RefCell<DebounceFilter> = RefCell::new(DebounceFilter::default());
+    static TAP_TRACKER: RefCell<TapTracker> = RefCell::new(TapTracker::default());
+    static ALONE_TRACKER: RefCell<AloneTracker> = RefCell::new(AloneTracker::default());
+    static PRESSED_KEYS: Cell<KeyboardState> = Cell::new(KeyboardState::default());
+    static KEY_PRESSES: RefCell<KeyPresses> = RefCell::new(KeyPresses::default());
+    static ACTIVE_REPEAT: RefCell<Option<ActiveRepeat>> = const { RefCell::new(None) };
+    static LAST_REPEAT_STATS: Cell<Option<RepeatStats>> = const { Cell::new(None) };
+    static DELAYED_INPUT: RefCell<DelayedInput> = RefCell::new(DelayedInput::default());
+    static INPUT_CHUNKING: Cell<InputChunking> = Cell::new(InputChunking::default());
+    static INJECTION_MODE: Cell<InjectionMode> = const { Cell::new(InjectionMode::Both) };
+    static MAX_DEPTH: Cell<u8> = const { Cell::new(0) };
+    static RELEASE_MODIFIERS: Cell<bool> = const { Cell::new(false) };
+    static NORMALIZE_NUMPAD: Cell<bool> = const { Cell::new(false) };
+    static LATENCY: RefCell<LatencyStats> = RefCell::new(LatencyStats::default());
+    static CLIPBOARD_SNAPSHOT: RefCell<Option<ClipboardSnapshot>> = const { RefCell::new(None) };
+    static FOREGROUND_WINDOW: RefCell<Option<Arc<WindowInfo>>> = const { RefCell::new(None) };
+    static KEY_CAPTURE: RefCell<KeyCapture> = RefCell::new(KeyCapture::default());
+}
+
+fn update_transform_map() {
+    let map = RULES.with_borrow(|rules| {
+        DISABLED_RULES.with_borrow(|disabled| {
+            rules
+                .as_ref()
+                .map(|r| KeyTransformMap::new(r.without_disabled(disabled).iter()))
+        })
+    });
+    TRANSFOFM_MAP.replace(map);
+}
+
+type CaptureCallback = Box<dyn FnOnce(&KeyEvent)>;
+
+/// One-shot diversion of the next key press.
+#[derive(Default)]
+struct KeyCapture {
+    callback: Option<CaptureCallback>,
+    /// Captured key until it is released.
+    key: Option<Key>,
 }
 
-fn install_keyboard_hook() {
+/// Custom auto-repeat of the rule actions.
+struct ActiveRepeat {
+    key: Key,
+    /// Repeat is not stopped by the other keys.
+    is_turbo: bool,
+    scheduler: RepeatScheduler,
+}
+
+/// Input parts waiting for the sequence delays to pass.
+#[derive(Default)]
+struct DelayedInput {
+    parts: VecDeque<InputPart>,
+    timer: Option<usize>,
+}
+
+fn install_keyboard_hook() -> Result<(), HookError> {
     if KEY_HOOK.get().is_some() {
         warn!("Keyboard hook already installed");
-        return;
+        return Ok(());
     }
 
-    match unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(key_hook_proc), None, 0) } {
+    match set_hook(HookKind::Keyboard, WH_KEYBOARD_LL, Some(key_hook_proc)) {
         Ok(handle) => {
             KEY_HOOK.replace(Some(handle));
             debug!("Keyboard hook installed");
+            Ok(())
         }
         Err(e) => {
             KEY_HOOK.replace(None);
-            warn!("Failed to install keyboard hook: {}", e);
+            warn!("{}", e);
+            failsafe::fire(&e.to_string());
+            Err(e)
+        }
+    }
+}
+
+/// Installs the hook making the attempts with the delays of the retry policy in between.
+fn set_hook(kind: HookKind, id: WINDOWS_HOOK_ID, proc: HOOKPROC) -> Result<HHOOK, HookError> {
+    let mut delays = HOOK_RETRY.get().delays();
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match unsafe { SetWindowsHookExW(id, proc, None, 0) } {
+            Ok(handle) => return Ok(handle),
+            Err(e) => {
+                let error = HookError::new(kind, e.code().0, &e.message(), attempts);
+                let Some(delay) = delays.next() else {
+                    return Err(error);
+                };
+                warn!("{}. Retrying in {} ms", error, delay);
+                thread::sleep(Duration::from_millis(delay as u64));
+            }
         }
     }
 }
@@ -101,22 +450,26 @@ fn uninstall_key_hook() {
     }
 }
 
-fn install_mouse_hook() {
+/// Removes the hooks if they were installed by the current thread. Hooks of the other
+/// threads are removed by the system when the process dies.
+pub(crate) fn remove_hooks() {
+    for hook in [&KEY_HOOK, &MOUSE_HOOK] {
+        if let Ok(Some(handle)) = hook.try_with(Cell::take) {
+            unsafe { UnhookWindowsHookEx(handle) }.ok();
+        }
+    }
+}
+
+fn install_mouse_hook() -> Result<(), HookError> {
     if MOUSE_HOOK.get().is_some() {
         warn!("Mouse hook already installed");
-        return;
+        return Ok(());
     }
 
-    match unsafe { SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook_proc), None, 0) } {
-        Ok(handle) => {
-            MOUSE_HOOK.replace(Some(handle));
-            debug!("Mouse hook installed");
-        }
-        Err(e) => {
-            MOUSE_HOOK.replace(None);
-            warn!("Failed to install mouse hook: {}", e);
-        }
-    }
+    let handle = set_hook(HookKind::Mouse, WH_MOUSE_LL, Some(mouse_hook_proc))?;
+    MOUSE_HOOK.replace(Some(handle));
+    debug!("Mouse hook installed");
+    Ok(())
 }
 
 fn uninstall_mouse_hook() {
@@ -133,8 +486,11 @@ fn uninstall_mouse_hook() {
 extern "system" fn key_hook_proc(code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
     if code == HC_ACTION as i32 {
         let input = unsafe { *(l_param.0 as *const KBDLLHOOKSTRUCT) };
+        if is_bounce(input) {
+            return LRESULT(1);
+        }
         let event = build_key_event(input);
-        if handle_event(&event) {
+        if handle_measured_event(&event) {
             return LRESULT(1);
         }
     }
@@ -147,7 +503,7 @@ extern "system" fn mouse_hook_proc(code: i32, w_param: WPARAM, l_param: LPARAM)
     if msg != WM_MOUSEMOVE {
         let input = unsafe { *(l_param.0 as *const MSLLHOOKSTRUCT) };
         let event = build_mouse_event(msg, input);
-        if handle_event(&event) {
+        if handle_measured_event(&event) {
             return LRESULT(1);
         }
     }
@@ -155,16 +511,109 @@ extern "system" fn mouse_hook_proc(code: i32, w_param: WPARAM, l_param: LPARAM)
     unsafe { CallNextHookEx(MOUSE_HOOK.get(), code, w_param, l_param) }
 }
 
+/// Handles the event recording the time spent. Notifications are posted after the processing
+/// so that they carry the latency.
+#[inline(always)]
+fn handle_measured_event(event: &KeyEvent) -> bool {
+    notify::defer();
+    let is_handled = handle_event(event);
+    let latency = elapsed_micros(event.timestamp);
+    LATENCY.with_borrow_mut(|stats| stats.record(latency));
+    notify::post_deferred(latency);
+    is_handled
+}
+
 #[inline(always)]
 fn handle_event(event: &KeyEvent) -> bool {
     trace!("Processing event: {event}");
 
+    if IS_SUSPENDED.get() {
+        trace!("Processing suspended");
+        notify_key_event(event.clone(), None);
+        return false;
+    }
+
     if event.is_private {
-        trace!("Event ignored");
+        return handle_private_event(event);
+    }
+
+    if handle_capture(event) {
+        notify_key_event(event.clone(), None);
+        return true;
+    }
+
+    if TOGGLE_TRIGGER.with_borrow(|t| t.as_ref().is_some_and(|t| t.matches(event))) {
+        set_enabled(!IS_ENABLED.get());
         notify_key_event(event.clone(), None);
+        return true;
+    }
+
+    /* the owner rolls the rules back on the notification, so the rules cannot intercept it */
+    if PANIC_TRIGGER.with_borrow(|t| t.as_ref().is_some_and(|t| t.matches(event))) {
+        debug!("Panic key pressed");
+        notify_key_event(event.clone(), None);
+        return true;
+    }
+
+    if !IS_ENABLED.get() {
+        trace!("Processing disabled");
+        notify_key_event(event.clone(), None);
+        update_kbd_state(&event.trigger.action);
         return false;
     }
 
+    if !event.is_repeat {
+        stop_repeat_on(&event.trigger.action);
+    }
+
+    if CODE_POINT_TRIGGER.with_borrow(|t| t.as_ref().is_some_and(|t| t.matches(event))) {
+        debug!("Code point entry started");
+        CODE_POINT_ENTRY.with_borrow_mut(|entry| entry.start(event.trigger.action.key));
+        notify_key_event(event.clone(), None);
+        return true;
+    }
+
+    match CODE_POINT_ENTRY.with_borrow_mut(|entry| entry.handle(&event.trigger.action)) {
+        Ignored => {}
+        Consumed => {
+            trace!("Event consumed by code point entry");
+            notify_key_event(event.clone(), None);
+            return true;
+        }
+        Commit(ch) => {
+            debug!("Code point entry committed: `{ch}`");
+            notify_key_event(event.clone(), None);
+            send_input(&build_unicode_input(ch));
+            return true;
+        }
+    }
+
+    if COMPOSE_TRIGGER.with_borrow(|t| t.as_ref().is_some_and(|t| t.matches(event))) {
+        debug!("Composition started");
+        COMPOSE_ENTRY.with_borrow_mut(|entry| entry.start(event.trigger.action.key));
+        notify_key_event(event.clone(), None);
+        return true;
+    }
+
+    match COMPOSE_TABLE.with_borrow(|table| {
+        COMPOSE_ENTRY.with_borrow_mut(|entry| {
+            entry.handle(&event.trigger.action, &event.trigger.modifiers, table)
+        })
+    }) {
+        ComposeInput::Ignored => {}
+        ComposeInput::Consumed => {
+            trace!("Event consumed by composition");
+            notify_key_event(event.clone(), None);
+            return true;
+        }
+        ComposeInput::Commit(ch) => {
+            debug!("Composed: `{ch}`");
+            notify_key_event(event.clone(), None);
+            send_input(&build_unicode_input(ch));
+            return true;
+        }
+    }
+
     if SUPPRESSED_KEYS.with_borrow(|set| set.contains(&event.trigger.action.key)) {
         trace!("Event suppressed");
         update_kbd_state(&event.trigger.action);
@@ -172,76 +621,513 @@ fn handle_event(event: &KeyEvent) -> bool {
         return true;
     }
 
-    match get_rule(&event) {
-        Some(rule) => {
+    match KEY_PRESSES.with_borrow_mut(|presses| presses.rule(event, get_rule)) {
+        PairedRule::Rule(rule) => {
+            if event.is_repeat && rule.repeat != KeyRepeat::Pass {
+                trace!("Auto-repeat suppressed");
+                notify_key_event(event.clone(), None);
+                return true;
+            }
+
             debug!("Applying rule: {}", rule);
             notify_key_event(event.clone(), Some(rule.clone()));
-            apply_rule(&rule);
-            true
+            apply_rule(&rule, 1);
+
+            if event.trigger.action.transition == Down && rule.target.is_none() {
+                match rule.repeat {
+                    KeyRepeat::Custom { delay, interval } => {
+                        start_repeat(event.trigger.action.key, &rule, delay, interval, false)
+                    }
+                    /* the first actions are already sent by the rule */
+                    KeyRepeat::Turbo { interval } => {
+                        start_repeat(event.trigger.action.key, &rule, interval, interval, true)
+                    }
+                    _ => {}
+                }
+            }
+
+            if rule.is_pass_through {
+                trace!("Event passed through");
+                update_kbd_state(&event.trigger.action);
+            }
+            !rule.is_pass_through
         }
-        None => {
+        PairedRule::None => {
             trace!("No matching rules");
             notify_key_event(event.clone(), None);
             update_kbd_state(&event.trigger.action);
             false
         }
+        PairedRule::Suppress => {
+            trace!("Release of the remapped key suppressed");
+            notify_key_event(event.clone(), None);
+            true
+        }
+    }
+}
+
+/// Hands the key press to the pending capture. Returns `true` if the event is swallowed.
+fn handle_capture(event: &KeyEvent) -> bool {
+    let action = event.trigger.action;
+    let is_captured_key = KEY_CAPTURE.with_borrow_mut(|capture| {
+        let is_captured = capture.key == Some(action.key);
+        if is_captured && action.transition == Up {
+            capture.key = None;
+        }
+        is_captured
+    });
+    if is_captured_key {
+        trace!("Captured key event suppressed");
+        return true;
+    }
+
+    if event.is_injected
+        || action.transition != Down
+        || action.key.is_modifier()
+        || action.key.is_mouse()
+    {
+        return false;
+    }
+
+    let Some(callback) = KEY_CAPTURE.with_borrow_mut(|capture| {
+        let callback = capture.callback.take();
+        if callback.is_some() {
+            capture.key = Some(action.key);
+        }
+        callback
+    }) else {
+        return false;
+    };
+
+    debug!("Key captured: {}", event.trigger);
+    callback(event);
+    true
+}
+
+/// Applies the rules to the input sent by the rules until the maximum depth is reached.
+/// Deeper expansion means the rules trigger each other endlessly so it is stopped.
+fn handle_private_event(event: &KeyEvent) -> bool {
+    let max_depth = MAX_DEPTH.get();
+    let rule = if max_depth > 0 && IS_ENABLED.get() {
+        get_rule(event)
+    } else {
+        None
+    };
+    let Some(rule) = rule else {
+        trace!("Event ignored");
+        notify_key_event(event.clone(), None);
+        return false;
+    };
+
+    if event.depth > max_depth {
+        warn!("Rules expansion stopped at depth {}: {}", event.depth, rule);
+        stop_repeat();
+        cancel_delayed_input();
+        notify_key_error(event.clone(), rule, KeyEventError::DepthExceeded(max_depth));
+        return true;
     }
+
+    debug!("Applying rule to private event: {}", rule);
+    notify_key_event(event.clone(), Some(rule.clone()));
+    apply_rule(&rule, event.depth + 1);
+    !rule.is_pass_through
+}
+
+fn set_enabled(enabled: bool) {
+    IS_ENABLED.set(enabled);
+    if !enabled {
+        stop_repeat();
+        cancel_delayed_input();
+    }
+    debug!("Processing {}", if_else(enabled, "enabled", "disabled"));
+}
+
+fn set_suspended(suspended: bool) {
+    if IS_SUSPENDED.replace(suspended) == suspended {
+        return;
+    }
+
+    if suspended {
+        stop_repeat();
+        cancel_delayed_input();
+    } else {
+        /* releases made on the other desktop were never seen */
+        KEYBOARD_STATE.set(KeyboardState::default());
+        PRESSED_KEYS.set(KeyboardState::default());
+        KEY_PRESSES.set(KeyPresses::default());
+        CODE_POINT_ENTRY.set(CodePointEntry::default());
+        COMPOSE_ENTRY.set(ComposeEntry::default());
+    }
+    debug!("Processing {}", if_else(suspended, "suspended", "resumed"));
 }
 
 #[inline(always)]
-fn get_rule(event: &KeyEvent) -> Option<KeyTransformRule> {
+fn get_rule(event: &KeyEvent) -> Option<Arc<KeyTransformRule>> {
     TRANSFOFM_MAP.with_borrow(|transform_map| {
-        transform_map
-            .as_ref()
-            .and_then(|map| map.get(&event.trigger).cloned())
+        let map = transform_map.as_ref()?;
+        map.get_shared(event).cloned().or_else(|| {
+            /* numpad keys with NUM_LOCK off fall back to the rules of the digits */
+            let digit = NORMALIZE_NUMPAD
+                .get()
+                .then(|| event.trigger.action.key.numpad_digit())
+                .flatten()?;
+            let mut normalized = event.clone();
+            normalized.trigger.action.key = digit;
+            map.get_shared(&normalized).cloned()
+        })
     })
 }
 
 #[inline(always)]
-fn apply_rule(rule: &KeyTransformRule) {
+fn apply_rule(rule: &KeyTransformRule, depth: u8) {
+    match &rule.target {
+        None => play_actions(&rule.actions, depth),
+        Some(condition) => match find_target_window(condition) {
+            Some(hwnd) => post_actions(&rule.actions, hwnd),
+            None => warn!("No target window matches `{}`", condition),
+        },
+    }
+}
+
+/// Posts the actions to the window. The keyboard state of the user does not apply to the
+/// window, so the modifiers and the locks are left as they are.
+fn post_actions(actions: &KeyActionSequence, hwnd: HWND) {
+    DELAYED_INPUT.with_borrow_mut(|delayed| {
+        let start = delayed.parts.len();
+        build_delayed_input(actions, INPUT_CHUNKING.get(), &mut delayed.parts);
+        for part in delayed.parts.range_mut(start..) {
+            part.target = Some(hwnd);
+        }
+    });
+    send_delayed_input();
+}
+
+/// Sends the actions marking the input with the number of expansions that produced it.
+fn play_actions(actions: &KeyActionSequence, depth: u8) {
+    let restore_input = build_locks_restore_input(actions, capture_locks());
+    let (release_input, released) = if RELEASE_MODIFIERS.get() {
+        build_modifiers_release_input(actions, KEYBOARD_STATE.get())
+    } else {
+        Default::default()
+    };
+    DELAYED_INPUT.with_borrow_mut(|delayed| {
+        let start = delayed.parts.len();
+        let chunking = INJECTION_MODE.get().chunking(INPUT_CHUNKING.get());
+        build_delayed_input(actions, chunking, &mut delayed.parts);
+        if let Some(first) = delayed.parts.get_mut(start) {
+            first.input.insert_many(0, release_input);
+        }
+        /* sent in the same call as the last part so that user input does not get in between */
+        if let Some(last) = delayed.parts.back_mut() {
+            last.input.extend(restore_input);
+            last.restore_modifiers = released;
+        }
+        for part in delayed.parts.range_mut(start..) {
+            set_injection_depth(&mut part.input, depth);
+        }
+    });
+    send_delayed_input();
+}
+
+/// Sends input parts until the one having delay. Then waits for the delay timer.
+fn send_delayed_input() {
+    loop {
+        let next = DELAYED_INPUT.with_borrow_mut(|delayed| {
+            if delayed.timer.is_some() {
+                return None;
+            }
+
+            let mut part = delayed.parts.pop_front()?;
+            if part.delay == 0 {
+                return Some(part);
+            }
+
+            let timer = unsafe { SetTimer(None, 0, part.delay, Some(delay_timer_proc)) };
+            if timer == 0 {
+                warn!("Failed to start delay timer");
+                return Some(part);
+            }
+
+            part.delay = 0;
+            delayed.parts.push_front(part);
+            delayed.timer = Some(timer);
+            None
+        });
+
+        match next {
+            Some(mut part) => {
+                if let Some(op) = part.clipboard {
+                    apply_clipboard_op(op);
+                }
+                if let Some(locale) = part.os_layout
+                    && let Err(e) = locale.activate()
+                {
+                    warn!("Failed to switch OS layout to `{}`: {}", locale, e);
+                }
+                if let Some(delay) = part.type_clipboard.take() {
+                    type_clipboard(part, delay);
+                    continue;
+                }
+                if let Some(hwnd) = part.target {
+                    post_input(hwnd, &part.input);
+                    continue;
+                }
+                if !part.restore_modifiers.is_empty() {
+                    /* the user may have released them while the sequence was delayed */
+                    part.input.extend(build_modifiers_restore_input(
+                        part.restore_modifiers,
+                        KEYBOARD_STATE.get(),
+                    ));
+                }
+                if !part.input.is_empty() {
+                    set_injection_mode(&mut part.input, INJECTION_MODE.get());
+                    send_input(&part.input);
+                }
+            }
+            None => break,
+        }
+    }
+}
+
+/// Queues the input typing the clipboard text followed by the rest of the part. The text is
+/// read only now so that the preceding actions may copy it.
+fn type_clipboard(mut part: InputPart, delay: u32) {
+    let text = read_clipboard_text().unwrap_or_else(|e| {
+        warn!("Failed to read clipboard text: {}", e);
+        String::new()
+    });
+    debug!("Typing {} clipboard characters", text.chars().count());
+
+    let mut parts = VecDeque::new();
+    let chunking = INJECTION_MODE.get().chunking(INPUT_CHUNKING.get());
+    build_typing_input(&text, delay, chunking, &mut parts);
+    for typing in parts.iter_mut() {
+        typing.target = part.target;
+    }
+    /* the clipboard operation and the layout switch are already done */
+    part.clipboard = None;
+    part.os_layout = None;
+    part.delay = if text.is_empty() { 0 } else { delay };
+    parts.push_back(part);
+
+    DELAYED_INPUT.with_borrow_mut(|delayed| {
+        while let Some(part) = parts.pop_back() {
+            delayed.parts.push_front(part);
+        }
+    });
+}
+
+/// Saves or restores the clipboard. Clipboard is not restored when it failed to save.
+fn apply_clipboard_op(op: ClipboardOp) {
+    match op {
+        ClipboardOp::Save => match ClipboardSnapshot::take() {
+            Ok(snapshot) => {
+                CLIPBOARD_SNAPSHOT.replace(Some(snapshot));
+            }
+            Err(e) => {
+                warn!("Failed to save clipboard: {}", e);
+                CLIPBOARD_SNAPSHOT.replace(None);
+            }
+        },
+        ClipboardOp::Restore => restore_clipboard(),
+    }
+}
+
+fn restore_clipboard() {
+    if let Some(snapshot) = CLIPBOARD_SNAPSHOT.take()
+        && let Err(e) = snapshot.restore()
+    {
+        warn!("Failed to restore clipboard: {}", e);
+    }
+}
+
+fn cancel_delayed_input() {
+    let timer = DELAYED_INPUT.with_borrow_mut(|delayed| {
+        delayed.parts.clear();
+        delayed.timer.take()
+    });
+    if let Some(timer) = timer {
+        kill_delay_timer(timer);
+        trace!("Delayed input cancelled");
+    }
+    /* clipboard scope interrupted by cancel is closed right away */
+    restore_clipboard();
+}
+
+fn kill_delay_timer(timer: usize) {
+    if let Err(e) = unsafe { KillTimer(None, timer) } {
+        warn!("Failed to stop delay timer: {}", e);
+    }
+}
+
+unsafe extern "system" fn delay_timer_proc(_hwnd: HWND, _msg: u32, timer: usize, _time: u32) {
+    kill_delay_timer(timer);
+    let is_current = DELAYED_INPUT.with_borrow_mut(|delayed| {
+        let is_current = delayed.timer == Some(timer);
+        if is_current {
+            delayed.timer = None;
+        }
+        is_current
+    });
+    if is_current {
+        send_delayed_input();
+    }
+}
+
+fn start_repeat(key: Key, rule: &KeyTransformRule, delay: u32, interval: u32, is_turbo: bool) {
+    stop_repeat();
+
+    let mut input = build_input(&rule.actions);
+    set_injection_mode(&mut input, INJECTION_MODE.get());
+    match RepeatScheduler::start(input, delay, interval) {
+        Ok(scheduler) => {
+            trace!("Repeat started for `{key}`");
+            ACTIVE_REPEAT.replace(Some(ActiveRepeat {
+                key,
+                is_turbo,
+                scheduler,
+            }));
+        }
+        Err(e) => warn!("Failed to start repeat: {}", e),
+    }
+}
+
+/// Stops repeat when repeating key is released or another key is pressed. Turbo keeps going
+/// until its own key is released.
+#[inline(always)]
+fn stop_repeat_on(action: &KeyAction) {
+    let is_stopping = ACTIVE_REPEAT.with_borrow(|repeat| {
+        repeat
+            .as_ref()
+            .is_some_and(|r| action.key == r.key || (action.transition == Down && !r.is_turbo))
+    });
+    if is_stopping {
+        stop_repeat();
+    }
+}
+
+fn stop_repeat() {
+    if let Some(repeat) = ACTIVE_REPEAT.take() {
+        let stats = repeat.scheduler.stats();
+        debug!("Repeat stopped for `{}`: {}", repeat.key, stats);
+        LAST_REPEAT_STATS.set(Some(stats));
+    }
+}
+
+#[inline(always)]
+pub(crate) fn send_input(input: &[INPUT]) {
     unsafe {
-        if SendInput(&build_input(&rule.actions), size_of::<INPUT>() as i32) == 0 {
-            warn!("Failed to send input: {:?}", GetLastError());
+        let sent = SendInput(input, size_of::<INPUT>() as i32) as usize;
+        track_input(&input[..sent]);
+        if sent < input.len() {
+            warn!(
+                "Failed to send input: {} of {} sent: {:?}",
+                sent,
+                input.len(),
+                GetLastError()
+            );
         }
     }
 }
 
 #[inline(always)]
 fn build_key_event(input: KBDLLHOOKSTRUCT) -> KeyEvent {
+    let timestamp = timestamp();
     let action = build_action_from_kbd_input(input);
+    let depth = injection_depth(input.dwExtraInfo);
+    let is_private = depth.is_some();
+    let is_injected = input.flags.contains(LLKHF_INJECTED);
+    let modifiers = prepare_kbd_state(&action);
+    let locks = capture_locks();
+    /* the other characters are resolved by the event consumers out of the hook */
+    let character = if action.key == Key::Packet {
+        CharResolver::resolve_packet(&action, input.scanCode as u16)
+    } else {
+        None
+    };
     KeyEvent {
         trigger: KeyTrigger {
             action,
-            modifiers: All(prepare_kbd_state(&action)),
+            modifiers: All(modifiers),
+            locks: Default::default(),
+            taps: 0,
+            is_alone: false,
         },
-        is_injected: input.flags.contains(LLKHF_INJECTED),
-        is_private: input.dwExtraInfo == PRIVATE_EVENT_MARKER,
+        locks,
+        taps: if_else(is_private, 1, track_taps(&action, input.time)),
+        is_alone: !is_private && track_alone(&action),
+        is_repeat: !is_private && track_repeat(&action),
+        is_injected,
+        is_private,
+        depth: depth.unwrap_or_default(),
         time: input.time,
+        timestamp,
+        device: if is_injected {
+            None
+        } else {
+            last_device((input.scanCode as u8, input.flags.contains(LLKHF_EXTENDED)))
+        },
+        window: FOREGROUND_WINDOW.with_borrow(Clone::clone),
+        character,
+    }
+}
+
+/// Checks the user input against the dead zone of the key switches.
+#[inline(always)]
+fn is_bounce(input: KBDLLHOOKSTRUCT) -> bool {
+    if input.flags.contains(LLKHF_INJECTED) {
+        return false;
+    }
+    let action = build_action_from_kbd_input(input);
+    let is_bounce = DEBOUNCE_FILTER.with_borrow_mut(|filter| filter.is_bounce(&action, input.time));
+    if is_bounce {
+        trace!("Key bounce ignored: {action}");
     }
+    is_bounce
 }
 
 #[inline(always)]
 fn build_mouse_event(msg: u32, input: MSLLHOOKSTRUCT) -> KeyEvent {
     let action = build_action_from_mouse_input(msg, input);
+    let depth = injection_depth(input.dwExtraInfo);
     KeyEvent {
         trigger: KeyTrigger {
             action,
             modifiers: All(prepare_kbd_state(&action)),
+            locks: Default::default(),
+            taps: 0,
+            is_alone: false,
         },
+        locks: capture_locks(),
+        taps: 1,
+        is_alone: depth.is_none() && track_alone(&action),
+        is_repeat: false,
         is_injected: (input.flags & (LLMHF_INJECTED | LLMHF_LOWER_IL_INJECTED)) != 0,
-        is_private: input.dwExtraInfo == PRIVATE_EVENT_MARKER,
+        is_private: depth.is_some(),
+        depth: depth.unwrap_or_default(),
         time: input.time,
+        timestamp: timestamp(),
+        device: None,
+        window: FOREGROUND_WINDOW.with_borrow(Clone::clone),
+        character: None,
     }
 }
 
 #[inline(always)]
-fn build_action_from_kbd_input(input: KBDLLHOOKSTRUCT) -> KeyAction {
-    KeyAction {
-        key: Key::from_code(
+pub(crate) fn build_action_from_kbd_input(input: KBDLLHOOKSTRUCT) -> KeyAction {
+    /* unicode characters injected with a packet carry the character in the scan code */
+    let key = if input.vkCode == VK_PACKET.0 as u32 {
+        Key::Packet
+    } else {
+        Key::from_code(
             input.vkCode as u8,
             input.scanCode as u8,
             input.flags.contains(LLKHF_EXTENDED),
-        ),
+        )
+    };
+
+    KeyAction {
+        key,
         transition: if_else(input.flags.contains(LLKHF_UP), Up, Down),
     }
 }
@@ -281,17 +1167,68 @@ fn build_mouse_x_button_key(input: MSLLHOOKSTRUCT) -> Key {
     }
 }
 
+#[inline(always)]
+fn capture_locks() -> KeyLocks {
+    KeyLocks::capture(|key| unsafe { GetKeyState(key.vk() as i32) & 1 != 0 })
+}
+
+#[inline(always)]
+fn track_taps(action: &KeyAction, time: u32) -> u8 {
+    TAP_TRACKER.with_borrow_mut(|tracker| tracker.track(action, time))
+}
+
+#[inline(always)]
+fn track_alone(action: &KeyAction) -> bool {
+    ALONE_TRACKER.with_borrow_mut(|tracker| tracker.track(action))
+}
+
+#[inline(always)]
+fn track_repeat(action: &KeyAction) -> bool {
+    let mut pressed = PRESSED_KEYS.get();
+    let is_repeat = action.transition == Down && pressed.contains(action.key);
+    pressed.update(action);
+    PRESSED_KEYS.set(pressed);
+    is_repeat
+}
+
 #[inline(always)]
 fn prepare_kbd_state(action: &KeyAction) -> KeyboardState {
     let mut state = KEYBOARD_STATE.get();
-    state.remove(&action);
+    state.remove(action);
     KEYBOARD_STATE.set(state);
     state
 }
 
 #[inline(always)]
 fn update_kbd_state(action: &KeyAction) {
+    if action.key.is_wheel() {
+        return;
+    }
+
     let mut state = KEYBOARD_STATE.get();
     state.update(action);
     KEYBOARD_STATE.set(state);
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::hook::HookRetry;
+
+    #[test]
+    fn test_hook_retry_delays() {
+        let retry = HookRetry::default();
+        assert_eq!(vec![100, 200, 400], retry.delays().collect::<Vec<_>>());
+
+        let retry = HookRetry {
+            attempts: 1,
+            delay: 100,
+        };
+        assert_eq!(None, retry.delays().next());
+
+        let retry = HookRetry {
+            attempts: 0,
+            delay: 100,
+        };
+        assert_eq!(None, retry.delays().next());
+    }
+}
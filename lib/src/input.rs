@@ -1,19 +1,393 @@
-use crate::action::{KeyAction, KeyActionSequence};
+use crate::action::{KeyAction, KeyActionSequence, KeySequenceItem};
+use crate::hook::{InjectionMode, InputChunking};
 use crate::key::Key;
+use crate::modifiers::{KeyLocks, LOCK_KEYS};
+use crate::os_layout::LayoutLocale;
+use crate::state::KeyboardState;
 use crate::transition::KeyTransition::{Down, Up};
+use smallvec::SmallVec;
+use std::collections::VecDeque;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
 use windows::Win32::UI::Input::KeyboardAndMouse::{
     INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY,
-    KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
+    KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE, KEYBD_EVENT_FLAGS, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
     MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP,
     MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT, MOUSE_EVENT_FLAGS,
-    VIRTUAL_KEY,
+    VIRTUAL_KEY, VK_LMENU, VK_MENU, VK_RMENU,
 };
-use windows::Win32::UI::WindowsAndMessaging::{XBUTTON1, XBUTTON2};
+use windows::Win32::UI::WindowsAndMessaging::{
+    WM_CHAR, WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP, XBUTTON1, XBUTTON2,
+};
+
+/// Virtual key not assigned to anything.
+const MASK_KEY_VK: u16 = 0xE8;
+
+/// Low bits of the marker carry the injection depth.
+const DEPTH_MASK: usize = 0xF;
+const MARKER_BASE: usize = 497298395 & !DEPTH_MASK;
+
+/// Deepest rule expansion the marker can carry.
+pub(crate) const MAX_INJECTION_DEPTH: u8 = DEPTH_MASK as u8;
+
+/// Marks the input sent by the hook. Input of the rules applied to the user input is
+/// one expansion deep.
+pub(crate) static PRIVATE_EVENT_MARKER: usize = private_event_marker(1);
+
+pub(crate) const fn private_event_marker(depth: u8) -> usize {
+    MARKER_BASE | (depth as usize & DEPTH_MASK)
+}
+
+/// Marks the input sent by the test harness. The hook processes it like the user input.
+#[cfg(any(test, feature = "test_support"))]
+pub(crate) const TEST_EVENT_MARKER: usize = MARKER_BASE + DEPTH_MASK + 1;
+
+/// Returns the injection depth of the event sent by the hook or `None` for other events.
+pub(crate) fn injection_depth(extra_info: usize) -> Option<u8> {
+    (extra_info & !DEPTH_MASK == MARKER_BASE).then_some((extra_info & DEPTH_MASK) as u8)
+}
+
+/// Marks the input with the injection depth.
+pub(crate) fn set_injection_depth(input: &mut [INPUT], depth: u8) {
+    for item in input {
+        match item.r#type {
+            INPUT_KEYBOARD => item.Anonymous.ki.dwExtraInfo = private_event_marker(depth),
+            INPUT_MOUSE => item.Anonymous.mi.dwExtraInfo = private_event_marker(depth),
+            _ => {}
+        }
+    }
+}
 
-pub(crate) static PRIVATE_EVENT_MARKER: usize = 497298395;
+/// Returns `true` if the event was sent by the test harness.
+#[cfg(any(test, feature = "test_support"))]
+pub(crate) fn is_test_input(extra_info: usize) -> bool {
+    extra_info == TEST_EVENT_MARKER
+}
+
+/// Marks the input as sent by the test harness.
+#[cfg(any(test, feature = "test_support"))]
+pub(crate) fn set_test_marker(input: &mut [INPUT]) {
+    for item in input {
+        match item.r#type {
+            INPUT_KEYBOARD => item.Anonymous.ki.dwExtraInfo = TEST_EVENT_MARKER,
+            INPUT_MOUSE => item.Anonymous.mi.dwExtraInfo = TEST_EVENT_MARKER,
+            _ => {}
+        }
+    }
+}
+
+/// Leaves only the key codes sent in the injection mode. Unicode and mask key input is kept.
+pub(crate) fn set_injection_mode(input: &mut [INPUT], mode: InjectionMode) {
+    for item in input {
+        if item.r#type != INPUT_KEYBOARD {
+            continue;
+        }
+
+        let ki = unsafe { &mut item.Anonymous.ki };
+        if !ki.dwFlags.contains(KEYEVENTF_SCANCODE) {
+            continue;
+        }
+
+        match mode {
+            InjectionMode::Both => {}
+            InjectionMode::Scancode => ki.wVk = VIRTUAL_KEY(0),
+            InjectionMode::Vk => ki.dwFlags &= !KEYEVENTF_SCANCODE,
+        }
+    }
+}
+
+/// Input of the sequence part. Typical sequences fit in without allocation.
+pub(crate) type InputBuffer = SmallVec<[INPUT; 8]>;
 
 pub(crate) fn build_input(seq: &KeyActionSequence) -> Vec<INPUT> {
-    seq.iter().filter_map(build_action_input).collect()
+    seq.actions().filter_map(build_action_input).collect()
+}
+
+/// Time in milliseconds the target application gets to read the clipboard before it is restored.
+pub(crate) const CLIPBOARD_RESTORE_DELAY: u32 = 100;
+
+/// Clipboard operation performed before the input of the part is sent.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ClipboardOp {
+    Save,
+    Restore,
+}
+
+/// Part of the sequence input sent after the delay.
+#[derive(Default)]
+pub(crate) struct InputPart {
+    pub(crate) delay: u32,
+    pub(crate) clipboard: Option<ClipboardOp>,
+    /// Input language switched after the clipboard operation.
+    pub(crate) os_layout: Option<LayoutLocale>,
+    /// Clipboard text typed before the input with the pause after each character.
+    pub(crate) type_clipboard: Option<u32>,
+    pub(crate) input: InputBuffer,
+    /// Modifier keys pressed again after the input when the user still holds them.
+    pub(crate) restore_modifiers: KeyboardState,
+    /// Window the input is posted to instead of being sent to the system input queue.
+    pub(crate) target: Option<HWND>,
+}
+
+impl InputPart {
+    fn new(delay: u32, clipboard: Option<ClipboardOp>) -> Self {
+        Self {
+            delay,
+            clipboard,
+            os_layout: None,
+            type_clipboard: None,
+            input: InputBuffer::new(),
+            restore_modifiers: KeyboardState::default(),
+            target: None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.clipboard.is_none()
+            && self.os_layout.is_none()
+            && self.type_clipboard.is_none()
+            && self.input.is_empty()
+    }
+}
+
+/// Builds input split into parts by the sequence delays, clipboard scopes, OS layout switches
+/// and the chunking. The parts are appended to the queue so that its buffer is reused.
+pub(crate) fn build_delayed_input(
+    seq: &KeyActionSequence,
+    chunking: InputChunking,
+    parts: &mut VecDeque<InputPart>,
+) {
+    parts.push_back(InputPart::default());
+    for item in seq.iter() {
+        let last = parts.back_mut().unwrap();
+        match item {
+            KeySequenceItem::Action(action) => {
+                if let Some(input) = build_action_input(action) {
+                    if chunking.size > 0 && last.input.len() >= chunking.size {
+                        let mut part = InputPart::new(chunking.delay, None);
+                        part.input.push(input);
+                        parts.push_back(part);
+                    } else {
+                        last.input.push(input);
+                    }
+                }
+            }
+            KeySequenceItem::Delay(delay) => {
+                if last.is_empty() {
+                    last.delay += delay
+                } else {
+                    parts.push_back(InputPart::new(*delay, None))
+                }
+            }
+            KeySequenceItem::SaveClipboard => {
+                if last.is_empty() {
+                    last.clipboard = Some(ClipboardOp::Save)
+                } else {
+                    parts.push_back(InputPart::new(0, Some(ClipboardOp::Save)))
+                }
+            }
+            KeySequenceItem::RestoreClipboard => {
+                if last.is_empty() {
+                    last.delay = last.delay.max(CLIPBOARD_RESTORE_DELAY);
+                    last.clipboard = Some(ClipboardOp::Restore)
+                } else {
+                    parts.push_back(InputPart::new(
+                        CLIPBOARD_RESTORE_DELAY,
+                        Some(ClipboardOp::Restore),
+                    ))
+                }
+            }
+            KeySequenceItem::OsLayout(locale) => {
+                if last.os_layout.is_none()
+                    && last.type_clipboard.is_none()
+                    && last.input.is_empty()
+                {
+                    last.os_layout = Some(*locale)
+                } else {
+                    parts.push_back(InputPart {
+                        os_layout: Some(*locale),
+                        ..Default::default()
+                    })
+                }
+            }
+            KeySequenceItem::TypeClipboard(delay) => {
+                if last.is_empty() {
+                    last.type_clipboard = Some(*delay)
+                } else {
+                    parts.push_back(InputPart {
+                        type_clipboard: Some(*delay),
+                        ..Default::default()
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Builds input typing the text as unicode characters. Line breaks and tabs are typed as
+/// `ENTER` and `TAB` since the applications do not take them as characters. The characters
+/// are split into parts by the pause after each one, or by the chunking when there is none.
+pub(crate) fn build_typing_input(
+    text: &str,
+    delay: u32,
+    chunking: InputChunking,
+    parts: &mut VecDeque<InputPart>,
+) {
+    let (size, delay) = if delay > 0 {
+        (1, delay)
+    } else {
+        (chunking.size, chunking.delay)
+    };
+
+    parts.push_back(InputPart::default());
+    let mut count = 0;
+    for ch in text.chars().filter(|&ch| ch != '\r') {
+        if size > 0 && count == size {
+            parts.push_back(InputPart::new(delay, None));
+            count = 0;
+        }
+        let last = parts.back_mut().unwrap();
+        match ch {
+            '\n' => last.input.extend(build_key_tap_input(Key::Enter)),
+            '\t' => last.input.extend(build_key_tap_input(Key::Tab)),
+            _ => last.input.extend(build_unicode_input(ch)),
+        }
+        count += 1;
+    }
+}
+
+fn build_key_tap_input(key: Key) -> impl Iterator<Item = INPUT> {
+    [Down, Up]
+        .into_iter()
+        .filter_map(move |transition| build_action_input(&KeyAction::new(key, transition)))
+}
+
+/// Builds input returning the lock keys to the state captured before the sequence. Only the
+/// locks toggled along with other keys are restored, like `NUM_LOCK` for Alt-code entry.
+/// Sequences of lock keys alone toggle them on purpose.
+pub(crate) fn build_locks_restore_input(seq: &KeyActionSequence, locks: KeyLocks) -> InputBuffer {
+    if seq.actions().all(|action| LOCK_KEYS.contains(&action.key)) {
+        return InputBuffer::new();
+    }
+
+    let toggled = locks.toggled_by(seq.actions());
+    LOCK_KEYS
+        .iter()
+        .filter(|&&key| toggled.get(key) != locks.get(key))
+        .flat_map(|&key| [KeyAction::new(key, Down), KeyAction::new(key, Up)])
+        .filter_map(|action| build_key_input(&action))
+        .collect()
+}
+
+/// Builds input releasing the modifier keys held by the user so that they do not modify the
+/// sequence keys. Modifiers the sequence presses or releases itself are left as is.
+/// Returns the input and the released modifiers.
+pub(crate) fn build_modifiers_release_input(
+    seq: &KeyActionSequence,
+    held: KeyboardState,
+) -> (InputBuffer, KeyboardState) {
+    let mut released = KeyboardState::default();
+    for key in held.keys() {
+        if key.is_modifier() && !seq.actions().any(|action| action.key == key) {
+            released.update(&KeyAction::new(key, Down));
+        }
+    }
+
+    let mut input = InputBuffer::new();
+    /* tapping unassigned key keeps released Alt or Win from activating the menu */
+    if [Key::LeftAlt, Key::RightAlt, Key::LeftWin, Key::RightWin]
+        .iter()
+        .any(|&key| released.contains(key))
+    {
+        input.push(build_mask_key_input(KEYBD_EVENT_FLAGS(0)));
+        input.push(build_mask_key_input(KEYEVENTF_KEYUP));
+    }
+    input.extend(
+        released
+            .keys()
+            .filter_map(|key| build_key_input(&KeyAction::new(key, Up))),
+    );
+    (input, released)
+}
+
+/// Builds input pressing the released modifier keys the user still holds.
+pub(crate) fn build_modifiers_restore_input(
+    released: KeyboardState,
+    held: KeyboardState,
+) -> InputBuffer {
+    released
+        .keys()
+        .filter(|&key| held.contains(key))
+        .filter_map(|key| build_key_input(&KeyAction::new(key, Down)))
+        .collect()
+}
+
+fn build_mask_key_input(flags: KEYBD_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(MASK_KEY_VK),
+                dwFlags: flags,
+                dwExtraInfo: PRIVATE_EVENT_MARKER,
+                ..Default::default()
+            },
+        },
+    }
+}
+
+pub(crate) fn build_unicode_input(ch: char) -> Vec<INPUT> {
+    let mut buffer = [0u16; 2];
+    let mut inputs = Vec::with_capacity(4);
+    for unit in ch.encode_utf16(&mut buffer).iter() {
+        inputs.push(build_unicode_unit_input(*unit, KEYEVENTF_UNICODE));
+        inputs.push(build_unicode_unit_input(*unit, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP));
+    }
+    inputs
+}
+
+fn build_unicode_unit_input(unit: u16, flags: KEYBD_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wScan: unit,
+                dwFlags: flags,
+                dwExtraInfo: PRIVATE_EVENT_MARKER,
+                ..Default::default()
+            },
+        },
+    }
+}
+
+/// Builds the window message posting the keyboard input: `WM_KEYDOWN` or `WM_KEYUP` for
+/// the keys and `WM_CHAR` for the unicode characters. Mouse input has no message.
+pub(crate) fn build_window_message(input: &INPUT) -> Option<(u32, WPARAM, LPARAM)> {
+    if input.r#type != INPUT_KEYBOARD {
+        return None;
+    }
+
+    let ki = unsafe { input.Anonymous.ki };
+    let is_up = ki.dwFlags.contains(KEYEVENTF_KEYUP);
+    if ki.dwFlags.contains(KEYEVENTF_UNICODE) {
+        return (!is_up).then_some((WM_CHAR, WPARAM(ki.wScan as usize), LPARAM(1)));
+    }
+
+    let is_alt = matches!(ki.wVk, VK_MENU | VK_LMENU | VK_RMENU);
+    let message = match (is_alt, is_up) {
+        (false, false) => WM_KEYDOWN,
+        (false, true) => WM_KEYUP,
+        (true, false) => WM_SYSKEYDOWN,
+        (true, true) => WM_SYSKEYUP,
+    };
+    /* repeat count, scan code, extended key flag, previous state and transition state */
+    let mut l_param = 1 | ((ki.wScan as isize & 0xFF) << 16);
+    if ki.dwFlags.contains(KEYEVENTF_EXTENDEDKEY) {
+        l_param |= 1 << 24;
+    }
+    if is_up {
+        l_param |= 0b11 << 30;
+    }
+    Some((message, WPARAM(ki.wVk.0 as usize), LPARAM(l_param)))
 }
 
 fn build_action_input(action: &KeyAction) -> Option<INPUT> {
@@ -24,12 +398,10 @@ fn build_action_input(action: &KeyAction) -> Option<INPUT> {
 }
 
 fn build_mouse_wheel_input(action: &KeyAction) -> Option<INPUT> {
-    if action.key == Key::WheelX {
-        build_mouse_input(MOUSEEVENTF_WHEEL, 0)
-    } else if action.key == Key::WheelY {
+    if matches!(action.key, Key::WheelX | Key::WheelY) {
         build_mouse_input(MOUSEEVENTF_WHEEL, 0)
     } else {
-        return None;
+        None
     }
 }
 
@@ -120,15 +492,31 @@ fn build_key_input(action: &KeyAction) -> Option<INPUT> {
 
 #[cfg(test)]
 mod tests {
-    use crate::action::KeyAction;
-    use crate::input::{build_action_input, build_key_input, PRIVATE_EVENT_MARKER};
-    use crate::key_action;
+    use crate::action::{KeyAction, KeyActionSequence};
+    use crate::alloc_count::allocations_in;
+    use crate::hook::{InjectionMode, InputChunking};
+    use crate::input::ClipboardOp::{Restore, Save};
+    use crate::input::{
+        build_action_input, build_delayed_input, build_key_input, build_locks_restore_input,
+        build_input, build_modifiers_release_input, build_modifiers_restore_input, build_typing_input, build_unicode_input, build_window_message,
+        injection_depth, is_test_input, set_injection_depth, set_injection_mode, set_test_marker,
+        InputPart, CLIPBOARD_RESTORE_DELAY, PRIVATE_EVENT_MARKER, TEST_EVENT_MARKER,
+    };
+    use crate::key::Key;
     use crate::key_code::ext_scan_code;
+    use crate::modifiers::KeyLocks;
+    use crate::os_layout::LayoutLocale;
+    use crate::state::KeyboardState;
+    use crate::{key_action, key_action_seq};
+    use std::collections::VecDeque;
     use std::str::FromStr;
     use windows::Win32::UI::Input::KeyboardAndMouse::{
         INPUT, INPUT_KEYBOARD, INPUT_MOUSE, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP,
-        KEYEVENTF_SCANCODE, MOUSEEVENTF_WHEEL, VK_RETURN,
+        KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE, MOUSEEVENTF_WHEEL, VIRTUAL_KEY, VK_LCONTROL,
+        VK_LMENU, VK_LSHIFT, VK_NUMLOCK, VK_RETURN,
     };
+    use windows::Win32::Foundation::{LPARAM, WPARAM};
+    use windows::Win32::UI::WindowsAndMessaging::{WM_CHAR, WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN};
 
     #[test]
     fn test_build_key_input() {
@@ -154,6 +542,68 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_injection_depth() {
+        assert_eq!(Some(1), injection_depth(PRIVATE_EVENT_MARKER));
+        assert_eq!(None, injection_depth(0));
+        assert_eq!(None, injection_depth(PRIVATE_EVENT_MARKER + 0x10));
+
+        let mut input = build_input(&key_action_seq!("A↓ → WHEEL_Y↓"));
+        set_injection_depth(&mut input, 3);
+        unsafe {
+            assert_eq!(Some(3), injection_depth(input[0].Anonymous.ki.dwExtraInfo));
+            assert_eq!(Some(3), injection_depth(input[1].Anonymous.mi.dwExtraInfo));
+        }
+    }
+
+    #[test]
+    fn test_test_marker() {
+        assert_eq!(None, injection_depth(TEST_EVENT_MARKER));
+        assert!(!is_test_input(PRIVATE_EVENT_MARKER));
+        assert!(!is_test_input(0));
+
+        let mut input = build_input(&key_action_seq!("A↓ → WHEEL_Y↓"));
+        set_test_marker(&mut input);
+        unsafe {
+            assert!(is_test_input(input[0].Anonymous.ki.dwExtraInfo));
+            assert!(is_test_input(input[1].Anonymous.mi.dwExtraInfo));
+        }
+    }
+
+    #[test]
+    fn test_injection_mode() {
+        let mut input = build_input(&key_action_seq!("NUM_ENTER↓ → WHEEL_Y↓"));
+        input.extend(build_unicode_input('A'));
+
+        set_injection_mode(&mut input, InjectionMode::Both);
+        unsafe {
+            assert_eq!(VK_RETURN, input[0].Anonymous.ki.wVk);
+            assert_eq!(
+                KEYEVENTF_SCANCODE | KEYEVENTF_EXTENDEDKEY,
+                input[0].Anonymous.ki.dwFlags
+            );
+        }
+
+        let mut scancode_input = input.clone();
+        set_injection_mode(&mut scancode_input, InjectionMode::Scancode);
+        unsafe {
+            assert_eq!(VIRTUAL_KEY(0), scancode_input[0].Anonymous.ki.wVk);
+            assert_eq!(
+                ext_scan_code(0x1C, true),
+                scancode_input[0].Anonymous.ki.wScan
+            );
+            assert_eq!(MOUSEEVENTF_WHEEL, scancode_input[1].Anonymous.mi.dwFlags);
+            assert_eq!(KEYEVENTF_UNICODE, scancode_input[2].Anonymous.ki.dwFlags);
+        }
+
+        set_injection_mode(&mut input, InjectionMode::Vk);
+        unsafe {
+            assert_eq!(VK_RETURN, input[0].Anonymous.ki.wVk);
+            assert_eq!(KEYEVENTF_EXTENDEDKEY, input[0].Anonymous.ki.dwFlags);
+            assert_eq!(KEYEVENTF_UNICODE, input[2].Anonymous.ki.dwFlags);
+        }
+    }
+
     #[test]
     fn test_build_mouse_wheel_input() {
         let actual: INPUT = build_action_input(&key_action!("WHEEL_Y*")).unwrap();
@@ -176,4 +626,280 @@ mod tests {
             assert_eq!(PRIVATE_EVENT_MARKER, actual.Anonymous.mi.dwExtraInfo);
         };
     }
+
+    #[test]
+    fn test_build_window_message() {
+        let input = build_input(&key_action_seq!(
+            "NUM_ENTER↓ → NUM_ENTER↑ → LEFT_ALT↓ → WHEEL_Y↓"
+        ));
+
+        assert_eq!(
+            Some((WM_KEYDOWN, WPARAM(VK_RETURN.0 as usize), LPARAM(0x011C0001))),
+            build_window_message(&input[0])
+        );
+        assert_eq!(
+            Some((WM_KEYUP, WPARAM(VK_RETURN.0 as usize), LPARAM(0xC11C0001))),
+            build_window_message(&input[1])
+        );
+        assert_eq!(
+            Some((WM_SYSKEYDOWN, WPARAM(VK_LMENU.0 as usize), LPARAM(0x00380001))),
+            build_window_message(&input[2])
+        );
+        assert_eq!(None, build_window_message(&input[3]));
+
+        let input = build_unicode_input('Я');
+        assert_eq!(
+            Some((WM_CHAR, WPARAM('Я' as usize), LPARAM(1))),
+            build_window_message(&input[0])
+        );
+        assert_eq!(None, build_window_message(&input[1]));
+    }
+
+    fn delayed_input(seq: &KeyActionSequence) -> VecDeque<InputPart> {
+        let mut parts = VecDeque::new();
+        build_delayed_input(seq, InputChunking::default(), &mut parts);
+        parts
+    }
+
+    #[test]
+    fn test_build_delayed_input() {
+        let actual = delayed_input(&key_action_seq!(
+            "DELAY(10) → A↓ → DELAY(20) → DELAY(30) → A↑ → B↓"
+        ));
+
+        assert_eq!(
+            vec![(10, 1), (50, 2)],
+            actual
+                .iter()
+                .map(|part| (part.delay, part.input.len()))
+                .collect::<Vec<_>>()
+        );
+
+        let actual = delayed_input(&key_action_seq!("A↓ → A↑"));
+        assert_eq!(1, actual.len());
+        assert_eq!(0, actual[0].delay);
+    }
+
+    #[test]
+    fn test_build_delayed_input_no_allocations() {
+        let seq = key_action_seq!("LEFT_CTRL↓ → C↓ → C↑ → LEFT_CTRL↑ → DELAY(10) → V↓ → V↑");
+        let mut parts = VecDeque::new();
+
+        /* the queue grows once and then reuses its buffer */
+        build_delayed_input(&seq, InputChunking::default(), &mut parts);
+        parts.clear();
+
+        let allocations = allocations_in(|| {
+            build_delayed_input(&seq, InputChunking::default(), &mut parts);
+            while parts.pop_front().is_some() {}
+        });
+        assert_eq!(0, allocations);
+    }
+
+    #[test]
+    fn test_build_delayed_input_chunking() {
+        let seq = key_action_seq!("A↓ → A↑ → B↓ → B↑ → DELAY(10) → C↓ → C↑ → D↓");
+        let chunking = InputChunking { size: 3, delay: 5 };
+        let mut parts = VecDeque::new();
+        build_delayed_input(&seq, chunking, &mut parts);
+
+        assert_eq!(
+            vec![(0, 3), (5, 1), (10, 3)],
+            parts
+                .iter()
+                .map(|part| (part.delay, part.input.len()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_build_delayed_input_preserve_clipboard() {
+        let actual = delayed_input(&key_action_seq!(
+            "A↓ → PRESERVE_CLIPBOARD { V↓ → V↑ } → B↓ → PRESERVE_CLIPBOARD { DELAY(500) }"
+        ));
+
+        assert_eq!(
+            vec![
+                (0, None, 1),
+                (0, Some(Save), 2),
+                (CLIPBOARD_RESTORE_DELAY, Some(Restore), 1),
+                (0, Some(Save), 0),
+                (500, Some(Restore), 0)
+            ],
+            actual
+                .iter()
+                .map(|part| (part.delay, part.clipboard, part.input.len()))
+                .collect::<Vec<_>>()
+        );
+
+        let actual = delayed_input(&key_action_seq!("PRESERVE_CLIPBOARD { V↓ }"));
+        assert_eq!(2, actual.len());
+        assert_eq!(Some(Save), actual[0].clipboard);
+    }
+
+    #[test]
+    fn test_build_delayed_input_os_layout() {
+        let actual = delayed_input(&key_action_seq!(
+            "OS_LAYOUT(en-US) → A↓ → OS_LAYOUT(ru-RU) → OS_LAYOUT(de-DE) → B↓"
+        ));
+
+        assert_eq!(
+            vec![
+                (Some(LayoutLocale::new("en-US").unwrap()), 1),
+                (Some(LayoutLocale::new("ru-RU").unwrap()), 0),
+                (Some(LayoutLocale::new("de-DE").unwrap()), 1)
+            ],
+            actual
+                .iter()
+                .map(|part| (part.os_layout, part.input.len()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_build_delayed_input_type_clipboard() {
+        let actual = delayed_input(&key_action_seq!(
+            "A↓ → TYPE_CLIPBOARD(10) → B↓ → OS_LAYOUT(en-US) → TYPE_CLIPBOARD"
+        ));
+
+        assert_eq!(
+            vec![(None, 1), (Some(10), 1), (None, 0), (Some(0), 0)],
+            actual
+                .iter()
+                .map(|part| (part.type_clipboard, part.input.len()))
+                .collect::<Vec<_>>()
+        );
+        assert!(actual[2].os_layout.is_some());
+    }
+
+    #[test]
+    fn test_build_typing_input() {
+        let mut parts = VecDeque::new();
+        build_typing_input("ab\r\nc", 20, InputChunking::default(), &mut parts);
+
+        assert_eq!(
+            vec![(0, 2), (20, 2), (20, 2), (20, 2)],
+            parts
+                .iter()
+                .map(|part| (part.delay, part.input.len()))
+                .collect::<Vec<_>>()
+        );
+        unsafe {
+            assert_eq!(KEYEVENTF_UNICODE, parts[0].input[0].Anonymous.ki.dwFlags);
+            assert_eq!(VK_RETURN, parts[2].input[0].Anonymous.ki.wVk);
+        }
+
+        let mut parts = VecDeque::new();
+        let chunking = InputChunking { size: 2, delay: 5 };
+        build_typing_input("abc", 0, chunking, &mut parts);
+
+        assert_eq!(
+            vec![(0, 4), (5, 2)],
+            parts
+                .iter()
+                .map(|part| (part.delay, part.input.len()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_build_locks_restore_input() {
+        let locks = KeyLocks::capture(|k| k == Key::NumLock);
+
+        let actual = build_locks_restore_input(
+            &key_action_seq!("NUM_LOCK → LEFT_ALT↓ → NUM_0 → NUM_1 → LEFT_ALT↑"),
+            locks,
+        );
+        assert_eq!(2, actual.len());
+        unsafe {
+            assert_eq!(VK_NUMLOCK, actual[0].Anonymous.ki.wVk);
+            assert_eq!(
+                KEYEVENTF_SCANCODE | KEYEVENTF_EXTENDEDKEY,
+                actual[0].Anonymous.ki.dwFlags
+            );
+            assert_eq!(VK_NUMLOCK, actual[1].Anonymous.ki.wVk);
+            assert_eq!(
+                KEYEVENTF_SCANCODE | KEYEVENTF_EXTENDEDKEY | KEYEVENTF_KEYUP,
+                actual[1].Anonymous.ki.dwFlags
+            );
+        };
+
+        assert!(
+            build_locks_restore_input(&key_action_seq!("NUM_LOCK → A → NUM_LOCK"), locks)
+                .is_empty()
+        );
+        assert!(build_locks_restore_input(&key_action_seq!("CAPS_LOCK"), locks).is_empty());
+        assert!(build_locks_restore_input(&key_action_seq!("A → B"), locks).is_empty());
+    }
+
+    #[test]
+    fn test_build_modifiers_release_input() {
+        let held = KeyboardState::from_str("LEFT_CTRL + LEFT_SHIFT + A").unwrap();
+
+        let (input, released) = build_modifiers_release_input(&key_action_seq!("DOWN"), held);
+        assert_eq!(
+            KeyboardState::from_str("LEFT_CTRL + LEFT_SHIFT").unwrap(),
+            released
+        );
+        assert_eq!(2, input.len());
+        unsafe {
+            assert_eq!(VK_LSHIFT, input[0].Anonymous.ki.wVk);
+            assert_eq!(VK_LCONTROL, input[1].Anonymous.ki.wVk);
+            assert!(input[1].Anonymous.ki.dwFlags.contains(KEYEVENTF_KEYUP));
+        };
+
+        let (input, released) =
+            build_modifiers_release_input(&key_action_seq!("LEFT_SHIFT↑ → DOWN"), held);
+        assert_eq!(KeyboardState::from_str("LEFT_CTRL").unwrap(), released);
+        assert_eq!(1, input.len());
+
+        let (input, _) = build_modifiers_release_input(
+            &key_action_seq!("DOWN"),
+            KeyboardState::from_str("LEFT_ALT").unwrap(),
+        );
+        assert_eq!(3, input.len());
+        unsafe {
+            assert_eq!(VIRTUAL_KEY(0xE8), input[0].Anonymous.ki.wVk);
+            assert_eq!(VIRTUAL_KEY(0xE8), input[1].Anonymous.ki.wVk);
+            assert_eq!(VK_LMENU, input[2].Anonymous.ki.wVk);
+        };
+    }
+
+    #[test]
+    fn test_build_modifiers_restore_input() {
+        let released = KeyboardState::from_str("LEFT_CTRL + LEFT_SHIFT").unwrap();
+
+        let input =
+            build_modifiers_restore_input(released, KeyboardState::from_str("LEFT_CTRL").unwrap());
+        assert_eq!(1, input.len());
+        unsafe {
+            assert_eq!(VK_LCONTROL, input[0].Anonymous.ki.wVk);
+            assert!(!input[0].Anonymous.ki.dwFlags.contains(KEYEVENTF_KEYUP));
+        };
+
+        assert!(build_modifiers_restore_input(released, KeyboardState::default()).is_empty());
+    }
+
+    #[test]
+    fn test_build_unicode_input() {
+        let actual = build_unicode_input('é');
+        assert_eq!(2, actual.len());
+        unsafe {
+            assert_eq!(INPUT_KEYBOARD, actual[0].r#type);
+            assert_eq!(0xE9, actual[0].Anonymous.ki.wScan);
+            assert_eq!(KEYEVENTF_UNICODE, actual[0].Anonymous.ki.dwFlags);
+            assert_eq!(
+                KEYEVENTF_UNICODE | KEYEVENTF_KEYUP,
+                actual[1].Anonymous.ki.dwFlags
+            );
+            assert_eq!(PRIVATE_EVENT_MARKER, actual[1].Anonymous.ki.dwExtraInfo);
+        };
+
+        let actual = build_unicode_input('😂');
+        assert_eq!(4, actual.len());
+        unsafe {
+            assert_eq!(0xD83D, actual[0].Anonymous.ki.wScan);
+            assert_eq!(0xDE02, actual[2].Anonymous.ki.wScan);
+        };
+    }
 }
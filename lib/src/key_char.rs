@@ -0,0 +1,143 @@
+use std::fmt::{Debug, Display, Formatter, Write};
+use std::ops::Deref;
+
+/* UTF-16 units the layout can produce for a key */
+pub(crate) const MAX_UNITS: usize = 8;
+/* UTF-8 takes up to 3 bytes per UTF-16 unit */
+const KEY_TEXT_CAPACITY: usize = MAX_UNITS * 3;
+
+/// Character produced by the key press in the active keyboard layout.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KeyChar {
+    Text(KeyText),
+    /// Dead key. Its character is composed with the next key.
+    Dead(char),
+    /// Text composed of the preceding dead key and this key.
+    Composed {
+        dead: char,
+        text: KeyText,
+    },
+}
+
+/// Text of the key press stored inline so that resolving it does not allocate.
+#[derive(Clone, Copy, Default, Eq, PartialEq)]
+pub struct KeyText {
+    bytes: [u8; KEY_TEXT_CAPACITY],
+    len: u8,
+}
+
+impl KeyText {
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap()
+    }
+
+    /// Appends the character unless the text is full.
+    fn push(&mut self, ch: char) {
+        let start = self.len as usize;
+        if start + ch.len_utf8() <= KEY_TEXT_CAPACITY {
+            self.len += ch.encode_utf8(&mut self.bytes[start..]).len() as u8;
+        }
+    }
+
+    #[cfg(any(test, feature = "win32"))]
+    pub(crate) fn from_utf16_lossy(units: &[u16]) -> Self {
+        char::decode_utf16(units.iter().copied())
+            .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    }
+}
+
+impl Deref for KeyText {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl FromIterator<char> for KeyText {
+    fn from_iter<T: IntoIterator<Item = char>>(iter: T) -> Self {
+        let mut text = Self::default();
+        for ch in iter {
+            text.push(ch);
+        }
+        text
+    }
+}
+
+impl From<&str> for KeyText {
+    fn from(s: &str) -> Self {
+        s.chars().collect()
+    }
+}
+
+impl Debug for KeyText {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Display for KeyText {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl Display for KeyChar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut s = String::new();
+        match self {
+            Self::Text(text) => write_escaped(&mut s, text)?,
+            Self::Dead(dead) => write!(s, "{dead} (dead)")?,
+            Self::Composed { dead, text } => {
+                write!(s, "{dead} → ")?;
+                write_escaped(&mut s, text)?;
+            }
+        }
+        f.pad(&s)
+    }
+}
+
+/// Control characters like `\r` are written escaped.
+fn write_escaped(s: &mut String, text: &str) -> std::fmt::Result {
+    for ch in text.chars() {
+        if ch.is_control() {
+            write!(s, "{}", ch.escape_default())?;
+        } else {
+            s.write_char(ch)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key_char::{KeyChar, KeyText};
+
+    #[test]
+    fn test_key_text() {
+        assert_eq!("á", KeyText::from_utf16_lossy(&[0xE1]).as_str());
+        assert_eq!("😀", KeyText::from_utf16_lossy(&[0xD83D, 0xDE00]).as_str());
+        assert_eq!("\u{FFFD}", KeyText::from_utf16_lossy(&[0xD83D]).as_str());
+        assert_eq!(
+            "€".repeat(8),
+            KeyText::from_utf16_lossy(&[0x20AC; 8]).as_str()
+        );
+    }
+
+    #[test]
+    fn test_key_char_display() {
+        assert_eq!("a", KeyChar::Text("a".into()).to_string());
+        assert_eq!("\\r", KeyChar::Text("\r".into()).to_string());
+        assert_eq!("^ (dead)", KeyChar::Dead('^').to_string());
+        assert_eq!(
+            "^ → ê",
+            KeyChar::Composed {
+                dead: '^',
+                text: "ê".into()
+            }
+            .to_string()
+        );
+        assert_eq!("[   a]", format!("[{:>4}]", KeyChar::Text("a".into())));
+    }
+}
@@ -0,0 +1,407 @@
+use crate::key::Key;
+use crate::key_code::virtual_key_name;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static CURRENT_THEME: AtomicU8 = AtomicU8::new(KeyNameTheme::Default as u8);
+
+/// Naming convention used to write key names. Names of all themes are accepted by the parser.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyNameTheme {
+    /// Own names like `LEFT_CTRL`.
+    #[default]
+    Default,
+    /// QMK firmware keycodes like `KC_LCTL`.
+    Qmk,
+    /// Windows virtual key names like `VK_LCONTROL`.
+    Vk,
+}
+
+impl KeyNameTheme {
+    /// Returns the theme used to write key names.
+    pub fn current() -> Self {
+        match CURRENT_THEME.load(Ordering::Relaxed) {
+            1 => Self::Qmk,
+            2 => Self::Vk,
+            _ => Self::Default,
+        }
+    }
+
+    /// Sets the theme used to write key names.
+    pub fn set_current(theme: Self) {
+        CURRENT_THEME.store(theme as u8, Ordering::Relaxed);
+    }
+
+    /// Returns the name of the key in this theme. Keys which have no distinct name
+    /// in the theme are named as in the default one.
+    pub fn key_name(&self, key: Key) -> &'static str {
+        let name = match self {
+            Self::Default => None,
+            Self::Qmk => QMK_NAMES
+                .iter()
+                .find(|(_, k)| *k == key)
+                .map(|(name, _)| *name),
+            Self::Vk => vk_name(key),
+        };
+        name.unwrap_or(key.as_str())
+    }
+}
+
+/// Returns the key by its alias, QMK or virtual key name.
+pub(crate) fn key_by_alias(name: &str) -> Option<Key> {
+    if name.starts_with("KC_") {
+        QMK_NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, key)| *key)
+    } else if name.starts_with("VK_") {
+        (0..=u8::MAX)
+            .filter_map(Key::from_index)
+            .find(|&key| vk_name(key) == Some(name))
+    } else {
+        ALIASES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, key)| *key)
+    }
+}
+
+/// Returns the names the parser accepts for the key besides its own one.
+pub fn key_aliases(key: Key) -> Vec<&'static str> {
+    QMK_NAMES
+        .iter()
+        .chain(ALIASES)
+        .filter(|(_, k)| *k == key)
+        .map(|(name, _)| *name)
+        .chain(vk_name(key))
+        .collect()
+}
+
+/// Virtual key name if it denotes the key unambiguously.
+fn vk_name(key: Key) -> Option<&'static str> {
+    /* several keys share some virtual key codes (e.g. ENTER and NUM_ENTER) */
+    if key == Key::Unassigned || Key::from_index(key.vk()) != Some(key) {
+        return None;
+    }
+    Some(virtual_key_name(key.vk())).filter(|name| name.starts_with("VK_"))
+}
+
+/* alternative names accepted by the parser, keys are always written by their own names */
+static ALIASES: &[(&str, Key)] = &[
+    ("RETURN", Key::Enter),
+    ("⏎", Key::Enter),
+    ("↵", Key::Enter),
+    ("ESCAPE", Key::Esc),
+    ("⎋", Key::Esc),
+    ("BKSP", Key::Backspace),
+    ("⌫", Key::Backspace),
+    ("⇥", Key::Tab),
+    ("␣", Key::Space),
+    ("DEL", Key::Delete),
+    ("⌦", Key::Delete),
+    ("INS", Key::Insert),
+    ("PGUP", Key::PageUp),
+    ("PGDN", Key::PageDown),
+    ("UP_ARROW", Key::Up),
+    ("DOWN_ARROW", Key::Down),
+    ("LEFT_ARROW", Key::Left),
+    ("RIGHT_ARROW", Key::Right),
+    ("CAPS", Key::CapsLock),
+    ("⇪", Key::CapsLock),
+    ("APPS", Key::Application),
+    ("MENU_KEY", Key::Application),
+    ("PRTSC", Key::PrintScreen),
+    ("GRAVE", Key::Backtick),
+    ("QUOTE", Key::Apostrophe),
+    ("EQUAL", Key::Eq),
+    ("PERIOD", Key::Dot),
+];
+
+/* the first name of a key is the one written, the rest are accepted aliases */
+static QMK_NAMES: &[(&str, Key)] = &[
+    ("KC_A", Key::A),
+    ("KC_B", Key::B),
+    ("KC_C", Key::C),
+    ("KC_D", Key::D),
+    ("KC_E", Key::E),
+    ("KC_F", Key::F),
+    ("KC_G", Key::G),
+    ("KC_H", Key::H),
+    ("KC_I", Key::I),
+    ("KC_J", Key::J),
+    ("KC_K", Key::K),
+    ("KC_L", Key::L),
+    ("KC_M", Key::M),
+    ("KC_N", Key::N),
+    ("KC_O", Key::O),
+    ("KC_P", Key::P),
+    ("KC_Q", Key::Q),
+    ("KC_R", Key::R),
+    ("KC_S", Key::S),
+    ("KC_T", Key::T),
+    ("KC_U", Key::U),
+    ("KC_V", Key::V),
+    ("KC_W", Key::W),
+    ("KC_X", Key::X),
+    ("KC_Y", Key::Y),
+    ("KC_Z", Key::Z),
+    ("KC_1", Key::Digit1),
+    ("KC_2", Key::Digit2),
+    ("KC_3", Key::Digit3),
+    ("KC_4", Key::Digit4),
+    ("KC_5", Key::Digit5),
+    ("KC_6", Key::Digit6),
+    ("KC_7", Key::Digit7),
+    ("KC_8", Key::Digit8),
+    ("KC_9", Key::Digit9),
+    ("KC_0", Key::Digit0),
+    ("KC_ENT", Key::Enter),
+    ("KC_ENTER", Key::Enter),
+    ("KC_ESC", Key::Esc),
+    ("KC_ESCAPE", Key::Esc),
+    ("KC_BSPC", Key::Backspace),
+    ("KC_BACKSPACE", Key::Backspace),
+    ("KC_TAB", Key::Tab),
+    ("KC_SPC", Key::Space),
+    ("KC_SPACE", Key::Space),
+    ("KC_MINS", Key::Minus),
+    ("KC_MINUS", Key::Minus),
+    ("KC_EQL", Key::Eq),
+    ("KC_EQUAL", Key::Eq),
+    ("KC_LBRC", Key::LeftBracket),
+    ("KC_LEFT_BRACKET", Key::LeftBracket),
+    ("KC_RBRC", Key::RightBracket),
+    ("KC_RIGHT_BRACKET", Key::RightBracket),
+    ("KC_BSLS", Key::Backslash),
+    ("KC_BACKSLASH", Key::Backslash),
+    ("KC_NUBS", Key::Backslash2),
+    ("KC_NONUS_BACKSLASH", Key::Backslash2),
+    ("KC_SCLN", Key::Semicolon),
+    ("KC_SEMICOLON", Key::Semicolon),
+    ("KC_QUOT", Key::Apostrophe),
+    ("KC_QUOTE", Key::Apostrophe),
+    ("KC_GRV", Key::Backtick),
+    ("KC_GRAVE", Key::Backtick),
+    ("KC_COMM", Key::Comma),
+    ("KC_COMMA", Key::Comma),
+    ("KC_DOT", Key::Dot),
+    ("KC_SLSH", Key::Slash),
+    ("KC_SLASH", Key::Slash),
+    ("KC_CAPS", Key::CapsLock),
+    ("KC_CAPS_LOCK", Key::CapsLock),
+    ("KC_F1", Key::F1),
+    ("KC_F2", Key::F2),
+    ("KC_F3", Key::F3),
+    ("KC_F4", Key::F4),
+    ("KC_F5", Key::F5),
+    ("KC_F6", Key::F6),
+    ("KC_F7", Key::F7),
+    ("KC_F8", Key::F8),
+    ("KC_F9", Key::F9),
+    ("KC_F10", Key::F10),
+    ("KC_F11", Key::F11),
+    ("KC_F12", Key::F12),
+    ("KC_F13", Key::F13),
+    ("KC_F14", Key::F14),
+    ("KC_F15", Key::F15),
+    ("KC_F16", Key::F16),
+    ("KC_F17", Key::F17),
+    ("KC_F18", Key::F18),
+    ("KC_F19", Key::F19),
+    ("KC_F20", Key::F20),
+    ("KC_F21", Key::F21),
+    ("KC_F22", Key::F22),
+    ("KC_F23", Key::F23),
+    ("KC_F24", Key::F24),
+    ("KC_PSCR", Key::PrintScreen),
+    ("KC_PRINT_SCREEN", Key::PrintScreen),
+    ("KC_SCRL", Key::ScrollLock),
+    ("KC_SCROLL_LOCK", Key::ScrollLock),
+    ("KC_PAUS", Key::Pause),
+    ("KC_PAUSE", Key::Pause),
+    ("KC_INS", Key::Insert),
+    ("KC_INSERT", Key::Insert),
+    ("KC_HOME", Key::Home),
+    ("KC_PGUP", Key::PageUp),
+    ("KC_PAGE_UP", Key::PageUp),
+    ("KC_DEL", Key::Delete),
+    ("KC_DELETE", Key::Delete),
+    ("KC_END", Key::End),
+    ("KC_PGDN", Key::PageDown),
+    ("KC_PAGE_DOWN", Key::PageDown),
+    ("KC_RGHT", Key::Right),
+    ("KC_RIGHT", Key::Right),
+    ("KC_LEFT", Key::Left),
+    ("KC_DOWN", Key::Down),
+    ("KC_UP", Key::Up),
+    ("KC_NUM", Key::NumLock),
+    ("KC_NUM_LOCK", Key::NumLock),
+    ("KC_PSLS", Key::NumDiv),
+    ("KC_KP_SLASH", Key::NumDiv),
+    ("KC_PAST", Key::NumMul),
+    ("KC_KP_ASTERISK", Key::NumMul),
+    ("KC_PMNS", Key::NumMinus),
+    ("KC_KP_MINUS", Key::NumMinus),
+    ("KC_PPLS", Key::NumPlus),
+    ("KC_KP_PLUS", Key::NumPlus),
+    ("KC_PENT", Key::NumEnter),
+    ("KC_KP_ENTER", Key::NumEnter),
+    ("KC_P1", Key::Num1),
+    ("KC_KP_1", Key::Num1),
+    ("KC_P2", Key::Num2),
+    ("KC_KP_2", Key::Num2),
+    ("KC_P3", Key::Num3),
+    ("KC_KP_3", Key::Num3),
+    ("KC_P4", Key::Num4),
+    ("KC_KP_4", Key::Num4),
+    ("KC_P5", Key::Num5),
+    ("KC_KP_5", Key::Num5),
+    ("KC_P6", Key::Num6),
+    ("KC_KP_6", Key::Num6),
+    ("KC_P7", Key::Num7),
+    ("KC_KP_7", Key::Num7),
+    ("KC_P8", Key::Num8),
+    ("KC_KP_8", Key::Num8),
+    ("KC_P9", Key::Num9),
+    ("KC_KP_9", Key::Num9),
+    ("KC_P0", Key::Num0),
+    ("KC_KP_0", Key::Num0),
+    ("KC_PDOT", Key::NumDot),
+    ("KC_KP_DOT", Key::NumDot),
+    ("KC_APP", Key::Application),
+    ("KC_APPLICATION", Key::Application),
+    ("KC_LCTL", Key::LeftCtrl),
+    ("KC_LEFT_CTRL", Key::LeftCtrl),
+    ("KC_LSFT", Key::LeftShift),
+    ("KC_LEFT_SHIFT", Key::LeftShift),
+    ("KC_LALT", Key::LeftAlt),
+    ("KC_LEFT_ALT", Key::LeftAlt),
+    ("KC_LGUI", Key::LeftWin),
+    ("KC_LEFT_GUI", Key::LeftWin),
+    ("KC_RCTL", Key::RightCtrl),
+    ("KC_RIGHT_CTRL", Key::RightCtrl),
+    ("KC_RSFT", Key::RightShift),
+    ("KC_RIGHT_SHIFT", Key::RightShift),
+    ("KC_RALT", Key::RightAlt),
+    ("KC_RIGHT_ALT", Key::RightAlt),
+    ("KC_RGUI", Key::RightWin),
+    ("KC_RIGHT_GUI", Key::RightWin),
+    ("KC_MUTE", Key::VolumeMute),
+    ("KC_AUDIO_MUTE", Key::VolumeMute),
+    ("KC_VOLU", Key::VolumeUp),
+    ("KC_AUDIO_VOL_UP", Key::VolumeUp),
+    ("KC_VOLD", Key::VolumeDown),
+    ("KC_AUDIO_VOL_DOWN", Key::VolumeDown),
+    ("KC_MNXT", Key::MediaNextTrack),
+    ("KC_MEDIA_NEXT_TRACK", Key::MediaNextTrack),
+    ("KC_MPRV", Key::MediaPrevTrack),
+    ("KC_MEDIA_PREV_TRACK", Key::MediaPrevTrack),
+    ("KC_MSTP", Key::MediaStop),
+    ("KC_MEDIA_STOP", Key::MediaStop),
+    ("KC_MPLY", Key::MediaPlayPause),
+    ("KC_MEDIA_PLAY_PAUSE", Key::MediaPlayPause),
+    ("KC_MAIL", Key::LaunchMail),
+    ("KC_MSEL", Key::LaunchMediaSelect),
+    ("KC_MEDIA_SELECT", Key::LaunchMediaSelect),
+    ("KC_WSCH", Key::BrowserSearch),
+    ("KC_WWW_SEARCH", Key::BrowserSearch),
+    ("KC_WHOM", Key::BrowserHome),
+    ("KC_WWW_HOME", Key::BrowserHome),
+    ("KC_WBAK", Key::BrowserBack),
+    ("KC_WWW_BACK", Key::BrowserBack),
+    ("KC_WFWD", Key::BrowserForward),
+    ("KC_WWW_FORWARD", Key::BrowserForward),
+    ("KC_WSTP", Key::BrowserStop),
+    ("KC_WWW_STOP", Key::BrowserStop),
+    ("KC_WREF", Key::BrowserRefresh),
+    ("KC_WWW_REFRESH", Key::BrowserRefresh),
+    ("KC_WFAV", Key::BrowserFavorites),
+    ("KC_WWW_FAVORITES", Key::BrowserFavorites),
+    ("KC_SLEP", Key::Sleep),
+    ("KC_SYSTEM_SLEEP", Key::Sleep),
+    ("KC_EXEC", Key::Execute),
+    ("KC_EXECUTE", Key::Execute),
+    ("KC_HELP", Key::Help),
+    ("KC_SLCT", Key::Select),
+    ("KC_SELECT", Key::Select),
+    ("KC_INT1", Key::Ro),
+    ("KC_INTERNATIONAL_1", Key::Ro),
+    ("KC_INT3", Key::Yen),
+    ("KC_INTERNATIONAL_3", Key::Yen),
+    ("KC_BTN1", Key::LeftButton),
+    ("KC_MS_BTN1", Key::LeftButton),
+    ("KC_BTN2", Key::RightButton),
+    ("KC_MS_BTN2", Key::RightButton),
+    ("KC_BTN3", Key::MiddleButton),
+    ("KC_MS_BTN3", Key::MiddleButton),
+    ("KC_BTN4", Key::Xbutton1),
+    ("KC_MS_BTN4", Key::Xbutton1),
+    ("KC_BTN5", Key::Xbutton2),
+    ("KC_MS_BTN5", Key::Xbutton2),
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::key::Key;
+    use crate::key_name::{KeyNameTheme, key_aliases, key_by_alias};
+
+    #[test]
+    fn test_key_name() {
+        assert_eq!("LEFT_CTRL", KeyNameTheme::Default.key_name(Key::LeftCtrl));
+        assert_eq!("KC_LCTL", KeyNameTheme::Qmk.key_name(Key::LeftCtrl));
+        assert_eq!("VK_LCONTROL", KeyNameTheme::Vk.key_name(Key::LeftCtrl));
+        assert_eq!("VK_A", KeyNameTheme::Vk.key_name(Key::A));
+    }
+
+    #[test]
+    fn test_key_name_fallback() {
+        assert_eq!("WHEEL_X", KeyNameTheme::Qmk.key_name(Key::WheelX));
+        assert_eq!("NUM_ENTER", KeyNameTheme::Vk.key_name(Key::NumEnter));
+        assert_eq!("<ESC>", KeyNameTheme::Vk.key_name(Key::_Esc_));
+    }
+
+    #[test]
+    fn test_key_by_alias() {
+        assert_eq!(Some(Key::LeftCtrl), key_by_alias("KC_LCTL"));
+        assert_eq!(Some(Key::LeftCtrl), key_by_alias("KC_LEFT_CTRL"));
+        assert_eq!(Some(Key::Enter), key_by_alias("VK_RETURN"));
+        assert_eq!(Some(Key::A), key_by_alias("VK_A"));
+        assert_eq!(None, key_by_alias("KC_NOPE"));
+        assert_eq!(Some(Key::Enter), key_by_alias("RETURN"));
+        assert_eq!(Some(Key::Enter), key_by_alias("⏎"));
+        assert_eq!(Some(Key::Up), key_by_alias("UP_ARROW"));
+        assert_eq!(None, key_by_alias("NOPE"));
+        assert_eq!(None, key_by_alias("LEFT_CTRL"));
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for theme in [KeyNameTheme::Default, KeyNameTheme::Qmk, KeyNameTheme::Vk] {
+            for key in (0..=u8::MAX).filter_map(Key::from_index) {
+                assert_eq!(
+                    Some(key),
+                    Key::from_str(theme.key_name(key)),
+                    "{:?} {:?}",
+                    theme,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_key_aliases() {
+        assert_eq!(
+            vec!["KC_ENT", "KC_ENTER", "RETURN", "⏎", "↵", "VK_RETURN"],
+            key_aliases(Key::Enter)
+        );
+        assert!(key_aliases(Key::LeftCtrl).contains(&"VK_LCONTROL"));
+        assert!(
+            key_aliases(Key::NumEnter)
+                .iter()
+                .all(|name| !name.starts_with("VK_"))
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::key::Key;
+use crate::key_name::key_aliases;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Row of the key table exported for the tools reading the rules on the other platforms,
+/// where the virtual keys and scan codes mean nothing and the HID usages identify the keys.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct KeyTableEntry {
+    pub name: &'static str,
+    /// Other names accepted by the parser.
+    pub aliases: Vec<&'static str>,
+    pub vk: u8,
+    /// Scan code with `0xE0` prefix in the high byte for the extended keys.
+    pub sc: u16,
+    /// USB HID usage page. Zero if the key has no usage.
+    pub hid_page: u16,
+    /// USB HID usage ID within the page.
+    pub hid_usage: u16,
+}
+
+impl From<Key> for KeyTableEntry {
+    fn from(key: Key) -> Self {
+        Self {
+            name: key.as_str(),
+            aliases: key_aliases(key),
+            vk: key.vk(),
+            sc: key.sc_ext(),
+            hid_page: (key.hid_usage() >> 16) as u16,
+            hid_usage: key.hid_usage() as u16,
+        }
+    }
+}
+
+/// Returns the entries of all keys except `UNASSIGNED` in the order of their indices.
+pub fn key_table() -> Vec<KeyTableEntry> {
+    (1..=u8::MAX)
+        .filter_map(Key::from_index)
+        .map(KeyTableEntry::from)
+        .collect()
+}
+
+pub fn key_table_json() -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(&key_table())
+}
+
+/// Returns the table as a Rust constant to be included into the other projects.
+pub fn key_table_rust() -> String {
+    let mut s = String::new();
+    s.push_str("/// Name, virtual key, scan code, HID usage page and HID usage ID of the keys.\n");
+    s.push_str("pub const KEYS: &[(&str, u8, u16, u16, u16)] = &[\n");
+    for entry in key_table() {
+        writeln!(
+            s,
+            "    ({:?}, 0x{:02X}, 0x{:04X}, 0x{:02X}, 0x{:04X}),",
+            entry.name, entry.vk, entry.sc, entry.hid_page, entry.hid_usage
+        )
+        .unwrap();
+    }
+    s.push_str("];\n");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key::Key;
+    use crate::key_table::{KeyTableEntry, key_table, key_table_json, key_table_rust};
+
+    #[test]
+    fn test_key_table_entry() {
+        assert_eq!(
+            KeyTableEntry {
+                name: "RIGHT_CTRL",
+                aliases: vec!["KC_RCTL", "KC_RIGHT_CTRL", "VK_RCONTROL"],
+                vk: 0xA3,
+                sc: 0xE01D,
+                hid_page: 0x07,
+                hid_usage: 0xE4,
+            },
+            KeyTableEntry::from(Key::RightCtrl)
+        );
+    }
+
+    #[test]
+    fn test_key_table() {
+        let table = key_table();
+
+        assert_eq!(Some("LEFT_BUTTON"), table.first().map(|e| e.name));
+        assert!(table.iter().all(|e| e.name != "UNASSIGNED"));
+        assert!(table.iter().any(|e| e.name == "FN_LAUNCH_APP2"));
+    }
+
+    #[test]
+    fn test_key_table_json() {
+        let json: serde_json::Value = serde_json::from_str(&key_table_json().unwrap()).unwrap();
+        let a = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["name"] == "A")
+            .unwrap();
+
+        assert_eq!(0x41, a["vk"]);
+        assert_eq!(0x1E, a["sc"]);
+        assert_eq!(0x07, a["hid_page"]);
+        assert_eq!(0x04, a["hid_usage"]);
+    }
+
+    #[test]
+    fn test_key_table_rust() {
+        let code = key_table_rust();
+
+        assert!(code.starts_with("/// Name, virtual key"));
+        assert!(code.contains("\n    (\"A\", 0x41, 0x001E, 0x07, 0x0004),\n"));
+        assert!(code.contains("\n    (\"<ESC>\", 0x00, 0xE001, 0x00, 0x0000),\n"));
+        assert!(code.ends_with("];\n"));
+    }
+}
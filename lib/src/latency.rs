@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+/// Number of the latest events the percentiles are calculated over.
+pub const LATENCY_SAMPLES: usize = 1024;
+
+static FREQUENCY: OnceLock<u64> = OnceLock::new();
+
+/// Returns the current value of the high-resolution performance counter.
+pub fn timestamp() -> u64 {
+    let mut counter = 0;
+    unsafe { QueryPerformanceCounter(&mut counter) }.ok();
+    counter as u64
+}
+
+/// Returns microseconds passed since the timestamp.
+pub fn elapsed_micros(timestamp: u64) -> u32 {
+    ticks_to_micros(self::timestamp().saturating_sub(timestamp), frequency())
+}
+
+fn frequency() -> u64 {
+    *FREQUENCY.get_or_init(|| {
+        let mut frequency = 0;
+        unsafe { QueryPerformanceFrequency(&mut frequency) }.ok();
+        (frequency as u64).max(1)
+    })
+}
+
+fn ticks_to_micros(ticks: u64, frequency: u64) -> u32 {
+    (ticks as u128 * 1_000_000 / frequency as u128).min(u32::MAX as u128) as u32
+}
+
+/// Processing latencies of the latest events in microseconds.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyStats {
+    samples: VecDeque<u32>,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, micros: u32) {
+        if self.samples.len() >= LATENCY_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(micros);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns the latency not exceeded by the percentage of the events, `None` if no
+    /// events were processed yet.
+    pub fn percentile(&self, percent: f64) -> Option<u32> {
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        percentile_of(&sorted, percent)
+    }
+
+    pub fn percentiles(&self) -> Option<LatencyPercentiles> {
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        Some(LatencyPercentiles {
+            p50: percentile_of(&sorted, 50.0)?,
+            p90: percentile_of(&sorted, 90.0)?,
+            p99: percentile_of(&sorted, 99.0)?,
+            max: *sorted.last()?,
+        })
+    }
+}
+
+fn percentile_of(sorted: &[u32], percent: f64) -> Option<u32> {
+    let last = sorted.len().checked_sub(1)?;
+    let index = (percent.clamp(0.0, 100.0) / 100.0 * last as f64).round() as usize;
+    Some(sorted[index])
+}
+
+/// Summary of the processing latencies in microseconds.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LatencyPercentiles {
+    pub p50: u32,
+    pub p90: u32,
+    pub p99: u32,
+    pub max: u32,
+}
+
+impl Display for LatencyPercentiles {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "p50: {} µs, p90: {} µs, p99: {} µs, max: {} µs",
+            self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::latency::{LATENCY_SAMPLES, LatencyPercentiles, LatencyStats, ticks_to_micros};
+
+    #[test]
+    fn test_ticks_to_micros() {
+        assert_eq!(1, ticks_to_micros(10, 10_000_000));
+        assert_eq!(1500, ticks_to_micros(15_000, 10_000_000));
+        assert_eq!(u32::MAX, ticks_to_micros(u64::MAX, 1));
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut stats = LatencyStats::default();
+        assert_eq!(None, stats.percentiles());
+
+        for micros in (1..=100).rev() {
+            stats.record(micros);
+        }
+
+        assert_eq!(Some(1), stats.percentile(0.0));
+        assert_eq!(
+            Some(LatencyPercentiles {
+                p50: 51,
+                p90: 90,
+                p99: 99,
+                max: 100,
+            }),
+            stats.percentiles()
+        );
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut stats = LatencyStats::default();
+        stats.record(1000);
+        for _ in 0..LATENCY_SAMPLES {
+            stats.record(10);
+        }
+
+        assert_eq!(LATENCY_SAMPLES, stats.len());
+        assert_eq!(Some(10), stats.percentile(100.0));
+    }
+}
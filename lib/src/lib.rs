@@ -1,15 +1,82 @@
+//! Key rules parsing and the Windows keyboard hook applying them.
+//!
+//! The model of the keys, actions and rules is platform independent. The hook, input injection
+//! and the other Win32 services are built with the `win32` feature enabled by default, so that
+//! the rules can be parsed and validated on the other platforms with
+//! `default-features = false`.
+
 pub mod action;
+pub mod alloc_count;
+pub mod audit;
+pub mod builder;
+#[cfg(feature = "win32")]
+pub mod char_resolver;
+pub mod client;
+#[cfg(feature = "win32")]
+mod clipboard;
+#[cfg(any(test, feature = "win32"))]
+mod code_point;
+pub mod compose;
+#[cfg(feature = "win32")]
+pub mod crash;
+pub mod debounce;
+#[cfg(feature = "win32")]
+mod device;
+#[cfg(feature = "win32")]
+pub mod engine;
 pub mod error;
 pub mod event;
+pub mod event_log;
+pub mod explain;
+pub mod failsafe;
+#[cfg(feature = "win32")]
+pub mod health;
+#[cfg(feature = "win32")]
 pub mod hook;
+#[cfg(feature = "win32")]
 mod input;
 pub mod key;
+pub mod key_char;
 pub mod key_code;
+pub mod key_name;
+pub mod key_table;
+#[cfg(feature = "win32")]
+pub mod latency;
+pub mod lint;
 pub mod modifiers;
 pub mod notify;
+pub mod os_layout;
+#[cfg(any(test, feature = "win32"))]
+mod pairing;
+#[cfg(any(test, feature = "win32"))]
+mod pending;
+pub mod physical;
+pub mod presets;
+pub mod recorder;
+pub mod repeat;
 pub mod rule;
+pub mod scancode_map;
+#[cfg(feature = "win32")]
+pub mod scheduler;
+#[cfg(feature = "win32")]
+pub mod session;
+pub mod stats;
 mod state;
-mod transform;
+pub mod subscription;
+pub mod system_hotkeys;
+#[cfg(any(test, feature = "win32"))]
+mod tap;
+#[cfg(feature = "win32")]
+mod target;
+#[cfg(all(feature = "win32", any(test, feature = "test_support")))]
+pub mod test_support;
+pub mod trace;
+pub mod transform;
 pub mod transition;
 pub mod trigger;
 pub mod utils;
+pub mod window;
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: alloc_count::CountingAllocator = alloc_count::CountingAllocator;
@@ -0,0 +1,409 @@
+use crate::action::KeySequenceItem;
+use crate::key::Key;
+use crate::rule::{KeyTransformRule, KeyTransformRules};
+use crate::state::KeyboardState;
+use crate::system_hotkeys::{
+    SYSTEM_HOTKEYS, SystemHotkey, SystemHotkeyKind, find_hotkey_conflicts,
+};
+use crate::transition::KeyTransition::{Down, Up};
+use crate::utils::if_else;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintSeverity {
+    Info,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// Rule is never applied because a later rule of the same or higher priority or an earlier
+    /// rule of higher priority has the same trigger.
+    Overridden,
+    /// Rule may match the same events as an earlier rule of the same specificity and priority,
+    /// so it is applied only when the earlier one does not match.
+    OrderDependent,
+    /// Output sequence presses the key again before releasing it.
+    RepeatedPress(Key),
+    /// Rule outputs exactly its trigger action.
+    SelfMapping,
+    /// Trigger of the rule is the hotkey of the system or of another application.
+    SystemHotkey(SystemHotkey),
+    /// Trigger is the top row digit key while the numpad key of the same digit is not bound.
+    TopRowDigit(Key),
+}
+
+impl LintKind {
+    pub fn severity(&self) -> LintSeverity {
+        match self {
+            LintKind::Overridden => LintSeverity::Warning,
+            LintKind::OrderDependent => LintSeverity::Info,
+            LintKind::RepeatedPress(_) => LintSeverity::Warning,
+            LintKind::SelfMapping => LintSeverity::Info,
+            LintKind::SystemHotkey(hotkey) => match hotkey.kind {
+                SystemHotkeyKind::Shell => LintSeverity::Info,
+                SystemHotkeyKind::Reserved | SystemHotkeyKind::Registered => LintSeverity::Warning,
+            },
+            LintKind::TopRowDigit(_) => LintSeverity::Info,
+        }
+    }
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LintKind::Overridden => write!(f, "Rule is overridden by a rule with the same trigger"),
+            LintKind::OrderDependent => {
+                write!(
+                    f,
+                    "Rule overlaps an earlier rule of the same specificity and priority"
+                )
+            }
+            LintKind::RepeatedPress(key) => {
+                write!(f, "Key `{key}` is pressed twice without release")
+            }
+            LintKind::SelfMapping => write!(f, "Rule maps the key to itself"),
+            LintKind::SystemHotkey(hotkey) => match hotkey.kind {
+                SystemHotkeyKind::Reserved => {
+                    write!(
+                        f,
+                        "Trigger is the system hotkey `{hotkey}` the hook cannot intercept"
+                    )
+                }
+                SystemHotkeyKind::Shell => write!(f, "Rule overrides the shell hotkey `{hotkey}`"),
+                SystemHotkeyKind::Registered => {
+                    write!(f, "Rule takes over the hotkey `{hotkey}`")
+                }
+            },
+            LintKind::TopRowDigit(key) => write!(
+                f,
+                "Trigger is the top row `{0}` only, `NUM_{0}` is the numpad one and `ANY_{0}` \
+                 is both",
+                key.as_str()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintIssue {
+    pub kind: LintKind,
+    pub rule: KeyTransformRule,
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?}: {}: `{}`",
+            self.kind.severity(),
+            self.kind,
+            self.rule
+        )
+    }
+}
+
+/// Checks rules for mistakes that do not prevent them from loading.
+///
+/// Among the rules matching an event the ones with the exact modifiers go first, then the ones
+/// having more conditions, then the ones of higher priority, then the earlier ones.
+pub fn lint_rules(rules: &KeyTransformRules) -> Vec<LintIssue> {
+    let rules: Vec<_> = rules.iter().collect();
+    let mut issues = vec![];
+
+    for (index, rule) in rules.iter().enumerate() {
+        let mut push = |kind| {
+            issues.push(LintIssue {
+                kind,
+                rule: (*rule).clone(),
+            })
+        };
+
+        if rules.iter().enumerate().any(|(i, r)| {
+            i != index
+                && has_same_conditions(r, rule)
+                && if_else(
+                    i > index,
+                    r.priority >= rule.priority,
+                    r.priority > rule.priority,
+                )
+        }) {
+            push(LintKind::Overridden);
+        } else if rules[..index].iter().any(|r| {
+            r.trigger.action == rule.trigger.action
+                && r.trigger.modifiers == rule.trigger.modifiers
+                && r.conditions_count() == rule.conditions_count()
+                && r.priority == rule.priority
+                && !has_same_conditions(r, rule)
+        }) {
+            push(LintKind::OrderDependent);
+        }
+
+        if let Some(key) = find_repeated_press(rule) {
+            push(LintKind::RepeatedPress(key));
+        }
+
+        let items: Vec<_> = rule.actions.iter().collect();
+        if let [KeySequenceItem::Action(action)] = items.as_slice()
+            && *action == rule.trigger.action
+        {
+            push(LintKind::SelfMapping);
+        }
+
+        for hotkey in find_hotkey_conflicts(rule, SYSTEM_HOTKEYS) {
+            push(LintKind::SystemHotkey(*hotkey));
+        }
+
+        if let Some(numpad) = rule.trigger.action.key.numpad_counterpart()
+            && !rules.iter().any(|r| {
+                r.trigger.action.key == numpad
+                    && r.trigger.action.transition == rule.trigger.action.transition
+                    && r.trigger.modifiers == rule.trigger.modifiers
+            })
+        {
+            push(LintKind::TopRowDigit(rule.trigger.action.key));
+        }
+    }
+
+    issues
+}
+
+/// Reports the rules triggered by the hotkeys, like the ones found by
+/// [`probe_registered_hotkeys`](crate::system_hotkeys::probe_registered_hotkeys). The known
+/// system hotkeys are reported by [`lint_rules`].
+pub fn lint_hotkeys(rules: &KeyTransformRules, hotkeys: &[SystemHotkey]) -> Vec<LintIssue> {
+    rules
+        .iter()
+        .flat_map(|rule| {
+            find_hotkey_conflicts(rule, hotkeys).map(|hotkey| LintIssue {
+                kind: LintKind::SystemHotkey(*hotkey),
+                rule: rule.clone(),
+            })
+        })
+        .collect()
+}
+
+fn has_same_conditions(a: &KeyTransformRule, b: &KeyTransformRule) -> bool {
+    a.trigger == b.trigger && a.device == b.device && a.window == b.window
+}
+
+fn find_repeated_press(rule: &KeyTransformRule) -> Option<Key> {
+    let mut pressed = KeyboardState::default();
+    for action in rule.actions.actions() {
+        match action.transition {
+            Down if pressed.contains(action.key) => return Some(action.key),
+            Down | Up => pressed.update(action),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key::Key;
+    use crate::lint::LintKind::{
+        OrderDependent, Overridden, RepeatedPress, SelfMapping, SystemHotkey, TopRowDigit,
+    };
+    use crate::lint::{LintKind, LintSeverity, lint_hotkeys, lint_rules};
+    use crate::rule::{KeyTransformRule, KeyTransformRules};
+    use crate::system_hotkeys::{self, MOD_ALT};
+    use crate::{key_rule, key_rules};
+    use std::str::FromStr;
+
+    fn lint_kinds(rules: &KeyTransformRules) -> Vec<LintKind> {
+        lint_rules(rules).iter().map(|i| i.kind).collect()
+    }
+
+    #[test]
+    fn test_lint_clean_rules() {
+        let rules = key_rules!(
+            r#"
+            A↓ : B↓
+            [LEFT_SHIFT] A↓ : C↓
+            A××↓ : D↓ → D↑ → D↓
+            "#
+        );
+
+        assert!(lint_rules(&rules).is_empty());
+    }
+
+    #[test]
+    fn test_lint_overridden() {
+        let rules = key_rules!(
+            r#"
+            A↓ : B↓
+            C↓ : D↓
+            A↓ : E↓
+            "#
+        );
+
+        let issues = lint_rules(&rules);
+        assert_eq!(1, issues.len());
+        assert_eq!(Overridden, issues[0].kind);
+        assert_eq!("A↓ : B↓", issues[0].rule.to_string());
+    }
+
+    #[test]
+    fn test_lint_overridden_toml() {
+        let rules: KeyTransformRules = toml::from_str(
+            r##"
+            "#2 A↓" = "B↓"
+            "#1 A↓" = "C↓"
+            "D↓" = "D↓"
+            "##,
+        )
+        .unwrap();
+
+        let issues = lint_rules(&rules);
+        assert_eq!(2, issues.len());
+        assert_eq!(Overridden, issues[0].kind);
+        assert_eq!("#2 A↓ : B↓", issues[0].rule.to_string());
+        assert_eq!(SelfMapping, issues[1].kind);
+        assert_eq!("D↓ : D↓", issues[1].rule.to_string());
+    }
+
+    #[test]
+    fn test_lint_overridden_priority() {
+        let rules = key_rules!(
+            r#"
+            A↓ : B↓ | PRIORITY=1
+            A↓ : C↓
+            D↓ : E↓
+            D↓ : F↓ | PRIORITY=1
+            "#
+        );
+
+        let issues = lint_rules(&rules);
+        assert_eq!(2, issues.len());
+        assert_eq!(Overridden, issues[0].kind);
+        assert_eq!("A↓ : C↓", issues[0].rule.to_string());
+        assert_eq!(Overridden, issues[1].kind);
+        assert_eq!("D↓ : E↓", issues[1].rule.to_string());
+    }
+
+    #[test]
+    fn test_lint_order_dependent() {
+        let rules = key_rules!(
+            r#"
+            A↓ : B↓ | DEVICE=PAD
+            A↓ : C↓ | DEVICE=LAPTOP
+            A↓ : D↓ | DEVICE=TABLET, PRIORITY=1
+            [NUM_LOCK=on] A↓ : E↓
+            [] A↓ : F↓ | DEVICE=PAD
+            "#
+        );
+
+        let issues = lint_rules(&rules);
+        assert_eq!(2, issues.len());
+        assert_eq!(OrderDependent, issues[0].kind);
+        assert_eq!(key_rule!("A↓ : C↓ | DEVICE=LAPTOP"), issues[0].rule);
+        assert_eq!(OrderDependent, issues[1].kind);
+        assert_eq!(key_rule!("[] A↓ : F↓ | DEVICE=PAD"), issues[1].rule);
+    }
+
+    #[test]
+    fn test_lint_order_dependent_toml() {
+        let rules: KeyTransformRules = toml::from_str(
+            r#"
+            "A↓ | DEVICE=PAD" = "B↓"
+            "A↓ | DEVICE=LAPTOP" = "C↓"
+            "#,
+        )
+        .unwrap();
+
+        let issues = lint_rules(&rules);
+        assert_eq!(1, issues.len());
+        assert_eq!(OrderDependent, issues[0].kind);
+        assert_eq!(key_rule!("A↓ : C↓ | DEVICE=LAPTOP"), issues[0].rule);
+    }
+
+    #[test]
+    fn test_lint_repeated_press() {
+        let rules = key_rules!("A↓ : B↓ → LEFT_SHIFT↓ → B↓");
+
+        assert_eq!(vec![RepeatedPress(Key::B)], lint_kinds(&rules));
+    }
+
+    #[test]
+    fn test_lint_self_mapping() {
+        let rules = key_rules!(
+            r#"
+            A↓ : A↓
+            [LEFT_CTRL] B↓ : B↓
+            C↓ : C↓ → C↑
+            "#
+        );
+
+        assert_eq!(vec![SelfMapping, SelfMapping], lint_kinds(&rules));
+    }
+
+    #[test]
+    fn test_lint_top_row_digit() {
+        let rules = key_rules!(
+            r#"
+            5↓ : A↓
+            ANY_6↓ : B↓
+            [LEFT_CTRL] 7↓ : C↓
+            7↓ : C↓
+            NUM_7↓ : C↓
+            "#
+        );
+        let issues = lint_rules(&rules);
+
+        assert_eq!(
+            vec![TopRowDigit(Key::Digit5), TopRowDigit(Key::Digit7)],
+            issues.iter().map(|i| i.kind).collect::<Vec<_>>()
+        );
+        assert_eq!(key_rule!("[LEFT_CTRL] 7↓ : C↓"), issues[1].rule);
+        assert_eq!(
+            "Trigger is the top row `5` only, `NUM_5` is the numpad one and `ANY_5` is both",
+            issues[0].kind.to_string()
+        );
+    }
+
+    #[test]
+    fn test_lint_system_hotkey() {
+        let rules = key_rules!(
+            r#"
+            [LEFT_WIN] L↓ : A↓
+            [RIGHT_CTRL + LEFT_SHIFT] ESC↓ : B↓
+            [LEFT_ALT] L↓ : C↓
+            "#
+        );
+
+        let issues = lint_rules(&rules);
+        assert_eq!(2, issues.len());
+        assert_eq!(LintSeverity::Warning, issues[0].kind.severity());
+        assert_eq!(
+            "Warning: Trigger is the system hotkey `Win+L (Lock the computer)` the hook cannot \
+            intercept: `[LEFT_WIN] L↓ : A↓`",
+            issues[0].to_string()
+        );
+        assert_eq!(LintSeverity::Info, issues[1].kind.severity());
+        assert_eq!(
+            key_rule!("[RIGHT_CTRL + LEFT_SHIFT] ESC↓ : B↓"),
+            issues[1].rule
+        );
+    }
+
+    #[test]
+    fn test_lint_hotkeys() {
+        let rules = key_rules!("[LEFT_ALT] L↓ : C↓\n[LEFT_ALT] K↓ : D↓");
+        let hotkey = system_hotkeys::SystemHotkey::registered(MOD_ALT, Key::L);
+
+        let issues = lint_hotkeys(&rules, &[hotkey]);
+        assert_eq!(1, issues.len());
+        assert_eq!(SystemHotkey(hotkey), issues[0].kind);
+        assert_eq!(LintSeverity::Warning, issues[0].kind.severity());
+        assert_eq!(
+            "Rule takes over the hotkey `Alt+L (Registered by another application)`",
+            issues[0].kind.to_string()
+        );
+    }
+
+    #[test]
+    fn test_lint_severity() {
+        assert_eq!(LintSeverity::Warning, Overridden.severity());
+        assert_eq!(LintSeverity::Info, SelfMapping.severity());
+    }
+}
@@ -1,16 +1,45 @@
+use crate::action::KeyAction;
 use crate::error::KeyError;
+use crate::key::Key;
 use crate::state::KeyboardState;
+use crate::key_error;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
 use crate::modifiers::KeyModifiers::{All, Any};
+use crate::transition::KeyTransition::Down;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub(crate) const LOCK_KEYS: [Key; 3] = [Key::NumLock, Key::CapsLock, Key::ScrollLock];
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
 pub enum KeyModifiers {
+    #[default]
     Any,
     All(KeyboardState),
 }
 
+impl KeyModifiers {
+    /// Parses modifiers part of a trigger like `[LEFT_SHIFT + NUM_LOCK=off]`
+    /// where `KEY=on|off` tokens are lock key conditions.
+    pub(crate) fn from_str_with_locks(s: &str) -> Result<(Self, KeyLocks), KeyError> {
+        let mut keys = KeyboardState::default();
+        let mut locks = KeyLocks::default();
+        let parts = s.trim().trim_start_matches('[').trim_end_matches(']');
+        for part in parts.split('+').map(str::trim) {
+            if part.contains('=') {
+                let (key, is_on) =
+                    KeyLocks::parse_condition(part).map_err(|e| e.within(s, part))?;
+                locks = locks.with(key, is_on);
+            } else if !part.is_empty() {
+                let key = Key::try_from_str(part).map_err(|e| e.within(s, part))?;
+                keys.update(&KeyAction::new(key, Down));
+            }
+        }
+
+        Ok((All(keys), locks))
+    }
+}
+
 impl Display for KeyModifiers {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -29,18 +58,141 @@ impl FromStr for KeyModifiers {
         }
 
         let part = s.trim().trim_start_matches('[').trim_end_matches(']');
-        Ok(All(KeyboardState::from_str(part)?))
+        Ok(All(
+            KeyboardState::from_str(part).map_err(|e| e.within(s, part))?
+        ))
+    }
+}
+
+/// State of the lock keys (NUM_LOCK, CAPS_LOCK, SCROLL_LOCK).
+/// In a trigger it is a condition where only masked locks are checked.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct KeyLocks {
+    mask: u8,
+    state: u8,
+}
+
+impl KeyLocks {
+    pub fn with(mut self, key: Key, is_on: bool) -> Self {
+        if let Some(bit) = Self::lock_bit(key) {
+            self.mask |= bit;
+            if is_on {
+                self.state |= bit;
+            } else {
+                self.state &= !bit;
+            }
+        }
+        self
+    }
+
+    #[cfg(any(test, feature = "win32"))]
+    pub(crate) fn capture(is_on: impl Fn(Key) -> bool) -> Self {
+        LOCK_KEYS
+            .iter()
+            .fold(Self::default(), |locks, &key| locks.with(key, is_on(key)))
+    }
+
+    pub fn get(&self, key: Key) -> Option<bool> {
+        let bit = Self::lock_bit(key)?;
+        if self.mask & bit != 0 {
+            Some(self.state & bit != 0)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    pub(crate) fn len(&self) -> u32 {
+        self.mask.count_ones()
+    }
+
+    /// Returns the state after the lock keys pressed by the actions are toggled.
+    #[cfg(any(test, feature = "win32"))]
+    pub(crate) fn toggled_by<'a>(self, actions: impl IntoIterator<Item = &'a KeyAction>) -> Self {
+        actions
+            .into_iter()
+            .filter(|action| action.transition == Down)
+            .fold(self, |locks, action| match locks.get(action.key) {
+                Some(is_on) => locks.with(action.key, !is_on),
+                None => locks,
+            })
+    }
+
+    /// Checks that actual lock state satisfies this condition.
+    pub fn matches(&self, actual: &KeyLocks) -> bool {
+        (self.state ^ actual.state) & self.mask == 0
+    }
+
+    fn lock_bit(key: Key) -> Option<u8> {
+        LOCK_KEYS
+            .iter()
+            .position(|k| *k == key)
+            .map(|i| 1 << i)
+    }
+
+    /// Parses the lock key condition like `NUM_LOCK=off`.
+    fn parse_condition(s: &str) -> Result<(Key, bool), KeyError> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or(key_error!("Invalid lock condition: `{s}`"))?;
+        let key = Key::try_from_str(name.trim()).map_err(|e| e.within(s, name.trim()))?;
+        if Self::lock_bit(key).is_none() {
+            return Err(key_error!("Not a lock key: `{key}`")
+                .with_token_in(s, name.trim())
+                .with_expected(&LOCK_KEYS.map(|k| k.as_str())));
+        }
+        match value.trim() {
+            "on" => Ok((key, true)),
+            "off" => Ok((key, false)),
+            v => Err(key_error!("Invalid lock state: `{v}`")
+                .with_token_in(s, v)
+                .with_expected(&["on", "off"])),
+        }
+    }
+}
+
+impl Display for KeyLocks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut is_first = true;
+        for key in LOCK_KEYS {
+            if let Some(is_on) = self.get(key) {
+                if !is_first {
+                    f.write_str(" + ")?;
+                }
+                is_first = false;
+                write!(f, "{}={}", key, if is_on { "on" } else { "off" })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for KeyLocks {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut this = Self::default();
+        for part in s.split('+').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, is_on) = Self::parse_condition(part).map_err(|e| e.within(s, part))?;
+            this = this.with(key, is_on);
+        }
+        Ok(this)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::key::Key;
-    use crate::modifiers::KeyModifiers;
+    use crate::modifiers::{KeyLocks, KeyModifiers};
     use crate::state::tests::kbd_state_from_keys;
     use crate::state::KeyboardState;
     use std::str::FromStr;
     use crate::modifiers::KeyModifiers::{All, Any};
+    use crate::key_action_seq;
+    use crate::action::KeyActionSequence;
 
     #[test]
     fn test_key_modifiers_to_str() {
@@ -85,4 +237,76 @@ mod tests {
     fn test_key_modifiers_from_str_fails() {
         assert!(KeyModifiers::from_str("BANANA").is_err());
     }
+
+    #[test]
+    fn test_key_modifiers_from_str_with_locks() {
+        assert_eq!(
+            Ok((
+                All(kbd_state_from_keys(&[Key::LeftShift])),
+                KeyLocks::default().with(Key::NumLock, false)
+            )),
+            KeyModifiers::from_str_with_locks("[LEFT_SHIFT + NUM_LOCK=off]")
+        );
+
+        assert_eq!(
+            Ok((
+                All(KeyboardState::default()),
+                KeyLocks::default().with(Key::CapsLock, true)
+            )),
+            KeyModifiers::from_str_with_locks("[CAPS_LOCK=on]")
+        );
+    }
+
+    #[test]
+    fn test_key_locks_to_str() {
+        assert_eq!(
+            "NUM_LOCK=on + SCROLL_LOCK=off",
+            KeyLocks::default()
+                .with(Key::ScrollLock, false)
+                .with(Key::NumLock, true)
+                .to_string()
+        );
+        assert_eq!("", KeyLocks::default().to_string());
+    }
+
+    #[test]
+    fn test_key_locks_from_str() {
+        assert_eq!(
+            Ok(KeyLocks::default()
+                .with(Key::NumLock, true)
+                .with(Key::CapsLock, false)),
+            KeyLocks::from_str("NUM_LOCK=on + CAPS_LOCK=off")
+        );
+        assert_eq!(Ok(KeyLocks::default()), KeyLocks::from_str(""));
+        assert!(KeyLocks::from_str("A=on").is_err());
+        assert!(KeyLocks::from_str("NUM_LOCK=maybe").is_err());
+        assert!(KeyLocks::from_str("NUM_LOCK").is_err());
+    }
+
+    #[test]
+    fn test_key_locks_toggled_by() {
+        let locks = KeyLocks::capture(|k| k == Key::CapsLock);
+        let actions = key_action_seq!(
+            "NUM_LOCK↓ → NUM_LOCK↑ → CAPS_LOCK↓ → A↓ → A↑ → CAPS_LOCK↓ → SCROLL_LOCK↑"
+        );
+
+        assert_eq!(
+            KeyLocks::capture(|k| k == Key::CapsLock || k == Key::NumLock),
+            locks.toggled_by(actions.actions())
+        );
+        assert_eq!(
+            KeyLocks::default(),
+            KeyLocks::default().toggled_by(actions.actions())
+        );
+    }
+
+    #[test]
+    fn test_key_locks_matches() {
+        let condition = KeyLocks::default().with(Key::NumLock, false);
+
+        assert!(condition.matches(&KeyLocks::capture(|_| false)));
+        assert!(condition.matches(&KeyLocks::capture(|k| k == Key::CapsLock)));
+        assert!(!condition.matches(&KeyLocks::capture(|k| k == Key::NumLock)));
+        assert!(KeyLocks::default().matches(&KeyLocks::capture(|_| true)));
+    }
 }
@@ -1,28 +1,103 @@
 use crate::event::KeyEvent;
 use crate::rule::KeyTransformRule;
+#[cfg(feature = "win32")]
 use std::cell::RefCell;
-use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
-use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+#[cfg(feature = "win32")]
+use windows::{
+    Win32::Foundation::{HWND, LPARAM, WPARAM},
+    Win32::UI::WindowsAndMessaging::PostMessageW,
+};
 
 pub const WM_KEY_HOOK_NOTIFY: u32 = 88475;
 
+#[cfg(feature = "win32")]
 thread_local! {
     static RECEIVER: RefCell<Option<HWND>> = RefCell::new(Default::default());
+    static DEFERRED: RefCell<Option<Vec<KeyEventNotification>>> = const { RefCell::new(None) };
 }
 
 pub struct KeyEventNotification {
     pub event: KeyEvent,
-    pub rule: Option<KeyTransformRule>,
+    pub rule: Option<Arc<KeyTransformRule>>,
+    /// Problem that prevented the rule from being applied.
+    pub error: Option<KeyEventError>,
+    /// Time in microseconds the hook spent processing the event.
+    pub latency: Option<u32>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyEventError {
+    /// Rules trigger each other deeper than the maximum injection depth.
+    DepthExceeded(u8),
+}
+
+impl Display for KeyEventError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DepthExceeded(depth) => {
+                write!(f, "Rules trigger each other deeper than {depth} expansions")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "win32")]
 pub(crate) fn install_notify_listener(owner: HWND) {
     RECEIVER.replace(Some(owner));
 }
 
-pub(crate) fn notify_key_event(event: KeyEvent, rule: Option<KeyTransformRule>) {
+#[cfg(feature = "win32")]
+pub(crate) fn notify_key_event(event: KeyEvent, rule: Option<Arc<KeyTransformRule>>) {
+    post_notification(KeyEventNotification {
+        event,
+        rule,
+        error: None,
+        latency: None,
+    });
+}
+
+#[cfg(feature = "win32")]
+pub(crate) fn notify_key_error(event: KeyEvent, rule: Arc<KeyTransformRule>, error: KeyEventError) {
+    post_notification(KeyEventNotification {
+        event,
+        rule: Some(rule),
+        error: Some(error),
+        latency: None,
+    });
+}
+
+/// Holds the notifications back until [`post_deferred`] so that they carry the latency of
+/// the whole processing.
+#[cfg(feature = "win32")]
+pub(crate) fn defer() {
+    DEFERRED.replace(Some(Vec::new()));
+}
+
+/// Posts the notifications held back since [`defer`].
+#[cfg(feature = "win32")]
+pub(crate) fn post_deferred(latency: u32) {
+    for mut notification in DEFERRED.take().unwrap_or_default() {
+        notification.latency = Some(latency);
+        post_notification(notification);
+    }
+}
+
+#[cfg(feature = "win32")]
+fn post_notification(notification: KeyEventNotification) {
+    let Some(notification) = DEFERRED.with_borrow_mut(|deferred| match deferred {
+        Some(deferred) => {
+            deferred.push(notification);
+            None
+        }
+        None => Some(notification),
+    }) else {
+        return;
+    };
+
     RECEIVER.with_borrow(|receiver| {
         if receiver.is_some() {
-            let notification = KeyEventNotification { event, rule };
             let raw_ptr = Box::into_raw(Box::new(notification)) as isize;
             unsafe {
                 PostMessageW(*receiver, WM_KEY_HOOK_NOTIFY, WPARAM(0), LPARAM(raw_ptr))
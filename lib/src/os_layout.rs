@@ -0,0 +1,121 @@
+use crate::error::KeyError;
+use crate::key_error;
+use std::fmt::{Debug, Display, Formatter};
+#[cfg(feature = "win32")]
+use windows::{
+    Win32::Foundation::{LPARAM, WPARAM},
+    Win32::Globalization::LocaleNameToLCID,
+    Win32::UI::Input::KeyboardAndMouse::{
+        GetKeyboardLayoutList, HKL, KLF_SUBSTITUTE_OK, LoadKeyboardLayoutW,
+    },
+    Win32::UI::WindowsAndMessaging::{
+        GetForegroundWindow, PostMessageW, WM_INPUTLANGCHANGEREQUEST,
+    },
+    core::HSTRING,
+};
+
+const MAX_LOCALE_NAME_LEN: usize = 16;
+
+/// Locale name of the Windows input language like `en-US`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct LayoutLocale {
+    name: [u8; MAX_LOCALE_NAME_LEN],
+    len: u8,
+}
+
+impl LayoutLocale {
+    pub fn new(name: &str) -> Result<Self, KeyError> {
+        let is_valid = (2..=MAX_LOCALE_NAME_LEN).contains(&name.len())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !is_valid {
+            return Err(key_error!("Invalid locale name: `{name}`")
+                .with_token(name)
+                .at(0));
+        }
+
+        let mut this = Self {
+            name: [0; MAX_LOCALE_NAME_LEN],
+            len: name.len() as u8,
+        };
+        this.name[..name.len()].copy_from_slice(name.as_bytes());
+        Ok(this)
+    }
+
+    pub fn as_str(&self) -> &str {
+        /* only ASCII is accepted by constructor */
+        std::str::from_utf8(&self.name[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "win32")]
+impl LayoutLocale {
+    /// Asks the foreground window to switch its input language to this one. Layout
+    /// of the language is loaded if it is not installed yet.
+    pub fn activate(&self) -> windows::core::Result<()> {
+        let layout = self.find_layout()?;
+        unsafe {
+            PostMessageW(
+                Some(GetForegroundWindow()),
+                WM_INPUTLANGCHANGEREQUEST,
+                WPARAM(0),
+                LPARAM(layout.0 as isize),
+            )
+        }
+    }
+
+    fn find_layout(&self) -> windows::core::Result<HKL> {
+        let lang_id = unsafe { LocaleNameToLCID(&HSTRING::from(self.as_str()), 0) } & 0xFFFF;
+        if lang_id == 0 {
+            return Err(windows::core::Error::from_thread());
+        }
+
+        let mut layouts = [HKL::default(); 64];
+        let count = unsafe { GetKeyboardLayoutList(Some(&mut layouts)) } as usize;
+        if let Some(layout) = layouts[..count]
+            .iter()
+            .find(|layout| layout.0 as usize & 0xFFFF == lang_id as usize)
+        {
+            return Ok(*layout);
+        }
+
+        unsafe {
+            LoadKeyboardLayoutW(
+                &HSTRING::from(format!("{:08X}", lang_id)),
+                KLF_SUBSTITUTE_OK,
+            )
+        }
+    }
+}
+
+impl Display for LayoutLocale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl Debug for LayoutLocale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::os_layout::LayoutLocale;
+
+    #[test]
+    fn test_layout_locale() {
+        assert_eq!("en-US", LayoutLocale::new("en-US").unwrap().as_str());
+        assert_eq!(
+            "ca-ES-valencia",
+            LayoutLocale::new("ca-ES-valencia").unwrap().to_string()
+        );
+
+        assert!(LayoutLocale::new("").is_err());
+        assert!(LayoutLocale::new("en US").is_err());
+        assert!(LayoutLocale::new("ru-RU)").is_err());
+        assert!(LayoutLocale::new("a-very-long-locale-name").is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::action::KeyAction;
+use crate::event::KeyEvent;
+use crate::rule::KeyTransformRule;
+use crate::transition::KeyTransition::{Down, Up};
+use std::sync::Arc;
+
+/// What the hook did with the press of the key being held.
+#[derive(Clone, Debug)]
+enum KeyPress {
+    /// Passed through without rules.
+    Passed,
+    /// Transformed by the `down` rule. The release is transformed by the `up` rule found
+    /// along with it, so that the release matches the press even if the modifiers changed
+    /// while the key was held.
+    Remapped {
+        down: Arc<KeyTransformRule>,
+        up: Option<Arc<KeyTransformRule>>,
+    },
+}
+
+/// Rule to apply to the event according to the press of the key.
+#[derive(Debug, PartialEq)]
+pub(crate) enum PairedRule {
+    Rule(Arc<KeyTransformRule>),
+    None,
+    /// Release of the key whose press was suppressed by the rule having no release pair.
+    Suppress,
+}
+
+/// Presses of the keys being held, so that their releases never leave the keys stuck down
+/// or release the wrong target keys.
+#[derive(Debug)]
+pub(crate) struct KeyPresses {
+    presses: [Option<KeyPress>; 256],
+}
+
+impl Default for KeyPresses {
+    fn default() -> Self {
+        Self {
+            presses: [const { None }; 256],
+        }
+    }
+}
+
+impl KeyPresses {
+    /// Returns the rule for the event. `find` looks up the rule by the current conditions.
+    pub(crate) fn rule(
+        &mut self,
+        event: &KeyEvent,
+        find: impl Fn(&KeyEvent) -> Option<Arc<KeyTransformRule>>,
+    ) -> PairedRule {
+        let action = event.trigger.action;
+        /* wheel has no presses */
+        if action.key.is_wheel() {
+            return PairedRule::from(find(event));
+        }
+
+        let index = action.key as usize;
+        if action.transition == Up {
+            return match self.presses[index].take() {
+                None => PairedRule::from(find(event)),
+                Some(KeyPress::Passed) => PairedRule::None,
+                Some(KeyPress::Remapped { up: Some(up), .. }) => PairedRule::Rule(up),
+                Some(KeyPress::Remapped { down, up: None }) => {
+                    if down.is_pass_through {
+                        PairedRule::None
+                    } else {
+                        PairedRule::Suppress
+                    }
+                }
+            };
+        }
+
+        if event.is_repeat {
+            match &self.presses[index] {
+                Some(KeyPress::Passed) => return PairedRule::None,
+                Some(KeyPress::Remapped { down, .. }) => return PairedRule::Rule(down.clone()),
+                None => {}
+            }
+        }
+
+        let rule = find(event);
+        self.presses[index] = Some(match &rule {
+            None => KeyPress::Passed,
+            Some(down) => KeyPress::Remapped {
+                down: down.clone(),
+                up: find(&release_of(event)),
+            },
+        });
+        PairedRule::from(rule)
+    }
+
+    #[cfg(feature = "win32")]
+    pub(crate) fn clear(&mut self) {
+        self.presses.fill(None);
+    }
+}
+
+impl From<Option<Arc<KeyTransformRule>>> for PairedRule {
+    fn from(rule: Option<Arc<KeyTransformRule>>) -> Self {
+        match rule {
+            Some(rule) => Self::Rule(rule),
+            None => Self::None,
+        }
+    }
+}
+
+/// Returns the event of the key release under the same conditions as the press.
+fn release_of(event: &KeyEvent) -> KeyEvent {
+    debug_assert_eq!(Down, event.trigger.action.transition);
+    let mut release = event.clone();
+    release.trigger.action = KeyAction::new(event.trigger.action.key, Up);
+    release.is_repeat = false;
+    release.character = None;
+    release
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::KeyEvent;
+    use crate::pairing::{KeyPresses, PairedRule};
+    use crate::rule::KeyTransformRule;
+    use crate::transform::KeyTransformMap;
+    use crate::trigger::KeyTrigger;
+    use crate::{key_event, key_rule};
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    fn rule(presses: &mut KeyPresses, map: &KeyTransformMap, event: &KeyEvent) -> PairedRule {
+        presses.rule(event, |e| map.get_shared(e).cloned())
+    }
+
+    fn paired(rule: &str) -> PairedRule {
+        PairedRule::Rule(Arc::new(KeyTransformRule::from_str(rule).unwrap()))
+    }
+
+    #[test]
+    fn test_release_after_modifier_released() {
+        let map = KeyTransformMap::new(
+            [
+                key_rule!("[LEFT_CTRL] J↓ : DOWN↓"),
+                key_rule!("[LEFT_CTRL] J↑ : DOWN↑"),
+                key_rule!("[] J↑ : K↑"),
+            ]
+            .iter(),
+        );
+        let mut presses = KeyPresses::default();
+
+        assert_eq!(
+            paired("[LEFT_CTRL] J↓ : DOWN↓"),
+            rule(&mut presses, &map, &key_event!("[LEFT_CTRL] J↓"))
+        );
+        assert_eq!(
+            paired("[LEFT_CTRL] J↓ : DOWN↓"),
+            rule(
+                &mut presses,
+                &map,
+                &KeyEvent {
+                    is_repeat: true,
+                    ..key_event!("[] J↓")
+                }
+            )
+        );
+        assert_eq!(
+            paired("[LEFT_CTRL] J↑ : DOWN↑"),
+            rule(&mut presses, &map, &key_event!("[] J↑"))
+        );
+
+        /* released without the press */
+        assert_eq!(
+            paired("[] J↑ : K↑"),
+            rule(&mut presses, &map, &key_event!("[] J↑"))
+        );
+    }
+
+    #[test]
+    fn test_release_of_passed_press() {
+        let map = KeyTransformMap::new([key_rule!("[LEFT_CTRL] J↑ : DOWN↑")].iter());
+        let mut presses = KeyPresses::default();
+
+        assert_eq!(
+            PairedRule::None,
+            rule(&mut presses, &map, &key_event!("[] J↓"))
+        );
+        assert_eq!(
+            PairedRule::None,
+            rule(&mut presses, &map, &key_event!("[LEFT_CTRL] J↑"))
+        );
+    }
+
+    #[test]
+    fn test_release_without_pair() {
+        let map = KeyTransformMap::new(
+            [
+                key_rule!("[LEFT_CTRL] J↓ : DOWN↓ → DOWN↑"),
+                key_rule!("[LEFT_CTRL] K↓ : DOWN↓ → DOWN↑ | PASS"),
+                key_rule!("[] J↑ : K↑"),
+            ]
+            .iter(),
+        );
+        let mut presses = KeyPresses::default();
+
+        rule(&mut presses, &map, &key_event!("[LEFT_CTRL] J↓"));
+        assert_eq!(
+            PairedRule::Suppress,
+            rule(&mut presses, &map, &key_event!("[] J↑"))
+        );
+
+        rule(&mut presses, &map, &key_event!("[LEFT_CTRL] K↓"));
+        assert_eq!(
+            PairedRule::None,
+            rule(&mut presses, &map, &key_event!("[] K↑"))
+        );
+    }
+
+    #[test]
+    fn test_wheel() {
+        let map = KeyTransformMap::new([key_rule!("[] WHEEL_Y↑ : B↓")].iter());
+        let mut presses = KeyPresses::default();
+
+        assert_eq!(
+            PairedRule::None,
+            rule(&mut presses, &map, &key_event!("[] WHEEL_Y↓"))
+        );
+        assert_eq!(
+            paired("[] WHEEL_Y↑ : B↓"),
+            rule(&mut presses, &map, &key_event!("[] WHEEL_Y↑"))
+        );
+    }
+}
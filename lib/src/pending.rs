@@ -0,0 +1,54 @@
+use crate::key::Key;
+
+/// Keys whose presses were swallowed by the modal entry, so that their releases are swallowed
+/// too. Auto-repeated presses of the held key are tracked once.
+#[derive(Debug, Default)]
+pub(crate) struct PendingReleases(Vec<Key>);
+
+impl PendingReleases {
+    pub(crate) fn push(&mut self, key: Key) {
+        if !self.0.contains(&key) {
+            self.0.push(key);
+        }
+    }
+
+    /// Returns `true` if the release of the key must be swallowed. The key is not pending
+    /// anymore.
+    pub(crate) fn take(&mut self, key: Key) -> bool {
+        match self.0.iter().position(|k| *k == key) {
+            Some(index) => {
+                self.0.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key::Key;
+    use crate::pending::PendingReleases;
+
+    #[test]
+    fn test_pending_releases() {
+        let mut pending = PendingReleases::default();
+        pending.push(Key::A);
+        pending.push(Key::B);
+
+        assert!(pending.take(Key::A));
+        assert!(!pending.take(Key::A));
+        assert!(!pending.take(Key::C));
+        assert!(pending.take(Key::B));
+    }
+
+    #[test]
+    fn test_pending_releases_repeat() {
+        let mut pending = PendingReleases::default();
+        pending.push(Key::A);
+        pending.push(Key::A);
+
+        assert!(pending.take(Key::A));
+        assert!(!pending.take(Key::A));
+    }
+}
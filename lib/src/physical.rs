@@ -0,0 +1,273 @@
+use crate::key::Key;
+use crate::key::Key::*;
+use serde::{Deserialize, Serialize};
+
+/// Width of the main keyboard block in key units.
+pub const ROW_WIDTH: f32 = 15.0;
+
+#[cfg(feature = "win32")]
+const JAPANESE_KEYBOARD_TYPE: i32 = 7;
+
+/// English variants sold with ISO keyboards, the other English ones are ANSI.
+const ISO_ENGLISH_LANG_IDS: [u16; 2] =
+    [0x0809 /* United Kingdom */, 0x1809 /* Ireland */];
+
+/// Primary languages whose keyboards are ISO.
+const ISO_PRIMARY_LANG_IDS: [u16; 19] = [
+    0x05, /* Czech */
+    0x06, /* Danish */
+    0x07, /* German */
+    0x08, /* Greek */
+    0x0A, /* Spanish */
+    0x0B, /* Finnish */
+    0x0C, /* French */
+    0x0E, /* Hungarian */
+    0x0F, /* Icelandic */
+    0x10, /* Italian */
+    0x13, /* Dutch */
+    0x14, /* Norwegian */
+    0x16, /* Portuguese */
+    0x1A, /* Croatian, Serbian, Bosnian */
+    0x1B, /* Slovak */
+    0x1D, /* Swedish */
+    0x1F, /* Turkish */
+    0x24, /* Slovenian */
+    0x25, /* Estonian */
+];
+
+/// Physical shape of the main keyboard block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PhysicalLayout {
+    #[default]
+    Ansi,
+    Iso,
+    Jis,
+}
+
+/// Key position on the physical keyboard. Column and width are measured in key units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyPlacement {
+    pub key: Key,
+    pub row: u8,
+    pub column: f32,
+    pub width: f32,
+}
+
+impl PhysicalLayout {
+    /// Guesses physical layout of the installed keyboard.
+    #[cfg(feature = "win32")]
+    pub fn detect() -> Self {
+        use windows::Win32::UI::Input::KeyboardAndMouse::{GetKeyboardLayout, GetKeyboardType};
+
+        if unsafe { GetKeyboardType(0) } == JAPANESE_KEYBOARD_TYPE {
+            return Self::Jis;
+        }
+
+        Self::from_lang_id(unsafe { GetKeyboardLayout(0) }.0 as usize as u16)
+    }
+
+    /// Guesses physical layout by the language of the keyboard layout. The languages which
+    /// are not known to use ISO keyboards fall back to ANSI, the most common shape.
+    pub fn from_lang_id(lang_id: u16) -> Self {
+        if ISO_ENGLISH_LANG_IDS.contains(&lang_id)
+            || ISO_PRIMARY_LANG_IDS.contains(&(lang_id & 0x3FF))
+        {
+            Self::Iso
+        } else {
+            Self::Ansi
+        }
+    }
+
+    /// Returns placements of the main block keys row by row.
+    pub fn placements(&self) -> Vec<KeyPlacement> {
+        let mut list = vec![];
+        for (row, keys) in self.rows().iter().enumerate() {
+            let mut column = 0.0;
+            for &(key, width) in keys.iter() {
+                list.push(KeyPlacement {
+                    key,
+                    row: row as u8,
+                    column,
+                    width,
+                });
+                column += width;
+            }
+        }
+        list
+    }
+
+    pub fn placement(&self, key: Key) -> Option<KeyPlacement> {
+        self.placements().into_iter().find(|p| p.key == key)
+    }
+
+    pub fn has_key(&self, key: Key) -> bool {
+        self.placement(key).is_some()
+    }
+
+    fn rows(&self) -> [Vec<(Key, f32)>; 5] {
+        match self {
+            Self::Ansi => [
+                number_row(&[(Backspace, 2.0)]),
+                letter_row(
+                    (Tab, 1.5),
+                    &[Q, W, E, R, T, Y, U, I, O, P, LeftBracket, RightBracket],
+                    &[(Backslash, 1.5)],
+                ),
+                letter_row(
+                    (CapsLock, 1.75),
+                    &[A, S, D, F, G, H, J, K, L, Semicolon, Apostrophe],
+                    &[(Enter, 2.25)],
+                ),
+                letter_row(
+                    (LeftShift, 2.25),
+                    &[Z, X, C, V, B, N, M, Comma, Dot, Slash],
+                    &[(RightShift, 2.75)],
+                ),
+                bottom_row(&[(Space, 6.25)]),
+            ],
+            Self::Iso => [
+                number_row(&[(Backspace, 2.0)]),
+                letter_row(
+                    (Tab, 1.5),
+                    &[Q, W, E, R, T, Y, U, I, O, P, LeftBracket, RightBracket],
+                    &[(Enter, 1.5)],
+                ),
+                letter_row(
+                    (CapsLock, 1.75),
+                    &[A, S, D, F, G, H, J, K, L, Semicolon, Apostrophe, Backslash],
+                    &[(Enter, 1.25)],
+                ),
+                letter_row(
+                    (LeftShift, 1.25),
+                    &[Backslash2, Z, X, C, V, B, N, M, Comma, Dot, Slash],
+                    &[(RightShift, 2.75)],
+                ),
+                bottom_row(&[(Space, 6.25)]),
+            ],
+            Self::Jis => [
+                number_row(&[(Yen, 1.0), (Backspace, 1.0)]),
+                letter_row(
+                    (Tab, 1.5),
+                    &[Q, W, E, R, T, Y, U, I, O, P, LeftBracket, RightBracket],
+                    &[(Enter, 1.5)],
+                ),
+                letter_row(
+                    (CapsLock, 1.75),
+                    &[A, S, D, F, G, H, J, K, L, Semicolon, Apostrophe, Backslash],
+                    &[(Enter, 1.25)],
+                ),
+                letter_row(
+                    (LeftShift, 2.25),
+                    &[Z, X, C, V, B, N, M, Comma, Dot, Slash, Ro],
+                    &[(RightShift, 1.75)],
+                ),
+                bottom_row(&[
+                    (NonConvert, 1.25),
+                    (Space, 2.5),
+                    (Convert, 1.25),
+                    (Kana, 1.25),
+                ]),
+            ],
+        }
+    }
+}
+
+fn number_row(tail: &[(Key, f32)]) -> Vec<(Key, f32)> {
+    letter_row(
+        (Backtick, 1.0),
+        &[
+            Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9, Digit0, Minus,
+            Eq,
+        ],
+        tail,
+    )
+}
+
+fn letter_row(head: (Key, f32), keys: &[Key], tail: &[(Key, f32)]) -> Vec<(Key, f32)> {
+    let mut row = vec![head];
+    row.extend(keys.iter().map(|&key| (key, 1.0)));
+    row.extend_from_slice(tail);
+    row
+}
+
+fn bottom_row(middle: &[(Key, f32)]) -> Vec<(Key, f32)> {
+    let mut row = vec![(LeftCtrl, 1.25), (LeftWin, 1.25), (LeftAlt, 1.25)];
+    row.extend_from_slice(middle);
+    row.extend_from_slice(&[
+        (RightAlt, 1.25),
+        (RightWin, 1.25),
+        (Application, 1.25),
+        (RightCtrl, 1.25),
+    ]);
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key::Key::{Backslash, Backslash2, Enter, Ro, Space, Yen};
+    use crate::physical::PhysicalLayout::{Ansi, Iso, Jis};
+    use crate::physical::{KeyPlacement, PhysicalLayout, ROW_WIDTH};
+
+    #[test]
+    fn test_rows_width() {
+        for layout in [Ansi, Iso, Jis] {
+            for row in layout.rows() {
+                let width: f32 = row.iter().map(|(_, w)| w).sum();
+                assert_eq!(ROW_WIDTH, width, "{:?}", layout);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_lang_id() {
+        assert_eq!(Ansi, PhysicalLayout::from_lang_id(0x0409)); /* en-US */
+        assert_eq!(Ansi, PhysicalLayout::from_lang_id(0x1009)); /* en-CA */
+        assert_eq!(Ansi, PhysicalLayout::from_lang_id(0x0419)); /* ru-RU */
+        assert_eq!(Ansi, PhysicalLayout::from_lang_id(0x0412)); /* ko-KR */
+        assert_eq!(Iso, PhysicalLayout::from_lang_id(0x0809)); /* en-GB */
+        assert_eq!(Iso, PhysicalLayout::from_lang_id(0x0407)); /* de-DE */
+        assert_eq!(Iso, PhysicalLayout::from_lang_id(0x0807)); /* de-CH */
+        assert_eq!(Iso, PhysicalLayout::from_lang_id(0x040C)); /* fr-FR */
+        assert_eq!(Iso, PhysicalLayout::from_lang_id(0x0C0A)); /* es-ES */
+    }
+
+    #[test]
+    fn test_has_key() {
+        assert!(!Ansi.has_key(Backslash2));
+        assert!(Iso.has_key(Backslash2));
+        assert!(!Jis.has_key(Backslash2));
+
+        assert!(!Ansi.has_key(Ro));
+        assert!(!Iso.has_key(Ro));
+        assert!(Jis.has_key(Ro));
+        assert!(Jis.has_key(Yen));
+    }
+
+    #[test]
+    fn test_placement() {
+        assert_eq!(
+            Some(KeyPlacement {
+                key: Backslash,
+                row: 1,
+                column: 13.5,
+                width: 1.5,
+            }),
+            Ansi.placement(Backslash)
+        );
+
+        assert_eq!(
+            Some(KeyPlacement {
+                key: Backslash,
+                row: 2,
+                column: 12.75,
+                width: 1.0,
+            }),
+            Iso.placement(Backslash)
+        );
+
+        assert_eq!(2, Ansi.placement(Enter).unwrap().row);
+        assert_eq!(1, Iso.placement(Enter).unwrap().row);
+        assert_eq!(2.5, Jis.placement(Space).unwrap().width);
+    }
+}
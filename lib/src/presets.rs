@@ -0,0 +1,183 @@
+use crate::builder::{RuleBuilder, SequenceBuilder, TriggerBuilder};
+use crate::key::Key;
+use crate::key::Key::*;
+use crate::rule::KeyTransformRules;
+use std::fmt::{Display, Formatter};
+
+/// Keys of the main block except the digits, row by row from `MINUS` to `SLASH`.
+#[rustfmt::skip]
+const QWERTY_KEYS: [Key; 35] = [
+    Minus, Eq,
+    Q, W, E, R, T, Y, U, I, O, P, LeftBracket, RightBracket,
+    A, S, D, F, G, H, J, K, L, Semicolon, Apostrophe,
+    Z, X, C, V, B, N, M, Comma, Dot, Slash,
+];
+
+#[rustfmt::skip]
+const COLEMAK_KEYS: [Key; 35] = [
+    Minus, Eq,
+    Q, W, F, P, G, J, L, U, Y, Semicolon, LeftBracket, RightBracket,
+    A, R, S, T, D, H, N, E, I, O, Apostrophe,
+    Z, X, C, V, B, K, M, Comma, Dot, Slash,
+];
+
+#[rustfmt::skip]
+const DVORAK_KEYS: [Key; 35] = [
+    LeftBracket, RightBracket,
+    Apostrophe, Comma, Dot, P, Y, F, G, C, R, L, Slash, Eq,
+    A, O, E, U, I, D, H, T, N, S, Minus,
+    Semicolon, Q, J, K, X, B, M, W, V, Z,
+];
+
+#[rustfmt::skip]
+const WORKMAN_KEYS: [Key; 35] = [
+    Minus, Eq,
+    Q, D, R, W, B, J, F, U, P, Semicolon, LeftBracket, RightBracket,
+    A, S, H, T, G, Y, N, E, O, I, Apostrophe,
+    Z, X, M, C, V, K, L, Comma, Dot, Slash,
+];
+
+/// Alternative alphanumeric layout emulated on top of the QWERTY one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LayoutPreset {
+    Colemak,
+    Dvorak,
+    Workman,
+}
+
+impl LayoutPreset {
+    pub const ALL: [Self; 3] = [Self::Colemak, Self::Dvorak, Self::Workman];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Colemak => "colemak",
+            Self::Dvorak => "dvorak",
+            Self::Workman => "workman",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Colemak => "Colemak",
+            Self::Dvorak => "Dvorak",
+            Self::Workman => "Workman",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns pairs of the QWERTY key and the key it produces in the preset. Keys staying
+    /// in place are skipped.
+    pub fn mappings(&self) -> impl Iterator<Item = (Key, Key)> {
+        let keys = match self {
+            Self::Colemak => &COLEMAK_KEYS,
+            Self::Dvorak => &DVORAK_KEYS,
+            Self::Workman => &WORKMAN_KEYS,
+        };
+        QWERTY_KEYS
+            .into_iter()
+            .zip(keys.iter().copied())
+            .filter(|(source, target)| source != target)
+    }
+
+    /// Returns rules remapping both the press and the release of the keys.
+    pub fn rules(&self) -> KeyTransformRules {
+        self.mappings()
+            .flat_map(|(source, target)| {
+                [
+                    RuleBuilder::new(
+                        TriggerBuilder::down(source).build(),
+                        SequenceBuilder::new().down(target).build(),
+                    ),
+                    RuleBuilder::new(
+                        TriggerBuilder::up(source).build(),
+                        SequenceBuilder::new().up(target).build(),
+                    ),
+                ]
+            })
+            .map(RuleBuilder::build)
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl Display for LayoutPreset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::KeyEvent;
+    use crate::key::Key;
+    use crate::key_event;
+    use crate::presets::{LayoutPreset, QWERTY_KEYS};
+    use crate::rule::{KeyTransformRule, KeyTransformRules};
+    use crate::trigger::KeyTrigger;
+    use crate::{key_rule, key_rules};
+    use fxhash::FxHashSet;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_preset_is_permutation() {
+        for preset in LayoutPreset::ALL {
+            let sources: FxHashSet<_> = preset.mappings().map(|(s, _)| s).collect();
+            let targets: FxHashSet<_> = preset.mappings().map(|(_, t)| t).collect();
+
+            assert_eq!(sources, targets, "{preset}");
+            assert!(sources.iter().all(|key| QWERTY_KEYS.contains(key)));
+        }
+    }
+
+    #[test]
+    fn test_preset_rules() {
+        let rules = LayoutPreset::Colemak.rules();
+
+        assert_eq!(
+            LayoutPreset::Colemak.mappings().count() * 2,
+            rules.iter().count()
+        );
+        assert_eq!(
+            Some(key_rule!("E↓ : F↓")),
+            rules.find_rule(&key_event!("E↓"))
+        );
+        assert_eq!(
+            Some(key_rule!("E↑ : F↑")),
+            rules.find_rule(&key_event!("E↑"))
+        );
+        assert_eq!(None, rules.find_rule(&key_event!("A↓")));
+
+        assert!(
+            LayoutPreset::Dvorak
+                .rules()
+                .iter()
+                .any(|rule| *rule == key_rule!("Q↓ : APOSTROPHE↓"))
+        );
+        assert_eq!(
+            key_rules!("D↓ : H↓\nD↑ : H↑"),
+            KeyTransformRules::from(
+                LayoutPreset::Workman
+                    .rules()
+                    .iter()
+                    .filter(|rule| rule.trigger.action.key == Key::D)
+                    .cloned()
+                    .collect::<Vec<_>>()
+            )
+        );
+    }
+
+    #[test]
+    fn test_preset_from_name() {
+        assert_eq!(
+            Some(LayoutPreset::Dvorak),
+            LayoutPreset::from_name("Dvorak")
+        );
+        assert_eq!(None, LayoutPreset::from_name("qwerty"));
+        assert_eq!("workman", LayoutPreset::Workman.to_string());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::action::{KeyAction, KeyActionSequence, KeySequenceItem};
+use crate::event::KeyEvent;
+use crate::repeat::KeyRepeat;
+use crate::rule::KeyTransformRule;
+use crate::trigger::KeyTrigger;
+use std::slice::Iter;
+
+/// Recorded action with time elapsed since the previous one (in milliseconds).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacroStep {
+    pub action: KeyAction,
+    pub delay: u32,
+}
+
+/// Captures incoming key events into a replayable macro.
+#[derive(Debug, Default)]
+pub struct MacroRecorder {
+    steps: Vec<MacroStep>,
+    last_time: Option<u32>,
+    is_recording: bool,
+}
+
+impl MacroRecorder {
+    pub fn start(&mut self) {
+        self.steps.clear();
+        self.last_time = None;
+        self.is_recording = true;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.is_recording
+    }
+
+    /// Adds event to the macro. Synthesized and auto-repeat events are ignored.
+    pub fn record(&mut self, event: &KeyEvent) {
+        if !self.is_recording || event.is_private || event.is_repeat {
+            return;
+        }
+
+        let delay = self
+            .last_time
+            .map_or(0, |time| event.time.wrapping_sub(time));
+        self.last_time = Some(event.time);
+        self.steps.push(MacroStep {
+            action: event.trigger.action,
+            delay,
+        });
+    }
+
+    pub fn stop(&mut self) -> RecordedMacro {
+        self.is_recording = false;
+        self.last_time = None;
+        RecordedMacro(self.steps.drain(..).collect())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordedMacro(Vec<MacroStep>);
+
+impl RecordedMacro {
+    pub fn steps(&self) -> Iter<'_, MacroStep> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Total time of the macro in milliseconds.
+    pub fn duration(&self) -> u32 {
+        self.0.iter().map(|s| s.delay).sum()
+    }
+
+    /// Returns actions sequence keeping recorded delays between actions.
+    pub fn sequence(&self) -> KeyActionSequence {
+        let mut items = Vec::with_capacity(self.0.len() * 2);
+        for step in &self.0 {
+            if step.delay > 0 {
+                items.push(KeySequenceItem::Delay(step.delay));
+            }
+            items.push(KeySequenceItem::Action(step.action));
+        }
+        KeyActionSequence::from_items(items)
+    }
+
+    /// Creates rule that plays the macro back on the trigger.
+    pub fn bind(&self, trigger: KeyTrigger) -> KeyTransformRule {
+        KeyTransformRule {
+            id: None,
+            trigger,
+            actions: self.sequence(),
+            repeat: KeyRepeat::Pass,
+            is_pass_through: false,
+            device: None,
+            window: None,
+            priority: 0,
+            target: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::KeyActionSequence;
+    use crate::event::KeyEvent;
+    use crate::recorder::MacroRecorder;
+    use crate::rule::KeyTransformRule;
+    use crate::trigger::KeyTrigger;
+    use crate::{key_action_seq, key_event, key_rule, key_trigger};
+    use std::str::FromStr;
+
+    fn timed_event(text: &str, time: u32) -> KeyEvent {
+        KeyEvent {
+            trigger: KeyTrigger::from_str(text).unwrap(),
+            time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let mut recorder = MacroRecorder::default();
+        recorder.record(&key_event!("A↓"));
+        assert!(!recorder.is_recording());
+
+        recorder.start();
+        recorder.record(&timed_event("A↓", 1000));
+        recorder.record(&KeyEvent {
+            is_repeat: true,
+            ..timed_event("A↓", 1030)
+        });
+        recorder.record(&timed_event("A↑", 1100));
+        recorder.record(&KeyEvent {
+            is_private: true,
+            ..timed_event("B↓", 1150)
+        });
+        recorder.record(&timed_event("C↓", 1200));
+        let recorded = recorder.stop();
+
+        assert!(!recorder.is_recording());
+        assert_eq!(
+            key_action_seq!("A↓ → DELAY(100) → A↑ → DELAY(100) → C↓"),
+            recorded.sequence()
+        );
+        assert_eq!(
+            vec![0, 100, 100],
+            recorded.steps().map(|s| s.delay).collect::<Vec<_>>()
+        );
+        assert_eq!(200, recorded.duration());
+    }
+
+    #[test]
+    fn test_restart_clears_steps() {
+        let mut recorder = MacroRecorder::default();
+        recorder.start();
+        recorder.record(&key_event!("A↓"));
+        recorder.start();
+
+        assert!(recorder.stop().is_empty());
+    }
+
+    #[test]
+    fn test_bind() {
+        let mut recorder = MacroRecorder::default();
+        recorder.start();
+        recorder.record(&key_event!("A↓"));
+        recorder.record(&key_event!("A↑"));
+
+        assert_eq!(
+            key_rule!("[LEFT_CTRL] F12↓ : A↓ → A↑"),
+            recorder.stop().bind(key_trigger!("[LEFT_CTRL] F12↓"))
+        );
+    }
+}
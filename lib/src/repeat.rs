@@ -0,0 +1,150 @@
+use crate::error::KeyError;
+use crate::{deserialize_from_string, key_err, key_error, serialize_to_string};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const NO_REPEAT: &str = "NO_REPEAT";
+const REPEAT: &str = "REPEAT";
+const TURBO: &str = "TURBO";
+
+/// Rule auto-repeat mode.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum KeyRepeat {
+    /// Auto-repeats of the source key are passed through.
+    #[default]
+    Pass,
+    /// Auto-repeats of the source key are ignored so the rule fires once.
+    Suppress,
+    /// Rule actions are repeated with custom initial delay and interval (in milliseconds)
+    /// while the source key is held.
+    Custom { delay: u32, interval: u32 },
+    /// Rule actions are repeated with the interval (in milliseconds) while the source key is
+    /// held, regardless of the other keys pressed meanwhile.
+    Turbo { interval: u32 },
+}
+
+impl KeyRepeat {
+    /// Returns `true` if the rule actions are repeated by the engine.
+    pub fn is_scheduled(&self) -> bool {
+        matches!(self, KeyRepeat::Custom { .. } | KeyRepeat::Turbo { .. })
+    }
+}
+
+impl Display for KeyRepeat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyRepeat::Pass => Ok(()),
+            KeyRepeat::Suppress => f.write_str(NO_REPEAT),
+            KeyRepeat::Custom { delay, interval } => write!(f, "{REPEAT}={delay}/{interval}"),
+            KeyRepeat::Turbo { interval } => write!(f, "{TURBO}={interval}"),
+        }
+    }
+}
+
+impl FromStr for KeyRepeat {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source = s;
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(KeyRepeat::Pass);
+        }
+        if s == NO_REPEAT {
+            return Ok(KeyRepeat::Suppress);
+        }
+
+        let Some((name, value)) = s.split_once('=') else {
+            return Err(invalid_option(source, s));
+        };
+        if name.trim() == TURBO {
+            let interval = value.trim().parse().map_err(|_| {
+                key_error!("Invalid turbo interval: `{value}`").with_token_in(source, value.trim())
+            })?;
+            if interval == 0 {
+                return key_err!("Turbo interval must be positive in `{s}`");
+            }
+            return Ok(KeyRepeat::Turbo { interval });
+        }
+        if name.trim() != REPEAT {
+            return Err(invalid_option(source, s));
+        }
+
+        let (delay, interval) = value
+            .split_once('/')
+            .ok_or(key_error!("Missing repeat interval in `{s}`"))?;
+        let delay = delay.trim().parse().map_err(|_| {
+            key_error!("Invalid repeat delay: `{delay}`").with_token_in(source, delay.trim())
+        })?;
+        let interval = interval.trim().parse().map_err(|_| {
+            key_error!("Invalid repeat interval: `{interval}`")
+                .with_token_in(source, interval.trim())
+        })?;
+        if interval == 0 {
+            return key_err!("Repeat interval must be positive in `{s}`");
+        }
+
+        Ok(KeyRepeat::Custom { delay, interval })
+    }
+}
+
+fn invalid_option(source: &str, s: &str) -> KeyError {
+    key_error!("Invalid repeat option: `{s}`")
+        .with_token_in(source, s)
+        .with_expected(&[NO_REPEAT, "REPEAT=<delay>/<interval>", "TURBO=<interval>"])
+}
+
+impl Serialize for KeyRepeat {
+    serialize_to_string!();
+}
+
+impl<'de> Deserialize<'de> for KeyRepeat {
+    deserialize_from_string!();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repeat::KeyRepeat;
+    use crate::repeat::KeyRepeat::{Custom, Pass, Suppress, Turbo};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_key_repeat_from_str() {
+        assert_eq!(Pass, KeyRepeat::from_str("").unwrap());
+        assert_eq!(Suppress, KeyRepeat::from_str("NO_REPEAT").unwrap());
+        assert_eq!(
+            Custom {
+                delay: 500,
+                interval: 30
+            },
+            KeyRepeat::from_str(" REPEAT = 500 / 30 ").unwrap()
+        );
+
+        assert_eq!(
+            Turbo { interval: 50 },
+            KeyRepeat::from_str("TURBO = 50").unwrap()
+        );
+
+        assert!(KeyRepeat::from_str("REPEAT=500").is_err());
+        assert!(KeyRepeat::from_str("TURBO=0").is_err());
+        assert!(KeyRepeat::from_str("TURBO=fast").is_err());
+        assert!(KeyRepeat::from_str("REPEAT=500/0").is_err());
+        assert!(KeyRepeat::from_str("ONCE").is_err());
+    }
+
+    #[test]
+    fn test_key_repeat_display() {
+        assert_eq!("", Pass.to_string());
+        assert_eq!("NO_REPEAT", Suppress.to_string());
+        assert_eq!(
+            "REPEAT=500/30",
+            Custom {
+                delay: 500,
+                interval: 30
+            }
+            .to_string()
+        );
+        assert_eq!("TURBO=50", Turbo { interval: 50 }.to_string());
+    }
+}
@@ -1,45 +1,136 @@
 use crate::action::KeyActionSequence;
 use crate::error::KeyError;
+use crate::event::KeyEvent;
+use crate::repeat::KeyRepeat;
+use crate::trace::MatchTrace;
+use crate::transform::KeyTransformMap;
 use crate::trigger::KeyTrigger;
+use crate::utils::contains_ignore_case;
+use crate::window::WindowCondition;
 use crate::{key_err, key_error, write_joined};
-use serde::de::{MapAccess, Visitor};
+use serde::de::{MapAccess, SeqAccess, Visitor};
 use serde::ser::SerializeMap;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeSet;
 use std::fmt;
 use std::fmt::Write;
 use std::fmt::{Display, Formatter};
 use std::slice::Iter;
 use std::str::{FromStr, Lines};
 
+const ID_PREFIX: char = '#';
+const OPTIONS_SEPARATOR: char = '|';
+const OPTIONS_DELIMITER: char = ',';
+const PASS_THROUGH: &str = "PASS";
+const DEVICE: &str = "DEVICE";
+const PRIORITY: &str = "PRIORITY";
+const TARGET: &str = "TARGET";
+const WINDOW_PREFIX: &str = "@window(\"";
+const WINDOW_SUFFIX: &str = "\")";
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct KeyTransformRule {
+    /// Stable rule identifier persisted in the rules source.
+    pub id: Option<u32>,
     pub trigger: KeyTrigger,
     pub actions: KeyActionSequence,
+    pub repeat: KeyRepeat,
+    /// Original event is passed through alongside the generated actions instead of being swallowed.
+    #[serde(default)]
+    pub is_pass_through: bool,
+    /// Part of the keyboard device name the rule is limited to.
+    #[serde(default)]
+    pub device: Option<String>,
+    /// Foreground window the rule is limited to.
+    #[serde(default)]
+    pub window: Option<WindowCondition>,
+    /// Rules of the same specificity matching the same event are tried in descending priority.
+    #[serde(default)]
+    pub priority: i32,
+    /// Window the actions are posted to as key messages instead of being sent as the global
+    /// input, so that it receives them in the background without taking the focus.
+    #[serde(default)]
+    pub target: Option<WindowCondition>,
+}
+
+/// Options of the rule following the actions in the rules source.
+#[derive(Default)]
+struct RuleOptions {
+    repeat: KeyRepeat,
+    is_pass_through: bool,
+    device: Option<String>,
+    priority: i32,
+    target: Option<WindowCondition>,
 }
 
 impl KeyTransformRule {
-    fn from_str_pair(triggers_str: &str, actions_str: &str) -> Result<Vec<Self>, KeyError> {
-        let triggers_list = KeyTrigger::from_str_expand_list(triggers_str)?;
-        let sequences = KeyActionSequence::from_str_expand(actions_str)?;
+    /// Parses the rules of an entry of the rules table of a layout file. The entry expands
+    /// into several rules when its trigger or actions are lists.
+    pub fn from_str_pair(triggers_str: &str, actions_str: &str) -> Result<Vec<Self>, KeyError> {
+        Self::from_str_parts(triggers_str, triggers_str, actions_str, actions_str)
+    }
+
+    /// Parses the rules of the trigger and actions parts sliced from their sources. Offsets
+    /// of the errors are the ones in the sources.
+    fn from_str_parts(
+        triggers_source: &str,
+        triggers_str: &str,
+        actions_source: &str,
+        actions_str: &str,
+    ) -> Result<Vec<Self>, KeyError> {
+        let (id, triggers_str) =
+            Self::parse_id(triggers_str).map_err(|e| e.within(triggers_source, triggers_str))?;
+        let (window, triggers_str) = Self::parse_window(triggers_str)
+            .map_err(|e| e.within(triggers_source, triggers_str))?;
+        let (triggers_str, key_options_str) = triggers_str
+            .split_once(OPTIONS_SEPARATOR)
+            .unwrap_or((triggers_str, ""));
+        let (actions_str, options_str) = actions_str
+            .split_once(OPTIONS_SEPARATOR)
+            .unwrap_or((actions_str, ""));
+        let mut options = RuleOptions::default();
+        Self::parse_options(&mut options, key_options_str)
+            .map_err(|e| e.within(triggers_source, key_options_str))?;
+        Self::parse_options(&mut options, options_str)
+            .map_err(|e| e.within(actions_source, options_str))?;
+        /* posted actions are not repeated by the scheduler sending the global input */
+        if options.target.is_some() && options.repeat.is_scheduled() {
+            return key_err!(
+                "Custom repeat is not supported with the target window in `{options_str}`"
+            );
+        }
+        let triggers_list = KeyTrigger::from_str_expand_list(triggers_str)
+            .map_err(|e| e.within(triggers_source, triggers_str))?;
+        let sequences = KeyActionSequence::from_str_expand(actions_str)
+            .map_err(|e| e.within(actions_source, actions_str))?;
         let mut rules = Vec::new();
 
         for triggers in triggers_list {
-            let len_t = triggers.len();
-            let len_s = sequences.len();
-            for i in 0..len_t.max(len_s) {
+            /* wheel has no release so the whole expanded press goes to the single trigger */
+            let sequences = if triggers.iter().all(|t| t.action.key.is_wheel()) {
+                vec![KeyActionSequence::from_items(
+                    sequences.iter().flat_map(|s| s.iter().copied()).collect(),
+                )]
+            } else {
+                sequences.clone()
+            };
+            /* the shorter list repeats its last item */
+            for i in 0..triggers.len().max(sequences.len()) {
+                let trigger = triggers.get(i).or(triggers.last());
+                let actions = sequences.get(i).or(sequences.last());
+                let (Some(trigger), Some(actions)) = (trigger, actions) else {
+                    return key_err!("Missing rule part in `{triggers_str} : {actions_str}`");
+                };
                 let rule = KeyTransformRule {
-                    trigger: if i < len_t {
-                        &triggers[i]
-                    } else {
-                        &triggers[len_t - 1]
-                    }
-                    .clone(),
-                    actions: if i < len_s {
-                        &sequences[i]
-                    } else {
-                        &sequences[len_s - 1]
-                    }
-                    .clone(),
+                    id,
+                    trigger: trigger.clone(),
+                    actions: actions.clone(),
+                    repeat: options.repeat,
+                    is_pass_through: options.is_pass_through,
+                    device: options.device.clone(),
+                    window: window.clone(),
+                    priority: options.priority,
+                    target: options.target.clone(),
                 };
 
                 rules.push(rule);
@@ -49,15 +140,181 @@ impl KeyTransformRule {
         Ok(rules)
     }
 
+    /// Checks that the event comes from the device the rule is limited to.
+    pub(crate) fn matches_device(&self, event: &KeyEvent) -> bool {
+        match (&self.device, &event.device) {
+            (None, _) => true,
+            (Some(device), Some(name)) => contains_ignore_case(name, device),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Checks that the event occurred in the foreground window the rule is limited to.
+    pub(crate) fn matches_window(&self, event: &KeyEvent) -> bool {
+        match (&self.window, &event.window) {
+            (None, _) => true,
+            (Some(condition), Some(window)) => condition.matches(window),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Number of conditions the rule imposes on the event. Rules having more of them are
+    /// more specific.
+    pub(crate) fn conditions_count(&self) -> u32 {
+        self.trigger.conditions_count()
+            + self.device.is_some() as u32
+            + self.window.is_some() as u32
+    }
+
+    fn parse_options(options: &mut RuleOptions, s: &str) -> Result<(), KeyError> {
+        for option in s.split(OPTIONS_DELIMITER).map(str::trim) {
+            if option == PASS_THROUGH {
+                options.is_pass_through = true;
+            } else if let Some((DEVICE, name)) = option.split_once('=').map(|(k, v)| (k.trim(), v))
+            {
+                let name = name.trim();
+                if name.is_empty() {
+                    return key_err!("Missing device name in `{option}`");
+                }
+                options.device = Some(name.to_string());
+            } else if let Some((PRIORITY, value)) =
+                option.split_once('=').map(|(k, v)| (k.trim(), v.trim()))
+            {
+                options.priority = value.parse().map_err(|_| {
+                    key_error!("Invalid rule priority: `{value}`").with_token_in(s, value)
+                })?;
+            } else if let Some((TARGET, pattern)) =
+                option.split_once('=').map(|(k, v)| (k.trim(), v.trim()))
+            {
+                if pattern.is_empty() {
+                    return key_err!("Missing target window pattern in `{option}`");
+                }
+                let condition = WindowCondition::new(pattern).map_err(|e| e.within(s, pattern))?;
+                options.target = Some(condition);
+            } else if !option.is_empty() {
+                options.repeat = KeyRepeat::from_str(option).map_err(|e| e.within(s, option))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_id(s: &str) -> Result<(Option<u32>, &str), KeyError> {
+        let text = s.trim();
+        match text.strip_prefix(ID_PREFIX) {
+            Some(rest) => {
+                let (id, rest) = rest
+                    .split_once(char::is_whitespace)
+                    .ok_or(key_error!("Missing trigger after rule ID in `{text}`"))?;
+                let id = id
+                    .parse()
+                    .map_err(|_| key_error!("Invalid rule ID: `{id}`").with_token_in(s, id))?;
+                Ok((Some(id), rest))
+            }
+            None => Ok((None, text)),
+        }
+    }
+
+    fn parse_window(s: &str) -> Result<(Option<WindowCondition>, &str), KeyError> {
+        let text = s.trim();
+        match text.strip_prefix(WINDOW_PREFIX) {
+            Some(rest) => {
+                let (pattern, rest) = rest
+                    .split_once(WINDOW_SUFFIX)
+                    .ok_or(key_error!("Missing closing `{WINDOW_SUFFIX}` in `{text}`"))?;
+                if pattern.is_empty() {
+                    return key_err!("Missing window pattern in `{text}`");
+                }
+                let condition = WindowCondition::new(pattern).map_err(|e| e.within(s, pattern))?;
+                Ok((Some(condition), rest))
+            }
+            None => Ok((None, text)),
+        }
+    }
+
+    /// Returns the trigger of the rule with its ID and window, e.g. `#5 A↓`.
+    pub fn trigger_key(&self) -> String {
+        let mut s = String::new();
+        if let Some(id) = self.id {
+            write!(s, "{ID_PREFIX}{id} ").unwrap();
+        }
+        if let Some(window) = &self.window {
+            write!(s, "{WINDOW_PREFIX}{window}{WINDOW_SUFFIX} ").unwrap();
+        }
+        write!(s, "{}", self.trigger).unwrap();
+        s
+    }
+
+    /// Returns the key of the rule in the rules table of a layout file, e.g. `#5 A↓ | DEVICE=PAD`.
+    /// Options telling apart the rules of the same trigger belong to the key, otherwise such
+    /// rules would collide in the table.
+    pub fn table_key(&self) -> String {
+        join_options(self.trigger_key(), self.key_options())
+    }
+
+    /// Returns the value of the rule in the rules table of a layout file, e.g. `B↓ | NO_REPEAT`.
+    pub fn table_value(&self) -> String {
+        join_options(self.actions.to_string(), self.value_options())
+    }
+
+    fn actions_value(&self) -> String {
+        let mut options = self.value_options();
+        options.extend(self.key_options());
+        join_options(self.actions.to_string(), options)
+    }
+
+    fn key_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        if let Some(device) = &self.device {
+            options.push(format!("{DEVICE}={device}"));
+        }
+        if self.priority != 0 {
+            options.push(format!("{PRIORITY}={}", self.priority));
+        }
+        if let Some(target) = &self.target {
+            options.push(format!("{TARGET}={target}"));
+        }
+        options
+    }
+
+    fn value_options(&self) -> Vec<String> {
+        let mut options = vec![];
+        if self.repeat != KeyRepeat::Pass {
+            options.push(self.repeat.to_string());
+        }
+        if self.is_pass_through {
+            options.push(PASS_THROUGH.to_string());
+        }
+        options
+    }
+
     fn from_str_expand(s: &str) -> Result<Vec<Self>, KeyError> {
-        let mut parts = s.trim().split(":");
-        Self::from_str_pair(
-            parts
-                .next()
-                .ok_or(key_error!("Missing trigger part in `{s}`"))?,
-            parts
-                .next()
-                .ok_or(key_error!("Missing rule part in `{s}`."))?,
+        let source = s;
+        let s = s.trim();
+        /* window pattern may contain the separator */
+        let start = s
+            .find(WINDOW_PREFIX)
+            .and_then(|i| s[i..].find(WINDOW_SUFFIX).map(|j| i + j))
+            .unwrap_or(0);
+        let (triggers_str, actions_str) = s[start..]
+            .find(':')
+            .map(|i| (&s[..start + i], &s[start + i + 1..]))
+            .ok_or(key_error!("Missing rule part in `{s}`."))?;
+        Self::from_str_parts(
+            source,
+            triggers_str,
+            source,
+            actions_str.split(':').next().unwrap_or_default(),
+        )
+    }
+}
+
+fn join_options(s: String, options: Vec<String>) -> String {
+    if options.is_empty() {
+        s
+    } else {
+        format!(
+            "{s} {OPTIONS_SEPARATOR} {}",
+            options.join(&format!("{OPTIONS_DELIMITER} "))
         )
     }
 }
@@ -65,7 +322,7 @@ impl KeyTransformRule {
 impl Display for KeyTransformRule {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let mut s = String::new();
-        write!(s, "{} : {}", self.trigger, self.actions)?;
+        write!(s, "{} : {}", self.trigger_key(), self.actions_value())?;
         f.pad(&s)
     }
 }
@@ -78,18 +335,22 @@ impl FromStr for KeyTransformRule {
         if vec.len() > 1 {
             return key_err!("String must be exactly single rule");
         }
-        Ok(vec[0].clone())
+        vec.first()
+            .cloned()
+            .ok_or_else(|| key_error!("Invalid rule string"))
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Default)]
+#[derive(Clone, Debug, Eq, PartialEq, Default)]
 pub struct KeyTransformRules(Vec<KeyTransformRule>);
 
 impl KeyTransformRules {
     pub fn from_lines(lines: Lines) -> Result<Self, KeyError> {
         let mut items = Vec::new();
-        for line in lines {
-            items.extend(KeyTransformRule::from_str_expand(line.trim())?);
+        for (index, line) in lines.enumerate() {
+            let rules =
+                KeyTransformRule::from_str_expand(line).map_err(|e| e.locate(index + 1, line))?;
+            items.extend(rules);
         }
 
         Ok(Self(items))
@@ -98,6 +359,56 @@ impl KeyTransformRules {
     pub fn iter(&self) -> Iter<'_, KeyTransformRule> {
         self.0.iter()
     }
+
+    /// Returns rules having specified ID.
+    pub fn find_by_id(&self, id: u32) -> impl Iterator<Item = &KeyTransformRule> {
+        self.0.iter().filter(move |r| r.id == Some(id))
+    }
+
+    /// Returns the rules except the disabled ones. Rules without ID cannot be disabled.
+    pub fn without_disabled(&self, disabled_ids: &BTreeSet<u32>) -> Self {
+        Self(
+            self.0
+                .iter()
+                .filter(|r| !r.id.is_some_and(|id| disabled_ids.contains(&id)))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Adds rule replacing the ones having the same trigger.
+    pub fn insert(&mut self, rule: KeyTransformRule) {
+        self.0.retain(|r| r.trigger != rule.trigger);
+        self.0.push(rule);
+    }
+
+    /// Returns the rule the hook would apply to the event. Builds the lookup map on every call,
+    /// so it suits tests and tools rather than processing of live events.
+    pub fn find_rule(&self, event: &KeyEvent) -> Option<KeyTransformRule> {
+        KeyTransformMap::new(self.iter()).get(event).cloned()
+    }
+
+    /// Returns the steps of the rule lookup for the event for debugging of the rules.
+    pub fn trace(&self, event: &KeyEvent) -> MatchTrace {
+        KeyTransformMap::new(self.iter()).trace(event)
+    }
+
+    /// Returns the largest ID of the rules.
+    pub fn max_id(&self) -> Option<u32> {
+        self.0.iter().filter_map(|r| r.id).max()
+    }
+
+    /// Gives new unique IDs to the rules that have none. The IDs follow the largest ID of the rules
+    /// and `last_id`, the largest one used elsewhere. Returns `true` if any rule got one.
+    pub fn assign_ids(&mut self, last_id: u32) -> bool {
+        let first_id = self.max_id().unwrap_or(0).max(last_id) + 1;
+        let mut assigned = false;
+        for (rule, id) in self.0.iter_mut().filter(|r| r.id.is_none()).zip(first_id..) {
+            rule.id = Some(id);
+            assigned = true;
+        }
+        assigned
+    }
 }
 
 impl Display for KeyTransformRules {
@@ -125,9 +436,17 @@ impl Serialize for KeyTransformRules {
     where
         S: Serializer,
     {
-        let mut map = serializer.serialize_map(Some(self.0.len()))?;
-        for rule in &self.0 {
-            map.serialize_entry(&rule.trigger, &rule.actions)?;
+        let entries = self
+            .0
+            .iter()
+            .map(|r| (r.table_key(), r.table_value()))
+            .collect::<Vec<_>>();
+        let mut map = serializer.serialize_map(None)?;
+        for (index, (key, value)) in entries.iter().enumerate() {
+            /* the rule is overridden by the later one of the same key */
+            if !entries[index + 1..].iter().any(|(k, _)| k == key) {
+                map.serialize_entry(key, value)?;
+            }
         }
         map.end()
     }
@@ -138,7 +457,7 @@ impl<'de> Deserialize<'de> for KeyTransformRules {
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_map(KeyTransformRuleVisitor)
+        deserializer.deserialize_any(KeyTransformRuleVisitor)
     }
 }
 
@@ -148,7 +467,21 @@ impl<'de> Visitor<'de> for KeyTransformRuleVisitor {
     type Value = KeyTransformRules;
 
     fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
-        formatter.write_str("map of string -> string")
+        formatter.write_str("map of string -> string or sequence of rule strings")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+
+        while let Some(s) = seq.next_element::<String>()? {
+            let rules = KeyTransformRule::from_str_expand(&s).map_err(de::Error::custom)?;
+            items.extend(rules);
+        }
+
+        Ok(KeyTransformRules(items))
     }
 
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
@@ -184,20 +517,36 @@ macro_rules! key_rules {
 
 #[cfg(test)]
 pub mod tests {
+    use crate::action::KeyAction;
     use crate::action::KeyActionSequence;
+    use crate::event::KeyEvent;
+    use crate::key::Key;
+    use crate::modifiers::KeyLocks;
+    use crate::repeat::KeyRepeat;
     use crate::rule::KeyTransformRule;
     use crate::rule::KeyTransformRules;
     use crate::trigger::KeyTrigger;
-    use crate::{key_action_seq, key_trigger};
+    use crate::window::WindowInfo;
+    use crate::{key_action_seq, key_event, key_trigger};
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
     use std::str::FromStr;
+    use std::sync::Arc;
 
     // Transform rule
 
     #[test]
     fn test_key_transform_rule_display() {
         let actual = KeyTransformRule {
+            id: None,
             trigger: key_trigger!("[LEFT_SHIFT] ENTER ↓"),
             actions: key_action_seq!("ENTER↓"),
+            repeat: Default::default(),
+            is_pass_through: false,
+            device: None,
+            window: None,
+            priority: 0,
+            target: None,
         };
 
         assert_eq!(
@@ -218,8 +567,15 @@ pub mod tests {
     fn test_key_transform_rule_from_str() {
         assert_eq!(
             KeyTransformRule {
+                id: None,
                 trigger: key_trigger!("[LEFT_SHIFT] ENTER↓"),
                 actions: key_action_seq!("A↓"),
+                repeat: Default::default(),
+                is_pass_through: false,
+                device: None,
+                window: None,
+                priority: 0,
+                target: None,
             },
             KeyTransformRule::from_str("[LEFT_SHIFT] ENTER↓ : A↓").unwrap()
         );
@@ -245,6 +601,143 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_key_transform_rule_id() {
+        let rule = key_rule!("#12 [LEFT_SHIFT] ENTER↓ : A↓");
+
+        assert_eq!(Some(12), rule.id);
+        assert_eq!(key_trigger!("[LEFT_SHIFT] ENTER↓"), rule.trigger);
+        assert_eq!("#12 [LEFT_SHIFT] ENTER↓ : A↓", rule.to_string());
+        assert_eq!(None, key_rule!("ENTER↓ : A↓").id);
+
+        assert!(KeyTransformRule::from_str("#12: A↓").is_err());
+        assert!(KeyTransformRule::from_str("#1x ENTER↓ : A↓").is_err());
+    }
+
+    #[test]
+    fn test_key_transform_rule_repeat() {
+        let rule = key_rule!("[LEFT_SHIFT] ENTER↓ : A↓ | REPEAT=500/30");
+
+        assert_eq!(key_action_seq!("A↓"), rule.actions);
+        assert_eq!(
+            KeyRepeat::Custom {
+                delay: 500,
+                interval: 30
+            },
+            rule.repeat
+        );
+        assert_eq!("[LEFT_SHIFT] ENTER↓ : A↓ | REPEAT=500/30", rule.to_string());
+
+        assert_eq!(
+            KeyRepeat::Suppress,
+            key_rule!("ENTER↓ : A↓ | NO_REPEAT").repeat
+        );
+        assert_eq!(KeyRepeat::Pass, key_rule!("ENTER↓ : A↓").repeat);
+        assert_eq!(
+            KeyRepeat::Turbo { interval: 40 },
+            key_rule!("F↓ : A↓ → A↑ | TURBO=40").repeat
+        );
+        assert!(KeyTransformRule::from_str("ENTER↓ : A↓ | ONCE").is_err());
+    }
+
+    #[test]
+    fn test_key_transform_rule_pass_through() {
+        let rule = key_rule!("CAPS_LOCK↓ : LEFT_CTRL↓ | PASS");
+
+        assert!(rule.is_pass_through);
+        assert_eq!(KeyRepeat::Pass, rule.repeat);
+        assert_eq!("CAPS_LOCK↓ : LEFT_CTRL↓ | PASS", rule.to_string());
+
+        let rule = key_rule!("ENTER↓ : A↓ | NO_REPEAT, PASS");
+        assert!(rule.is_pass_through);
+        assert_eq!(KeyRepeat::Suppress, rule.repeat);
+        assert_eq!("ENTER↓ : A↓ | NO_REPEAT, PASS", rule.to_string());
+
+        assert!(!key_rule!("ENTER↓ : A↓ | NO_REPEAT").is_pass_through);
+        assert!(KeyTransformRule::from_str("ENTER↓ : A↓ | PASS, ONCE").is_err());
+    }
+
+    #[test]
+    fn test_key_transform_rule_priority() {
+        let rule = key_rule!("F13↓ : A↓ | PRIORITY = -2, PASS");
+
+        assert_eq!(-2, rule.priority);
+        assert_eq!("F13↓ : A↓ | PASS, PRIORITY=-2", rule.to_string());
+        assert_eq!(0, key_rule!("F13↓ : A↓").priority);
+        assert_eq!("F13↓ : A↓", key_rule!("F13↓ : A↓ | PRIORITY=0").to_string());
+        assert!(KeyTransformRule::from_str("F13↓ : A↓ | PRIORITY=high").is_err());
+    }
+
+    #[test]
+    fn test_key_transform_rule_device() {
+        let rule = key_rule!("F13↓ : A↓ | DEVICE=VID_1234&PID_5678");
+
+        assert_eq!(Some("VID_1234&PID_5678".to_string()), rule.device);
+        assert_eq!("F13↓ : A↓ | DEVICE=VID_1234&PID_5678", rule.to_string());
+        assert_eq!(
+            "F13↓ : A↓ | NO_REPEAT, PASS, DEVICE=PAD",
+            key_rule!("F13↓ : A↓ | DEVICE = PAD, PASS, NO_REPEAT").to_string()
+        );
+        assert!(KeyTransformRule::from_str("F13↓ : A↓ | DEVICE=").is_err());
+
+        let mut event = key_event!("F13↓");
+        assert!(!rule.matches_device(&event));
+        assert!(key_rule!("F13↓ : A↓").matches_device(&event));
+
+        event.device = Some(r"\\?\HID#vid_1234&pid_5678#7&1a2b".into());
+        assert!(rule.matches_device(&event));
+
+        event.device = Some(r"\\?\HID#VID_0001&PID_0002#7&1a2b".into());
+        assert!(!rule.matches_device(&event));
+    }
+
+    #[test]
+    fn test_key_transform_rule_window() {
+        let rule = key_rule!(r#"#7 @window("a:b|c") [LEFT_CTRL] K↓ : A↓ | NO_REPEAT"#);
+
+        assert_eq!(Some(7), rule.id);
+        assert_eq!("a:b|c", rule.window.as_ref().unwrap().pattern());
+        assert_eq!(key_trigger!("[LEFT_CTRL] K↓"), rule.trigger);
+        assert_eq!(
+            r#"#7 @window("a:b|c") [LEFT_CTRL] K↓ : A↓ | NO_REPEAT"#,
+            rule.to_string()
+        );
+        assert!(KeyTransformRule::from_str(r#"@window("Code" K↓ : A↓"#).is_err());
+        assert!(KeyTransformRule::from_str(r#"@window("") K↓ : A↓"#).is_err());
+        assert!(KeyTransformRule::from_str(r#"@window("(") K↓ : A↓"#).is_err());
+
+        let mut event = key_event!("[LEFT_CTRL] K↓");
+        assert!(!rule.matches_window(&event));
+        assert!(key_rule!("K↓ : A↓").matches_window(&event));
+
+        event.window = Some(Arc::new(WindowInfo {
+            title: "a:b".to_string(),
+            class_name: Default::default(),
+            process_path: Default::default(),
+        }));
+        assert!(rule.matches_window(&event));
+    }
+
+    #[test]
+    fn test_key_transform_rule_target() {
+        let rule = key_rule!(r"F13↓ : A↓ | TARGET = Notepad|notepad\.exe$ , NO_REPEAT");
+
+        assert_eq!(
+            r"Notepad|notepad\.exe$",
+            rule.target.as_ref().unwrap().pattern()
+        );
+        assert_eq!(KeyRepeat::Suppress, rule.repeat);
+        assert_eq!(
+            r"F13↓ : A↓ | NO_REPEAT, TARGET=Notepad|notepad\.exe$",
+            rule.to_string()
+        );
+        assert_eq!(None, key_rule!("F13↓ : A↓").target);
+        assert!(KeyTransformRule::from_str("F13↓ : A↓ | TARGET=").is_err());
+        assert!(KeyTransformRule::from_str("F13↓ : A↓ | TARGET=(").is_err());
+        assert!(KeyTransformRule::from_str("F13↓ : A↓ | TARGET=Notepad, REPEAT=300/50").is_err());
+        assert!(KeyTransformRule::from_str("F13↓ : A↓ | TARGET=Notepad, TURBO=50").is_err());
+    }
+
     #[test]
     fn test_key_transform_rule_serialize() {
         let source = key_rule!("[LEFT_SHIFT] ENTER↓ : ENTER↓");
@@ -307,6 +800,22 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_key_transform_rules_from_str_expand_wheel() {
+        assert_eq!(
+            key_rules!(
+                r#"
+                [LEFT_ALT] WHEEL_Y↓ : VOLUME_UP↓ → VOLUME_UP↑
+                "#
+            ),
+            key_rules!(
+                r#"
+                [LEFT_ALT] WHEEL_UP : VOLUME_UP
+                "#
+            )
+        );
+    }
+
     #[test]
     fn test_key_transform_rules_from_str_expand_list_no_any_transition() {
         assert_eq!(
@@ -379,6 +888,31 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_key_transform_rules_from_str_error_position() {
+        let error = KeyTransformRules::from_str("A↓ : B↓\nC↓ : BAD↓").unwrap_err();
+
+        assert_eq!(Some("BAD".to_string()), error.token);
+        assert_eq!(Some(2), error.line);
+        assert_eq!(Some(6), error.column);
+
+        let error = KeyTransformRules::from_str("A↓ | PRIORITY=A : B↓").unwrap_err();
+
+        assert_eq!(Some(15), error.column);
+        assert_eq!(
+            "Invalid rule priority: `A` at line 1, column 15",
+            error.to_string()
+        );
+
+        let error = KeyTransformRules::from_str("A↓ : A↓ → DELAY()").unwrap_err();
+
+        assert_eq!(Some(17), error.column);
+
+        let error = KeyTransformRules::from_str("[LEFT_SHIFT + A=on] A↓ : B↓").unwrap_err();
+
+        assert_eq!(Some(15), error.column);
+    }
+
     #[test]
     fn test_key_transform_rules_deserialize() {
         assert_eq!(
@@ -391,4 +925,284 @@ pub mod tests {
             .unwrap()
         );
     }
+
+    #[test]
+    fn test_key_transform_rules_ids() {
+        let rules = key_rules!(
+            r#"
+            #1 A : B
+            C↓ : D↓
+            "#
+        );
+
+        assert_eq!(2, rules.find_by_id(1).count());
+        assert_eq!(0, rules.find_by_id(2).count());
+    }
+
+    #[test]
+    fn test_key_transform_rules_without_disabled() {
+        let rules = key_rules!(
+            r#"
+            #1 A : B
+            #2 C↓ : D↓
+            E↓ : F↓
+            "#
+        );
+
+        assert_eq!(
+            key_rules!(
+                r#"
+                #2 C↓ : D↓
+                E↓ : F↓
+                "#
+            ),
+            rules.without_disabled(&BTreeSet::from([1, 3]))
+        );
+        assert_eq!(rules, rules.without_disabled(&BTreeSet::new()));
+    }
+
+    #[test]
+    fn test_key_transform_rules_insert() {
+        let mut rules = key_rules!(
+            r#"
+            A↓ : B↓
+            C↓ : D↓
+            "#
+        );
+        rules.insert(key_rule!("A↓ : E↓"));
+
+        assert_eq!(
+            key_rules!(
+                r#"
+                C↓ : D↓
+                A↓ : E↓
+                "#
+            ),
+            rules
+        );
+    }
+
+    #[test]
+    fn test_key_transform_rules_find_rule() {
+        let rules = key_rules!(
+            r#"
+            A↓ : B↓
+            [LEFT_SHIFT] A↓ : C↓
+            "#
+        );
+
+        assert_eq!(
+            Some(key_rule!("A↓ : B↓")),
+            rules.find_rule(&key_event!("[LEFT_CTRL] A↓"))
+        );
+        assert_eq!(
+            Some(key_rule!("[LEFT_SHIFT] A↓ : C↓")),
+            rules.find_rule(&key_event!("[LEFT_SHIFT] A↓"))
+        );
+        assert_eq!(None, rules.find_rule(&key_event!("[] A↑")));
+    }
+
+    #[test]
+    fn test_key_transform_rules_assign_ids() {
+        let mut rules = key_rules!(
+            r#"
+            A↓ : B↓
+            #5 C↓ : D↓
+            E↓ : F↓
+            "#
+        );
+        assert!(rules.assign_ids(0));
+        assert_eq!(
+            vec![Some(6), Some(5), Some(7)],
+            rules.iter().map(|r| r.id).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(7), rules.max_id());
+        assert!(!rules.assign_ids(0));
+
+        let mut rules = key_rules!("A↓ : B↓");
+        rules.assign_ids(10);
+        assert_eq!(Some(11), rules.max_id());
+    }
+
+    #[test]
+    fn test_key_transform_rules_serialize_ids() {
+        let source = key_rules!(
+            r#"
+            #3 A : B
+            C↓ : D↓ | NO_REPEAT
+            "#
+        );
+        let text = toml::to_string(&source).unwrap();
+
+        assert!(text.contains(r##""#3 A↓" = "B↓""##));
+        assert_eq!(source, toml::from_str(&text).unwrap());
+    }
+
+    #[test]
+    fn test_key_transform_rules_serialize_order() {
+        let text = r#"
+            "[NUM_LOCK=on] A↓" = "B↓"
+            "[CAPS_LOCK=on] A↓" = "C↓"
+            "#;
+        let rules: KeyTransformRules = toml::from_str(text).unwrap();
+        let mut event = key_event!("[] A↓");
+        event.locks = KeyLocks::capture(|k| k == Key::NumLock || k == Key::CapsLock);
+
+        /* the rules are equally specific so the first one in the file wins */
+        assert_eq!(
+            Some(key_rule!("[NUM_LOCK=on] A↓ : B↓")),
+            rules.find_rule(&event)
+        );
+    }
+
+    #[test]
+    fn test_key_transform_rules_serialize_options() {
+        let source = key_rules!(
+            r#"
+            A↓ : B↓ | DEVICE=PAD
+            A↓ : C↓ | DEVICE=MOUSE, NO_REPEAT
+            A↓ : D↓ | PRIORITY=1
+            A↓ : E↓ | TARGET=Notepad
+            A↓ : F↓ | PASS
+            "#
+        );
+        let text = toml::to_string(&source).unwrap();
+
+        assert!(text.contains(r#""A↓ | DEVICE=MOUSE" = "C↓ | NO_REPEAT""#));
+        assert_eq!(source, toml::from_str(&text).unwrap());
+        assert_eq!(
+            source,
+            serde_json::from_str(&serde_json::to_string(&source).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_transform_rules_serialize_overridden() {
+        let source = key_rules!(
+            r#"
+            A↓ : B↓
+            C↓ : D↓
+            A↓ : E↓
+            "#
+        );
+        let text = toml::to_string(&source).unwrap();
+
+        assert_eq!(
+            key_rules!(
+                r#"
+                C↓ : D↓
+                A↓ : E↓
+                "#
+            ),
+            toml::from_str(&text).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_transform_rules_deserialize_sequence() {
+        assert_eq!(
+            key_rules!(
+                r#"
+                A↓ : B↓ | DEVICE=PAD
+                C↓ : D↓
+                C↑ : D↑
+                "#
+            ),
+            serde_json::from_str(r#"["A↓ : B↓ | DEVICE=PAD", "C : D"]"#).unwrap()
+        );
+    }
+
+    // Properties
+
+    /// Keys which names can be written in the rules. Wheel keys expand differently and the
+    /// names of the scan-code-only keys contain the sequence separator.
+    fn rule_key_names() -> Vec<&'static str> {
+        (1..=u8::MAX)
+            .filter_map(Key::from_index)
+            .filter(|key| !key.is_wheel() && *key != Key::Packet && !key.as_str().starts_with('<'))
+            .map(|key| key.as_str())
+            .collect()
+    }
+
+    fn key_action_str() -> impl Strategy<Value = String> {
+        (prop::sample::select(rule_key_names()), prop::bool::ANY)
+            .prop_map(|(key, is_down)| format!("{key}{}", if is_down { '↓' } else { '↑' }))
+    }
+
+    fn rule_str() -> impl Strategy<Value = String> {
+        let id = prop::option::of(0..10_000u32).prop_map(|id| match id {
+            Some(id) => format!("#{id} "),
+            None => String::new(),
+        });
+        let window = prop::option::of("[A-Za-z]{1,8}").prop_map(|pattern| match pattern {
+            Some(pattern) => format!("@window(\"{pattern}\") "),
+            None => String::new(),
+        });
+        let modifiers = prop::option::of(prop::sample::subsequence(
+            vec!["LEFT_CTRL", "LEFT_SHIFT", "LEFT_ALT", "RIGHT_WIN"],
+            0..=4,
+        ))
+        .prop_map(|keys| match keys {
+            Some(keys) => format!("[{}] ", keys.join(" + ")),
+            None => String::new(),
+        });
+        let actions =
+            prop::collection::vec(key_action_str(), 1..5).prop_map(|actions| actions.join(" → "));
+        let options = prop::collection::vec(
+            prop_oneof![
+                Just("NO_REPEAT".to_string()),
+                (0..1000u32, 1..100u32).prop_map(|(d, i)| format!("REPEAT={d}/{i}")),
+                (1..100u32).prop_map(|i| format!("TURBO={i}")),
+                Just("PASS".to_string()),
+                "[A-Za-z]{1,8}".prop_map(|name| format!("DEVICE={name}")),
+                (-100..100i32).prop_map(|p| format!("PRIORITY={p}")),
+            ],
+            0..3,
+        )
+        .prop_map(|options| match options.is_empty() {
+            true => String::new(),
+            false => format!(" | {}", options.join(", ")),
+        });
+
+        (id, window, modifiers, key_action_str(), actions, options).prop_map(
+            |(id, window, modifiers, trigger, actions, options)| {
+                format!("{id}{window}{modifiers}{trigger} : {actions}{options}")
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn test_parse_never_panics(s in "\\PC*") {
+            let _ = KeyTransformRule::from_str(&s);
+            let _ = KeyTransformRules::from_str(&s);
+            let _ = KeyTrigger::from_str(&s);
+            let _ = KeyActionSequence::from_str(&s);
+            let _ = KeyAction::from_str(&s);
+        }
+
+        #[test]
+        fn test_parse_rule_syntax_never_panics(
+            s in "[#@\\[\\]:|,=+→>↓↑*~ A-Z_0-9()\"{}]{0,40}"
+        ) {
+            let _ = KeyTransformRule::from_str(&s);
+            let _ = KeyTransformRules::from_str(&s);
+            let _ = KeyTrigger::from_str(&s);
+            let _ = KeyActionSequence::from_str(&s);
+            let _ = KeyAction::from_str(&s);
+        }
+
+        #[test]
+        fn test_rule_display_round_trip(s in rule_str()) {
+            let rule = KeyTransformRule::from_str(&s).unwrap();
+            prop_assert_eq!(&rule, &KeyTransformRule::from_str(&rule.to_string()).unwrap());
+        }
+
+        #[test]
+        fn test_rule_table_round_trip(s in rule_str()) {
+            let rules = KeyTransformRules::from(vec![KeyTransformRule::from_str(&s).unwrap()]);
+            let text = toml::to_string(&rules).unwrap();
+            prop_assert_eq!(&rules, &toml::from_str::<KeyTransformRules>(&text).unwrap());
+        }
+    }
 }
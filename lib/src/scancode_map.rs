@@ -0,0 +1,256 @@
+use crate::action::{KeyAction, KeyActionSequence, KeySequenceItem};
+use crate::error::KeyError;
+use crate::key::Key;
+use crate::key_error;
+use crate::repeat::KeyRepeat;
+use crate::rule::{KeyTransformRule, KeyTransformRules};
+use crate::transition::KeyTransition::{Down, Up};
+use crate::trigger::KeyTrigger;
+use std::fmt::Write;
+
+/// Registry key of the `Scancode Map` value.
+pub const SCANCODE_MAP_KEY: &str = r"SYSTEM\CurrentControlSet\Control\Keyboard Layout";
+pub const SCANCODE_MAP_VALUE: &str = "Scancode Map";
+
+/* version and flags */
+const HEADER_SIZE: usize = 8;
+const ENTRY_SIZE: usize = 4;
+
+/// Scan code remapping applied by the keyboard driver so it works without the application
+/// running, e.g. on the logon screen. Target `UNASSIGNED` disables the source key.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScancodeMap {
+    mappings: Vec<(Key, Key)>,
+}
+
+impl ScancodeMap {
+    /// Collects simple 1:1 key remaps of the rules. Other rules are skipped as the map
+    /// cannot express them. Both press and release of the key must be remapped.
+    pub fn from_rules(rules: &KeyTransformRules) -> Self {
+        let mut down = Vec::new();
+        let mut up = Vec::new();
+        for rule in rules.iter() {
+            if let Some(target) = simple_remap_target(rule) {
+                let mappings = if rule.trigger.action.transition == Down {
+                    &mut down
+                } else {
+                    &mut up
+                };
+                /* later rule overrides previous one like in the hook */
+                mappings.retain(|(source, _)| *source != rule.trigger.action.key);
+                mappings.push((rule.trigger.action.key, target));
+            }
+        }
+
+        Self {
+            mappings: down.into_iter().filter(|m| up.contains(m)).collect(),
+        }
+    }
+
+    /// Source and target keys of the mappings.
+    pub fn mappings(&self) -> &[(Key, Key)] {
+        &self.mappings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Returns rules doing the same remapping as the map.
+    pub fn to_rules(&self) -> KeyTransformRules {
+        let mut rules = Vec::new();
+        for &(source, target) in &self.mappings {
+            for transition in [Down, Up] {
+                rules.push(KeyTransformRule {
+                    id: None,
+                    trigger: KeyTrigger {
+                        action: KeyAction::new(source, transition),
+                        ..Default::default()
+                    },
+                    actions: KeyActionSequence::new(vec![KeyAction::new(target, transition)]),
+                    repeat: KeyRepeat::Pass,
+                    is_pass_through: false,
+                    device: None,
+                    window: None,
+                    priority: 0,
+                    target: None,
+                });
+            }
+        }
+        KeyTransformRules::from(rules)
+    }
+
+    /// Parses binary `Scancode Map` registry value.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, KeyError> {
+        let entries = data
+            .get(HEADER_SIZE..)
+            .filter(|e| !e.is_empty() && e.len() % ENTRY_SIZE == 0)
+            .ok_or_else(|| key_error!("Invalid scancode map size: {}", data.len()))?;
+        let mut words = entries
+            .chunks_exact(ENTRY_SIZE)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]));
+
+        let count = words.next().unwrap_or_default() as usize;
+        if count == 0 || count != entries.len() / ENTRY_SIZE - 1 {
+            return Err(key_error!("Invalid scancode map entries count: {count}"));
+        }
+
+        let mut mappings = Vec::new();
+        for entry in words.take(count - 1) {
+            let target = key_of_scan_code(entry as u16)?;
+            let source = key_of_scan_code((entry >> 16) as u16)?;
+            mappings.push((source, target));
+        }
+        Ok(Self { mappings })
+    }
+
+    /// Returns binary `Scancode Map` registry value.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data.extend((self.mappings.len() as u32 + 1).to_le_bytes());
+        for &(source, target) in &self.mappings {
+            let entry = target.sc_ext() as u32 | (source.sc_ext() as u32) << 16;
+            data.extend(entry.to_le_bytes());
+        }
+        data.extend(0u32.to_le_bytes());
+        data
+    }
+
+    /// Returns `.reg` file text setting the map. Empty map deletes the registry value.
+    pub fn to_reg_file(&self) -> String {
+        let mut s = String::from("Windows Registry Editor Version 5.00\r\n\r\n");
+        write!(s, "[HKEY_LOCAL_MACHINE\\{SCANCODE_MAP_KEY}]\r\n").unwrap();
+        if self.is_empty() {
+            write!(s, "\"{SCANCODE_MAP_VALUE}\"=-\r\n").unwrap();
+        } else {
+            let hex: Vec<_> = self.to_bytes().iter().map(|b| format!("{b:02x}")).collect();
+            write!(s, "\"{SCANCODE_MAP_VALUE}\"=hex:{}\r\n", hex.join(",")).unwrap();
+        }
+        s
+    }
+}
+
+/// Target key if the rule is a plain remap of the key press or release.
+fn simple_remap_target(rule: &KeyTransformRule) -> Option<Key> {
+    let trigger = &rule.trigger;
+    let is_plain = *trigger
+        == KeyTrigger {
+            action: trigger.action,
+            ..Default::default()
+        }
+        && rule.repeat == KeyRepeat::Pass
+        && !rule.is_pass_through
+        && rule.device.is_none()
+        && trigger.action.key.sc() != 0;
+    if !is_plain {
+        return None;
+    }
+
+    match rule.actions.iter().as_slice() {
+        [KeySequenceItem::Action(action)]
+            if action.transition == trigger.action.transition
+                && (action.key.sc() != 0 || action.key == Key::Unassigned) =>
+        {
+            Some(action.key)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the key of the scan code. Specific keys are preferred to generic modifiers.
+fn key_of_scan_code(code: u16) -> Result<Key, KeyError> {
+    if code == 0 {
+        return Ok(Key::Unassigned);
+    }
+
+    (1..=u8::MAX)
+        .filter_map(Key::from_index)
+        .filter(|key| !matches!(key, Key::Shift | Key::Ctrl | Key::Menu | Key::NumClear))
+        .find(|key| key.sc_ext() == code)
+        .ok_or_else(|| key_error!("Unsupported scan code: 0x{code:04X}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key::Key;
+    use crate::key_rules;
+    use crate::rule::KeyTransformRules;
+    use crate::scancode_map::ScancodeMap;
+    use std::str::FromStr;
+
+    const CAPS_TO_CTRL: [u8; 20] = [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x1D, 0x00, 0x3A,
+        0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_from_rules() {
+        let rules = key_rules!(
+            r#"
+            CAPS_LOCK : LEFT_CTRL
+            RIGHT_ALT↓ : RIGHT_WIN↓
+            [LEFT_SHIFT] A : B
+            C↓ : D↓ → E↓
+            F : G
+            F : H
+            "#
+        );
+
+        assert_eq!(
+            &[(Key::CapsLock, Key::LeftCtrl), (Key::F, Key::H)],
+            ScancodeMap::from_rules(&rules).mappings()
+        );
+    }
+
+    #[test]
+    fn test_to_rules() {
+        let map = ScancodeMap::from_bytes(&CAPS_TO_CTRL).unwrap();
+
+        assert_eq!(key_rules!("CAPS_LOCK : LEFT_CTRL"), map.to_rules());
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        let map = ScancodeMap::from_rules(&key_rules!("CAPS_LOCK : LEFT_CTRL"));
+
+        assert_eq!(CAPS_TO_CTRL.to_vec(), map.to_bytes());
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let map = ScancodeMap::from_rules(&key_rules!(
+            r#"
+            RIGHT_CTRL : NUM_ENTER
+            INSERT↓ : UNASSIGNED↓
+            INSERT↑ : UNASSIGNED↑
+            "#
+        ));
+
+        assert_eq!(map, ScancodeMap::from_bytes(&map.to_bytes()).unwrap());
+        assert_eq!(
+            &[(Key::CapsLock, Key::LeftCtrl)],
+            ScancodeMap::from_bytes(&CAPS_TO_CTRL).unwrap().mappings()
+        );
+
+        assert!(ScancodeMap::from_bytes(&CAPS_TO_CTRL[..10]).is_err());
+        assert!(ScancodeMap::from_bytes(&CAPS_TO_CTRL[..16]).is_err());
+        assert!(ScancodeMap::from_bytes(&[0; 8]).is_err());
+    }
+
+    #[test]
+    fn test_to_reg_file() {
+        let map = ScancodeMap::from_bytes(&CAPS_TO_CTRL).unwrap();
+
+        assert_eq!(
+            "Windows Registry Editor Version 5.00\r\n\r\n\
+            [HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Control\\Keyboard Layout]\r\n\
+            \"Scancode Map\"=hex:00,00,00,00,00,00,00,00,02,00,00,00,1d,00,3a,00,00,00,00,00\r\n",
+            map.to_reg_file()
+        );
+        assert!(
+            ScancodeMap::default()
+                .to_reg_file()
+                .ends_with("\"Scancode Map\"=-\r\n")
+        );
+    }
+}
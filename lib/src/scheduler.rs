@@ -0,0 +1,177 @@
+use crate::hook::send_input;
+use log::warn;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::System::Threading::{
+    CREATE_WAITABLE_TIMER_HIGH_RESOLUTION, CreateEventW, CreateWaitableTimerExW, INFINITE,
+    SetEvent, SetWaitableTimer, TIMER_ALL_ACCESS, WaitForMultipleObjects,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::INPUT;
+use windows::core::PCWSTR;
+
+/// Timing accuracy of the repeat ticks.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RepeatStats {
+    pub ticks: u32,
+    /// Sum of the ticks lateness in microseconds.
+    pub total_jitter_us: u64,
+    pub max_jitter_us: u64,
+}
+
+impl RepeatStats {
+    pub fn mean_jitter_us(&self) -> u64 {
+        if self.ticks == 0 {
+            0
+        } else {
+            self.total_jitter_us / self.ticks as u64
+        }
+    }
+
+    fn record(&mut self, jitter: Duration) {
+        let jitter = jitter.as_micros() as u64;
+        self.ticks += 1;
+        self.total_jitter_us += jitter;
+        self.max_jitter_us = self.max_jitter_us.max(jitter);
+    }
+}
+
+impl Display for RepeatStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ticks, jitter mean {} µs, max {} µs",
+            self.ticks,
+            self.mean_jitter_us(),
+            self.max_jitter_us
+        )
+    }
+}
+
+/// Kernel object handle closed on drop.
+struct OwnedHandle(HANDLE);
+
+/* kernel object handles may be used from any thread */
+unsafe impl Send for OwnedHandle {}
+unsafe impl Sync for OwnedHandle {}
+
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+/// Sends the input repeatedly from its own thread woken by a high resolution waitable timer.
+/// Thread timers of the hook cannot fire more often than about 64 times per second.
+pub(crate) struct RepeatScheduler {
+    stop_event: Arc<OwnedHandle>,
+    stats: Arc<Mutex<RepeatStats>>,
+}
+
+impl RepeatScheduler {
+    pub(crate) fn start(
+        input: Vec<INPUT>,
+        delay: u32,
+        interval: u32,
+    ) -> windows::core::Result<Self> {
+        let stop_event = Arc::new(OwnedHandle(unsafe {
+            CreateEventW(None, true, false, PCWSTR::null())?
+        }));
+        let stats = Arc::new(Mutex::new(RepeatStats::default()));
+
+        thread::spawn({
+            let stop_event = stop_event.clone();
+            let stats = stats.clone();
+            move || {
+                run(&input, delay, interval, &stop_event, &stats)
+                    .unwrap_or_else(|e| warn!("Repeat scheduler failed: {}", e));
+            }
+        });
+
+        Ok(Self { stop_event, stats })
+    }
+
+    pub(crate) fn stats(&self) -> RepeatStats {
+        *self.stats.lock().unwrap()
+    }
+}
+
+impl Drop for RepeatScheduler {
+    /* not waiting for the thread since it may be blocked sending input into the hook */
+    fn drop(&mut self) {
+        unsafe {
+            SetEvent(self.stop_event.0)
+                .unwrap_or_else(|e| warn!("Failed to stop repeat scheduler: {}", e));
+        }
+    }
+}
+
+fn run(
+    input: &[INPUT],
+    delay: u32,
+    interval: u32,
+    stop_event: &OwnedHandle,
+    stats: &Mutex<RepeatStats>,
+) -> windows::core::Result<()> {
+    let timer = OwnedHandle(unsafe {
+        /* high resolution timers are not available before Windows 10 1803 */
+        CreateWaitableTimerExW(
+            None,
+            PCWSTR::null(),
+            CREATE_WAITABLE_TIMER_HIGH_RESOLUTION,
+            TIMER_ALL_ACCESS.0,
+        )
+        .or_else(|_| CreateWaitableTimerExW(None, PCWSTR::null(), 0, TIMER_ALL_ACCESS.0))?
+    });
+
+    let interval = Duration::from_millis(interval as u64);
+    let mut deadline = Instant::now() + Duration::from_millis(delay as u64);
+    loop {
+        /* negative due time is relative and measured in 100 ns units */
+        let due_time = -((deadline
+            .saturating_duration_since(Instant::now())
+            .as_nanos()
+            / 100) as i64);
+        unsafe {
+            SetWaitableTimer(timer.0, &due_time, 0, None, None, false)?;
+            if WaitForMultipleObjects(&[stop_event.0, timer.0], false, INFINITE) == WAIT_OBJECT_0 {
+                return Ok(());
+            }
+        }
+
+        let now = Instant::now();
+        stats
+            .lock()
+            .unwrap()
+            .record(now.saturating_duration_since(deadline));
+        send_input(input);
+
+        /* skip ticks missed while the system was busy instead of sending them at once */
+        deadline = (deadline + interval).max(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scheduler::RepeatStats;
+    use std::time::Duration;
+
+    #[test]
+    fn test_repeat_stats() {
+        let mut stats = RepeatStats::default();
+        assert_eq!(0, stats.mean_jitter_us());
+
+        stats.record(Duration::from_micros(100));
+        stats.record(Duration::from_micros(300));
+        stats.record(Duration::from_micros(200));
+
+        assert_eq!(3, stats.ticks);
+        assert_eq!(200, stats.mean_jitter_us());
+        assert_eq!(300, stats.max_jitter_us);
+        assert_eq!("3 ticks, jitter mean 200 µs, max 300 µs", stats.to_string());
+    }
+}
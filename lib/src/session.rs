@@ -0,0 +1,320 @@
+use log::{debug, warn};
+use std::cell::Cell;
+use std::ffi::c_void;
+use windows::Win32::Foundation::{HANDLE, HWND, LPARAM, WPARAM};
+use windows::Win32::System::RemoteDesktop::{
+    NOTIFY_FOR_THIS_SESSION, WTSRegisterSessionNotification, WTSUnRegisterSessionNotification,
+};
+use windows::Win32::System::StationsAndDesktops::{
+    CloseDesktop, DESKTOP_CONTROL_FLAGS, DESKTOP_READOBJECTS, GetUserObjectInformationW,
+    OpenInputDesktop, UOI_NAME,
+};
+use windows::Win32::UI::Accessibility::{HWINEVENTHOOK, SetWinEventHook, UnhookWinEvent};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EVENT_SYSTEM_DESKTOPSWITCH, GetSystemMetrics, PostMessageW, SM_REMOTESESSION,
+    WINEVENT_OUTOFCONTEXT, WM_WTSSESSION_CHANGE, WTS_CONSOLE_CONNECT, WTS_REMOTE_CONNECT,
+    WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
+};
+
+const WM_DESKTOP_SWITCH_NOTIFY: u32 = 88478;
+
+thread_local! {
+    static DESKTOP_SWITCH_RECEIVER: Cell<Option<HWND>> = const { Cell::new(None) };
+}
+
+/// State of the user session the hook runs in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SessionState {
+    #[default]
+    Active,
+    /// Lock screen is shown.
+    Locked,
+    /// Input goes to a desktop other than the user's one, e.g. UAC prompt or Ctrl+Alt+Del screen.
+    SecureDesktop,
+    /// Session is controlled remotely. The remote client has its own remapping.
+    Remote,
+}
+
+impl SessionState {
+    /// Returns `true` if the hook should not apply the rules in this state.
+    pub fn is_suspended(self) -> bool {
+        self != Self::Active
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct SessionFlags {
+    is_locked: bool,
+    is_secure_desktop: bool,
+    is_remote: bool,
+}
+
+impl SessionFlags {
+    fn state(self) -> SessionState {
+        if self.is_locked {
+            SessionState::Locked
+        } else if self.is_secure_desktop {
+            SessionState::SecureDesktop
+        } else if self.is_remote {
+            SessionState::Remote
+        } else {
+            SessionState::Active
+        }
+    }
+}
+
+/// Watches the session lock, desktop switches and remote connections.
+///
+/// Messages of the owner window must be passed to [`SessionWatcher::handle_message`].
+#[derive(Debug, Default)]
+pub struct SessionWatcher {
+    hwnd: Cell<Option<HWND>>,
+    win_event_hook: Cell<Option<HWINEVENTHOOK>>,
+    flags: Cell<SessionFlags>,
+}
+
+impl SessionWatcher {
+    pub fn setup(&self, owner: HWND) {
+        self.hwnd.set(Some(owner));
+        self.flags.set(SessionFlags {
+            is_locked: false,
+            is_secure_desktop: is_secure_desktop(),
+            is_remote: unsafe { GetSystemMetrics(SM_REMOTESESSION) } != 0,
+        });
+
+        if let Err(e) = unsafe { WTSRegisterSessionNotification(owner, NOTIFY_FOR_THIS_SESSION) } {
+            warn!("Failed to register session notification: {}", e);
+        }
+
+        DESKTOP_SWITCH_RECEIVER.set(Some(owner));
+        let hook = unsafe {
+            SetWinEventHook(
+                EVENT_SYSTEM_DESKTOPSWITCH,
+                EVENT_SYSTEM_DESKTOPSWITCH,
+                None,
+                Some(desktop_switch_proc),
+                0,
+                0,
+                WINEVENT_OUTOFCONTEXT,
+            )
+        };
+        if hook.is_invalid() {
+            warn!("Failed to set desktop switch hook");
+        } else {
+            self.win_event_hook.set(Some(hook));
+        }
+
+        debug!("Session watch started: {:?}", self.state());
+    }
+
+    pub fn stop(&self) {
+        let Some(hwnd) = self.hwnd.take() else {
+            return;
+        };
+
+        if let Some(hook) = self.win_event_hook.take()
+            && !unsafe { UnhookWinEvent(hook) }.as_bool()
+        {
+            warn!("Failed to unhook desktop switch hook");
+        }
+        DESKTOP_SWITCH_RECEIVER.set(None);
+
+        if let Err(e) = unsafe { WTSUnRegisterSessionNotification(hwnd) } {
+            warn!("Failed to unregister session notification: {}", e);
+        }
+
+        debug!("Session watch stopped");
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.flags.get().state()
+    }
+
+    /// Returns the new state if the message changed it.
+    pub fn handle_message(&self, msg: u32, w_param: usize) -> Option<SessionState> {
+        self.hwnd.get()?;
+
+        let flags = match msg {
+            WM_WTSSESSION_CHANGE => self.apply_session_change(w_param as u32)?,
+            WM_DESKTOP_SWITCH_NOTIFY => SessionFlags {
+                is_secure_desktop: is_secure_desktop(),
+                ..self.flags.get()
+            },
+            _ => return None,
+        };
+
+        let previous = self.flags.replace(flags).state();
+        let state = flags.state();
+        if state == previous {
+            return None;
+        }
+
+        debug!("Session state: {:?}", state);
+        Some(state)
+    }
+
+    fn apply_session_change(&self, change: u32) -> Option<SessionFlags> {
+        let mut flags = self.flags.get();
+        match change {
+            WTS_SESSION_LOCK => flags.is_locked = true,
+            WTS_SESSION_UNLOCK => flags.is_locked = false,
+            WTS_REMOTE_CONNECT => flags.is_remote = true,
+            WTS_CONSOLE_CONNECT => flags.is_remote = false,
+            _ => return None,
+        }
+        Some(flags)
+    }
+}
+
+impl Drop for SessionWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Returns `true` if the input goes to a desktop other than the default one. The secure
+/// desktops cannot even be opened by the user processes.
+fn is_secure_desktop() -> bool {
+    let desktop =
+        match unsafe { OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_READOBJECTS) } {
+            Ok(desktop) => desktop,
+            Err(_) => return true,
+        };
+
+    let mut name = [0u16; 64];
+    let result = unsafe {
+        GetUserObjectInformationW(
+            HANDLE(desktop.0),
+            UOI_NAME,
+            Some(name.as_mut_ptr() as *mut c_void),
+            size_of_val(&name) as u32,
+            None,
+        )
+    };
+    if let Err(e) = unsafe { CloseDesktop(desktop) } {
+        warn!("Failed to close input desktop: {}", e);
+    }
+
+    match result {
+        Ok(_) => {
+            let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+            !String::from_utf16_lossy(&name[..len]).eq_ignore_ascii_case("Default")
+        }
+        Err(e) => {
+            warn!("Failed to get input desktop name: {}", e);
+            false
+        }
+    }
+}
+
+/* called on the thread that set the hook when it dispatches messages */
+unsafe extern "system" fn desktop_switch_proc(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    _hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    if let Some(receiver) = DESKTOP_SWITCH_RECEIVER.get()
+        && let Err(e) = unsafe {
+            PostMessageW(
+                Some(receiver),
+                WM_DESKTOP_SWITCH_NOTIFY,
+                WPARAM(0),
+                LPARAM(0),
+            )
+        }
+    {
+        warn!("Failed to post desktop switch notification: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::session::{SessionFlags, SessionState, SessionWatcher};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        WM_KEYDOWN, WM_WTSSESSION_CHANGE, WTS_CONSOLE_CONNECT, WTS_REMOTE_CONNECT,
+        WTS_SESSION_LOCK, WTS_SESSION_LOGON, WTS_SESSION_UNLOCK,
+    };
+
+    fn watcher() -> SessionWatcher {
+        /* not registered so that the test does not depend on the session */
+        let watcher = SessionWatcher::default();
+        watcher.hwnd.set(Some(HWND::default()));
+        watcher
+    }
+
+    #[test]
+    fn test_state_priority() {
+        let flags = SessionFlags {
+            is_locked: true,
+            is_secure_desktop: true,
+            is_remote: true,
+        };
+        assert_eq!(SessionState::Locked, flags.state());
+        assert_eq!(
+            SessionState::SecureDesktop,
+            SessionFlags {
+                is_locked: false,
+                ..flags
+            }
+            .state()
+        );
+        assert_eq!(
+            SessionState::Remote,
+            SessionFlags {
+                is_remote: true,
+                ..SessionFlags::default()
+            }
+            .state()
+        );
+        assert_eq!(SessionState::Active, SessionFlags::default().state());
+        assert!(!SessionState::Active.is_suspended());
+        assert!(SessionState::Remote.is_suspended());
+    }
+
+    #[test]
+    fn test_handle_session_change() {
+        let watcher = watcher();
+
+        assert_eq!(
+            Some(SessionState::Locked),
+            watcher.handle_message(WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK as usize)
+        );
+        assert_eq!(
+            None,
+            watcher.handle_message(WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK as usize)
+        );
+        assert_eq!(
+            None,
+            watcher.handle_message(WM_WTSSESSION_CHANGE, WTS_REMOTE_CONNECT as usize)
+        );
+        assert_eq!(
+            Some(SessionState::Remote),
+            watcher.handle_message(WM_WTSSESSION_CHANGE, WTS_SESSION_UNLOCK as usize)
+        );
+        assert_eq!(
+            Some(SessionState::Active),
+            watcher.handle_message(WM_WTSSESSION_CHANGE, WTS_CONSOLE_CONNECT as usize)
+        );
+        assert_eq!(
+            None,
+            watcher.handle_message(WM_WTSSESSION_CHANGE, WTS_SESSION_LOGON as usize)
+        );
+        assert_eq!(None, watcher.handle_message(WM_KEYDOWN, 0));
+    }
+
+    #[test]
+    fn test_not_started() {
+        let watcher = SessionWatcher::default();
+
+        assert_eq!(
+            None,
+            watcher.handle_message(WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK as usize)
+        );
+        assert_eq!(SessionState::Active, watcher.state());
+    }
+}
@@ -16,6 +16,22 @@ use KeyTransition::{Down, Up};
 pub struct KeyboardState([u64; 4]);
 
 impl KeyboardState {
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    /// Returns pressed keys in the order of their codes.
+    pub fn keys(&self) -> impl Iterator<Item = Key> + '_ {
+        (0..=255)
+            .filter(|&i| self.is_bit_set(i))
+            .filter_map(Key::from_index)
+    }
+
+    pub(crate) fn contains(&self, key: Key) -> bool {
+        self.is_bit_set(key as u8)
+    }
+
+    #[cfg(feature = "win32")]
     pub(crate) fn remove(&mut self, action: &KeyAction) {
         self.clear_bit(action.key as u8);
     }
@@ -27,6 +43,24 @@ impl KeyboardState {
         }
     }
 
+    /// Returns the bits of the pressed left and right SHIFT, CTRL, ALT and WIN keys
+    /// or `None` if any other key is pressed.
+    #[inline]
+    pub(crate) fn modifiers_mask(&self) -> Option<u8> {
+        /* LEFT_SHIFT..RIGHT_ALT are 0xA0..0xA5, LEFT_WIN and RIGHT_WIN are 0x5B and 0x5C */
+        const SHIFT_CTRL_ALT_BIT: u64 = 0xA0 - 128;
+        const WIN_BIT: u64 = 0x5B - 64;
+        let [low, win, shift_ctrl_alt, high] = self.0;
+        if low != 0
+            || high != 0
+            || win & !(0b11 << WIN_BIT) != 0
+            || shift_ctrl_alt & !(0x3F << SHIFT_CTRL_ALT_BIT) != 0
+        {
+            return None;
+        }
+        Some((shift_ctrl_alt >> SHIFT_CTRL_ALT_BIT) as u8 | ((win >> WIN_BIT) as u8) << 6)
+    }
+
     #[inline]
     fn is_bit_set(&self, index: u8) -> bool {
         let (part_index, bit_index) = self.bit_pos(index);
@@ -71,7 +105,8 @@ impl FromStr for KeyboardState {
         let mut this = Self::default();
         for part in s.split('+') {
             let name = part.trim();
-            let key = Key::from_str(name).ok_or(key_error!("Invalid key name: `{}`", name))?;
+            let key = Key::from_str(name)
+                .ok_or_else(|| key_error!("Invalid key name: `{}`", name).with_token_in(s, name))?;
             this.set_bit(key as u8);
         }
         Ok(this)
@@ -97,7 +132,7 @@ impl Display for KeyboardState {
             }
             is_first = false;
 
-            f.write_str(key.as_str())?;
+            f.write_str(key.name())?;
         }
 
         Ok(())
@@ -186,6 +221,24 @@ pub mod tests {
         assert!(state.is_bit_set(41));
     }
 
+    #[test]
+    fn test_keyboard_state_modifiers_mask() {
+        assert_eq!(Some(0), KeyboardState::default().modifiers_mask());
+        assert_eq!(
+            Some(0b1000_0001),
+            kbd_state_from_keys(&[Key::LeftShift, Key::RightWin]).modifiers_mask()
+        );
+        assert_eq!(
+            Some(0b0110_0000),
+            kbd_state_from_keys(&[Key::RightAlt, Key::LeftWin]).modifiers_mask()
+        );
+        assert_eq!(
+            None,
+            kbd_state_from_keys(&[Key::LeftCtrl, Key::A]).modifiers_mask()
+        );
+        assert_eq!(None, kbd_state_from_keys(&[Key::Shift]).modifiers_mask());
+    }
+
     #[test]
     fn test_keyboard_state_to_string() {
         assert_eq!(
@@ -0,0 +1,203 @@
+use crate::event::KeyEvent;
+use crate::key::Key;
+use crate::notify::KeyEventNotification;
+use crate::transition::KeyTransition::Down;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Pause between the key presses longer than this one is not counted as typing time.
+pub const IDLE_INTERVAL: u32 = 2000;
+
+/// Aggregated usage of the keyboard. Only the user input is counted, neither the auto-repeat
+/// nor the input sent by the rules.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyStats {
+    /// Press counts by the key names.
+    key_presses: BTreeMap<String, u64>,
+    /// Application counts of the rules as written in the rules source.
+    rules_fired: BTreeMap<String, u64>,
+    /// Sum of the intervals between the successive key presses in milliseconds.
+    typing_time: u64,
+    #[serde(skip)]
+    last_press_time: Option<u32>,
+}
+
+impl KeyStats {
+    pub fn record(&mut self, notification: &KeyEventNotification) {
+        let event = &notification.event;
+        if !Self::is_counted(event) {
+            return;
+        }
+
+        *self
+            .key_presses
+            .entry(event.trigger.action.key.as_str().to_string())
+            .or_default() += 1;
+
+        if let (Some(rule), None) = (&notification.rule, &notification.error) {
+            *self.rules_fired.entry(rule.to_string()).or_default() += 1;
+        }
+
+        if let Some(last) = self.last_press_time.replace(event.time) {
+            let interval = event.time.wrapping_sub(last);
+            if interval < IDLE_INTERVAL {
+                self.typing_time += interval as u64;
+            }
+        }
+    }
+
+    fn is_counted(event: &KeyEvent) -> bool {
+        event.trigger.action.transition == Down
+            && !event.is_repeat
+            && !event.is_injected
+            && !event.is_private
+    }
+
+    pub fn total_presses(&self) -> u64 {
+        self.key_presses.values().sum()
+    }
+
+    pub fn presses(&self, key: Key) -> u64 {
+        self.key_presses.get(key.as_str()).copied().unwrap_or(0)
+    }
+
+    /// Returns the press counts of the keys, the most pressed first.
+    pub fn key_presses(&self) -> Vec<(Key, u64)> {
+        let mut items: Vec<_> = self
+            .key_presses
+            .iter()
+            .filter_map(|(name, &count)| Some((Key::from_str(name)?, count)))
+            .collect();
+        items.sort_by_key(|&(_, count)| Reverse(count));
+        items
+    }
+
+    /// Returns the rules with the counts of their applications, the most applied first.
+    pub fn rules_fired(&self) -> Vec<(&str, u64)> {
+        let mut items: Vec<_> = self
+            .rules_fired
+            .iter()
+            .map(|(rule, &count)| (rule.as_str(), count))
+            .collect();
+        items.sort_by_key(|&(_, count)| Reverse(count));
+        items
+    }
+
+    /// Returns the press counts of the keys relative to the most pressed one, from 0 to 1.
+    pub fn heatmap(&self) -> Vec<(Key, f32)> {
+        let presses = self.key_presses();
+        let max = presses.first().map_or(0, |(_, count)| *count).max(1) as f32;
+        presses
+            .into_iter()
+            .map(|(key, count)| (key, count as f32 / max))
+            .collect()
+    }
+
+    /// Returns average number of the key presses per minute of the typing time.
+    pub fn typing_rate(&self) -> f64 {
+        if self.typing_time == 0 {
+            return 0.0;
+        }
+        self.total_presses() as f64 * 60_000.0 / self.typing_time as f64
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::KeyEvent;
+    use crate::key::Key;
+    use crate::notify::KeyEventNotification;
+    use crate::rule::KeyTransformRule;
+    use crate::stats::KeyStats;
+    use crate::trigger::KeyTrigger;
+    use crate::{key_event, key_rule};
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    fn notification(event: KeyEvent, time: u32) -> KeyEventNotification {
+        KeyEventNotification {
+            event: KeyEvent { time, ..event },
+            rule: None,
+            error: None,
+            latency: None,
+        }
+    }
+
+    #[test]
+    fn test_record_presses() {
+        let mut stats = KeyStats::default();
+        stats.record(&notification(key_event!("A↓"), 0));
+        stats.record(&notification(key_event!("A↑"), 50));
+        stats.record(&notification(key_event!("B↓"), 100));
+        stats.record(&notification(key_event!("A↓"), 200));
+        stats.record(&notification(
+            KeyEvent {
+                is_repeat: true,
+                ..key_event!("A↓")
+            },
+            300,
+        ));
+        stats.record(&notification(
+            KeyEvent {
+                is_injected: true,
+                ..key_event!("C↓")
+            },
+            400,
+        ));
+
+        assert_eq!(3, stats.total_presses());
+        assert_eq!(2, stats.presses(Key::A));
+        assert_eq!(0, stats.presses(Key::C));
+        assert_eq!(vec![(Key::A, 2), (Key::B, 1)], stats.key_presses());
+        assert_eq!(vec![(Key::A, 1.0), (Key::B, 0.5)], stats.heatmap());
+    }
+
+    #[test]
+    fn test_record_rules() {
+        let mut stats = KeyStats::default();
+        let rule = Arc::new(key_rule!("A↓ : B↓"));
+        stats.record(&KeyEventNotification {
+            rule: Some(rule.clone()),
+            ..notification(key_event!("A↓"), 0)
+        });
+        stats.record(&KeyEventNotification {
+            rule: Some(rule.clone()),
+            ..notification(key_event!("A↓"), 100)
+        });
+
+        assert_eq!(vec![(rule.to_string().as_str(), 2)], stats.rules_fired());
+    }
+
+    #[test]
+    fn test_typing_rate() {
+        let mut stats = KeyStats::default();
+        assert_eq!(0.0, stats.typing_rate());
+
+        stats.record(&notification(key_event!("A↓"), 1000));
+        stats.record(&notification(key_event!("B↓"), 1500));
+        stats.record(&notification(key_event!("C↓"), 2000));
+        /* idle */
+        stats.record(&notification(key_event!("D↓"), 10000));
+
+        assert_eq!(4.0 * 60.0, stats.typing_rate());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut stats = KeyStats::default();
+        stats.record(&notification(key_event!("A↓"), 0));
+        stats.record(&notification(key_event!("B↓"), 100));
+
+        let text = serde_json::to_string(&stats).unwrap();
+        let loaded: KeyStats = serde_json::from_str(&text).unwrap();
+
+        assert_eq!(stats.key_presses(), loaded.key_presses());
+        assert_eq!(stats.typing_rate(), loaded.typing_rate());
+    }
+}
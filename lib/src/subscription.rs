@@ -0,0 +1,171 @@
+use crate::event::KeyEvent;
+use crate::key::Key;
+use crate::notify::KeyEventNotification;
+use crate::transition::KeyTransition;
+
+pub type SubscriptionId = u32;
+
+/// Selects the events passed to a subscriber.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventFilter {
+    /// Keys of the events. Any key when empty.
+    pub keys: Vec<Key>,
+    /// Transition of the events. Any transition when not set.
+    pub transition: Option<KeyTransition>,
+}
+
+impl EventFilter {
+    pub fn keys(keys: &[Key]) -> Self {
+        Self {
+            keys: keys.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_transition(mut self, transition: KeyTransition) -> Self {
+        self.transition = Some(transition);
+        self
+    }
+
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        let action = event.trigger.action;
+        (self.keys.is_empty() || self.keys.contains(&action.key))
+            && self.transition.is_none_or(|t| t == action.transition)
+    }
+}
+
+type Listener<C> = Box<dyn Fn(&C, &KeyEventNotification)>;
+
+struct Subscription<C> {
+    id: SubscriptionId,
+    filter: EventFilter,
+    listener: Listener<C>,
+}
+
+/// Passes the hook notifications to any number of independent subscribers.
+///
+/// Listeners receive the context given to [`dispatch`](Self::dispatch) so that they do not
+/// have to own the objects they update.
+pub struct KeyEventDispatcher<C = ()> {
+    last_id: SubscriptionId,
+    subscriptions: Vec<Subscription<C>>,
+}
+
+impl<C> Default for KeyEventDispatcher<C> {
+    fn default() -> Self {
+        Self {
+            last_id: 0,
+            subscriptions: Vec::new(),
+        }
+    }
+}
+
+impl<C> KeyEventDispatcher<C> {
+    /// Adds the listener of the events matching the filter. Returns the ID to unsubscribe.
+    pub fn subscribe(
+        &mut self,
+        filter: EventFilter,
+        listener: impl Fn(&C, &KeyEventNotification) + 'static,
+    ) -> SubscriptionId {
+        self.last_id += 1;
+        self.subscriptions.push(Subscription {
+            id: self.last_id,
+            filter,
+            listener: Box::new(listener),
+        });
+        self.last_id
+    }
+
+    /// Removes the listener. Returns false if there is no such subscription.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let len = self.subscriptions.len();
+        self.subscriptions.retain(|s| s.id != id);
+        self.subscriptions.len() != len
+    }
+
+    /// Passes the notification to the matching listeners in the order of subscription.
+    pub fn dispatch(&self, context: &C, notification: &KeyEventNotification) {
+        for subscription in &self.subscriptions {
+            if subscription.filter.matches(&notification.event) {
+                (subscription.listener)(context, notification);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::KeyEvent;
+    use crate::key::Key;
+    use crate::key_event;
+    use crate::notify::KeyEventNotification;
+    use crate::subscription::{EventFilter, KeyEventDispatcher};
+    use crate::transition::KeyTransition::Down;
+    use crate::trigger::KeyTrigger;
+    use std::cell::RefCell;
+    use std::str::FromStr;
+
+    fn notification(event: KeyEvent) -> KeyEventNotification {
+        KeyEventNotification {
+            event,
+            rule: None,
+            error: None,
+            latency: None,
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        assert!(EventFilter::default().matches(&key_event!("[] A↓")));
+        assert!(EventFilter::keys(&[Key::A, Key::B]).matches(&key_event!("[] B↑")));
+        assert!(!EventFilter::keys(&[Key::A]).matches(&key_event!("[] B↑")));
+        assert!(
+            EventFilter::keys(&[Key::A])
+                .with_transition(Down)
+                .matches(&key_event!("[] A↓"))
+        );
+        assert!(
+            !EventFilter::default()
+                .with_transition(Down)
+                .matches(&key_event!("[] A↑"))
+        );
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let log = RefCell::new(Vec::new());
+        let mut dispatcher: KeyEventDispatcher<RefCell<Vec<String>>> = Default::default();
+        let all = dispatcher.subscribe(EventFilter::default(), |log, n| {
+            log.borrow_mut().push(format!("all {}", n.event.trigger))
+        });
+        dispatcher.subscribe(EventFilter::keys(&[Key::A]), |log, n| {
+            log.borrow_mut().push(format!("A {}", n.event.trigger))
+        });
+        assert_eq!(2, dispatcher.len());
+
+        dispatcher.dispatch(&log, &notification(key_event!("[] A↓")));
+        dispatcher.dispatch(&log, &notification(key_event!("[] B↓")));
+
+        assert!(dispatcher.unsubscribe(all));
+        assert!(!dispatcher.unsubscribe(all));
+        dispatcher.dispatch(&log, &notification(key_event!("[] A↑")));
+
+        assert_eq!(
+            vec![
+                format!("all {}", key_event!("[] A↓").trigger),
+                format!("A {}", key_event!("[] A↓").trigger),
+                format!("all {}", key_event!("[] B↓").trigger),
+                format!("A {}", key_event!("[] A↑").trigger),
+            ],
+            *log.borrow()
+        );
+    }
+}
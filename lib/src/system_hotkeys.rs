@@ -0,0 +1,233 @@
+//! Hotkeys of the system and the other applications the rules may collide with.
+//!
+//! Some hotkeys are handled by the system before the keyboard hook sees them, so the rules
+//! of their triggers are never applied. The others are taken away from the shell or from the
+//! application that registered them when a rule suppresses the trigger.
+use crate::key::Key;
+use crate::modifiers::KeyModifiers;
+use crate::rule::KeyTransformRule;
+use crate::transition::KeyTransition::Down;
+use std::fmt::{Display, Formatter};
+
+/* values of the `RegisterHotKey` modifier flags */
+pub const MOD_ALT: u8 = 0x01;
+pub const MOD_CONTROL: u8 = 0x02;
+pub const MOD_SHIFT: u8 = 0x04;
+pub const MOD_WIN: u8 = 0x08;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemHotkeyKind {
+    /// Handled by the system before the hook so it cannot be intercepted.
+    Reserved,
+    /// Shell binding overridden by the rule suppressing it.
+    Shell,
+    /// Registered by another application with `RegisterHotKey`.
+    Registered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemHotkey {
+    /// Combination of the `MOD_*` flags. Left and right modifier keys are not distinguished.
+    pub modifiers: u8,
+    pub key: Key,
+    pub kind: SystemHotkeyKind,
+    pub description: &'static str,
+}
+
+const fn hotkey(
+    modifiers: u8,
+    key: Key,
+    kind: SystemHotkeyKind,
+    description: &'static str,
+) -> SystemHotkey {
+    SystemHotkey {
+        modifiers,
+        key,
+        kind,
+        description,
+    }
+}
+
+/// Known hotkeys of Windows.
+pub const SYSTEM_HOTKEYS: &[SystemHotkey] = {
+    use SystemHotkeyKind::{Reserved, Shell};
+    &[
+        hotkey(
+            MOD_CONTROL | MOD_ALT,
+            Key::Delete,
+            Reserved,
+            "Secure attention sequence",
+        ),
+        hotkey(MOD_WIN, Key::L, Reserved, "Lock the computer"),
+        hotkey(
+            MOD_WIN | MOD_CONTROL | MOD_SHIFT,
+            Key::B,
+            Reserved,
+            "Restart the graphics driver",
+        ),
+        hotkey(MOD_CONTROL | MOD_SHIFT, Key::Esc, Shell, "Task Manager"),
+        hotkey(MOD_CONTROL, Key::Esc, Shell, "Start menu"),
+        hotkey(MOD_ALT, Key::Tab, Shell, "Switch windows"),
+        hotkey(MOD_WIN, Key::Tab, Shell, "Task view"),
+        hotkey(MOD_WIN, Key::D, Shell, "Show desktop"),
+        hotkey(MOD_WIN, Key::E, Shell, "File Explorer"),
+        hotkey(MOD_WIN, Key::I, Shell, "Settings"),
+        hotkey(MOD_WIN, Key::R, Shell, "Run dialog"),
+        hotkey(MOD_WIN, Key::S, Shell, "Search"),
+        hotkey(MOD_WIN, Key::V, Shell, "Clipboard history"),
+        hotkey(MOD_WIN, Key::X, Shell, "Quick link menu"),
+        hotkey(MOD_WIN, Key::Dot, Shell, "Emoji panel"),
+        hotkey(MOD_WIN, Key::Space, Shell, "Switch input language"),
+        hotkey(MOD_WIN | MOD_SHIFT, Key::S, Shell, "Screen snip"),
+    ]
+};
+
+impl SystemHotkey {
+    /// Hotkey of another application found by [`probe_registered_hotkeys`].
+    pub fn registered(modifiers: u8, key: Key) -> Self {
+        hotkey(
+            modifiers,
+            key,
+            SystemHotkeyKind::Registered,
+            "Registered by another application",
+        )
+    }
+
+    /// Returns `true` if the rule is triggered by the press of this hotkey. Rules of any
+    /// modifiers are not reported as they apply to the other combinations as well.
+    pub fn conflicts_with(&self, rule: &KeyTransformRule) -> bool {
+        let action = rule.trigger.action;
+        action.key == self.key
+            && action.transition == Down
+            && trigger_hotkey_modifiers(rule) == Some(self.modifiers)
+    }
+}
+
+impl Display for SystemHotkey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (flag, name) in [
+            (MOD_WIN, "Win"),
+            (MOD_CONTROL, "Ctrl"),
+            (MOD_ALT, "Alt"),
+            (MOD_SHIFT, "Shift"),
+        ] {
+            if self.modifiers & flag != 0 {
+                write!(f, "{name}+")?;
+            }
+        }
+        write!(f, "{} ({})", self.key, self.description)
+    }
+}
+
+/// Returns the `MOD_*` flags of the trigger modifiers. `None` if the rule applies to any
+/// modifiers or the modifiers include the other keys.
+fn trigger_hotkey_modifiers(rule: &KeyTransformRule) -> Option<u8> {
+    let KeyModifiers::All(state) = rule.trigger.modifiers else {
+        return None;
+    };
+
+    let mut modifiers = 0;
+    for key in state.keys() {
+        modifiers |= match key {
+            Key::LeftAlt | Key::RightAlt | Key::Menu => MOD_ALT,
+            Key::LeftCtrl | Key::RightCtrl | Key::Ctrl => MOD_CONTROL,
+            Key::LeftShift | Key::RightShift | Key::Shift => MOD_SHIFT,
+            Key::LeftWin | Key::RightWin => MOD_WIN,
+            _ => return None,
+        };
+    }
+    Some(modifiers)
+}
+
+/// Returns the hotkeys among the given ones the rule collides with.
+pub fn find_hotkey_conflicts<'a>(
+    rule: &KeyTransformRule,
+    hotkeys: &'a [SystemHotkey],
+) -> impl Iterator<Item = &'a SystemHotkey> {
+    hotkeys.iter().filter(move |h| h.conflicts_with(rule))
+}
+
+/// Finds the triggers of the rules registered as hotkeys by the other applications. Each
+/// trigger is registered for a moment, so the hotkeys are probed only when asked.
+#[cfg(feature = "win32")]
+pub fn probe_registered_hotkeys<'a>(
+    rules: impl IntoIterator<Item = &'a KeyTransformRule>,
+) -> Vec<SystemHotkey> {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        HOT_KEY_MODIFIERS, MOD_NOREPEAT, RegisterHotKey, UnregisterHotKey,
+    };
+
+    const PROBE_HOTKEY_ID: i32 = 0xBFFF;
+
+    let mut found: Vec<SystemHotkey> = vec![];
+    for rule in rules {
+        let Some(modifiers) = trigger_hotkey_modifiers(rule) else {
+            continue;
+        };
+        let key = rule.trigger.action.key;
+        if modifiers == 0
+            || rule.trigger.action.transition != Down
+            || SYSTEM_HOTKEYS.iter().any(|h| h.conflicts_with(rule))
+            || found
+                .iter()
+                .any(|h| h.modifiers == modifiers && h.key == key)
+        {
+            continue;
+        }
+
+        let flags = HOT_KEY_MODIFIERS(modifiers as u32) | MOD_NOREPEAT;
+        unsafe {
+            if RegisterHotKey(None, PROBE_HOTKEY_ID, flags, key.vk() as u32).is_ok() {
+                let _ = UnregisterHotKey(None, PROBE_HOTKEY_ID);
+            } else {
+                found.push(SystemHotkey::registered(modifiers, key));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key::Key;
+    use crate::key_rule;
+    use crate::rule::KeyTransformRule;
+    use crate::system_hotkeys::{
+        MOD_CONTROL, MOD_SHIFT, MOD_WIN, SYSTEM_HOTKEYS, SystemHotkey, find_hotkey_conflicts,
+    };
+    use std::str::FromStr;
+
+    #[test]
+    fn test_hotkey_conflicts_with() {
+        let hotkey = SystemHotkey::registered(MOD_WIN, Key::L);
+
+        assert!(hotkey.conflicts_with(&key_rule!("[LEFT_WIN] L↓ : A↓")));
+        assert!(hotkey.conflicts_with(&key_rule!("[RIGHT_WIN] L↓ : A↓")));
+        assert!(!hotkey.conflicts_with(&key_rule!("[LEFT_WIN] L↑ : A↑")));
+        assert!(!hotkey.conflicts_with(&key_rule!("[LEFT_WIN + LEFT_SHIFT] L↓ : A↓")));
+        assert!(!hotkey.conflicts_with(&key_rule!("[LEFT_WIN + A] L↓ : A↓")));
+        assert!(!hotkey.conflicts_with(&key_rule!("L↓ : A↓")));
+        assert!(!hotkey.conflicts_with(&key_rule!("[] L↓ : A↓")));
+    }
+
+    #[test]
+    fn test_find_hotkey_conflicts() {
+        let rule = key_rule!("[LEFT_CTRL + RIGHT_SHIFT] ESC↓ : A↓");
+        let conflicts: Vec<_> = find_hotkey_conflicts(&rule, SYSTEM_HOTKEYS).collect();
+
+        assert_eq!(1, conflicts.len());
+        assert_eq!(MOD_CONTROL | MOD_SHIFT, conflicts[0].modifiers);
+        assert_eq!("Ctrl+Shift+ESC (Task Manager)", conflicts[0].to_string());
+
+        let rule = key_rule!("[LEFT_CTRL] A↓ : B↓");
+        assert_eq!(0, find_hotkey_conflicts(&rule, SYSTEM_HOTKEYS).count());
+    }
+
+    #[test]
+    fn test_hotkey_display() {
+        assert_eq!(
+            "Win+L (Registered by another application)",
+            SystemHotkey::registered(MOD_WIN, Key::L).to_string()
+        );
+    }
+}
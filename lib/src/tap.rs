@@ -0,0 +1,166 @@
+use crate::action::KeyAction;
+use crate::key::Key;
+use crate::transition::KeyTransition::{Down, Up};
+
+pub(crate) const DEFAULT_TAP_INTERVAL: u32 = 300;
+
+/// Counts successive taps of the same key. A tap continues the series when it is pressed
+/// within the interval since the previous press and no other key was pressed in between.
+#[derive(Debug)]
+pub(crate) struct TapTracker {
+    interval: u32,
+    held: Option<(Key, u8)>,
+    last: Option<(Key, u32, u8)>,
+}
+
+impl Default for TapTracker {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_TAP_INTERVAL,
+            held: None,
+            last: None,
+        }
+    }
+}
+
+impl TapTracker {
+    pub(crate) fn set_interval(&mut self, interval: u32) {
+        self.interval = interval;
+    }
+
+    /// Returns the tap number of the action in the series (starting from 1).
+    pub(crate) fn track(&mut self, action: &KeyAction, time: u32) -> u8 {
+        let key = action.key;
+        match action.transition {
+            Down => {
+                if let Some((held_key, count)) = self.held
+                    && held_key == key
+                {
+                    return count; /* auto-repeat */
+                }
+
+                let count = match self.last {
+                    Some((last_key, last_time, count))
+                        if last_key == key && time.wrapping_sub(last_time) <= self.interval =>
+                    {
+                        count.saturating_add(1)
+                    }
+                    _ => 1,
+                };
+
+                self.held = Some((key, count));
+                self.last = Some((key, time, count));
+                count
+            }
+            Up => match self.held {
+                Some((held_key, count)) if held_key == key => {
+                    self.held = None;
+                    count
+                }
+                _ => 1,
+            },
+        }
+    }
+}
+
+/// Tracks whether the key is released with no other key pressed since its press.
+#[derive(Debug, Default)]
+pub(crate) struct AloneTracker {
+    pressed: Option<Key>,
+}
+
+impl AloneTracker {
+    /// Returns `true` if the action releases the key pressed last. Wheel rotation in either
+    /// direction counts as a press.
+    pub(crate) fn track(&mut self, action: &KeyAction) -> bool {
+        let key = action.key;
+        match action.transition {
+            _ if key.is_wheel() => {
+                self.pressed = None;
+                false
+            }
+            Down => {
+                self.pressed = Some(key);
+                false
+            }
+            Up => {
+                if self.pressed == Some(key) {
+                    self.pressed = None;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::KeyAction;
+    use crate::key_action;
+    use crate::tap::{AloneTracker, TapTracker};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_tap_tracker_double_tap() {
+        let mut tracker = TapTracker::default();
+
+        assert_eq!(1, tracker.track(&key_action!("A↓"), 1000));
+        assert_eq!(1, tracker.track(&key_action!("A↑"), 1050));
+        assert_eq!(2, tracker.track(&key_action!("A↓"), 1200));
+        assert_eq!(2, tracker.track(&key_action!("A↓"), 1250));
+        assert_eq!(2, tracker.track(&key_action!("A↑"), 1300));
+        assert_eq!(3, tracker.track(&key_action!("A↓"), 1400));
+    }
+
+    #[test]
+    fn test_tap_tracker_interval_expired() {
+        let mut tracker = TapTracker::default();
+        tracker.set_interval(100);
+
+        assert_eq!(1, tracker.track(&key_action!("A↓"), 1000));
+        assert_eq!(1, tracker.track(&key_action!("A↑"), 1050));
+        assert_eq!(1, tracker.track(&key_action!("A↓"), 1200));
+    }
+
+    #[test]
+    fn test_tap_tracker_other_key_breaks_series() {
+        let mut tracker = TapTracker::default();
+
+        assert_eq!(1, tracker.track(&key_action!("A↓"), 1000));
+        assert_eq!(1, tracker.track(&key_action!("A↑"), 1050));
+        assert_eq!(1, tracker.track(&key_action!("B↓"), 1060));
+        assert_eq!(1, tracker.track(&key_action!("B↑"), 1070));
+        assert_eq!(1, tracker.track(&key_action!("A↓"), 1100));
+    }
+
+    #[test]
+    fn test_alone_tracker() {
+        let mut tracker = AloneTracker::default();
+
+        assert!(!tracker.track(&key_action!("LEFT_CTRL↓")));
+        assert!(!tracker.track(&key_action!("LEFT_CTRL↓")));
+        assert!(tracker.track(&key_action!("LEFT_CTRL↑")));
+        assert!(!tracker.track(&key_action!("LEFT_CTRL↑")));
+    }
+
+    #[test]
+    fn test_alone_tracker_other_key_pressed() {
+        let mut tracker = AloneTracker::default();
+
+        assert!(!tracker.track(&key_action!("LEFT_CTRL↓")));
+        assert!(!tracker.track(&key_action!("C↓")));
+        assert!(tracker.track(&key_action!("C↑")));
+        assert!(!tracker.track(&key_action!("LEFT_CTRL↑")));
+
+        assert!(!tracker.track(&key_action!("A↓")));
+        assert!(!tracker.track(&key_action!("LEFT_SHIFT↓")));
+        assert!(!tracker.track(&key_action!("A↑")));
+        assert!(tracker.track(&key_action!("LEFT_SHIFT↑")));
+
+        assert!(!tracker.track(&key_action!("LEFT_CTRL↓")));
+        assert!(!tracker.track(&key_action!("WHEEL_Y↑")));
+        assert!(!tracker.track(&key_action!("LEFT_CTRL↑")));
+    }
+}
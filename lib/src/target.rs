@@ -0,0 +1,100 @@
+use crate::input::build_window_message;
+use crate::window::{WindowCondition, WindowInfo};
+use log::warn;
+use windows::Win32::Foundation::{CloseHandle, HWND, LPARAM};
+use windows::Win32::System::Threading::{
+    OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION, QueryFullProcessImageNameW,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::INPUT;
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GUITHREADINFO, GetClassNameW, GetGUIThreadInfo, GetWindowTextW,
+    GetWindowThreadProcessId, IsWindowVisible, PostMessageW,
+};
+use windows::core::{BOOL, PWSTR};
+
+const MAX_TEXT_LEN: usize = 512;
+
+/// Finds the topmost visible window matching the condition.
+pub(crate) fn find_target_window(condition: &WindowCondition) -> Option<HWND> {
+    let mut search = (condition, None);
+    /* enumeration stopped by the callback reports an error */
+    let _ = unsafe {
+        EnumWindows(
+            Some(enum_window_proc),
+            LPARAM(&mut search as *mut _ as isize),
+        )
+    };
+    search.1
+}
+
+unsafe extern "system" fn enum_window_proc(hwnd: HWND, l_param: LPARAM) -> BOOL {
+    let search = unsafe { &mut *(l_param.0 as *mut (&WindowCondition, Option<HWND>)) };
+    if unsafe { IsWindowVisible(hwnd) }.as_bool() && search.0.matches(&window_info(hwnd)) {
+        search.1 = Some(hwnd);
+        return BOOL::from(false);
+    }
+    BOOL::from(true)
+}
+
+fn window_info(hwnd: HWND) -> WindowInfo {
+    let mut buffer = [0u16; MAX_TEXT_LEN];
+    let len = unsafe { GetWindowTextW(hwnd, &mut buffer) }.max(0) as usize;
+    let title = String::from_utf16_lossy(&buffer[..len]);
+    let len = unsafe { GetClassNameW(hwnd, &mut buffer) }.max(0) as usize;
+    let class_name = String::from_utf16_lossy(&buffer[..len]);
+
+    WindowInfo {
+        title,
+        class_name,
+        process_path: process_path(hwnd).unwrap_or_default(),
+    }
+}
+
+fn process_path(hwnd: HWND) -> Option<String> {
+    let mut pid = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    if pid == 0 {
+        return None;
+    }
+
+    let mut buffer = [0u16; MAX_TEXT_LEN];
+    let mut len = buffer.len() as u32;
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let result = QueryFullProcessImageNameW(
+            handle,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut len,
+        );
+        let _ = CloseHandle(handle);
+        result.ok()?;
+    }
+    Some(String::from_utf16_lossy(&buffer[..len as usize]))
+}
+
+/// Posts the input to the window as key and character messages instead of sending it
+/// to the system input queue, so the window receives it without being activated. The
+/// messages go to the focused control of the window when it has one.
+pub(crate) fn post_input(hwnd: HWND, input: &[INPUT]) {
+    let receiver = focused_control(hwnd).unwrap_or(hwnd);
+    for item in input {
+        let Some((message, w_param, l_param)) = build_window_message(item) else {
+            continue;
+        };
+        if let Err(e) = unsafe { PostMessageW(Some(receiver), message, w_param, l_param) } {
+            warn!("Failed to post input to the target window: {}", e);
+            return;
+        }
+    }
+}
+
+fn focused_control(hwnd: HWND) -> Option<HWND> {
+    let thread_id = unsafe { GetWindowThreadProcessId(hwnd, None) };
+    let mut info = GUITHREADINFO {
+        cbSize: size_of::<GUITHREADINFO>() as u32,
+        ..Default::default()
+    };
+    unsafe { GetGUIThreadInfo(thread_id, &mut info) }.ok()?;
+    (!info.hwndFocus.is_invalid()).then_some(info.hwndFocus)
+}
@@ -0,0 +1,274 @@
+//! Harness for the end-to-end tests of the transform pipeline. Enabled by the `test_support`
+//! feature.
+//!
+//! The harness watches the keyboard input with its own low-level hook installed before the
+//! engine starts. The system calls the latest installed hook first, so the harness sees the
+//! input the way the applications do: without the events suppressed by the rules and with the
+//! events sent by the rules.
+//!
+//! The input is injected into the whole session, so the tests must run on an unlocked
+//! desktop nobody types on.
+//!
+//! ```no_run
+//! use keympostor::key_action_seq;
+//! use keympostor::action::KeyActionSequence;
+//! use keympostor::rule::KeyTransformRules;
+//! use keympostor::test_support::TestHarness;
+//! use std::str::FromStr;
+//!
+//! let harness = TestHarness::start(KeyTransformRules::from_str("A↓ : B↓\nA↑ : B↑")?)?;
+//! assert_eq!(key_action_seq!("B↓ → B↑"), harness.run(&key_action_seq!("A↓ → A↑")));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::action::{KeyAction, KeyActionSequence};
+use crate::engine::{Engine, EngineError};
+use crate::hook::build_action_from_kbd_input;
+use crate::input::{build_input, injection_depth, is_test_input, set_test_marker};
+use crate::rule::KeyTransformRules;
+use log::warn;
+use std::cell::{Cell, RefCell};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Input::KeyboardAndMouse::{INPUT, SendInput};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, DispatchMessageW, GetMessageW, HC_ACTION, HHOOK, KBDLLHOOKSTRUCT, MSG,
+    PM_NOREMOVE, PeekMessageW, PostThreadMessageW, SetWindowsHookExW, TranslateMessage,
+    UnhookWindowsHookEx, WH_KEYBOARD_LL, WM_QUIT,
+};
+
+/// Time without input after which the transformed input is considered complete.
+pub const SETTLE_TIMEOUT: Duration = Duration::from_millis(300);
+
+thread_local! {
+    static CAPTURE_SENDER: RefCell<Option<Sender<CapturedEvent>>> = const { RefCell::new(None) };
+    static CAPTURE_HOOK: Cell<Option<HHOOK>> = const { Cell::new(None) };
+}
+
+/// Sender of the input as told by its marker.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputSource {
+    /// Input of the user or of other applications.
+    Other,
+    /// Input injected by the harness.
+    Test,
+    /// Input sent by the rules with the injection depth.
+    Engine(u8),
+}
+
+impl InputSource {
+    pub fn from_extra_info(extra_info: usize) -> Self {
+        if is_test_input(extra_info) {
+            Self::Test
+        } else {
+            injection_depth(extra_info).map_or(Self::Other, Self::Engine)
+        }
+    }
+}
+
+/// Keyboard event passed through the engine.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CapturedEvent {
+    pub action: KeyAction,
+    pub source: InputSource,
+}
+
+/// Runs the engine with the rules and feeds it the scripted input. The engine and the capture
+/// are stopped when dropped.
+pub struct TestHarness {
+    engine: Engine,
+    capture: Capture,
+}
+
+impl TestHarness {
+    /// Starts the capture and then the engine, so that the engine hook is called first.
+    pub fn start(rules: KeyTransformRules) -> Result<Self, EngineError> {
+        let capture = Capture::start()?;
+        let mut engine = Engine::new(rules);
+        engine.start()?;
+        Ok(Self { engine, capture })
+    }
+
+    pub fn engine(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    /// Sends the input marked as the test one. Delays and clipboard scopes are skipped.
+    pub fn inject(&self, input: &KeyActionSequence) {
+        let mut input = build_input(input);
+        set_test_marker(&mut input);
+        let sent = unsafe { SendInput(&input, size_of::<INPUT>() as i32) };
+        if sent as usize != input.len() {
+            warn!("Sent {} of {} test input events", sent, input.len());
+        }
+    }
+
+    /// Returns the test and the engine events passed through since the last call. Waits until
+    /// no input comes for the settle timeout.
+    pub fn capture(&self) -> Vec<CapturedEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.capture.events.recv_timeout(SETTLE_TIMEOUT) {
+            if event.source != InputSource::Other {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Injects the input and returns the key actions the applications received.
+    pub fn run(&self, input: &KeyActionSequence) -> KeyActionSequence {
+        /* forget the input left from the previous runs */
+        self.capture.events.try_iter().for_each(drop);
+        self.inject(input);
+        transformed(&self.capture())
+    }
+}
+
+/// Returns the actions of the test and the engine input in the order they were received.
+pub fn transformed(events: &[CapturedEvent]) -> KeyActionSequence {
+    KeyActionSequence::new(
+        events
+            .iter()
+            .filter(|event| event.source != InputSource::Other)
+            .map(|event| event.action)
+            .collect(),
+    )
+}
+
+/// Thread running the low-level hook that records the keyboard input.
+struct Capture {
+    thread_id: u32,
+    handle: Option<JoinHandle<()>>,
+    events: Receiver<CapturedEvent>,
+}
+
+impl Capture {
+    fn start() -> Result<Self, EngineError> {
+        let (events_sender, events) = channel();
+        let (ready_sender, ready) = channel();
+        let handle = thread::Builder::new()
+            .name("keympostor-test-capture".into())
+            .spawn(move || run_capture(events_sender, ready_sender))
+            .map_err(|e| EngineError::Start(e.to_string()))?;
+
+        let thread_id = ready
+            .recv()
+            .map_err(|e| EngineError::Start(e.to_string()))?
+            .map_err(EngineError::Start)?;
+
+        Ok(Self {
+            thread_id,
+            handle: Some(handle),
+            events,
+        })
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        if let Err(e) = unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0)) }
+        {
+            warn!("Failed to stop test capture: {}", e);
+            return;
+        }
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            warn!("Test capture thread panicked");
+        }
+    }
+}
+
+fn run_capture(events: Sender<CapturedEvent>, ready: Sender<Result<u32, String>>) {
+    CAPTURE_SENDER.replace(Some(events));
+    let hook = match unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(capture_hook_proc), None, 0) }
+    {
+        Ok(hook) => hook,
+        Err(e) => {
+            let _ = ready.send(Err(e.to_string()));
+            return;
+        }
+    };
+    CAPTURE_HOOK.set(Some(hook));
+
+    let mut msg = MSG::default();
+    /* the queue must exist before the quit message is posted */
+    let _ = unsafe { PeekMessageW(&mut msg, None, 0, 0, PM_NOREMOVE) };
+    let _ = ready.send(Ok(unsafe { GetCurrentThreadId() }));
+
+    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.as_bool() {
+        unsafe {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+
+    if let Err(e) = unsafe { UnhookWindowsHookEx(hook) } {
+        warn!("Failed to uninstall test capture hook: {}", e);
+    }
+    CAPTURE_HOOK.set(None);
+    CAPTURE_SENDER.replace(None);
+}
+
+extern "system" fn capture_hook_proc(code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let input = unsafe { *(l_param.0 as *const KBDLLHOOKSTRUCT) };
+        let event = CapturedEvent {
+            action: build_action_from_kbd_input(input),
+            source: InputSource::from_extra_info(input.dwExtraInfo),
+        };
+        CAPTURE_SENDER.with_borrow(|sender| {
+            if let Some(sender) = sender {
+                let _ = sender.send(event);
+            }
+        });
+    }
+
+    unsafe { CallNextHookEx(CAPTURE_HOOK.get(), code, w_param, l_param) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::KeyActionSequence;
+    use crate::input::{PRIVATE_EVENT_MARKER, TEST_EVENT_MARKER, private_event_marker};
+    use crate::key_action_seq;
+    use crate::test_support::{CapturedEvent, InputSource, transformed};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_input_source() {
+        assert_eq!(InputSource::Other, InputSource::from_extra_info(0));
+        assert_eq!(
+            InputSource::Test,
+            InputSource::from_extra_info(TEST_EVENT_MARKER)
+        );
+        assert_eq!(
+            InputSource::Engine(1),
+            InputSource::from_extra_info(PRIVATE_EVENT_MARKER)
+        );
+        assert_eq!(
+            InputSource::Engine(2),
+            InputSource::from_extra_info(private_event_marker(2))
+        );
+    }
+
+    #[test]
+    fn test_transformed() {
+        let seq = key_action_seq!("A↓ → B↓ → C↓");
+        let sources = [
+            InputSource::Test,
+            InputSource::Other,
+            InputSource::Engine(1),
+        ];
+        let events: Vec<_> = seq
+            .actions()
+            .zip(sources)
+            .map(|(&action, source)| CapturedEvent { action, source })
+            .collect();
+
+        assert_eq!(key_action_seq!("A↓ → C↓"), transformed(&events));
+    }
+}
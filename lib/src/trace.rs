@@ -0,0 +1,254 @@
+use crate::action::KeyAction;
+use crate::event::KeyEvent;
+use crate::modifiers::KeyModifiers;
+use crate::modifiers::KeyModifiers::Any;
+use crate::rule::KeyTransformRule;
+use std::fmt::{Display, Formatter};
+
+/// Condition the candidate rule failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RejectReason {
+    Locks,
+    Taps,
+    Alone,
+    Device,
+    Window,
+}
+
+impl RejectReason {
+    /// Returns the first condition of the rule the event does not satisfy.
+    pub(crate) fn check(rule: &KeyTransformRule, event: &KeyEvent) -> Option<Self> {
+        if !rule.trigger.locks.matches(&event.locks) {
+            Some(Self::Locks)
+        } else if rule.trigger.taps != 0 && rule.trigger.taps != event.taps {
+            Some(Self::Taps)
+        } else if rule.trigger.is_alone && !event.is_alone {
+            Some(Self::Alone)
+        } else if !rule.matches_device(event) {
+            Some(Self::Device)
+        } else if !rule.matches_window(event) {
+            Some(Self::Window)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for RejectReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Locks => "lock keys state differs",
+            Self::Taps => "tap number differs",
+            Self::Alone => "other key pressed in between",
+            Self::Device => "keyboard device differs",
+            Self::Window => "foreground window differs",
+        })
+    }
+}
+
+/// Single step of the rule lookup.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TraceStep {
+    /// Rules of the action were looked up.
+    Action {
+        action: KeyAction,
+        found: bool,
+    },
+    /// Rules of the action having the modifiers were consulted.
+    Bucket {
+        modifiers: KeyModifiers,
+        candidates: usize,
+    },
+    Rejected {
+        rule: KeyTransformRule,
+        reason: RejectReason,
+    },
+    Matched {
+        rule: KeyTransformRule,
+    },
+}
+
+impl Display for TraceStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Action {
+                action,
+                found: true,
+            } => write!(f, "Rules of {action} found"),
+            Self::Action {
+                action,
+                found: false,
+            } => write!(f, "No rules of {action}"),
+            Self::Bucket {
+                modifiers: Any,
+                candidates,
+            } => write!(f, "Bucket of any modifiers: {candidates} candidates"),
+            Self::Bucket {
+                modifiers,
+                candidates,
+            } => write!(
+                f,
+                "Bucket of {modifiers} modifiers: {candidates} candidates"
+            ),
+            Self::Rejected { rule, reason } => write!(f, "  Rejected `{rule}`: {reason}"),
+            Self::Matched { rule } => write!(f, "  Matched `{rule}`"),
+        }
+    }
+}
+
+/// Steps of the rule lookup for the event in the order they are taken by the hook.
+/// Candidates of a bucket are checked from the most specific ones.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MatchTrace {
+    pub steps: Vec<TraceStep>,
+}
+
+impl MatchTrace {
+    pub fn matched(&self) -> Option<&KeyTransformRule> {
+        self.steps.iter().find_map(|step| match step {
+            TraceStep::Matched { rule } => Some(rule),
+            _ => None,
+        })
+    }
+}
+
+impl Display for MatchTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+        match self.matched() {
+            Some(rule) => write!(f, "Result: `{rule}`"),
+            None => write!(f, "Result: no rule, the event is passed unchanged"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action::KeyAction;
+    use crate::event::KeyEvent;
+    use crate::modifiers::KeyModifiers;
+    use crate::rule::{KeyTransformRule, KeyTransformRules};
+    use crate::trace::RejectReason::{Alone, Locks, Taps};
+    use crate::trace::TraceStep::{Action, Bucket, Matched, Rejected};
+    use crate::trace::{MatchTrace, RejectReason};
+    use crate::trigger::KeyTrigger;
+    use crate::{key_action, key_event, key_rule, key_rules};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_reject_reason() {
+        let event = KeyEvent {
+            taps: 2,
+            ..key_event!("[] A↓")
+        };
+
+        assert_eq!(None, RejectReason::check(&key_rule!("A↓ : B↓"), &event));
+        assert_eq!(
+            Some(Locks),
+            RejectReason::check(&key_rule!("[NUM_LOCK=on] A↓ : B↓"), &event)
+        );
+        assert_eq!(
+            Some(Taps),
+            RejectReason::check(&key_rule!("A×↓ : B↓"), &event)
+        );
+        assert_eq!(
+            Some(Alone),
+            RejectReason::check(&key_rule!("A!↑ : B↓"), &key_event!("[] A↑"))
+        );
+        assert_eq!(
+            Some(RejectReason::Device),
+            RejectReason::check(&key_rule!("A↓ : B↓ | DEVICE=PAD"), &event)
+        );
+        assert_eq!(
+            Some(RejectReason::Window),
+            RejectReason::check(&key_rule!(r#"@window("Code") A↓ : B↓"#), &event)
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let rules = key_rules!(
+            r#"
+            [LEFT_SHIFT + NUM_LOCK=on] A↓ : C↓
+            [LEFT_SHIFT] A××↓ : D↓
+            A↓ : B↓
+            "#
+        );
+
+        let trace = rules.trace(&key_event!("[LEFT_SHIFT] A↓"));
+
+        assert_eq!(
+            vec![
+                Action {
+                    action: key_action!("A↓"),
+                    found: true
+                },
+                Bucket {
+                    modifiers: KeyModifiers::from_str("[LEFT_SHIFT]").unwrap(),
+                    candidates: 2
+                },
+                Rejected {
+                    rule: key_rule!("[LEFT_SHIFT + NUM_LOCK=on] A↓ : C↓"),
+                    reason: Locks
+                },
+                Rejected {
+                    rule: key_rule!("[LEFT_SHIFT] A××↓ : D↓"),
+                    reason: Taps
+                },
+                Bucket {
+                    modifiers: KeyModifiers::Any,
+                    candidates: 1
+                },
+                Matched {
+                    rule: key_rule!("A↓ : B↓")
+                },
+            ],
+            trace.steps
+        );
+        assert_eq!(Some(&key_rule!("A↓ : B↓")), trace.matched());
+        assert_eq!(
+            rules.find_rule(&key_event!("[LEFT_SHIFT] A↓")).as_ref(),
+            trace.matched()
+        );
+    }
+
+    #[test]
+    fn test_trace_no_rules() {
+        let trace = key_rules!("A↓ : B↓").trace(&key_event!("[] C↓"));
+
+        assert_eq!(
+            vec![Action {
+                action: key_action!("C↓"),
+                found: false
+            }],
+            trace.steps
+        );
+        assert_eq!(
+            "No rules of C↓\nResult: no rule, the event is passed unchanged",
+            trace.to_string()
+        );
+    }
+
+    #[test]
+    fn test_trace_display() {
+        let trace = MatchTrace {
+            steps: vec![
+                Bucket {
+                    modifiers: KeyModifiers::from_str("[LEFT_CTRL]").unwrap(),
+                    candidates: 1,
+                },
+                Matched {
+                    rule: key_rule!("[LEFT_CTRL] A↓ : B↓"),
+                },
+            ],
+        };
+
+        assert_eq!(
+            "Bucket of [LEFT_CTRL] modifiers: 1 candidates\n  Matched `[LEFT_CTRL] A↓ : B↓`\n\
+             Result: `[LEFT_CTRL] A↓ : B↓`",
+            trace.to_string()
+        );
+    }
+}
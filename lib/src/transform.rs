@@ -1,47 +1,210 @@
 use crate::action::KeyAction;
+use crate::event::KeyEvent;
 use crate::modifiers::KeyModifiers;
-use crate::modifiers::KeyModifiers::Any;
+use crate::modifiers::KeyModifiers::{All, Any};
 use crate::rule::KeyTransformRule;
-use crate::trigger::KeyTrigger;
-use fxhash::FxHashMap;
+use crate::trace::{MatchTrace, RejectReason, TraceStep};
+use std::cmp::Reverse;
 use std::slice::Iter;
+use std::sync::Arc;
 
-#[derive(Debug, Default)]
-pub(crate) struct KeyTransformMap {
-    map: FxHashMap<KeyAction, FxHashMap<KeyModifiers, KeyTransformRule>>,
+/// Rules with the same action and modifiers differing by conditions.
+/// The most specific ones go first, then the ones of higher priority, then in the rules order.
+/// Rules are shared with the hook so that applying them does not copy.
+type Candidates = Vec<Arc<KeyTransformRule>>;
+
+/// Every key index has press and release slots.
+const TABLE_SIZE: usize = 256 * 2;
+
+/// Buckets of the SHIFT, CTRL, ALT and WIN masks followed by the one of any modifiers.
+const BUCKETS_SIZE: usize = 256 + 1;
+const ANY_BUCKET: usize = 256;
+
+/// Rules of the action grouped by modifiers.
+#[derive(Debug, Clone)]
+struct ActionGroup {
+    lists: Vec<(KeyModifiers, Candidates)>,
+    /// Position of the list plus one by the modifiers bucket, zero when there is none.
+    /// The modifiers with the other keys have no bucket and are searched in the lists.
+    buckets: Box<[u16; BUCKETS_SIZE]>,
+}
+
+impl Default for ActionGroup {
+    fn default() -> Self {
+        Self {
+            lists: vec![],
+            buckets: Box::new([0; BUCKETS_SIZE]),
+        }
+    }
+}
+
+impl ActionGroup {
+    #[inline(always)]
+    fn get(&self, modifiers: &KeyModifiers) -> Option<&Candidates> {
+        match bucket_index(modifiers) {
+            Some(index) => {
+                let position = self.buckets[index].checked_sub(1)?;
+                Some(&self.lists[position as usize].1)
+            }
+            None => self
+                .lists
+                .iter()
+                .find(|(m, _)| m == modifiers)
+                .map(|(_, candidates)| candidates),
+        }
+    }
+
+    fn get_or_insert(&mut self, modifiers: KeyModifiers) -> &mut Candidates {
+        let position = match self.lists.iter().position(|(m, _)| *m == modifiers) {
+            Some(position) => position,
+            None => {
+                self.lists.push((modifiers, vec![]));
+                if let Some(index) = bucket_index(&modifiers) {
+                    self.buckets[index] = self.lists.len() as u16;
+                }
+                self.lists.len() - 1
+            }
+        };
+        &mut self.lists[position].1
+    }
+}
+
+/// Rules lookup of the hook.
+#[derive(Debug)]
+pub struct KeyTransformMap {
+    /// Groups indexed by the action so that the hook does not hash it on every event.
+    table: Box<[Option<ActionGroup>]>,
 }
 
 impl KeyTransformMap {
-    pub(crate) fn new(rules: Iter<KeyTransformRule>) -> Self {
-        let mut map: FxHashMap<KeyAction, FxHashMap<KeyModifiers, KeyTransformRule>> =
-            Default::default();
+    pub fn new(rules: Iter<KeyTransformRule>) -> Self {
+        let mut table: Box<[Option<ActionGroup>]> = vec![None; TABLE_SIZE].into_boxed_slice();
 
         for rule in rules {
             let trigger = &rule.trigger;
-            map.entry(trigger.action)
-                .or_default()
-                .insert(trigger.modifiers, rule.clone());
+            let candidates = table[table_index(&trigger.action)]
+                .get_or_insert_default()
+                .get_or_insert(trigger.modifiers);
+
+            match candidates.iter_mut().find(|r| {
+                r.trigger == *trigger && r.device == rule.device && r.window == rule.window
+            }) {
+                /* later rule with the same conditions overrides unless its priority is lower */
+                Some(existing) if rule.priority >= existing.priority => {
+                    *existing = Arc::new(rule.clone())
+                }
+                Some(_) => {}
+                None => candidates.push(Arc::new(rule.clone())),
+            }
         }
 
-        Self { map }
+        for (_, candidates) in table.iter_mut().flatten().flat_map(|g| g.lists.iter_mut()) {
+            /* stable sort keeps the rules order */
+            candidates.sort_by_key(|r| (Reverse(r.conditions_count()), Reverse(r.priority)));
+        }
+
+        Self { table }
+    }
+
+    pub fn get(&self, event: &KeyEvent) -> Option<&KeyTransformRule> {
+        self.get_shared(event).map(Arc::as_ref)
+    }
+
+    /// Same as [`Self::get`] returning the rule shared with the map.
+    pub(crate) fn get_shared(&self, event: &KeyEvent) -> Option<&Arc<KeyTransformRule>> {
+        let group = self.group(&event.trigger.action)?;
+        Self::find(group.get(&event.trigger.modifiers), event)
+            .or_else(|| Self::find(group.get(&Any), event))
+    }
+
+    /// Repeats the lookup of [`Self::get`] recording every step.
+    pub(crate) fn trace(&self, event: &KeyEvent) -> MatchTrace {
+        let mut trace = MatchTrace::default();
+        let action = event.trigger.action;
+        let group = self.group(&action);
+        trace.steps.push(TraceStep::Action {
+            action,
+            found: group.is_some(),
+        });
+        let Some(group) = group else {
+            return trace;
+        };
+
+        let mut buckets = vec![event.trigger.modifiers];
+        if event.trigger.modifiers != Any {
+            buckets.push(Any);
+        }
+        for modifiers in buckets {
+            let candidates = group.get(&modifiers).map(Vec::as_slice).unwrap_or_default();
+            trace.steps.push(TraceStep::Bucket {
+                modifiers,
+                candidates: candidates.len(),
+            });
+            for rule in candidates {
+                match RejectReason::check(rule, event) {
+                    Some(reason) => trace.steps.push(TraceStep::Rejected {
+                        rule: rule.as_ref().clone(),
+                        reason,
+                    }),
+                    None => {
+                        trace.steps.push(TraceStep::Matched {
+                            rule: rule.as_ref().clone(),
+                        });
+                        return trace;
+                    }
+                }
+            }
+        }
+        trace
+    }
+
+    #[inline(always)]
+    fn group(&self, action: &KeyAction) -> Option<&ActionGroup> {
+        self.table[table_index(action)].as_ref()
     }
 
-    pub(crate) fn get(&self, trigger: &KeyTrigger) -> Option<&KeyTransformRule> {
-        self.map
-            .get(&trigger.action)?
-            .get(&trigger.modifiers)
-            .or_else(|| self.map.get(&trigger.action)?.get(&Any))
+    fn find<'a>(
+        candidates: Option<&'a Candidates>,
+        event: &KeyEvent,
+    ) -> Option<&'a Arc<KeyTransformRule>> {
+        candidates?.iter().find(|r| {
+            r.trigger.matches_conditions(event)
+                && r.matches_device(event)
+                && r.matches_window(event)
+        })
+    }
+}
+
+#[inline(always)]
+fn table_index(action: &KeyAction) -> usize {
+    ((action.key as usize) << 1) | action.transition as usize
+}
+
+#[inline(always)]
+fn bucket_index(modifiers: &KeyModifiers) -> Option<usize> {
+    match modifiers {
+        Any => Some(ANY_BUCKET),
+        All(state) => state.modifiers_mask().map(usize::from),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::alloc_count::allocations_in;
+    use crate::event::KeyEvent;
+    use crate::key::Key;
+    use crate::modifiers::KeyLocks;
     use crate::rule::KeyTransformRule;
     use crate::transform::KeyAction;
     use crate::transform::KeyTransformMap;
+    use crate::transform::{TABLE_SIZE, table_index};
+    use crate::transition::KeyTransition::{Down, Up};
     use crate::trigger::KeyTrigger;
-    use crate::{key_action, key_rule, key_trigger};
+    use crate::window::WindowInfo;
+    use crate::{key_action, key_event, key_rule};
+    use fxhash::FxHashSet;
     use std::str::FromStr;
+    use std::sync::Arc;
 
     #[test]
     fn test_get() {
@@ -55,17 +218,17 @@ mod tests {
 
         assert_eq!(
             Some(&key_rule!("[LEFT_SHIFT] A↓ : B↓")),
-            map.get(&key_trigger!("[LEFT_SHIFT] A↓"))
+            map.get(&key_event!("[LEFT_SHIFT] A↓"))
         );
         assert_eq!(
             Some(&key_rule!("[LEFT_ALT + LEFT_CTRL]A↓ : C↓")),
-            map.get(&key_trigger!("[LEFT_CTRL + LEFT_ALT] A↓"))
+            map.get(&key_event!("[LEFT_CTRL + LEFT_ALT] A↓"))
         );
-        assert_eq!(None, map.get(&key_trigger!("A↓")));
-        assert_eq!(None, map.get(&key_trigger!("[LEFT_SHIFT] A↑")));
-        assert_eq!(None, map.get(&key_trigger!("[LEFT_ALT] LEFT_ALT↓")));
-        assert_eq!(None, map.get(&key_trigger!("[LEFT_SHIFT] LEFT_SHIFT↓")));
-        assert_eq!(None, map.get(&key_trigger!("[LEFT_CTRL] LEFT_CTRL↓")));
+        assert_eq!(None, map.get(&key_event!("A↓")));
+        assert_eq!(None, map.get(&key_event!("[LEFT_SHIFT] A↑")));
+        assert_eq!(None, map.get(&key_event!("[LEFT_ALT] LEFT_ALT↓")));
+        assert_eq!(None, map.get(&key_event!("[LEFT_SHIFT] LEFT_SHIFT↓")));
+        assert_eq!(None, map.get(&key_event!("[LEFT_CTRL] LEFT_CTRL↓")));
     }
 
     #[test]
@@ -75,12 +238,12 @@ mod tests {
         let rule = key_rule!("A↓ : B↓");
         let exp = Some(&rule);
 
-        assert_eq!(exp, map.get(&key_trigger!("A↓")));
-        assert_eq!(exp, map.get(&key_trigger!("[] A↓")));
-        assert_eq!(exp, map.get(&key_trigger!("[LEFT_SHIFT] A↓")));
-        assert_eq!(exp, map.get(&key_trigger!("[LEFT_CTRL] A↓")));
-        assert_eq!(exp, map.get(&key_trigger!("[LEFT_ALT] A↓")));
-        assert_eq!(exp, map.get(&key_trigger!("[LEFT_CTRL + LEFT_ALT] A↓")));
+        assert_eq!(exp, map.get(&key_event!("A↓")));
+        assert_eq!(exp, map.get(&key_event!("[] A↓")));
+        assert_eq!(exp, map.get(&key_event!("[LEFT_SHIFT] A↓")));
+        assert_eq!(exp, map.get(&key_event!("[LEFT_CTRL] A↓")));
+        assert_eq!(exp, map.get(&key_event!("[LEFT_ALT] A↓")));
+        assert_eq!(exp, map.get(&key_event!("[LEFT_CTRL + LEFT_ALT] A↓")));
     }
 
     #[test]
@@ -89,12 +252,12 @@ mod tests {
 
         assert_eq!(
             Some(&key_rule!("[] A↓ : B↓")),
-            map.get(&key_trigger!("[] A↓"))
+            map.get(&key_event!("[] A↓"))
         );
-        assert_eq!(None, map.get(&key_trigger!("[LEFT_SHIFT] A↓")));
-        assert_eq!(None, map.get(&key_trigger!("[LEFT_CTRL] A↓")));
-        assert_eq!(None, map.get(&key_trigger!("[LEFT_ALT] A↓")));
-        assert_eq!(None, map.get(&key_trigger!("[LEFT_CTRL + LEFT_ALT] A↓")));
+        assert_eq!(None, map.get(&key_event!("[LEFT_SHIFT] A↓")));
+        assert_eq!(None, map.get(&key_event!("[LEFT_CTRL] A↓")));
+        assert_eq!(None, map.get(&key_event!("[LEFT_ALT] A↓")));
+        assert_eq!(None, map.get(&key_event!("[LEFT_CTRL + LEFT_ALT] A↓")));
     }
 
     #[test]
@@ -104,11 +267,37 @@ mod tests {
         let rule = key_rule!("A↓ : B↓");
         let exp = Some(&rule);
 
-        assert_eq!(exp, map.get(&key_trigger!("A↓")));
-        assert_eq!(exp, map.get(&key_trigger!("[LEFT_SHIFT] A↓")));
-        assert_eq!(exp, map.get(&key_trigger!("[LEFT_CTRL] A↓")));
-        assert_eq!(exp, map.get(&key_trigger!("[LEFT_ALT] A↓")));
-        assert_eq!(exp, map.get(&key_trigger!("[LEFT_CTRL + LEFT_ALT] A↓")));
+        assert_eq!(exp, map.get(&key_event!("A↓")));
+        assert_eq!(exp, map.get(&key_event!("[LEFT_SHIFT] A↓")));
+        assert_eq!(exp, map.get(&key_event!("[LEFT_CTRL] A↓")));
+        assert_eq!(exp, map.get(&key_event!("[LEFT_ALT] A↓")));
+        assert_eq!(exp, map.get(&key_event!("[LEFT_CTRL + LEFT_ALT] A↓")));
+    }
+
+    #[test]
+    fn test_get_chord_modifiers() {
+        let map = KeyTransformMap::new(
+            [
+                key_rule!("[SPACE] J↓ : LEFT↓"),
+                key_rule!("[LEFT_SHIFT + SPACE] J↓ : HOME↓"),
+                key_rule!("[LEFT_SHIFT] J↓ : B↓"),
+            ]
+            .iter(),
+        );
+
+        assert_eq!(
+            Some(&key_rule!("[SPACE] J↓ : LEFT↓")),
+            map.get(&key_event!("[SPACE] J↓"))
+        );
+        assert_eq!(
+            Some(&key_rule!("[LEFT_SHIFT + SPACE] J↓ : HOME↓")),
+            map.get(&key_event!("[SPACE + LEFT_SHIFT] J↓"))
+        );
+        assert_eq!(
+            Some(&key_rule!("[LEFT_SHIFT] J↓ : B↓")),
+            map.get(&key_event!("[LEFT_SHIFT] J↓"))
+        );
+        assert_eq!(None, map.get(&key_event!("[SPACE + LEFT_CTRL] J↓")));
     }
 
     #[test]
@@ -122,11 +311,188 @@ mod tests {
             .iter(),
         );
 
-        assert_eq!(1, map.map.len());
-        assert_eq!(1, map.map.get(&key_action!("A↓")).unwrap().len());
+        assert_eq!(1, map.table.iter().flatten().count());
+        assert_eq!(1, map.group(&key_action!("A↓")).unwrap().lists.len());
         assert_eq!(
             Some(&key_rule!("[LEFT_SHIFT] A↓ : B↓")),
-            map.get(&key_trigger!("[LEFT_SHIFT] A↓"))
+            map.get(&key_event!("[LEFT_SHIFT] A↓"))
+        );
+    }
+
+    #[test]
+    fn test_get_locks() {
+        let map = KeyTransformMap::new(
+            [
+                key_rule!("[] NUM_4↓ : A↓"),
+                key_rule!("[NUM_LOCK=off] NUM_4↓ : LEFT↓"),
+                key_rule!("[NUM_LOCK=on] NUM_4↓ : B↓"),
+            ]
+            .iter(),
+        );
+
+        let mut event = key_event!("[] NUM_4↓");
+
+        event.locks = KeyLocks::capture(|_| false);
+        assert_eq!(
+            Some(&key_rule!("[NUM_LOCK=off] NUM_4↓ : LEFT↓")),
+            map.get(&event)
+        );
+
+        event.locks = KeyLocks::capture(|k| k == Key::NumLock);
+        assert_eq!(
+            Some(&key_rule!("[NUM_LOCK=on] NUM_4↓ : B↓")),
+            map.get(&event)
+        );
+    }
+
+    #[test]
+    fn test_get_device() {
+        let map =
+            KeyTransformMap::new([key_rule!("A↓ : B↓ | DEVICE=PAD"), key_rule!("A↓ : C↓")].iter());
+
+        let mut event = key_event!("A↓");
+        assert_eq!(Some(&key_rule!("A↓ : C↓")), map.get(&event));
+
+        event.device = Some("MACRO_PAD".into());
+        assert_eq!(Some(&key_rule!("A↓ : B↓ | DEVICE=PAD")), map.get(&event));
+
+        event.device = Some("LAPTOP".into());
+        assert_eq!(Some(&key_rule!("A↓ : C↓")), map.get(&event));
+    }
+
+    #[test]
+    fn test_get_priority() {
+        let map = KeyTransformMap::new(
+            [
+                key_rule!("A↓ : B↓ | DEVICE=PAD"),
+                key_rule!(r#"@window("Chrome") A↓ : C↓ | PRIORITY=1"#),
+                key_rule!("[NUM_LOCK=on + CAPS_LOCK=on] A↓ : D↓"),
+                key_rule!("E↓ : F↓ | PRIORITY=1"),
+                key_rule!("E↓ : G↓"),
+            ]
+            .iter(),
+        );
+
+        let mut event = key_event!("[] A↓");
+        event.device = Some("PAD".into());
+        event.window = Some(Arc::new(WindowInfo {
+            title: "Chrome".into(),
+            class_name: Default::default(),
+            process_path: "chrome.exe".into(),
+        }));
+        assert_eq!(
+            Some(&key_rule!(r#"@window("Chrome") A↓ : C↓ | PRIORITY=1"#)),
+            map.get(&event)
+        );
+
+        event.locks = KeyLocks::capture(|k| k == Key::NumLock || k == Key::CapsLock);
+        assert_eq!(
+            Some(&key_rule!("[NUM_LOCK=on + CAPS_LOCK=on] A↓ : D↓")),
+            map.get(&event)
+        );
+
+        assert_eq!(
+            Some(&key_rule!("E↓ : F↓ | PRIORITY=1")),
+            map.get(&key_event!("E↓"))
+        );
+    }
+
+    #[test]
+    fn test_get_no_allocations() {
+        let map = KeyTransformMap::new(
+            [
+                key_rule!(r#"@window("Code") [LEFT_CTRL] K↓ : B↓ | DEVICE=PAD"#),
+                key_rule!("[LEFT_CTRL] K↓ : C↓ | DEVICE=LAPTOP"),
+                key_rule!("K↓ : D↓"),
+            ]
+            .iter(),
+        );
+        let mut event = key_event!("[LEFT_CTRL] K↓");
+        event.device = Some("MACRO_PAD".into());
+        event.window = Some(Arc::new(WindowInfo {
+            title: "main.rs - Visual Studio Code".into(),
+            class_name: Default::default(),
+            process_path: "Code.exe".into(),
+        }));
+
+        /* regex allocates its cache on the first match */
+        map.get(&event);
+
+        let mut rule = None;
+        let allocations = allocations_in(|| rule = map.get_shared(&event.clone()).cloned());
+
+        assert_eq!(0, allocations);
+        assert_eq!(
+            Some(&key_rule!(
+                r#"@window("Code") [LEFT_CTRL] K↓ : B↓ | DEVICE=PAD"#
+            )),
+            rule.as_deref()
+        );
+    }
+
+    #[test]
+    fn test_table_index() {
+        let indices: FxHashSet<_> = (0..=u8::MAX)
+            .filter_map(Key::from_index)
+            .flat_map(|key| [KeyAction::new(key, Down), KeyAction::new(key, Up)])
+            .map(|action| table_index(&action))
+            .collect();
+
+        assert!(indices.iter().all(|&index| index < TABLE_SIZE));
+        assert_eq!(
+            2 * (0..=u8::MAX).filter_map(Key::from_index).count(),
+            indices.len()
+        );
+    }
+
+    #[test]
+    fn test_get_window() {
+        let map = KeyTransformMap::new(
+            [
+                key_rule!(r#"@window("Visual Studio Code") [LEFT_CTRL] K↓ : B↓"#),
+                key_rule!("[LEFT_CTRL] K↓ : C↓"),
+            ]
+            .iter(),
+        );
+
+        let mut event = key_event!("[LEFT_CTRL] K↓");
+        assert_eq!(Some(&key_rule!("[LEFT_CTRL] K↓ : C↓")), map.get(&event));
+
+        event.window = Some(Arc::new(WindowInfo {
+            title: "main.rs - Visual Studio Code".to_string(),
+            class_name: Default::default(),
+            process_path: r"C:\Program Files\Microsoft VS Code\Code.exe".to_string(),
+        }));
+        assert_eq!(
+            Some(&key_rule!(
+                r#"@window("Visual Studio Code") [LEFT_CTRL] K↓ : B↓"#
+            )),
+            map.get(&event)
+        );
+
+        event.window = Some(Arc::new(WindowInfo::default()));
+        assert_eq!(Some(&key_rule!("[LEFT_CTRL] K↓ : C↓")), map.get(&event));
+    }
+
+    #[test]
+    fn test_get_taps() {
+        let map = KeyTransformMap::new(
+            [
+                key_rule!("LEFT_SHIFT↓ : A↓"),
+                key_rule!("LEFT_SHIFT××↓ : CAPS_LOCK↓"),
+            ]
+            .iter(),
+        );
+
+        let mut event = key_event!("[] LEFT_SHIFT↓");
+
+        event.taps = 1;
+        assert_eq!(Some(&key_rule!("LEFT_SHIFT↓ : A↓")), map.get(&event));
+
+        event.taps = 2;
+        assert_eq!(
+            Some(&key_rule!("LEFT_SHIFT××↓ : CAPS_LOCK↓")),
+            map.get(&event)
         );
     }
 }
@@ -1,10 +1,11 @@
 use crate::error::KeyError;
-use crate::key_err;
+use crate::key_error;
 use crate::transition::KeyTransition::{Down, Up};
 use std::fmt::{Display, Formatter, Write};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
 pub enum KeyTransition {
+    #[default]
     Up,
     Down,
 }
@@ -12,27 +13,21 @@ pub enum KeyTransition {
 impl KeyTransition {
     #[inline(always)]
     pub(crate) const fn is_transition_char(char: char) -> bool {
-        match char {
-            '*' | '↓' | '^' | '↑' => true,
-            _ => false,
-        }
+        matches!(char, '*' | '↓' | '^' | '↑')
     }
 
     pub(crate) fn from_char(char: char) -> Result<Self, KeyError> {
         match char {
             '*' | '↓' => Ok(Down),
             '^' | '↑' => Ok(Up),
-            _ => key_err!("Invalid transition character: `{char}`"),
+            _ => Err(key_error!("Invalid transition character: `{char}`")
+                .with_token(&char.to_string())
+                .at(0)
+                .with_expected(&["↓", "↑", "*", "^"])),
         }
     }
 }
 
-impl Default for KeyTransition {
-    fn default() -> Self {
-        Up
-    }
-}
-
 impl Display for KeyTransition {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,7 +1,10 @@
 use crate::action::KeyAction;
 use crate::error::KeyError;
-use crate::modifiers::KeyModifiers;
+use crate::event::KeyEvent;
 use crate::modifiers::KeyModifiers::{All, Any};
+use crate::modifiers::{KeyLocks, KeyModifiers};
+use crate::transition::KeyTransition;
+use crate::transition::KeyTransition::Down;
 use crate::{deserialize_from_string, key_err, key_error, serialize_to_string};
 use serde::{de, Deserialize, Serialize};
 use serde::{Deserializer, Serializer};
@@ -9,48 +12,174 @@ use std::fmt::Write;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+const TAP_CHAR: char = '×';
+const ALONE_CHAR: char = '!';
+const ANY_DIGIT_PREFIX: &str = "ANY_";
+const WHEEL_DIRECTIONS: [(&str, &str); 4] = [
+    ("WHEEL_UP", "WHEEL_Y↓"),
+    ("WHEEL_DOWN", "WHEEL_Y↑"),
+    ("WHEEL_LEFT", "WHEEL_X↑"),
+    ("WHEEL_RIGHT", "WHEEL_X↓"),
+];
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct KeyTrigger {
     pub action: KeyAction,
     pub modifiers: KeyModifiers,
+    pub locks: KeyLocks,
+    /// Number of the tap in a series of successive taps of the key (0 means any).
+    pub taps: u8,
+    /// Key is released with no other key pressed since its press, like a lone modifier tap.
+    pub is_alone: bool,
 }
 
 impl KeyTrigger {
+    /// Checks that the event satisfies this trigger.
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        self.action == event.trigger.action
+            && (self.modifiers == Any || self.modifiers == event.trigger.modifiers)
+            && self.matches_conditions(event)
+    }
+
+    /// Checks the event against additional conditions besides action and modifiers.
+    pub(crate) fn matches_conditions(&self, event: &KeyEvent) -> bool {
+        self.locks.matches(&event.locks)
+            && (self.taps == 0 || self.taps == event.taps)
+            && (!self.is_alone || event.is_alone)
+    }
+
+    /// Number of additional conditions. More specific triggers take precedence.
+    pub(crate) fn conditions_count(&self) -> u32 {
+        self.locks.len() + (self.taps > 0) as u32 + self.is_alone as u32
+    }
+
     pub(crate) fn from_str_expand_list(s: &str) -> Result<Vec<Vec<Self>>, KeyError> {
-        Ok(s.split(',')
-            .map(|part| Self::from_str_expand(part.trim()))
-            .collect::<Result<Vec<_>, _>>()?)
+        let mut list = vec![];
+        for part in s.split(',').map(str::trim) {
+            for text in expand_any_digit(part) {
+                list.push(Self::from_str_expand(&text).map_err(|e| e.within(s, part))?);
+            }
+        }
+        Ok(list)
     }
 
     fn from_str_expand(s: &str) -> Result<Vec<KeyTrigger>, KeyError> {
+        let (s, taps, is_alone) = split_marks(s)?;
+        let s = &replace_wheel_direction(&s);
+
         let mut list = Vec::with_capacity(2);
 
         if s.starts_with('[') {
             let mut parts = s.split(']');
 
-            let modifiers =
-                KeyModifiers::from_str(parts.next().ok_or(key_error!("Missing modifiers part"))?)?;
+            let part = parts.next().ok_or(key_error!("Missing modifiers part"))?;
+            let (modifiers, locks) =
+                KeyModifiers::from_str_with_locks(part).map_err(|e| e.within(s, part))?;
 
-            let actions = KeyAction::from_str_expand(
-                parts.next().ok_or(key_error!("Missing actions part"))?,
-            )?;
+            let part = parts.next().ok_or(key_error!("Missing actions part"))?;
+            let actions = KeyAction::from_str_expand(part).map_err(|e| e.within(s, part))?;
 
             for action in actions {
-                list.push(Self { action, modifiers });
+                list.push(Self {
+                    action,
+                    modifiers,
+                    locks,
+                    taps,
+                    is_alone,
+                });
             }
         } else {
             for action in KeyAction::from_str_expand(s)? {
                 list.push(Self {
                     action,
                     modifiers: Any,
+                    locks: Default::default(),
+                    taps,
+                    is_alone,
                 });
             }
         }
 
+        if is_alone && list.iter().any(|t| t.action.transition == Down) {
+            return Err(
+                key_error!("Alone trigger must be a key release: `{s}`").with_token_in(s, s.trim())
+            );
+        }
+
         Ok(list)
     }
 }
 
+/// Splits the tap marks and the alone mark off the key of the trigger like `A××!↓`. The marks
+/// are accepted only right after the key name, before its transitions. They are blanked out in
+/// the returned text so that the offsets in it stay the ones in the source.
+fn split_marks(s: &str) -> Result<(String, u8, bool), KeyError> {
+    let start = s.find(']').map_or(0, |p| p + 1);
+    let end = s[start..]
+        .find(KeyTransition::is_transition_char)
+        .map_or(s.len(), |p| start + p);
+    let marks_end = s[..end].trim_end().len();
+    let taps_end = s[..marks_end]
+        .strip_suffix(ALONE_CHAR)
+        .map_or(marks_end, str::len);
+    let key_end = s[..taps_end].trim_end_matches(TAP_CHAR).len();
+
+    if let Some((_, mark)) = s
+        .match_indices([TAP_CHAR, ALONE_CHAR])
+        .find(|(p, _)| !(key_end..marks_end).contains(p))
+    {
+        return Err(
+            key_error!("Mark `{mark}` must follow the key name: `{s}`").with_token_in(s, mark)
+        );
+    }
+
+    let taps = s[key_end..taps_end].chars().count();
+    let taps = u8::try_from(taps).map_err(|_| key_error!("Too many taps: `{s}`"))?;
+    let blank = " ".repeat(marks_end - key_end);
+    Ok((
+        format!("{}{blank}{}", &s[..key_end], &s[marks_end..]),
+        taps,
+        taps_end < marks_end,
+    ))
+}
+
+/// Replaces each `ANY_n` with the top row digit `n` and the numpad digit `NUM_n`. The top row
+/// digit is padded to the length of the prefix so that the offsets stay the ones in the source.
+fn expand_any_digit(s: &str) -> Vec<String> {
+    let Some(p) = s.find(ANY_DIGIT_PREFIX) else {
+        return vec![s.to_string()];
+    };
+    let (head, rest) = s.split_at(p + ANY_DIGIT_PREFIX.len());
+    match rest.chars().next().filter(char::is_ascii_digit) {
+        Some(digit) => {
+            let head = &head[..p];
+            let blank = " ".repeat(ANY_DIGIT_PREFIX.len());
+            expand_any_digit(&rest[1..])
+                .iter()
+                .flat_map(|tail| {
+                    [
+                        format!("{head}{blank}{digit}{tail}"),
+                        format!("{head}NUM_{digit}{tail}"),
+                    ]
+                })
+                .collect()
+        }
+        None => expand_any_digit(rest)
+            .iter()
+            .map(|tail| format!("{head}{tail}"))
+            .collect(),
+    }
+}
+
+/// Replaces wheel direction like `WHEEL_UP` with the wheel axis and its transition.
+fn replace_wheel_direction(s: &str) -> String {
+    WHEEL_DIRECTIONS
+        .iter()
+        .find(|(direction, _)| s.trim_end().ends_with(direction))
+        .map(|(direction, action)| s.trim_end().replace(direction, action))
+        .unwrap_or_else(|| s.to_string())
+}
+
 impl FromStr for KeyTrigger {
     type Err = KeyError;
 
@@ -69,9 +198,26 @@ impl Display for KeyTrigger {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut s = String::new();
         if let All(m) = self.modifiers {
-            write!(s, "[{}] ", m)?
+            if self.locks.is_empty() {
+                write!(s, "[{}] ", m)?
+            } else if m.is_empty() {
+                write!(s, "[{}] ", self.locks)?
+            } else {
+                write!(s, "[{} + {}] ", m, self.locks)?
+            }
         };
-        write!(s, "{}", self.action)?;
+        if self.taps > 0 || self.is_alone {
+            write!(s, "{}", self.action.key)?;
+            for _ in 0..self.taps {
+                s.push(TAP_CHAR);
+            }
+            if self.is_alone {
+                s.push(ALONE_CHAR);
+            }
+            write!(s, "{}", self.action.transition)?;
+        } else {
+            write!(s, "{}", self.action)?;
+        }
         f.pad(&s)
     }
 }
@@ -93,8 +239,10 @@ macro_rules! key_trigger {
 
 #[cfg(test)]
 mod tests {
+    use crate::event::KeyEvent;
     use crate::key::Key;
-    use crate::key_action;
+    use crate::{key_action, key_event};
+    use crate::modifiers::KeyLocks;
     use crate::modifiers::KeyModifiers::{All, Any};
     use crate::state::tests::kbd_state_from_keys;
     use crate::state::KeyboardState;
@@ -108,24 +256,36 @@ mod tests {
         let actual = KeyTrigger {
             action: key_action!("A↓"),
             modifiers: All(kbd_state_from_keys(&[Key::LeftShift])),
+            locks: KeyLocks::default(),
+            taps: 0,
+            is_alone: false,
         };
         assert_eq!("[LEFT_SHIFT] A↓", format!("{}", actual));
 
         let actual = KeyTrigger {
             action: key_action!("A↓"),
             modifiers: All(KeyboardState::default()),
+            locks: KeyLocks::default(),
+            taps: 0,
+            is_alone: false,
         };
         assert_eq!("[] A↓", format!("{}", actual));
 
         let actual = KeyTrigger {
             action: key_action!("A↓"),
             modifiers: Any,
+            locks: KeyLocks::default(),
+            taps: 0,
+            is_alone: false,
         };
         assert_eq!("A↓", format!("{}", actual));
 
         let actual = KeyTrigger {
             action: key_action!("A↓"),
             modifiers: All(kbd_state_from_keys(&[Key::LeftShift])),
+            locks: KeyLocks::default(),
+            taps: 0,
+            is_alone: false,
         };
         assert_eq!("|     [LEFT_SHIFT] A↓|", format!("|{:>20}|", actual));
     }
@@ -136,6 +296,9 @@ mod tests {
             KeyTrigger {
                 action: key_action!("A*"),
                 modifiers: All(kbd_state_from_keys(&[Key::LeftShift])),
+                locks: KeyLocks::default(),
+                taps: 0,
+                is_alone: false,
             },
             KeyTrigger::from_str("[LEFT_SHIFT] A*").unwrap()
         );
@@ -147,6 +310,9 @@ mod tests {
             KeyTrigger {
                 action: key_action!("A*"),
                 modifiers: All(KeyboardState::default()),
+                locks: KeyLocks::default(),
+                taps: 0,
+                is_alone: false,
             },
             KeyTrigger::from_str("[] A*").unwrap()
         );
@@ -158,6 +324,9 @@ mod tests {
             KeyTrigger {
                 action: key_action!("A*"),
                 modifiers: Any,
+                locks: KeyLocks::default(),
+                taps: 0,
+                is_alone: false,
             },
             KeyTrigger::from_str("A*").unwrap()
         );
@@ -166,11 +335,135 @@ mod tests {
             KeyTrigger {
                 action: key_action!("A*"),
                 modifiers: Any,
+                locks: KeyLocks::default(),
+                taps: 0,
+                is_alone: false,
             },
             KeyTrigger::from_str("A*").unwrap()
         );
     }
 
+    #[test]
+    fn test_key_trigger_from_str_locks() {
+        assert_eq!(
+            KeyTrigger {
+                action: key_action!("NUM_4↓"),
+                modifiers: All(KeyboardState::default()),
+                locks: KeyLocks::default().with(Key::NumLock, false),
+                taps: 0,
+                is_alone: false,
+            },
+            KeyTrigger::from_str("[NUM_LOCK=off] NUM_4↓").unwrap()
+        );
+
+        assert_eq!(
+            KeyTrigger {
+                action: key_action!("A↓"),
+                modifiers: All(kbd_state_from_keys(&[Key::LeftShift])),
+                locks: KeyLocks::default().with(Key::CapsLock, true),
+                taps: 0,
+                is_alone: false,
+            },
+            KeyTrigger::from_str("[LEFT_SHIFT + CAPS_LOCK=on] A↓").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_trigger_display_locks() {
+        assert_eq!(
+            "[NUM_LOCK=off] NUM_4↓",
+            key_trigger!("[NUM_LOCK=off]NUM_4↓").to_string()
+        );
+        assert_eq!(
+            "[LEFT_SHIFT + CAPS_LOCK=on] A↓",
+            key_trigger!("[CAPS_LOCK=on + LEFT_SHIFT] A↓").to_string()
+        );
+    }
+
+    #[test]
+    fn test_key_trigger_taps() {
+        assert_eq!(
+            vec![vec![
+                KeyTrigger {
+                    action: key_action!("LEFT_SHIFT↓"),
+                    modifiers: Any,
+                    locks: KeyLocks::default(),
+                    taps: 2,
+                    is_alone: false,
+                },
+                KeyTrigger {
+                    action: key_action!("LEFT_SHIFT↑"),
+                    modifiers: Any,
+                    locks: KeyLocks::default(),
+                    taps: 2,
+                    is_alone: false,
+                }
+            ]],
+            KeyTrigger::from_str_expand_list("LEFT_SHIFT××").unwrap()
+        );
+
+        assert_eq!("[] A××↓", key_trigger!("[] A××↓").to_string());
+        assert_eq!("A↓", key_trigger!("A↓").to_string());
+    }
+
+    #[test]
+    fn test_key_trigger_taps_misplaced() {
+        let error = KeyTrigger::from_str_expand_list("[LEFT_CTRL×] A↓").unwrap_err();
+
+        assert_eq!(Some("×".to_string()), error.token);
+        assert_eq!(Some(10), error.offset);
+
+        let error = KeyTrigger::from_str_expand_list("B↓, A×↓×").unwrap_err();
+
+        assert_eq!(Some(12), error.offset);
+        assert!(KeyTrigger::from_str("A×B↓").is_err());
+    }
+
+    #[test]
+    fn test_key_trigger_alone() {
+        let trigger = key_trigger!("[] LEFT_CTRL!↑");
+
+        assert!(trigger.is_alone);
+        assert_eq!(key_action!("LEFT_CTRL↑"), trigger.action);
+        assert_eq!("[] LEFT_CTRL!↑", trigger.to_string());
+        assert_eq!("A×!↑", key_trigger!("A×!↑").to_string());
+        assert!(KeyTrigger::from_str("LEFT_CTRL!↓").is_err());
+        assert!(KeyTrigger::from_str_expand_list("LEFT_CTRL!").is_err());
+        assert!(KeyTrigger::from_str("A!×↑").is_err());
+        assert!(KeyTrigger::from_str("A!!↑").is_err());
+
+        let error = KeyTrigger::from_str("[LEFT_CTRL!] A↑").unwrap_err();
+
+        assert_eq!(Some("!".to_string()), error.token);
+        assert_eq!(Some(10), error.offset);
+
+        let error = KeyTrigger::from_str("A↑!").unwrap_err();
+
+        assert_eq!(Some(4), error.offset);
+
+        let mut event = key_event!("[] LEFT_CTRL↑");
+        assert!(!trigger.matches(&event));
+        event.is_alone = true;
+        assert!(trigger.matches(&event));
+        assert!(key_trigger!("[] LEFT_CTRL↑").matches(&event));
+    }
+
+    #[test]
+    fn test_key_trigger_matches() {
+        assert!(key_trigger!("A↓").matches(&key_event!("[LEFT_SHIFT] A↓")));
+        assert!(key_trigger!("[LEFT_SHIFT] A↓").matches(&key_event!("[LEFT_SHIFT] A↓")));
+        assert!(!key_trigger!("[] A↓").matches(&key_event!("[LEFT_SHIFT] A↓")));
+        assert!(!key_trigger!("A↓").matches(&key_event!("A↑")));
+        assert!(key_trigger!("[NUM_LOCK=off] A↓").matches(&key_event!("[] A↓")));
+
+        let mut event = key_event!("A↓");
+        event.taps = 1;
+        assert!(!key_trigger!("A××↓").matches(&event));
+        assert!(key_trigger!("A↓").matches(&event));
+        event.taps = 2;
+        assert!(key_trigger!("A××↓").matches(&event));
+    }
+
     #[test]
     fn test_key_trigger_from_str_to_vec() {
         assert_eq!(
@@ -242,6 +535,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_key_trigger_from_str_any_digit() {
+        assert_eq!(
+            vec![vec![key_trigger!("5↓")], vec![key_trigger!("NUM_5↓")]],
+            KeyTrigger::from_str_expand_list("ANY_5↓").unwrap()
+        );
+
+        assert_eq!(
+            vec![
+                vec![key_trigger!("[LEFT_CTRL] 0××↓")],
+                vec![key_trigger!("[LEFT_CTRL] NUM_0××↓")],
+                vec![key_trigger!("A↓")],
+            ],
+            KeyTrigger::from_str_expand_list("[LEFT_CTRL] ANY_0××↓, A↓").unwrap()
+        );
+
+        assert_eq!(
+            vec![
+                vec![key_trigger!("[1] 2↓")],
+                vec![key_trigger!("[NUM_1] 2↓")],
+                vec![key_trigger!("[1] NUM_2↓")],
+                vec![key_trigger!("[NUM_1] NUM_2↓")],
+            ],
+            KeyTrigger::from_str_expand_list("[ANY_1] ANY_2↓").unwrap()
+        );
+
+        assert_eq!(
+            vec![vec![key_trigger!("5↓")]],
+            KeyTrigger::from_str_expand_list("5↓").unwrap()
+        );
+        assert!(KeyTrigger::from_str_expand_list("ANY_X↓").is_err());
+
+        let error = KeyTrigger::from_str_expand_list("A↓, [ANY_1] ANY_2X↓").unwrap_err();
+
+        assert_eq!(Some("2X".to_string()), error.token);
+        assert_eq!(Some(18), error.offset);
+    }
+
+    #[test]
+    fn test_key_trigger_from_str_wheel_direction() {
+        assert_eq!(
+            key_trigger!("[LEFT_ALT] WHEEL_Y↓"),
+            KeyTrigger::from_str("[LEFT_ALT] WHEEL_UP").unwrap()
+        );
+        assert_eq!(
+            key_trigger!("WHEEL_Y↑"),
+            KeyTrigger::from_str("WHEEL_DOWN").unwrap()
+        );
+        assert_eq!(
+            key_trigger!("[LEFT_CTRL] WHEEL_X↑"),
+            KeyTrigger::from_str("[LEFT_CTRL] WHEEL_LEFT").unwrap()
+        );
+        assert_eq!(
+            key_trigger!("WHEEL_X↓"),
+            KeyTrigger::from_str("WHEEL_RIGHT").unwrap()
+        );
+        assert!(KeyTrigger::from_str("WHEEL_UP↓").is_err());
+    }
+
     #[test]
     fn test_key_trigger_serialize() {
         let source = SerdeWrapper::new(key_trigger!("[LEFT_SHIFT] A*"));
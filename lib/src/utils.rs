@@ -3,6 +3,16 @@ pub fn if_else<T>(condition: bool, a: T, b: T) -> T {
     if condition { a } else { b }
 }
 
+/// Checks that the text contains the pattern ignoring ASCII case. Unlike comparing the
+/// uppercase copies it does not allocate.
+pub fn contains_ignore_case(text: &str, pattern: &str) -> bool {
+    pattern.is_empty()
+        || text
+            .as_bytes()
+            .windows(pattern.len())
+            .any(|window| window.eq_ignore_ascii_case(pattern.as_bytes()))
+}
+
 // #[macro_export]
 // macro_rules! ife {
 //     ($condition:expr, $a:expr, $b:expr) => {
@@ -0,0 +1,177 @@
+use crate::error::KeyError;
+use crate::{deserialize_from_string, key_error, serialize_to_string};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// Foreground window properties the rules and the profiles can be conditioned on.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WindowInfo {
+    pub title: String,
+    pub class_name: String,
+    pub process_path: String,
+}
+
+/// Variables replaced by [`WindowInfo::substitute`] with the window title, the window class
+/// and the process path.
+pub const WINDOW_VARIABLES: [&str; 3] = ["$TITLE", "$CLASS", "$EXE"];
+
+impl WindowInfo {
+    /// Replaces the window variables in the text with the properties of the window.
+    pub fn substitute(&self, text: &str) -> String {
+        let values = [&self.title, &self.class_name, &self.process_path];
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(index) = rest.find('$') {
+            result.push_str(&rest[..index]);
+            rest = &rest[index..];
+            match WINDOW_VARIABLES
+                .iter()
+                .position(|variable| rest.starts_with(variable))
+            {
+                Some(i) => {
+                    result.push_str(values[i]);
+                    rest = &rest[WINDOW_VARIABLES[i].len()..];
+                }
+                None => {
+                    result.push('$');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+impl Display for WindowInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` (class: `{}`, process: `{}`)",
+            self.title, self.class_name, self.process_path
+        )
+    }
+}
+
+/// Rule condition matching the title or the process path of the foreground window by regex.
+#[derive(Clone, Debug)]
+pub struct WindowCondition {
+    regex: Regex,
+}
+
+impl WindowCondition {
+    pub fn new(pattern: &str) -> Result<Self, KeyError> {
+        let regex = Regex::new(pattern).map_err(|e| {
+            key_error!("Invalid window pattern: `{pattern}`: {e}")
+                .with_token(pattern)
+                .at(0)
+        })?;
+        Ok(Self { regex })
+    }
+
+    pub fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    pub fn matches(&self, window: &WindowInfo) -> bool {
+        self.regex.is_match(&window.title) || self.regex.is_match(&window.process_path)
+    }
+}
+
+impl PartialEq for WindowCondition {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern() == other.pattern()
+    }
+}
+
+impl Eq for WindowCondition {}
+
+impl Hash for WindowCondition {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pattern().hash(state);
+    }
+}
+
+impl Display for WindowCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.pattern())
+    }
+}
+
+impl FromStr for WindowCondition {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Serialize for WindowCondition {
+    serialize_to_string!();
+}
+
+impl<'de> Deserialize<'de> for WindowCondition {
+    deserialize_from_string!();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::window::{WindowCondition, WindowInfo};
+
+    #[test]
+    fn test_window_condition_matches() {
+        let window = WindowInfo {
+            title: "main.rs - Visual Studio Code".into(),
+            class_name: Default::default(),
+            process_path: r"C:\Program Files\Microsoft VS Code\Code.exe".into(),
+        };
+
+        assert!(
+            WindowCondition::new("Visual Studio Code")
+                .unwrap()
+                .matches(&window)
+        );
+        assert!(
+            WindowCondition::new(r"Code\.exe$")
+                .unwrap()
+                .matches(&window)
+        );
+        assert!(!WindowCondition::new("Notepad").unwrap().matches(&window));
+    }
+
+    #[test]
+    fn test_window_info_substitute() {
+        let window = WindowInfo {
+            title: "$CLASS price".into(),
+            class_name: "Notepad".into(),
+            process_path: r"C:\Windows\notepad.exe".into(),
+        };
+
+        assert_eq!(
+            r"C:\Windows\notepad.exe --class Notepad --title $CLASS price",
+            window.substitute("$EXE --class $CLASS --title $TITLE")
+        );
+        assert_eq!("$5 and $", window.substitute("$5 and $"));
+        assert_eq!("", window.substitute(""));
+    }
+
+    #[test]
+    fn test_window_condition_invalid() {
+        assert!(WindowCondition::new("(").is_err());
+    }
+
+    #[test]
+    fn test_window_condition_eq() {
+        assert_eq!(
+            WindowCondition::new("Code").unwrap(),
+            WindowCondition::new("Code").unwrap()
+        );
+        assert_ne!(
+            WindowCondition::new("Code").unwrap(),
+            WindowCondition::new("code").unwrap()
+        );
+    }
+}
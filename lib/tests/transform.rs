@@ -0,0 +1,71 @@
+//! End-to-end tests of the transform pipeline. They inject the input into the desktop session,
+//! so they are ignored by default. Run on an unlocked desktop nobody types on:
+//! `cargo test -p lib --features test_support --test transform -- --ignored --test-threads=1`.
+use keympostor::action::KeyActionSequence;
+use keympostor::key_action_seq;
+use keympostor::rule::KeyTransformRules;
+use keympostor::test_support::{InputSource, TestHarness};
+use std::str::FromStr;
+
+fn harness(rules: &str) -> TestHarness {
+    TestHarness::start(KeyTransformRules::from_str(rules).unwrap()).unwrap()
+}
+
+#[test]
+#[ignore = "injects input into the desktop session"]
+fn test_remap() {
+    let harness = harness("F13↓ : F14↓\nF13↑ : F14↑");
+
+    assert_eq!(
+        key_action_seq!("F14↓ → F14↑"),
+        harness.run(&key_action_seq!("F13↓ → F13↑"))
+    );
+}
+
+#[test]
+#[ignore = "injects input into the desktop session"]
+fn test_pass_through() {
+    let harness = harness("F13↓ : F14↓\nF13↑ : F14↑");
+
+    assert_eq!(
+        key_action_seq!("F15↓ → F15↑"),
+        harness.run(&key_action_seq!("F15↓ → F15↑"))
+    );
+}
+
+#[test]
+#[ignore = "injects input into the desktop session"]
+fn test_sources() {
+    let harness = harness("F13↓ : F14↓\nF13↑ : F14↑");
+
+    harness.inject(&key_action_seq!("F13↓ → F13↑ → F15↓ → F15↑"));
+    let sources: Vec<_> = harness
+        .capture()
+        .into_iter()
+        .map(|event| event.source)
+        .collect();
+
+    assert_eq!(
+        vec![
+            InputSource::Engine(1),
+            InputSource::Engine(1),
+            InputSource::Test,
+            InputSource::Test,
+        ],
+        sources
+    );
+}
+
+#[test]
+#[ignore = "injects input into the desktop session"]
+fn test_rules_update() {
+    let mut harness = harness("F13↓ : F14↓\nF13↑ : F14↑");
+    harness
+        .engine()
+        .update_rules(KeyTransformRules::from_str("F13↓ : F16↓\nF13↑ : F16↑").unwrap());
+
+    assert_eq!(
+        key_action_seq!("F16↓ → F16↑"),
+        harness.run(&key_action_seq!("F13↓ → F13↑"))
+    );
+}
@@ -1,48 +1,163 @@
-use crate::indicator::notify_layout_changed;
+use crate::audio::{SoundEvent, SoundSettings, play_event_sound};
+use crate::blacklist::AppBlacklist;
+use crate::compose::load_compose_table;
+use crate::focus_watch::{FocusState, FocusWatcher};
+use crate::health_watch::HookHealthWatcher;
+use crate::import::import_layout;
+use crate::indicator::{notify_layout_changed, set_profile_sound_enabled, set_sound_muted};
+use crate::ipc::{IpcCommand, IpcRequest, IpcServer};
+use crate::jump_list::{update_jump_list, JumpListTask};
 use crate::kb_watch::{KeyboardLayoutState, KeyboardLayoutWatcher};
-use crate::layout::{KeyTransformLayout, KeyTransformLayoutList};
+use crate::layout::{KeyTransformLayout, KeyTransformLayoutList, LAYOUTS_PATH};
+use crate::layout_files::{
+    create_layout_file, delete_layout_file, duplicate_layout_file, edit_layout_file,
+    rename_layout_file, save_layout_file,
+};
 use crate::profile::LayoutAutoswitchProfile;
-use crate::settings::AppSettings;
+use crate::scancode::{read_scancode_map, save_scancode_map_layout};
+use crate::settings::{
+    AppSettings, CloseAction, DebounceSettings, FocusSettings, IpcSettings, OverlaySettings,
+    SystemEventsSettings, ThemeMode,
+};
+use crate::startup::{StartupCommand, StartupMethod, relaunch_elevated, update_url_protocol};
+use crate::stats::{load_stats, save_stats};
+use crate::sys_watch::{SystemEvent, SystemEventWatcher};
+use crate::trial::{LayoutsRollback, LayoutsTrial};
 use crate::ui::main_window::MainWindow;
 use crate::ui::res::RESOURCES;
-use crate::ui::res_ids::{IDS_FAILED_LOAD_LAYOUTS, IDS_FAILED_LOAD_SETTINGS};
+use crate::ui::res_ids::{
+    IDS_CONFIRM_CLOSE, IDS_CONFIRM_DELETE_LAYOUT, IDS_CONFIRM_EXIT, IDS_FAILED_AUTOSTART,
+    IDS_FAILED_EXPORT_LOG, IDS_FAILED_EXPORT_SCANCODE_MAP, IDS_FAILED_IMPORT_LAYOUT,
+    IDS_FAILED_IMPORT_SCANCODE_MAP, IDS_FAILED_LOAD_LAYOUTS, IDS_FAILED_LOAD_SETTINGS,
+    IDS_FAILED_MANAGE_LAYOUT, IDS_FAILED_RELAUNCH, IDS_FAILED_SAVE_LAYOUT, IDS_FAILED_SETUP,
+    IDS_LAYOUT_CHECK_PASSED, IDS_LAYOUT_IN_USE, IDS_NO_SCANCODE_MAP, IDS_NO_SCANCODE_MAPPINGS,
+};
+use crate::ui::rules_editor::CapturedKey;
+use crate::ui::style::is_theme_change;
 use crate::ui::utils::RelaxedAtomicBool;
-use crate::win_watch::WindowWatcher;
+use crate::ui::wizard::{Wizard, WizardChoice};
+use crate::win_watch::{ProfileActivation, WindowWatcher};
 use crate::{rs, show_warn_message, ui};
-use keympostor::hook::KeyboardHook;
+use keympostor::char_resolver::CharResolver;
+use keympostor::client::RemoteState;
+use keympostor::failsafe::FailsafeCommand;
+use keympostor::health::HookHealth;
+use keympostor::hook::{InputChunking, KeyboardHook};
+use keympostor::key_name::KeyNameTheme;
+use keympostor::lint::{lint_hotkeys, lint_rules};
 use keympostor::notify::{KeyEventNotification, WM_KEY_HOOK_NOTIFY};
+use keympostor::recorder::MacroRecorder;
+use keympostor::rule::KeyTransformRules;
+use keympostor::scancode_map::ScancodeMap;
+use keympostor::session::SessionWatcher;
+use keympostor::stats::KeyStats;
+use keympostor::subscription::{EventFilter, KeyEventDispatcher};
+use keympostor::system_hotkeys::probe_registered_hotkeys;
 use keympostor::trigger::KeyTrigger;
+use keympostor::window::WindowInfo;
 use log::{debug, warn};
-use native_windows_gui::{stop_thread_dispatch, ControlHandle, Event};
+use native_windows_gui::{stop_thread_dispatch, Clipboard, ControlHandle, Event};
+use serde_json::Value;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::path::Path;
 use std::rc::Rc;
 use ui::utils;
-use utils::drain_timer_msg_queue;
+use utils::{drain_timer_msg_queue, show_confirm_message, show_info_message};
+use windows::Win32::Foundation::RECT;
+use windows::Win32::UI::WindowsAndMessaging::{
+    WM_DISPLAYCHANGE, WM_DPICHANGED, WM_INPUT, WM_SETTINGCHANGE,
+};
 
 #[derive(Default)]
 pub(crate) struct App {
     pub(crate) window: MainWindow,
     key_hook: KeyboardHook,
     win_watcher: WindowWatcher,
+    layouts_trial: LayoutsTrial,
     keyboard_layout_watcher: KeyboardLayoutWatcher,
+    hook_health_watcher: HookHealthWatcher,
+    focus_watcher: FocusWatcher,
+    system_event_watcher: SystemEventWatcher,
+    session_watcher: SessionWatcher,
     is_processing_enabled: RelaxedAtomicBool,
     is_log_enabled: RelaxedAtomicBool,
     is_autoswitch_enabled: RelaxedAtomicBool,
     autoswitch_profiles: Rc<RefCell<HashMap<String, LayoutAutoswitchProfile>>>,
+    autoswitch_poll_interval: RefCell<Option<u32>>,
+    autoswitch_debounce_interval: RefCell<Option<u32>>,
+    /// Windows in which the transformation is bypassed.
+    blacklist: RefCell<AppBlacklist>,
+    is_window_blacklisted: RelaxedAtomicBool,
     layouts: RefCell<KeyTransformLayoutList>,
     current_profile_name: RefCell<Option<String>>,
     current_layout_name: RefCell<String>,
     no_profile_layout_name: RefCell<String>,
     toggle_layout_hot_key: RefCell<Option<KeyTrigger>>,
+    toggle_processing_hot_key: RefCell<Option<KeyTrigger>>,
+    code_point_hot_key: RefCell<Option<KeyTrigger>>,
+    compose_hot_key: RefCell<Option<KeyTrigger>>,
+    compose_table: RefCell<Option<String>>,
+    tap_interval: RefCell<Option<u32>>,
+    debounce: RefCell<Option<DebounceSettings>>,
+    input_chunking: RefCell<Option<InputChunking>>,
+    max_injection_depth: RefCell<Option<u8>>,
+    release_modifiers: RefCell<Option<bool>>,
+    normalize_numpad: RefCell<Option<bool>>,
+    panic_hot_key: RefCell<Option<KeyTrigger>>,
+    close_action: RefCell<Option<CloseAction>>,
+    key_names: RefCell<Option<KeyNameTheme>>,
+    theme: RefCell<Option<ThemeMode>>,
+    failsafe: RefCell<Option<FailsafeCommand>>,
+    macro_recorder: RefCell<MacroRecorder>,
+    macro_record_hot_key: RefCell<Option<KeyTrigger>>,
+    macro_play_hot_key: RefCell<Option<KeyTrigger>>,
+    macros: RefCell<KeyTransformRules>,
+    /// IDs of the rules disabled by the user, by the layout names.
+    disabled_rules: RefCell<HashMap<String, BTreeSet<u32>>>,
+    overlay_settings: RefCell<Option<OverlaySettings>>,
+    sound_settings: RefCell<Option<SoundSettings>>,
+    /// Collected statistics. `None` when the collection is disabled.
+    stats: RefCell<Option<KeyStats>>,
+    ipc_server: IpcServer,
+    ipc_settings: RefCell<Option<IpcSettings>>,
+    focus_settings: RefCell<Option<FocusSettings>>,
+    /// Profile to restore when the user may be disturbed again.
+    profile_before_focus: RefCell<Option<Option<String>>>,
+    system_events_settings: RefCell<Option<SystemEventsSettings>>,
+    key_event_dispatcher: RefCell<KeyEventDispatcher<App>>,
+    /// Resolves characters of the logged events out of the hook.
+    char_resolver: RefCell<CharResolver>,
 }
 
 impl App {
     fn load_settings(&self) {
-        let settings = AppSettings::load().unwrap_or_else(|e| {
-            show_warn_message!("{}:\n{}", rs!(IDS_FAILED_LOAD_SETTINGS), e);
+        let settings = if AppSettings::exists() {
+            AppSettings::load().unwrap_or_else(|e| {
+                show_warn_message!("{}:\n{}", rs!(IDS_FAILED_LOAD_SETTINGS), e);
+                AppSettings::default()
+            })
+        } else {
             AppSettings::default()
-        });
+        };
+
+        KeyNameTheme::set_current(settings.key_names.unwrap_or_default());
+        self.key_names.replace(settings.key_names);
+
+        self.window.apply_theme(settings.theme.unwrap_or_default());
+        self.theme.replace(settings.theme);
+
+        if settings.collect_stats.unwrap_or_default() {
+            let stats = load_stats().unwrap_or_else(|e| {
+                warn!("Failed to load statistics: {}", e);
+                KeyStats::default()
+            });
+            self.stats.replace(Some(stats));
+        }
+
+        self.disabled_rules
+            .replace(settings.disabled_rules.unwrap_or_default());
 
         let layout_name = settings
             .last_transform_layout
@@ -50,18 +165,98 @@ impl App {
         self.apply_layout(layout_name.as_str());
         self.no_profile_layout_name.replace(layout_name);
 
+        /* applied after the initial layout so that the overlay is not shown on startup */
+        self.window
+            .apply_overlay_settings(settings.overlay.as_ref());
+        self.overlay_settings.replace(settings.overlay);
+
+        if let Some(sound_settings) = &settings.sounds {
+            sound_settings.apply();
+        }
+        self.sound_settings.replace(settings.sounds);
+
         if let Some(la_settings) = settings.layout_autoswitch {
             *self.autoswitch_profiles.borrow_mut() = la_settings.profiles.unwrap_or_default();
             self.is_autoswitch_enabled.store(la_settings.enabled);
+            self.autoswitch_poll_interval.replace(la_settings.poll_interval);
+            self.autoswitch_debounce_interval.replace(la_settings.debounce_interval);
         };
 
+        let blacklist = settings.blacklist.unwrap_or_default();
+        self.win_watcher.set_blacklist(blacklist.clone());
+        self.blacklist.replace(blacklist);
+
         self.is_log_enabled.store(settings.keys_logging_enabled);
 
-        let hot_key = settings.toggle_layout_hot_key;
-        if let Some(key) = &hot_key {
-            self.key_hook.suppress_keys(&[key.action.key]);
+        let suppressed_keys: Vec<_> = [
+            &settings.toggle_layout_hot_key,
+            &settings.macro_record_hot_key,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|key| key.action.key)
+        .collect();
+        self.key_hook.suppress_keys(&suppressed_keys);
+        self.toggle_layout_hot_key
+            .replace(settings.toggle_layout_hot_key);
+
+        self.key_hook
+            .set_toggle_trigger(settings.toggle_processing_hot_key.clone());
+        self.toggle_processing_hot_key
+            .replace(settings.toggle_processing_hot_key);
+
+        self.key_hook
+            .set_code_point_trigger(settings.code_point_hot_key.clone());
+        self.code_point_hot_key.replace(settings.code_point_hot_key);
+
+        if settings.compose_hot_key.is_some() {
+            match load_compose_table(settings.compose_table.as_deref()) {
+                Ok(table) => self.key_hook.set_compose_table(table),
+                Err(e) => warn!("Failed to load compose table: {}", e),
+            }
+        }
+        self.key_hook
+            .set_compose_trigger(settings.compose_hot_key.clone());
+        self.compose_hot_key.replace(settings.compose_hot_key);
+        self.compose_table.replace(settings.compose_table);
+
+        if let Some(interval) = settings.tap_interval {
+            self.key_hook.set_tap_interval(interval);
         }
-        self.toggle_layout_hot_key.replace(hot_key);
+        self.tap_interval.replace(settings.tap_interval);
+        self.key_hook.set_debounce(
+            settings
+                .debounce
+                .as_ref()
+                .map(DebounceSettings::to_debounce)
+                .unwrap_or_default(),
+        );
+        self.debounce.replace(settings.debounce);
+        self.key_hook
+            .set_input_chunking(settings.input_chunking.unwrap_or_default());
+        self.input_chunking.replace(settings.input_chunking);
+        self.key_hook
+            .set_max_injection_depth(settings.max_injection_depth.unwrap_or_default());
+        self.max_injection_depth
+            .replace(settings.max_injection_depth);
+        self.key_hook
+            .set_release_modifiers(settings.release_modifiers.unwrap_or_default());
+        self.release_modifiers.replace(settings.release_modifiers);
+        self.key_hook
+            .set_normalize_numpad(settings.normalize_numpad.unwrap_or_default());
+        self.normalize_numpad.replace(settings.normalize_numpad);
+        self.panic_hot_key.replace(settings.panic_hot_key);
+        self.close_action.replace(settings.close_action);
+        self.key_hook
+            .set_failsafe_command(settings.failsafe.clone());
+        self.failsafe.replace(settings.failsafe);
+        self.macro_record_hot_key
+            .replace(settings.macro_record_hot_key);
+        self.macro_play_hot_key.replace(settings.macro_play_hot_key);
+        self.macros.replace(settings.macros.unwrap_or_default());
+        self.ipc_settings.replace(settings.ipc);
+        self.focus_settings.replace(settings.focus);
+        self.system_events_settings.replace(settings.system_events);
 
         self.window.apply_settings(&settings.main_window);
     }
@@ -71,14 +266,83 @@ impl App {
 
         self.window.update_settings(&mut settings.main_window);
         settings.toggle_layout_hot_key = self.toggle_layout_hot_key.borrow().clone();
+        settings.toggle_processing_hot_key = self.toggle_processing_hot_key.borrow().clone();
+        settings.code_point_hot_key = self.code_point_hot_key.borrow().clone();
+        settings.compose_hot_key = self.compose_hot_key.borrow().clone();
+        settings.compose_table = self.compose_table.borrow().clone();
+        settings.tap_interval = *self.tap_interval.borrow();
+        settings.debounce = self.debounce.borrow().clone();
+        settings.input_chunking = *self.input_chunking.borrow();
+        settings.max_injection_depth = *self.max_injection_depth.borrow();
+        settings.release_modifiers = *self.release_modifiers.borrow();
+        settings.normalize_numpad = *self.normalize_numpad.borrow();
+        settings.panic_hot_key = self.panic_hot_key.borrow().clone();
+        settings.close_action = *self.close_action.borrow();
+        settings.key_names = *self.key_names.borrow();
+        settings.theme = *self.theme.borrow();
+        settings.collect_stats = self.stats.borrow().is_some().then_some(true);
+        settings.failsafe = self.failsafe.borrow().clone();
+        settings.macro_record_hot_key = self.macro_record_hot_key.borrow().clone();
+        settings.macro_play_hot_key = self.macro_play_hot_key.borrow().clone();
+        settings.macros = Some(self.macros.borrow().clone());
+        settings.disabled_rules = Some(
+            self.disabled_rules
+                .borrow()
+                .iter()
+                .filter(|(_, ids)| !ids.is_empty())
+                .map(|(name, ids)| (name.clone(), ids.clone()))
+                .collect(),
+        );
+        settings.overlay = self.overlay_settings.borrow().clone();
+        settings.sounds = self.sound_settings.borrow().clone();
+        settings.ipc = self.ipc_settings.borrow().clone();
+        settings.focus = self.focus_settings.borrow().clone();
+        settings.system_events = self.system_events_settings.borrow().clone();
         settings.keys_logging_enabled = self.is_log_enabled.load();
         settings.last_transform_layout = Some(self.current_layout_name.borrow().clone());
 
         let autoswitch_settings = settings.layout_autoswitch.get_or_insert_default();
         autoswitch_settings.enabled = self.is_autoswitch_enabled.load();
         autoswitch_settings.profiles = Some(self.autoswitch_profiles.borrow().clone());
+        autoswitch_settings.poll_interval = *self.autoswitch_poll_interval.borrow();
+        autoswitch_settings.debounce_interval = *self.autoswitch_debounce_interval.borrow();
+
+        let blacklist = self.blacklist.borrow();
+        settings.blacklist = (!blacklist.is_empty()).then(|| blacklist.clone());
 
         settings.save();
+        self.save_stats();
+    }
+
+    /// Walks the user through the initial settings on the first run.
+    fn run_setup_wizard(&self) {
+        let choice = match Wizard::run(&self.layouts.borrow()) {
+            Ok(Some(choice)) => choice,
+            Ok(None) => {
+                /* dismissed wizard is not shown again */
+                AppSettings::default().save();
+                return;
+            }
+            Err(e) => {
+                warn!("Failed to show setup wizard: {}", e);
+                return;
+            }
+        };
+
+        self.apply_setup(&choice).unwrap_or_else(|e| {
+            show_warn_message!("{}:\n{}", rs!(IDS_FAILED_SETUP), e);
+        });
+    }
+
+    fn apply_setup(&self, choice: &WizardChoice) -> Result<(), Box<dyn Error>> {
+        if let Some(layout) = self.layouts.borrow().find(&choice.layout) {
+            save_layout_file(LAYOUTS_PATH, layout)?;
+        }
+        if let Some(method) = choice.autostart {
+            StartupCommand::Enable(method).execute()?;
+        }
+        choice.to_settings().save();
+        Ok(())
     }
 
     fn load_layouts(&self) {
@@ -87,6 +351,12 @@ impl App {
             KeyTransformLayoutList::default()
         });
 
+        for layout in &layouts {
+            for issue in lint_rules(&layout.effective_rules()) {
+                warn!("Layout `{}`: {}", layout.name, issue);
+            }
+        }
+
         self.window.set_layouts(&layouts);
         self.layouts.replace(layouts);
     }
@@ -123,8 +393,9 @@ impl App {
         }
 
         self.with_current_layout(|layout| {
-            self.key_hook.set_rules(Some(&layout.rules));
-            self.window.on_layout_changed(Some(layout));
+            self.set_hook_rules(layout);
+            self.window
+                .on_layout_changed(Some(layout), self.disabled_rules.borrow().get(&layout.name));
             notify_layout_changed(layout, &KeyboardLayoutState::capture());
         });
 
@@ -136,21 +407,100 @@ impl App {
         self.update_window();
     }
 
+    /// Applies the enabled rules of the layout along with the macros.
+    fn set_hook_rules(&self, layout: &KeyTransformLayout) {
+        self.set_hook_disabled_rules(layout);
+        self.key_hook
+            .set_rules(Some(&self.with_macros(&layout.effective_rules())));
+    }
+
+    fn set_hook_disabled_rules(&self, layout: &KeyTransformLayout) {
+        let disabled = self.disabled_rules.borrow().get(&layout.name).cloned();
+        self.key_hook
+            .set_disabled_rules(disabled.unwrap_or_default());
+    }
+
+    /// Disables the rules of the current layout having the ID, or enables them back.
+    pub(crate) fn on_set_rule_enabled(&self, rule_id: u32, enabled: bool) {
+        self.with_current_layout(|layout| {
+            {
+                let mut disabled_rules = self.disabled_rules.borrow_mut();
+                let disabled = disabled_rules.entry(layout.name.clone()).or_default();
+                if enabled {
+                    disabled.remove(&rule_id);
+                } else {
+                    disabled.insert(rule_id);
+                }
+            }
+            debug!(
+                "Rule #{} of `{}` enabled: {}",
+                rule_id, layout.name, enabled
+            );
+
+            self.set_hook_disabled_rules(layout);
+            self.window
+                .on_rules_enabled_changed(layout, self.disabled_rules.borrow().get(&layout.name));
+        });
+    }
+
+    /// Returns layout rules extended with recorded macros, which take precedence.
+    fn with_macros(&self, rules: &KeyTransformRules) -> KeyTransformRules {
+        let macros = self.macros.borrow();
+        KeyTransformRules::from(
+            rules
+                .iter()
+                .chain(macros.iter())
+                .cloned()
+                .collect::<Vec<_>>(),
+        )
+    }
+
     pub(crate) fn handle_event(&self, evt: Event, handle: ControlHandle) {
         match evt {
             Event::OnInit => self.on_init(),
             _ => {}
         }
         self.win_watcher.handle_event(&self, evt, handle);
+        self.layouts_trial.handle_event(&self, evt, handle);
         self.keyboard_layout_watcher
             .handle_event(&self, evt, handle);
+        self.hook_health_watcher.handle_event(&self, evt, handle);
+        self.focus_watcher.handle_event(&self, evt, handle);
         self.window.handle_event(&self, evt, handle);
     }
 
-    pub(crate) fn handle_raw_event(&self, msg: u32, l_param: isize) {
+    pub(crate) fn handle_raw_event(&self, msg: u32, w_param: usize, l_param: isize) {
+        self.system_event_watcher
+            .handle_raw_event(&self, msg, w_param);
+        if self.session_watcher.handle_message(msg, w_param).is_some() {
+            self.update_suspended();
+        }
+
         if msg == WM_KEY_HOOK_NOTIFY {
-            let param = unsafe { &*(l_param as *const KeyEventNotification) };
+            let param = unsafe { &mut *(l_param as *mut KeyEventNotification) };
+            if self.is_log_enabled.load() || self.ipc_server.has_subscribers() {
+                self.char_resolver
+                    .borrow_mut()
+                    .resolve_event(&mut param.event);
+            }
             self.on_key_hook_notify(param);
+        } else if msg == WM_DISPLAYCHANGE {
+            self.window.on_display_change();
+        } else if msg == WM_SETTINGCHANGE && is_theme_change(l_param) {
+            self.window
+                .apply_theme(self.theme.borrow().unwrap_or_default());
+        } else if msg == WM_DPICHANGED {
+            /* low word of w_param is X DPI, the same as Y DPI */
+            let rect = unsafe { &*(l_param as *const RECT) };
+            self.window.on_dpi_changed(w_param as u32 & 0xFFFF, rect);
+        } else if msg == WM_INPUT {
+            self.key_hook.handle_raw_input(l_param);
+        } else if let Some(task) = JumpListTask::from_message(msg, l_param) {
+            self.on_jump_list_task(task);
+        } else if let Some(request) = IpcRequest::from_message(msg, l_param) {
+            self.on_ipc_request(request);
+        } else if let Some(key) = CapturedKey::from_message(msg, l_param) {
+            self.window.on_key_captured(&key.0);
         }
     }
 
@@ -162,6 +512,7 @@ impl App {
                 self.is_autoswitch_enabled.load(),
                 self.is_processing_enabled.load(),
                 self.is_log_enabled.load(),
+                self.layouts_trial.is_active(),
                 profile_name.as_deref(),
                 layout,
             );
@@ -175,25 +526,112 @@ impl App {
 
     fn on_init(&self) {
         self.load_layouts();
+        if !AppSettings::exists() {
+            self.run_setup_wizard();
+        }
         self.load_settings();
 
+        self.subscribe_key_events();
+
         let hwnd = self.window.hwnd();
         self.key_hook.setup(hwnd);
-        self.key_hook.install();
+        if let Err(e) = self.key_hook.install() {
+            self.window.show_hook_error(&e.to_string());
+        }
         self.is_processing_enabled.store(true);
         self.keyboard_layout_watcher.setup(hwnd);
+        self.hook_health_watcher.setup(hwnd);
+        self.session_watcher.setup(hwnd);
+        self.update_suspended();
+        self.layouts_trial.setup(hwnd);
         self.win_watcher.setup(
             hwnd,
             self.autoswitch_profiles.borrow().clone(),
             self.is_autoswitch_enabled.load(),
+            *self.autoswitch_poll_interval.borrow(),
+            *self.autoswitch_debounce_interval.borrow(),
         );
 
         self.update_window();
+        update_jump_list();
+        update_url_protocol().unwrap_or_else(|e| warn!("Failed to register URL protocol: {}", e));
+        self.window.set_autostart(StartupMethod::current());
+
+        if let Some(settings) = self.ipc_settings.borrow().as_ref() {
+            self.ipc_server.start(hwnd, &settings.pipe);
+        }
+
+        if self.focus_settings.borrow().is_some() {
+            self.focus_watcher.setup(hwnd);
+        }
+
+        if self.system_events_settings.borrow().is_some() {
+            self.system_event_watcher.setup(hwnd);
+        }
+
+        if let Some(task) = JumpListTask::from_args() {
+            self.on_jump_list_task(task);
+        }
 
         #[cfg(feature = "debug")]
         self.window.set_visible(true);
     }
 
+    pub(crate) fn on_foreground_window_changed(&self, window: WindowInfo) {
+        debug!("Foreground window changed: `{}`", window.process_path);
+        self.key_hook.set_foreground_window(Some(window));
+        self.update_bypass_app();
+    }
+
+    /// Passes the keys through untransformed while a blacklisted window is in foreground.
+    pub(crate) fn on_window_blacklisted(&self, is_blacklisted: bool) {
+        debug!("Foreground window blacklisted: {}", is_blacklisted);
+        self.is_window_blacklisted.store(is_blacklisted);
+        self.update_suspended();
+    }
+
+    /// Adds the last application to the blacklist or removes it from there.
+    pub(crate) fn on_toggle_app_blacklisted(&self) {
+        let Some(window) = self.win_watcher.last_app_window() else {
+            return;
+        };
+        let is_blacklisted = self.blacklist.borrow_mut().toggle(&window);
+        debug!(
+            "Application `{}` blacklisted: {}",
+            window.process_path, is_blacklisted
+        );
+        self.win_watcher.set_blacklist(self.blacklist.borrow().clone());
+        self.update_bypass_app();
+    }
+
+    fn update_bypass_app(&self) {
+        let window = self.win_watcher.last_app_window();
+        self.window.set_app_blacklisted(
+            window.map(|window| self.blacklist.borrow().matches(&window)),
+        );
+    }
+
+    /// Selects the profile of the foreground window and runs its command.
+    pub(crate) fn on_profile_activated(&self, activation: ProfileActivation) {
+        if let Some(window) = &activation.window {
+            debug!("Profile activation window: {}", window);
+        }
+
+        self.on_select_profile(activation.profile_name.as_deref());
+
+        let Some(window) = activation.window else {
+            return;
+        };
+        if *self.current_profile_name.borrow() != activation.profile_name {
+            return;
+        }
+        self.with_current_profile(|profile| {
+            if let Some(command) = profile.and_then(|p| p.exec.as_ref()) {
+                command.spawn(&window);
+            }
+        });
+    }
+
     pub(crate) fn on_select_profile(&self, profile_name: Option<&str>) {
         match profile_name {
             None => {
@@ -216,10 +654,115 @@ impl App {
             None => self.no_profile_layout_name.borrow().clone(),
         });
         self.apply_layout(layout_name.as_str());
+
+        self.apply_profile_overrides();
+
+        #[cfg(feature = "openrgb")]
+        self.with_current_profile(|profile| {
+            if let Some(settings) = profile.and_then(|p| p.openrgb.as_ref()) {
+                crate::openrgb::set_lighting(settings);
+            }
+        });
+    }
+
+    /// Layers the overrides of the current profile over the global settings.
+    fn apply_profile_overrides(&self) {
+        let overrides = self.with_current_profile(|profile| {
+            profile
+                .and_then(|p| p.overrides.clone())
+                .unwrap_or_default()
+        });
+
+        self.key_hook
+            .set_injection_mode(overrides.injection_mode.unwrap_or_default());
+        self.key_hook.set_input_chunking(
+            overrides
+                .input_chunking
+                .or(*self.input_chunking.borrow())
+                .unwrap_or_default(),
+        );
+        self.key_hook.set_release_modifiers(
+            overrides
+                .release_modifiers
+                .or(*self.release_modifiers.borrow())
+                .unwrap_or_default(),
+        );
+        self.key_hook.set_normalize_numpad(
+            overrides
+                .normalize_numpad
+                .or(*self.normalize_numpad.borrow())
+                .unwrap_or_default(),
+        );
+        self.key_hook.set_debounce(
+            self.debounce
+                .borrow()
+                .clone()
+                .unwrap_or_default()
+                .with_overrides(overrides.debounce.as_ref())
+                .to_debounce(),
+        );
+        set_profile_sound_enabled(overrides.sound_enabled.unwrap_or(true));
+    }
+
+    fn save_stats(&self) {
+        if let Some(stats) = self.stats.borrow().as_ref() {
+            save_stats(stats).unwrap_or_else(|e| warn!("Failed to save statistics: {}", e));
+        }
+    }
+
+    pub(crate) fn on_tab_changed(&self) {
+        self.window.update_stats(self.stats.borrow().as_ref());
+    }
+
+    fn play_sound(&self, event: SoundEvent) {
+        play_event_sound(self.sound_settings.borrow().as_ref(), event);
+    }
+
+    /// Selects the focus profile and mutes sounds while the user must not be disturbed.
+    pub(crate) fn on_focus_state_changed(&self, state: FocusState) {
+        let Some(settings) = self.focus_settings.borrow().clone() else {
+            return;
+        };
+
+        let is_quiet = state == FocusState::Quiet;
+        set_sound_muted(is_quiet && settings.mute_sounds);
+
+        if let Some(profile_name) = settings.profile.as_deref() {
+            if is_quiet {
+                let current = self.current_profile_name.borrow().clone();
+                self.profile_before_focus.replace(Some(current));
+                self.on_select_profile(Some(profile_name));
+            } else if let Some(previous) = self.profile_before_focus.take() {
+                self.on_select_profile(previous.as_deref());
+            }
+            self.update_window();
+        }
+    }
+
+    /// Selects the profile and plays the actions bound to the system event.
+    pub(crate) fn on_system_event(&self, event: SystemEvent) {
+        let binding = self
+            .system_events_settings
+            .borrow()
+            .as_ref()
+            .and_then(|settings| settings.binding(event))
+            .cloned();
+        let Some(binding) = binding else {
+            return;
+        };
+
+        if let Some(profile_name) = binding.profile.as_deref() {
+            self.on_select_profile(Some(profile_name).filter(|n| !n.is_empty()));
+            self.update_window();
+        }
+        if let Some(actions) = &binding.actions {
+            self.key_hook.play(actions);
+        }
     }
 
     pub(crate) fn on_select_layout(&self, layout_name: &str) {
         self.apply_layout(layout_name);
+        self.play_sound(SoundEvent::LayoutSwitch);
 
         if self.current_profile_name.borrow().is_none() {
             self.no_profile_layout_name.replace(layout_name.to_string());
@@ -238,16 +781,349 @@ impl App {
         self.on_select_layout(next_name.as_str());
     }
 
-    pub(crate) fn on_toggle_processing_enabled(&self) {
-        self.is_processing_enabled.toggle();
-        if self.is_processing_enabled.load() {
-            self.key_hook.install();
+    pub(crate) fn on_try_reload_layouts(&self) {
+        let layouts = match KeyTransformLayoutList::load() {
+            Ok(layouts) if layouts.into_iter().next().is_some() => layouts,
+            Ok(_) => {
+                self.play_sound(SoundEvent::RuleError);
+                show_warn_message!("{}", rs!(IDS_FAILED_LOAD_LAYOUTS));
+                return;
+            }
+            Err(e) => {
+                self.play_sound(SoundEvent::RuleError);
+                show_warn_message!("{}:\n{}", rs!(IDS_FAILED_LOAD_LAYOUTS), e);
+                return;
+            }
+        };
+
+        let layout_name = self.current_layout_name.borrow().clone();
+        let previous = self.layouts.replace(layouts);
+        self.start_layouts_trial(previous);
+
+        let layout_name = match self.layouts.borrow().find(&layout_name) {
+            Some(layout) => layout.name.clone(),
+            None => self.layouts.borrow().first().name.clone(),
+        };
+        self.window.set_layouts(&self.layouts.borrow());
+        self.apply_layout(&layout_name);
+        self.update_window();
+    }
+
+    /// Starts the trial of the changed layouts, or goes on with the started one keeping the
+    /// layouts to return to.
+    fn start_layouts_trial(&self, previous: KeyTransformLayoutList) {
+        let rollback = self.layouts_trial.finish().unwrap_or(LayoutsRollback {
+            layouts: previous,
+            layout_name: self.current_layout_name.borrow().clone(),
+        });
+        self.layouts_trial.start(rollback);
+        self.key_hook
+            .set_panic_trigger(self.panic_hot_key.borrow().clone());
+    }
+
+    pub(crate) fn on_keep_layouts(&self) {
+        self.layouts_trial.confirm();
+        self.key_hook.set_panic_trigger(None);
+        self.update_window();
+    }
+
+    pub(crate) fn on_rollback_layouts(&self) {
+        let Some(rollback) = self.layouts_trial.finish() else {
+            return;
+        };
+        self.key_hook.set_panic_trigger(None);
+
+        warn!("Layouts trial is not confirmed. Restoring previous layouts");
+        self.layouts.replace(rollback.layouts);
+        self.window.set_layouts(&self.layouts.borrow());
+        self.apply_layout(&rollback.layout_name);
+        self.update_window();
+    }
+
+    pub(crate) fn on_check_layout(&self) {
+        self.with_current_layout(|layout| {
+            let rules = layout.effective_rules();
+            let hotkeys = probe_registered_hotkeys(rules.iter());
+            let mut issues = lint_rules(&rules);
+            issues.extend(lint_hotkeys(&rules, &hotkeys));
+            if issues.is_empty() {
+                show_info_message(rs!(IDS_LAYOUT_CHECK_PASSED));
+            } else {
+                let text = issues
+                    .iter()
+                    .map(|issue| issue.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                show_info_message(&text);
+            }
+        });
+    }
+
+    pub(crate) fn on_export_scancode_map(&self) {
+        self.with_current_layout(|layout| {
+            let map = ScancodeMap::from_rules(&layout.effective_rules());
+            if map.is_empty() {
+                show_info_message(rs!(IDS_NO_SCANCODE_MAPPINGS));
+                return;
+            }
+            self.window.export_scancode_map(&map).unwrap_or_else(|e| {
+                show_warn_message!("{}:\n{}", rs!(IDS_FAILED_EXPORT_SCANCODE_MAP), e);
+            });
+        });
+    }
+
+    pub(crate) fn on_import_scancode_map(&self) {
+        let result = read_scancode_map().and_then(|map| match map {
+            Some(map) => save_scancode_map_layout(&map).map(Some),
+            None => Ok(None),
+        });
+        match result {
+            Ok(Some(_)) => self.on_try_reload_layouts(),
+            Ok(None) => show_info_message(rs!(IDS_NO_SCANCODE_MAP)),
+            Err(e) => {
+                show_warn_message!("{}:\n{}", rs!(IDS_FAILED_IMPORT_SCANCODE_MAP), e);
+            }
+        }
+    }
+
+    pub(crate) fn on_import_layout(&self) {
+        let url = Clipboard::data_text(self.window.handle()).unwrap_or_default();
+        match import_layout(url.trim()) {
+            Ok(true) => self.on_try_reload_layouts(),
+            Ok(false) => {}
+            Err(e) => {
+                show_warn_message!("{}:\n{}", rs!(IDS_FAILED_IMPORT_LAYOUT), e);
+            }
+        }
+    }
+
+    pub(crate) fn on_create_layout(&self, name: &str) {
+        let result = create_layout_file(LAYOUTS_PATH, name).map(|_| ());
+        self.on_layout_files_changed(result);
+    }
+
+    pub(crate) fn on_duplicate_layout(&self, source: &str, name: &str) {
+        let result = duplicate_layout_file(LAYOUTS_PATH, source, name).map(|_| ());
+        self.on_layout_files_changed(result);
+    }
+
+    pub(crate) fn on_rename_layout(&self, source: &str, name: &str) {
+        let result = rename_layout_file(LAYOUTS_PATH, source, name).map(|_| ());
+        if result.is_ok() {
+            self.rename_layout_references(source, name);
+        }
+        self.on_layout_files_changed(result);
+    }
+
+    /// Deletes the layout file unless the profiles refer to the layout.
+    pub(crate) fn on_delete_layout(&self, name: &str) {
+        let mut profiles: Vec<_> = self
+            .autoswitch_profiles
+            .borrow()
+            .iter()
+            .filter(|(_, p)| p.transform_layout == name)
+            .map(|(profile_name, _)| profile_name.clone())
+            .collect();
+        if !profiles.is_empty() {
+            profiles.sort();
+            show_warn_message!("{}:\n{}", rs!(IDS_LAYOUT_IN_USE), profiles.join(", "));
+            return;
+        }
+        if !show_confirm_message(&format!("{}\n\n{name}", rs!(IDS_CONFIRM_DELETE_LAYOUT))) {
+            return;
+        }
+
+        let result = delete_layout_file(LAYOUTS_PATH, name);
+        if result.is_ok() {
+            self.disabled_rules.borrow_mut().remove(name);
+        }
+        self.on_layout_files_changed(result);
+    }
+
+    pub(crate) fn on_edit_layout_file(&self, path: &Path) {
+        if let Err(e) = edit_layout_file(path) {
+            show_warn_message!("{}:\n{}", rs!(IDS_FAILED_MANAGE_LAYOUT), e);
+        }
+    }
+
+    /// Updates the settings referring to the renamed layout.
+    fn rename_layout_references(&self, name: &str, new_name: &str) {
+        for layout_name in [&self.current_layout_name, &self.no_profile_layout_name] {
+            if *layout_name.borrow() == name {
+                layout_name.replace(new_name.to_string());
+            }
+        }
+
+        let mut disabled_rules = self.disabled_rules.borrow_mut();
+        if let Some(ids) = disabled_rules.remove(name) {
+            disabled_rules.insert(new_name.to_string(), ids);
+        }
+
+        for profile in self.autoswitch_profiles.borrow_mut().values_mut() {
+            if profile.transform_layout == name {
+                profile.transform_layout = new_name.to_string();
+            }
+        }
+    }
+
+    /// Reloads the layouts changed by the layout manager. The layouts of the deleted files are
+    /// replaced with the first one.
+    fn on_layout_files_changed(&self, result: Result<(), Box<dyn Error>>) {
+        if let Err(e) = result {
+            self.play_sound(SoundEvent::RuleError);
+            show_warn_message!("{}:\n{}", rs!(IDS_FAILED_MANAGE_LAYOUT), e);
+            return;
+        }
+
+        match KeyTransformLayoutList::load() {
+            Ok(layouts) => {
+                self.window.set_layouts(&layouts);
+                self.layouts.replace(layouts);
+            }
+            Err(e) => {
+                show_warn_message!("{}:\n{}", rs!(IDS_FAILED_LOAD_LAYOUTS), e);
+                return;
+            }
+        }
+
+        let (layout_name, no_profile_layout_name) = {
+            let layouts = self.layouts.borrow();
+            let existing = |name: &str| {
+                layouts
+                    .find(name)
+                    .unwrap_or_else(|| layouts.first())
+                    .name
+                    .clone()
+            };
+            (
+                existing(&self.current_layout_name.borrow()),
+                existing(&self.no_profile_layout_name.borrow()),
+            )
+        };
+        self.no_profile_layout_name.replace(no_profile_layout_name);
+        self.apply_layout(&layout_name);
+        self.save_settings();
+    }
+
+    /// Starts or cancels diverting the next key press into the rules editor.
+    pub(crate) fn set_key_capture(&self, enabled: bool) {
+        if enabled {
+            let hwnd = self.window.hwnd();
+            self.key_hook
+                .capture_next(move |event| CapturedKey(event.clone()).post(hwnd));
         } else {
-            self.key_hook.uninstall();
+            self.key_hook.cancel_capture();
+        }
+    }
+
+    pub(crate) fn on_save_layout_rules(&self, rules: KeyTransformRules) {
+        if let Err(e) = self.set_layout_rules(None, rules) {
+            self.play_sound(SoundEvent::RuleError);
+            show_warn_message!("{}:\n{}", rs!(IDS_FAILED_SAVE_LAYOUT), e);
         }
+    }
+
+    /// Saves rules of the layout, or of the current one, and reapplies the current layout
+    /// for a trial.
+    fn set_layout_rules(
+        &self,
+        layout_name: Option<&str>,
+        rules: KeyTransformRules,
+    ) -> Result<(), Box<dyn Error>> {
+        let current_name = self.current_layout_name.borrow().clone();
+        let layout_name = layout_name.unwrap_or(&current_name);
+        let previous = self.layouts.borrow().clone();
+        self.layouts
+            .borrow_mut()
+            .update_rules(layout_name, rules)?;
+        self.start_layouts_trial(previous);
+        self.apply_layout(&current_name);
+        self.update_window();
+        Ok(())
+    }
+
+    pub(crate) fn on_toggle_processing_enabled(&self) {
+        self.is_processing_enabled.toggle();
+        self.key_hook.set_enabled(self.is_processing_enabled.load());
         self.update_window();
     }
 
+    fn on_set_processing_enabled(&self, enabled: bool) {
+        if self.is_processing_enabled.load() != enabled {
+            self.on_toggle_processing_enabled();
+        }
+    }
+
+    fn on_jump_list_task(&self, task: JumpListTask) {
+        debug!("Jump list task: {:?}", task);
+        match task {
+            JumpListTask::EnableProcessing => self.on_set_processing_enabled(true),
+            JumpListTask::DisableProcessing => self.on_set_processing_enabled(false),
+            JumpListTask::NextLayout => self.on_select_next_layout(),
+        }
+    }
+
+    fn on_ipc_request(&self, request: IpcRequest) {
+        debug!("IPC request: {:?}", request.command);
+        let result = match &request.command {
+            IpcCommand::SetProfile(Some(name))
+                if !self.autoswitch_profiles.borrow().contains_key(name) =>
+            {
+                Err(format!("Profile not found: `{name}`"))
+            }
+            IpcCommand::SetProfile(name) => {
+                self.on_select_profile(name.as_deref());
+                Ok(Value::Null)
+            }
+            IpcCommand::Enable => {
+                self.on_set_processing_enabled(true);
+                Ok(Value::Null)
+            }
+            IpcCommand::Disable => {
+                self.on_set_processing_enabled(false);
+                Ok(Value::Null)
+            }
+            IpcCommand::Reload => {
+                self.on_try_reload_layouts();
+                Ok(Value::Null)
+            }
+            IpcCommand::SetRules { layout, rules } => self
+                .set_layout_rules(layout.as_deref(), rules.clone())
+                .map(|_| Value::Null)
+                .map_err(|e| e.to_string()),
+            IpcCommand::GetState => serde_json::to_value(self.state()).map_err(|e| e.to_string()),
+            IpcCommand::Subscribe => {
+                self.ipc_server.subscribe(&request.client);
+                Ok(Value::Null)
+            }
+            IpcCommand::Unsubscribe => {
+                self.ipc_server.unsubscribe(&request.client);
+                Ok(Value::Null)
+            }
+        };
+        request.reply(result);
+    }
+
+    fn state(&self) -> RemoteState {
+        let mut profiles: Vec<_> = self.autoswitch_profiles.borrow().keys().cloned().collect();
+        profiles.sort();
+
+        RemoteState {
+            enabled: self.is_processing_enabled.load(),
+            autoswitch_enabled: self.is_autoswitch_enabled.load(),
+            layouts_trial: self.layouts_trial.is_active(),
+            profile: self.current_profile_name.borrow().clone(),
+            layout: self.current_layout_name.borrow().clone(),
+            profiles,
+            layouts: self
+                .layouts
+                .borrow()
+                .into_iter()
+                .map(|layout| layout.name.clone())
+                .collect(),
+        }
+    }
+
     pub(crate) fn on_toggle_logging_enabled(&self) {
         self.is_log_enabled.toggle();
         self.update_window();
@@ -255,15 +1131,90 @@ impl App {
     }
 
     fn on_key_hook_notify(&self, notification: &KeyEventNotification) {
+        if let Some(key) = self.toggle_processing_hot_key.borrow().as_ref() {
+            if key.matches(&notification.event) {
+                self.is_processing_enabled.store(self.key_hook.is_enabled());
+                self.update_window();
+            }
+        }
+
         if let Some(key) = self.toggle_layout_hot_key.borrow().as_ref() {
             if &notification.event.trigger == key {
                 self.on_select_next_layout();
             }
         }
 
-        if self.is_log_enabled.load() {
-            self.window.on_key_hook_notify(notification);
+        if let Some(key) = self.macro_record_hot_key.borrow().as_ref() {
+            if &notification.event.trigger == key {
+                self.on_toggle_macro_recording();
+            } else if notification.event.trigger.action.key != key.action.key {
+                self.macro_recorder.borrow_mut().record(&notification.event);
+            }
+        }
+
+        if self.layouts_trial.is_active() {
+            if let Some(key) = self.panic_hot_key.borrow().as_ref() {
+                if key.matches(&notification.event) {
+                    self.on_rollback_layouts();
+                }
+            }
+        }
+
+        if let (Some(error), Some(rule)) = (&notification.error, &notification.rule) {
+            self.window.show_rules_warning(&format!("{error}:\n{rule}"));
+        }
+
+        self.key_event_dispatcher
+            .borrow()
+            .dispatch(self, notification);
+    }
+
+    fn subscribe_key_events(&self) {
+        let mut dispatcher = self.key_event_dispatcher.borrow_mut();
+        dispatcher.subscribe(EventFilter::default(), |app, notification| {
+            app.ipc_server.notify_key_event(notification)
+        });
+        dispatcher.subscribe(EventFilter::default(), |app, notification| {
+            if let Some(stats) = app.stats.borrow_mut().as_mut() {
+                stats.record(notification);
+            }
+        });
+        dispatcher.subscribe(EventFilter::default(), |app, notification| {
+            if app.is_log_enabled.load() {
+                app.window.on_key_hook_notify(notification);
+            }
+        });
+    }
+
+    fn on_toggle_macro_recording(&self) {
+        let mut recorder = self.macro_recorder.borrow_mut();
+        if !recorder.is_recording() {
+            recorder.start();
+            debug!("Macro recording started");
+            self.play_sound(SoundEvent::MacroRecordStart);
+            return;
+        }
+
+        let recorded = recorder.stop();
+        drop(recorder);
+        debug!("Macro recording stopped");
+        self.play_sound(SoundEvent::MacroRecordStop);
+
+        if recorded.is_empty() {
+            return;
         }
+        let Some(trigger) = self.macro_play_hot_key.borrow().clone() else {
+            warn!("Macro playback key is not set. Recorded macro discarded");
+            return;
+        };
+
+        let rule = recorded.bind(trigger);
+        debug!("Macro recorded: {}", rule);
+        self.macros.borrow_mut().insert(rule);
+
+        let layout_name = self.current_layout_name.borrow().clone();
+        self.apply_layout(&layout_name);
+        self.save_settings();
     }
 
     pub(crate) fn on_toggle_auto_switch_layout(&self) {
@@ -275,14 +1226,35 @@ impl App {
 
     pub(crate) fn on_window_close(&self) {
         self.update_window();
-        #[cfg(feature = "debug")]
-        self.on_app_exit()
+
+        let close_action = self.close_action.borrow().unwrap_or_default();
+        match close_action {
+            CloseAction::Tray => {}
+            CloseAction::Prompt => {
+                if show_confirm_message(rs!(IDS_CONFIRM_CLOSE)) {
+                    self.exit();
+                }
+            }
+            CloseAction::Exit => self.on_app_exit(),
+        }
     }
 
     pub(crate) fn on_app_exit(&self) {
+        if self.is_processing_enabled.load() && !show_confirm_message(rs!(IDS_CONFIRM_EXIT)) {
+            return;
+        }
+        self.exit();
+    }
+
+    fn exit(&self) {
         // self.save_settings();
+        self.save_stats();
         self.keyboard_layout_watcher.stop();
-        self.win_watcher.enable(false);
+        self.hook_health_watcher.stop();
+        self.focus_watcher.stop();
+        self.system_event_watcher.stop();
+        self.session_watcher.stop();
+        self.win_watcher.stop();
         drain_timer_msg_queue();
         stop_thread_dispatch();
     }
@@ -298,4 +1270,44 @@ impl App {
     pub(crate) fn on_log_view_clear(&self) {
         self.window.clear_log();
     }
+
+    pub(crate) fn on_log_view_export(&self) {
+        self.window.export_log().unwrap_or_else(|e| {
+            show_warn_message!("{}:\n{}", rs!(IDS_FAILED_EXPORT_LOG), e);
+        });
+    }
+
+    pub(crate) fn on_toggle_autostart(&self, method: StartupMethod) {
+        let command = if StartupMethod::current() == Some(method) {
+            StartupCommand::Disable
+        } else {
+            StartupCommand::Enable(method)
+        };
+        command.execute().unwrap_or_else(|e| {
+            show_warn_message!("{}:\n{}", rs!(IDS_FAILED_AUTOSTART), e);
+        });
+        self.window.set_autostart(StartupMethod::current());
+    }
+
+    pub(crate) fn on_hook_health_changed(&self, health: HookHealth) {
+        self.window.set_hook_health(health);
+    }
+
+    /// Suspends the hook while the user is away from the session desktop or a blacklisted
+    /// window is in foreground.
+    fn update_suspended(&self) {
+        self.key_hook.set_suspended(
+            self.session_watcher.state().is_suspended() || self.is_window_blacklisted.load(),
+        );
+    }
+
+    pub(crate) fn on_relaunch_elevated(&self) {
+        match relaunch_elevated() {
+            Ok(true) => self.exit(),
+            Ok(false) => {}
+            Err(e) => {
+                show_warn_message!("{}:\n{}", rs!(IDS_FAILED_RELAUNCH), e);
+            }
+        }
+    }
 }
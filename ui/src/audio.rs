@@ -0,0 +1,163 @@
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Sender, channel};
+use std::thread;
+use windows::Win32::Foundation::GetLastError;
+use windows::Win32::Media::Audio::{
+    HWAVEOUT, PlaySoundW, SND_ALIAS, SND_FILENAME, SND_NODEFAULT, SND_SYNC, waveOutSetVolume,
+};
+use windows::core::PCWSTR;
+
+static PLAYER: OnceLock<Sender<String>> = OnceLock::new();
+static IS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Application events that may be accompanied by a sound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SoundEvent {
+    LayoutSwitch,
+    MacroRecordStart,
+    MacroRecordStop,
+    RuleError,
+}
+
+/// Audio feedback. Sound is a WAV file path or a system sound alias like `SystemAsterisk`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SoundSettings {
+    pub(crate) enabled: bool,
+    /// Volume in percents.
+    pub(crate) volume: u8,
+    pub(crate) on_layout_switch: Option<String>,
+    pub(crate) on_macro_record_start: Option<String>,
+    pub(crate) on_macro_record_stop: Option<String>,
+    pub(crate) on_rule_error: Option<String>,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 100,
+            on_layout_switch: None,
+            on_macro_record_start: None,
+            on_macro_record_stop: None,
+            on_rule_error: None,
+        }
+    }
+}
+
+impl SoundSettings {
+    pub(crate) fn sound(&self, event: SoundEvent) -> Option<&str> {
+        match event {
+            SoundEvent::LayoutSwitch => self.on_layout_switch.as_deref(),
+            SoundEvent::MacroRecordStart => self.on_macro_record_start.as_deref(),
+            SoundEvent::MacroRecordStop => self.on_macro_record_stop.as_deref(),
+            SoundEvent::RuleError => self.on_rule_error.as_deref(),
+        }
+        .filter(|s| !s.is_empty())
+    }
+
+    /// Applies the settings to all the sounds played by the application.
+    pub(crate) fn apply(&self) {
+        IS_ENABLED.store(self.enabled, Ordering::Relaxed);
+        set_volume(self.volume);
+    }
+}
+
+/// Plays the sound bound to the event, if any.
+pub(crate) fn play_event_sound(settings: Option<&SoundSettings>, event: SoundEvent) {
+    if let Some(sound) = settings.and_then(|s| s.sound(event)) {
+        debug!("Playing sound for event: {:?}", event);
+        play_sound(sound);
+    }
+}
+
+/// Queues the sound. Sounds are played one after another on the player thread so that
+/// the caller is never blocked and the sounds do not cut each other off.
+pub(crate) fn play_sound(sound: &str) {
+    if !IS_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let player = PLAYER.get_or_init(|| {
+        let (sender, receiver) = channel::<String>();
+        thread::spawn(move || {
+            for sound in receiver {
+                play_sound_sync(&sound);
+            }
+        });
+        sender
+    });
+
+    if let Err(e) = player.send(sound.to_string()) {
+        warn!("Unable to queue sound: `{}` : {}", sound, e);
+    }
+}
+
+fn play_sound_sync(sound: &str) {
+    let source = if Path::new(sound).is_file() {
+        SND_FILENAME
+    } else {
+        SND_ALIAS
+    };
+    let w_sound: Vec<u16> = sound.encode_utf16().chain(std::iter::once(0)).collect();
+    let result = unsafe {
+        PlaySoundW(
+            PCWSTR(w_sound.as_ptr()),
+            None,
+            source | SND_NODEFAULT | SND_SYNC,
+        )
+    };
+
+    if !result.as_bool() {
+        warn!("Unable to play sound: `{}` : {:?}", sound, unsafe {
+            GetLastError()
+        });
+    }
+}
+
+fn set_volume(percents: u8) {
+    let channel = volume_level(percents);
+    let result = unsafe { waveOutSetVolume(HWAVEOUT::default(), channel << 16 | channel) };
+    if result != 0 {
+        warn!("Unable to set sound volume: {}", result);
+    }
+}
+
+/// Converts percents to the level of one channel.
+fn volume_level(percents: u8) -> u32 {
+    percents.min(100) as u32 * 0xFFFF / 100
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::{SoundEvent, SoundSettings, volume_level};
+    use crate::str;
+
+    #[test]
+    fn test_event_sound() {
+        let settings = SoundSettings {
+            on_macro_record_start: Some(str!("start.wav")),
+            on_rule_error: Some(str!("")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Some("start.wav"),
+            settings.sound(SoundEvent::MacroRecordStart)
+        );
+        assert_eq!(None, settings.sound(SoundEvent::MacroRecordStop));
+        assert_eq!(None, settings.sound(SoundEvent::RuleError));
+    }
+
+    #[test]
+    fn test_volume_level() {
+        assert_eq!(0, volume_level(0));
+        assert_eq!(0x7FFF, volume_level(50));
+        assert_eq!(0xFFFF, volume_level(100));
+        assert_eq!(0xFFFF, volume_level(200));
+    }
+}
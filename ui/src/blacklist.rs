@@ -0,0 +1,100 @@
+use crate::profile::WindowMatch;
+use keympostor::window::WindowInfo;
+use serde::{Deserialize, Serialize};
+
+/// Windows in which the keys are passed through untransformed, like the virtual machines, the
+/// remote desktops or the games with anti-cheat, e.g.
+/// `blacklist = [{ exe = "mstsc.exe" }, { title = "VirtualBox$" }]`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct AppBlacklist(Vec<WindowMatch>);
+
+impl AppBlacklist {
+    pub(crate) fn matches(&self, window: &WindowInfo) -> bool {
+        self.0.iter().any(|m| m.matches(window))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds the executable of the window to the list, or removes the entries matching the
+    /// window when it is listed. Returns `true` if the window is listed after that.
+    pub(crate) fn toggle(&mut self, window: &WindowInfo) -> bool {
+        if self.matches(window) {
+            self.0.retain(|m| !m.matches(window));
+            false
+        } else {
+            let exe = window
+                .process_path
+                .rsplit(['\\', '/'])
+                .next()
+                .unwrap_or_default();
+            self.0.push(WindowMatch {
+                exe: Some(exe.to_string()),
+                ..Default::default()
+            });
+            true
+        }
+    }
+}
+
+impl From<Vec<WindowMatch>> for AppBlacklist {
+    fn from(vec: Vec<WindowMatch>) -> Self {
+        Self(vec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blacklist::AppBlacklist;
+    use crate::profile::WindowMatch;
+    use crate::str;
+    use keympostor::window::WindowInfo;
+
+    fn window(title: &str, process_path: &str) -> WindowInfo {
+        WindowInfo {
+            title: title.into(),
+            class_name: Default::default(),
+            process_path: process_path.into(),
+        }
+    }
+
+    #[test]
+    fn test_blacklist_matches() {
+        let blacklist = AppBlacklist(vec![
+            WindowMatch {
+                exe: Some(str!("mstsc.exe")),
+                ..Default::default()
+            },
+            WindowMatch {
+                title: Some(str!("VirtualBox$")),
+                ..Default::default()
+            },
+        ]);
+
+        assert!(blacklist.matches(&window("Remote", r"C:\Windows\System32\MSTSC.EXE")));
+        assert!(blacklist.matches(&window("Ubuntu - VirtualBox", r"C:\vm\VirtualBoxVM.exe")));
+        assert!(!blacklist.matches(&window("Notepad", r"C:\Windows\notepad.exe")));
+        assert!(!AppBlacklist::default().matches(&window("Notepad", r"C:\Windows\notepad.exe")));
+    }
+
+    #[test]
+    fn test_blacklist_toggle() {
+        let notepad = window("Notepad", r"C:\Windows\notepad.exe");
+        let mut blacklist = AppBlacklist::default();
+
+        assert!(blacklist.toggle(&notepad));
+        assert!(blacklist.matches(&notepad));
+        assert_eq!(
+            AppBlacklist(vec![WindowMatch {
+                exe: Some(str!("notepad.exe")),
+                ..Default::default()
+            }]),
+            blacklist
+        );
+
+        assert!(!blacklist.toggle(&notepad));
+        assert!(blacklist.is_empty());
+    }
+}
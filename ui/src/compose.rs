@@ -0,0 +1,43 @@
+use keympostor::compose::ComposeTable;
+use log::debug;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+pub(crate) const COMPOSE_TABLE_FILE: &str = "compose.toml";
+
+/// Loads the compose table. Missing file means an empty table.
+pub(crate) fn load_compose_table(path: Option<&str>) -> Result<ComposeTable, Box<dyn Error>> {
+    load_compose_table_from(path.unwrap_or(COMPOSE_TABLE_FILE))
+}
+
+fn load_compose_table_from<P: AsRef<Path>>(path: P) -> Result<ComposeTable, Box<dyn Error>> {
+    if !path.as_ref().exists() {
+        debug!("Compose table not found: `{}`", path.as_ref().display());
+        return Ok(ComposeTable::default());
+    }
+    let text = fs::read_to_string(path)?;
+    Ok(ComposeTable::from_str(&text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compose::load_compose_table_from;
+
+    #[test]
+    fn test_load_compose_table() {
+        let table = load_compose_table_from("etc/test_data/compose.toml").unwrap();
+
+        assert!(!table.is_empty());
+        assert_eq!(Some('á'), table.get("a'"));
+        assert_eq!(Some('€'), table.get("=e"));
+    }
+
+    #[test]
+    fn test_load_missing_compose_table() {
+        let table = load_compose_table_from("etc/test_data/missing.toml").unwrap();
+
+        assert!(table.is_empty());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::app::App;
+use log::{debug, warn};
+use native_windows_gui::{ControlHandle, Event};
+use std::cell::Cell;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Shell::{
+    QUERY_USER_NOTIFICATION_STATE, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME,
+    QUNS_RUNNING_D3D_FULL_SCREEN, SHQueryUserNotificationState,
+};
+use windows::Win32::UI::WindowsAndMessaging::{KillTimer, SetTimer};
+
+const TIMER_ID: usize = 19722;
+const WATCH_INTERVAL: u32 = 2000;
+
+/// Whether the user may be disturbed according to the shell notification state.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum FocusState {
+    #[default]
+    Normal,
+    /// Presentation mode, full screen application or Focus Assist quiet hours.
+    Quiet,
+}
+
+impl FocusState {
+    pub(crate) fn query() -> Self {
+        match unsafe { SHQueryUserNotificationState() } {
+            Ok(state) => Self::from_notification_state(state),
+            Err(e) => {
+                debug!("Failed to query user notification state: {}", e);
+                Self::Normal
+            }
+        }
+    }
+
+    fn from_notification_state(state: QUERY_USER_NOTIFICATION_STATE) -> Self {
+        match state {
+            QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE | QUNS_QUIET_TIME => {
+                Self::Quiet
+            }
+            _ => Self::Normal,
+        }
+    }
+}
+
+/// Polls the shell notification state.
+#[derive(Default)]
+pub(crate) struct FocusWatcher {
+    hwnd: Cell<Option<HWND>>,
+    last_state: Cell<FocusState>,
+}
+
+impl FocusWatcher {
+    pub(crate) fn setup(&self, hwnd: HWND) {
+        self.hwnd.set(Some(hwnd));
+
+        unsafe {
+            SetTimer(Some(hwnd), TIMER_ID, WATCH_INTERVAL, None);
+        }
+
+        debug!("Focus state watch started");
+    }
+
+    pub(crate) fn stop(&self) {
+        let Some(hwnd) = self.hwnd.take() else {
+            return;
+        };
+
+        unsafe {
+            KillTimer(Some(hwnd), TIMER_ID).unwrap_or_else(|e| {
+                if e.code().is_err() {
+                    warn!("Failed to kill focus state watch timer: {}", e);
+                }
+            });
+        }
+
+        debug!("Focus state watch stopped");
+    }
+
+    pub(crate) fn handle_event(&self, app: &App, evt: Event, handle: ControlHandle) {
+        if let Event::OnTimerTick = evt {
+            if let Some((_, timer_id)) = handle.timer() {
+                if timer_id == TIMER_ID as u32 {
+                    self.check_state(app);
+                }
+            }
+        }
+    }
+
+    fn check_state(&self, app: &App) {
+        let state = FocusState::query();
+        if state == self.last_state.get() {
+            return;
+        }
+
+        debug!("Focus state: {:?}", state);
+
+        self.last_state.set(state);
+        app.on_focus_state_changed(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::focus_watch::FocusState;
+    use windows::Win32::UI::Shell::{
+        QUNS_ACCEPTS_NOTIFICATIONS, QUNS_APP, QUNS_NOT_PRESENT, QUNS_PRESENTATION_MODE,
+        QUNS_QUIET_TIME,
+    };
+
+    #[test]
+    fn test_focus_state_from_notification_state() {
+        assert_eq!(
+            FocusState::Quiet,
+            FocusState::from_notification_state(QUNS_PRESENTATION_MODE)
+        );
+        assert_eq!(
+            FocusState::Quiet,
+            FocusState::from_notification_state(QUNS_QUIET_TIME)
+        );
+        assert_eq!(
+            FocusState::Normal,
+            FocusState::from_notification_state(QUNS_ACCEPTS_NOTIFICATIONS)
+        );
+        assert_eq!(
+            FocusState::Normal,
+            FocusState::from_notification_state(QUNS_NOT_PRESENT)
+        );
+        assert_eq!(
+            FocusState::Normal,
+            FocusState::from_notification_state(QUNS_APP)
+        );
+    }
+}
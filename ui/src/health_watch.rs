@@ -0,0 +1,63 @@
+use crate::app::App;
+use keympostor::health::HookHealth;
+use log::{debug, warn};
+use native_windows_gui::{ControlHandle, Event};
+use std::cell::Cell;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{KillTimer, SetTimer};
+
+const TIMER_ID: usize = 19721;
+const WATCH_INTERVAL: u32 = 1000;
+
+/// Watches whether the hook is able to send input to the foreground window.
+#[derive(Default)]
+pub(crate) struct HookHealthWatcher {
+    hwnd: Cell<HWND>,
+    last_health: Cell<HookHealth>,
+}
+
+impl HookHealthWatcher {
+    pub(crate) fn setup(&self, hwnd: HWND) {
+        self.hwnd.set(hwnd);
+
+        unsafe {
+            SetTimer(Some(hwnd), TIMER_ID, WATCH_INTERVAL, None);
+        }
+
+        debug!("Hook health watch started");
+    }
+
+    pub(crate) fn stop(&self) {
+        unsafe {
+            KillTimer(Some(self.hwnd.get()), TIMER_ID).unwrap_or_else(|e| {
+                if e.code().is_err() {
+                    warn!("Failed to kill hook health watch timer: {}", e);
+                }
+            });
+        }
+
+        debug!("Hook health watch stopped");
+    }
+
+    pub(crate) fn handle_event(&self, app: &App, evt: Event, handle: ControlHandle) {
+        if let Event::OnTimerTick = evt {
+            if let Some((_, timer_id)) = handle.timer() {
+                if timer_id == TIMER_ID as u32 {
+                    self.check_health(app);
+                }
+            }
+        }
+    }
+
+    fn check_health(&self, app: &App) {
+        let health = HookHealth::check();
+        if health == self.last_health.get() {
+            return;
+        }
+
+        debug!("Hook health: {:?}", health);
+
+        self.last_health.set(health);
+        app.on_hook_health_changed(health);
+    }
+}
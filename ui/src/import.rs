@@ -0,0 +1,237 @@
+use crate::layout::{KeyTransformLayout, LAYOUTS_PATH, LayoutFormat};
+use crate::layout_files::new_layout_path;
+use crate::rs;
+use crate::ui::res::RESOURCES;
+use crate::ui::res_ids::IDS_CONFIRM_IMPORT_LAYOUT;
+use crate::ui::utils::show_confirm_message;
+use log::{debug, info};
+use std::error::Error;
+use std::ffi::c_void;
+use std::ptr::null_mut;
+use std::{env, fs};
+use windows::Win32::Networking::WinHttp::{
+    URL_COMPONENTS, WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY, WINHTTP_FLAG_SECURE,
+    WINHTTP_INTERNET_SCHEME_HTTPS, WINHTTP_QUERY_FLAG_NUMBER, WINHTTP_QUERY_STATUS_CODE,
+    WinHttpCloseHandle, WinHttpConnect, WinHttpCrackUrl, WinHttpOpen, WinHttpOpenRequest,
+    WinHttpQueryHeaders, WinHttpReadData, WinHttpReceiveResponse, WinHttpSendRequest,
+};
+use windows::core::{HSTRING, PCWSTR, w};
+
+const PROTOCOL_PREFIX: &str = "keympostor://import?";
+const MAX_LAYOUT_SIZE: usize = 256 * 1024;
+const PREVIEW_LINES: usize = 10;
+
+/// Returns layout URL passed as `keympostor://import?url=...` command line argument.
+pub(crate) fn import_url_from_args() -> Option<String> {
+    env::args()
+        .skip(1)
+        .find_map(|arg| import_url_from_protocol(&arg))
+}
+
+fn import_url_from_protocol(arg: &str) -> Option<String> {
+    arg.strip_prefix(PROTOCOL_PREFIX)?
+        .split('&')
+        .find_map(|param| param.strip_prefix("url="))
+        .and_then(percent_decode)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'%' => {
+                let hex = [iter.next()?, iter.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            b'+' => bytes.push(b' '),
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Downloads layout, shows its preview and saves it into the layouts directory when confirmed.
+/// Returns `false` when user cancelled the import.
+pub(crate) fn import_layout(url: &str) -> Result<bool, Box<dyn Error>> {
+    info!("Importing layout from `{url}`");
+
+    let format = format_of_url(url)?;
+    let text = String::from_utf8(download(url, MAX_LAYOUT_SIZE)?)?;
+    let layout = format.parse(&text)?;
+    /* existing layout of the name is never replaced */
+    let path = new_layout_path(LAYOUTS_PATH, &layout.name, format)?;
+    if !show_confirm_message(&format!(
+        "{}\n\n{}\n\n{}",
+        rs!(IDS_CONFIRM_IMPORT_LAYOUT),
+        path.display(),
+        preview(&layout)
+    )) {
+        return Ok(false);
+    }
+
+    fs::write(&path, text)?;
+    info!("Layout saved to `{}`", path.display());
+    Ok(true)
+}
+
+fn format_of_url(url: &str) -> Result<LayoutFormat, Box<dyn Error>> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    LayoutFormat::from_path(path)
+        .ok_or_else(|| format!("Unsupported layout file format: `{url}`").into())
+}
+
+fn preview(layout: &KeyTransformLayout) -> String {
+    let text = format!("{}\n{}", layout.title, layout);
+    let mut lines: Vec<_> = text.lines().take(PREVIEW_LINES + 1).collect();
+    if lines.len() > PREVIEW_LINES {
+        lines[PREVIEW_LINES] = "...";
+    }
+    lines.join("\n")
+}
+
+/// Internet handle closed on drop.
+struct HttpHandle(*mut c_void);
+
+impl HttpHandle {
+    fn new(handle: *mut c_void) -> windows::core::Result<Self> {
+        if handle.is_null() {
+            Err(windows::core::Error::from_thread())
+        } else {
+            Ok(Self(handle))
+        }
+    }
+}
+
+impl Drop for HttpHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = WinHttpCloseHandle(self.0);
+        }
+    }
+}
+
+/// Downloads content over HTTPS failing when it exceeds the size limit.
+fn download(url: &str, max_size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    let url: Vec<u16> = url.encode_utf16().collect();
+
+    /* zero pointers with non-zero lengths make it return parts of the source string */
+    let mut components = URL_COMPONENTS {
+        dwStructSize: size_of::<URL_COMPONENTS>() as u32,
+        dwHostNameLength: u32::MAX,
+        dwUrlPathLength: u32::MAX,
+        dwExtraInfoLength: u32::MAX,
+        ..Default::default()
+    };
+    unsafe { WinHttpCrackUrl(&url, 0, &mut components)? };
+    if components.nScheme != WINHTTP_INTERNET_SCHEME_HTTPS {
+        return Err("Only HTTPS links are supported".into());
+    }
+
+    let (host, object) = unsafe {
+        let host = std::slice::from_raw_parts(
+            components.lpszHostName.0,
+            components.dwHostNameLength as usize,
+        );
+        /* query string follows the path in the source */
+        let object = std::slice::from_raw_parts(
+            components.lpszUrlPath.0,
+            (components.dwUrlPathLength + components.dwExtraInfoLength) as usize,
+        );
+        (HSTRING::from_wide(host), HSTRING::from_wide(object))
+    };
+
+    unsafe {
+        let session = HttpHandle::new(WinHttpOpen(
+            w!("Keympostor"),
+            WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            0,
+        ))?;
+        let connection = HttpHandle::new(WinHttpConnect(session.0, &host, components.nPort, 0))?;
+        let request = HttpHandle::new(WinHttpOpenRequest(
+            connection.0,
+            w!("GET"),
+            &object,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            null_mut(),
+            WINHTTP_FLAG_SECURE,
+        ))?;
+
+        WinHttpSendRequest(request.0, None, None, 0, 0, 0)?;
+        WinHttpReceiveResponse(request.0, null_mut())?;
+
+        let mut status = 0u32;
+        let mut size = size_of::<u32>() as u32;
+        WinHttpQueryHeaders(
+            request.0,
+            WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+            PCWSTR::null(),
+            Some(&mut status as *mut _ as *mut c_void),
+            &mut size,
+            null_mut(),
+        )?;
+        if status != 200 {
+            return Err(format!("Server responded with status {status}").into());
+        }
+
+        let mut content = vec![];
+        let mut buffer = [0u8; 8192];
+        loop {
+            let mut read = 0;
+            WinHttpReadData(
+                request.0,
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len() as u32,
+                &mut read,
+            )?;
+            if read == 0 {
+                break;
+            }
+
+            content.extend_from_slice(&buffer[..read as usize]);
+            if content.len() > max_size {
+                return Err(format!("Layout is larger than {max_size} bytes").into());
+            }
+        }
+
+        debug!("Downloaded {} bytes", content.len());
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::import::{format_of_url, import_url_from_protocol};
+    use crate::layout::LayoutFormat;
+
+    #[test]
+    fn test_import_url_from_protocol() {
+        assert_eq!(
+            Some("https://example.com/layouts/my.toml?raw=1".to_string()),
+            import_url_from_protocol(
+                "keympostor://import?url=https%3A%2F%2Fexample.com%2Flayouts%2Fmy.toml%3Fraw%3D1"
+            )
+        );
+        assert_eq!(
+            Some("https://example.com/a.json".to_string()),
+            import_url_from_protocol("keympostor://import?name=a&url=https://example.com/a.json")
+        );
+        assert_eq!(
+            None,
+            import_url_from_protocol("keympostor://import?url=%ZZ")
+        );
+        assert_eq!(None, import_url_from_protocol("--enable"));
+    }
+
+    #[test]
+    fn test_format_of_url() {
+        assert_eq!(
+            LayoutFormat::Yaml,
+            format_of_url("https://example.com/my.yml?raw=1").unwrap()
+        );
+        assert!(format_of_url("https://example.com/my.txt").is_err());
+    }
+}
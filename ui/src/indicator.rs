@@ -1,14 +1,17 @@
 use crate::kb_watch::KeyboardLayoutState;
 use crate::layout::KeyTransformLayout;
-use crate::util::play_sound;
+use crate::audio::play_sound;
 use log::{debug, error};
 use lomen_core::color::LightingColors;
 use lomen_core::light_control::*;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 static NO_LAYOUT_LIGHTING_COLORS: OnceLock<Option<LightingColors>> = OnceLock::new();
+static IS_SOUND_MUTED: AtomicBool = AtomicBool::new(false);
+static IS_PROFILE_SOUND_ENABLED: AtomicBool = AtomicBool::new(true);
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 #[serde(into = "Vec<String>", from = "Vec<String>")]
@@ -70,7 +73,22 @@ fn set_layout_keyboard_lighting(
     }
 }
 
+/// Layout sounds are not played while muted.
+pub(crate) fn set_sound_muted(muted: bool) {
+    IS_SOUND_MUTED.store(muted, Ordering::Relaxed);
+}
+
+/// Layout sounds are not played while the current profile disables them.
+pub(crate) fn set_profile_sound_enabled(enabled: bool) {
+    IS_PROFILE_SOUND_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
 fn play_layout_sound(layout: &KeyTransformLayout, keyboard_state: &KeyboardLayoutState) {
+    if IS_SOUND_MUTED.load(Ordering::Relaxed) || !IS_PROFILE_SOUND_ENABLED.load(Ordering::Relaxed) {
+        debug!("Layout sound muted");
+        return;
+    }
+
     if let Some(layout_settings) = layout.sound.as_ref() {
         let locks = &keyboard_state.locks();
         if let Some(locks_settings) = layout_settings
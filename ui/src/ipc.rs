@@ -0,0 +1,386 @@
+use crate::pipe::{PipeListener, PipeStream};
+use keympostor::event::KeyEventRecord;
+use keympostor::notify::KeyEventNotification;
+use keympostor::rule::KeyTransformRules;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::mpsc::{Sender, channel};
+use std::thread;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+pub(crate) const WM_IPC_REQUEST: u32 = 88476;
+
+/* JSON-RPC 2.0 error codes */
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const SERVER_ERROR: i32 = -32000;
+
+/// Command received from a control client.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum IpcCommand {
+    /// Selects the profile by name or no profile.
+    SetProfile(Option<String>),
+    /// Replaces rules of the layout by name or of the current one.
+    SetRules {
+        layout: Option<String>,
+        rules: KeyTransformRules,
+    },
+    Enable,
+    Disable,
+    Reload,
+    GetState,
+    /// Starts sending `key_event` notifications to the client.
+    Subscribe,
+    Unsubscribe,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetProfileParams {
+    name: Option<String>,
+}
+
+/// Rules are the array of the rule lines. The table of the layout files is accepted too.
+#[derive(Debug, Deserialize)]
+struct SetRulesParams {
+    layout: Option<String>,
+    rules: KeyTransformRules,
+}
+
+impl IpcCommand {
+    fn from_request(request: &RpcRequest) -> Result<Self, (i32, String)> {
+        let command = match request.method.as_str() {
+            "set_profile" => {
+                let params: SetProfileParams = serde_json::from_value(request.params.clone())
+                    .map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+                Self::SetProfile(params.name)
+            }
+            "set_rules" => {
+                let params: SetRulesParams = serde_json::from_value(request.params.clone())
+                    .map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+                Self::SetRules {
+                    layout: params.layout,
+                    rules: params.rules,
+                }
+            }
+            "enable" => Self::Enable,
+            "disable" => Self::Disable,
+            "reload" => Self::Reload,
+            "get_state" => Self::GetState,
+            "subscribe" => Self::Subscribe,
+            "unsubscribe" => Self::Unsubscribe,
+            method => {
+                return Err((METHOD_NOT_FOUND, format!("Method not found: `{method}`")));
+            }
+        };
+        Ok(command)
+    }
+}
+
+/// Connection to a control client. Lines sent to it are written to the pipe by its own thread.
+#[derive(Debug, Clone)]
+pub(crate) struct IpcClient {
+    id: u32,
+    sender: Sender<String>,
+}
+
+impl IpcClient {
+    /// Returns `false` when the client is disconnected.
+    fn send(&self, line: String) -> bool {
+        self.sender.send(line).is_ok()
+    }
+}
+
+/// Command passed from a client thread to the main window.
+#[derive(Debug)]
+pub(crate) struct IpcRequest {
+    pub(crate) command: IpcCommand,
+    pub(crate) client: IpcClient,
+    id: Option<Value>,
+}
+
+impl IpcRequest {
+    /// Takes ownership of the request posted by a client thread.
+    pub(crate) fn from_message(msg: u32, l_param: isize) -> Option<Self> {
+        if msg == WM_IPC_REQUEST {
+            Some(*unsafe { Box::from_raw(l_param as *mut Self) })
+        } else {
+            None
+        }
+    }
+
+    /// Sends the command result unless the request was a notification.
+    pub(crate) fn reply(&self, result: Result<Value, String>) {
+        if let Some(id) = &self.id {
+            let response = match result {
+                Ok(result) => response(id, result),
+                Err(message) => error_response(id, SERVER_ERROR, &message),
+            };
+            self.client.send(response);
+        }
+    }
+
+    fn post(self, hwnd: isize) {
+        let raw_ptr = Box::into_raw(Box::new(self));
+        unsafe {
+            if let Err(e) = PostMessageW(
+                Some(HWND(hwnd as _)),
+                WM_IPC_REQUEST,
+                WPARAM(0),
+                LPARAM(raw_ptr as isize),
+            ) {
+                warn!("Failed to post IPC request: {}", e);
+                drop(Box::from_raw(raw_ptr));
+            }
+        }
+    }
+}
+
+/// Named pipe server of line delimited JSON-RPC 2.0 control commands. Only the user running the
+/// application can connect to it.
+#[derive(Default)]
+pub(crate) struct IpcServer {
+    subscribers: RefCell<Vec<IpcClient>>,
+}
+
+impl IpcServer {
+    pub(crate) fn start(&self, owner: HWND, pipe: &str) {
+        let mut listener = match PipeListener::bind(pipe) {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("Failed to start IPC server on `{}`: {}", pipe, e);
+                return;
+            }
+        };
+        info!("IPC server listening on `{}`", pipe);
+
+        /* window handle is not `Send` */
+        let hwnd = owner.0 as isize;
+        thread::spawn(move || {
+            for id in 1.. {
+                match listener.accept() {
+                    Ok(stream) => {
+                        thread::spawn(move || {
+                            serve_client(stream, id, hwnd)
+                                .unwrap_or_else(|e| debug!("IPC client {} failed: {}", id, e));
+                        });
+                    }
+                    Err(e) => {
+                        warn!("Failed to accept IPC client: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    pub(crate) fn subscribe(&self, client: &IpcClient) {
+        let mut subscribers = self.subscribers.borrow_mut();
+        if !subscribers.iter().any(|c| c.id == client.id) {
+            subscribers.push(client.clone());
+        }
+    }
+
+    pub(crate) fn unsubscribe(&self, client: &IpcClient) {
+        self.subscribers.borrow_mut().retain(|c| c.id != client.id);
+    }
+
+    /// Sends the event to subscribed clients dropping disconnected ones.
+    pub(crate) fn has_subscribers(&self) -> bool {
+        !self.subscribers.borrow().is_empty()
+    }
+
+    pub(crate) fn notify_key_event(&self, notification: &KeyEventNotification) {
+        let mut subscribers = self.subscribers.borrow_mut();
+        if subscribers.is_empty() {
+            return;
+        }
+
+        let record = KeyEventRecord::new(&notification.event, notification.rule.as_deref());
+        let message = json!({
+            "jsonrpc": "2.0",
+            "method": "key_event",
+            "params": record,
+        })
+        .to_string();
+        subscribers.retain(|client| client.send(message.clone()));
+    }
+}
+
+fn serve_client(stream: PipeStream, id: u32, hwnd: isize) -> std::io::Result<()> {
+    debug!("IPC client {} connected", id);
+
+    let (sender, receiver) = channel::<String>();
+    let mut writer = stream.try_clone()?;
+    thread::spawn(move || {
+        for line in receiver {
+            if writeln!(writer, "{line}").is_err() {
+                break;
+            }
+        }
+    });
+
+    let client = IpcClient { id, sender };
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_request(&line) {
+            Ok((request_id, command)) => IpcRequest {
+                command,
+                client: client.clone(),
+                id: request_id,
+            }
+            .post(hwnd),
+            Err(response) => {
+                client.send(response);
+            }
+        }
+    }
+
+    debug!("IPC client {} disconnected", id);
+    Ok(())
+}
+
+/// Returns the request id and command or the error response.
+fn parse_request(line: &str) -> Result<(Option<Value>, IpcCommand), String> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| error_response(&Value::Null, PARSE_ERROR, &e.to_string()))?;
+    let request: RpcRequest = serde_json::from_value(value)
+        .map_err(|e| error_response(&Value::Null, INVALID_REQUEST, &e.to_string()))?;
+    let command = IpcCommand::from_request(&request).map_err(|(code, message)| {
+        error_response(request.id.as_ref().unwrap_or(&Value::Null), code, &message)
+    })?;
+    Ok((request.id, command))
+}
+
+fn response(id: &Value, result: Value) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "result": result,
+    })
+    .to_string()
+}
+
+fn error_response(id: &Value, code: i32, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": code,
+            "message": message,
+        },
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ipc::{IpcCommand, error_response, parse_request, response};
+    use crate::str;
+    use keympostor::key_rules;
+    use keympostor::rule::KeyTransformRules;
+    use serde_json::{Value, json};
+    use std::str::FromStr;
+
+    fn parse_error_code(line: &str) -> Value {
+        let response: Value = serde_json::from_str(&parse_request(line).unwrap_err()).unwrap();
+        response["error"]["code"].clone()
+    }
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(
+            (Some(json!(1)), IpcCommand::GetState),
+            parse_request(r#"{"jsonrpc": "2.0", "id": 1, "method": "get_state"}"#).unwrap()
+        );
+        assert_eq!(
+            (None, IpcCommand::Enable),
+            parse_request(r#"{"jsonrpc": "2.0", "method": "enable"}"#).unwrap()
+        );
+        assert_eq!(
+            (
+                Some(json!("a")),
+                IpcCommand::SetProfile(Some(str!("chrome")))
+            ),
+            parse_request(
+                r#"{"jsonrpc": "2.0", "id": "a", "method": "set_profile", "params": {"name": "chrome"}}"#
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            (
+                Some(json!(3)),
+                IpcCommand::SetRules {
+                    layout: None,
+                    rules: key_rules!("A↓ : B↓"),
+                }
+            ),
+            parse_request(
+                r#"{"jsonrpc": "2.0", "id": 3, "method": "set_rules", "params": {"rules": {"A↓": "B↓"}}}"#
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            (
+                Some(json!(4)),
+                IpcCommand::SetRules {
+                    layout: Some(str!("main")),
+                    rules: key_rules!("B↓ : C↓ | DEVICE=PAD\nB↓ : D↓ | DEVICE=MOUSE\nA↓ : B↓"),
+                }
+            ),
+            parse_request(
+                r#"{"jsonrpc": "2.0", "id": 4, "method": "set_rules", "params": {"layout": "main", "rules": ["B↓ : C↓ | DEVICE=PAD", "B↓ : D↓ | DEVICE=MOUSE", "A↓ : B↓"]}}"#
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            (Some(json!(2)), IpcCommand::SetProfile(None)),
+            parse_request(
+                r#"{"jsonrpc": "2.0", "id": 2, "method": "set_profile", "params": {"name": null}}"#
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_request_errors() {
+        assert_eq!(json!(-32700), parse_error_code("{"));
+        assert_eq!(json!(-32600), parse_error_code(r#"{"id": 1}"#));
+        assert_eq!(
+            json!(-32601),
+            parse_error_code(r#"{"id": 1, "method": "shutdown"}"#)
+        );
+        assert_eq!(
+            json!(-32602),
+            parse_error_code(r#"{"id": 1, "method": "set_profile", "params": 5}"#)
+        );
+    }
+
+    #[test]
+    fn test_response() {
+        assert_eq!(
+            json!({"jsonrpc": "2.0", "id": 1, "result": {"enabled": true}}),
+            serde_json::from_str::<Value>(&response(&json!(1), json!({"enabled": true}))).unwrap()
+        );
+        assert_eq!(
+            json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32000, "message": "Failed"}}),
+            serde_json::from_str::<Value>(&error_response(&Value::Null, -32000, "Failed")).unwrap()
+        );
+    }
+}
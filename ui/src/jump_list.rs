@@ -0,0 +1,167 @@
+use crate::rs;
+use crate::ui::res::RESOURCES;
+use crate::ui::res_ids::{IDS_DISABLE_PROCESSING, IDS_ENABLE_PROCESSING, IDS_NEXT_LAYOUT};
+use log::{debug, warn};
+use std::env;
+use std::sync::OnceLock;
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+use windows::Win32::System::Com::StructuredStorage::{PROPVARIANT, PropVariantChangeType};
+use windows::Win32::System::Com::{
+    CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED, CoCreateInstance, CoInitializeEx,
+};
+use windows::Win32::System::Variant::VT_LPWSTR;
+use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+use windows::Win32::UI::Shell::{
+    DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, ShellLink,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    HWND_BROADCAST, PostMessageW, RegisterWindowMessageW,
+};
+use windows::core::{HSTRING, Interface};
+
+const TASK_MESSAGE_NAME: &str = "Keympostor.JumpListTask";
+
+/// Action available from the taskbar jump list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum JumpListTask {
+    EnableProcessing,
+    DisableProcessing,
+    NextLayout,
+}
+
+impl JumpListTask {
+    const ALL: [Self; 3] = [
+        Self::EnableProcessing,
+        Self::DisableProcessing,
+        Self::NextLayout,
+    ];
+
+    fn argument(&self) -> &'static str {
+        match self {
+            Self::EnableProcessing => "--enable",
+            Self::DisableProcessing => "--disable",
+            Self::NextLayout => "--next-layout",
+        }
+    }
+
+    fn title(&self) -> String {
+        match self {
+            Self::EnableProcessing => rs!(IDS_ENABLE_PROCESSING).to_string(),
+            Self::DisableProcessing => rs!(IDS_DISABLE_PROCESSING).to_string(),
+            Self::NextLayout => rs!(IDS_NEXT_LAYOUT).to_string(),
+        }
+    }
+
+    /// Returns task requested by the command line arguments of the process.
+    pub(crate) fn from_args() -> Option<Self> {
+        env::args()
+            .skip(1)
+            .find_map(|arg| Self::from_argument(&arg))
+    }
+
+    fn from_argument(arg: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|task| task.argument() == arg)
+    }
+
+    /// Decodes task posted by another instance of the application.
+    pub(crate) fn from_message(msg: u32, l_param: isize) -> Option<Self> {
+        if msg == task_message() {
+            Self::ALL.get(l_param as usize).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Passes the task to the already running instance of the application.
+    pub(crate) fn post_to_running_app(&self) {
+        let index = Self::ALL.iter().position(|t| t == self).unwrap_or_default();
+        unsafe {
+            PostMessageW(
+                Some(HWND_BROADCAST),
+                task_message(),
+                WPARAM(0),
+                LPARAM(index as isize),
+            )
+            .unwrap_or_else(|e| warn!("Failed to post jump list task: {}", e));
+        }
+    }
+}
+
+fn task_message() -> u32 {
+    static MESSAGE: OnceLock<u32> = OnceLock::new();
+    *MESSAGE.get_or_init(|| unsafe { RegisterWindowMessageW(&HSTRING::from(TASK_MESSAGE_NAME)) })
+}
+
+/// Adds application tasks to the taskbar button jump list.
+pub(crate) fn update_jump_list() {
+    match build_jump_list() {
+        Ok(_) => debug!("Jump list updated"),
+        Err(e) => warn!("Failed to update jump list: {}", e),
+    }
+}
+
+fn build_jump_list() -> windows::core::Result<()> {
+    let exe_path = HSTRING::from(env::current_exe().unwrap_or_default().as_os_str());
+
+    unsafe {
+        /* COM may already be initialized by the GUI library */
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+
+        let list: ICustomDestinationList =
+            CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+        let mut min_slots = 0;
+        let _removed: IObjectArray = list.BeginList(&mut min_slots)?;
+
+        let tasks: IObjectCollection =
+            CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+        for task in JumpListTask::ALL {
+            let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+            link.SetPath(&exe_path)?;
+            link.SetArguments(&HSTRING::from(task.argument()))?;
+            link.SetIconLocation(&exe_path, 0)?;
+
+            /* shell expects the title as a wide string value */
+            let mut title = PROPVARIANT::default();
+            PropVariantChangeType(
+                &mut title,
+                &PROPVARIANT::from(task.title().as_str()),
+                Default::default(),
+                VT_LPWSTR,
+            )?;
+
+            let properties: IPropertyStore = link.cast()?;
+            properties.SetValue(&PKEY_Title, &title)?;
+            properties.Commit()?;
+
+            tasks.AddObject(&link)?;
+        }
+
+        list.AddUserTasks(&tasks.cast::<IObjectArray>()?)?;
+        list.CommitList()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::jump_list::JumpListTask;
+    use crate::jump_list::JumpListTask::{DisableProcessing, EnableProcessing, NextLayout};
+
+    #[test]
+    fn test_task_from_argument() {
+        assert_eq!(
+            Some(EnableProcessing),
+            JumpListTask::from_argument("--enable")
+        );
+        assert_eq!(
+            Some(DisableProcessing),
+            JumpListTask::from_argument("--disable")
+        );
+        assert_eq!(
+            Some(NextLayout),
+            JumpListTask::from_argument("--next-layout")
+        );
+        assert_eq!(None, JumpListTask::from_argument("--unknown"));
+    }
+}
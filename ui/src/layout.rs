@@ -1,15 +1,22 @@
 use crate::indicator::SerdeLightingColors;
-use keympostor::rule::KeyTransformRules;
+use keympostor::presets::LayoutPreset;
+use keympostor::rule::{KeyTransformRule, KeyTransformRules};
+use log::warn;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Key, Table, TableLike, value};
 
-const LAYOUTS_PATH: &str = "layouts";
+pub(crate) const LAYOUTS_PATH: &str = "layouts";
 
-#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+/// Nesting of the included files deeper than this is considered a cycle.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub(crate) struct KeyTransformLayout {
     pub(crate) name: String,
     pub(crate) rules: KeyTransformRules,
@@ -17,30 +24,328 @@ pub(crate) struct KeyTransformLayout {
     pub(crate) icon: Option<String>,
     pub(crate) sound: Option<HashMap<String, HashMap<String, String>>>,
     pub(crate) keyboard_lighting: Option<HashMap<String, HashMap<String, SerdeLightingColors>>>,
+    /// Files with the shared rules, relative to the layout file.
+    pub(crate) include: Option<Vec<String>>,
+    /// Name of the layout whose rules this one inherits.
+    pub(crate) extends: Option<String>,
+    /// Rules of the included files.
+    #[serde(skip)]
+    pub(crate) included_rules: KeyTransformRules,
+    /// Included files, the nested inclusions first.
+    #[serde(skip)]
+    included_files: Vec<PathBuf>,
+    /// Rules inherited from the extended layouts.
+    #[serde(skip)]
+    pub(crate) inherited_rules: Vec<KeyTransformRule>,
+}
+
+/// File with the rules shared by several layouts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LayoutInclude {
+    rules: KeyTransformRules,
+    include: Option<Vec<String>>,
+}
+
+/// Layout file format detected by file extension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LayoutFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl LayoutFormat {
+    pub(crate) fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            Self::Toml => "toml",
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+        }
+    }
+
+    pub(crate) fn parse(&self, text: &str) -> Result<KeyTransformLayout, Box<dyn Error>> {
+        self.deserialize(text)
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, text: &str) -> Result<T, Box<dyn Error>> {
+        let value = match self {
+            Self::Toml => toml::from_str(text)?,
+            Self::Json => serde_json::from_str(text)?,
+            Self::Yaml => serde_yaml::from_str(text)?,
+        };
+        Ok(value)
+    }
+
+    fn format<T: Serialize>(&self, value: &T) -> Result<String, Box<dyn Error>> {
+        let text = match self {
+            Self::Toml => toml::to_string(value)?,
+            Self::Json => serde_json::to_string_pretty(value)?,
+            Self::Yaml => serde_yaml::to_string(value)?,
+        };
+        Ok(text)
+    }
 }
 
 impl KeyTransformLayout {
     pub(crate) fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
-        let text = fs::read_to_string(path)?;
-        let this = toml::from_str(&text)?;
-        Ok(this)
+        let format = Self::format_of(&path)?;
+        let text = fs::read_to_string(&path)?;
+        let mut layout = format.parse(&text)?;
+        let included = load_includes(&path, layout.include.as_deref(), 0)?;
+        layout.included_rules = included
+            .iter()
+            .flat_map(|(_, rules)| rules.iter().cloned())
+            .collect::<Vec<_>>()
+            .into();
+        layout.included_files = included.into_iter().map(|(path, _)| path).collect();
+        Ok(layout)
+    }
+
+    /// Returns the rules the layout applies: the inherited ones, then the included ones, then
+    /// its own. Later rules override the earlier ones with the same conditions.
+    pub(crate) fn effective_rules(&self) -> KeyTransformRules {
+        self.inherited_rules
+            .iter()
+            .chain(self.included_rules.iter())
+            .chain(self.rules.iter())
+            .cloned()
+            .collect::<Vec<_>>()
+            .into()
     }
 
-    #[allow(dead_code)]
-    fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
-        let text = toml::to_string(self)?;
+    /// Writes the layout into the file. An existing TOML file keeps its comments, formatting
+    /// and the order of the rules, only the changed rules are rewritten.
+    pub(crate) fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let format = Self::format_of(&path)?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) if format == LayoutFormat::Toml => merge_toml(&text, &self.rules)?,
+            _ => format.format(self)?,
+        };
         fs::write(path, text)?;
         Ok(())
     }
+
+    /// Gives IDs following `last_id` to the rules of the layout file and of the files it includes
+    /// having none and writes them into the files, so that every rule can be disabled and keeps
+    /// its ID between the sessions. Returns `true` if any file was changed.
+    fn save_rule_ids<P: AsRef<Path>>(
+        &self,
+        path: P,
+        last_id: &mut u32,
+    ) -> Result<bool, Box<dyn Error>> {
+        let mut is_changed = false;
+        for include_path in &self.included_files {
+            is_changed |=
+                save_rule_ids(include_path, last_id, |i: &mut LayoutInclude| &mut i.rules)?;
+        }
+        is_changed |= save_rule_ids(path.as_ref(), last_id, |l: &mut Self| &mut l.rules)?;
+        Ok(is_changed)
+    }
+
+    fn format_of<P: AsRef<Path>>(path: P) -> Result<LayoutFormat, Box<dyn Error>> {
+        let path = path.as_ref();
+        LayoutFormat::from_path(path)
+            .ok_or_else(|| format!("Unsupported layout file format: `{}`", path.display()).into())
+    }
+}
+
+/// Loads the rules of the files included by the file, the nested inclusions first.
+fn load_includes<P: AsRef<Path>>(
+    path: P,
+    include: Option<&[String]>,
+    depth: usize,
+) -> Result<Vec<(PathBuf, KeyTransformRules)>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let Some(include) = include else {
+        return Ok(vec![]);
+    };
+    if depth >= MAX_INCLUDE_DEPTH {
+        return Err(format!("Files include each other: `{}`", path.display()).into());
+    }
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut files = vec![];
+    for name in include {
+        let include_path = dir.join(name);
+        let format = KeyTransformLayout::format_of(&include_path)?;
+        let text = fs::read_to_string(&include_path)
+            .map_err(|e| format!("Failed to read `{}`: {e}", include_path.display()))?;
+        let included: LayoutInclude = format.deserialize(&text)?;
+
+        files.extend(load_includes(
+            &include_path,
+            included.include.as_deref(),
+            depth + 1,
+        )?);
+        files.push((include_path, included.rules));
+    }
+    Ok(files)
+}
+
+/// Gives IDs following `last_id` to the rules of the file having none and writes them into
+/// the file. Returns `true` if the file was changed.
+fn save_rule_ids<T, F>(path: &Path, last_id: &mut u32, rules_of: F) -> Result<bool, Box<dyn Error>>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce(&mut T) -> &mut KeyTransformRules,
+{
+    let format = KeyTransformLayout::format_of(path)?;
+    let text = fs::read_to_string(path)?;
+    let text = match format {
+        LayoutFormat::Toml => assign_toml_ids(&text, last_id)?,
+        _ => {
+            let mut value: T = format.deserialize(&text)?;
+            let rules = rules_of(&mut value);
+            match rules.assign_ids(*last_id) {
+                true => {
+                    *last_id = rules.max_id().unwrap_or(*last_id);
+                    Some(format.format(&value)?)
+                }
+                false => None,
+            }
+        }
+    };
+
+    match text {
+        Some(text) => {
+            fs::write(path, text)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Replaces the rules table of the TOML document keeping the entries whose rules did not change
+/// along with their comments. Changed entries are updated in place, new rules go to the end.
+/// Entries that cannot be parsed are kept as they are.
+fn merge_toml(text: &str, rules: &KeyTransformRules) -> Result<String, Box<dyn Error>> {
+    let mut doc: DocumentMut = text.parse()?;
+    let mut remaining: Vec<&KeyTransformRule> = rules.iter().collect();
+
+    let table = doc
+        .entry("rules")
+        .or_insert(Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or("Layout rules must be a table")?;
+
+    let keys: Vec<String> = table.iter().map(|(k, _)| k.to_string()).collect();
+    for key in keys {
+        let parsed = match table.get(&key).and_then(Item::as_str) {
+            Some(v) => KeyTransformRule::from_str_pair(&key, v).map_err(|e| e.to_string()),
+            None => Err("Rule actions must be a string".to_string()),
+        };
+        /* the entry edited after the layout was loaded is left for the user to fix */
+        let parsed = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                warn!("Kept invalid rule `{}` of the layout: {}", key, e);
+                continue;
+            }
+        };
+        if take_rules(&mut remaining, &parsed) {
+            continue;
+        }
+
+        match remaining.iter().position(|r| r.table_key() == key) {
+            Some(index) => {
+                let rule = remaining.remove(index);
+                update_value(table, &key, &rule.table_value());
+            }
+            None => {
+                table.remove(&key);
+            }
+        }
+    }
+
+    for rule in remaining {
+        table.insert(&rule.table_key(), value(rule.table_value()));
+    }
+    Ok(doc.to_string())
+}
+
+/// Prefixes the keys of the rules table entries having no rule ID with the IDs following
+/// `last_id`, keeping the comments and the order of the entries. All the rules of an entry get
+/// the same ID. Returns `None` if every entry has the ID.
+fn assign_toml_ids(text: &str, last_id: &mut u32) -> Result<Option<String>, Box<dyn Error>> {
+    let mut doc: DocumentMut = text.parse()?;
+    let Some(table) = doc.get_mut("rules").and_then(Item::as_table_like_mut) else {
+        return Ok(None);
+    };
+
+    let is_missing_id = |key: &str, item: &Item| {
+        item.as_str()
+            .and_then(|v| KeyTransformRule::from_str_pair(key, v).ok())
+            .is_some_and(|rules| rules.iter().any(|r| r.id.is_none()))
+    };
+    if !table.iter().any(|(k, v)| is_missing_id(k, v)) {
+        return Ok(None);
+    }
+
+    /* keys cannot be renamed in place so the table is filled again in the same order */
+    let entries: Vec<(Key, Item)> = table
+        .iter()
+        .filter_map(|(k, _)| table.get_key_value(k))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    table.clear();
+    for (key, item) in entries {
+        let key = if is_missing_id(key.get(), &item) {
+            *last_id += 1;
+            Key::new(format!("#{} {}", last_id, key.get()))
+                .with_leaf_decor(key.leaf_decor().clone())
+        } else {
+            key
+        };
+        table.entry_format(&key).or_insert(item);
+    }
+    Ok(Some(doc.to_string()))
+}
+
+/// Removes the rules from the remaining ones if all of them are there.
+fn take_rules(remaining: &mut Vec<&KeyTransformRule>, rules: &[KeyTransformRule]) -> bool {
+    let mut rest = remaining.clone();
+    for rule in rules {
+        match rest.iter().position(|r| *r == rule) {
+            Some(index) => {
+                rest.remove(index);
+            }
+            None => return false,
+        }
+    }
+    *remaining = rest;
+    true
+}
+
+/// Replaces the value of the entry keeping the comments around it.
+fn update_value(table: &mut dyn TableLike, key: &str, text: &str) {
+    let Some(item) = table.get_mut(key) else {
+        return;
+    };
+    let mut new_value = toml_edit::Value::from(text);
+    if let Some(old_value) = item.as_value() {
+        *new_value.decor_mut() = old_value.decor().clone();
+    }
+    *item = Item::Value(new_value);
 }
 
 impl Display for KeyTransformLayout {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\n{}", self.title, self.rules)
+        write!(f, "{}", self.rules)
     }
 }
 
-#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub(crate) struct KeyTransformLayoutList(Vec<KeyTransformLayout>);
 
 impl<'a> IntoIterator for &'a KeyTransformLayoutList {
@@ -54,27 +359,171 @@ impl<'a> IntoIterator for &'a KeyTransformLayoutList {
 
 impl KeyTransformLayoutList {
     pub(crate) fn load() -> Result<KeyTransformLayoutList, Box<dyn Error>> {
-        Self::load_from(LAYOUTS_PATH)
+        let mut list = Self::load_from(LAYOUTS_PATH)?;
+        list.add_presets();
+        list.resolve_extends()?;
+        Ok(list)
+    }
+
+    /// Updates the inherited rules of the layouts extending other ones.
+    fn resolve_extends(&mut self) -> Result<(), Box<dyn Error>> {
+        let inherited = self
+            .0
+            .iter()
+            .map(|layout| match layout.extends.as_deref() {
+                Some(parent) => self.chain_rules(parent, &layout.name),
+                None => Ok(vec![]),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (layout, rules) in self.0.iter_mut().zip(inherited) {
+            layout.inherited_rules = rules;
+        }
+        Ok(())
+    }
+
+    /// Returns the rules of the layout along with the ones of the layouts it extends.
+    fn chain_rules(
+        &self,
+        name: &str,
+        descendant: &str,
+    ) -> Result<Vec<KeyTransformRule>, Box<dyn Error>> {
+        let mut chain: Vec<&KeyTransformLayout> = vec![];
+        let mut next = Some(name);
+        while let Some(name) = next {
+            if name == descendant || chain.iter().any(|l| l.name == name) {
+                return Err(format!("Layouts extend each other: `{descendant}`").into());
+            }
+            let layout = self
+                .find(name)
+                .ok_or_else(|| format!("Extended layout not found: `{name}`"))?;
+            chain.push(layout);
+            next = layout.extends.as_deref();
+        }
+
+        Ok(chain
+            .iter()
+            .rev()
+            .flat_map(|l| l.included_rules.iter().chain(l.rules.iter()))
+            .cloned()
+            .collect())
+    }
+
+    /// Adds the built-in layouts of the presets unless the files define the layouts of the same
+    /// names. Built-in layouts have no files so their rules cannot be edited.
+    fn add_presets(&mut self) {
+        for preset in LayoutPreset::ALL {
+            if self.find(preset.name()).is_none() {
+                /* the rules of the preset do not change so neither do their IDs */
+                let mut rules = preset.rules();
+                rules.assign_ids(0);
+                self.0.push(KeyTransformLayout {
+                    name: preset.name().to_string(),
+                    rules,
+                    title: preset.title().to_string(),
+                    ..Default::default()
+                });
+            }
+        }
     }
 
     fn load_from<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let (paths, mut list) = Self::load_files(&path)?;
+
+        let mut last_id = list.max_rule_id();
+        let mut is_changed = false;
+        for (path, layout) in paths.iter().zip(&list.0) {
+            match layout.save_rule_ids(path, &mut last_id) {
+                Ok(changed) => is_changed |= changed,
+                Err(e) => warn!("Failed to save rule IDs of `{}`: {}", path.display(), e),
+            }
+        }
+        if is_changed {
+            (_, list) = Self::load_files(&path)?;
+        }
+
+        /* the files may be read-only, the rules have the IDs for the session at least */
+        let mut last_id = list.max_rule_id();
+        for layout in &mut list.0 {
+            for rules in [&mut layout.included_rules, &mut layout.rules] {
+                rules.assign_ids(last_id);
+                last_id = rules.max_id().unwrap_or(last_id);
+            }
+        }
+        Ok(list)
+    }
+
+    fn load_files<P: AsRef<Path>>(path: P) -> Result<(Vec<PathBuf>, Self), Box<dyn Error>> {
+        let mut paths = vec![];
         let mut items = vec![];
 
         for entry in fs::read_dir(path)? {
             let path = entry?.path();
-            if path.is_file() {
-                let layout = KeyTransformLayout::load(path)?;
-                items.push(layout);
+            if !path.is_file() {
+                continue;
             }
+            if LayoutFormat::from_path(&path).is_none() {
+                warn!("Skipped unsupported layout file: `{}`", path.display());
+                continue;
+            }
+
+            let layout = KeyTransformLayout::load(&path)?;
+            paths.push(path);
+            items.push(layout);
         }
 
-        Ok(Self(items))
+        Ok((paths, Self(items)))
+    }
+
+    /// Returns the largest ID of the rules of the layouts and of the files they include.
+    fn max_rule_id(&self) -> u32 {
+        self.0
+            .iter()
+            .flat_map(|l| [l.included_rules.max_id(), l.rules.max_id()])
+            .flatten()
+            .max()
+            .unwrap_or(0)
     }
 
     pub(crate) fn find(&self, name: &str) -> Option<&KeyTransformLayout> {
         self.0.iter().find(|l| l.name == *name)
     }
 
+    /// Replaces rules of the layout and writes it back into its file.
+    pub(crate) fn update_rules(
+        &mut self,
+        name: &str,
+        mut rules: KeyTransformRules,
+    ) -> Result<(), Box<dyn Error>> {
+        let path = Self::find_file(LAYOUTS_PATH, name)?;
+        rules.assign_ids(self.max_rule_id());
+        let layout = self
+            .0
+            .iter_mut()
+            .find(|l| l.name == *name)
+            .ok_or_else(|| format!("Layout not found: `{name}`"))?;
+
+        let previous = std::mem::replace(&mut layout.rules, rules);
+        if let Err(e) = layout.save(path) {
+            layout.rules = previous;
+            return Err(e);
+        }
+        self.resolve_extends()
+    }
+
+    /// Returns the file the layout was loaded from.
+    fn find_file<P: AsRef<Path>>(dir: P, name: &str) -> Result<PathBuf, Box<dyn Error>> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if LayoutFormat::from_path(&path).is_some()
+                && KeyTransformLayout::load(&path).is_ok_and(|l| l.name == name)
+            {
+                return Ok(path);
+            }
+        }
+        Err(format!("Layout file not found: `{name}`").into())
+    }
+
     pub(crate) fn first(&self) -> &KeyTransformLayout {
         self.0.first().expect("Layouts cannot be empty")
     }
@@ -91,11 +540,18 @@ impl KeyTransformLayoutList {
 #[cfg(test)]
 pub mod tests {
     use crate::indicator::SerdeLightingColors;
-    use crate::layout::{KeyTransformLayout, KeyTransformLayoutList};
+    use crate::layout::{
+        KeyTransformLayout, KeyTransformLayoutList, LayoutFormat, assign_toml_ids, load_includes,
+        merge_toml,
+    };
     use crate::{map, str};
-    use keympostor::key_rule;
+    use keympostor::event::KeyEvent;
     use keympostor::rule::KeyTransformRule;
     use keympostor::rule::KeyTransformRules;
+    use keympostor::trigger::KeyTrigger;
+    use keympostor::{key_event, key_rule, key_rules};
+    use std::fs;
+    use std::path::Path;
     use std::str::FromStr;
 
     fn create_test_layout() -> KeyTransformLayout {
@@ -154,7 +610,7 @@ pub mod tests {
     #[test]
     fn test_layout_deserialize() {
         let actual = toml::from_str(
-            &r#"
+            r#"
             name = "test"
             title = "Test layout"
             [rules]
@@ -164,12 +620,43 @@ pub mod tests {
         )
         .unwrap();
 
-        /* NOTE: rules deserialized as a sorted map so check the "expected" order */
         let expected = create_test_layout();
 
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_layout_format_from_path() {
+        assert_eq!(
+            Some(LayoutFormat::Toml),
+            LayoutFormat::from_path("a/layout.toml")
+        );
+        assert_eq!(
+            Some(LayoutFormat::Json),
+            LayoutFormat::from_path("layout.JSON")
+        );
+        assert_eq!(
+            Some(LayoutFormat::Yaml),
+            LayoutFormat::from_path("layout.yaml")
+        );
+        assert_eq!(
+            Some(LayoutFormat::Yaml),
+            LayoutFormat::from_path("layout.yml")
+        );
+        assert_eq!(None, LayoutFormat::from_path("layout.txt"));
+        assert_eq!(None, LayoutFormat::from_path("layout"));
+    }
+
+    #[test]
+    fn test_layout_formats_round_trip() {
+        let layout = create_test_layout();
+
+        for format in [LayoutFormat::Toml, LayoutFormat::Json, LayoutFormat::Yaml] {
+            let text = format.format(&layout).unwrap();
+            assert_eq!(layout, format.parse(&text).unwrap(), "{:?}", format);
+        }
+    }
+
     #[test]
     fn test_layout_load() {
         let expected = KeyTransformLayout {
@@ -207,9 +694,10 @@ pub mod tests {
                 ],
             ]),
             rules: KeyTransformRules::from(vec![
-                key_rule!("[LEFT_SHIFT]CAPS_LOCK↓ : CAPS_LOCK↓ → CAPS_LOCK↑"),
                 key_rule!("[]CAPS_LOCK↓ : LEFT_WIN↓ → SPACE↓ → SPACE↑ → LEFT_WIN↑"),
+                key_rule!("[LEFT_SHIFT]CAPS_LOCK↓ : CAPS_LOCK↓ → CAPS_LOCK↑"),
             ]),
+            ..Default::default()
         };
 
         let actual = KeyTransformLayout::load("etc/test_data/layouts/test.toml").unwrap();
@@ -242,17 +730,175 @@ pub mod tests {
                     ]),
                 ],
             ]),
+            ..Default::default()
         };
 
         layout.save("etc/test_data/tmp/saved_layout.toml").unwrap();
     }
 
+    #[test]
+    fn test_layout_merge_toml() {
+        let text = r#"# Sample layout
+name = "test"
+title = "Test layout"
+
+[rules]
+# switch the language
+"CAPS_LOCK↓" = "LEFT_WIN↓ → SPACE↓" # press
+"CAPS_LOCK↑" = "SPACE↑ → LEFT_WIN↑"
+# letters
+"A↓, B↓" = "X↓"
+"Q↓" = "W↓"
+"#;
+        let rules = KeyTransformRules::from(vec![
+            key_rule!("CAPS_LOCK↓ : LEFT_WIN↓ → SPACE↓"),
+            key_rule!("CAPS_LOCK↑ : LEFT_CTRL↑"),
+            key_rule!("A↓ : X↓"),
+            key_rule!("B↓ : X↓"),
+            key_rule!("Z↓ : Q↓"),
+        ]);
+
+        let expected = r#"# Sample layout
+name = "test"
+title = "Test layout"
+
+[rules]
+# switch the language
+"CAPS_LOCK↓" = "LEFT_WIN↓ → SPACE↓" # press
+"CAPS_LOCK↑" = "LEFT_CTRL↑"
+# letters
+"A↓, B↓" = "X↓"
+"Z↓" = "Q↓"
+"#;
+        let actual = merge_toml(text, &rules).unwrap();
+
+        assert_eq!(expected, actual);
+
+        let layout = LayoutFormat::Toml.parse(&actual).unwrap();
+        assert_eq!(rules, layout.rules);
+    }
+
+    #[test]
+    fn test_layout_assign_toml_ids() {
+        let text = r##"name = "test"
+
+[rules]
+# copy
+"F1" = "LEFT_CTRL → C" # press and release
+"#3 F2" = "V↓"
+"F3↓" = "X↓"
+"##;
+        let expected = r##"name = "test"
+
+[rules]
+# copy
+"#6 F1" = "LEFT_CTRL → C" # press and release
+"#3 F2" = "V↓"
+"#7 F3↓" = "X↓"
+"##;
+        let mut last_id = 5;
+
+        assert_eq!(
+            Some(expected.to_string()),
+            assign_toml_ids(text, &mut last_id).unwrap()
+        );
+        assert_eq!(7, last_id);
+        assert_eq!(None, assign_toml_ids(expected, &mut last_id).unwrap());
+    }
+
+    #[test]
+    fn test_layout_merge_toml_invalid_rules() {
+        let text = "[rules]\n\"A↓\" = \"B↓\"\n\"BAD↓\" = \"C↓\" # typo\n\"D↓\" = 1\n";
+        let rules = KeyTransformRules::from(vec![key_rule!("A↓ : E↓")]);
+
+        assert_eq!(
+            "[rules]\n\"A↓\" = \"E↓\"\n\"BAD↓\" = \"C↓\" # typo\n\"D↓\" = 1\n",
+            merge_toml(text, &rules).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_layout_merge_toml_devices() {
+        let text = "[rules]\n\"A↓ | DEVICE=PAD\" = \"B↓\"\n";
+        let rules = KeyTransformRules::from(vec![
+            key_rule!("A↓ : B↓ | DEVICE=PAD"),
+            key_rule!("A↓ : C↓ | DEVICE=MOUSE"),
+        ]);
+
+        assert_eq!(
+            "[rules]\n\"A↓ | DEVICE=PAD\" = \"B↓\"\n\"A↓ | DEVICE=MOUSE\" = \"C↓\"\n",
+            merge_toml(text, &rules).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_layout_merge_toml_inline_rules() {
+        let text = "name = \"test\" # name\nrules = { \"A↓\" = \"B↓\" }\n";
+        let rules = KeyTransformRules::from(vec![key_rule!("A↓ : C↓")]);
+
+        assert_eq!(
+            "name = \"test\" # name\nrules = { \"A↓\" = \"C↓\" }\n",
+            merge_toml(text, &rules).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_layout_display_round_trip() {
+        let layout = create_test_layout();
+
+        let text = layout.to_string();
+
+        assert_eq!(
+            "[LEFT_SHIFT] CAPS_LOCK↓ : CAPS_LOCK↓ → CAPS_LOCK↑\n\
+             [] CAPS_LOCK↓ : LEFT_WIN↓ → SPACE↓ → SPACE↑ → LEFT_WIN↑",
+            text
+        );
+        assert_eq!(layout.rules, KeyTransformRules::from_str(&text).unwrap());
+    }
+
     #[test]
     fn test_layouts_load() {
         let result = KeyTransformLayoutList::load_from("etc/test_data/layouts/");
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_layouts_load_rule_ids() {
+        let dir = Path::new("etc/test_data/tmp/rule_ids");
+        fs::create_dir_all(dir.join("include")).unwrap();
+        fs::write(
+            dir.join("main.toml"),
+            "name = \"main\"\ntitle = \"Main\"\ninclude = [\"include/shared.json\"]\n\n[rules]\n\"#3 A\" = \"B\"\n\"C↓\" = \"D↓\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("include/shared.json"),
+            r#"{"rules": {"E↓": "F↓"}}"#,
+        )
+        .unwrap();
+
+        let layouts = KeyTransformLayoutList::load_from(dir).unwrap();
+
+        let rules = layouts.find("main").unwrap().effective_rules();
+        assert_eq!(
+            key_rules!(
+                r#"
+                #4 E↓ : F↓
+                #3 A↓ : B↓
+                #3 A↑ : B↑
+                #5 C↓ : D↓
+                "#
+            ),
+            rules
+        );
+        assert!(
+            fs::read_to_string(dir.join("main.toml"))
+                .unwrap()
+                .contains(r##""#5 C↓" = "D↓""##)
+        );
+        assert_eq!(layouts, KeyTransformLayoutList::load_from(dir).unwrap());
+    }
+
     #[test]
     fn test_layouts_find() {
         let layouts = create_test_layouts();
@@ -268,6 +914,36 @@ pub mod tests {
         assert_eq!(None, layouts.find(""));
     }
 
+    #[test]
+    fn test_layouts_add_presets() {
+        let mut layouts = KeyTransformLayoutList(vec![KeyTransformLayout {
+            name: str!("dvorak"),
+            title: str!("Custom Dvorak"),
+            ..Default::default()
+        }]);
+        layouts.add_presets();
+
+        assert_eq!("Custom Dvorak", layouts.find("dvorak").unwrap().title);
+        assert_eq!("Colemak", layouts.find("colemak").unwrap().title);
+        assert!(layouts.find("workman").unwrap().rules.iter().count() > 0);
+        assert!(
+            layouts
+                .find("colemak")
+                .unwrap()
+                .rules
+                .iter()
+                .all(|r| r.id.is_some())
+        );
+        assert_eq!(3, layouts.into_iter().count());
+    }
+
+    #[test]
+    fn test_layouts_find_file() {
+        let path = KeyTransformLayoutList::find_file("etc/test_data/layouts/", "minimal").unwrap();
+        assert!(path.ends_with("minimal.toml"));
+        assert!(KeyTransformLayoutList::find_file("etc/test_data/layouts/", "missing").is_err());
+    }
+
     #[test]
     fn test_layouts_cyclic_next() {
         let layouts = create_test_layouts();
@@ -288,4 +964,91 @@ pub mod tests {
             layouts.cyclic_next("")
         );
     }
+
+    #[test]
+    fn test_layout_load_includes() {
+        let layout = KeyTransformLayout::load("etc/test_data/layouts/included.toml").unwrap();
+
+        assert_eq!(
+            KeyTransformRules::from(vec![
+                key_rule!("CAPS_LOCK↓ : LEFT_CTRL↓"),
+                key_rule!("CAPS_LOCK↑ : LEFT_CTRL↑"),
+                key_rule!("Q↓ : X↓"),
+                key_rule!("Q↑ : X↑"),
+            ]),
+            layout.included_rules
+        );
+
+        let rules = layout.effective_rules();
+        assert_eq!(
+            Some(key_rule!("Q↓ : Y↓")),
+            rules.find_rule(&key_event!("Q↓"))
+        );
+        assert_eq!(
+            Some(key_rule!("CAPS_LOCK↓ : LEFT_CTRL↓")),
+            rules.find_rule(&key_event!("CAPS_LOCK↓"))
+        );
+    }
+
+    #[test]
+    fn test_layout_include_cycle_fails() {
+        let result = load_includes(
+            "etc/test_data/layouts/include/cycle.toml",
+            Some(&[str!("cycle.toml")]),
+            0,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_layouts_resolve_extends() {
+        let mut layouts = KeyTransformLayoutList(vec![
+            KeyTransformLayout {
+                name: str!("child"),
+                extends: Some(str!("parent")),
+                rules: KeyTransformRules::from(vec![key_rule!("A↓ : C↓")]),
+                ..Default::default()
+            },
+            KeyTransformLayout {
+                name: str!("parent"),
+                extends: Some(str!("base")),
+                rules: KeyTransformRules::from(vec![key_rule!("A↓ : B↓")]),
+                ..Default::default()
+            },
+            KeyTransformLayout {
+                name: str!("base"),
+                rules: KeyTransformRules::from(vec![key_rule!("D↓ : E↓")]),
+                ..Default::default()
+            },
+        ]);
+        layouts.resolve_extends().unwrap();
+
+        let rules = layouts.find("child").unwrap().effective_rules();
+        assert_eq!(
+            Some(key_rule!("A↓ : C↓")),
+            rules.find_rule(&key_event!("A↓"))
+        );
+        assert_eq!(
+            Some(key_rule!("D↓ : E↓")),
+            rules.find_rule(&key_event!("D↓"))
+        );
+
+        let rules = layouts.find("parent").unwrap().effective_rules();
+        assert_eq!(
+            Some(key_rule!("A↓ : B↓")),
+            rules.find_rule(&key_event!("A↓"))
+        );
+    }
+
+    #[test]
+    fn test_layouts_resolve_extends_fails() {
+        let mut layouts = create_test_layouts();
+        layouts.0[0].extends = Some(str!("layout_2"));
+        layouts.0[1].extends = Some(str!("layout_1"));
+        assert!(layouts.resolve_extends().is_err());
+
+        let mut layouts = create_test_layouts();
+        layouts.0[0].extends = Some(str!("missing"));
+        assert!(layouts.resolve_extends().is_err());
+    }
 }
@@ -0,0 +1,392 @@
+use crate::layout::{KeyTransformLayout, LayoutFormat};
+use log::{info, warn};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, value};
+use windows::Win32::UI::Shell::{SEE_MASK_NOASYNC, SHELLEXECUTEINFOW, ShellExecuteExW};
+use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+use windows::core::{HSTRING, PCWSTR, w};
+
+/// Content of the new layout files. Name and title are replaced with the name of the layout.
+const LAYOUT_TEMPLATE: &str = r#"name = ""
+title = ""
+
+[rules]
+# "CAPS_LOCK↓" = "LEFT_CTRL↓"
+# "CAPS_LOCK↑" = "LEFT_CTRL↑"
+"#;
+
+/// Layout file of the layouts directory.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LayoutFile {
+    pub(crate) name: String,
+    pub(crate) title: String,
+    /// Name of the layout this one extends.
+    pub(crate) extends: Option<String>,
+    pub(crate) path: PathBuf,
+}
+
+/// Returns the layout files of the directory sorted by the layout names. Files failed to load
+/// are skipped.
+pub(crate) fn list_layout_files<P: AsRef<Path>>(dir: P) -> Result<Vec<LayoutFile>, Box<dyn Error>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() || LayoutFormat::from_path(&path).is_none() {
+            continue;
+        }
+
+        match KeyTransformLayout::load(&path) {
+            Ok(layout) => files.push(LayoutFile {
+                name: layout.name,
+                title: layout.title,
+                extends: layout.extends,
+                path,
+            }),
+            Err(e) => warn!("Skipped layout file `{}`: {}", path.display(), e),
+        }
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+/// Creates TOML layout file of the template.
+pub(crate) fn create_layout_file<P: AsRef<Path>>(
+    dir: P,
+    name: &str,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let path = new_layout_path(&dir, name, LayoutFormat::Toml)?;
+    let mut doc: DocumentMut = LAYOUT_TEMPLATE.parse()?;
+    doc["name"] = value(name);
+    doc["title"] = value(name);
+
+    fs::write(&path, doc.to_string())?;
+    info!("Layout created: `{}`", path.display());
+    Ok(path)
+}
+
+/// Writes the layout having no file, like the built-in presets, into the new TOML file so that
+/// it can be edited. Returns the file of the layout if it already exists.
+pub(crate) fn save_layout_file<P: AsRef<Path>>(
+    dir: P,
+    layout: &KeyTransformLayout,
+) -> Result<PathBuf, Box<dyn Error>> {
+    if let Ok(file) = find_layout_file(&dir, &layout.name) {
+        return Ok(file.path);
+    }
+
+    let path = new_layout_path(&dir, &layout.name, LayoutFormat::Toml)?;
+    layout.save(&path)?;
+    info!("Layout saved: `{}`", path.display());
+    Ok(path)
+}
+
+/// Copies the layout file of the source layout into the file of the new name.
+pub(crate) fn duplicate_layout_file<P: AsRef<Path>>(
+    dir: P,
+    source: &str,
+    name: &str,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let source = find_layout_file(&dir, source)?;
+    let (format, text) = read_renamed(&source.path, name)?;
+    let path = new_layout_path(&dir, name, format)?;
+
+    fs::write(&path, text)?;
+    info!(
+        "Layout `{}` duplicated to `{}`",
+        source.name,
+        path.display()
+    );
+    Ok(path)
+}
+
+/// Renames the layout and its file. Layouts extended by others cannot be renamed.
+pub(crate) fn rename_layout_file<P: AsRef<Path>>(
+    dir: P,
+    source: &str,
+    name: &str,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let source = find_layout_file(&dir, source)?;
+    check_not_extended(&dir, &source.name)?;
+    let (format, text) = read_renamed(&source.path, name)?;
+    let path = new_layout_path(&dir, name, format)?;
+
+    fs::write(&path, text)?;
+    fs::remove_file(&source.path)?;
+    info!("Layout `{}` renamed to `{}`", source.name, path.display());
+    Ok(path)
+}
+
+/// Deletes the layout file. Layouts extended by others cannot be deleted.
+pub(crate) fn delete_layout_file<P: AsRef<Path>>(dir: P, name: &str) -> Result<(), Box<dyn Error>> {
+    let file = find_layout_file(&dir, name)?;
+    check_not_extended(&dir, name)?;
+
+    fs::remove_file(&file.path)?;
+    info!("Layout deleted: `{}`", file.path.display());
+    Ok(())
+}
+
+/// Opens the layout file in the editor associated with its type, or the default application
+/// when there is no editor.
+pub(crate) fn edit_layout_file<P: AsRef<Path>>(path: P) -> windows::core::Result<()> {
+    let file = HSTRING::from(path.as_ref());
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOASYNC,
+        lpVerb: w!("edit"),
+        lpFile: PCWSTR(file.as_ptr()),
+        nShow: SW_SHOWNORMAL.0,
+        ..Default::default()
+    };
+
+    if unsafe { ShellExecuteExW(&mut info) }.is_err() {
+        info.lpVerb = w!("open");
+        unsafe { ShellExecuteExW(&mut info)? };
+    }
+    Ok(())
+}
+
+/// Layout name becomes the file name so it must not contain path characters.
+pub(crate) fn check_layout_name(name: &str) -> Result<(), Box<dyn Error>> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(())
+    } else {
+        Err(format!("Invalid layout name: `{name}`").into())
+    }
+}
+
+fn find_layout_file<P: AsRef<Path>>(dir: P, name: &str) -> Result<LayoutFile, Box<dyn Error>> {
+    list_layout_files(dir)?
+        .into_iter()
+        .find(|f| f.name == name)
+        .ok_or_else(|| format!("Layout file not found: `{name}`").into())
+}
+
+fn check_not_extended<P: AsRef<Path>>(dir: P, name: &str) -> Result<(), Box<dyn Error>> {
+    match list_layout_files(dir)?
+        .into_iter()
+        .find(|f| f.extends.as_deref() == Some(name))
+    {
+        Some(child) => Err(format!("Layout `{name}` is extended by `{}`", child.name).into()),
+        None => Ok(()),
+    }
+}
+
+/// Returns the path of the new layout file checking that neither the file nor the layout of
+/// the name exists.
+pub(crate) fn new_layout_path<P: AsRef<Path>>(
+    dir: P,
+    name: &str,
+    format: LayoutFormat,
+) -> Result<PathBuf, Box<dyn Error>> {
+    check_layout_name(name)?;
+    if list_layout_files(&dir)?.iter().any(|f| f.name == name) {
+        return Err(format!("Layout already exists: `{name}`").into());
+    }
+
+    let path = dir.as_ref().join(format!("{name}.{}", format.extension()));
+    if path.exists() {
+        return Err(format!("File already exists: `{}`", path.display()).into());
+    }
+    Ok(path)
+}
+
+/// Reads the layout file replacing the layout name in it. TOML files keep their comments.
+fn read_renamed(path: &Path, name: &str) -> Result<(LayoutFormat, String), Box<dyn Error>> {
+    let format = LayoutFormat::from_path(path)
+        .ok_or_else(|| format!("Unsupported layout file format: `{}`", path.display()))?;
+    let text = fs::read_to_string(path)?;
+    Ok((format, set_layout_name(format, &text, name)?))
+}
+
+fn set_layout_name(format: LayoutFormat, text: &str, name: &str) -> Result<String, Box<dyn Error>> {
+    let text = match format {
+        LayoutFormat::Toml => {
+            let mut doc: DocumentMut = text.parse()?;
+            doc["name"] = value(name);
+            doc.to_string()
+        }
+        LayoutFormat::Json => {
+            let mut json: serde_json::Value = serde_json::from_str(text)?;
+            json.as_object_mut()
+                .ok_or("Layout must be an object")?
+                .insert("name".into(), name.into());
+            serde_json::to_string_pretty(&json)?
+        }
+        LayoutFormat::Yaml => {
+            let mut yaml: serde_yaml::Value = serde_yaml::from_str(text)?;
+            yaml.as_mapping_mut()
+                .ok_or("Layout must be a mapping")?
+                .insert("name".into(), name.into());
+            serde_yaml::to_string(&yaml)?
+        }
+    };
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::{KeyTransformLayout, LayoutFormat};
+    use crate::layout_files::{
+        check_layout_name, create_layout_file, delete_layout_file, duplicate_layout_file,
+        list_layout_files, rename_layout_file, save_layout_file, set_layout_name,
+    };
+    use keympostor::key_rules;
+    use keympostor::rule::KeyTransformRules;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    fn create_test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("keympostor_{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_check_layout_name() {
+        assert!(check_layout_name("my-layout_2").is_ok());
+        assert!(check_layout_name("").is_err());
+        assert!(check_layout_name("../evil").is_err());
+        assert!(check_layout_name("a b").is_err());
+    }
+
+    #[test]
+    fn test_set_layout_name() {
+        let text = set_layout_name(
+            LayoutFormat::Toml,
+            "# comment\nname = \"old\"\n\n[rules]\n\"A↓\" = \"B↓\"\n",
+            "new",
+        )
+        .unwrap();
+        assert_eq!(
+            "# comment\nname = \"new\"\n\n[rules]\n\"A↓\" = \"B↓\"\n",
+            text
+        );
+
+        let text = set_layout_name(
+            LayoutFormat::Json,
+            r#"{"name": "old", "title": "Old", "rules": {}}"#,
+            "new",
+        )
+        .unwrap();
+        assert_eq!("new", LayoutFormat::Json.parse(&text).unwrap().name);
+
+        let text = set_layout_name(
+            LayoutFormat::Yaml,
+            "name: old\ntitle: Old\nrules: {}\n",
+            "new",
+        )
+        .unwrap();
+        assert_eq!("new", LayoutFormat::Yaml.parse(&text).unwrap().name);
+
+        assert!(set_layout_name(LayoutFormat::Json, "[]", "new").is_err());
+    }
+
+    #[test]
+    fn test_create_layout_file() {
+        let dir = create_test_dir("create_layout_file");
+
+        let path = create_layout_file(&dir, "custom").unwrap();
+        assert_eq!(dir.join("custom.toml"), path);
+
+        let layout = KeyTransformLayout::load(&path).unwrap();
+        assert_eq!("custom", layout.name);
+        assert_eq!("custom", layout.title);
+        assert_eq!(KeyTransformRules::default(), layout.rules);
+
+        assert!(create_layout_file(&dir, "custom").is_err());
+        assert!(create_layout_file(&dir, "bad/name").is_err());
+    }
+
+    #[test]
+    fn test_save_layout_file() {
+        let dir = create_test_dir("save_layout_file");
+        let layout = KeyTransformLayout {
+            name: "preset".into(),
+            title: "Preset".into(),
+            rules: key_rules!("A↓ : B↓"),
+            ..Default::default()
+        };
+
+        let path = save_layout_file(&dir, &layout).unwrap();
+        assert_eq!(dir.join("preset.toml"), path);
+
+        let saved = KeyTransformLayout::load(&path).unwrap();
+        assert_eq!("preset", saved.name);
+        assert_eq!("Preset", saved.title);
+        assert_eq!(layout.rules, saved.rules);
+
+        assert_eq!(path, save_layout_file(&dir, &layout).unwrap());
+    }
+
+    #[test]
+    fn test_duplicate_layout_file() {
+        let dir = create_test_dir("duplicate_layout_file");
+        fs::copy(
+            "etc/test_data/layouts/minimal.toml",
+            dir.join("minimal.toml"),
+        )
+        .unwrap();
+
+        let path = duplicate_layout_file(&dir, "minimal", "copy").unwrap();
+        assert_eq!(dir.join("copy.toml"), path);
+
+        let files = list_layout_files(&dir).unwrap();
+        assert_eq!(
+            vec!["copy", "minimal"],
+            files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!("Minimal layout", files[0].title);
+        assert_eq!(
+            KeyTransformLayout::load(dir.join("minimal.toml"))
+                .unwrap()
+                .rules,
+            KeyTransformLayout::load(path).unwrap().rules
+        );
+
+        assert!(duplicate_layout_file(&dir, "minimal", "copy").is_err());
+        assert!(duplicate_layout_file(&dir, "missing", "other").is_err());
+    }
+
+    #[test]
+    fn test_rename_layout_file() {
+        let dir = create_test_dir("rename_layout_file");
+        fs::copy(
+            "etc/test_data/layouts/minimal.toml",
+            dir.join("minimal.toml"),
+        )
+        .unwrap();
+
+        let path = rename_layout_file(&dir, "minimal", "renamed").unwrap();
+        assert_eq!(dir.join("renamed.toml"), path);
+        assert!(!dir.join("minimal.toml").exists());
+        assert_eq!("renamed", KeyTransformLayout::load(path).unwrap().name);
+    }
+
+    #[test]
+    fn test_delete_layout_file() {
+        let dir = create_test_dir("delete_layout_file");
+        create_layout_file(&dir, "base").unwrap();
+        fs::write(
+            dir.join("child.toml"),
+            "name = \"child\"\ntitle = \"Child\"\nextends = \"base\"\n[rules]\n",
+        )
+        .unwrap();
+
+        assert!(delete_layout_file(&dir, "base").is_err());
+        assert!(rename_layout_file(&dir, "base", "other").is_err());
+
+        delete_layout_file(&dir, "child").unwrap();
+        delete_layout_file(&dir, "base").unwrap();
+        assert!(list_layout_files(&dir).unwrap().is_empty());
+        assert!(delete_layout_file(&dir, "base").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use keympostor::event::KeyEventRecord;
+use log::info;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const CSV_HEADER: &str = "time,key,vk,scan_code,transition,modifiers,locks,taps,is_repeat,\
+    is_injected,is_private,device,rule_id,rule,character";
+
+/// Log file format detected by file extension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LogFormat {
+    Csv,
+    JsonLines,
+}
+
+impl LogFormat {
+    pub(crate) fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "csv" => Some(Self::Csv),
+            "jsonl" => Some(Self::JsonLines),
+            _ => None,
+        }
+    }
+
+    fn format(&self, records: &[KeyEventRecord]) -> Result<String, Box<dyn Error>> {
+        let mut text = String::new();
+        if let Self::Csv = self {
+            text.push_str(CSV_HEADER);
+            text.push('\n');
+        }
+        for record in records {
+            match self {
+                Self::Csv => text.push_str(&csv_row(record)),
+                Self::JsonLines => text.push_str(&serde_json::to_string(record)?),
+            }
+            text.push('\n');
+        }
+        Ok(text)
+    }
+}
+
+/// Writes the records into the file in the format of its extension.
+pub(crate) fn export_log<P: AsRef<Path>>(
+    path: P,
+    records: &[KeyEventRecord],
+) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    let format = LogFormat::from_path(path)
+        .ok_or_else(|| format!("Unsupported log file format: `{}`", path.display()))?;
+    fs::write(path, format.format(records)?)?;
+    info!(
+        "{} log records exported to `{}`",
+        records.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn csv_row(record: &KeyEventRecord) -> String {
+    [
+        record.time.to_string(),
+        record.key.clone(),
+        format!("0x{:02X}", record.vk),
+        format!("0x{:04X}", record.scan_code),
+        record.transition.clone(),
+        record.modifiers.join(" + "),
+        record.locks.join(" + "),
+        record.taps.to_string(),
+        record.is_repeat.to_string(),
+        record.is_injected.to_string(),
+        record.is_private.to_string(),
+        record.device.clone().unwrap_or_default(),
+        record.rule_id.map(|id| id.to_string()).unwrap_or_default(),
+        record.rule.clone().unwrap_or_default(),
+        record.character.clone().unwrap_or_default(),
+    ]
+    .iter()
+    .map(|field| csv_field(field))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Quotes the field when it contains separators.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::log_export::{LogFormat, csv_field};
+    use keympostor::event::{KeyEvent, KeyEventRecord};
+    use keympostor::rule::KeyTransformRule;
+    use keympostor::trigger::KeyTrigger;
+    use keympostor::{key_event, key_rule};
+    use std::str::FromStr;
+
+    fn create_test_records() -> Vec<KeyEventRecord> {
+        vec![
+            KeyEventRecord::new(&key_event!("[LEFT_SHIFT] A↓"), None),
+            KeyEventRecord::new(
+                &key_event!("[] B↑"),
+                Some(&key_rule!("#2 B↑ : C↑ | NO_REPEAT, PASS")),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_log_format_from_path() {
+        assert_eq!(Some(LogFormat::Csv), LogFormat::from_path("log.CSV"));
+        assert_eq!(
+            Some(LogFormat::JsonLines),
+            LogFormat::from_path("log.jsonl")
+        );
+        assert_eq!(None, LogFormat::from_path("log.txt"));
+    }
+
+    #[test]
+    fn test_log_format_csv() {
+        let text = LogFormat::Csv.format(&create_test_records()).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+
+        assert_eq!(3, lines.len());
+        assert!(lines[0].starts_with("time,key,vk,scan_code,transition,"));
+        assert_eq!(
+            "0,A,0x41,0x001E,DOWN,LEFT_SHIFT,,0,false,false,false,,,,",
+            lines[1]
+        );
+        assert_eq!(
+            "0,B,0x42,0x0030,UP,,,0,false,false,false,,2,\"#2 B↑ : C↑ | NO_REPEAT, PASS\",",
+            lines[2]
+        );
+    }
+
+    #[test]
+    fn test_log_format_json_lines() {
+        let records = create_test_records();
+        let text = LogFormat::JsonLines.format(&records).unwrap();
+
+        let actual: Vec<KeyEventRecord> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records, actual);
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!("A", csv_field("A"));
+        assert_eq!("\"A, B\"", csv_field("A, B"));
+        assert_eq!("\"say \"\"hi\"\"\"", csv_field("say \"hi\""));
+    }
+}
@@ -4,6 +4,7 @@ use crate::ui::app_ui::AppUI;
 use chrono::Local;
 use fern::colors::{Color, ColoredLevelConfig};
 use fern::Dispatch;
+use keympostor::crash::install_crash_handler;
 use log::LevelFilter::{Trace, Warn};
 use std::error::Error;
 use std::fs::File;
@@ -11,17 +12,36 @@ use std::io::stdout;
 use std::thread;
 
 mod app;
+mod audio;
+mod blacklist;
+mod compose;
+mod focus_watch;
+mod health_watch;
+mod import;
 mod indicator;
+mod ipc;
+mod jump_list;
 mod kb_watch;
 mod layout;
+mod layout_files;
+mod log_export;
+#[cfg(feature = "openrgb")]
+mod openrgb;
+mod pipe;
 mod profile;
+mod scancode;
 mod settings;
+mod stats;
+mod startup;
+mod sys_watch;
+mod trial;
 mod ui;
 mod util;
 mod win_watch;
 
 fn main() {
     log_panics::init();
+    install_crash_handler("keympostor-crash.log");
     setup_logger().expect("Failed to initialize logger.");
 
     let app = App::default();
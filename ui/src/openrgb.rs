@@ -0,0 +1,125 @@
+use crate::profile::{OpenRgbSettings, OpenRgbZone};
+use log::{debug, warn};
+use std::error::Error;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:6742";
+const TIMEOUT: Duration = Duration::from_secs(1);
+const CLIENT_NAME: &[u8] = b"Keympostor\0";
+
+/* OpenRGB SDK protocol packet identifiers */
+const PACKET_MAGIC: &[u8; 4] = b"ORGB";
+const SET_CLIENT_NAME: u32 = 50;
+const UPDATE_ZONE_LEDS: u32 = 1051;
+const SET_CUSTOM_MODE: u32 = 1100;
+
+/// Sets the backlight from another thread so that server failures never delay key processing.
+pub(crate) fn set_lighting(settings: &OpenRgbSettings) {
+    let settings = settings.clone();
+    thread::spawn(move || {
+        send_lighting(&settings).unwrap_or_else(|e| warn!("Failed to set OpenRGB lighting: {}", e));
+    });
+}
+
+fn send_lighting(settings: &OpenRgbSettings) -> Result<(), Box<dyn Error>> {
+    let address = settings.address.as_deref().unwrap_or(DEFAULT_ADDRESS);
+    let socket_address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("Invalid OpenRGB server address: `{address}`"))?;
+
+    let mut stream = TcpStream::connect_timeout(&socket_address, TIMEOUT)?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(&packet(0, SET_CLIENT_NAME, CLIENT_NAME))?;
+
+    let mut devices = vec![];
+    for zone in &settings.zones {
+        if !devices.contains(&zone.device) {
+            stream.write_all(&packet(zone.device, SET_CUSTOM_MODE, &[]))?;
+            devices.push(zone.device);
+        }
+        stream.write_all(&packet(
+            zone.device,
+            UPDATE_ZONE_LEDS,
+            &zone_leds_data(zone)?,
+        ))?;
+    }
+
+    debug!("OpenRGB lighting set for {} zones", settings.zones.len());
+    Ok(())
+}
+
+fn packet(device: u32, id: u32, data: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(16 + data.len());
+    packet.extend_from_slice(PACKET_MAGIC);
+    packet.extend_from_slice(&device.to_le_bytes());
+    packet.extend_from_slice(&id.to_le_bytes());
+    packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    packet.extend_from_slice(data);
+    packet
+}
+
+/// Zone colors prefixed with the size of the whole data block.
+fn zone_leds_data(zone: &OpenRgbZone) -> Result<Vec<u8>, Box<dyn Error>> {
+    let size = 4 + 4 + 2 + 4 * zone.colors.len();
+    let mut data = Vec::with_capacity(size);
+    data.extend_from_slice(&(size as u32).to_le_bytes());
+    data.extend_from_slice(&zone.zone.to_le_bytes());
+    data.extend_from_slice(&(zone.colors.len() as u16).to_le_bytes());
+    for color in &zone.colors {
+        let [r, g, b] = parse_color(color)?;
+        data.extend_from_slice(&[r, g, b, 0]);
+    }
+    Ok(data)
+}
+
+fn parse_color(s: &str) -> Result<[u8; 3], Box<dyn Error>> {
+    let hex = s
+        .strip_prefix('#')
+        .filter(|h| h.len() == 6)
+        .ok_or_else(|| format!("Invalid color: `{s}`"))?;
+    let [_, r, g, b] = u32::from_str_radix(hex, 16)?.to_be_bytes();
+    Ok([r, g, b])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::openrgb::{packet, parse_color, zone_leds_data};
+    use crate::profile::OpenRgbZone;
+    use crate::str;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!([0x12, 0x34, 0xAB], parse_color("#1234ab").unwrap());
+        assert!(parse_color("1234AB").is_err());
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("#GGGGGG").is_err());
+    }
+
+    #[test]
+    fn test_packet() {
+        assert_eq!(
+            vec![
+                b'O', b'R', b'G', b'B', 2, 0, 0, 0, 0x4C, 0x04, 0, 0, 0, 0, 0, 0
+            ],
+            packet(2, 1100, &[])
+        );
+    }
+
+    #[test]
+    fn test_zone_leds_data() {
+        let zone = OpenRgbZone {
+            device: 0,
+            zone: 3,
+            colors: vec![str!("#FF0000"), str!("#0000FF")],
+        };
+
+        assert_eq!(
+            vec![18, 0, 0, 0, 3, 0, 0, 0, 2, 0, 0xFF, 0, 0, 0, 0, 0, 0xFF, 0],
+            zone_leds_data(&zone).unwrap()
+        );
+    }
+}
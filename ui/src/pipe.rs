@@ -0,0 +1,226 @@
+use std::ffi::c_void;
+use std::io::{self, Read, Write};
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::ptr::null_mut;
+use std::sync::Arc;
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_BROKEN_PIPE, ERROR_IO_PENDING, ERROR_PIPE_CONNECTED, HANDLE, HLOCAL,
+    INVALID_HANDLE_VALUE, LocalFree,
+};
+use windows::Win32::Security::Authorization::{
+    ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+};
+use windows::Win32::Security::{
+    GetTokenInformation, PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES, TOKEN_QUERY, TOKEN_USER,
+    TokenUser,
+};
+use windows::Win32::Storage::FileSystem::{
+    FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAG_OVERLAPPED, PIPE_ACCESS_DUPLEX, ReadFile, WriteFile,
+};
+use windows::Win32::System::IO::{GetOverlappedResult, OVERLAPPED};
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+    PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows::Win32::System::Threading::{CreateEventW, GetCurrentProcess, OpenProcessToken};
+use windows::core::{HSTRING, PWSTR};
+
+const BUFFER_SIZE: u32 = 4096;
+
+/// Server end of the named pipe. Only the user running the application can connect to it,
+/// remote clients are rejected.
+pub(crate) struct PipeListener {
+    name: HSTRING,
+    security: SecurityDescriptor,
+    /// Instance waiting for the next client.
+    instance: OwnedHandle,
+}
+
+impl PipeListener {
+    /// Fails when the pipe of the name is already created by another process.
+    pub(crate) fn bind(name: &str) -> io::Result<Self> {
+        let name = HSTRING::from(name);
+        let security = SecurityDescriptor::current_user()?;
+        let instance = create_instance(&name, &security, true)?;
+        Ok(Self {
+            name,
+            security,
+            instance,
+        })
+    }
+
+    /// Waits for the next client.
+    pub(crate) fn accept(&mut self) -> io::Result<PipeStream> {
+        let next = create_instance(&self.name, &self.security, false)?;
+        let stream = PipeStream::new(Arc::new(std::mem::replace(&mut self.instance, next)))?;
+        match stream
+            .complete(|pipe, overlapped| unsafe { ConnectNamedPipe(pipe, Some(overlapped)) })
+        {
+            /* client connected before the server started waiting */
+            Err(e) if e.code() == ERROR_PIPE_CONNECTED.to_hresult() => Ok(stream),
+            Err(e) => Err(e.into()),
+            Ok(_) => Ok(stream),
+        }
+    }
+}
+
+fn create_instance(
+    name: &HSTRING,
+    security: &SecurityDescriptor,
+    first: bool,
+) -> io::Result<OwnedHandle> {
+    let mut open_mode = PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED;
+    if first {
+        open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    let attributes = SECURITY_ATTRIBUTES {
+        nLength: size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: security.0.0,
+        bInheritHandle: false.into(),
+    };
+
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name,
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            Some(&attributes),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedHandle::from_raw_handle(handle.0) })
+}
+
+/// Connected client. Clones of the stream may read and write concurrently.
+pub(crate) struct PipeStream {
+    pipe: Arc<OwnedHandle>,
+    /// Signaled when an operation of this clone completes.
+    event: OwnedHandle,
+}
+
+impl PipeStream {
+    fn new(pipe: Arc<OwnedHandle>) -> io::Result<Self> {
+        let event = unsafe { CreateEventW(None, true, false, None)? };
+        Ok(Self {
+            pipe,
+            event: unsafe { OwnedHandle::from_raw_handle(event.0) },
+        })
+    }
+
+    pub(crate) fn try_clone(&self) -> io::Result<Self> {
+        Self::new(self.pipe.clone())
+    }
+
+    /// Starts the overlapped operation and waits until it completes. Returns transferred bytes.
+    fn complete<F>(&self, operation: F) -> windows::core::Result<usize>
+    where
+        F: FnOnce(HANDLE, *mut OVERLAPPED) -> windows::core::Result<()>,
+    {
+        let pipe = HANDLE(self.pipe.as_raw_handle());
+        let mut overlapped = OVERLAPPED {
+            hEvent: HANDLE(self.event.as_raw_handle()),
+            ..Default::default()
+        };
+        let mut transferred = 0;
+
+        match operation(pipe, &mut overlapped) {
+            Err(e) if e.code() != ERROR_IO_PENDING.to_hresult() => return Err(e),
+            _ => {}
+        }
+        unsafe { GetOverlappedResult(pipe, &overlapped, &mut transferred, true)? };
+        Ok(transferred as usize)
+    }
+}
+
+impl Read for PipeStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.complete(|pipe, overlapped| unsafe {
+            ReadFile(pipe, Some(buf), None, Some(overlapped))
+        }) {
+            /* client disconnected */
+            Err(e) if e.code() == ERROR_BROKEN_PIPE.to_hresult() => Ok(0),
+            result => Ok(result?),
+        }
+    }
+}
+
+impl Write for PipeStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.complete(|pipe, overlapped| unsafe {
+            WriteFile(pipe, Some(buf), None, Some(overlapped))
+        })?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Security descriptor granting access to the current user only.
+struct SecurityDescriptor(PSECURITY_DESCRIPTOR);
+
+/* the descriptor is only read after it is created */
+unsafe impl Send for SecurityDescriptor {}
+
+impl SecurityDescriptor {
+    fn current_user() -> windows::core::Result<Self> {
+        let sddl = HSTRING::from(format!("D:P(A;;GA;;;{})", current_user_sid()?));
+        let mut descriptor = PSECURITY_DESCRIPTOR(null_mut());
+        unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                &sddl,
+                SDDL_REVISION_1,
+                &mut descriptor,
+                None,
+            )?;
+        }
+        Ok(Self(descriptor))
+    }
+}
+
+impl Drop for SecurityDescriptor {
+    fn drop(&mut self) {
+        unsafe { LocalFree(Some(HLOCAL(self.0.0))) };
+    }
+}
+
+/// Returns the string SID of the user running the application.
+fn current_user_sid() -> windows::core::Result<String> {
+    let mut token = HANDLE::default();
+    unsafe {
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token)?;
+        let sid = query_user_sid(token);
+        let _ = CloseHandle(token);
+        sid
+    }
+}
+
+unsafe fn query_user_sid(token: HANDLE) -> windows::core::Result<String> {
+    unsafe {
+        let mut size = 0;
+        let _ = GetTokenInformation(token, TokenUser, None, 0, &mut size);
+
+        /* u64 buffer keeps the SID aligned */
+        let mut buffer = vec![0u64; (size as usize).div_ceil(size_of::<u64>())];
+        GetTokenInformation(
+            token,
+            TokenUser,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            size,
+            &mut size,
+        )?;
+
+        let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+        let mut sid = PWSTR::null();
+        ConvertSidToStringSidW(user.User.Sid, &mut sid)?;
+        let result = String::from_utf16_lossy(sid.as_wide());
+        LocalFree(Some(HLOCAL(sid.0 as *mut c_void)));
+        Ok(result)
+    }
+}
@@ -1,12 +1,156 @@
+use crate::settings::DebounceSettings;
+use keympostor::hook::{InjectionMode, InputChunking};
+use keympostor::window::WindowInfo;
+use log::{debug, warn};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::process::Command;
 use std::str::FromStr;
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[derive(Clone)]
 pub(crate) struct LayoutAutoswitchProfile {
+    /// Regex of the title or the process path of the window activating the profile.
     pub(crate) activation_rule: Option<String>,
+    /// Conditions on the window activating the profile. Either of them and the activation
+    /// rule activates the profile.
+    #[serde(rename = "match")]
+    pub(crate) window_match: Option<WindowMatch>,
     pub(crate) transform_layout: String,
+    /// Keyboard backlight set when the profile is activated.
+    pub(crate) openrgb: Option<OpenRgbSettings>,
+    /// Settings replacing the global ones while the profile is active.
+    pub(crate) overrides: Option<SettingsOverrides>,
+    /// Command executed when a window activates the profile.
+    pub(crate) exec: Option<ProfileCommand>,
+}
+
+/// Conditions on the foreground window, e.g.
+/// `match = { class = "CASCADIA_HOSTING_WINDOW_CLASS", exe = "WindowsTerminal.exe" }`.
+/// All the conditions of the table must match. Alternatives are listed in `any`, like
+/// `match = { any = [{ exe = "chrome.exe" }, { exe = "firefox.exe" }] }`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct WindowMatch {
+    /// Regex of the window title.
+    pub(crate) title: Option<String>,
+    /// Window class name, ignoring case.
+    pub(crate) class: Option<String>,
+    /// File name or full path of the process executable, ignoring case.
+    pub(crate) exe: Option<String>,
+    /// Conditions at least one of which must match.
+    pub(crate) any: Option<Vec<WindowMatch>>,
+    /// Conditions all of which must match.
+    pub(crate) all: Option<Vec<WindowMatch>>,
+}
+
+impl WindowMatch {
+    /// Checks the window against the conditions. Table without conditions matches nothing.
+    pub(crate) fn matches(&self, window: &WindowInfo) -> bool {
+        !self.is_empty()
+            && self.title.as_deref().is_none_or(|title| {
+                Regex::from_str(title).is_ok_and(|regex| regex.is_match(&window.title))
+            })
+            && self
+                .class
+                .as_deref()
+                .is_none_or(|class| class.eq_ignore_ascii_case(&window.class_name))
+            && self
+                .exe
+                .as_deref()
+                .is_none_or(|exe| exe_matches(exe, &window.process_path))
+            && self
+                .any
+                .as_ref()
+                .is_none_or(|any| any.iter().any(|m| m.matches(window)))
+            && self
+                .all
+                .as_ref()
+                .is_none_or(|all| all.iter().all(|m| m.matches(window)))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.class.is_none()
+            && self.exe.is_none()
+            && self.any.is_none()
+            && self.all.is_none()
+    }
+}
+
+/// Compares the full path when the pattern has separators, otherwise the file name only.
+fn exe_matches(exe: &str, process_path: &str) -> bool {
+    let is_path = exe.contains(['\\', '/']);
+    let actual = if is_path {
+        process_path
+    } else {
+        process_path
+            .rsplit(['\\', '/'])
+            .next()
+            .unwrap_or(process_path)
+    };
+    actual.eq_ignore_ascii_case(&exe.replace('/', "\\"))
+}
+
+/// Per-profile layer over the global settings. Missing values fall back to the global ones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SettingsOverrides {
+    /// Key codes the rules output is sent with, e.g. `scancode` for the games ignoring
+    /// virtual keys.
+    pub(crate) injection_mode: Option<InjectionMode>,
+    /// Layout sounds are not played when `false`.
+    pub(crate) sound_enabled: Option<bool>,
+    pub(crate) input_chunking: Option<InputChunking>,
+    pub(crate) release_modifiers: Option<bool>,
+    pub(crate) normalize_numpad: Option<bool>,
+    pub(crate) debounce: Option<DebounceSettings>,
+}
+
+/// External command run on the profile activation, e.g.
+/// `exec = { program = "notify.cmd", args = ["$EXE", "$TITLE"] }`. `$TITLE`, `$CLASS` and `$EXE`
+/// in the program and the arguments are replaced with the properties of the activating window.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ProfileCommand {
+    pub(crate) program: String,
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+}
+
+impl ProfileCommand {
+    /// Returns the program and the arguments with the window variables substituted.
+    fn expand(&self, window: &WindowInfo) -> (String, Vec<String>) {
+        (
+            window.substitute(&self.program),
+            self.args.iter().map(|arg| window.substitute(arg)).collect(),
+        )
+    }
+
+    pub(crate) fn spawn(&self, window: &WindowInfo) {
+        let (program, args) = self.expand(window);
+        match Command::new(&program).args(&args).spawn() {
+            Ok(_) => debug!("Profile command started: `{}` {:?}", program, args),
+            Err(e) => warn!("Failed to start profile command `{}`: {}", program, e),
+        }
+    }
+}
+
+/// Colors of the backlight zones set through the OpenRGB SDK server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct OpenRgbSettings {
+    /// Server address, `127.0.0.1:6742` by default.
+    pub(crate) address: Option<String>,
+    pub(crate) zones: Vec<OpenRgbZone>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct OpenRgbZone {
+    /// Index of the device in the OpenRGB devices list.
+    pub(crate) device: u32,
+    /// Index of the zone in the device.
+    pub(crate) zone: u32,
+    /// `#RRGGBB` colors of the zone LEDs in order.
+    pub(crate) colors: Vec<String>,
 }
 
 impl LayoutAutoswitchProfile {
@@ -15,6 +159,18 @@ impl LayoutAutoswitchProfile {
             .as_deref()
             .and_then(|r| Regex::from_str(r).ok())
     }
+
+    /// Checks whether the window activates the profile.
+    pub(crate) fn matches(&self, window: &WindowInfo) -> bool {
+        let is_rule_matched = self
+            .rule_regex()
+            .is_some_and(|r| r.is_match(&window.title) || r.is_match(&window.process_path));
+        is_rule_matched
+            || self
+                .window_match
+                .as_ref()
+                .is_some_and(|m| m.matches(window))
+    }
 }
 
 // #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
@@ -48,9 +204,120 @@ pub mod tests {
         let profile = LayoutAutoswitchProfile {
             //name: str!("name"),
             activation_rule: Some(str!("")),
+            window_match: None,
             transform_layout: Default::default(),
+            openrgb: None,
+            overrides: None,
+            exec: None,
         };
 
         assert!(profile.rule_regex().unwrap().is_match("test"));
     }
+
+    fn terminal_window() -> WindowInfo {
+        WindowInfo {
+            title: str!("Windows PowerShell"),
+            class_name: str!("CASCADIA_HOSTING_WINDOW_CLASS"),
+            process_path: str!(r"C:\Program Files\WindowsApps\WindowsTerminal.exe"),
+        }
+    }
+
+    #[test]
+    fn test_window_match() {
+        let window = terminal_window();
+        let parse = |text: &str| toml::from_str::<WindowMatch>(text).unwrap();
+
+        assert!(parse(r#"class = "cascadia_hosting_window_class""#).matches(&window));
+        assert!(
+            parse(
+                r#"class = "CASCADIA_HOSTING_WINDOW_CLASS"
+                exe = "WindowsTerminal.exe""#
+            )
+            .matches(&window)
+        );
+        assert!(
+            !parse(
+                r#"class = "CASCADIA_HOSTING_WINDOW_CLASS"
+                exe = "cmd.exe""#
+            )
+            .matches(&window)
+        );
+        assert!(
+            parse(r#"exe = "c:/program files/windowsapps/windowsterminal.exe""#).matches(&window)
+        );
+        assert!(!parse(r#"exe = "Terminal.exe""#).matches(&window));
+        assert!(parse(r#"title = "^Windows Power""#).matches(&window));
+        assert!(!parse("").matches(&window));
+    }
+
+    #[test]
+    fn test_window_match_combinators() {
+        let window = terminal_window();
+        let parse = |text: &str| toml::from_str::<WindowMatch>(text).unwrap();
+
+        assert!(
+            parse(r#"any = [{ exe = "cmd.exe" }, { exe = "WindowsTerminal.exe" }]"#)
+                .matches(&window)
+        );
+        assert!(
+            !parse(r#"any = [{ exe = "cmd.exe" }, { class = "ConsoleWindowClass" }]"#)
+                .matches(&window)
+        );
+        assert!(
+            !parse(r#"all = [{ title = "PowerShell" }, { exe = "cmd.exe" }]"#).matches(&window)
+        );
+        assert!(
+            parse(
+                r#"title = "PowerShell"
+                any = [{ exe = "cmd.exe" }, { all = [{ class = "CASCADIA_HOSTING_WINDOW_CLASS" }] }]"#
+            )
+            .matches(&window)
+        );
+    }
+
+    #[test]
+    fn test_profile_matches() {
+        let window = terminal_window();
+        let profile: LayoutAutoswitchProfile = toml::from_str(
+            r#"
+            transform_layout = "default"
+            match = { class = "CASCADIA_HOSTING_WINDOW_CLASS", exe = "WindowsTerminal.exe" }
+            "#,
+        )
+        .unwrap();
+
+        assert!(profile.matches(&window));
+        assert!(!profile.matches(&WindowInfo::default()));
+        assert!(
+            LayoutAutoswitchProfile {
+                activation_rule: Some(str!("PowerShell")),
+                window_match: None,
+                ..profile
+            }
+            .matches(&window)
+        );
+    }
+
+    #[test]
+    fn test_profile_command_expand() {
+        let command: ProfileCommand = toml::from_str(
+            r#"
+            program = "notify.cmd"
+            args = ["--exe", "$EXE", "$TITLE ($CLASS)"]
+            "#,
+        )
+        .unwrap();
+
+        let (program, args) = command.expand(&terminal_window());
+
+        assert_eq!("notify.cmd", program);
+        assert_eq!(
+            vec![
+                str!("--exe"),
+                str!(r"C:\Program Files\WindowsApps\WindowsTerminal.exe"),
+                str!("Windows PowerShell (CASCADIA_HOSTING_WINDOW_CLASS)"),
+            ],
+            args
+        );
+    }
 }
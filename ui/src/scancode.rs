@@ -0,0 +1,71 @@
+use crate::layout::{KeyTransformLayout, LAYOUTS_PATH};
+use keympostor::scancode_map::{SCANCODE_MAP_KEY, SCANCODE_MAP_VALUE, ScancodeMap};
+use log::info;
+use std::error::Error;
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use windows::Win32::Foundation::ERROR_FILE_NOT_FOUND;
+use windows::Win32::System::Registry::{HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY, RegGetValueW};
+use windows::core::HSTRING;
+
+const IMPORTED_LAYOUT_NAME: &str = "scancode-map";
+const IMPORTED_LAYOUT_TITLE: &str = "Scancode Map";
+
+/// Reads the scancode map set in the registry. Returns `None` when it is not set.
+pub(crate) fn read_scancode_map() -> Result<Option<ScancodeMap>, Box<dyn Error>> {
+    let key = HSTRING::from(SCANCODE_MAP_KEY);
+    let value = HSTRING::from(SCANCODE_MAP_VALUE);
+
+    let mut size = 0;
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            &key,
+            &value,
+            RRF_RT_REG_BINARY,
+            None,
+            None,
+            Some(&mut size),
+        )
+    };
+    if result == ERROR_FILE_NOT_FOUND {
+        return Ok(None);
+    }
+    result.ok()?;
+
+    let mut data = vec![0u8; size as usize];
+    unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            &key,
+            &value,
+            RRF_RT_REG_BINARY,
+            None,
+            Some(data.as_mut_ptr() as *mut c_void),
+            Some(&mut size),
+        )
+    }
+    .ok()?;
+    data.truncate(size as usize);
+
+    Ok(Some(ScancodeMap::from_bytes(&data)?))
+}
+
+/// Saves the rules of the scancode map as a new layout. Returns the path of the layout file.
+pub(crate) fn save_scancode_map_layout(map: &ScancodeMap) -> Result<PathBuf, Box<dyn Error>> {
+    let path = Path::new(LAYOUTS_PATH).join(format!("{IMPORTED_LAYOUT_NAME}.toml"));
+    if path.exists() {
+        return Err(format!("Layout file already exists: `{}`", path.display()).into());
+    }
+
+    KeyTransformLayout {
+        name: IMPORTED_LAYOUT_NAME.into(),
+        title: IMPORTED_LAYOUT_TITLE.into(),
+        rules: map.to_rules(),
+        ..Default::default()
+    }
+    .save(&path)?;
+
+    info!("Scancode map saved to `{}`", path.display());
+    Ok(path)
+}
@@ -1,40 +1,137 @@
+use crate::audio::SoundSettings;
+use crate::blacklist::AppBlacklist;
 use crate::profile::LayoutAutoswitchProfile;
+use crate::sys_watch::SystemEvent;
+use keympostor::action::KeyActionSequence;
+use keympostor::client::DEFAULT_PIPE;
+use keympostor::debounce::Debounce;
+use keympostor::failsafe::FailsafeCommand;
+use keympostor::hook::InputChunking;
+use keympostor::key::Key;
+use keympostor::key_name::KeyNameTheme;
 use keympostor::key_trigger;
+use keympostor::rule::KeyTransformRules;
 use keympostor::trigger::KeyTrigger;
-use log::debug;
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::error::Error;
 use std::fs;
 use std::path::Path;
 use std::str::FromStr;
+use toml::{Table, Value};
 
 const SETTINGS_FILE: &str = "settings.toml";
+const SETTINGS_BACKUP_FILE: &str = "settings.toml.bak";
+
+/// Version of the settings schema written by this build. Files without the version are
+/// version 1.
+const SETTINGS_VERSION: i64 = 1;
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct AppSettings {
+    pub(crate) version: i64,
+    #[serde(default)]
     pub(crate) keys_logging_enabled: bool,
     pub(crate) last_transform_layout: Option<String>,
     pub(crate) toggle_layout_hot_key: Option<KeyTrigger>,
+    pub(crate) toggle_processing_hot_key: Option<KeyTrigger>,
+    pub(crate) code_point_hot_key: Option<KeyTrigger>,
+    pub(crate) compose_hot_key: Option<KeyTrigger>,
+    /// Path of the compose table file, `compose.toml` by default.
+    pub(crate) compose_table: Option<String>,
+    pub(crate) tap_interval: Option<u32>,
+    pub(crate) debounce: Option<DebounceSettings>,
+    /// Splitting of the long sequences for the applications that lose fast input.
+    pub(crate) input_chunking: Option<InputChunking>,
+    /// How many times the rules may be applied to the input sent by the rules.
+    pub(crate) max_injection_depth: Option<u8>,
+    /// Release the modifier keys held by the user while the rule output is sent.
+    pub(crate) release_modifiers: Option<bool>,
+    /// Apply the rules of the numpad digits regardless of the NUM_LOCK state.
+    pub(crate) normalize_numpad: Option<bool>,
+    pub(crate) panic_hot_key: Option<KeyTrigger>,
+    pub(crate) close_action: Option<CloseAction>,
+    /// Naming convention of the keys written to the files and shown in the UI.
+    pub(crate) key_names: Option<KeyNameTheme>,
+    /// Color theme of the UI. Follows the Windows app theme by default.
+    pub(crate) theme: Option<ThemeMode>,
+    /// Collect keyboard usage statistics into the stats file.
+    pub(crate) collect_stats: Option<bool>,
+    /// Command executed when the engine fails unrecoverably.
+    pub(crate) failsafe: Option<FailsafeCommand>,
+    pub(crate) macro_record_hot_key: Option<KeyTrigger>,
+    pub(crate) macro_play_hot_key: Option<KeyTrigger>,
+    pub(crate) macros: Option<KeyTransformRules>,
+    /// IDs of the rules disabled without editing the layouts, by the layout names.
+    pub(crate) disabled_rules: Option<HashMap<String, BTreeSet<u32>>>,
+    pub(crate) overlay: Option<OverlaySettings>,
+    pub(crate) sounds: Option<SoundSettings>,
     pub(crate) layout_autoswitch: Option<LayoutAutoSwitchSettings>,
+    /// Windows in which the transformation is bypassed.
+    pub(crate) blacklist: Option<AppBlacklist>,
+    pub(crate) ipc: Option<IpcSettings>,
+    pub(crate) focus: Option<FocusSettings>,
+    pub(crate) system_events: Option<SystemEventsSettings>,
+    #[serde(default)]
     pub(crate) main_window: MainWindowSettings,
 }
 
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
+            version: SETTINGS_VERSION,
             keys_logging_enabled: false,
             toggle_layout_hot_key: Some(key_trigger!("[]FN_LAUNCH_APP2^")),
+            toggle_processing_hot_key: None,
+            code_point_hot_key: None,
+            compose_hot_key: None,
+            compose_table: None,
+            tap_interval: None,
+            debounce: None,
+            input_chunking: None,
+            max_injection_depth: None,
+            release_modifiers: None,
+            normalize_numpad: None,
+            panic_hot_key: Some(key_trigger!("[LEFT_CTRL + LEFT_ALT] ESC↓")),
+            close_action: None,
+            key_names: None,
+            theme: None,
+            collect_stats: None,
+            failsafe: None,
+            macro_record_hot_key: None,
+            macro_play_hot_key: None,
+            macros: None,
+            disabled_rules: None,
+            overlay: None,
+            sounds: None,
             last_transform_layout: Default::default(),
             layout_autoswitch: Default::default(),
+            blacklist: None,
+            ipc: None,
+            focus: None,
+            system_events: None,
             main_window: Default::default(),
         }
     }
 }
 
 impl AppSettings {
+    /// Loads the settings. The file that cannot be loaded is kept as a backup so that it is
+    /// not lost when the default settings are saved over it.
     pub(crate) fn load() -> Result<Self, Box<dyn Error>> {
-        Self::load_from(SETTINGS_FILE)
+        Self::load_from(SETTINGS_FILE).inspect_err(|_| {
+            if Path::new(SETTINGS_FILE).exists() {
+                fs::copy(SETTINGS_FILE, SETTINGS_BACKUP_FILE)
+                    .map(|_| warn!("Unreadable settings copied to `{SETTINGS_BACKUP_FILE}`"))
+                    .unwrap_or_else(|e| warn!("Failed to back up settings: {e}"));
+            }
+        })
+    }
+
+    /// Returns `false` when the app runs for the first time and has no settings file yet.
+    pub(crate) fn exists() -> bool {
+        Path::new(SETTINGS_FILE).exists()
     }
 
     pub(crate) fn save(&self) {
@@ -44,7 +141,9 @@ impl AppSettings {
 
     fn load_from<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
         let text = fs::read_to_string(path)?;
-        let this = toml::from_str(&text)?;
+        let mut table = toml::from_str(&text)?;
+        migrate(&mut table)?;
+        let this = Value::Table(table).try_into()?;
         Ok(this)
     }
 
@@ -55,10 +154,198 @@ impl AppSettings {
     }
 }
 
+/// Upgrades the settings written by the previous versions step by step. Refuses the settings
+/// written by the newer versions.
+fn migrate(table: &mut Table) -> Result<(), Box<dyn Error>> {
+    let version = table
+        .get("version")
+        .and_then(Value::as_integer)
+        .unwrap_or(1);
+    if version > SETTINGS_VERSION {
+        return Err(format!("Settings version {version} is newer than supported").into());
+    }
+
+    table.insert("version".into(), SETTINGS_VERSION.into());
+    Ok(())
+}
+
+/// What happens when the main window is closed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CloseAction {
+    #[default]
+    Tray,
+    Prompt,
+    Exit,
+}
+
+/// Color theme of the UI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ThemeMode {
+    /// Light or dark as the Windows app theme.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// On-screen display of the layout name shown when the layout changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct OverlaySettings {
+    pub(crate) position: OverlayPosition,
+    /// Display time in milliseconds.
+    pub(crate) duration: u32,
+    pub(crate) font_family: String,
+    pub(crate) font_size: u32,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            position: Default::default(),
+            duration: 1500,
+            font_family: "Segoe UI".into(),
+            font_size: 32,
+        }
+    }
+}
+
+/// Place of the overlay on the screen work area.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OverlayPosition {
+    TopLeft,
+    Top,
+    TopRight,
+    Center,
+    BottomLeft,
+    #[default]
+    Bottom,
+    BottomRight,
+}
+
+/// Dead zone for the chattering key switches, e.g.
+/// `debounce = { interval = 30, keys = { SPACE = 50 } }`. The profile overrides replace the
+/// interval and add the keys.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct DebounceSettings {
+    /// Milliseconds after a key release in which a press of the same key is ignored.
+    pub(crate) interval: Option<u32>,
+    /// Intervals of the particular keys by the key names.
+    pub(crate) keys: BTreeMap<String, u32>,
+}
+
+impl DebounceSettings {
+    pub(crate) fn with_overrides(&self, overrides: Option<&Self>) -> Self {
+        let Some(overrides) = overrides else {
+            return self.clone();
+        };
+        let mut keys = self.keys.clone();
+        keys.extend(overrides.keys.clone());
+        Self {
+            interval: overrides.interval.or(self.interval),
+            keys,
+        }
+    }
+
+    pub(crate) fn to_debounce(&self) -> Debounce {
+        Debounce {
+            interval: self.interval.unwrap_or_default(),
+            keys: self
+                .keys
+                .iter()
+                .filter_map(|(name, &interval)| match Key::from_str(name) {
+                    Some(key) => Some((key, interval)),
+                    None => {
+                        warn!("Unknown debounce key: `{name}`");
+                        None
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Local control server. It is not started when the settings are missing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct IpcSettings {
+    /// Pipe path, `\\.\pipe\keympostor` by default.
+    pub(crate) pipe: String,
+}
+
+impl Default for IpcSettings {
+    fn default() -> Self {
+        Self {
+            pipe: DEFAULT_PIPE.into(),
+        }
+    }
+}
+
+/// Adaptation to presentation mode and Focus Assist. Not watched when the settings are missing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct FocusSettings {
+    /// Profile selected while the user must not be disturbed. Previous one is restored after.
+    pub(crate) profile: Option<String>,
+    /// Layout sounds are not played while the user must not be disturbed.
+    pub(crate) mute_sounds: bool,
+}
+
+impl Default for FocusSettings {
+    fn default() -> Self {
+        Self {
+            profile: None,
+            mute_sounds: true,
+        }
+    }
+}
+
+/// Profile switches and actions bound to the system events. Not watched when the settings
+/// are missing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SystemEventsSettings {
+    pub(crate) on_lock: Option<SystemEventBinding>,
+    pub(crate) on_unlock: Option<SystemEventBinding>,
+    pub(crate) on_ac_power: Option<SystemEventBinding>,
+    pub(crate) on_battery_power: Option<SystemEventBinding>,
+    pub(crate) on_display_change: Option<SystemEventBinding>,
+}
+
+impl SystemEventsSettings {
+    pub(crate) fn binding(&self, event: SystemEvent) -> Option<&SystemEventBinding> {
+        match event {
+            SystemEvent::Lock => self.on_lock.as_ref(),
+            SystemEvent::Unlock => self.on_unlock.as_ref(),
+            SystemEvent::AcPower => self.on_ac_power.as_ref(),
+            SystemEvent::BatteryPower => self.on_battery_power.as_ref(),
+            SystemEvent::DisplayChange => self.on_display_change.as_ref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SystemEventBinding {
+    /// Profile selected on the event. Empty name selects no profile.
+    pub(crate) profile: Option<String>,
+    /// Actions played on the event.
+    pub(crate) actions: Option<KeyActionSequence>,
+}
+
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 pub(crate) struct LayoutAutoSwitchSettings {
     pub(crate) enabled: bool,
     pub(crate) profiles: Option<HashMap<String, LayoutAutoswitchProfile>>,
+    /// Interval of the foreground window polling in milliseconds, 500 by default.
+    pub(crate) poll_interval: Option<u32>,
+    /// Time in milliseconds the profile window must stay in foreground before its profile is
+    /// selected, 200 by default.
+    pub(crate) debounce_interval: Option<u32>,
 }
 
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
@@ -66,44 +353,156 @@ pub(crate) struct MainWindowSettings {
     pub(crate) position: Option<(i32, i32)>,
     pub(crate) size: Option<(u32, u32)>,
     pub(crate) selected_page: Option<usize>,
+    pub(crate) placements: Option<HashMap<String, WindowPlacementSettings>>,
+    #[serde(default)]
     pub(crate) log_view: LogViewSettings,
 }
 
+/// Window position and size saved for a particular monitors configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WindowPlacementSettings {
+    pub(crate) position: (i32, i32),
+    pub(crate) size: (u32, u32),
+}
+
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 pub(crate) struct LogViewSettings {
     pub(crate) columns: Option<HashMap<usize, isize>>,
+    /// Maximum number of the log records.
+    pub(crate) capacity: Option<usize>,
+    /// Show the time the hook spent processing the events.
+    pub(crate) show_latency: Option<bool>,
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::profile::LayoutAutoswitchProfile;
+    use crate::profile::{
+        LayoutAutoswitchProfile, OpenRgbSettings, OpenRgbZone, ProfileCommand, SettingsOverrides,
+        WindowMatch,
+    };
     use crate::{map, str};
+    use keympostor::hook::InjectionMode;
+    use keympostor::{key_action_seq, key_rules};
 
     #[test]
     fn test_save_load_settings() {
         let settings = AppSettings {
+            version: SETTINGS_VERSION,
             keys_logging_enabled: false,
             toggle_layout_hot_key: None,
+            toggle_processing_hot_key: Some(key_trigger!("[LEFT_CTRL + LEFT_ALT] PAUSE↓")),
+            code_point_hot_key: Some(key_trigger!("[RIGHT_ALT] U↓")),
+            compose_hot_key: Some(key_trigger!("[] RIGHT_WIN↓")),
+            compose_table: Some(str!("compose.toml")),
+            tap_interval: Some(250),
+            debounce: Some(DebounceSettings {
+                interval: Some(30),
+                keys: BTreeMap::from([(str!("SPACE"), 50)]),
+            }),
+            input_chunking: Some(InputChunking {
+                size: 16,
+                delay: 10,
+            }),
+            max_injection_depth: Some(2),
+            release_modifiers: Some(true),
+            normalize_numpad: Some(true),
+            panic_hot_key: Some(key_trigger!("[LEFT_CTRL + LEFT_ALT] PAUSE↓")),
+            close_action: Some(CloseAction::Prompt),
+            key_names: Some(KeyNameTheme::Qmk),
+            theme: Some(ThemeMode::Dark),
+            collect_stats: Some(true),
+            failsafe: Some(FailsafeCommand {
+                program: str!("restore.cmd"),
+                args: vec![str!("--alert")],
+            }),
+            macro_record_hot_key: Some(key_trigger!("[LEFT_CTRL + LEFT_ALT] F11↓")),
+            macro_play_hot_key: Some(key_trigger!("[LEFT_CTRL + LEFT_ALT] F12↓")),
+            macros: Some(key_rules!(
+                "[LEFT_CTRL + LEFT_ALT] F12↓ : A↓ → A↑ → B↓ → B↑"
+            )),
+            disabled_rules: Some(map![
+                str!("test-layout") => BTreeSet::from([1, 3]),
+            ]),
+            overlay: Some(OverlaySettings {
+                position: OverlayPosition::TopRight,
+                ..Default::default()
+            }),
+            sounds: Some(SoundSettings {
+                volume: 50,
+                on_layout_switch: Some(str!("switch.wav")),
+                on_rule_error: Some(str!("SystemHand")),
+                ..Default::default()
+            }),
             last_transform_layout: Some(str!("test-layout")),
+            ipc: Some(IpcSettings::default()),
+            focus: Some(FocusSettings {
+                profile: Some(str!("presentation")),
+                ..Default::default()
+            }),
+            system_events: Some(SystemEventsSettings {
+                on_unlock: Some(SystemEventBinding {
+                    profile: Some(str!("docked")),
+                    actions: Some(key_action_seq!("NUM_LOCK↓ → NUM_LOCK↑")),
+                }),
+                ..Default::default()
+            }),
             main_window: MainWindowSettings {
                 position: Some((0, 0)),
                 size: Some((100, 200)),
                 selected_page: Some(0),
+                placements: Some(map![
+                    str!("0:0:1920x1080") => WindowPlacementSettings {
+                        position: (10, 20),
+                        size: (100, 200),
+                    },
+                    str!("0:0:1920x1080;1920:0:2560x1440") => WindowPlacementSettings {
+                        position: (2000, 20),
+                        size: (300, 400),
+                    },
+                ]),
                 log_view: Default::default(),
             },
+            blacklist: Some(AppBlacklist::from(vec![WindowMatch {
+                exe: Some(str!("mstsc.exe")),
+                ..Default::default()
+            }])),
             layout_autoswitch: Some(LayoutAutoSwitchSettings {
                 enabled: true,
                 profiles: Some(map![
                     str!("chrome") => LayoutAutoswitchProfile {
                         activation_rule: Some(str!("Chrome")),
+                        window_match: None,
                         transform_layout: str!("desktop"),
+                        openrgb: Some(OpenRgbSettings {
+                            address: None,
+                            zones: vec![OpenRgbZone {
+                                device: 0,
+                                zone: 1,
+                                colors: vec![str!("#FF0000"), str!("#00FF00")],
+                            }],
+                        }),
+                        overrides: None,
+                        exec: None,
                     },
                     str!("tc") => LayoutAutoswitchProfile {
                         activation_rule: Some(str!("TOTALCMD64.EXE")),
+                        window_match: None,
                         transform_layout: str!("game"),
+                        openrgb: None,
+                        overrides: Some(SettingsOverrides {
+                            injection_mode: Some(InjectionMode::Scancode),
+                            sound_enabled: Some(false),
+                            ..Default::default()
+                        }),
+                        exec: Some(ProfileCommand {
+                            program: str!("notify.cmd"),
+                            args: vec![str!("$EXE")],
+                        }),
                     },
-                ])
+                ]),
+                poll_interval: Some(1000),
+                debounce_interval: None,
             }),
         };
 
@@ -114,4 +513,58 @@ pub mod tests {
         let loaded = AppSettings::load_from(PATH).unwrap();
         assert_eq!(settings, loaded);
     }
+
+    #[test]
+    fn test_migrate_unversioned_settings() {
+        let mut table = toml::from_str(
+            r#"
+            keys_logging_enabled = true
+
+            [layout_autoswitch]
+            enabled = true
+
+            [layout_autoswitch.profiles.game]
+            transform_layout = "game"
+
+            [main_window]
+            selected_page = 1
+
+            [main_window.log_view]
+            "#,
+        )
+        .unwrap();
+        migrate(&mut table).unwrap();
+
+        let settings: AppSettings = Value::Table(table).try_into().unwrap();
+
+        assert_eq!(SETTINGS_VERSION, settings.version);
+        assert!(settings.keys_logging_enabled);
+        assert_eq!(Some(1), settings.main_window.selected_page);
+    }
+
+    #[test]
+    fn test_migrate_newer_settings_fails() {
+        let mut table = toml::from_str("version = 99").unwrap();
+
+        assert!(migrate(&mut table).is_err());
+    }
+
+    #[test]
+    fn test_debounce_settings_overrides() {
+        let settings = DebounceSettings {
+            interval: Some(30),
+            keys: BTreeMap::from([(str!("SPACE"), 50), (str!("A"), 40)]),
+        };
+        let overrides = DebounceSettings {
+            interval: None,
+            keys: BTreeMap::from([(str!("A"), 60), (str!("NO_SUCH_KEY"), 10)]),
+        };
+        let debounce = settings.with_overrides(Some(&overrides)).to_debounce();
+
+        assert_eq!(30, debounce.interval(Key::B));
+        assert_eq!(50, debounce.interval(Key::Space));
+        assert_eq!(60, debounce.interval(Key::A));
+        assert_eq!(2, debounce.keys.len());
+        assert_eq!(settings, settings.with_overrides(None));
+    }
 }
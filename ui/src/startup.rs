@@ -0,0 +1,358 @@
+use log::{debug, info};
+use std::env;
+use std::error::Error;
+use std::ffi::c_void;
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+use windows::Win32::Foundation::{CloseHandle, ERROR_CANCELLED, ERROR_FILE_NOT_FOUND, HANDLE};
+use windows::Win32::System::Registry::{
+    HKEY, HKEY_CURRENT_USER, KEY_SET_VALUE, REG_SZ, RRF_RT_REG_SZ, RegCloseKey, RegDeleteValueW,
+    RegGetValueW, RegOpenKeyExW, RegSetKeyValueW, RegSetValueExW,
+};
+use windows::Win32::System::Threading::{GetExitCodeProcess, INFINITE, WaitForSingleObject};
+use windows::Win32::UI::Shell::{SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW, ShellExecuteExW};
+use windows::Win32::UI::WindowsAndMessaging::SW_HIDE;
+use windows::core::{HSTRING, PCWSTR, w};
+
+const RUN_KEY: PCWSTR = w!(r"Software\Microsoft\Windows\CurrentVersion\Run");
+const PROTOCOL_KEY: &str = r"Software\Classes\keympostor";
+const ENTRY_NAME: &str = "Keympostor";
+const AUTOSTART_ARG_PREFIX: &str = "--autostart=";
+const RELAUNCH_ARG: &str = "--relaunch";
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// The way the application is started at user logon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum StartupMethod {
+    /// Entry of the user `Run` registry key. Starts without elevation.
+    RunKey,
+    /// Logon task of the Task Scheduler running with highest privileges,
+    /// so that the hook also works in elevated applications.
+    ElevatedTask,
+}
+
+impl StartupMethod {
+    /// Returns the method the application is registered with if any.
+    pub(crate) fn current() -> Option<Self> {
+        if is_run_key_registered() {
+            Some(Self::RunKey)
+        } else if is_task_registered() {
+            Some(Self::ElevatedTask)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn register(&self) -> Result<(), Box<dyn Error>> {
+        let exe = env::current_exe()?;
+        match self {
+            Self::RunKey => register_run_key(&exe)?,
+            Self::ElevatedTask => run_elevated("schtasks.exe", &task_create_parameters(&exe))?,
+        }
+        info!("Registered for autostart: {:?}", self);
+        Ok(())
+    }
+
+    pub(crate) fn unregister(&self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Self::RunKey => unregister_run_key()?,
+            Self::ElevatedTask => run_elevated("schtasks.exe", &task_delete_parameters())?,
+        }
+        info!("Unregistered from autostart: {:?}", self);
+        Ok(())
+    }
+}
+
+/// Autostart change requested by `--autostart=off|run|task` command line argument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum StartupCommand {
+    Enable(StartupMethod),
+    Disable,
+}
+
+impl StartupCommand {
+    pub(crate) fn from_args() -> Option<Self> {
+        env::args()
+            .skip(1)
+            .find_map(|arg| Self::from_argument(&arg))
+    }
+
+    fn from_argument(arg: &str) -> Option<Self> {
+        match arg.strip_prefix(AUTOSTART_ARG_PREFIX)? {
+            "off" => Some(Self::Disable),
+            "run" => Some(Self::Enable(StartupMethod::RunKey)),
+            "task" => Some(Self::Enable(StartupMethod::ElevatedTask)),
+            _ => None,
+        }
+    }
+
+    /// Replaces current registration with the requested one.
+    pub(crate) fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let current = StartupMethod::current();
+        if let Some(method) = current {
+            if *self == Self::Enable(method) {
+                return method.register(); /* updates the path of a moved executable */
+            }
+            method.unregister()?;
+        }
+        if let Self::Enable(method) = self {
+            method.register()?;
+        }
+        Ok(())
+    }
+}
+
+fn is_run_key_registered() -> bool {
+    let name = HSTRING::from(ENTRY_NAME);
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            RUN_KEY,
+            &name,
+            RRF_RT_REG_SZ,
+            None,
+            None,
+            None,
+        )
+    };
+    result.ok().is_ok()
+}
+
+fn register_run_key(exe: &Path) -> windows::core::Result<()> {
+    let value = reg_sz(&quote(&exe.to_string_lossy()));
+    with_run_key(|key| unsafe {
+        RegSetValueExW(key, &HSTRING::from(ENTRY_NAME), None, REG_SZ, Some(&value)).ok()
+    })
+}
+
+fn unregister_run_key() -> windows::core::Result<()> {
+    with_run_key(|key| unsafe {
+        let result = RegDeleteValueW(key, &HSTRING::from(ENTRY_NAME));
+        if result == ERROR_FILE_NOT_FOUND {
+            Ok(())
+        } else {
+            result.ok()
+        }
+    })
+}
+
+fn with_run_key<F>(action: F) -> windows::core::Result<()>
+where
+    F: FnOnce(HKEY) -> windows::core::Result<()>,
+{
+    let mut key = HKEY::default();
+    unsafe {
+        RegOpenKeyExW(HKEY_CURRENT_USER, RUN_KEY, None, KEY_SET_VALUE, &mut key).ok()?;
+        let result = action(key);
+        let _ = RegCloseKey(key);
+        result
+    }
+}
+
+/// Registers the executable as the handler of the `keympostor://` links of the current user
+/// unless it is registered already, so that a moved executable is registered again.
+pub(crate) fn update_url_protocol() -> Result<(), Box<dyn Error>> {
+    let exe = env::current_exe()?;
+    if registered_protocol_command().as_deref() != Some(protocol_command(&exe).as_str()) {
+        register_url_protocol(&exe)?;
+    }
+    Ok(())
+}
+
+fn registered_protocol_command() -> Option<String> {
+    let key = HSTRING::from(format!(r"{PROTOCOL_KEY}\shell\open\command"));
+    let mut buffer = [0u16; 1024];
+    let mut size = size_of_val(&buffer) as u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &key,
+            PCWSTR::null(),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            Some(&mut size),
+        )
+    }
+    .ok()
+    .ok()?;
+
+    /* the size includes the terminating null */
+    let len = (size as usize / size_of::<u16>()).saturating_sub(1);
+    Some(String::from_utf16_lossy(&buffer[..len]))
+}
+
+fn register_url_protocol(exe: &Path) -> windows::core::Result<()> {
+    let command_key = format!(r"{PROTOCOL_KEY}\shell\open\command");
+    for (key, name, value) in [
+        (PROTOCOL_KEY, "", "URL:Keympostor".to_string()),
+        (PROTOCOL_KEY, "URL Protocol", String::new()),
+        (command_key.as_str(), "", protocol_command(exe)),
+    ] {
+        let data = reg_sz(&value);
+        unsafe {
+            RegSetKeyValueW(
+                HKEY_CURRENT_USER,
+                &HSTRING::from(key),
+                &HSTRING::from(name),
+                REG_SZ.0,
+                Some(data.as_ptr().cast()),
+                data.len() as u32,
+            )
+            .ok()?;
+        }
+    }
+    info!("Registered `keympostor://` links handler");
+    Ok(())
+}
+
+fn protocol_command(exe: &Path) -> String {
+    format!("{} \"%1\"", quote(&exe.to_string_lossy()))
+}
+
+/// Null terminated UTF-16 bytes of the `REG_SZ` registry value.
+fn reg_sz(value: &str) -> Vec<u8> {
+    value
+        .encode_utf16()
+        .chain([0])
+        .flat_map(|c| c.to_le_bytes())
+        .collect()
+}
+
+fn is_task_registered() -> bool {
+    Command::new("schtasks.exe")
+        .args(["/Query", "/TN", ENTRY_NAME])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Runs the program with elevation prompt and waits for it to finish.
+fn run_elevated(program: &str, parameters: &str) -> Result<(), Box<dyn Error>> {
+    let process = start_elevated(program, parameters)?;
+
+    let mut exit_code = 0;
+    unsafe {
+        WaitForSingleObject(process, INFINITE);
+        let result = GetExitCodeProcess(process, &mut exit_code);
+        let _ = CloseHandle(process);
+        result?;
+    }
+
+    if exit_code == 0 {
+        Ok(())
+    } else {
+        Err(format!("`{}` failed with exit code {}", program, exit_code).into())
+    }
+}
+
+/// Starts the program with elevation prompt. Returns the process handle to be closed.
+fn start_elevated(program: &str, parameters: &str) -> windows::core::Result<HANDLE> {
+    debug!("Running elevated: {} {}", program, parameters);
+
+    let program = HSTRING::from(program);
+    let parameters = HSTRING::from(parameters);
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOCLOSEPROCESS,
+        lpVerb: w!("runas"),
+        lpFile: PCWSTR(program.as_ptr()),
+        lpParameters: PCWSTR(parameters.as_ptr()),
+        nShow: SW_HIDE.0,
+        ..Default::default()
+    };
+
+    unsafe { ShellExecuteExW(&mut info)? };
+    Ok(info.hProcess)
+}
+
+/// Starts an elevated instance of the application.
+/// Returns `false` when the user declined the elevation prompt.
+pub(crate) fn relaunch_elevated() -> Result<bool, Box<dyn Error>> {
+    let exe = env::current_exe()?;
+    match start_elevated(&exe.to_string_lossy(), RELAUNCH_ARG) {
+        Ok(process) => {
+            unsafe {
+                let _ = CloseHandle(process);
+            }
+            info!("Relaunched elevated");
+            Ok(true)
+        }
+        Err(e) if e.code() == ERROR_CANCELLED.to_hresult() => {
+            debug!("Elevation declined");
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Returns `true` if the application was started by [`relaunch_elevated`]
+/// while the previous instance was exiting.
+pub(crate) fn is_relaunched() -> bool {
+    env::args().skip(1).any(|arg| arg == RELAUNCH_ARG)
+}
+
+fn task_create_parameters(exe: &Path) -> String {
+    /* quotes inside of the task command are escaped for the schtasks parser */
+    let command = quote(&exe.to_string_lossy()).replace('"', "\\\"");
+    format!("/Create /TN {ENTRY_NAME} /TR \"{command}\" /SC ONLOGON /RL HIGHEST /IT /F")
+}
+
+fn task_delete_parameters() -> String {
+    format!("/Delete /TN {ENTRY_NAME} /F")
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{s}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::startup::StartupCommand::{Disable, Enable};
+    use crate::startup::StartupMethod::{ElevatedTask, RunKey};
+    use crate::startup::{
+        StartupCommand, protocol_command, reg_sz, task_create_parameters, task_delete_parameters,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn test_startup_command_from_argument() {
+        assert_eq!(
+            Some(Disable),
+            StartupCommand::from_argument("--autostart=off")
+        );
+        assert_eq!(
+            Some(Enable(RunKey)),
+            StartupCommand::from_argument("--autostart=run")
+        );
+        assert_eq!(
+            Some(Enable(ElevatedTask)),
+            StartupCommand::from_argument("--autostart=task")
+        );
+        assert_eq!(None, StartupCommand::from_argument("--autostart=yes"));
+        assert_eq!(None, StartupCommand::from_argument("--enable"));
+    }
+
+    #[test]
+    fn test_task_parameters() {
+        assert_eq!(
+            r#"/Create /TN Keympostor /TR "\"C:\Program Files\keympostor.exe\"" /SC ONLOGON /RL HIGHEST /IT /F"#,
+            task_create_parameters(Path::new(r"C:\Program Files\keympostor.exe"))
+        );
+        assert_eq!("/Delete /TN Keympostor /F", task_delete_parameters());
+    }
+
+    #[test]
+    fn test_protocol_command() {
+        assert_eq!(
+            r#""C:\Program Files\keympostor.exe" "%1""#,
+            protocol_command(Path::new(r"C:\Program Files\keympostor.exe"))
+        );
+    }
+
+    #[test]
+    fn test_reg_sz() {
+        assert_eq!(vec![b'a', 0, 0x16, 0x04, 0, 0], reg_sz("aЖ"));
+    }
+}
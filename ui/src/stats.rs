@@ -0,0 +1,147 @@
+use keympostor::physical::PhysicalLayout;
+use keympostor::stats::KeyStats;
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const STATS_FILE: &str = "stats.json";
+
+/// Shades of the keyboard keys from never pressed to the most pressed one.
+const KEY_SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// Width of the key unit of the keyboard in characters.
+const KEY_UNIT_CHARS: f32 = 4.0;
+
+/// Loads the collected statistics. Missing file means nothing has been collected yet.
+pub(crate) fn load_stats() -> Result<KeyStats, Box<dyn Error>> {
+    load_stats_from(STATS_FILE)
+}
+
+pub(crate) fn save_stats(stats: &KeyStats) -> Result<(), Box<dyn Error>> {
+    save_stats_to(stats, STATS_FILE)?;
+    debug!("Statistics saved");
+    Ok(())
+}
+
+fn load_stats_from<P: AsRef<Path>>(path: P) -> Result<KeyStats, Box<dyn Error>> {
+    if !path.as_ref().exists() {
+        return Ok(KeyStats::default());
+    }
+    let text = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+fn save_stats_to<P: AsRef<Path>>(stats: &KeyStats, path: P) -> Result<(), Box<dyn Error>> {
+    fs::write(path, serde_json::to_string_pretty(stats)?)?;
+    Ok(())
+}
+
+/// Returns the statistics summary shown in the UI.
+pub(crate) fn format_stats(stats: &KeyStats, layout: PhysicalLayout) -> String {
+    let mut s = String::new();
+    s.push_str(&format!("Key presses: {}\r\n", stats.total_presses()));
+    s.push_str(&format!(
+        "Typing rate: {:.0} keys per minute\r\n",
+        stats.typing_rate()
+    ));
+
+    s.push_str("\r\nKeyboard:\r\n");
+    s.push_str(&format_keyboard(stats, layout));
+
+    s.push_str("\r\nKeys:\r\n");
+    for (key, share) in stats.heatmap() {
+        s.push_str(&format!(
+            "  {:<16}{:>8}  {}\r\n",
+            key.to_string(),
+            stats.presses(key),
+            "#".repeat((share * 20.0).round() as usize)
+        ));
+    }
+
+    s.push_str("\r\nRules:\r\n");
+    for (rule, count) in stats.rules_fired() {
+        s.push_str(&format!("  {:>8}  {}\r\n", count, rule));
+    }
+    s
+}
+
+/// Draws the main block of the physical keyboard with the keys shaded by their presses.
+fn format_keyboard(stats: &KeyStats, layout: PhysicalLayout) -> String {
+    let heatmap: HashMap<_, _> = stats.heatmap().into_iter().collect();
+    let mut rows: Vec<String> = vec![];
+    for placement in layout.placements() {
+        if rows.len() <= placement.row as usize {
+            rows.push("  ".to_string());
+        }
+        let start = (placement.column * KEY_UNIT_CHARS).round() as usize;
+        let end = ((placement.column + placement.width) * KEY_UNIT_CHARS).round() as usize;
+        let share = heatmap.get(&placement.key).copied().unwrap_or_default();
+        let shade = KEY_SHADES[(share * (KEY_SHADES.len() - 1) as f32).ceil() as usize];
+
+        let row = rows.last_mut().unwrap();
+        row.extend(std::iter::repeat_n(shade, end - start - 1));
+        row.push(' ');
+    }
+
+    rows.iter()
+        .map(|row| format!("{}\r\n", row.trim_end()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{format_stats, load_stats_from, save_stats_to};
+    use keympostor::event::KeyEvent;
+    use keympostor::notify::KeyEventNotification;
+    use keympostor::physical::PhysicalLayout;
+    use keympostor::stats::KeyStats;
+    use keympostor::trigger::KeyTrigger;
+    use std::str::FromStr;
+
+    fn create_test_stats() -> KeyStats {
+        let mut stats = KeyStats::default();
+        for (text, time) in [("A↓", 0), ("A↓", 100), ("B↓", 200)] {
+            stats.record(&KeyEventNotification {
+                event: KeyEvent {
+                    trigger: KeyTrigger::from_str(text).unwrap(),
+                    time,
+                    ..Default::default()
+                },
+                rule: None,
+                error: None,
+                latency: None,
+            });
+        }
+        stats
+    }
+
+    #[test]
+    fn test_save_load_stats() {
+        const PATH: &str = "etc/test_data/test_stats.json";
+        let stats = create_test_stats();
+
+        save_stats_to(&stats, PATH).unwrap();
+        let loaded = load_stats_from(PATH).unwrap();
+
+        assert_eq!(stats.key_presses(), loaded.key_presses());
+        assert_eq!(
+            KeyStats::default(),
+            load_stats_from("etc/test_data/missing.json").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_stats() {
+        let text = format_stats(&create_test_stats(), PhysicalLayout::Ansi);
+
+        assert!(text.starts_with("Key presses: 3\r\n"));
+        assert!(
+            text.contains("\r\n  ······ ███ ··· ··· ··· ··· ··· ··· ··· ··· ··· ··· ········\r\n")
+        );
+        assert!(text.contains("··· ··· ··· ··· ▒▒▒ ··· ··· ··· ··· ··· ··········\r\n"));
+        assert!(text.contains("  A                      2  ####################\r\n"));
+        assert!(text.contains("  B                      1  ##########\r\n"));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::app::App;
+use log::{debug, warn};
+use std::cell::Cell;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+use windows::Win32::UI::WindowsAndMessaging::{
+    PBT_APMPOWERSTATUSCHANGE, WM_DISPLAYCHANGE, WM_POWERBROADCAST, WM_WTSSESSION_CHANGE,
+    WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
+};
+
+/// System event the profile switch and the actions can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SystemEvent {
+    Lock,
+    Unlock,
+    AcPower,
+    BatteryPower,
+    DisplayChange,
+}
+
+impl SystemEvent {
+    fn from_session_change(change: u32) -> Option<Self> {
+        match change {
+            WTS_SESSION_LOCK => Some(Self::Lock),
+            WTS_SESSION_UNLOCK => Some(Self::Unlock),
+            _ => None,
+        }
+    }
+
+    fn from_power_source(is_ac_power: bool) -> Self {
+        if is_ac_power {
+            Self::AcPower
+        } else {
+            Self::BatteryPower
+        }
+    }
+}
+
+/// Listens to the session lock, power source and display configuration changes.
+#[derive(Default)]
+pub(crate) struct SystemEventWatcher {
+    hwnd: Cell<Option<HWND>>,
+    is_ac_power: Cell<Option<bool>>,
+}
+
+impl SystemEventWatcher {
+    pub(crate) fn setup(&self, hwnd: HWND) {
+        self.hwnd.set(Some(hwnd));
+        self.is_ac_power.set(query_ac_power());
+        /* session notifications are registered by the session watcher of the hook */
+
+        debug!("System events watch started");
+    }
+
+    pub(crate) fn stop(&self) {
+        if self.hwnd.take().is_none() {
+            return;
+        }
+
+        debug!("System events watch stopped");
+    }
+
+    pub(crate) fn handle_raw_event(&self, app: &App, msg: u32, w_param: usize) {
+        if self.hwnd.get().is_none() {
+            return;
+        }
+
+        if let Some(event) = self.detect_event(msg, w_param) {
+            debug!("System event: {:?}", event);
+            app.on_system_event(event);
+        }
+    }
+
+    fn detect_event(&self, msg: u32, w_param: usize) -> Option<SystemEvent> {
+        match msg {
+            WM_WTSSESSION_CHANGE => SystemEvent::from_session_change(w_param as u32),
+            WM_POWERBROADCAST if w_param as u32 == PBT_APMPOWERSTATUSCHANGE => {
+                /* status change is also broadcast on battery level changes */
+                let is_ac_power = query_ac_power()?;
+                if self.is_ac_power.replace(Some(is_ac_power)) == Some(is_ac_power) {
+                    return None;
+                }
+                Some(SystemEvent::from_power_source(is_ac_power))
+            }
+            WM_DISPLAYCHANGE => Some(SystemEvent::DisplayChange),
+            _ => None,
+        }
+    }
+}
+
+fn query_ac_power() -> Option<bool> {
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if let Err(e) = unsafe { GetSystemPowerStatus(&mut status) } {
+        warn!("Failed to get power status: {}", e);
+        return None;
+    }
+
+    match status.ACLineStatus {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sys_watch::{SystemEvent, SystemEventWatcher};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        WM_DISPLAYCHANGE, WM_KEYDOWN, WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK, WTS_SESSION_LOGON,
+        WTS_SESSION_UNLOCK,
+    };
+
+    #[test]
+    fn test_detect_event() {
+        let watcher = SystemEventWatcher::default();
+
+        assert_eq!(
+            Some(SystemEvent::Lock),
+            watcher.detect_event(WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK as usize)
+        );
+        assert_eq!(
+            Some(SystemEvent::Unlock),
+            watcher.detect_event(WM_WTSSESSION_CHANGE, WTS_SESSION_UNLOCK as usize)
+        );
+        assert_eq!(
+            None,
+            watcher.detect_event(WM_WTSSESSION_CHANGE, WTS_SESSION_LOGON as usize)
+        );
+        assert_eq!(
+            Some(SystemEvent::DisplayChange),
+            watcher.detect_event(WM_DISPLAYCHANGE, 32)
+        );
+        assert_eq!(None, watcher.detect_event(WM_KEYDOWN, 0));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::app::App;
+use crate::layout::KeyTransformLayoutList;
+use log::{debug, warn};
+use native_windows_gui::{ControlHandle, Event};
+use std::cell::RefCell;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{KillTimer, SetTimer};
+
+const TIMER_ID: usize = 19719;
+const TRIAL_DURATION: u32 = 60_000;
+
+/// Layouts state to return to when trial is not confirmed.
+pub(crate) struct LayoutsRollback {
+    pub(crate) layouts: KeyTransformLayoutList,
+    pub(crate) layout_name: String,
+}
+
+/// Keeps previous layouts while reloaded ones are applied for a limited time.
+#[derive(Default)]
+pub(crate) struct LayoutsTrial {
+    owner: RefCell<HWND>,
+    rollback: RefCell<Option<LayoutsRollback>>,
+}
+
+impl LayoutsTrial {
+    pub(crate) fn setup(&self, owner: HWND) {
+        self.owner.replace(owner);
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.rollback.borrow().is_some()
+    }
+
+    pub(crate) fn start(&self, rollback: LayoutsRollback) {
+        self.rollback.replace(Some(rollback));
+        unsafe {
+            SetTimer(Some(*self.owner.borrow()), TIMER_ID, TRIAL_DURATION, None);
+        }
+        debug!("Layouts trial started");
+    }
+
+    /// Ends the trial keeping current layouts.
+    pub(crate) fn confirm(&self) {
+        if self.finish().is_some() {
+            debug!("Layouts trial confirmed");
+        }
+    }
+
+    /// Ends the trial returning previous layouts state.
+    pub(crate) fn finish(&self) -> Option<LayoutsRollback> {
+        let rollback = self.rollback.take()?;
+        unsafe {
+            KillTimer(Some(*self.owner.borrow()), TIMER_ID).unwrap_or_else(|e| {
+                if e.code().is_err() {
+                    warn!("Failed to kill layouts trial timer: {}", e);
+                }
+            });
+        }
+        Some(rollback)
+    }
+
+    pub(crate) fn handle_event(&self, app: &App, evt: Event, handle: ControlHandle) {
+        if let Event::OnTimerTick = evt {
+            if let Some((_, timer_id)) = handle.timer() {
+                if timer_id == TIMER_ID as u32 {
+                    app.on_rollback_layouts();
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,19 @@
 pub(crate) mod app_ui;
 mod layout_view;
+mod learn_view;
 mod layouts_menu;
 mod log_view;
 mod main_menu;
+mod display;
+mod overlay;
+pub(crate) mod rules_editor;
+mod rules_history;
+mod stats_view;
 pub(crate) mod main_window;
 mod style;
 mod test_editor;
 mod tray;
 pub(crate) mod utils;
+pub(crate) mod wizard;
 pub mod res;
 pub(crate) mod res_ids;
@@ -1,9 +1,15 @@
 use crate::app::App;
+use crate::import::{import_layout, import_url_from_args};
+use crate::jump_list::JumpListTask;
 use crate::rs;
+use crate::startup::{StartupCommand, is_relaunched};
 use crate::ui::res::RESOURCES;
-use crate::ui::res_ids::IDS_APP_ALREADY_RUNNING;
-use crate::ui::style::display_font;
-use crate::ui::utils::show_warn_message;
+use crate::ui::res_ids::{
+    IDS_APP_ALREADY_RUNNING, IDS_FAILED_AUTOSTART, IDS_FAILED_IMPORT_LAYOUT, IDS_LAYOUT_IMPORTED,
+};
+use crate::ui::display::DEFAULT_DPI;
+use crate::ui::style::{DEFAULT_FONT, build_font};
+use crate::ui::utils::{show_info_message, show_warn_message};
 use crate::util::is_app_running;
 use native_windows_gui as nwg;
 use std::rc::Rc;
@@ -16,7 +22,7 @@ pub struct AppUI {
 impl AppUI {
     pub(crate) fn build(mut app: App) -> Self {
         nwg::init().expect("Failed to init Native Windows GUI.");
-        nwg::Font::set_global_default(Some(display_font(17)));
+        nwg::Font::set_global_default(Some(build_font(DEFAULT_FONT, DEFAULT_DPI)));
 
         app.window.build().expect("Failed to build main window.");
 
@@ -24,9 +30,29 @@ impl AppUI {
     }
 
     pub(crate) fn run(&self) {
+        if let Some(command) = StartupCommand::from_args() {
+            if let Err(e) = command.execute() {
+                show_warn_message(&format!("{}:\n{}", rs!(IDS_FAILED_AUTOSTART), e));
+            }
+            return;
+        }
+
+        let import_url = import_url_from_args();
+        let is_imported = import_url.is_some_and(|url| match import_layout(&url) {
+            Ok(is_imported) => is_imported,
+            Err(e) => {
+                show_warn_message(&format!("{}:\n{}", rs!(IDS_FAILED_IMPORT_LAYOUT), e));
+                false
+            }
+        });
+
         #[cfg(not(feature = "debug"))]
-        if is_app_running() {
-            show_warn_message(rs!(IDS_APP_ALREADY_RUNNING));
+        if is_app_running() && !is_relaunched() {
+            match JumpListTask::from_args() {
+                Some(task) => task.post_to_running_app(),
+                None if is_imported => show_info_message(rs!(IDS_LAYOUT_IMPORTED)),
+                None => show_warn_message(rs!(IDS_APP_ALREADY_RUNNING)),
+            }
             return;
         }
         self.setup_event_handlers();
@@ -46,9 +72,9 @@ impl AppUI {
         nwg::bind_raw_event_handler(
             &self.app.window.handle(),
             0x10000,
-            move |_hwnd, msg, _w_param, l_param| {
+            move |_hwnd, msg, w_param, l_param| {
                 if let Some(app) = app_rc.upgrade() {
-                    app.handle_raw_event(msg, l_param);
+                    app.handle_raw_event(msg, w_param, l_param);
                 }
                 None
             },
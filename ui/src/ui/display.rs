@@ -0,0 +1,206 @@
+use crate::settings::OverlayPosition;
+use log::warn;
+use windows::Win32::Foundation::{HWND, LPARAM, RECT};
+use windows::Win32::Graphics::Gdi::{
+    EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITOR_DEFAULTTONEAREST, MONITORINFO,
+    MonitorFromWindow,
+};
+use windows::Win32::UI::HiDpi::{GetDpiForMonitor, GetDpiForWindow, MDT_EFFECTIVE_DPI};
+use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+use windows::core::BOOL;
+
+/// DPI of the monitor at 100% scale. Sizes given in the settings are of this DPI.
+pub(crate) const DEFAULT_DPI: u32 = 96;
+
+/// Display monitor with its DPI.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Monitor {
+    pub(crate) rect: RECT,
+    pub(crate) work_area: RECT,
+    pub(crate) dpi: u32,
+}
+
+impl Monitor {
+    fn from_handle(handle: HMONITOR) -> Option<Self> {
+        let mut info = MONITORINFO {
+            cbSize: size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if !unsafe { GetMonitorInfoW(handle, &mut info) }.as_bool() {
+            warn!("Failed to get monitor info");
+            return None;
+        }
+
+        let (mut dpi_x, mut dpi_y) = (DEFAULT_DPI, DEFAULT_DPI);
+        unsafe { GetDpiForMonitor(handle, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) }
+            .unwrap_or_else(|e| warn!("Failed to get monitor DPI: {}", e));
+
+        Some(Self {
+            rect: info.rcMonitor,
+            work_area: info.rcWork,
+            dpi: dpi_x,
+        })
+    }
+
+    /// Scales the value of the default DPI to the DPI of the monitor.
+    pub(crate) fn scale(&self, value: i32) -> i32 {
+        scale(value, self.dpi)
+    }
+
+    /// Returns top left corner of the window of the (unscaled) size placed in the work area.
+    pub(crate) fn place(
+        &self,
+        position: OverlayPosition,
+        size: (i32, i32),
+        margin: i32,
+    ) -> (i32, i32) {
+        place(
+            position,
+            self.work_area,
+            (self.scale(size.0), self.scale(size.1)),
+            self.scale(margin),
+        )
+    }
+}
+
+/// Returns all display monitors.
+pub(crate) fn monitors() -> Vec<Monitor> {
+    unsafe extern "system" fn enum_monitor_proc(
+        monitor: HMONITOR,
+        _hdc: HDC,
+        _rect: *mut RECT,
+        data: LPARAM,
+    ) -> BOOL {
+        let monitors = unsafe { &mut *(data.0 as *mut Vec<Monitor>) };
+        monitors.extend(Monitor::from_handle(monitor));
+        BOOL::from(true)
+    }
+
+    let mut monitors: Vec<Monitor> = vec![];
+    unsafe {
+        let _ = EnumDisplayMonitors(
+            None,
+            None,
+            Some(enum_monitor_proc),
+            LPARAM(&mut monitors as *mut _ as isize),
+        );
+    }
+    monitors
+}
+
+/// Monitor containing the active window, the one where the user currently works.
+pub(crate) fn active_monitor() -> Option<Monitor> {
+    Monitor::from_handle(unsafe {
+        MonitorFromWindow(GetForegroundWindow(), MONITOR_DEFAULTTONEAREST)
+    })
+}
+
+/// DPI of the monitor the window is on.
+pub(crate) fn window_dpi(hwnd: HWND) -> u32 {
+    match unsafe { GetDpiForWindow(hwnd) } {
+        0 => DEFAULT_DPI,
+        dpi => dpi,
+    }
+}
+
+/// Scales the value of the default DPI to the DPI.
+pub(crate) fn scale(value: i32, dpi: u32) -> i32 {
+    (value as i64 * dpi as i64 + DEFAULT_DPI as i64 / 2).div_euclid(DEFAULT_DPI as i64) as i32
+}
+
+/// Returns top left corner of the window of the size placed in the area.
+pub(crate) fn place(
+    position: OverlayPosition,
+    area: RECT,
+    size: (i32, i32),
+    margin: i32,
+) -> (i32, i32) {
+    let left = area.left + margin;
+    let right = area.right - margin - size.0;
+    let top = area.top + margin;
+    let bottom = area.bottom - margin - size.1;
+    let center_x = (area.left + area.right - size.0) / 2;
+    let center_y = (area.top + area.bottom - size.1) / 2;
+
+    match position {
+        OverlayPosition::TopLeft => (left, top),
+        OverlayPosition::Top => (center_x, top),
+        OverlayPosition::TopRight => (right, top),
+        OverlayPosition::Center => (center_x, center_y),
+        OverlayPosition::BottomLeft => (left, bottom),
+        OverlayPosition::Bottom => (center_x, bottom),
+        OverlayPosition::BottomRight => (right, bottom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::OverlayPosition;
+    use crate::ui::display::{Monitor, place, scale};
+    use windows::Win32::Foundation::RECT;
+
+    #[test]
+    fn test_scale() {
+        assert_eq!(48, scale(48, 96));
+        assert_eq!(60, scale(48, 120));
+        assert_eq!(72, scale(48, 144));
+        assert_eq!(96, scale(48, 192));
+        assert_eq!(19, scale(15, 120));
+        assert_eq!(-60, scale(-48, 120));
+    }
+
+    #[test]
+    fn test_place() {
+        let area = RECT {
+            left: 0,
+            top: 0,
+            right: 1000,
+            bottom: 800,
+        };
+
+        assert_eq!(
+            (48, 48),
+            place(OverlayPosition::TopLeft, area, (200, 50), 48)
+        );
+        assert_eq!(
+            (400, 375),
+            place(OverlayPosition::Center, area, (200, 50), 48)
+        );
+        assert_eq!(
+            (752, 702),
+            place(OverlayPosition::BottomRight, area, (200, 50), 48)
+        );
+        assert_eq!(
+            (400, 702),
+            place(OverlayPosition::Bottom, area, (200, 50), 48)
+        );
+    }
+
+    #[test]
+    fn test_monitor_place() {
+        /* secondary monitor at 150% to the left of the primary one */
+        let rect = RECT {
+            left: -2400,
+            top: 0,
+            right: 0,
+            bottom: 1600,
+        };
+        let monitor = Monitor {
+            rect,
+            work_area: RECT {
+                bottom: 1540,
+                ..rect
+            },
+            dpi: 144,
+        };
+
+        assert_eq!(
+            (-2328, 72),
+            monitor.place(OverlayPosition::TopLeft, (200, 50), 48)
+        );
+        assert_eq!(
+            (-372, 1393),
+            monitor.place(OverlayPosition::BottomRight, (200, 50), 48)
+        );
+    }
+}
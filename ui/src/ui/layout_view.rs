@@ -1,41 +1,401 @@
-use crate::layout::KeyTransformLayout;
-use crate::ui::style::SMALL_MONO_FONT;
-use native_windows_gui::{ControlHandle, NwgError, Tab, TextBox};
+use crate::app::App;
+use crate::layout::{KeyTransformLayout, LAYOUTS_PATH};
+use crate::layout_files::{LayoutFile, list_layout_files};
+use crate::rs;
+use crate::ui::res::RESOURCES;
+use crate::ui::res_ids::{
+    IDS_ACTIONS, IDS_DELETE, IDS_DUPLICATE, IDS_EDIT, IDS_FILE, IDS_ID, IDS_NAME, IDS_NEW,
+    IDS_RENAME, IDS_TITLE, IDS_TRIGGER,
+};
+use crate::ui::style::rescale_layout;
+use crate::ui::utils::{
+    enable_list_view_check_boxes, is_list_view_item_checked, set_list_view_item_checked,
+};
+use keympostor::rule::KeyTransformRule;
+use log::warn;
+use native_windows_gui::stretch::geometry::{Rect, Size};
+use native_windows_gui::stretch::style::Dimension::Points as PT;
+use native_windows_gui::stretch::style::{Dimension as D, FlexDirection};
+use native_windows_gui::{
+    Button, ControlHandle, Event, FlexboxLayout, InsertListViewColumn, ListView,
+    ListViewColumnFlags, ListViewExFlags, ListViewFlags, ListViewStyle, NwgError, Tab, TextInput,
+};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
 
+/// Layout files with the management buttons and the rules applied by the current layout.
+/// Unchecked rules are disabled until checked back.
 #[derive(Default)]
 pub(crate) struct LayoutView {
-    view: TextBox,
+    layout: FlexboxLayout,
+    lists_layout: FlexboxLayout,
+    buttons_layout: FlexboxLayout,
+    files_view: ListView,
+    list_view: ListView,
+    name_input: TextInput,
+    new_button: Button,
+    duplicate_button: Button,
+    rename_button: Button,
+    delete_button: Button,
+    edit_button: Button,
+    /// Listed layout files.
+    files: RefCell<Vec<LayoutFile>>,
+    /// IDs of the listed rules. Rules without ID cannot be disabled.
+    rule_ids: RefCell<Vec<Option<u32>>>,
+    /// Check states of the listed rules.
+    checked: RefCell<Vec<bool>>,
+    is_updating: Cell<bool>,
 }
 
 impl LayoutView {
-    pub(crate) fn view(&self) -> impl Into<ControlHandle> {
-        &self.view
-    }
-
     pub(crate) fn build(&mut self, parent: &Tab) -> Result<(), NwgError> {
-        TextBox::builder()
+        ListView::builder()
+            .parent(parent)
+            .list_style(ListViewStyle::Detailed)
+            .ex_flags(ListViewExFlags::GRID | ListViewExFlags::FULL_ROW_SELECT)
+            .flags(
+                ListViewFlags::VISIBLE
+                    | ListViewFlags::TAB_STOP
+                    | ListViewFlags::SINGLE_SELECTION
+                    | ListViewFlags::ALWAYS_SHOW_SELECTION,
+            )
+            .build(&mut self.files_view)?;
+
+        self.files_view.set_headers_enabled(true);
+
+        for (index, (text, width)) in [
+            (rs!(IDS_NAME), 100),
+            (rs!(IDS_TITLE), 140),
+            (rs!(IDS_FILE), 120),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            self.files_view.insert_column(InsertListViewColumn {
+                index: Some(index as i32),
+                fmt: Some(ListViewColumnFlags::LEFT),
+                width: Some(width),
+                text: Some(text.into()),
+            });
+        }
+
+        ListView::builder()
+            .parent(parent)
+            .list_style(ListViewStyle::Detailed)
+            .ex_flags(ListViewExFlags::GRID | ListViewExFlags::FULL_ROW_SELECT)
+            .flags(ListViewFlags::VISIBLE | ListViewFlags::TAB_STOP)
+            .build(&mut self.list_view)?;
+
+        self.list_view.set_headers_enabled(true);
+        enable_list_view_check_boxes(&self.list_view);
+
+        for (index, (text, width)) in [
+            (rs!(IDS_TRIGGER), 200),
+            (rs!(IDS_ACTIONS), 360),
+            (rs!(IDS_ID), 40),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            self.list_view.insert_column(InsertListViewColumn {
+                index: Some(index as i32),
+                fmt: Some(ListViewColumnFlags::LEFT),
+                width: Some(width),
+                text: Some(text.into()),
+            });
+        }
+
+        TextInput::builder()
+            .parent(parent)
+            .build(&mut self.name_input)?;
+
+        for (button, text) in [
+            (&mut self.new_button, rs!(IDS_NEW)),
+            (&mut self.duplicate_button, rs!(IDS_DUPLICATE)),
+            (&mut self.rename_button, rs!(IDS_RENAME)),
+            (&mut self.delete_button, rs!(IDS_DELETE)),
+            (&mut self.edit_button, rs!(IDS_EDIT)),
+        ] {
+            Button::builder().parent(parent).text(text).build(button)?;
+        }
+
+        /* Files and rules */
+        FlexboxLayout::builder()
             .parent(parent)
-            .readonly(true)
-            .font(Some(&SMALL_MONO_FONT))
-            .build(&mut self.view)
+            .child(&self.files_view)
+            .child_size(Size {
+                width: D::Points(240.0),
+                height: D::Auto,
+            })
+            .child_margin(Rect {
+                start: PT(0.0),
+                end: PT(4.0),
+                top: PT(0.0),
+                bottom: PT(0.0),
+            })
+            .child(&self.list_view)
+            .child_flex_grow(1.0)
+            .build_partial(&self.lists_layout)?;
+
+        /* Buttons */
+        let button_size = Size {
+            width: D::Points(90.0),
+            height: D::Points(26.0),
+        };
+        FlexboxLayout::builder()
+            .parent(parent)
+            .child(&self.name_input)
+            .child_size(Size {
+                width: D::Points(160.0),
+                height: D::Points(26.0),
+            })
+            .child(&self.new_button)
+            .child_size(button_size)
+            .child(&self.duplicate_button)
+            .child_size(button_size)
+            .child(&self.rename_button)
+            .child_size(button_size)
+            .child(&self.delete_button)
+            .child_size(button_size)
+            .child(&self.edit_button)
+            .child_size(button_size)
+            .build_partial(&self.buttons_layout)?;
+
+        FlexboxLayout::builder()
+            .parent(parent)
+            .flex_direction(FlexDirection::Column)
+            .child_layout(&self.lists_layout)
+            .child_flex_grow(1.0)
+            .child_margin(Rect {
+                start: PT(4.0),
+                end: PT(16.0),
+                top: PT(6.0),
+                bottom: PT(4.0),
+            })
+            .child_layout(&self.buttons_layout)
+            .child_size(Size {
+                width: D::Auto,
+                height: D::Points(34.0),
+            })
+            .child_margin(Rect {
+                start: PT(4.0),
+                end: PT(16.0),
+                top: PT(0.0),
+                bottom: PT(40.0),
+            })
+            .build(&self.layout)
+    }
+
+    /// Rescales the layout after the DPI change.
+    pub(crate) fn rescale(&self, factor: f32) {
+        rescale_layout(&self.layout, factor);
+        rescale_layout(&self.lists_layout, factor);
+        rescale_layout(&self.buttons_layout, factor);
+        self.layout
+            .fit()
+            .unwrap_or_else(|e| warn!("Failed to fit layout: {:?}", e));
+    }
+
+    pub(crate) fn handle_event(&self, app: &App, evt: Event, handle: ControlHandle) {
+        match evt {
+            Event::OnButtonClick => {
+                self.on_button_click(app, handle);
+            }
+            Event::OnListViewItemChanged => {
+                if handle == self.list_view.handle && !self.is_updating.get() {
+                    self.on_item_changed(app);
+                } else if handle == self.files_view.handle {
+                    self.on_select_file();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Lists the files of the layouts directory.
+    pub(crate) fn update_files(&self) {
+        let files = list_layout_files(LAYOUTS_PATH).unwrap_or_else(|e| {
+            warn!("Failed to list layout files: {}", e);
+            vec![]
+        });
+
+        self.files_view.set_redraw(false);
+        self.files_view.clear();
+        for file in &files {
+            self.files_view.insert_items_row(None, &file_columns(file));
+        }
+        self.files_view.set_redraw(true);
+
+        self.files.replace(files);
+    }
+
+    pub(crate) fn update_ui(
+        &self,
+        layout: Option<&KeyTransformLayout>,
+        disabled_rules: Option<&BTreeSet<u32>>,
+    ) {
+        let rules: Vec<_> = layout
+            .map(|l| l.effective_rules().iter().cloned().collect())
+            .unwrap_or_default();
+        let checked: Vec<_> = rules
+            .iter()
+            .map(|rule| is_rule_checked(rule, disabled_rules))
+            .collect();
+
+        self.is_updating.set(true);
+        self.list_view.set_redraw(false);
+        self.list_view.clear();
+        for (index, rule) in rules.iter().enumerate() {
+            self.list_view.insert_items_row(None, &rule_columns(rule));
+            set_list_view_item_checked(&self.list_view, index, checked[index]);
+        }
+        self.list_view.set_redraw(true);
+        self.is_updating.set(false);
+
+        self.rule_ids
+            .replace(rules.iter().map(|rule| rule.id).collect());
+        self.checked.replace(checked);
+    }
+
+    /// Runs the action on the selected file. The app updates the files so none of them is
+    /// borrowed while it runs.
+    fn on_button_click(&self, app: &App, handle: ControlHandle) {
+        let name = self.name_input.text().trim().to_string();
+        let selected = self
+            .files_view
+            .selected_item()
+            .and_then(|index| self.files.borrow().get(index).cloned());
+
+        if handle == self.new_button.handle {
+            app.on_create_layout(&name);
+            return;
+        }
+        let Some(selected) = selected else {
+            return;
+        };
+        if handle == self.duplicate_button.handle {
+            app.on_duplicate_layout(&selected.name, &name);
+        } else if handle == self.rename_button.handle {
+            app.on_rename_layout(&selected.name, &name);
+        } else if handle == self.delete_button.handle {
+            app.on_delete_layout(&selected.name);
+        } else if handle == self.edit_button.handle {
+            app.on_edit_layout_file(&selected.path);
+        }
+    }
+
+    fn on_select_file(&self) {
+        let Some(index) = self.files_view.selected_item() else {
+            return;
+        };
+        if let Some(file) = self.files.borrow().get(index) {
+            self.name_input.set_text(&file.name);
+        }
     }
 
-    pub(crate) fn update_ui(&self, layout: Option<&KeyTransformLayout>) {
-        let mut text = String::new();
-        match layout {
-            None => {
-                text.push_str(&format!("{}\r\n", "NONE"));
+    /// Reports the rule which check box was toggled.
+    fn on_item_changed(&self, app: &App) {
+        let rule_ids = self.rule_ids.borrow().clone();
+        for (index, id) in rule_ids.into_iter().enumerate() {
+            let is_checked = is_list_view_item_checked(&self.list_view, index);
+            if self.checked.borrow().get(index) == Some(&is_checked) {
+                continue;
             }
-            Some(l) => {
-                text.push_str(&format!("{}\r\n", l.title));
-                text.push_str(&"-".repeat(l.title.len()));
-                text.push_str("\r\n");
-                for rule in l.rules.iter() {
-                    text.push_str(&format!("{:22} : {}\r\n", rule.trigger, rule.actions));
+
+            match id {
+                Some(id) => {
+                    /* every rule of the ID is toggled, the view is updated by the app */
+                    app.on_set_rule_enabled(id, is_checked);
+                    return;
+                }
+                None => {
+                    self.is_updating.set(true);
+                    set_list_view_item_checked(&self.list_view, index, true);
+                    self.is_updating.set(false);
                 }
             }
         }
+    }
+}
+
+fn is_rule_checked(rule: &KeyTransformRule, disabled_rules: Option<&BTreeSet<u32>>) -> bool {
+    match (rule.id, disabled_rules) {
+        (Some(id), Some(disabled)) => !disabled.contains(&id),
+        _ => true,
+    }
+}
+
+fn file_columns(file: &LayoutFile) -> [String; 3] {
+    [
+        file.name.clone(),
+        file.title.clone(),
+        file.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    ]
+}
+
+fn rule_columns(rule: &KeyTransformRule) -> [String; 3] {
+    [
+        rule.trigger.to_string(),
+        rule.actions.to_string(),
+        rule.id.map(|id| id.to_string()).unwrap_or_default(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout_files::LayoutFile;
+    use crate::ui::layout_view::{file_columns, is_rule_checked, rule_columns};
+    use keympostor::key_rule;
+    use keympostor::rule::KeyTransformRule;
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_is_rule_checked() {
+        let disabled = BTreeSet::from([1]);
+
+        assert!(!is_rule_checked(&key_rule!("#1 A↓ : B↓"), Some(&disabled)));
+        assert!(is_rule_checked(&key_rule!("#2 A↓ : B↓"), Some(&disabled)));
+        assert!(is_rule_checked(&key_rule!("A↓ : B↓"), Some(&disabled)));
+        assert!(is_rule_checked(&key_rule!("#1 A↓ : B↓"), None));
+    }
+
+    #[test]
+    fn test_rule_columns() {
+        assert_eq!(
+            [
+                "[LEFT_CTRL] A↓".to_string(),
+                "B↓ → B↑".to_string(),
+                "7".to_string(),
+            ],
+            rule_columns(&key_rule!("#7 [LEFT_CTRL] A↓ : B↓ → B↑"))
+        );
+        assert_eq!(
+            ["A↑".to_string(), "B↑".to_string(), String::new()],
+            rule_columns(&key_rule!("A↑ : B↑"))
+        );
+    }
+
+    #[test]
+    fn test_file_columns() {
+        let file = LayoutFile {
+            name: "minimal".to_string(),
+            title: "Minimal layout".to_string(),
+            extends: None,
+            path: PathBuf::from("layouts").join("minimal.toml"),
+        };
 
-        self.view.set_text(&text);
+        assert_eq!(
+            [
+                "minimal".to_string(),
+                "Minimal layout".to_string(),
+                "minimal.toml".to_string(),
+            ],
+            file_columns(&file)
+        );
     }
 }
@@ -1,5 +1,8 @@
 use crate::layout::{KeyTransformLayout, KeyTransformLayoutList};
-use crate::ui::res_ids::{IDS_AUTO_SWITCH_LAYOUT, IDS_LAYOUT};
+use crate::ui::res_ids::{
+    IDS_AUTO_SWITCH_LAYOUT, IDS_CHECK_LAYOUT, IDS_EXPORT_SCANCODE_MAP, IDS_IMPORT_LAYOUT,
+    IDS_IMPORT_SCANCODE_MAP, IDS_KEEP_LAYOUTS, IDS_LAYOUT, IDS_TRY_RELOAD_LAYOUTS,
+};
 use crate::ui::res::RESOURCES;
 use crate::rs;
 use crate::app::App;
@@ -10,6 +13,12 @@ use std::cell::RefCell;
 pub(crate) struct LayoutsMenu {
     menu: Menu,
     toggle_auto_switch_layout_item: MenuItem,
+    try_reload_layouts_item: MenuItem,
+    keep_layouts_item: MenuItem,
+    check_layout_item: MenuItem,
+    import_layout_item: MenuItem,
+    export_scancode_map_item: MenuItem,
+    import_scancode_map_item: MenuItem,
     items: RefCell<Vec<(MenuItem, String)>>,
     separator: MenuSeparator,
 }
@@ -26,6 +35,37 @@ impl LayoutsMenu {
             .text(rs!(IDS_AUTO_SWITCH_LAYOUT))
             .build(&mut self.toggle_auto_switch_layout_item)?;
 
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_TRY_RELOAD_LAYOUTS))
+            .build(&mut self.try_reload_layouts_item)?;
+
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_KEEP_LAYOUTS))
+            .disabled(true)
+            .build(&mut self.keep_layouts_item)?;
+
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_CHECK_LAYOUT))
+            .build(&mut self.check_layout_item)?;
+
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_IMPORT_LAYOUT))
+            .build(&mut self.import_layout_item)?;
+
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_EXPORT_SCANCODE_MAP))
+            .build(&mut self.export_scancode_map_item)?;
+
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_IMPORT_SCANCODE_MAP))
+            .build(&mut self.import_scancode_map_item)?;
+
         MenuSeparator::builder()
             .parent(&self.menu)
             .build(&mut self.separator)?;
@@ -42,10 +82,12 @@ impl LayoutsMenu {
     pub(crate) fn update_ui(
         &self,
         is_auto_switch_layout_enabled: bool,
+        is_layouts_trial_active: bool,
         current_layout: &KeyTransformLayout,
     ) {
         self.toggle_auto_switch_layout_item
             .set_checked(is_auto_switch_layout_enabled);
+        self.keep_layouts_item.set_enabled(is_layouts_trial_active);
 
         for (item, item_layout_name) in self.items.borrow().iter() {
             item.set_checked(item_layout_name == &current_layout.name);
@@ -57,6 +99,18 @@ impl LayoutsMenu {
             Event::OnMenuItemSelected => {
                 if &handle == &self.toggle_auto_switch_layout_item {
                     app.on_toggle_auto_switch_layout();
+                } else if &handle == &self.try_reload_layouts_item {
+                    app.on_try_reload_layouts();
+                } else if &handle == &self.keep_layouts_item {
+                    app.on_keep_layouts();
+                } else if &handle == &self.check_layout_item {
+                    app.on_check_layout();
+                } else if &handle == &self.import_layout_item {
+                    app.on_import_layout();
+                } else if &handle == &self.export_scancode_map_item {
+                    app.on_export_scancode_map();
+                } else if &handle == &self.import_scancode_map_item {
+                    app.on_import_scancode_map();
                 } else {
                     for (item, layout_name) in self.items.borrow().iter() {
                         if item.handle == handle {
@@ -77,11 +131,11 @@ pub(crate) fn build_layout_items(
 ) -> Result<Vec<(MenuItem, String)>, NwgError> {
     let mut items = vec![];
 
-    for layout in layouts {
+    for (index, layout) in layouts.into_iter().enumerate() {
         let mut item: MenuItem = MenuItem::default();
         MenuItem::builder()
             .parent(parent)
-            .text(&layout.title)
+            .text(&layout_item_text(index, &layout.title))
             .build(&mut item)?;
 
         items.push((item, layout.name.clone()));
@@ -89,3 +143,25 @@ pub(crate) fn build_layout_items(
 
     Ok(items)
 }
+
+/// Returns menu item text with digit mnemonic for the first nine layouts.
+fn layout_item_text(index: usize, title: &str) -> String {
+    let title = title.replace('&', "&&");
+    if index < 9 {
+        format!("&{} {}", index + 1, title)
+    } else {
+        title
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::layouts_menu::layout_item_text;
+
+    #[test]
+    fn test_layout_item_text() {
+        assert_eq!("&1 Default", layout_item_text(0, "Default"));
+        assert_eq!("&9 Ctrl && Alt", layout_item_text(8, "Ctrl & Alt"));
+        assert_eq!("Extra", layout_item_text(9, "Extra"));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::ui::style::{SMALL_MONO_FONT, font, rescale_layout};
+use keympostor::key::Key;
+use keympostor::modifiers::KeyModifiers;
+use keympostor::rule::KeyTransformRules;
+use log::warn;
+use native_windows_gui::stretch::geometry::{Rect, Size};
+use native_windows_gui::stretch::style::Dimension::Points as PT;
+use native_windows_gui::stretch::style::{Dimension as D, FlexDirection};
+use native_windows_gui::{ControlHandle, Event, FlexboxLayout, NwgError, Tab, TextBox, TextInput};
+use std::str::FromStr;
+
+const EXAMPLE_RULE: &str = "[LEFT_SHIFT] CAPS_LOCK : LEFT_WIN → SPACE";
+
+/// Shows parsed structure of the rule being typed.
+#[derive(Default)]
+pub(crate) struct LearnView {
+    layout: FlexboxLayout,
+    rule_input: TextInput,
+    structure_view: TextBox,
+}
+
+impl LearnView {
+    pub(crate) fn build(&mut self, parent: &Tab) -> Result<(), NwgError> {
+        TextInput::builder()
+            .parent(parent)
+            .text(EXAMPLE_RULE)
+            .font(Some(font(SMALL_MONO_FONT)))
+            .build(&mut self.rule_input)?;
+
+        TextBox::builder()
+            .parent(parent)
+            .readonly(true)
+            .font(Some(font(SMALL_MONO_FONT)))
+            .build(&mut self.structure_view)?;
+
+        FlexboxLayout::builder()
+            .parent(parent)
+            .flex_direction(FlexDirection::Column)
+            .child(&self.rule_input)
+            .child_size(Size {
+                width: D::Auto,
+                height: D::Points(28.0),
+            })
+            .child_margin(Rect {
+                start: PT(4.0),
+                end: PT(16.0),
+                top: PT(6.0),
+                bottom: PT(4.0),
+            })
+            .child(&self.structure_view)
+            .child_flex_grow(1.0)
+            .child_margin(Rect {
+                start: PT(4.0),
+                end: PT(16.0),
+                top: PT(4.0),
+                bottom: PT(40.0),
+            })
+            .build(&self.layout)?;
+
+        self.update_structure();
+        Ok(())
+    }
+
+    /// Rescales the layout after the DPI change.
+    pub(crate) fn rescale(&self, factor: f32) {
+        rescale_layout(&self.layout, factor);
+        self.layout
+            .fit()
+            .unwrap_or_else(|e| warn!("Failed to fit layout: {:?}", e));
+    }
+
+    pub(crate) fn handle_event(&self, evt: Event, handle: ControlHandle) {
+        if let Event::OnTextInput = evt {
+            if handle == self.rule_input.handle {
+                self.update_structure();
+            }
+        }
+    }
+
+    fn update_structure(&self) {
+        self.structure_view
+            .set_text(&explain_rule(&self.rule_input.text()));
+    }
+}
+
+/// Returns tree of the rule parts as it is understood by the parser.
+fn explain_rule(text: &str) -> String {
+    let mut s = String::new();
+    if text.trim().is_empty() {
+        return s;
+    }
+
+    let rules = match KeyTransformRules::from_str(text) {
+        Ok(rules) => rules,
+        Err(e) => return format!("Error: {e}"),
+    };
+
+    for (index, rule) in rules.iter().enumerate() {
+        let trigger = &rule.trigger;
+        s.push_str(&format!("Rule {}: {}\r\n", index + 1, rule));
+        if let Some(id) = rule.id {
+            s.push_str(&format!("  ID: {id}\r\n"));
+        }
+        s.push_str(&format!("  Trigger: {}\r\n", trigger));
+        s.push_str(&format!(
+            "    Key: {}{}\r\n",
+            trigger.action.key,
+            key_hint(trigger.action.key)
+        ));
+        s.push_str(&format!(
+            "    Transition: {}\r\n",
+            trigger.action.transition
+        ));
+        match trigger.modifiers {
+            KeyModifiers::Any => s.push_str("    Modifiers: any\r\n"),
+            modifiers => s.push_str(&format!("    Modifiers: {}\r\n", modifiers)),
+        }
+        if !trigger.locks.is_empty() {
+            s.push_str(&format!("    Locks: {}\r\n", trigger.locks));
+        }
+        if trigger.taps > 0 {
+            s.push_str(&format!("    Taps: {}\r\n", trigger.taps));
+        }
+        if trigger.is_alone {
+            s.push_str("    Alone: yes\r\n");
+        }
+        s.push_str("  Actions:\r\n");
+        for item in rule.actions.iter() {
+            s.push_str(&format!("    {}\r\n", item));
+        }
+        if rule.repeat != Default::default() {
+            s.push_str(&format!("  Repeat: {}\r\n", rule.repeat));
+        }
+        if let Some(device) = &rule.device {
+            s.push_str(&format!("  Device: {device}\r\n"));
+        }
+        if let Some(target) = &rule.target {
+            s.push_str(&format!("  Target window: {target}\r\n"));
+        }
+        if rule.is_pass_through {
+            s.push_str("  Pass through: yes\r\n");
+        }
+    }
+
+    s
+}
+
+/// Clarifies which of the keys with the same symbol is bound.
+fn key_hint(key: Key) -> String {
+    let name = key.as_str();
+    if let Some(numpad) = key.numpad_counterpart() {
+        format!(
+            " (top row; {} is numpad, ANY_{name} is both)",
+            numpad.as_str()
+        )
+    } else if name.strip_prefix("NUM_").is_some_and(|d| d.len() == 1) {
+        " (numpad)".to_string()
+    } else {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::learn_view::explain_rule;
+
+    #[test]
+    fn test_explain_rule() {
+        let expected = [
+            "Rule 1: [LEFT_SHIFT] A↓ : B↓ → C↑",
+            "  Trigger: [LEFT_SHIFT] A↓",
+            "    Key: A",
+            "    Transition: ↓",
+            "    Modifiers: [LEFT_SHIFT]",
+            "  Actions:",
+            "    B↓",
+            "    C↑",
+            "Rule 2: [LEFT_SHIFT] A↑ : B↑ → C↑",
+            "  Trigger: [LEFT_SHIFT] A↑",
+            "    Key: A",
+            "    Transition: ↑",
+            "    Modifiers: [LEFT_SHIFT]",
+            "  Actions:",
+            "    B↑",
+            "    C↑",
+            "",
+        ]
+        .join("\r\n");
+
+        assert_eq!(expected, explain_rule("[LEFT_SHIFT] A : B → C↑"));
+    }
+
+    #[test]
+    fn test_explain_rule_digits() {
+        let text = explain_rule("ANY_5↓ : A↓");
+
+        assert!(text.contains("Key: 5 (top row; NUM_5 is numpad, ANY_5 is both)"));
+        assert!(text.contains("Key: NUM_5 (numpad)"));
+    }
+
+    #[test]
+    fn test_explain_rule_error() {
+        assert!(explain_rule("A↓ : BAD↓").starts_with("Error: "));
+        assert_eq!("", explain_rule("  "));
+    }
+}
@@ -1,31 +1,45 @@
+use crate::log_export::export_log;
 use crate::rs;
 use crate::settings::MainWindowSettings;
 use crate::ui::res::RESOURCES;
 use crate::ui::res_ids::{
-    IDS_ACTION, IDS_KEY, IDS_MODIFIERS, IDS_RULE, IDS_SCAN_CODE, IDS_STATUS, IDS_TIME,
-    IDS_TRANSITION, IDS_VIRTUAL_KEY,
+    IDS_ACTION, IDS_CHARACTER, IDS_KEY, IDS_LATENCY, IDS_MODIFIERS, IDS_RULE, IDS_SCAN_CODE,
+    IDS_STATUS, IDS_TIME, IDS_TRANSITION, IDS_VIRTUAL_KEY,
 };
+use crate::ui::style::{colorref, theme};
 use crate::ui::utils::get_list_view_column_width;
-use crate::ui::utils::{scroll_list_view_to_end, set_list_view_item_data};
+use crate::ui::utils::{key_display_name, scroll_list_view_to_end, set_list_view_item_data};
+use crate::util::{get_current_keyboard_layout, get_key_label};
+use keympostor::event::KeyEventRecord;
+use keympostor::event_log::{DEFAULT_LOG_CAPACITY, KeyEventLog};
 use keympostor::notify::KeyEventNotification;
 use keympostor::utils::if_else;
 use native_windows_gui::{
     bind_raw_event_handler, ControlHandle, InsertListViewColumn, ListView, ListViewColumnFlags,
     ListViewExFlags, ListViewStyle, NwgError, Tab,
 };
+use std::cell::RefCell;
 use std::collections::HashMap;
-use windows::Win32::Foundation::COLORREF;
+use std::error::Error;
+use std::path::Path;
 use windows::Win32::UI::Controls::{
     CDDS_ITEMPREPAINT, CDDS_PREPAINT, CDRF_DODEFAULT, CDRF_NEWFONT, CDRF_NOTIFYITEMDRAW,
     NMHDR, NMLVCUSTOMDRAW, NM_CUSTOMDRAW,
 };
 use windows::Win32::UI::WindowsAndMessaging::WM_NOTIFY;
 
-const MAX_LOG_ITEMS: usize = 256;
+const LATENCY_COLUMN: usize = 10;
+const LATENCY_COLUMN_WIDTH: isize = 70;
+
+/* kinds of the records kept in the item data */
+const RECORD_RULE: usize = 1;
+const RECORD_PRIVATE: usize = 2;
+const RECORD_INJECTED: usize = 3;
 
 #[derive(Default)]
 pub(crate) struct LogView {
     list_view: ListView,
+    log: RefCell<KeyEventLog>,
 }
 
 impl LogView {
@@ -101,6 +115,21 @@ impl LogView {
             text: Some(rs!(IDS_STATUS).into()),
         });
 
+        self.list_view.insert_column(InsertListViewColumn {
+            index: Some(9),
+            fmt: Some(ListViewColumnFlags::LEFT),
+            width: Some(50),
+            text: Some(rs!(IDS_CHARACTER).into()),
+        });
+
+        /* hidden unless enabled in the settings */
+        self.list_view.insert_column(InsertListViewColumn {
+            index: Some(LATENCY_COLUMN as i32),
+            fmt: Some(ListViewColumnFlags::RIGHT),
+            width: Some(0),
+            text: Some(rs!(IDS_LATENCY).into()),
+        });
+
         bind_raw_event_handler(
             &parent.handle,
             0x10001,
@@ -122,6 +151,19 @@ impl LogView {
                 }
             }
         }
+
+        if !settings.log_view.show_latency.unwrap_or_default() {
+            self.list_view.set_column_width(LATENCY_COLUMN, 0);
+        } else if get_list_view_column_width(&self.list_view, LATENCY_COLUMN) == 0 {
+            self.list_view
+                .set_column_width(LATENCY_COLUMN, LATENCY_COLUMN_WIDTH);
+        }
+
+        let mut log = self.log.borrow_mut();
+        log.set_capacity(settings.log_view.capacity.unwrap_or(DEFAULT_LOG_CAPACITY));
+        while self.list_view.len() > log.len() {
+            self.list_view.remove_item(0);
+        }
     }
 
     pub(crate) fn update_settings(&self, settings: &mut MainWindowSettings) {
@@ -130,18 +172,30 @@ impl LogView {
             map.insert(i, get_list_view_column_width(&self.list_view, i));
         }
         settings.log_view.columns = Some(map);
+        settings.log_view.capacity = Some(self.log.borrow().capacity());
     }
 
     pub(crate) fn append(&self, notification: &KeyEventNotification) {
         self.list_view.set_redraw(false);
 
-        while self.list_view.len() > MAX_LOG_ITEMS {
-            self.list_view.remove_item(0);
-        }
-
         let event = &notification.event;
         let trigger = &event.trigger;
-        let rule = notification.rule.as_ref();
+        let rule = notification.rule.as_deref();
+
+        if self
+            .log
+            .borrow_mut()
+            .push(KeyEventRecord::new(event, rule))
+            .is_some()
+        {
+            self.list_view.remove_item(0);
+        }
+        let key = trigger.action.key;
+        let key_name = key_display_name(key);
+        let key_label = match get_key_label(key, get_current_keyboard_layout()) {
+            Some(label) if label != key_name => format!("{} {}", key_name, label),
+            _ => key_name,
+        };
 
         self.list_view.insert_items_row(
             None,
@@ -149,7 +203,7 @@ impl LogView {
                 trigger.to_string(),
                 rule.map(|r| r.to_string()).unwrap_or("".to_string()),
                 trigger.modifiers.to_string(),
-                trigger.action.key.to_string(),
+                key_label,
                 trigger.action.transition.to_string(),
                 format!("0x{:02X}", trigger.action.key.vk()),
                 format!("0x{:04X}", trigger.action.key.sc_ext()),
@@ -160,21 +214,30 @@ impl LogView {
                     if_else(event.is_injected, "I", "-"),
                     if_else(event.is_private, "P", "-"),
                 ),
+                event
+                    .character
+                    .as_ref()
+                    .map(|c| c.to_string())
+                    .unwrap_or_default(),
+                notification
+                    .latency
+                    .map(|l| format!("{l} µs"))
+                    .unwrap_or_default(),
             ],
         );
 
-        /* set color (encoded as BGR) for custom item drawing */
-        let color = if rule.is_some() {
-            Some(0xAAAAAA)
+        /* set record kind for custom item drawing with the theme colors */
+        let kind = if rule.is_some() {
+            Some(RECORD_RULE)
         } else if event.is_private {
-            Some(0xCC0000)
+            Some(RECORD_PRIVATE)
         } else if event.is_injected {
-            Some(0xCC00AA)
+            Some(RECORD_INJECTED)
         } else {
             None
         };
-        if let Some(color) = color {
-            set_list_view_item_data(&self.list_view, self.list_view.len() - 1, color)
+        if let Some(kind) = kind {
+            set_list_view_item_data(&self.list_view, self.list_view.len() - 1, kind)
         };
 
         self.list_view.set_redraw(true);
@@ -183,7 +246,13 @@ impl LogView {
     }
 
     pub(crate) fn clear(&self) {
-        self.list_view.clear()
+        self.list_view.clear();
+        self.log.borrow_mut().clear();
+    }
+
+    pub(crate) fn export<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let records: Vec<_> = self.log.borrow().iter().cloned().collect();
+        export_log(path, &records)
     }
 
     fn handle_custom_draw(msg: u32, l_param: isize) -> Option<isize> {
@@ -204,11 +273,15 @@ impl LogView {
         }
 
         if stage == CDDS_ITEMPREPAINT {
-            let item_color = cd.nmcd.lItemlParam.0 as u32;
-            if item_color != 0 {
-                cd.clrText = COLORREF(item_color);
-                return Some(CDRF_NEWFONT as isize);
-            }
+            let theme = theme();
+            let color = match cd.nmcd.lItemlParam.0 as usize {
+                RECORD_RULE => theme.log_rule,
+                RECORD_PRIVATE => theme.log_private,
+                RECORD_INJECTED => theme.log_injected,
+                _ => return Some(CDRF_DODEFAULT as isize),
+            };
+            cd.clrText = colorref(color);
+            return Some(CDRF_NEWFONT as isize);
         }
 
         Some(CDRF_DODEFAULT as isize)
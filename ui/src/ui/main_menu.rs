@@ -1,10 +1,12 @@
 use crate::app::App;
 use crate::layout::{KeyTransformLayout, KeyTransformLayoutList};
 use crate::rs;
+use crate::startup::StartupMethod;
 use crate::ui::layouts_menu::LayoutsMenu;
 use crate::ui::res::RESOURCES;
-use crate::ui::res_ids::IDS_PROCESSING_ENABLED;
-use crate::ui::res_ids::{IDS_CLEAR_LOG, IDS_EXIT, IDS_FILE, IDS_LOGGING_ENABLED};
+use crate::ui::res_ids::{IDS_AUTOSTART, IDS_AUTOSTART_ELEVATED, IDS_PROCESSING_ENABLED};
+use crate::ui::res_ids::IDS_BYPASS_APP;
+use crate::ui::res_ids::{IDS_CLEAR_LOG, IDS_EXIT, IDS_EXPORT_LOG, IDS_FILE, IDS_LOGGING_ENABLED};
 use log::warn;
 use native_windows_gui::{ControlHandle, Event, Menu, MenuItem, MenuSeparator, NwgError, Window};
 
@@ -13,9 +15,13 @@ pub(crate) struct MainMenu {
     menu: Menu,
     layout_menu: LayoutsMenu,
     toggle_processing_enabled_item: MenuItem,
+    toggle_bypass_app_item: MenuItem,
     toggle_logging_enabled_item: MenuItem,
     clear_log_item: MenuItem,
-    separators: [MenuSeparator; 2],
+    export_log_item: MenuItem,
+    autostart_item: MenuItem,
+    autostart_elevated_item: MenuItem,
+    separators: [MenuSeparator; 3],
     exit_app_item: MenuItem,
 }
 
@@ -33,6 +39,12 @@ impl MainMenu {
             .text(rs!(IDS_PROCESSING_ENABLED))
             .build(&mut self.toggle_processing_enabled_item)?;
 
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_BYPASS_APP))
+            .disabled(true)
+            .build(&mut self.toggle_bypass_app_item)?;
+
         MenuSeparator::builder()
             .parent(&self.menu)
             .build(&mut self.separators[0])?;
@@ -47,10 +59,29 @@ impl MainMenu {
             .text(rs!(IDS_CLEAR_LOG))
             .build(&mut self.clear_log_item)?;
 
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_EXPORT_LOG))
+            .build(&mut self.export_log_item)?;
+
         MenuSeparator::builder()
             .parent(&self.menu)
             .build(&mut self.separators[1])?;
 
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_AUTOSTART))
+            .build(&mut self.autostart_item)?;
+
+        MenuItem::builder()
+            .parent(&self.menu)
+            .text(rs!(IDS_AUTOSTART_ELEVATED))
+            .build(&mut self.autostart_elevated_item)?;
+
+        MenuSeparator::builder()
+            .parent(&self.menu)
+            .build(&mut self.separators[2])?;
+
         MenuItem::builder()
             .parent(&self.menu)
             .text(rs!(IDS_EXIT))
@@ -62,14 +93,33 @@ impl MainMenu {
         is_auto_switch_layout_enabled: bool,
         is_processing_enabled: bool,
         is_logging_enabled: bool,
+        is_layouts_trial_active: bool,
         current_layout: &KeyTransformLayout,
     ) {
         self.toggle_processing_enabled_item
             .set_checked(is_processing_enabled);
         self.toggle_logging_enabled_item
             .set_checked(is_logging_enabled);
-        self.layout_menu
-            .update_ui(is_auto_switch_layout_enabled, current_layout);
+        self.layout_menu.update_ui(
+            is_auto_switch_layout_enabled,
+            is_layouts_trial_active,
+            current_layout,
+        );
+    }
+
+    pub(crate) fn update_autostart(&self, method: Option<StartupMethod>) {
+        self.autostart_item
+            .set_checked(method == Some(StartupMethod::RunKey));
+        self.autostart_elevated_item
+            .set_checked(method == Some(StartupMethod::ElevatedTask));
+    }
+
+    /// Shows whether the last application is blacklisted, `None` when there is no one.
+    pub(crate) fn update_bypass_app(&self, is_blacklisted: Option<bool>) {
+        self.toggle_bypass_app_item
+            .set_enabled(is_blacklisted.is_some());
+        self.toggle_bypass_app_item
+            .set_checked(is_blacklisted.unwrap_or_default());
     }
 
     pub(crate) fn build_layouts_menu(&self, layouts: &KeyTransformLayoutList) {
@@ -83,12 +133,20 @@ impl MainMenu {
             Event::OnMenuItemSelected => {
                 if &handle == &self.clear_log_item {
                     app.on_log_view_clear();
+                } else if &handle == &self.export_log_item {
+                    app.on_log_view_export();
                 } else if &handle == &self.exit_app_item {
                     app.on_app_exit();
                 } else if &handle == &self.toggle_processing_enabled_item {
                     app.on_toggle_processing_enabled();
+                } else if &handle == &self.toggle_bypass_app_item {
+                    app.on_toggle_app_blacklisted();
                 } else if &handle == &self.toggle_logging_enabled_item {
                     app.on_toggle_logging_enabled();
+                } else if &handle == &self.autostart_item {
+                    app.on_toggle_autostart(StartupMethod::RunKey);
+                } else if &handle == &self.autostart_elevated_item {
+                    app.on_toggle_autostart(StartupMethod::ElevatedTask);
                 }
             }
             _ => {}
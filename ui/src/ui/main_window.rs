@@ -1,40 +1,77 @@
 use crate::app::App;
 use crate::layout::{KeyTransformLayout, KeyTransformLayoutList};
-use crate::settings::MainWindowSettings;
+use crate::settings::{MainWindowSettings, OverlaySettings, ThemeMode, WindowPlacementSettings};
+use crate::startup::StartupMethod;
+use crate::ui::display::{DEFAULT_DPI, window_dpi};
 use crate::ui::layout_view::LayoutView;
+use crate::ui::learn_view::LearnView;
 use crate::ui::log_view::LogView;
 use crate::ui::main_menu::MainMenu;
+use crate::ui::overlay::Overlay;
 use crate::ui::res::RESOURCES;
-use crate::ui::res_ids::{IDI_ICON_APP, IDS_APP_TITLE, IDS_LAYOUT, IDS_LOG, IDS_NO_PROFILE};
-use crate::ui::style::INFO_LABEL_FONT;
+use crate::ui::res_ids::{
+    IDI_ICON_APP, IDS_APP_TITLE, IDS_LAYOUT, IDS_LEARN, IDS_LOG, IDS_NO_PROFILE, IDS_RULES,
+    IDS_STATS,
+};
+use crate::ui::rules_editor::RulesEditor;
+use crate::ui::stats_view::StatsView;
+use crate::ui::style::{
+    INFO_LABEL_FONT, Theme, apply_theme, font, handle_theme_message, is_system_dark_theme,
+    rescale_fonts, rescale_layout,
+};
 use crate::ui::test_editor::TypeTestEditor;
 use crate::ui::tray::Tray;
-use crate::ui::utils::hwnd;
+use crate::ui::utils::{center_window, hwnd, is_window_on_screen, monitor_topology_id};
 use crate::{r_icon, rs, ui};
+use keympostor::event::KeyEvent;
+use keympostor::health::HookHealth;
 use keympostor::notify::KeyEventNotification;
+use keympostor::scancode_map::ScancodeMap;
+use keympostor::stats::KeyStats;
 use native_windows_gui::stretch::geometry::{Rect, Size};
 use native_windows_gui::stretch::style::Dimension::Points as PT;
 use native_windows_gui::stretch::style::{Dimension as D, FlexDirection};
+use log::{debug, warn};
 use native_windows_gui::{
-    ControlHandle, Event, FlexboxLayout, Label, NwgError, Tab, TabsContainer, Window, WindowFlags,
+    ControlHandle, Event, FileDialog, FileDialogAction, FlexboxLayout, Label, NwgError, Tab,
+    TabsContainer, Window, WindowFlags, bind_raw_event_handler,
 };
-use windows::Win32::Foundation::HWND;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::UI::WindowsAndMessaging::{SWP_NOACTIVATE, SWP_NOZORDER, SetWindowPos};
+
+const LOG_FILE_FILTERS: &str = "CSV(*.csv)|JSON Lines(*.jsonl)";
+const REG_FILE_FILTERS: &str = "Registration Entries(*.reg)";
+const THEME_HANDLER_ID: usize = 0x10002;
 
 #[derive(Default)]
 pub(crate) struct MainWindow {
     window: Window,
     layout: FlexboxLayout,
     tab_log_layout: FlexboxLayout,
-    tab_layouts_layout: FlexboxLayout,
     tab_log: Tab,
     tab_layouts: Tab,
+    tab_learn: Tab,
+    tab_rules: Tab,
+    tab_stats: Tab,
     main_menu: MainMenu,
     tab_container: TabsContainer,
     layout_view: LayoutView,
+    learn_view: LearnView,
+    rules_editor: RulesEditor,
+    stats_view: StatsView,
     log_view: LogView,
     key_event_label: Label,
     test_editor: TypeTestEditor,
     tray: Tray,
+    overlay: Overlay,
+    placements: RefCell<HashMap<String, WindowPlacementSettings>>,
+    monitor_topology: RefCell<String>,
+    dpi: Cell<u32>,
 }
 
 impl MainWindow {
@@ -49,7 +86,7 @@ impl MainWindow {
         Label::builder()
             .parent(&self.window)
             .text("*")
-            .font(Some(&INFO_LABEL_FONT))
+            .font(Some(font(INFO_LABEL_FONT)))
             .build(&mut self.key_event_label)?;
 
         self.test_editor.build(&mut self.window)?;
@@ -70,10 +107,29 @@ impl MainWindow {
             .parent(&self.tab_container)
             .build(&mut self.tab_layouts)?;
 
+        Tab::builder()
+            .text(rs!(IDS_LEARN))
+            .parent(&self.tab_container)
+            .build(&mut self.tab_learn)?;
+
+        Tab::builder()
+            .text(rs!(IDS_RULES))
+            .parent(&self.tab_container)
+            .build(&mut self.tab_rules)?;
+
+        Tab::builder()
+            .text(rs!(IDS_STATS))
+            .parent(&self.tab_container)
+            .build(&mut self.tab_stats)?;
+
         self.main_menu.build(&mut self.window)?;
         self.log_view.build(&mut self.tab_log)?;
         self.layout_view.build(&mut self.tab_layouts)?;
+        self.learn_view.build(&mut self.tab_learn)?;
+        self.rules_editor.build(&mut self.tab_rules)?;
+        self.stats_view.build(&mut self.tab_stats)?;
         self.tray.build(&self.window)?;
+        self.overlay.build(&self.window)?;
 
         /* Layout view */
         FlexboxLayout::builder()
@@ -87,18 +143,6 @@ impl MainWindow {
             })
             .build(&self.tab_log_layout)?;
 
-        /* Layout tab layout */
-        FlexboxLayout::builder()
-            .parent(&self.tab_container)
-            .child(self.layout_view.view())
-            .child_margin(Rect {
-                start: PT(4.0),
-                end: PT(16.0),
-                top: PT(6.0),
-                bottom: PT(40.0),
-            })
-            .build(&self.tab_layouts_layout)?;
-
         /* Main window */
         FlexboxLayout::builder()
             .parent(&self.window)
@@ -118,19 +162,47 @@ impl MainWindow {
                 width: D::Auto,
                 height: D::Points(40.0),
             })
-            .build(&self.layout)
+            .build(&self.layout)?;
+
+        /* windows hosting the controls paint them in the theme colors */
+        for handle in [
+            &self.window.handle,
+            &self.tab_log.handle,
+            &self.tab_layouts.handle,
+            &self.tab_learn.handle,
+            &self.tab_rules.handle,
+            &self.tab_stats.handle,
+        ] {
+            bind_raw_event_handler(handle, THEME_HANDLER_ID, |hwnd, msg, w_param, _| {
+                handle_theme_message(HWND(hwnd as _), msg, w_param)
+            })?;
+        }
+
+        /* controls are built for the default DPI */
+        self.dpi.set(DEFAULT_DPI);
+        self.rescale(window_dpi(self.hwnd()));
+        Ok(())
     }
 
     pub(crate) fn handle_event(&self, app: &App, evt: Event, handle: ControlHandle) {
         self.main_menu.handle_event(app, evt, handle);
         self.tray.handle_event(app, evt, handle);
+        self.overlay.handle_event(evt, handle);
         self.test_editor.handle_event(evt);
+        self.learn_view.handle_event(evt, handle);
+        self.rules_editor.handle_event(app, evt, handle);
+        self.layout_view.handle_event(app, evt, handle);
         match evt {
             Event::OnWindowClose => {
                 if &handle == &self.window.handle {
                     app.on_window_close();
                 }
             }
+            Event::TabsContainerChanged => {
+                if &handle == &self.tab_container.handle {
+                    app.on_tab_changed();
+                }
+            }
             _ => {}
         }
     }
@@ -148,6 +220,7 @@ impl MainWindow {
         is_auto_switch_layout_enabled: bool,
         is_processing_enabled: bool,
         is_logging_enabled: bool,
+        is_layouts_trial_active: bool,
         auto_switch_profile_name: Option<&str>,
         layout: &KeyTransformLayout,
     ) {
@@ -155,6 +228,7 @@ impl MainWindow {
             is_auto_switch_layout_enabled,
             is_processing_enabled,
             is_logging_enabled,
+            is_layouts_trial_active,
             layout,
         );
         self.tray.update_ui(layout);
@@ -163,12 +237,20 @@ impl MainWindow {
     }
 
     pub(crate) fn apply_settings(&self, settings: &MainWindowSettings) {
-        if let Some(position) = settings.position {
-            self.window.set_position(position.0, position.1);
-        }
-        if let Some(size) = settings.size {
-            ui::utils::set_window_size(&self.window, size);
+        self.placements
+            .replace(settings.placements.clone().unwrap_or_default());
+        self.monitor_topology.replace(monitor_topology_id());
+
+        if !self.restore_placement() {
+            if let Some(position) = settings.position {
+                self.window.set_position(position.0, position.1);
+            }
+            if let Some(size) = settings.size {
+                ui::utils::set_window_size(&self.window, size);
+            }
+            self.rescue_off_screen();
         }
+
         if let Some(page) = settings.selected_page {
             self.tab_container.set_selected_tab(page);
         }
@@ -176,15 +258,107 @@ impl MainWindow {
     }
 
     pub(crate) fn update_settings(&self, settings: &mut MainWindowSettings) {
+        self.store_placement();
+
         settings.position = Some(self.window.position());
         settings.size = Some(ui::utils::get_window_size(&self.window));
         settings.selected_page = Some(self.tab_container.selected_tab());
+        settings.placements = Some(self.placements.borrow().clone());
         self.log_view.update_settings(settings);
     }
 
+    pub(crate) fn on_display_change(&self) {
+        let topology = monitor_topology_id();
+        if topology == *self.monitor_topology.borrow() {
+            self.rescue_off_screen();
+            return;
+        }
+
+        /* remember where the window was in the previous configuration before switching */
+        self.store_placement();
+        debug!("Monitors configuration changed: `{}`", topology);
+        self.monitor_topology.replace(topology);
+
+        if !self.restore_placement() {
+            self.rescue_off_screen();
+        }
+    }
+
+    /// Rescales the window moved to the monitor of the other DPI into the suggested rect.
+    pub(crate) fn on_dpi_changed(&self, dpi: u32, rect: &RECT) {
+        debug!("DPI changed: {}", dpi);
+        self.rescale(dpi);
+        unsafe {
+            SetWindowPos(
+                self.hwnd(),
+                None,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            )
+            .unwrap_or_else(|e| warn!("Failed to resize window: {}", e));
+        }
+    }
+
+    fn rescale(&self, dpi: u32) {
+        let factor = dpi as f32 / self.dpi.replace(dpi) as f32;
+        if factor == 1.0 {
+            return;
+        }
+
+        rescale_fonts(self.hwnd(), dpi);
+        rescale_layout(&self.layout, factor);
+        rescale_layout(&self.tab_log_layout, factor);
+        self.layout_view.rescale(factor);
+        self.learn_view.rescale(factor);
+        self.rules_editor.rescale(factor);
+        self.stats_view.rescale(factor);
+        self.layout
+            .fit()
+            .unwrap_or_else(|e| warn!("Failed to fit layout: {:?}", e));
+    }
+
+    /// Applies the theme of the mode. The system mode follows the Windows app theme.
+    pub(crate) fn apply_theme(&self, mode: ThemeMode) {
+        apply_theme(self.hwnd(), Theme::of_mode(mode, is_system_dark_theme()));
+    }
+
+    fn store_placement(&self) {
+        let placement = WindowPlacementSettings {
+            position: self.window.position(),
+            size: ui::utils::get_window_size(&self.window),
+        };
+        self.placements
+            .borrow_mut()
+            .insert(self.monitor_topology.borrow().clone(), placement);
+    }
+
+    fn restore_placement(&self) -> bool {
+        let placements = self.placements.borrow();
+        let Some(placement) = placements.get(self.monitor_topology.borrow().as_str()) else {
+            return false;
+        };
+
+        ui::utils::set_window_size(&self.window, placement.size);
+        self.window
+            .set_position(placement.position.0, placement.position.1);
+        self.rescue_off_screen();
+        true
+    }
+
+    fn rescue_off_screen(&self) {
+        if !is_window_on_screen(&self.window) {
+            debug!("Main window is off screen. Centering");
+            center_window(&self.window);
+        }
+    }
+
     pub(crate) fn set_layouts(&self, layouts: &KeyTransformLayoutList) {
         self.main_menu.build_layouts_menu(layouts);
         self.tray.build_layout_menu(layouts);
+        self.layout_view.update_files();
     }
 
     pub(crate) fn set_visible(&self, visible: bool) {
@@ -199,8 +373,96 @@ impl MainWindow {
         self.log_view.clear()
     }
 
-    pub(crate) fn on_layout_changed(&self, layout: Option<&KeyTransformLayout>) {
-        self.layout_view.update_ui(layout);
+    /// Asks for the file and exports the log into it. Does nothing when cancelled.
+    pub(crate) fn export_log(&self) -> Result<(), Box<dyn Error>> {
+        let mut dialog = FileDialog::default();
+        FileDialog::builder()
+            .action(FileDialogAction::Save)
+            .filters(LOG_FILE_FILTERS)
+            .build(&mut dialog)?;
+
+        if !dialog.run(Some(&self.window)) {
+            return Ok(());
+        }
+
+        let mut path = PathBuf::from(dialog.get_selected_item()?);
+        if path.extension().is_none() {
+            path.set_extension("csv");
+        }
+        self.log_view.export(path)
+    }
+
+    /// Asks for the file and saves the registry patch setting the scancode map into it.
+    /// Does nothing when cancelled.
+    pub(crate) fn export_scancode_map(&self, map: &ScancodeMap) -> Result<(), Box<dyn Error>> {
+        let mut dialog = FileDialog::default();
+        FileDialog::builder()
+            .action(FileDialogAction::Save)
+            .filters(REG_FILE_FILTERS)
+            .build(&mut dialog)?;
+
+        if !dialog.run(Some(&self.window)) {
+            return Ok(());
+        }
+
+        let mut path = PathBuf::from(dialog.get_selected_item()?);
+        if path.extension().is_none() {
+            path.set_extension("reg");
+        }
+        fs::write(path, map.to_reg_file())?;
+        Ok(())
+    }
+
+    pub(crate) fn on_layout_changed(
+        &self,
+        layout: Option<&KeyTransformLayout>,
+        disabled_rules: Option<&BTreeSet<u32>>,
+    ) {
+        self.layout_view.update_ui(layout, disabled_rules);
+        self.rules_editor.update_ui(layout);
+        if let Some(layout) = layout {
+            self.overlay.show(&layout.title);
+        }
+    }
+
+    pub(crate) fn on_rules_enabled_changed(
+        &self,
+        layout: &KeyTransformLayout,
+        disabled_rules: Option<&BTreeSet<u32>>,
+    ) {
+        self.layout_view.update_ui(Some(layout), disabled_rules);
+    }
+
+    pub(crate) fn apply_overlay_settings(&self, settings: Option<&OverlaySettings>) {
+        self.overlay.apply_settings(settings);
+    }
+
+    pub(crate) fn set_autostart(&self, method: Option<StartupMethod>) {
+        self.main_menu.update_autostart(method);
+    }
+
+    pub(crate) fn set_app_blacklisted(&self, is_blacklisted: Option<bool>) {
+        self.main_menu.update_bypass_app(is_blacklisted);
+    }
+
+    pub(crate) fn set_hook_health(&self, health: HookHealth) {
+        self.tray.set_hook_health(health);
+    }
+
+    pub(crate) fn show_hook_error(&self, text: &str) {
+        self.tray.show_hook_error(text);
+    }
+
+    pub(crate) fn show_rules_warning(&self, text: &str) {
+        self.tray.show_rules_warning(text);
+    }
+
+    pub(crate) fn update_stats(&self, stats: Option<&KeyStats>) {
+        self.stats_view.update(stats);
+    }
+
+    pub(crate) fn on_key_captured(&self, event: &KeyEvent) {
+        self.rules_editor.on_key_captured(event);
     }
 
     pub(crate) fn on_key_hook_notify(&self, notification: &KeyEventNotification) {
@@ -0,0 +1,130 @@
+use crate::settings::OverlaySettings;
+use crate::ui::display::{DEFAULT_DPI, active_monitor, scale};
+use crate::ui::utils::hwnd;
+use log::warn;
+use native_windows_gui::{
+    ControlHandle, Event, Font, HTextAlign, Label, NwgError, Window, WindowFlags,
+};
+use std::cell::{Cell, RefCell};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{
+    HWND_TOPMOST, KillTimer, SW_HIDE, SWP_NOACTIVATE, SWP_SHOWWINDOW, SetTimer, SetWindowPos,
+    ShowWindow, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+};
+
+const TIMER_ID: usize = 19720;
+const MARGIN: i32 = 48;
+
+/// Borderless topmost window briefly showing the active layout name.
+#[derive(Default)]
+pub(crate) struct Overlay {
+    window: Window,
+    label: Label,
+    font: RefCell<Font>,
+    font_dpi: Cell<u32>,
+    owner: RefCell<HWND>,
+    settings: RefCell<Option<OverlaySettings>>,
+}
+
+impl Overlay {
+    pub(crate) fn build(&mut self, parent: &Window) -> Result<(), NwgError> {
+        Window::builder()
+            .parent(Some(parent))
+            .flags(WindowFlags::POPUP)
+            .ex_flags((WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE).0)
+            .build(&mut self.window)?;
+
+        Label::builder()
+            .parent(&self.window)
+            .h_align(HTextAlign::Center)
+            .background_color(Some([255, 255, 225]))
+            .build(&mut self.label)?;
+
+        self.owner.replace(hwnd(parent.handle));
+        Ok(())
+    }
+
+    /// Applies settings. Overlay is not shown when there are no settings.
+    pub(crate) fn apply_settings(&self, settings: Option<&OverlaySettings>) {
+        if let Some(settings) = settings {
+            self.update_font(settings, DEFAULT_DPI);
+        }
+        self.settings.replace(settings.cloned());
+    }
+
+    /// Builds the font of the size scaled to the DPI of the monitor showing the overlay.
+    fn update_font(&self, settings: &OverlaySettings, dpi: u32) {
+        let mut font = Font::default();
+        Font::builder()
+            .family(&settings.font_family)
+            .size(scale(settings.font_size as i32, dpi) as u32)
+            .build(&mut font)
+            .unwrap_or_else(|e| warn!("Failed to build overlay font: {}", e));
+        self.label.set_font(Some(&font));
+        self.font.replace(font);
+        self.font_dpi.set(dpi);
+    }
+
+    pub(crate) fn show(&self, text: &str) {
+        let settings = self.settings.borrow();
+        let Some(settings) = settings.as_ref() else {
+            return;
+        };
+
+        /* approximate text extent is enough for a short layout title */
+        let size = (
+            (text.chars().count() as i32 + 2) * settings.font_size as i32 * 3 / 5,
+            settings.font_size as i32 * 2,
+        );
+        let Some(monitor) = active_monitor() else {
+            return;
+        };
+        if self.font_dpi.get() != monitor.dpi {
+            self.update_font(settings, monitor.dpi);
+        }
+        let (x, y) = monitor.place(settings.position, size, MARGIN);
+        let size = (monitor.scale(size.0), monitor.scale(size.1));
+
+        self.label.set_text(text);
+        self.label.set_size(size.0 as u32, size.1 as u32);
+        unsafe {
+            SetWindowPos(
+                hwnd(self.window.handle),
+                Some(HWND_TOPMOST),
+                x,
+                y,
+                size.0,
+                size.1,
+                SWP_NOACTIVATE | SWP_SHOWWINDOW,
+            )
+            .unwrap_or_else(|e| warn!("Failed to show overlay: {}", e));
+            SetTimer(
+                Some(*self.owner.borrow()),
+                TIMER_ID,
+                settings.duration,
+                None,
+            );
+        }
+    }
+
+    pub(crate) fn handle_event(&self, evt: Event, handle: ControlHandle) {
+        if let Event::OnTimerTick = evt {
+            if let Some((_, timer_id)) = handle.timer() {
+                if timer_id == TIMER_ID as u32 {
+                    self.hide();
+                }
+            }
+        }
+    }
+
+    fn hide(&self) {
+        unsafe {
+            KillTimer(Some(*self.owner.borrow()), TIMER_ID).unwrap_or_else(|e| {
+                if e.code().is_err() {
+                    warn!("Failed to kill overlay timer: {}", e);
+                }
+            });
+            let _ = ShowWindow(hwnd(self.window.handle), SW_HIDE);
+        }
+    }
+}
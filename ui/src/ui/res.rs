@@ -50,18 +50,31 @@ impl Resources {
             .string(res_id as u32)
             .expect(&format!("Unable to read resource string:{res_id}"))
     }
+
+    /// Returns the string or `None` if there is no such one or it is empty.
+    pub(crate) fn find_string(&self, res_id: usize) -> Option<String> {
+        self.embed.string(res_id as u32).filter(|s| !s.is_empty())
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::ui::res_ids::{IDI_ICON_APP, IDS_APP_TITLE};
+    use crate::ui::res_ids::{IDI_ICON_APP, IDS_APP_TITLE, IDS_KEY_NAMES};
     use crate::ui::res::RESOURCES;
+    use keympostor::key::Key;
 
     #[test]
     fn test_rs() {
         assert_eq!("Keympostor", rs!(IDS_APP_TITLE));
     }
 
+    #[test]
+    fn test_find_string() {
+        let find = |key: Key| RESOURCES.with(|r| r.find_string(IDS_KEY_NAMES + key as usize));
+        assert!(find(Key::Up).is_some());
+        assert_eq!(None, find(Key::A));
+    }
+
     #[test]
     fn test_r_icon() {
         assert_ne!(std::ptr::null_mut(), r_icon!(IDI_ICON_APP).handle);
@@ -25,3 +25,73 @@ pub(crate) const IDS_APP_ALREADY_RUNNING: usize = 1023;
 pub(crate) const IDS_FAILED_LOAD_SETTINGS: usize = 1024;
 pub(crate) const IDS_FAILED_LOAD_LAYOUTS: usize = 1025;
 pub(crate) const IDS_SETTINGS: usize = 1026;
+pub(crate) const IDS_TRY_RELOAD_LAYOUTS: usize = 1027;
+pub(crate) const IDS_KEEP_LAYOUTS: usize = 1028;
+pub(crate) const IDS_CONFIRM_CLOSE: usize = 1029;
+pub(crate) const IDS_CONFIRM_EXIT: usize = 1030;
+pub(crate) const IDS_ENABLE_PROCESSING: usize = 1031;
+pub(crate) const IDS_DISABLE_PROCESSING: usize = 1032;
+pub(crate) const IDS_NEXT_LAYOUT: usize = 1033;
+pub(crate) const IDS_CHECK_LAYOUT: usize = 1034;
+pub(crate) const IDS_LAYOUT_CHECK_PASSED: usize = 1035;
+pub(crate) const IDS_LEARN: usize = 1036;
+pub(crate) const IDS_IMPORT_LAYOUT: usize = 1037;
+pub(crate) const IDS_CONFIRM_IMPORT_LAYOUT: usize = 1038;
+pub(crate) const IDS_FAILED_IMPORT_LAYOUT: usize = 1039;
+pub(crate) const IDS_LAYOUT_IMPORTED: usize = 1040;
+pub(crate) const IDS_RULES: usize = 1041;
+pub(crate) const IDS_ID: usize = 1042;
+pub(crate) const IDS_TRIGGER: usize = 1043;
+pub(crate) const IDS_ACTIONS: usize = 1044;
+pub(crate) const IDS_CAPTURE_KEY: usize = 1045;
+pub(crate) const IDS_PRESS_KEY: usize = 1046;
+pub(crate) const IDS_ADD: usize = 1047;
+pub(crate) const IDS_UPDATE: usize = 1048;
+pub(crate) const IDS_REMOVE: usize = 1049;
+pub(crate) const IDS_SAVE: usize = 1050;
+pub(crate) const IDS_FAILED_SAVE_LAYOUT: usize = 1051;
+pub(crate) const IDS_EXPORT_LOG: usize = 1052;
+pub(crate) const IDS_FAILED_EXPORT_LOG: usize = 1053;
+pub(crate) const IDS_TRACE: usize = 1054;
+pub(crate) const IDS_AUTOSTART: usize = 1055;
+pub(crate) const IDS_AUTOSTART_ELEVATED: usize = 1056;
+pub(crate) const IDS_FAILED_AUTOSTART: usize = 1057;
+pub(crate) const IDS_HOOK_BLOCKED: usize = 1058;
+pub(crate) const IDS_RELAUNCH_ELEVATED: usize = 1059;
+pub(crate) const IDS_FAILED_RELAUNCH: usize = 1060;
+pub(crate) const IDS_CHARACTER: usize = 1061;
+pub(crate) const IDS_EXPORT_SCANCODE_MAP: usize = 1062;
+pub(crate) const IDS_IMPORT_SCANCODE_MAP: usize = 1063;
+pub(crate) const IDS_FAILED_EXPORT_SCANCODE_MAP: usize = 1064;
+pub(crate) const IDS_FAILED_IMPORT_SCANCODE_MAP: usize = 1065;
+pub(crate) const IDS_NO_SCANCODE_MAPPINGS: usize = 1066;
+pub(crate) const IDS_NO_SCANCODE_MAP: usize = 1067;
+pub(crate) const IDS_RULES_LOOP: usize = 1068;
+pub(crate) const IDS_STATS: usize = 1069;
+pub(crate) const IDS_STATS_DISABLED: usize = 1070;
+pub(crate) const IDS_LATENCY: usize = 1071;
+pub(crate) const IDS_BYPASS_APP: usize = 1072;
+pub(crate) const IDS_HOOK_FAILED: usize = 1073;
+pub(crate) const IDS_NEW: usize = 1074;
+pub(crate) const IDS_DUPLICATE: usize = 1075;
+pub(crate) const IDS_RENAME: usize = 1076;
+pub(crate) const IDS_DELETE: usize = 1077;
+pub(crate) const IDS_EDIT: usize = 1078;
+pub(crate) const IDS_NAME: usize = 1079;
+pub(crate) const IDS_TITLE: usize = 1080;
+pub(crate) const IDS_CONFIRM_DELETE_LAYOUT: usize = 1081;
+pub(crate) const IDS_FAILED_MANAGE_LAYOUT: usize = 1082;
+pub(crate) const IDS_LAYOUT_IN_USE: usize = 1083;
+pub(crate) const IDS_UNDO: usize = 1084;
+pub(crate) const IDS_REDO: usize = 1085;
+pub(crate) const IDS_SETUP: usize = 1086;
+pub(crate) const IDS_SETUP_LAYOUT: usize = 1087;
+pub(crate) const IDS_SETUP_AUTOSTART: usize = 1088;
+pub(crate) const IDS_SETUP_TOGGLE_HOT_KEY: usize = 1089;
+pub(crate) const IDS_BACK: usize = 1090;
+pub(crate) const IDS_NEXT: usize = 1091;
+pub(crate) const IDS_FINISH: usize = 1092;
+pub(crate) const IDS_NO_AUTOSTART: usize = 1093;
+pub(crate) const IDS_NO_HOT_KEY: usize = 1094;
+pub(crate) const IDS_FAILED_SETUP: usize = 1095;
+pub(crate) const IDS_KEY_NAMES: usize = 2000;
@@ -0,0 +1,634 @@
+use crate::app::App;
+use crate::layout::KeyTransformLayout;
+use crate::rs;
+use crate::ui::res::RESOURCES;
+use crate::ui::res_ids::{
+    IDS_ACTIONS, IDS_ADD, IDS_CAPTURE_KEY, IDS_ID, IDS_MODIFIERS, IDS_PRESS_KEY, IDS_REDO,
+    IDS_REMOVE, IDS_SAVE, IDS_TRACE, IDS_TRIGGER, IDS_UNDO, IDS_UPDATE,
+};
+use crate::ui::rules_history::{RulesEdit, RulesHistory};
+use crate::ui::style::{SMALL_MONO_FONT, font, rescale_layout};
+use crate::ui::utils::set_tooltip_max_width;
+use crate::util::is_key_pressed;
+use keympostor::error::KeyError;
+use keympostor::event::KeyEvent;
+use keympostor::explain::explain_rule;
+use keympostor::rule::{KeyTransformRule, KeyTransformRules};
+use keympostor::trigger::KeyTrigger;
+use log::warn;
+use native_windows_gui::stretch::geometry::{Rect, Size};
+use native_windows_gui::stretch::style::Dimension::Points as PT;
+use native_windows_gui::stretch::style::{Dimension as D, FlexDirection};
+use native_windows_gui::{
+    Button, ControlHandle, Event, FlexboxLayout, InsertListViewColumn, Label, ListView,
+    ListViewColumnFlags, ListViewExFlags, ListViewFlags, ListViewStyle, NwgError, Tab, TextBox,
+    TextInput, Tooltip,
+};
+use std::cell::{Cell, RefCell};
+use std::str::FromStr;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::Input::KeyboardAndMouse::{VK_CONTROL, VK_Y, VK_Z};
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+pub(crate) const WM_KEY_CAPTURED: u32 = 88479;
+
+/// Key press the hook captured for the trigger being edited.
+pub(crate) struct CapturedKey(pub(crate) KeyEvent);
+
+impl CapturedKey {
+    /// Takes ownership of the event posted from the hook procedure.
+    pub(crate) fn from_message(msg: u32, l_param: isize) -> Option<Self> {
+        if msg == WM_KEY_CAPTURED {
+            Some(*unsafe { Box::from_raw(l_param as *mut Self) })
+        } else {
+            None
+        }
+    }
+
+    /// Posts the event so that the editor is updated out of the hook procedure.
+    pub(crate) fn post(self, hwnd: HWND) {
+        let raw_ptr = Box::into_raw(Box::new(self));
+        unsafe {
+            if let Err(e) = PostMessageW(
+                Some(hwnd),
+                WM_KEY_CAPTURED,
+                WPARAM(0),
+                LPARAM(raw_ptr as isize),
+            ) {
+                warn!("Failed to post captured key: {}", e);
+                drop(Box::from_raw(raw_ptr));
+            }
+        }
+    }
+}
+
+/// Grid of the current layout rules with the row editor. Edits of the rules are undone with
+/// Ctrl+Z and redone with Ctrl+Y until they are saved.
+#[derive(Default)]
+pub(crate) struct RulesEditor {
+    layout: FlexboxLayout,
+    inputs_layout: FlexboxLayout,
+    buttons_layout: FlexboxLayout,
+    list_view: ListView,
+    /// Plain language description of the selected rule.
+    list_tooltip: Tooltip,
+    id_input: TextInput,
+    modifiers_input: TextInput,
+    trigger_input: TextInput,
+    actions_input: TextInput,
+    capture_button: Button,
+    add_button: Button,
+    update_button: Button,
+    remove_button: Button,
+    undo_button: Button,
+    redo_button: Button,
+    save_button: Button,
+    trace_button: Button,
+    status_label: Label,
+    trace_view: TextBox,
+    rules: RefCell<Vec<KeyTransformRule>>,
+    history: RefCell<RulesHistory>,
+    is_capturing: Cell<bool>,
+}
+
+impl RulesEditor {
+    pub(crate) fn build(&mut self, parent: &Tab) -> Result<(), NwgError> {
+        ListView::builder()
+            .parent(parent)
+            .list_style(ListViewStyle::Detailed)
+            .ex_flags(ListViewExFlags::GRID | ListViewExFlags::FULL_ROW_SELECT)
+            .flags(
+                ListViewFlags::VISIBLE
+                    | ListViewFlags::TAB_STOP
+                    | ListViewFlags::SINGLE_SELECTION
+                    | ListViewFlags::ALWAYS_SHOW_SELECTION,
+            )
+            .build(&mut self.list_view)?;
+
+        self.list_view.set_headers_enabled(true);
+
+        Tooltip::builder()
+            .register(&self.list_view, "")
+            .build(&mut self.list_tooltip)?;
+        set_tooltip_max_width(&self.list_tooltip, 480);
+
+        for (index, (text, width)) in [
+            (rs!(IDS_ID), 40),
+            (rs!(IDS_MODIFIERS), 160),
+            (rs!(IDS_TRIGGER), 120),
+            (rs!(IDS_ACTIONS), 360),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            self.list_view.insert_column(InsertListViewColumn {
+                index: Some(index as i32),
+                fmt: Some(ListViewColumnFlags::LEFT),
+                width: Some(width),
+                text: Some(text.into()),
+            });
+        }
+
+        for input in [
+            &mut self.id_input,
+            &mut self.modifiers_input,
+            &mut self.trigger_input,
+            &mut self.actions_input,
+        ] {
+            TextInput::builder()
+                .parent(parent)
+                .font(Some(font(SMALL_MONO_FONT)))
+                .build(input)?;
+        }
+
+        for (button, text) in [
+            (&mut self.capture_button, rs!(IDS_CAPTURE_KEY)),
+            (&mut self.add_button, rs!(IDS_ADD)),
+            (&mut self.update_button, rs!(IDS_UPDATE)),
+            (&mut self.remove_button, rs!(IDS_REMOVE)),
+            (&mut self.undo_button, rs!(IDS_UNDO)),
+            (&mut self.redo_button, rs!(IDS_REDO)),
+            (&mut self.save_button, rs!(IDS_SAVE)),
+            (&mut self.trace_button, rs!(IDS_TRACE)),
+        ] {
+            Button::builder().parent(parent).text(text).build(button)?;
+        }
+        self.update_history_buttons();
+
+        Label::builder()
+            .parent(parent)
+            .text("")
+            .build(&mut self.status_label)?;
+
+        TextBox::builder()
+            .parent(parent)
+            .readonly(true)
+            .font(Some(font(SMALL_MONO_FONT)))
+            .build(&mut self.trace_view)?;
+
+        /* Row editor */
+        FlexboxLayout::builder()
+            .parent(parent)
+            .child(&self.id_input)
+            .child_size(Size {
+                width: D::Points(40.0),
+                height: D::Points(24.0),
+            })
+            .child(&self.modifiers_input)
+            .child_flex_grow(1.0)
+            .child(&self.trigger_input)
+            .child_flex_grow(1.0)
+            .child(&self.actions_input)
+            .child_flex_grow(3.0)
+            .build_partial(&self.inputs_layout)?;
+
+        /* Buttons */
+        let button_size = Size {
+            width: D::Points(90.0),
+            height: D::Points(26.0),
+        };
+        FlexboxLayout::builder()
+            .parent(parent)
+            .child(&self.capture_button)
+            .child_size(button_size)
+            .child(&self.add_button)
+            .child_size(button_size)
+            .child(&self.update_button)
+            .child_size(button_size)
+            .child(&self.remove_button)
+            .child_size(button_size)
+            .child(&self.undo_button)
+            .child_size(button_size)
+            .child(&self.redo_button)
+            .child_size(button_size)
+            .child(&self.save_button)
+            .child_size(button_size)
+            .child(&self.trace_button)
+            .child_size(button_size)
+            .child(&self.status_label)
+            .child_flex_grow(1.0)
+            .build_partial(&self.buttons_layout)?;
+
+        FlexboxLayout::builder()
+            .parent(parent)
+            .flex_direction(FlexDirection::Column)
+            .child(&self.list_view)
+            .child_flex_grow(1.0)
+            .child_margin(Rect {
+                start: PT(4.0),
+                end: PT(16.0),
+                top: PT(6.0),
+                bottom: PT(4.0),
+            })
+            .child(&self.trace_view)
+            .child_size(Size {
+                width: D::Auto,
+                height: D::Points(110.0),
+            })
+            .child_margin(Rect {
+                start: PT(4.0),
+                end: PT(16.0),
+                top: PT(0.0),
+                bottom: PT(4.0),
+            })
+            .child_layout(&self.inputs_layout)
+            .child_size(Size {
+                width: D::Auto,
+                height: D::Points(32.0),
+            })
+            .child_margin(Rect {
+                start: PT(4.0),
+                end: PT(16.0),
+                top: PT(0.0),
+                bottom: PT(0.0),
+            })
+            .child_layout(&self.buttons_layout)
+            .child_size(Size {
+                width: D::Auto,
+                height: D::Points(34.0),
+            })
+            .child_margin(Rect {
+                start: PT(4.0),
+                end: PT(16.0),
+                top: PT(0.0),
+                bottom: PT(40.0),
+            })
+            .build(&self.layout)
+    }
+
+    /// Rescales the layout after the DPI change.
+    pub(crate) fn rescale(&self, factor: f32) {
+        rescale_layout(&self.layout, factor);
+        rescale_layout(&self.inputs_layout, factor);
+        rescale_layout(&self.buttons_layout, factor);
+        self.layout
+            .fit()
+            .unwrap_or_else(|e| warn!("Failed to fit layout: {:?}", e));
+    }
+
+    pub(crate) fn handle_event(&self, app: &App, evt: Event, handle: ControlHandle) {
+        match evt {
+            Event::OnButtonClick => {
+                if handle == self.capture_button.handle {
+                    let is_capturing = !self.is_capturing.get();
+                    app.set_key_capture(is_capturing);
+                    self.set_capturing(is_capturing);
+                } else if handle == self.add_button.handle {
+                    self.on_add();
+                } else if handle == self.update_button.handle {
+                    self.on_update();
+                } else if handle == self.remove_button.handle {
+                    self.on_remove();
+                } else if handle == self.undo_button.handle {
+                    self.on_undo();
+                } else if handle == self.redo_button.handle {
+                    self.on_redo();
+                } else if handle == self.save_button.handle {
+                    /* saving reloads the rules into the editor */
+                    let rules = KeyTransformRules::from(self.rules.borrow().clone());
+                    app.on_save_layout_rules(rules);
+                } else if handle == self.trace_button.handle {
+                    self.on_trace();
+                }
+            }
+            Event::OnListViewItemChanged => {
+                if handle == self.list_view.handle {
+                    self.on_select();
+                }
+            }
+            Event::OnKeyPress => {
+                /* the text inputs keep their own undo */
+                if handle == self.list_view.handle || self.is_button(handle) {
+                    self.on_key_press();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn update_ui(&self, layout: Option<&KeyTransformLayout>) {
+        self.rules.replace(
+            layout
+                .map(|l| l.rules.iter().cloned().collect())
+                .unwrap_or_default(),
+        );
+        self.history.borrow_mut().clear();
+        self.status_label.set_text("");
+        self.update_list();
+        self.update_history_buttons();
+    }
+
+    /// Puts the captured key into the trigger fields.
+    pub(crate) fn on_key_captured(&self, event: &KeyEvent) {
+        if !self.is_capturing.get() {
+            return;
+        }
+
+        let (modifiers, trigger) = split_trigger(&event.trigger.to_string());
+        self.modifiers_input.set_text(&modifiers);
+        self.trigger_input.set_text(&trigger);
+        self.set_capturing(false);
+    }
+
+    fn set_capturing(&self, is_capturing: bool) {
+        self.is_capturing.set(is_capturing);
+        self.capture_button.set_text(if is_capturing {
+            rs!(IDS_PRESS_KEY)
+        } else {
+            rs!(IDS_CAPTURE_KEY)
+        });
+    }
+
+    fn on_select(&self) {
+        let Some(index) = self.list_view.selected_item() else {
+            return;
+        };
+        if let Some(rule) = self.rules.borrow().get(index) {
+            let [id, modifiers, trigger, actions] = rule_columns(rule);
+            self.id_input.set_text(&id);
+            self.modifiers_input.set_text(&modifiers);
+            self.trigger_input.set_text(&trigger);
+            self.actions_input.set_text(&actions);
+            self.list_tooltip.set_text(
+                &self.list_view.handle,
+                &explain_rule(rule).replace('\n', "\r\n"),
+            );
+        }
+    }
+
+    fn on_add(&self) {
+        if let Some(parsed) = self.parse_inputs() {
+            let edit = RulesEdit::add(&self.rules.borrow(), parsed);
+            self.execute(edit);
+        }
+    }
+
+    fn on_update(&self) {
+        let Some(index) = self.list_view.selected_item() else {
+            return;
+        };
+        if let Some(parsed) = self.parse_inputs() {
+            let edit = RulesEdit::update(&self.rules.borrow(), index, parsed);
+            if let Some(edit) = edit {
+                self.execute(edit);
+            }
+        }
+    }
+
+    fn on_remove(&self) {
+        let Some(index) = self.list_view.selected_item() else {
+            return;
+        };
+        let edit = RulesEdit::remove(&self.rules.borrow(), index);
+        if let Some(edit) = edit {
+            self.execute(edit);
+        }
+    }
+
+    fn execute(&self, edit: RulesEdit) {
+        self.history
+            .borrow_mut()
+            .execute(edit, &mut self.rules.borrow_mut());
+        self.update_list();
+        self.update_history_buttons();
+    }
+
+    fn on_undo(&self) {
+        let index = self.history.borrow_mut().undo(&mut self.rules.borrow_mut());
+        self.on_history_changed(index);
+    }
+
+    fn on_redo(&self) {
+        let index = self.history.borrow_mut().redo(&mut self.rules.borrow_mut());
+        self.on_history_changed(index);
+    }
+
+    /// Shows the rules restored by undo or redo selecting the first affected one.
+    fn on_history_changed(&self, index: Option<usize>) {
+        let Some(index) = index else {
+            return;
+        };
+        self.update_list();
+        self.update_history_buttons();
+        if index < self.rules.borrow().len() {
+            self.list_view.select_item(index, true);
+        }
+    }
+
+    fn on_key_press(&self) {
+        if !is_key_pressed(VK_CONTROL) {
+            return;
+        }
+        if is_key_pressed(VK_Z) {
+            self.on_undo();
+        } else if is_key_pressed(VK_Y) {
+            self.on_redo();
+        }
+    }
+
+    fn is_button(&self, handle: ControlHandle) -> bool {
+        [
+            &self.capture_button,
+            &self.add_button,
+            &self.update_button,
+            &self.remove_button,
+            &self.undo_button,
+            &self.redo_button,
+            &self.save_button,
+            &self.trace_button,
+        ]
+        .iter()
+        .any(|button| button.handle == handle)
+    }
+
+    fn update_history_buttons(&self) {
+        let history = self.history.borrow();
+        self.undo_button.set_enabled(history.can_undo());
+        self.redo_button.set_enabled(history.can_redo());
+    }
+
+    /// Shows how the edited rules are looked up for the event of the modifiers and trigger inputs.
+    fn on_trace(&self) {
+        match trace_event(&self.modifiers_input.text(), &self.trigger_input.text()) {
+            Ok(event) => {
+                let rules = KeyTransformRules::from(self.rules.borrow().clone());
+                let text = rules.trace(&event).to_string();
+                self.trace_view.set_text(&text.replace('\n', "\r\n"));
+                self.status_label.set_text("");
+            }
+            Err(e) => self.status_label.set_text(&e.to_string()),
+        }
+    }
+
+    fn parse_inputs(&self) -> Option<Vec<KeyTransformRule>> {
+        match parse_row(
+            &self.id_input.text(),
+            &self.modifiers_input.text(),
+            &self.trigger_input.text(),
+            &self.actions_input.text(),
+        ) {
+            Ok(rules) => {
+                self.status_label.set_text("");
+                Some(rules)
+            }
+            Err(e) => {
+                self.status_label.set_text(&e.to_string());
+                None
+            }
+        }
+    }
+
+    fn update_list(&self) {
+        self.list_view.clear();
+        for rule in self.rules.borrow().iter() {
+            self.list_view.insert_items_row(None, &rule_columns(rule));
+        }
+    }
+}
+
+/// Splits trigger text into the bracketed modifiers and the key with transition.
+fn split_trigger(text: &str) -> (String, String) {
+    match text.split_once("] ") {
+        Some((modifiers, key)) if modifiers.starts_with('[') => {
+            (format!("{modifiers}]"), key.to_string())
+        }
+        _ => (String::new(), text.to_string()),
+    }
+}
+
+/// Returns ID, modifiers, trigger and actions columns of the rule.
+fn rule_columns(rule: &KeyTransformRule) -> [String; 4] {
+    let (modifiers, trigger) = split_trigger(&rule.trigger.to_string());
+    let text = rule.to_string();
+    let actions = text
+        .split_once(" : ")
+        .map(|(_, actions)| actions)
+        .unwrap_or_default();
+
+    [
+        rule.id.map(|id| id.to_string()).unwrap_or_default(),
+        modifiers,
+        trigger,
+        actions.to_string(),
+    ]
+}
+
+/// Parses edited row. Single row may expand into several rules.
+fn parse_row(
+    id: &str,
+    modifiers: &str,
+    trigger: &str,
+    actions: &str,
+) -> Result<Vec<KeyTransformRule>, KeyError> {
+    let mut text = String::new();
+    if !id.trim().is_empty() {
+        text.push_str(&format!("#{} ", id.trim()));
+    }
+    let modifiers = modifiers.trim();
+    if !modifiers.is_empty() {
+        if modifiers.starts_with('[') {
+            text.push_str(&format!("{modifiers} "));
+        } else {
+            text.push_str(&format!("[{modifiers}] "));
+        }
+    }
+    text.push_str(&format!("{} : {}", trigger.trim(), actions.trim()));
+
+    Ok(KeyTransformRules::from_str(&text)?
+        .iter()
+        .cloned()
+        .collect())
+}
+
+/// Builds synthetic event of the trigger. No modifiers means that none of them is pressed.
+fn trace_event(modifiers: &str, trigger: &str) -> Result<KeyEvent, KeyError> {
+    let modifiers = modifiers.trim();
+    let text = match modifiers {
+        "" => format!("[] {}", trigger.trim()),
+        m if m.starts_with('[') => format!("{m} {}", trigger.trim()),
+        m => format!("[{m}] {}", trigger.trim()),
+    };
+    let trigger = KeyTrigger::from_str(&text)?;
+
+    Ok(KeyEvent {
+        locks: trigger.locks,
+        taps: trigger.taps,
+        is_alone: trigger.is_alone,
+        trigger,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::rules_editor::{parse_row, rule_columns, split_trigger, trace_event};
+    use keympostor::key::Key;
+    use keympostor::key_rule;
+    use keympostor::modifiers::KeyLocks;
+    use keympostor::rule::KeyTransformRule;
+    use keympostor::trigger::KeyTrigger;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_split_trigger() {
+        assert_eq!(
+            ("[LEFT_SHIFT]".to_string(), "A↓".to_string()),
+            split_trigger("[LEFT_SHIFT] A↓")
+        );
+        assert_eq!(("[]".to_string(), "A↓".to_string()), split_trigger("[] A↓"));
+        assert_eq!((String::new(), "A↓".to_string()), split_trigger("A↓"));
+    }
+
+    #[test]
+    fn test_rule_columns() {
+        assert_eq!(
+            [
+                "3".to_string(),
+                "[LEFT_CTRL]".to_string(),
+                "A↓".to_string(),
+                "B↓ → B↑ | NO_REPEAT".to_string(),
+            ],
+            rule_columns(&key_rule!("#3 [LEFT_CTRL] A↓ : B↓ → B↑ | NO_REPEAT"))
+        );
+        assert_eq!(
+            [
+                String::new(),
+                String::new(),
+                "A↑".to_string(),
+                "B↑".to_string(),
+            ],
+            rule_columns(&key_rule!("A↑ : B↑"))
+        );
+    }
+
+    #[test]
+    fn test_parse_row() {
+        assert_eq!(
+            vec![key_rule!("#3 [LEFT_CTRL] A↓ : B↓ → B↑")],
+            parse_row("3", "LEFT_CTRL", "A↓", "B↓ → B↑").unwrap()
+        );
+        assert_eq!(
+            vec![key_rule!("[] A↓ : B↓")],
+            parse_row("", "[]", "A↓", "B↓").unwrap()
+        );
+        assert_eq!(2, parse_row("", "", "ANY_5↓", "B↓").unwrap().len());
+        assert!(parse_row("", "", "BANANA↓", "B↓").is_err());
+        assert!(parse_row("", "", "A↓", "").is_err());
+    }
+
+    #[test]
+    fn test_trace_event() {
+        let event = trace_event("", "A↓").unwrap();
+        assert_eq!(KeyTrigger::from_str("[] A↓").unwrap(), event.trigger);
+
+        let event = trace_event("LEFT_SHIFT + NUM_LOCK=on", "A××↓").unwrap();
+        assert_eq!(
+            KeyTrigger::from_str("[LEFT_SHIFT + NUM_LOCK=on] A××↓").unwrap(),
+            event.trigger
+        );
+        assert_eq!(2, event.taps);
+        assert_eq!(KeyLocks::default().with(Key::NumLock, true), event.locks);
+
+        assert!(trace_event("[]", "BANANA↓").is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use keympostor::rule::KeyTransformRule;
+
+/// Edits kept for undo. Older ones are dropped.
+const MAX_HISTORY: usize = 100;
+
+/// Edit of the rules list replacing the rules at the index with the other ones. Adding,
+/// updating and removing of the rules are the edits of the different ranges.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RulesEdit {
+    index: usize,
+    removed: Vec<KeyTransformRule>,
+    inserted: Vec<KeyTransformRule>,
+}
+
+impl RulesEdit {
+    pub(crate) fn add(rules: &[KeyTransformRule], inserted: Vec<KeyTransformRule>) -> Self {
+        Self {
+            index: rules.len(),
+            removed: vec![],
+            inserted,
+        }
+    }
+
+    pub(crate) fn update(
+        rules: &[KeyTransformRule],
+        index: usize,
+        inserted: Vec<KeyTransformRule>,
+    ) -> Option<Self> {
+        Some(Self {
+            index,
+            removed: vec![rules.get(index)?.clone()],
+            inserted,
+        })
+    }
+
+    pub(crate) fn remove(rules: &[KeyTransformRule], index: usize) -> Option<Self> {
+        Self::update(rules, index, vec![])
+    }
+
+    fn apply(&self, rules: &mut Vec<KeyTransformRule>) {
+        rules.splice(
+            self.index..self.index + self.removed.len(),
+            self.inserted.iter().cloned(),
+        );
+    }
+
+    fn revert(&self, rules: &mut Vec<KeyTransformRule>) {
+        rules.splice(
+            self.index..self.index + self.inserted.len(),
+            self.removed.iter().cloned(),
+        );
+    }
+}
+
+/// Undo and redo stacks of the edits of the rules.
+#[derive(Debug, Default)]
+pub(crate) struct RulesHistory {
+    undo: Vec<RulesEdit>,
+    redo: Vec<RulesEdit>,
+}
+
+impl RulesHistory {
+    /// Applies the edit to the rules. Undone edits cannot be redone after that.
+    pub(crate) fn execute(&mut self, edit: RulesEdit, rules: &mut Vec<KeyTransformRule>) {
+        edit.apply(rules);
+        self.undo.push(edit);
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Reverts the last edit. Returns the index of the first affected rule.
+    pub(crate) fn undo(&mut self, rules: &mut Vec<KeyTransformRule>) -> Option<usize> {
+        let edit = self.undo.pop()?;
+        edit.revert(rules);
+        let index = edit.index;
+        self.redo.push(edit);
+        Some(index)
+    }
+
+    /// Applies the last undone edit. Returns the index of the first affected rule.
+    pub(crate) fn redo(&mut self, rules: &mut Vec<KeyTransformRule>) -> Option<usize> {
+        let edit = self.redo.pop()?;
+        edit.apply(rules);
+        let index = edit.index;
+        self.undo.push(edit);
+        Some(index)
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::rules_history::{MAX_HISTORY, RulesEdit, RulesHistory};
+    use keympostor::key_rule;
+    use keympostor::rule::KeyTransformRule;
+    use std::str::FromStr;
+
+    fn create_test_rules() -> Vec<KeyTransformRule> {
+        vec![key_rule!("A↓ : B↓"), key_rule!("C↓ : D↓")]
+    }
+
+    #[test]
+    fn test_rules_edit() {
+        let rules = create_test_rules();
+
+        let mut edited = rules.clone();
+        let edit = RulesEdit::add(&edited, vec![key_rule!("E↓ : F↓")]);
+        edit.apply(&mut edited);
+        assert_eq!(key_rule!("E↓ : F↓"), edited[2]);
+        edit.revert(&mut edited);
+        assert_eq!(rules, edited);
+
+        let edit = RulesEdit::update(&edited, 0, vec![key_rule!("A↓ : X↓"), key_rule!("A↑ : X↑")])
+            .unwrap();
+        edit.apply(&mut edited);
+        assert_eq!(
+            vec![
+                key_rule!("A↓ : X↓"),
+                key_rule!("A↑ : X↑"),
+                key_rule!("C↓ : D↓")
+            ],
+            edited
+        );
+        edit.revert(&mut edited);
+        assert_eq!(rules, edited);
+
+        let edit = RulesEdit::remove(&edited, 1).unwrap();
+        edit.apply(&mut edited);
+        assert_eq!(vec![key_rule!("A↓ : B↓")], edited);
+        edit.revert(&mut edited);
+        assert_eq!(rules, edited);
+
+        assert!(RulesEdit::remove(&edited, 2).is_none());
+    }
+
+    #[test]
+    fn test_rules_history() {
+        let mut rules = create_test_rules();
+        let mut history = RulesHistory::default();
+        assert!(!history.can_undo());
+        assert_eq!(None, history.undo(&mut rules));
+
+        history.execute(RulesEdit::remove(&rules, 0).unwrap(), &mut rules);
+        history.execute(
+            RulesEdit::add(&rules, vec![key_rule!("E↓ : F↓")]),
+            &mut rules,
+        );
+        assert_eq!(vec![key_rule!("C↓ : D↓"), key_rule!("E↓ : F↓")], rules);
+
+        assert_eq!(Some(1), history.undo(&mut rules));
+        assert_eq!(Some(0), history.undo(&mut rules));
+        assert_eq!(create_test_rules(), rules);
+        assert!(!history.can_undo());
+        assert!(history.can_redo());
+
+        assert_eq!(Some(0), history.redo(&mut rules));
+        assert_eq!(vec![key_rule!("C↓ : D↓")], rules);
+
+        history.execute(
+            RulesEdit::update(&rules, 0, vec![key_rule!("C↓ : X↓")]).unwrap(),
+            &mut rules,
+        );
+        assert!(!history.can_redo());
+        assert_eq!(None, history.redo(&mut rules));
+        assert_eq!(vec![key_rule!("C↓ : X↓")], rules);
+
+        history.clear();
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_rules_history_limit() {
+        let mut rules = vec![];
+        let mut history = RulesHistory::default();
+        for _ in 0..MAX_HISTORY + 10 {
+            history.execute(
+                RulesEdit::add(&rules, vec![key_rule!("A↓ : B↓")]),
+                &mut rules,
+            );
+        }
+
+        let mut count = 0;
+        while history.undo(&mut rules).is_some() {
+            count += 1;
+        }
+        assert_eq!(MAX_HISTORY, count);
+        assert_eq!(10, rules.len());
+    }
+}
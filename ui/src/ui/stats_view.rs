@@ -0,0 +1,56 @@
+use crate::rs;
+use crate::stats::format_stats;
+use crate::ui::res::RESOURCES;
+use crate::ui::res_ids::IDS_STATS_DISABLED;
+use crate::ui::style::{SMALL_MONO_FONT, font, rescale_layout};
+use keympostor::physical::PhysicalLayout;
+use keympostor::stats::KeyStats;
+use log::warn;
+use native_windows_gui::stretch::geometry::Rect;
+use native_windows_gui::stretch::style::Dimension::Points as PT;
+use native_windows_gui::{FlexboxLayout, NwgError, Tab, TextBox};
+
+/// Shows the collected keyboard usage statistics.
+#[derive(Default)]
+pub(crate) struct StatsView {
+    layout: FlexboxLayout,
+    text_view: TextBox,
+}
+
+impl StatsView {
+    pub(crate) fn build(&mut self, parent: &Tab) -> Result<(), NwgError> {
+        TextBox::builder()
+            .parent(parent)
+            .readonly(true)
+            .font(Some(font(SMALL_MONO_FONT)))
+            .build(&mut self.text_view)?;
+
+        FlexboxLayout::builder()
+            .parent(parent)
+            .child(&self.text_view)
+            .child_flex_grow(1.0)
+            .child_margin(Rect {
+                start: PT(4.0),
+                end: PT(16.0),
+                top: PT(6.0),
+                bottom: PT(40.0),
+            })
+            .build(&self.layout)
+    }
+
+    /// Rescales the layout after the DPI change.
+    pub(crate) fn rescale(&self, factor: f32) {
+        rescale_layout(&self.layout, factor);
+        self.layout
+            .fit()
+            .unwrap_or_else(|e| warn!("Failed to fit layout: {:?}", e));
+    }
+
+    pub(crate) fn update(&self, stats: Option<&KeyStats>) {
+        let text = match stats {
+            Some(stats) => format_stats(stats, PhysicalLayout::detect()),
+            None => rs!(IDS_STATS_DISABLED).to_string(),
+        };
+        self.text_view.set_text(&text);
+    }
+}
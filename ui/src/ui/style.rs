@@ -1,37 +1,385 @@
-use native_windows_gui::Font;
-use std::sync::LazyLock;
+use crate::settings::ThemeMode;
+use crate::ui::display::{DEFAULT_DPI, scale};
+use log::warn;
+use native_windows_gui::stretch::geometry::{Rect, Size};
+use native_windows_gui::stretch::style::{Dimension, Style};
+use native_windows_gui::{FlexboxLayout, Font};
+use std::cell::{Cell, RefCell};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, RECT, WPARAM};
+use windows::Win32::Graphics::Dwm::{DWMWA_USE_IMMERSIVE_DARK_MODE, DwmSetWindowAttribute};
+use windows::Win32::Graphics::Gdi::{
+    CreateSolidBrush, FillRect, HBRUSH, HDC, InvalidateRect, SetBkColor, SetTextColor,
+};
+use windows::Win32::System::Registry::{HKEY_CURRENT_USER, RRF_RT_REG_DWORD, RegGetValueW};
+use windows::Win32::UI::Controls::{
+    LVM_SETBKCOLOR, LVM_SETTEXTBKCOLOR, LVM_SETTEXTCOLOR, SetWindowTheme,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumChildWindows, GetClassNameW, GetClientRect, SendMessageW, WM_CTLCOLORBTN, WM_CTLCOLOREDIT,
+    WM_CTLCOLORLISTBOX, WM_CTLCOLORSTATIC, WM_ERASEBKGND, WM_GETFONT, WM_SETFONT,
+};
+use windows::core::{BOOL, PCWSTR, w};
 
-pub static INFO_LABEL_FONT: LazyLock<Font> = LazyLock::new(|| {
-    let mut font = Font::default();
-    Font::builder()
-        .family("Consolas")
-        .size(28)
-        .weight(700)
-        .build(&mut font)
-        .expect("Failed to build font");
-    font
-});
+/// Font of the controls. Sizes are of the default DPI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FontStyle {
+    family: &'static str,
+    size: u32,
+    weight: u32,
+}
 
-pub static SMALL_MONO_FONT: LazyLock<Font> = LazyLock::new(|| mono_font(15));
+pub(crate) const DEFAULT_FONT: FontStyle = FontStyle {
+    family: "Segoe UI",
+    size: 17,
+    weight: 400,
+};
 
-pub static BIG_MONO_FONT: LazyLock<Font> = LazyLock::new(|| mono_font(18));
+pub(crate) const INFO_LABEL_FONT: FontStyle = FontStyle {
+    family: "Consolas",
+    size: 28,
+    weight: 700,
+};
 
-pub(crate) fn display_font(size: u32) -> Font {
-    let mut font = Font::default();
-    Font::builder()
-        .family("Segoe UI")
-        .size(size)
-        .build(&mut font)
-        .expect("Failed to build font");
-    font
+pub(crate) const SMALL_MONO_FONT: FontStyle = FontStyle {
+    family: "Consolas",
+    size: 15,
+    weight: 400,
+};
+
+pub(crate) const BIG_MONO_FONT: FontStyle = FontStyle {
+    family: "Consolas",
+    size: 18,
+    weight: 400,
+};
+
+thread_local! {
+    /// Fonts built for the DPIs of the monitors. Controls keep using them until the app exits.
+    static FONTS: RefCell<Vec<(FontStyle, u32, &'static Font)>> = RefCell::new(vec![]);
 }
 
-fn mono_font(size: u32) -> Font {
+pub(crate) fn build_font(style: FontStyle, dpi: u32) -> Font {
     let mut font = Font::default();
     Font::builder()
-        .family("Consolas")
-        .size(size)
+        .family(style.family)
+        .size(scale(style.size as i32, dpi) as u32)
+        .weight(style.weight)
         .build(&mut font)
         .expect("Failed to build font");
     font
 }
+
+/// Returns the font of the default DPI.
+pub(crate) fn font(style: FontStyle) -> &'static Font {
+    scaled_font(style, DEFAULT_DPI)
+}
+
+/// Returns the font scaled to the DPI.
+pub(crate) fn scaled_font(style: FontStyle, dpi: u32) -> &'static Font {
+    FONTS.with_borrow_mut(|fonts| {
+        if let Some((_, _, font)) = fonts.iter().find(|(s, d, _)| *s == style && *d == dpi) {
+            return *font;
+        }
+        let font = Box::leak(Box::new(build_font(style, dpi)));
+        fonts.push((style, dpi, font));
+        font
+    })
+}
+
+/// Replaces the fonts of all child controls of the window with the fonts of the DPI.
+/// Controls with the fonts built elsewhere get the default font.
+pub(crate) fn rescale_fonts(window: HWND, dpi: u32) {
+    unsafe extern "system" fn enum_child_proc(hwnd: HWND, data: LPARAM) -> BOOL {
+        let dpi = data.0 as u32;
+        let handle = unsafe { SendMessageW(hwnd, WM_GETFONT, None, None) }.0;
+        let style = FONTS.with_borrow(|fonts| {
+            fonts
+                .iter()
+                .find(|(_, _, font)| font.handle as isize == handle)
+                .map_or(DEFAULT_FONT, |(style, _, _)| *style)
+        });
+        let font = scaled_font(style, dpi);
+        unsafe {
+            SendMessageW(
+                hwnd,
+                WM_SETFONT,
+                Some(WPARAM(font.handle as usize)),
+                Some(LPARAM(1)),
+            );
+        }
+        BOOL::from(true)
+    }
+
+    unsafe {
+        let _ = EnumChildWindows(Some(window), Some(enum_child_proc), LPARAM(dpi as isize));
+    }
+}
+
+/// Multiplies paddings, margins and sizes of the layout and its children by the factor.
+/// The layout must be fitted after that.
+pub(crate) fn rescale_layout(layout: &FlexboxLayout, factor: f32) {
+    layout.set_style(scale_style(&layout.style(), factor));
+    for child in layout.children_mut().children() {
+        child.style = scale_style(&child.style, factor);
+    }
+}
+
+fn scale_style(style: &Style, factor: f32) -> Style {
+    let scale = |d: Dimension| match d {
+        Dimension::Points(value) => Dimension::Points(value * factor),
+        d => d,
+    };
+    let scale_size = |s: Size<Dimension>| Size {
+        width: scale(s.width),
+        height: scale(s.height),
+    };
+    let scale_rect = |r: Rect<Dimension>| Rect {
+        start: scale(r.start),
+        end: scale(r.end),
+        top: scale(r.top),
+        bottom: scale(r.bottom),
+    };
+
+    Style {
+        position: scale_rect(style.position),
+        margin: scale_rect(style.margin),
+        padding: scale_rect(style.padding),
+        border: scale_rect(style.border),
+        flex_basis: scale(style.flex_basis),
+        size: scale_size(style.size),
+        min_size: scale_size(style.min_size),
+        max_size: scale_size(style.max_size),
+        ..*style
+    }
+}
+
+/// Colors of the UI. The light theme leaves the windows and controls to the system.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Theme {
+    pub(crate) is_dark: bool,
+    pub(crate) background: [u8; 3],
+    pub(crate) text: [u8; 3],
+    pub(crate) editor_background: [u8; 3],
+    pub(crate) editor_text: [u8; 3],
+    /// Text of the log records of the events processed by the rules.
+    pub(crate) log_rule: [u8; 3],
+    pub(crate) log_private: [u8; 3],
+    pub(crate) log_injected: [u8; 3],
+}
+
+pub(crate) const LIGHT_THEME: Theme = Theme {
+    is_dark: false,
+    background: [240, 240, 240],
+    text: [0, 0, 0],
+    editor_background: [255, 255, 255],
+    editor_text: [0, 0, 0],
+    log_rule: [170, 170, 170],
+    log_private: [0, 0, 204],
+    log_injected: [170, 0, 204],
+};
+
+pub(crate) const DARK_THEME: Theme = Theme {
+    is_dark: true,
+    background: [32, 32, 32],
+    text: [230, 230, 230],
+    editor_background: [43, 43, 43],
+    editor_text: [230, 230, 230],
+    log_rule: [128, 128, 128],
+    log_private: [110, 150, 255],
+    log_injected: [210, 120, 255],
+};
+
+const PERSONALIZE_KEY: PCWSTR = w!(r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize");
+
+thread_local! {
+    static THEME: Cell<&'static Theme> = const { Cell::new(&LIGHT_THEME) };
+    /// Brushes of the theme colors. Kept until the app exits as the windows paint with them.
+    static BRUSHES: RefCell<Vec<([u8; 3], HBRUSH)>> = RefCell::new(vec![]);
+}
+
+impl Theme {
+    /// Returns the theme of the mode. The system mode follows the Windows app theme.
+    pub(crate) fn of_mode(mode: ThemeMode, is_system_dark: bool) -> &'static Theme {
+        match mode {
+            ThemeMode::System if is_system_dark => &DARK_THEME,
+            ThemeMode::System | ThemeMode::Light => &LIGHT_THEME,
+            ThemeMode::Dark => &DARK_THEME,
+        }
+    }
+}
+
+/// Current theme of the UI.
+pub(crate) fn theme() -> &'static Theme {
+    THEME.get()
+}
+
+/// Returns `true` if the Windows app theme is dark.
+pub(crate) fn is_system_dark_theme() -> bool {
+    let mut value = 1u32;
+    let mut size = size_of::<u32>() as u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            PERSONALIZE_KEY,
+            w!("AppsUseLightTheme"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut _ as _),
+            Some(&mut size),
+        )
+    }
+    .ok()
+    .is_ok_and(|_| value == 0)
+}
+
+/// Returns `true` if `WM_SETTINGCHANGE` of the parameter notifies about the app theme change.
+pub(crate) fn is_theme_change(l_param: isize) -> bool {
+    l_param != 0
+        && unsafe { PCWSTR(l_param as *const u16).to_string() }
+            .is_ok_and(|area| area == "ImmersiveColorSet")
+}
+
+pub(crate) fn colorref([r, g, b]: [u8; 3]) -> COLORREF {
+    COLORREF(r as u32 | (g as u32) << 8 | (b as u32) << 16)
+}
+
+fn brush(color: [u8; 3]) -> HBRUSH {
+    BRUSHES.with_borrow_mut(|brushes| {
+        if let Some((_, brush)) = brushes.iter().find(|(c, _)| *c == color) {
+            return *brush;
+        }
+        let brush = unsafe { CreateSolidBrush(colorref(color)) };
+        brushes.push((color, brush));
+        brush
+    })
+}
+
+/// Makes the theme current and repaints the window with all its child controls.
+pub(crate) fn apply_theme(window: HWND, theme: &'static Theme) {
+    unsafe extern "system" fn enum_child_proc(hwnd: HWND, _data: LPARAM) -> BOOL {
+        let theme = THEME.get();
+        unsafe {
+            /* dark scrollbars, buttons and headers, the light ones are restored by null */
+            let name = if theme.is_dark {
+                w!("DarkMode_Explorer")
+            } else {
+                PCWSTR::null()
+            };
+            let _ = SetWindowTheme(hwnd, name, PCWSTR::null());
+
+            let mut class = [0u16; 32];
+            let len = GetClassNameW(hwnd, &mut class) as usize;
+            if String::from_utf16_lossy(&class[..len]) == "SysListView32" {
+                let background = Some(LPARAM(colorref(theme.editor_background).0 as isize));
+                SendMessageW(hwnd, LVM_SETBKCOLOR, None, background);
+                SendMessageW(hwnd, LVM_SETTEXTBKCOLOR, None, background);
+                SendMessageW(
+                    hwnd,
+                    LVM_SETTEXTCOLOR,
+                    None,
+                    Some(LPARAM(colorref(theme.editor_text).0 as isize)),
+                );
+            }
+            let _ = InvalidateRect(Some(hwnd), None, true);
+        }
+        BOOL::from(true)
+    }
+
+    THEME.set(theme);
+    unsafe {
+        let is_dark = BOOL::from(theme.is_dark);
+        DwmSetWindowAttribute(
+            window,
+            DWMWA_USE_IMMERSIVE_DARK_MODE,
+            &is_dark as *const _ as _,
+            size_of::<BOOL>() as u32,
+        )
+        .unwrap_or_else(|e| warn!("Failed to set window dark mode: {}", e));
+        let _ = EnumChildWindows(Some(window), Some(enum_child_proc), LPARAM(0));
+        let _ = InvalidateRect(Some(window), None, true);
+    }
+}
+
+/// Paints the backgrounds of the window and the colors of its child controls in the dark
+/// theme. Bound as the raw event handler of the windows hosting the controls.
+pub(crate) fn handle_theme_message(hwnd: HWND, msg: u32, w_param: usize) -> Option<isize> {
+    let theme = theme();
+    if !theme.is_dark {
+        return None;
+    }
+
+    let hdc = HDC(w_param as _);
+    if msg == WM_ERASEBKGND {
+        let mut rect = RECT::default();
+        unsafe {
+            GetClientRect(hwnd, &mut rect).ok()?;
+            FillRect(hdc, &rect, brush(theme.background));
+        }
+        return Some(1);
+    }
+
+    let (background, text) = match msg {
+        WM_CTLCOLOREDIT | WM_CTLCOLORLISTBOX => (theme.editor_background, theme.editor_text),
+        WM_CTLCOLORSTATIC | WM_CTLCOLORBTN => (theme.background, theme.text),
+        _ => return None,
+    };
+    unsafe {
+        SetTextColor(hdc, colorref(text));
+        SetBkColor(hdc, colorref(background));
+    }
+    Some(brush(background).0 as isize)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::ThemeMode;
+    use crate::ui::style::{DARK_THEME, LIGHT_THEME, Theme, colorref, scale_style};
+    use native_windows_gui::stretch::geometry::{Rect, Size};
+    use native_windows_gui::stretch::style::{Dimension, Style};
+
+    #[test]
+    fn test_scale_style() {
+        let style = Style {
+            margin: Rect {
+                start: Dimension::Points(4.0),
+                end: Dimension::Points(16.0),
+                top: Dimension::Auto,
+                bottom: Dimension::Percent(0.5),
+            },
+            size: Size {
+                width: Dimension::Auto,
+                height: Dimension::Points(26.0),
+            },
+            flex_grow: 1.0,
+            ..Default::default()
+        };
+
+        let scaled = scale_style(&style, 2.0);
+
+        assert_eq!(Dimension::Points(8.0), scaled.margin.start);
+        assert_eq!(Dimension::Points(32.0), scaled.margin.end);
+        assert_eq!(Dimension::Auto, scaled.margin.top);
+        assert_eq!(Dimension::Percent(0.5), scaled.margin.bottom);
+        assert_eq!(Dimension::Auto, scaled.size.width);
+        assert_eq!(Dimension::Points(52.0), scaled.size.height);
+        assert_eq!(1.0, scaled.flex_grow);
+        assert_eq!(
+            Dimension::Points(16.0),
+            scale_style(&scaled, 0.5).margin.end
+        );
+    }
+
+    #[test]
+    fn test_theme_of_mode() {
+        assert_eq!(&LIGHT_THEME, Theme::of_mode(ThemeMode::System, false));
+        assert_eq!(&DARK_THEME, Theme::of_mode(ThemeMode::System, true));
+        assert_eq!(&LIGHT_THEME, Theme::of_mode(ThemeMode::Light, true));
+        assert_eq!(&DARK_THEME, Theme::of_mode(ThemeMode::Dark, false));
+    }
+
+    #[test]
+    fn test_colorref() {
+        assert_eq!(0xCC0000, colorref(LIGHT_THEME.log_private).0);
+        assert_eq!(0xCC00AA, colorref(LIGHT_THEME.log_injected).0);
+        assert_eq!(0xAAAAAA, colorref(LIGHT_THEME.log_rule).0);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::ui::style::BIG_MONO_FONT;
+use crate::ui::style::{BIG_MONO_FONT, font};
 use native_windows_gui::{ControlHandle, Event, NwgError, TextInput, Window};
 
 const MAX_LENGTH: usize = 150;
@@ -13,7 +13,7 @@ impl TypeTestEditor {
         TextInput::builder()
             .parent(parent)
             .focus(true)
-            .font(Some(&BIG_MONO_FONT))
+            .font(Some(font(BIG_MONO_FONT)))
             .build(&mut self.view)
     }
 
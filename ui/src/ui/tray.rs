@@ -1,24 +1,31 @@
 use crate::layout::{KeyTransformLayout, KeyTransformLayoutList};
-use crate::ui::res_ids::{IDI_ICON_APP, IDS_EXIT, IDS_LAYOUT, IDS_SETTINGS, IDS_TRAY_TIP};
+use crate::ui::res_ids::{
+    IDI_ICON_APP, IDS_EXIT, IDS_HOOK_BLOCKED, IDS_HOOK_FAILED, IDS_LAYOUT, IDS_RELAUNCH_ELEVATED,
+    IDS_RULES_LOOP, IDS_SETTINGS, IDS_TRAY_TIP,
+};
+use crate::ui::layouts_menu::build_layout_items;
 use crate::ui::res::RESOURCES;
 use crate::app::App;
 use crate::{r_icon, rs};
+use keympostor::health::{HookHealth, is_elevated};
 use log::warn;
 use native_windows_gui::{
     ControlHandle, Event, GlobalCursor, Icon, Menu, MenuItem, MenuSeparator, MousePressEvent,
-    NwgError, TrayNotification, Window,
+    NwgError, TrayNotification, TrayNotificationFlags, Window,
 };
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 #[derive(Default)]
 pub(crate) struct Tray {
     notification: TrayNotification,
     menu: Menu,
     open_app_item: MenuItem,
+    relaunch_elevated_item: MenuItem,
     exit_app_item: MenuItem,
     layouts_item: Menu,
     separator: MenuSeparator,
     layout_items: RefCell<Vec<(MenuItem, String)>>,
+    is_blocked_warning_shown: Cell<bool>,
 }
 
 impl Tray {
@@ -35,7 +42,7 @@ impl Tray {
             .build(&mut self.menu)?;
 
         Menu::builder()
-            .text(rs!(IDS_LAYOUT))
+            .text(&format!("&{}", rs!(IDS_LAYOUT)))
             .parent(&self.menu)
             .build(&mut self.layouts_item)?;
 
@@ -48,6 +55,12 @@ impl Tray {
             .parent(&self.menu)
             .build(&mut self.open_app_item)?;
 
+        MenuItem::builder()
+            .text(rs!(IDS_RELAUNCH_ELEVATED))
+            .disabled(is_elevated())
+            .parent(&self.menu)
+            .build(&mut self.relaunch_elevated_item)?;
+
         MenuItem::builder()
             .text(rs!(IDS_EXIT))
             .parent(&self.menu)
@@ -55,22 +68,8 @@ impl Tray {
     }
 
     pub(crate) fn build_layout_menu(&self, layouts: &KeyTransformLayoutList) {
-        let mut layout_items = vec![];
-
-        for layout in layouts {
-            let mut item: MenuItem = MenuItem::default();
-            MenuItem::builder()
-                .parent(&self.layouts_item)
-                .text(&layout.title)
-                .build(&mut item)
-                .unwrap();
-
-            layout_items.push((item, layout.name.clone()));
-        }
-
+        let layout_items = build_layout_items(&self.layouts_item, layouts).unwrap();
         self.layout_items.replace(layout_items);
-
-
     }
 
     pub(crate) fn update_ui(&self, layout: &KeyTransformLayout) {
@@ -92,6 +91,44 @@ impl Tray {
         }
     }
 
+    /// Shows the warning in the tip. The balloon is shown only once per session.
+    pub(crate) fn set_hook_health(&self, health: HookHealth) {
+        match health {
+            HookHealth::Ok => self.notification.set_tip(rs!(IDS_TRAY_TIP)),
+            HookHealth::ElevatedForeground => {
+                self.notification.set_tip(rs!(IDS_HOOK_BLOCKED));
+                if !self.is_blocked_warning_shown.replace(true) {
+                    self.notification.show(
+                        rs!(IDS_HOOK_BLOCKED),
+                        Some(rs!(IDS_TRAY_TIP)),
+                        Some(TrayNotificationFlags::WARNING_ICON),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Shows the balloon with the problem of the rules processing.
+    pub(crate) fn show_hook_error(&self, text: &str) {
+        self.notification.set_tip(rs!(IDS_HOOK_FAILED));
+        self.notification.show(
+            text,
+            Some(rs!(IDS_HOOK_FAILED)),
+            Some(TrayNotificationFlags::ERROR_ICON),
+            None,
+        );
+    }
+
+    pub(crate) fn show_rules_warning(&self, text: &str) {
+        self.notification.show(
+            text,
+            Some(rs!(IDS_RULES_LOOP)),
+            Some(TrayNotificationFlags::WARNING_ICON),
+            None,
+        );
+    }
+
     pub(crate) fn handle_event(&self, app: &App, evt: Event, handle: ControlHandle) {
         match evt {
             Event::OnMousePress(MousePressEvent::MousePressLeftUp) => {
@@ -107,6 +144,8 @@ impl Tray {
             Event::OnMenuItemSelected => {
                 if &handle == &self.open_app_item {
                     app.on_show_main_window();
+                } else if &handle == &self.relaunch_elevated_item {
+                    app.on_relaunch_elevated();
                 } else if &handle == &self.exit_app_item {
                     app.on_app_exit();
                 } else {
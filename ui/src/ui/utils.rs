@@ -1,19 +1,33 @@
 use crate::rs;
+use crate::ui::display::monitors;
 use crate::ui::res::RESOURCES;
-use crate::ui::res_ids::IDS_APP_TITLE;
+use crate::ui::res_ids::{IDS_APP_TITLE, IDS_KEY_NAMES};
+use keympostor::key::Key;
 use native_windows_gui::{
-    message, ControlHandle, ListView, MessageButtons, MessageIcons, MessageParams, Window,
+    message, ControlHandle, ListView, MessageButtons, MessageChoice, MessageIcons, MessageParams,
+    Tooltip, Window,
 };
 use std::mem;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::Relaxed;
 use windows::Win32::Foundation::{HWND, LPARAM, RECT, WPARAM};
-use windows::Win32::UI::Controls::{LVIF_PARAM, LVITEMW, LVM_ENSUREVISIBLE, LVM_GETCOLUMNWIDTH, LVM_SETITEMW};
+use windows::Win32::Graphics::Gdi::{
+    GetMonitorInfoW, MonitorFromRect, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+    MONITOR_DEFAULTTONULL,
+};
+use windows::Win32::UI::Controls::{
+    LIST_VIEW_ITEM_STATE_FLAGS, LVIF_PARAM, LVIS_STATEIMAGEMASK, LVITEMW, LVM_ENSUREVISIBLE,
+    LVM_GETCOLUMNWIDTH, LVM_GETITEMSTATE, LVM_SETEXTENDEDLISTVIEWSTYLE, LVM_SETITEMSTATE,
+    LVM_SETITEMW, LVS_EX_CHECKBOXES, TTM_SETMAXTIPWIDTH,
+};
 use windows::Win32::UI::WindowsAndMessaging::{
     GetWindowRect, PeekMessageW, SendMessageW, SetWindowPos, MSG, PM_REMOVE, SWP_NOACTIVATE,
-    SWP_NOCOPYBITS, SWP_NOMOVE, SWP_NOOWNERZORDER, SWP_NOZORDER, WM_TIMER,
+    SWP_NOCOPYBITS, SWP_NOMOVE, SWP_NOOWNERZORDER, SWP_NOSIZE, SWP_NOZORDER, WM_TIMER,
 };
 
+/// Height of the window's top strip that must stay on screen to be able to drag the window.
+const CAPTION_HEIGHT: i32 = 32;
+
 pub fn try_hwnd(handle: ControlHandle) -> Option<HWND> {
     handle.hwnd().map(|h| HWND(h as _))
 }
@@ -47,6 +61,60 @@ pub fn set_window_size(window: &Window, size: (u32, u32)) {
     }
 }
 
+/// Returns string identifying current monitors configuration e.g. `0:0:1920x1080;1920:0:2560x1440`.
+pub(crate) fn monitor_topology_id() -> String {
+    let mut rects: Vec<RECT> = monitors().iter().map(|m| m.rect).collect();
+    rects.sort_by_key(|r| (r.left, r.top));
+    rects
+        .iter()
+        .map(|r| format!("{}:{}:{}x{}", r.left, r.top, r.right - r.left, r.bottom - r.top))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Checks that window's title bar is visible on some monitor so the window can be dragged.
+pub(crate) fn is_window_on_screen(window: &Window) -> bool {
+    unsafe {
+        let mut r: RECT = mem::zeroed();
+        GetWindowRect(hwnd(window.handle), &mut r).unwrap();
+        r.bottom = r.top + CAPTION_HEIGHT;
+        !MonitorFromRect(&r, MONITOR_DEFAULTTONULL).is_invalid()
+    }
+}
+
+/// Moves window to the center of the work area of the nearest monitor.
+pub(crate) fn center_window(window: &Window) {
+    unsafe {
+        let hwnd = hwnd(window.handle);
+        let mut info = MONITORINFO {
+            cbSize: size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if !GetMonitorInfoW(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST), &mut info).as_bool()
+        {
+            return;
+        }
+
+        let mut r: RECT = mem::zeroed();
+        GetWindowRect(hwnd, &mut r).unwrap();
+
+        let work = info.rcWork;
+        let x = work.left + ((work.right - work.left) - (r.right - r.left)).max(0) / 2;
+        let y = work.top + ((work.bottom - work.top) - (r.bottom - r.top)).max(0) / 2;
+
+        SetWindowPos(
+            hwnd,
+            None,
+            x,
+            y,
+            0,
+            0,
+            SWP_NOZORDER | SWP_NOSIZE | SWP_NOACTIVATE | SWP_NOOWNERZORDER,
+        )
+        .unwrap()
+    }
+}
+
 pub fn set_list_view_item_data(view: &ListView, index: usize, data: usize) {
     let mut item = LVITEMW::default();
     item.mask = LVIF_PARAM;
@@ -63,6 +131,55 @@ pub fn set_list_view_item_data(view: &ListView, index: usize, data: usize) {
     }
 }
 
+/* state images of the check box */
+const UNCHECKED_STATE_IMAGE: u32 = 1;
+const CHECKED_STATE_IMAGE: u32 = 2;
+
+/// Shows the check boxes in the list view items.
+pub fn enable_list_view_check_boxes(view: &ListView) {
+    unsafe {
+        SendMessageW(
+            hwnd(view.handle),
+            LVM_SETEXTENDEDLISTVIEWSTYLE,
+            Some(WPARAM(LVS_EX_CHECKBOXES as usize)),
+            Some(LPARAM(LVS_EX_CHECKBOXES as isize)),
+        );
+    }
+}
+
+pub fn is_list_view_item_checked(view: &ListView, index: usize) -> bool {
+    let state = unsafe {
+        SendMessageW(
+            hwnd(view.handle),
+            LVM_GETITEMSTATE,
+            Some(WPARAM(index)),
+            Some(LPARAM(LVIS_STATEIMAGEMASK.0 as isize)),
+        )
+        .0
+    };
+    (state as u32 & LVIS_STATEIMAGEMASK.0) >> 12 == CHECKED_STATE_IMAGE
+}
+
+pub fn set_list_view_item_checked(view: &ListView, index: usize, checked: bool) {
+    let image = if checked {
+        CHECKED_STATE_IMAGE
+    } else {
+        UNCHECKED_STATE_IMAGE
+    };
+    let mut item = LVITEMW::default();
+    item.stateMask = LVIS_STATEIMAGEMASK;
+    item.state = LIST_VIEW_ITEM_STATE_FLAGS(image << 12);
+
+    unsafe {
+        SendMessageW(
+            hwnd(view.handle),
+            LVM_SETITEMSTATE,
+            Some(WPARAM(index)),
+            Some(LPARAM(&item as *const _ as _)),
+        );
+    }
+}
+
 pub fn get_list_view_column_width(view: &ListView, index: usize) -> isize {
     unsafe {
         SendMessageW(
@@ -89,6 +206,18 @@ pub fn scroll_list_view_to_end(view: &ListView) {
     }
 }
 
+/// Enables multiline text of the tooltip wrapping it at the width.
+pub fn set_tooltip_max_width(tooltip: &Tooltip, width: isize) {
+    unsafe {
+        SendMessageW(
+            hwnd(tooltip.handle),
+            TTM_SETMAXTIPWIDTH,
+            None,
+            Some(LPARAM(width)),
+        );
+    }
+}
+
 pub(crate) fn show_warn_message(text: &str) {
     message(&MessageParams {
         title: rs!(IDS_APP_TITLE),
@@ -98,6 +227,33 @@ pub(crate) fn show_warn_message(text: &str) {
     });
 }
 
+pub(crate) fn show_info_message(text: &str) {
+    message(&MessageParams {
+        title: rs!(IDS_APP_TITLE),
+        content: text,
+        buttons: MessageButtons::Ok,
+        icons: MessageIcons::Info,
+    });
+}
+
+pub(crate) fn show_confirm_message(text: &str) -> bool {
+    let choice = message(&MessageParams {
+        title: rs!(IDS_APP_TITLE),
+        content: text,
+        buttons: MessageButtons::YesNo,
+        icons: MessageIcons::Question,
+    });
+    choice == MessageChoice::Yes
+}
+
+/// Returns the human-readable name of the key in the UI language. Keys without one are named
+/// as in the layout files.
+pub(crate) fn key_display_name(key: Key) -> String {
+    RESOURCES
+        .with(|r| r.find_string(IDS_KEY_NAMES + key as usize))
+        .unwrap_or_else(|| key.to_string())
+}
+
 pub(crate) fn drain_timer_msg_queue() {
     unsafe {
         let mut msg = MSG::default();
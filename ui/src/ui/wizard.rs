@@ -0,0 +1,352 @@
+use crate::layout::KeyTransformLayoutList;
+use crate::settings::{AppSettings, ThemeMode};
+use crate::startup::StartupMethod;
+use crate::ui::display::{DEFAULT_DPI, active_monitor, scale};
+use crate::ui::res::RESOURCES;
+use crate::ui::res_ids::{
+    IDI_ICON_APP, IDS_AUTOSTART, IDS_AUTOSTART_ELEVATED, IDS_BACK, IDS_FINISH, IDS_NEXT,
+    IDS_NO_AUTOSTART, IDS_NO_HOT_KEY, IDS_SETUP, IDS_SETUP_AUTOSTART, IDS_SETUP_LAYOUT,
+    IDS_SETUP_TOGGLE_HOT_KEY,
+};
+use crate::ui::style::{
+    DEFAULT_FONT, Theme, apply_theme, handle_theme_message, is_system_dark_theme, scaled_font,
+};
+use crate::ui::utils::hwnd;
+use crate::{r_icon, rs};
+use keympostor::trigger::KeyTrigger;
+use native_windows_gui::{
+    Button, ControlHandle, Event, Label, ListBox, NwgError, RadioButton, RadioButtonState, Window,
+    WindowFlags, bind_raw_event_handler, dispatch_thread_events, full_bind_event_handler,
+    stop_thread_dispatch, unbind_event_handler,
+};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::str::FromStr;
+use windows::Win32::Foundation::HWND;
+
+const THEME_HANDLER_ID: usize = 0x10003;
+
+/// Hotkeys offered for turning the keys processing on and off.
+const TOGGLE_HOT_KEYS: [&str; 3] = [
+    "[LEFT_CTRL + LEFT_ALT] PAUSE↓",
+    "[LEFT_CTRL + LEFT_ALT] K↓",
+    "[] SCROLL_LOCK↓",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum WizardPage {
+    #[default]
+    Layout,
+    Autostart,
+    ToggleHotKey,
+}
+
+impl WizardPage {
+    fn next(self) -> Option<Self> {
+        match self {
+            Self::Layout => Some(Self::Autostart),
+            Self::Autostart => Some(Self::ToggleHotKey),
+            Self::ToggleHotKey => None,
+        }
+    }
+
+    fn previous(self) -> Option<Self> {
+        match self {
+            Self::Layout => None,
+            Self::Autostart => Some(Self::Layout),
+            Self::ToggleHotKey => Some(Self::Autostart),
+        }
+    }
+}
+
+/// Initial options chosen by the user.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct WizardChoice {
+    pub(crate) layout: String,
+    pub(crate) autostart: Option<StartupMethod>,
+    pub(crate) toggle_hot_key: Option<KeyTrigger>,
+}
+
+impl WizardChoice {
+    /// Settings of the choice. The other settings are the defaults.
+    pub(crate) fn to_settings(&self) -> AppSettings {
+        AppSettings {
+            last_transform_layout: Some(self.layout.clone()),
+            toggle_processing_hot_key: self.toggle_hot_key.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// First run wizard walking the user through the base layout, autostart and the hotkey
+/// turning the processing on and off.
+#[derive(Default)]
+pub(crate) struct Wizard {
+    window: Window,
+    prompt_label: Label,
+    layouts_list: ListBox<String>,
+    no_autostart_radio: RadioButton,
+    autostart_radio: RadioButton,
+    autostart_elevated_radio: RadioButton,
+    hot_keys_list: ListBox<String>,
+    back_button: Button,
+    next_button: Button,
+    layout_names: Vec<String>,
+    page: Cell<WizardPage>,
+    choice: RefCell<Option<WizardChoice>>,
+}
+
+impl Wizard {
+    /// Shows the wizard and waits until the user finishes it. Returns `None` when the wizard
+    /// is closed.
+    pub(crate) fn run(layouts: &KeyTransformLayoutList) -> Result<Option<WizardChoice>, NwgError> {
+        let mut wizard = Self::default();
+        wizard.build(layouts)?;
+        let wizard = Rc::new(wizard);
+
+        let wizard_rc = Rc::downgrade(&wizard);
+        let handler = full_bind_event_handler(&wizard.window.handle, move |evt, _, handle| {
+            if let Some(wizard) = wizard_rc.upgrade() {
+                wizard.handle_event(evt, handle);
+            }
+        });
+
+        wizard.show_page(WizardPage::default());
+        wizard.window.set_visible(true);
+        dispatch_thread_events();
+
+        unbind_event_handler(&handler);
+        wizard.window.set_visible(false);
+        Ok(wizard.choice.take())
+    }
+
+    fn build(&mut self, layouts: &KeyTransformLayoutList) -> Result<(), NwgError> {
+        let dpi = active_monitor().map_or(DEFAULT_DPI, |m| m.dpi);
+        let font = Some(scaled_font(DEFAULT_FONT, dpi));
+        let pos = |x, y| (scale(x, dpi), scale(y, dpi));
+        let size = pos;
+
+        Window::builder()
+            .size(size(420, 320))
+            .center(true)
+            .flags(WindowFlags::WINDOW)
+            .icon(Some(&r_icon!(IDI_ICON_APP)))
+            .title(rs!(IDS_SETUP))
+            .build(&mut self.window)?;
+
+        Label::builder()
+            .parent(&self.window)
+            .position(pos(16, 16))
+            .size(size(388, 24))
+            .font(font)
+            .build(&mut self.prompt_label)?;
+
+        self.layout_names = layouts.into_iter().map(|l| l.name.clone()).collect();
+        ListBox::builder()
+            .parent(&self.window)
+            .position(pos(16, 48))
+            .size(size(388, 200))
+            .font(font)
+            .collection(layouts.into_iter().map(|l| l.title.clone()).collect())
+            .selected_index(Some(0))
+            .build(&mut self.layouts_list)?;
+
+        for (index, (radio, text)) in [
+            (&mut self.no_autostart_radio, rs!(IDS_NO_AUTOSTART)),
+            (&mut self.autostart_radio, rs!(IDS_AUTOSTART)),
+            (
+                &mut self.autostart_elevated_radio,
+                rs!(IDS_AUTOSTART_ELEVATED),
+            ),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            RadioButton::builder()
+                .parent(&self.window)
+                .position(pos(16, 48 + 32 * index as i32))
+                .size(size(388, 24))
+                .font(font)
+                .text(text)
+                .check_state(if index == 0 {
+                    RadioButtonState::Checked
+                } else {
+                    RadioButtonState::Unchecked
+                })
+                .build(radio)?;
+        }
+
+        ListBox::builder()
+            .parent(&self.window)
+            .position(pos(16, 48))
+            .size(size(388, 200))
+            .font(font)
+            .collection(
+                [rs!(IDS_NO_HOT_KEY)]
+                    .into_iter()
+                    .chain(TOGGLE_HOT_KEYS)
+                    .map(String::from)
+                    .collect(),
+            )
+            .selected_index(Some(0))
+            .build(&mut self.hot_keys_list)?;
+
+        for (button, text, x) in [
+            (&mut self.back_button, rs!(IDS_BACK), 216),
+            (&mut self.next_button, rs!(IDS_NEXT), 314),
+        ] {
+            Button::builder()
+                .parent(&self.window)
+                .position(pos(x, 264))
+                .size(size(90, 26))
+                .font(font)
+                .text(text)
+                .build(button)?;
+        }
+
+        bind_raw_event_handler(
+            &self.window.handle,
+            THEME_HANDLER_ID,
+            |hwnd, msg, w_param, _| handle_theme_message(HWND(hwnd as _), msg, w_param),
+        )?;
+        apply_theme(
+            hwnd(self.window.handle),
+            Theme::of_mode(ThemeMode::System, is_system_dark_theme()),
+        );
+
+        Ok(())
+    }
+
+    fn handle_event(&self, evt: Event, handle: ControlHandle) {
+        match evt {
+            Event::OnButtonClick if handle == self.back_button.handle => {
+                if let Some(page) = self.page.get().previous() {
+                    self.show_page(page);
+                }
+            }
+            Event::OnButtonClick if handle == self.next_button.handle => {
+                match self.page.get().next() {
+                    Some(page) => self.show_page(page),
+                    None => {
+                        self.choice.replace(Some(self.current_choice()));
+                        stop_thread_dispatch();
+                    }
+                }
+            }
+            Event::OnWindowClose if handle == self.window.handle => stop_thread_dispatch(),
+            _ => {}
+        }
+    }
+
+    fn show_page(&self, page: WizardPage) {
+        self.page.set(page);
+        let prompt_id = match page {
+            WizardPage::Layout => IDS_SETUP_LAYOUT,
+            WizardPage::Autostart => IDS_SETUP_AUTOSTART,
+            WizardPage::ToggleHotKey => IDS_SETUP_TOGGLE_HOT_KEY,
+        };
+        self.prompt_label.set_text(rs!(prompt_id));
+
+        self.layouts_list.set_visible(page == WizardPage::Layout);
+        for radio in [
+            &self.no_autostart_radio,
+            &self.autostart_radio,
+            &self.autostart_elevated_radio,
+        ] {
+            radio.set_visible(page == WizardPage::Autostart);
+        }
+        self.hot_keys_list
+            .set_visible(page == WizardPage::ToggleHotKey);
+
+        self.back_button.set_enabled(page.previous().is_some());
+        let next_id = if page.next().is_some() {
+            IDS_NEXT
+        } else {
+            IDS_FINISH
+        };
+        self.next_button.set_text(rs!(next_id));
+    }
+
+    fn current_choice(&self) -> WizardChoice {
+        let autostart = if self.autostart_radio.check_state() == RadioButtonState::Checked {
+            Some(StartupMethod::RunKey)
+        } else if self.autostart_elevated_radio.check_state() == RadioButtonState::Checked {
+            Some(StartupMethod::ElevatedTask)
+        } else {
+            None
+        };
+
+        WizardChoice {
+            layout: self.layout_names[self.layouts_list.selection().unwrap_or(0)].clone(),
+            autostart,
+            toggle_hot_key: toggle_hot_key(self.hot_keys_list.selection().unwrap_or(0)),
+        }
+    }
+}
+
+/// Returns the hotkey of the item of the hotkeys list. The first item is no hotkey.
+fn toggle_hot_key(index: usize) -> Option<KeyTrigger> {
+    let text = TOGGLE_HOT_KEYS.get(index.checked_sub(1)?)?;
+    KeyTrigger::from_str(text).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::wizard::{TOGGLE_HOT_KEYS, WizardChoice, WizardPage, toggle_hot_key};
+    use keympostor::key_trigger;
+    use keympostor::trigger::KeyTrigger;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_wizard_pages() {
+        let mut pages = vec![WizardPage::default()];
+        while let Some(page) = pages.last().unwrap().next() {
+            pages.push(page);
+        }
+        assert_eq!(
+            vec![
+                WizardPage::Layout,
+                WizardPage::Autostart,
+                WizardPage::ToggleHotKey
+            ],
+            pages
+        );
+
+        assert_eq!(None, WizardPage::Layout.previous());
+        assert_eq!(
+            Some(WizardPage::Autostart),
+            WizardPage::ToggleHotKey.previous()
+        );
+    }
+
+    #[test]
+    fn test_toggle_hot_key() {
+        assert_eq!(None, toggle_hot_key(0));
+        assert_eq!(
+            Some(key_trigger!("[LEFT_CTRL + LEFT_ALT] PAUSE↓")),
+            toggle_hot_key(1)
+        );
+        assert_eq!(None, toggle_hot_key(TOGGLE_HOT_KEYS.len() + 1));
+
+        for index in 1..=TOGGLE_HOT_KEYS.len() {
+            assert!(toggle_hot_key(index).is_some());
+        }
+    }
+
+    #[test]
+    fn test_wizard_choice_settings() {
+        let choice = WizardChoice {
+            layout: "colemak".into(),
+            autostart: None,
+            toggle_hot_key: Some(key_trigger!("[] SCROLL_LOCK↓")),
+        };
+
+        let settings = choice.to_settings();
+        assert_eq!(Some("colemak"), settings.last_transform_layout.as_deref());
+        assert_eq!(
+            Some(key_trigger!("[] SCROLL_LOCK↓")),
+            settings.toggle_processing_hot_key
+        );
+        assert!(settings.toggle_layout_hot_key.is_some());
+    }
+}
@@ -1,21 +1,24 @@
-use log::warn;
+use keympostor::key::Key;
 use std::cell::RefCell;
 use std::ptr::null_mut;
-use windows::core::{PCSTR, PCWSTR, PWSTR};
+use windows::core::{PCSTR, PWSTR};
 use windows::Win32::Foundation::{CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, HWND, MAX_PATH};
-use windows::Win32::Media::Audio::{PlaySoundW, SND_ASYNC, SND_FILENAME, SND_NODEFAULT};
 use windows::Win32::Storage::FileSystem::SYNCHRONIZE;
 use windows::Win32::System::Threading::{
     CreateMutexExA, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
     PROCESS_QUERY_LIMITED_INFORMATION,
 };
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    GetKeyState, GetKeyboardLayout, HKL, VIRTUAL_KEY,
+    GetKeyState, GetKeyboardLayout, ToUnicodeEx, HKL, VIRTUAL_KEY,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
-    GetForegroundWindow, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId,
+    GetClassNameW, GetForegroundWindow, GetWindowTextLengthW, GetWindowTextW,
+    GetWindowThreadProcessId,
 };
 
+/* window class names are limited to 256 characters */
+const MAX_CLASS_NAME: usize = 256;
+
 pub(crate) fn is_app_running() -> bool {
     const APP_MUTEX_ID: &[u8] = b"Global\\8e32f9ab-067f-0f01-8dc2-6047b7aa2a99\0";
 
@@ -32,33 +35,46 @@ pub(crate) fn is_app_running() -> bool {
     }
 }
 
-pub(crate) fn play_sound(filename: &str) {
-    unsafe {
-        let w_filename: Vec<u16> = filename.encode_utf16().chain(std::iter::once(0)).collect();
-        let result = PlaySoundW(
-            PCWSTR(w_filename.as_ptr()),
-            None,
-            SND_FILENAME | SND_NODEFAULT | SND_ASYNC,
-        );
-
-        if !result.as_bool() {
-            warn!(
-                "Unable to play sound: `{}` : {:?}",
-                filename,
-                GetLastError()
-            );
-        }
-    }
-}
-
 pub(crate) fn get_current_keyboard_layout() -> HKL {
     unsafe { GetKeyboardLayout(GetWindowThreadProcessId(GetForegroundWindow(), None)) }
 }
 
+/// Returns the character produced by the key under the specified keyboard layout.
+pub(crate) fn get_key_label(key: Key, layout: HKL) -> Option<String> {
+    const NO_STATE_CHANGE: u32 = 0x4;
+
+    let state = [0u8; 256];
+    let mut buffer = [0u16; 8];
+    let len = unsafe {
+        ToUnicodeEx(
+            key.vk() as u32,
+            key.sc() as u32,
+            &state,
+            &mut buffer,
+            NO_STATE_CHANGE,
+            Some(layout),
+        )
+    };
+
+    /* negative length means dead key, its character is still in the buffer */
+    let len = if len < 0 { 1 } else { len as usize };
+    let label = String::from_utf16_lossy(&buffer[..len]);
+    if label.is_empty() || label.chars().any(char::is_control) {
+        None
+    } else {
+        Some(label.to_uppercase())
+    }
+}
+
 pub(crate) fn get_keyboard_lock_state(vk: VIRTUAL_KEY) -> bool {
     unsafe { (GetKeyState(vk.0 as i32) & 1) != 0 }
 }
 
+/// Returns whether the key is down as of the message being processed.
+pub(crate) fn is_key_pressed(vk: VIRTUAL_KEY) -> bool {
+    unsafe { GetKeyState(vk.0 as i32) < 0 }
+}
+
 thread_local! {
     static PROCESS_PATH_BUFFER: RefCell<[u16;MAX_PATH as usize]> = RefCell::new([0u16;MAX_PATH as usize]);
 }
@@ -122,6 +138,17 @@ pub(crate) fn with_window_title<R>(hwnd: HWND, f: impl FnOnce(&str) -> R) -> Opt
     })
 }
 
+pub(crate) fn with_window_class<R>(hwnd: HWND, f: impl FnOnce(&str) -> R) -> Option<R> {
+    let mut buffer = [0u16; MAX_CLASS_NAME];
+    let len = unsafe { GetClassNameW(hwnd, &mut buffer) };
+    if len <= 0 {
+        return None;
+    }
+
+    let class_name = String::from_utf16_lossy(&buffer[..len as usize]);
+    Some(f(&class_name))
+}
+
 #[cfg(test)]
 
 pub mod tests {
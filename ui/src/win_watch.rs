@@ -1,23 +1,46 @@
 use crate::app::App;
+use crate::blacklist::AppBlacklist;
 use crate::profile::LayoutAutoswitchProfile;
-use crate::util::{with_process_path, with_window_title};
+use crate::util::{with_process_path, with_window_class, with_window_title};
+use keympostor::window::WindowInfo;
 use log::{debug, warn};
 use native_windows_gui::{ControlHandle, Event};
-use regex::Regex;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
-use windows::Win32::UI::WindowsAndMessaging::{KillTimer, SetTimer};
+use std::time::{Duration, Instant};
+use windows::Win32::UI::Accessibility::{HWINEVENTHOOK, SetWinEventHook, UnhookWinEvent};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EVENT_SYSTEM_FOREGROUND, KillTimer, SetTimer, USER_TIMER_MINIMUM, WINEVENT_OUTOFCONTEXT,
+};
 use windows::Win32::{Foundation::HWND, UI::WindowsAndMessaging::GetForegroundWindow};
 
 const TIMER_ID: usize = 19717;
-const WATCH_INTERVAL: u32 = 500;
+/// Timer checking the foreground window soon after the activation event.
+const ACTIVATION_TIMER_ID: usize = 19718;
+/// Polling catches the activations the event hook misses, e.g. of the elevated windows.
+const DEFAULT_POLL_INTERVAL: u32 = 500;
+/// Profile window must stay in foreground that long before its profile is selected.
+const DEFAULT_DEBOUNCE_INTERVAL: u32 = 200;
+
+thread_local! {
+    static ACTIVATION_RECEIVER: Cell<Option<HWND>> = Cell::new(None);
+}
 
 #[derive(Default)]
 pub(crate) struct WindowWatcher {
     owner: RefCell<HWND>,
     profiles: RefCell<Rc<HashMap<String, LayoutAutoswitchProfile>>>,
-    last_hwnd: RefCell<Option<HWND>>,
+    activation: RefCell<Debounce<Option<(HWND, String)>>>,
+    last_window: RefCell<Option<WindowInfo>>,
+    /// Last foreground window of the other applications than this one and the taskbar.
+    last_app_window: RefCell<Option<WindowInfo>>,
+    blacklist: RefCell<AppBlacklist>,
+    is_blacklisted: Cell<bool>,
+    is_autoswitch_enabled: Cell<bool>,
+    win_event_hook: Cell<Option<HWINEVENTHOOK>>,
+    poll_interval: Cell<u32>,
+    debounce_interval: Cell<u32>,
 }
 
 impl WindowWatcher {
@@ -26,91 +49,329 @@ impl WindowWatcher {
         owner: HWND,
         profiles: HashMap<String, LayoutAutoswitchProfile>,
         enable: bool,
+        poll_interval: Option<u32>,
+        debounce_interval: Option<u32>,
     ) {
         self.owner.replace(owner);
         self.profiles.replace(Rc::from(profiles));
+        self.poll_interval
+            .set(poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL));
+        self.debounce_interval
+            .set(debounce_interval.unwrap_or(DEFAULT_DEBOUNCE_INTERVAL));
         self.enable(enable);
-    }
 
-    pub(crate) fn enable(&self, enable: bool) {
-        if enable {
-            unsafe {
-                SetTimer(Some(*self.owner.borrow()), TIMER_ID, WATCH_INTERVAL, None);
-            }
-            debug!("Window watch timer started");
+        ACTIVATION_RECEIVER.set(Some(owner));
+        let hook = unsafe {
+            SetWinEventHook(
+                EVENT_SYSTEM_FOREGROUND,
+                EVENT_SYSTEM_FOREGROUND,
+                None,
+                Some(foreground_proc),
+                0,
+                0,
+                WINEVENT_OUTOFCONTEXT,
+            )
+        };
+        if hook.is_invalid() {
+            warn!("Failed to set foreground window hook");
         } else {
-            unsafe {
-                KillTimer(Some(*self.owner.borrow()), TIMER_ID).unwrap_or_else(|e| {
-                    if e.code().is_err() {
-                        warn!("Failed to kill window watch timer: {}", e);
-                    }
-                });
+            self.win_event_hook.set(Some(hook));
+        }
+
+        /* foreground window is watched for the rules even when autoswitch is disabled */
+        unsafe {
+            SetTimer(Some(owner), TIMER_ID, self.poll_interval.get(), None);
+        }
+        debug!(
+            "Window watch started, polling interval: {} ms",
+            self.poll_interval.get()
+        );
+    }
+
+    pub(crate) fn stop(&self) {
+        if let Some(hook) = self.win_event_hook.take() {
+            if !unsafe { UnhookWinEvent(hook) }.as_bool() {
+                warn!("Failed to unhook foreground window hook");
             }
-            debug!("Window watch timer stopped");
         }
+        ACTIVATION_RECEIVER.set(None);
+
+        let owner = *self.owner.borrow();
+        for timer_id in [TIMER_ID, ACTIVATION_TIMER_ID] {
+            kill_timer(owner, timer_id);
+        }
+        debug!("Window watch stopped");
+    }
+
+    /// Sets the windows in which the transformation is bypassed. Takes effect on the next check
+    /// of the foreground window.
+    pub(crate) fn set_blacklist(&self, blacklist: AppBlacklist) {
+        self.blacklist.replace(blacklist);
+    }
+
+    /// Returns the last foreground window of the other applications, e.g. to blacklist it
+    /// from the main window.
+    pub(crate) fn last_app_window(&self) -> Option<WindowInfo> {
+        self.last_app_window.borrow().clone()
+    }
+
+    /// Enables or disables switching of the profiles by the foreground window.
+    pub(crate) fn enable(&self, enable: bool) {
+        self.is_autoswitch_enabled.set(enable);
+        debug!("Layout autoswitch enabled: {}", enable);
     }
 
     pub(crate) fn handle_event(&self, app: &App, evt: Event, handle: ControlHandle) {
         if let Event::OnTimerTick = evt {
-            if !is_our_timer_tick(handle) {
+            match timer_id(handle) {
+                Some(TIMER_ID) => {}
+                Some(ACTIVATION_TIMER_ID) => kill_timer(*self.owner.borrow(), ACTIVATION_TIMER_ID),
+                _ => return,
+            }
+
+            if let Some(window) = self.detect_window_change() {
+                app.on_foreground_window_changed(window);
+            }
+
+            if let Some(is_blacklisted) = self.detect_blacklist_change() {
+                app.on_window_blacklisted(is_blacklisted);
+            }
+
+            if !self.is_autoswitch_enabled.get() {
                 return;
             }
 
-            if let Some(profile_name) = self.detect_profile_change() {
-                app.on_select_profile(profile_name.as_deref())
+            if let Some(activation) = self.detect_profile_change() {
+                app.on_profile_activated(activation)
+            }
+
+            if self.activation.borrow().is_pending() {
+                /* check again when the activation settles */
+                unsafe {
+                    SetTimer(
+                        Some(*self.owner.borrow()),
+                        ACTIVATION_TIMER_ID,
+                        self.debounce_interval.get().max(USER_TIMER_MINIMUM),
+                        None,
+                    );
+                }
             }
         }
     }
 
-    fn detect_profile_change(&self) -> Option<Option<String>> {
+    fn detect_window_change(&self) -> Option<WindowInfo> {
+        let hwnd = unsafe { GetForegroundWindow() };
+        if hwnd.is_invalid() {
+            return None;
+        }
+
+        let window = window_info(hwnd);
+        if self.last_window.borrow().as_ref() == Some(&window) {
+            return None;
+        }
+
+        if !is_own_window(&window) {
+            self.last_app_window.replace(Some(window.clone()));
+        }
+        self.last_window.replace(Some(window.clone()));
+        Some(window)
+    }
+
+    /// Returns whether the foreground window is blacklisted if that has changed.
+    fn detect_blacklist_change(&self) -> Option<bool> {
+        let is_blacklisted = self
+            .last_window
+            .borrow()
+            .as_ref()
+            .is_some_and(|window| self.blacklist.borrow().matches(window));
+        if is_blacklisted == self.is_blacklisted.replace(is_blacklisted) {
+            return None;
+        }
+        Some(is_blacklisted)
+    }
+
+    fn detect_profile_change(&self) -> Option<ProfileActivation> {
         let profiles = self.profiles.borrow();
 
-        let match_result = profiles.iter().find_map(|(profile_name, profile)| {
-            let regex = profile.rule_regex()?;
-            active_window_matches(&regex).map(|hwnd| (hwnd, profile_name.clone()))
+        let hwnd = unsafe { GetForegroundWindow() };
+        let window = (!hwnd.is_invalid()).then(|| window_info(hwnd));
+        let match_result = window.as_ref().and_then(|window| {
+            profiles
+                .iter()
+                .find(|(_, profile)| profile.matches(window))
+                .map(|(profile_name, _)| (hwnd, profile_name.clone()))
         });
 
-        if let Some((hwnd, profile_name)) = match_result {
-            let is_new_activation = self.last_hwnd.borrow().map_or(true, |prev| prev != hwnd);
-            self.last_hwnd.replace(Some(hwnd));
+        let interval = Duration::from_millis(self.debounce_interval.get() as u64);
+        let mut activation = self.activation.borrow_mut();
 
-            if is_new_activation {
+        let profile_name = match activation.update(match_result, Instant::now(), interval)? {
+            Some((_, profile_name)) => {
                 debug!("Window detected for profile: `{}`", profile_name);
-                return Some(Some(profile_name));
+                Some(profile_name.clone())
             }
+            None => {
+                debug!("No active profile windows");
+                None
+            }
+        };
+
+        Some(ProfileActivation {
+            profile_name,
+            window,
+        })
+    }
+}
 
+/// Profile selected by the foreground window.
+#[derive(Debug)]
+pub(crate) struct ProfileActivation {
+    /// `None` when no profile window is in foreground.
+    pub(crate) profile_name: Option<String>,
+    pub(crate) window: Option<WindowInfo>,
+}
+
+/// Coalesces the rapid changes of the value. The change is reported once the new value stays
+/// the same for the interval, the values passed through in between are dropped.
+#[derive(Debug, Default)]
+struct Debounce<T> {
+    current: T,
+    pending: Option<(T, Instant)>,
+}
+
+impl<T: PartialEq> Debounce<T> {
+    /// Returns the new value if it has settled.
+    fn update(&mut self, value: T, now: Instant, interval: Duration) -> Option<&T> {
+        if value == self.current {
+            self.pending = None;
             return None;
         }
 
-        if self.last_hwnd.borrow().is_some() {
-            debug!("No active profile windows");
-            self.last_hwnd.replace(None);
-            return Some(None);
+        let since = match self.pending.take() {
+            Some((pending, since)) if pending == value => since,
+            _ => now,
+        };
+
+        if now.duration_since(since) < interval {
+            self.pending = Some((value, since));
+            return None;
         }
 
-        None
+        self.current = value;
+        Some(&self.current)
+    }
+
+    fn is_pending(&self) -> bool {
+        self.pending.is_some()
     }
 }
 
-fn is_our_timer_tick(handle: ControlHandle) -> bool {
-    handle
-        .timer()
-        .is_some_and(|(_, timer_id)| timer_id == TIMER_ID as u32)
+/// Schedules the check of the foreground window. Runs on the thread that set the hook.
+unsafe extern "system" fn foreground_proc(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    _hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    if let Some(owner) = ACTIVATION_RECEIVER.get() {
+        unsafe {
+            SetTimer(Some(owner), ACTIVATION_TIMER_ID, USER_TIMER_MINIMUM, None);
+        }
+    }
+}
+
+fn timer_id(handle: ControlHandle) -> Option<usize> {
+    handle.timer().map(|(_, timer_id)| timer_id as usize)
 }
 
-fn active_window_matches(regex: &Regex) -> Option<HWND> {
-    let hwnd = unsafe { GetForegroundWindow() };
-    if hwnd.is_invalid() {
-        return None;
+fn kill_timer(owner: HWND, timer_id: usize) {
+    unsafe {
+        KillTimer(Some(owner), timer_id).unwrap_or_else(|e| {
+            if e.code().is_err() {
+                warn!("Failed to kill window watch timer: {}", e);
+            }
+        });
     }
+}
+
+/// Checks if the window belongs to this application or the taskbar showing its tray menu.
+fn is_own_window(window: &WindowInfo) -> bool {
+    const TASKBAR_CLASSES: [&str; 3] = [
+        "Shell_TrayWnd",
+        "Shell_SecondaryTrayWnd",
+        "NotifyIconOverflowWindow",
+    ];
+    TASKBAR_CLASSES.contains(&window.class_name.as_str())
+        || std::env::current_exe()
+            .is_ok_and(|exe| exe.as_os_str().eq_ignore_ascii_case(&window.process_path))
+}
 
-    if with_window_title(hwnd, |t| regex.is_match(t)).unwrap_or(false) {
-        return Some(hwnd);
+fn window_info(hwnd: HWND) -> WindowInfo {
+    WindowInfo {
+        title: with_window_title(hwnd, str::to_string).unwrap_or_default(),
+        class_name: with_window_class(hwnd, str::to_string).unwrap_or_default(),
+        process_path: with_process_path(hwnd, str::to_string).unwrap_or_default(),
     }
+}
 
-    if with_process_path(hwnd, |n| regex.is_match(n)).unwrap_or(false) {
-        return Some(hwnd);
+#[cfg(test)]
+mod tests {
+    use crate::win_watch::Debounce;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_debounce_settles() {
+        let interval = Duration::from_millis(200);
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut debounce = Debounce::<Option<&str>>::default();
+
+        assert_eq!(None, debounce.update(Some("a"), at(0), interval));
+        assert!(debounce.is_pending());
+        assert_eq!(None, debounce.update(Some("a"), at(100), interval));
+        assert_eq!(
+            Some(&Some("a")),
+            debounce.update(Some("a"), at(200), interval)
+        );
+        assert!(!debounce.is_pending());
+        assert_eq!(None, debounce.update(Some("a"), at(500), interval));
+    }
+
+    #[test]
+    fn test_debounce_coalesces() {
+        let interval = Duration::from_millis(200);
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut debounce = Debounce::<Option<&str>>::default();
+
+        assert_eq!(None, debounce.update(Some("a"), at(0), interval));
+        assert_eq!(None, debounce.update(Some("b"), at(150), interval));
+        assert_eq!(None, debounce.update(Some("a"), at(250), interval));
+        /* switched back to the current value before settling */
+        assert_eq!(None, debounce.update(None, at(300), interval));
+        assert!(!debounce.is_pending());
+
+        assert_eq!(None, debounce.update(Some("b"), at(400), interval));
+        assert_eq!(
+            Some(&Some("b")),
+            debounce.update(Some("b"), at(600), interval)
+        );
+        assert_eq!(None, debounce.update(None, at(700), interval));
+        assert_eq!(Some(&None), debounce.update(None, at(900), interval));
     }
 
-    None
+    #[test]
+    fn test_debounce_zero_interval() {
+        let now = Instant::now();
+        let mut debounce = Debounce::<Option<&str>>::default();
+
+        assert_eq!(
+            Some(&Some("a")),
+            debounce.update(Some("a"), now, Duration::ZERO)
+        );
+        assert!(!debounce.is_pending());
+    }
 }